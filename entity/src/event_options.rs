@@ -14,6 +14,9 @@ pub struct Model {
     #[sea_orm(column_type = "Decimal(Some((12, 2)))")]
     pub total_backing: Decimal,
     pub is_winning_option: Option<bool>,
+    pub shares_issued: i32,
+    #[sea_orm(column_type = "Decimal(Some((10, 2)))", nullable)]
+    pub payout_per_share: Option<Decimal>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -26,14 +29,24 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Events,
+    #[sea_orm(has_many = "super::candles::Entity")]
+    Candles,
     #[sea_orm(has_many = "super::orders::Entity")]
     Orders,
+    #[sea_orm(has_many = "super::price_history::Entity")]
+    PriceHistory,
     #[sea_orm(has_many = "super::trades::Entity")]
     Trades,
     #[sea_orm(has_many = "super::user_positions::Entity")]
     UserPositions,
 }
 
+impl Related<super::candles::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Candles.def()
+    }
+}
+
 impl Related<super::events::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Events.def()
@@ -46,6 +59,12 @@ impl Related<super::orders::Entity> for Entity {
     }
 }
 
+impl Related<super::price_history::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PriceHistory.def()
+    }
+}
+
 impl Related<super::trades::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Trades.def()
@@ -26,6 +26,23 @@ pub struct Model {
     #[sea_orm(column_type = "Text")]
     pub resolution_note: String,
     pub resolved_at: DateTime,
+    #[sea_orm(column_type = "Decimal(Some((8, 2)))")]
+    pub min_price: Decimal,
+    #[sea_orm(column_type = "Decimal(Some((8, 2)))")]
+    pub max_price: Decimal,
+    #[sea_orm(column_type = "Decimal(Some((8, 2)))")]
+    pub tick_size: Decimal,
+    pub max_order_quantity: i32,
+    pub execution_venue: String,
+    pub pricing_strategy: String,
+    pub complement_pricing: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub settlement_rules: Option<String>,
+    pub currency: String,
+    pub featured_rank: Option<i32>,
+    pub featured_from: Option<DateTime>,
+    pub featured_until: Option<DateTime>,
+    pub tenant_id: i32,
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
@@ -56,6 +73,16 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     Users1,
+    #[sea_orm(has_many = "super::promotions::Entity")]
+    Promotions,
+    #[sea_orm(
+        belongs_to = "super::tenants::Entity",
+        from = "Column::TenantId",
+        to = "super::tenants::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Restrict"
+    )]
+    Tenants,
 }
 
 impl Related<super::event_options::Entity> for Entity {
@@ -82,4 +109,16 @@ impl Related<super::user_positions::Entity> for Entity {
     }
 }
 
+impl Related<super::promotions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Promotions.def()
+    }
+}
+
+impl Related<super::tenants::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Tenants.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -2,10 +2,34 @@
 
 pub mod prelude;
 
+pub mod api_keys;
+pub mod audit_logs;
+pub mod candles;
 pub mod event_options;
+pub mod event_oracles;
 pub mod events;
+pub mod kyc_documents;
+pub mod legacy_imports;
+pub mod margin_ledger;
+pub mod order_events;
 pub mod orders;
+pub mod payment_intents;
+pub mod price_history;
+pub mod promotion_usages;
+pub mod promotions;
+pub mod role_permissions;
+pub mod roles;
+pub mod settings;
+pub mod support_ticket_messages;
+pub mod support_tickets;
+pub mod tenants;
 pub mod trades;
 pub mod transaction;
+pub mod user_consents;
 pub mod user_positions;
+pub mod user_preferences;
+pub mod user_roles;
+pub mod user_security;
+pub mod user_tiers;
 pub mod users;
+pub mod wallets;
@@ -18,8 +18,15 @@ pub struct Model {
     pub quantity: i32,
     pub filled_quantity: i32,
     pub status: String,
+    pub client_order_id: Option<String>,
+    pub currency: String,
+    pub tenant_id: i32,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
+    pub correlation_id: Option<String>,
+    #[sea_orm(column_type = "Decimal(Some((20, 8)))", nullable)]
+    pub hold_price: Option<Decimal>,
+    pub short_quantity: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -40,6 +47,8 @@ pub enum Relation {
         on_delete = "Restrict"
     )]
     Events,
+    #[sea_orm(has_many = "super::order_events::Entity")]
+    OrderEvents,
     #[sea_orm(
         belongs_to = "super::users::Entity",
         from = "Column::UserId",
@@ -48,6 +57,14 @@ pub enum Relation {
         on_delete = "Restrict"
     )]
     Users,
+    #[sea_orm(
+        belongs_to = "super::tenants::Entity",
+        from = "Column::TenantId",
+        to = "super::tenants::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Restrict"
+    )]
+    Tenants,
 }
 
 impl Related<super::event_options::Entity> for Entity {
@@ -62,10 +79,22 @@ impl Related<super::events::Entity> for Entity {
     }
 }
 
+impl Related<super::order_events::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OrderEvents.def()
+    }
+}
+
 impl Related<super::users::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Users.def()
     }
 }
 
+impl Related<super::tenants::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Tenants.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -1,9 +1,33 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
+pub use super::api_keys::Entity as ApiKeys;
+pub use super::audit_logs::Entity as AuditLogs;
+pub use super::candles::Entity as Candles;
 pub use super::event_options::Entity as EventOptions;
+pub use super::event_oracles::Entity as EventOracles;
 pub use super::events::Entity as Events;
+pub use super::kyc_documents::Entity as KycDocuments;
+pub use super::legacy_imports::Entity as LegacyImports;
+pub use super::margin_ledger::Entity as MarginLedger;
+pub use super::order_events::Entity as OrderEvents;
 pub use super::orders::Entity as Orders;
+pub use super::payment_intents::Entity as PaymentIntents;
+pub use super::price_history::Entity as PriceHistory;
+pub use super::promotion_usages::Entity as PromotionUsages;
+pub use super::promotions::Entity as Promotions;
+pub use super::role_permissions::Entity as RolePermissions;
+pub use super::roles::Entity as Roles;
+pub use super::settings::Entity as Settings;
+pub use super::support_ticket_messages::Entity as SupportTicketMessages;
+pub use super::support_tickets::Entity as SupportTickets;
+pub use super::tenants::Entity as Tenants;
 pub use super::trades::Entity as Trades;
 pub use super::transaction::Entity as Transaction;
+pub use super::user_consents::Entity as UserConsents;
 pub use super::user_positions::Entity as UserPositions;
+pub use super::user_preferences::Entity as UserPreferences;
+pub use super::user_roles::Entity as UserRoles;
+pub use super::user_security::Entity as UserSecurity;
+pub use super::user_tiers::Entity as UserTiers;
 pub use super::users::Entity as Users;
+pub use super::wallets::Entity as Wallets;
@@ -18,7 +18,22 @@ pub struct Model {
     pub quantity: i32,
     #[sea_orm(column_type = "Decimal(Some((20, 8)))")]
     pub total_amount: Decimal,
+    pub currency: String,
     pub timestamp: DateTimeWithTimeZone,
+    pub sequence: i64,
+    #[sea_orm(column_type = "Decimal(Some((20, 8)))")]
+    pub buyer_fee: Decimal,
+    #[sea_orm(column_type = "Decimal(Some((20, 8)))")]
+    pub seller_fee: Decimal,
+    pub settlement_status: String,
+    pub settlement_attempts: i32,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub settlement_error: Option<String>,
+    pub buyer_position_before: Option<i32>,
+    pub seller_position_before: Option<i32>,
+    pub settled_at: Option<DateTimeWithTimeZone>,
+    pub buy_correlation_id: Option<String>,
+    pub sell_correlation_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -11,6 +11,7 @@ pub struct Model {
     pub r#type: String,
     #[sea_orm(column_type = "Decimal(Some((10, 2)))")]
     pub amount: Decimal,
+    pub currency: String,
     #[sea_orm(column_type = "Decimal(Some((10, 2)))")]
     pub balance_before: Decimal,
     #[sea_orm(column_type = "Decimal(Some((10, 2)))")]
@@ -18,6 +19,14 @@ pub struct Model {
     pub status: String,
     pub reference_id: String,
     pub created_at: DateTime,
+    pub reviewed_by: Option<i32>,
+    pub reviewed_at: Option<DateTime>,
+    pub rejection_reason: Option<String>,
+    pub gateway: Option<String>,
+    pub gateway_reference: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub failure_reason: Option<String>,
+    pub correlation_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
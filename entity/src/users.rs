@@ -15,12 +15,15 @@ pub struct Model {
     pub phone: Option<String>,
     pub password_hash: String,
     pub full_name: Option<String>,
-    #[sea_orm(column_type = "Decimal(Some((10, 2)))")]
-    pub wallet_balance: Decimal,
+    pub margin_enabled: bool,
     pub is_active: bool,
     pub created_at: DateTime,
     pub updated_at: DateTime,
     pub role: String,
+    pub tenant_id: i32,
+    pub kyc_status: String,
+    pub date_of_birth: Option<Date>,
+    pub age_status: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -31,6 +34,40 @@ pub enum Relation {
     Transaction,
     #[sea_orm(has_many = "super::user_positions::Entity")]
     UserPositions,
+    #[sea_orm(has_one = "super::user_preferences::Entity")]
+    UserPreferences,
+    #[sea_orm(has_one = "super::user_tiers::Entity")]
+    UserTiers,
+    #[sea_orm(has_many = "super::promotion_usages::Entity")]
+    PromotionUsages,
+    #[sea_orm(has_many = "super::margin_ledger::Entity")]
+    MarginLedger,
+    #[sea_orm(has_many = "super::api_keys::Entity")]
+    ApiKeys,
+    #[sea_orm(has_one = "super::user_security::Entity")]
+    UserSecurity,
+    #[sea_orm(has_many = "super::user_roles::Entity")]
+    UserRoles,
+    #[sea_orm(has_many = "super::audit_logs::Entity")]
+    AuditLogs,
+    #[sea_orm(has_many = "super::kyc_documents::Entity")]
+    KycDocuments,
+    #[sea_orm(has_many = "super::payment_intents::Entity")]
+    PaymentIntents,
+    #[sea_orm(has_many = "super::wallets::Entity")]
+    Wallets,
+    #[sea_orm(has_many = "super::support_tickets::Entity")]
+    SupportTickets,
+    #[sea_orm(has_many = "super::user_consents::Entity")]
+    UserConsents,
+    #[sea_orm(
+        belongs_to = "super::tenants::Entity",
+        from = "Column::TenantId",
+        to = "super::tenants::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Restrict"
+    )]
+    Tenants,
 }
 
 impl Related<super::orders::Entity> for Entity {
@@ -51,4 +88,88 @@ impl Related<super::user_positions::Entity> for Entity {
     }
 }
 
+impl Related<super::user_preferences::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserPreferences.def()
+    }
+}
+
+impl Related<super::user_tiers::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserTiers.def()
+    }
+}
+
+impl Related<super::promotion_usages::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PromotionUsages.def()
+    }
+}
+
+impl Related<super::margin_ledger::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MarginLedger.def()
+    }
+}
+
+impl Related<super::api_keys::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiKeys.def()
+    }
+}
+
+impl Related<super::user_security::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserSecurity.def()
+    }
+}
+
+impl Related<super::user_roles::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserRoles.def()
+    }
+}
+
+impl Related<super::audit_logs::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AuditLogs.def()
+    }
+}
+
+impl Related<super::kyc_documents::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::KycDocuments.def()
+    }
+}
+
+impl Related<super::payment_intents::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PaymentIntents.def()
+    }
+}
+
+impl Related<super::wallets::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Wallets.def()
+    }
+}
+
+impl Related<super::support_tickets::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SupportTickets.def()
+    }
+}
+
+impl Related<super::user_consents::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserConsents.def()
+    }
+}
+
+impl Related<super::tenants::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Tenants.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -8,6 +8,49 @@ mod m20250707_223909_transactions;
 mod m20250708_130208_events;
 mod m20250708_150357_event_options;
 mod m20250709_000000_add_user_role;
+mod m20250710_000000_add_event_trading_limits;
+mod m20250711_000000_create_order_events_table;
+mod m20250712_000000_create_notification_preferences_table;
+mod m20250713_000000_add_trade_sequence;
+mod m20250714_000000_create_user_preferences_table;
+mod m20250715_000000_create_user_tiers_table;
+mod m20250715_000001_add_trade_fees;
+mod m20250716_000000_create_promotions_table;
+mod m20250716_000001_create_promotion_usages_table;
+mod m20250717_000000_add_event_featuring;
+mod m20250718_000000_create_candles_table;
+mod m20250719_000000_add_user_reserved_balance;
+mod m20250720_000000_create_margin_accounts;
+mod m20250721_000000_add_order_client_order_id;
+mod m20250722_000000_add_event_execution_venue;
+mod m20250723_000000_create_api_keys_table;
+mod m20250724_000000_dedupe_user_positions;
+mod m20250725_000000_add_option_shares_issued;
+mod m20250726_000000_create_user_security_table;
+mod m20250727_000000_create_roles_tables;
+mod m20250728_000000_create_tenants_table;
+mod m20250729_000000_create_audit_logs_table;
+mod m20250730_000000_create_settings_table;
+mod m20250731_000000_add_change_notify_triggers;
+mod m20250801_000000_create_kyc_tables;
+mod m20250802_000000_add_withdrawal_review;
+mod m20250803_000000_create_payment_intents;
+mod m20250804_000000_create_wallets;
+mod m20250805_000000_add_trade_settlement_status;
+mod m20250806_000000_add_transaction_failure_reason;
+mod m20250807_000000_create_support_tickets;
+mod m20250808_000000_create_user_consents;
+mod m20250809_000000_add_user_age_verification;
+mod m20250810_000000_add_option_payout_per_share;
+mod m20250811_000000_create_event_oracles;
+mod m20250812_000000_add_event_complement_pricing;
+mod m20250813_000000_add_correlation_ids;
+mod m20250814_000000_create_legacy_imports;
+mod m20250815_000000_add_event_settlement_rules;
+mod m20250816_000000_add_event_pricing_strategy;
+mod m20250817_000000_create_price_history;
+mod m20250818_000000_add_order_hold_price;
+mod m20250819_000000_add_order_short_quantity;
 
 pub struct Migrator;
 
@@ -23,6 +66,49 @@ impl MigratorTrait for Migrator {
             Box::new(m20250109_110000_create_trades_table::Migration),
             Box::new(m20250109_120000_create_user_positions_table::Migration),
             Box::new(m20250709_000000_add_user_role::Migration),
+            Box::new(m20250710_000000_add_event_trading_limits::Migration),
+            Box::new(m20250711_000000_create_order_events_table::Migration),
+            Box::new(m20250712_000000_create_notification_preferences_table::Migration),
+            Box::new(m20250713_000000_add_trade_sequence::Migration),
+            Box::new(m20250714_000000_create_user_preferences_table::Migration),
+            Box::new(m20250715_000000_create_user_tiers_table::Migration),
+            Box::new(m20250715_000001_add_trade_fees::Migration),
+            Box::new(m20250716_000000_create_promotions_table::Migration),
+            Box::new(m20250716_000001_create_promotion_usages_table::Migration),
+            Box::new(m20250717_000000_add_event_featuring::Migration),
+            Box::new(m20250718_000000_create_candles_table::Migration),
+            Box::new(m20250719_000000_add_user_reserved_balance::Migration),
+            Box::new(m20250720_000000_create_margin_accounts::Migration),
+            Box::new(m20250721_000000_add_order_client_order_id::Migration),
+            Box::new(m20250722_000000_add_event_execution_venue::Migration),
+            Box::new(m20250723_000000_create_api_keys_table::Migration),
+            Box::new(m20250724_000000_dedupe_user_positions::Migration),
+            Box::new(m20250725_000000_add_option_shares_issued::Migration),
+            Box::new(m20250726_000000_create_user_security_table::Migration),
+            Box::new(m20250727_000000_create_roles_tables::Migration),
+            Box::new(m20250728_000000_create_tenants_table::Migration),
+            Box::new(m20250729_000000_create_audit_logs_table::Migration),
+            Box::new(m20250730_000000_create_settings_table::Migration),
+            Box::new(m20250731_000000_add_change_notify_triggers::Migration),
+            Box::new(m20250801_000000_create_kyc_tables::Migration),
+            Box::new(m20250802_000000_add_withdrawal_review::Migration),
+            Box::new(m20250803_000000_create_payment_intents::Migration),
+            Box::new(m20250804_000000_create_wallets::Migration),
+            Box::new(m20250805_000000_add_trade_settlement_status::Migration),
+            Box::new(m20250806_000000_add_transaction_failure_reason::Migration),
+            Box::new(m20250807_000000_create_support_tickets::Migration),
+            Box::new(m20250808_000000_create_user_consents::Migration),
+            Box::new(m20250809_000000_add_user_age_verification::Migration),
+            Box::new(m20250810_000000_add_option_payout_per_share::Migration),
+            Box::new(m20250811_000000_create_event_oracles::Migration),
+            Box::new(m20250812_000000_add_event_complement_pricing::Migration),
+            Box::new(m20250813_000000_add_correlation_ids::Migration),
+            Box::new(m20250814_000000_create_legacy_imports::Migration),
+            Box::new(m20250815_000000_add_event_settlement_rules::Migration),
+            Box::new(m20250816_000000_add_event_pricing_strategy::Migration),
+            Box::new(m20250817_000000_create_price_history::Migration),
+            Box::new(m20250818_000000_add_order_hold_price::Migration),
+            Box::new(m20250819_000000_add_order_short_quantity::Migration),
         ]
     }
 }
@@ -0,0 +1,65 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Add per-event price band, tick size and order size limit columns
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Events::Table)
+                    .add_column(
+                        ColumnDef::new(Events::MinPrice)
+                            .decimal_len(8, 2)
+                            .not_null()
+                            .default(0.01),
+                    )
+                    .add_column(
+                        ColumnDef::new(Events::MaxPrice)
+                            .decimal_len(8, 2)
+                            .not_null()
+                            .default(99.99),
+                    )
+                    .add_column(
+                        ColumnDef::new(Events::TickSize)
+                            .decimal_len(8, 2)
+                            .not_null()
+                            .default(0.01),
+                    )
+                    .add_column(
+                        ColumnDef::new(Events::MaxOrderQuantity)
+                            .integer()
+                            .not_null()
+                            .default(10000),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Events::Table)
+                    .drop_column(Events::MinPrice)
+                    .drop_column(Events::MaxPrice)
+                    .drop_column(Events::TickSize)
+                    .drop_column(Events::MaxOrderQuantity)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Events {
+    Table,
+    MinPrice,
+    MaxPrice,
+    TickSize,
+    MaxOrderQuantity,
+}
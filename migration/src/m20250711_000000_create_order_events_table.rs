@@ -0,0 +1,93 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Append-only history of every status transition of an order
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderEvents::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderEvents::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OrderEvents::OrderId).string().not_null())
+                    .col(ColumnDef::new(OrderEvents::Kind).string().not_null())
+                    .col(ColumnDef::new(OrderEvents::Status).string().not_null())
+                    .col(
+                        ColumnDef::new(OrderEvents::Price)
+                            .decimal_len(20, 8)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OrderEvents::Quantity).integer().not_null())
+                    .col(
+                        ColumnDef::new(OrderEvents::FilledQuantity)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OrderEvents::TradeId).string().null())
+                    .col(
+                        ColumnDef::new(OrderEvents::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_order_events_order")
+                            .from(OrderEvents::Table, OrderEvents::OrderId)
+                            .to(Orders::Table, Orders::Id)
+                            .on_delete(ForeignKeyAction::Restrict)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_order_events_order")
+                    .table(OrderEvents::Table)
+                    .col(OrderEvents::OrderId)
+                    .col(OrderEvents::Id)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OrderEvents::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum OrderEvents {
+    Table,
+    Id,
+    OrderId,
+    Kind,
+    Status,
+    Price,
+    Quantity,
+    FilledQuantity,
+    TradeId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+}
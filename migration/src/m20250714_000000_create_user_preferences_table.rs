@@ -0,0 +1,118 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Notification settings grow into general user preferences
+        manager
+            .rename_table(
+                Table::rename()
+                    .table(NotificationPreferences::Table, UserPreferences::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserPreferences::Table)
+                    .add_column(
+                        ColumnDef::new(UserPreferences::EmailEnabled)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .add_column(
+                        ColumnDef::new(UserPreferences::PushEnabled)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(
+                        ColumnDef::new(UserPreferences::WsEnabled)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .add_column(
+                        ColumnDef::new(UserPreferences::FillsEnabled)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .add_column(
+                        ColumnDef::new(UserPreferences::SettlementsEnabled)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .add_column(
+                        ColumnDef::new(UserPreferences::MarketingEnabled)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(
+                        ColumnDef::new(UserPreferences::Locale)
+                            .string()
+                            .not_null()
+                            .default("en-US"),
+                    )
+                    .add_column(
+                        ColumnDef::new(UserPreferences::DisplayCurrency)
+                            .string()
+                            .not_null()
+                            .default("USD"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserPreferences::Table)
+                    .drop_column(UserPreferences::EmailEnabled)
+                    .drop_column(UserPreferences::PushEnabled)
+                    .drop_column(UserPreferences::WsEnabled)
+                    .drop_column(UserPreferences::FillsEnabled)
+                    .drop_column(UserPreferences::SettlementsEnabled)
+                    .drop_column(UserPreferences::MarketingEnabled)
+                    .drop_column(UserPreferences::Locale)
+                    .drop_column(UserPreferences::DisplayCurrency)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .rename_table(
+                Table::rename()
+                    .table(UserPreferences::Table, NotificationPreferences::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NotificationPreferences {
+    Table,
+}
+
+#[derive(DeriveIden)]
+enum UserPreferences {
+    Table,
+    EmailEnabled,
+    PushEnabled,
+    WsEnabled,
+    FillsEnabled,
+    SettlementsEnabled,
+    MarketingEnabled,
+    Locale,
+    DisplayCurrency,
+}
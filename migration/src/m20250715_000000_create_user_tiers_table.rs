@@ -0,0 +1,78 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Each user's tier as of the last recalculation, with the figures it was based on
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserTiers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserTiers::UserId)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(UserTiers::Tier)
+                            .string()
+                            .not_null()
+                            .default("standard"),
+                    )
+                    .col(
+                        ColumnDef::new(UserTiers::TradingVolume)
+                            .decimal_len(20, 8)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(UserTiers::WalletBalance)
+                            .decimal_len(20, 8)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(UserTiers::CalculatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_tiers_user")
+                            .from(UserTiers::Table, UserTiers::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserTiers::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UserTiers {
+    Table,
+    UserId,
+    Tier,
+    TradingVolume,
+    WalletBalance,
+    CalculatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
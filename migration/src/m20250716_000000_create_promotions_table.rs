@@ -0,0 +1,117 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Admin-configured promotions: fee-free trading windows and deposit matches
+        manager
+            .create_table(
+                Table::create()
+                    .table(Promotions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Promotions::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Promotions::Name).string().not_null())
+                    .col(ColumnDef::new(Promotions::Kind).string().not_null())
+                    .col(ColumnDef::new(Promotions::EventId).integer().null())
+                    .col(
+                        ColumnDef::new(Promotions::MatchPercent)
+                            .decimal_len(10, 4)
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(Promotions::MaxRewardPerUser)
+                            .decimal_len(20, 8)
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(Promotions::StartsAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Promotions::EndsAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Promotions::IsActive)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(ColumnDef::new(Promotions::CreatedBy).integer().not_null())
+                    .col(
+                        ColumnDef::new(Promotions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Promotions::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_promotions_event")
+                            .from(Promotions::Table, Promotions::EventId)
+                            .to(Events::Table, Events::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_promotions_kind_window")
+                    .table(Promotions::Table)
+                    .col(Promotions::Kind)
+                    .col(Promotions::StartsAt)
+                    .col(Promotions::EndsAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Promotions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Promotions {
+    Table,
+    Id,
+    Name,
+    Kind,
+    EventId,
+    MatchPercent,
+    MaxRewardPerUser,
+    StartsAt,
+    EndsAt,
+    IsActive,
+    CreatedBy,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Events {
+    Table,
+    Id,
+}
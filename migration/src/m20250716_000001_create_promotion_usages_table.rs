@@ -0,0 +1,105 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per reward a user received from a promotion: a waived fee or a deposit match
+        manager
+            .create_table(
+                Table::create()
+                    .table(PromotionUsages::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PromotionUsages::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PromotionUsages::PromotionId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PromotionUsages::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(PromotionUsages::Amount)
+                            .decimal_len(20, 8)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PromotionUsages::Reference)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PromotionUsages::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_promotion_usages_promotion")
+                            .from(PromotionUsages::Table, PromotionUsages::PromotionId)
+                            .to(Promotions::Table, Promotions::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_promotion_usages_user")
+                            .from(PromotionUsages::Table, PromotionUsages::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_promotion_usages_promotion_user")
+                    .table(PromotionUsages::Table)
+                    .col(PromotionUsages::PromotionId)
+                    .col(PromotionUsages::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PromotionUsages::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PromotionUsages {
+    Table,
+    Id,
+    PromotionId,
+    UserId,
+    Amount,
+    Reference,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Promotions {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
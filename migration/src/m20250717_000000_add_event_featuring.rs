@@ -0,0 +1,62 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // An event is featured while it has a rank and now falls inside its
+        // optional window; lower ranks come first on the homepage
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Events::Table)
+                    .add_column(ColumnDef::new(Events::FeaturedRank).integer().null())
+                    .add_column(ColumnDef::new(Events::FeaturedFrom).timestamp().null())
+                    .add_column(ColumnDef::new(Events::FeaturedUntil).timestamp().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_events_featured_rank")
+                    .table(Events::Table)
+                    .col(Events::FeaturedRank)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_events_featured_rank")
+                    .table(Events::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Events::Table)
+                    .drop_column(Events::FeaturedRank)
+                    .drop_column(Events::FeaturedFrom)
+                    .drop_column(Events::FeaturedUntil)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Events {
+    Table,
+    FeaturedRank,
+    FeaturedFrom,
+    FeaturedUntil,
+}
@@ -0,0 +1,107 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // OHLCV bars per option and interval, built up as trades settle
+        manager
+            .create_table(
+                Table::create()
+                    .table(Candles::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Candles::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Candles::EventId).integer().not_null())
+                    .col(ColumnDef::new(Candles::OptionId).integer().not_null())
+                    .col(ColumnDef::new(Candles::Interval).string_len(8).not_null())
+                    .col(
+                        ColumnDef::new(Candles::OpenTime)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Candles::Open).decimal_len(20, 8).not_null())
+                    .col(ColumnDef::new(Candles::High).decimal_len(20, 8).not_null())
+                    .col(ColumnDef::new(Candles::Low).decimal_len(20, 8).not_null())
+                    .col(ColumnDef::new(Candles::Close).decimal_len(20, 8).not_null())
+                    .col(ColumnDef::new(Candles::Volume).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(Candles::Notional)
+                            .decimal_len(20, 8)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Candles::TradeCount).integer().not_null())
+                    .col(
+                        ColumnDef::new(Candles::FirstSequence)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Candles::LastSequence)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_candles_option")
+                            .from(Candles::Table, Candles::OptionId)
+                            .to(EventOptions::Table, EventOptions::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_candles_option_interval_open_time")
+                    .table(Candles::Table)
+                    .col(Candles::OptionId)
+                    .col(Candles::Interval)
+                    .col(Candles::OpenTime)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Candles::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Candles {
+    Table,
+    Id,
+    EventId,
+    OptionId,
+    Interval,
+    OpenTime,
+    Open,
+    High,
+    Low,
+    Close,
+    Volume,
+    Notional,
+    TradeCount,
+    FirstSequence,
+    LastSequence,
+}
+
+#[derive(DeriveIden)]
+enum EventOptions {
+    Table,
+    Id,
+}
@@ -0,0 +1,80 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Fee rate buy orders hold funds for on top of their notional, matching the
+/// server's TRADING_FEE_BPS
+fn trading_fee_bps() -> u32 {
+    std::env::var("TRADING_FEE_BPS")
+        .ok()
+        .and_then(|bps| bps.parse().ok())
+        .unwrap_or(0)
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Part of wallet_balance held for open buy orders; only the rest can be
+        // spent on new orders or withdrawn
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(
+                        ColumnDef::new(Users::ReservedBalance)
+                            .decimal_len(10, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Buy orders already open hold what they would have held if placed
+        // now: the notional of their unfilled quantity plus the worst-case
+        // fee, rounded up to the cent. Without it, their fills and cancels
+        // would release holds that were never taken.
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                r#"
+                UPDATE users
+                SET reserved_balance = held.amount
+                FROM (
+                    SELECT user_id,
+                           SUM(CEIL((notional + ROUND(notional * {bps} / 10000, 8)) * 100) / 100)
+                               AS amount
+                    FROM (
+                        SELECT user_id, (quantity - filled_quantity) * price AS notional
+                        FROM orders
+                        WHERE side = 'Buy' AND status IN ('Pending', 'PartiallyFilled')
+                    ) open_orders
+                    GROUP BY user_id
+                ) held
+                WHERE users.id = held.user_id
+                "#,
+                bps = trading_fee_bps()
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::ReservedBalance)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    ReservedBalance,
+}
@@ -0,0 +1,135 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Margin accounts are opt-in. Collateral for short positions is moved out
+        // of wallet_balance into margin_balance until the shorts are closed.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(
+                        ColumnDef::new(Users::MarginEnabled)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(
+                        ColumnDef::new(Users::MarginBalance)
+                            .decimal_len(10, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Every movement of collateral, kept apart from the wallet transaction log
+        manager
+            .create_table(
+                Table::create()
+                    .table(MarginLedger::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MarginLedger::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(MarginLedger::UserId).integer().not_null())
+                    .col(ColumnDef::new(MarginLedger::EventId).integer().not_null())
+                    .col(ColumnDef::new(MarginLedger::OptionId).integer().not_null())
+                    .col(ColumnDef::new(MarginLedger::Kind).string().not_null())
+                    .col(ColumnDef::new(MarginLedger::Shares).integer().not_null())
+                    .col(
+                        ColumnDef::new(MarginLedger::Amount)
+                            .decimal_len(10, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MarginLedger::MarginBefore)
+                            .decimal_len(10, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MarginLedger::MarginAfter)
+                            .decimal_len(10, 2)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(MarginLedger::Reference).string().not_null())
+                    .col(
+                        ColumnDef::new(MarginLedger::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_margin_ledger_user")
+                            .from(MarginLedger::Table, MarginLedger::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_margin_ledger_user_created_at")
+                    .table(MarginLedger::Table)
+                    .col(MarginLedger::UserId)
+                    .col(MarginLedger::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MarginLedger::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::MarginEnabled)
+                    .drop_column(Users::MarginBalance)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MarginLedger {
+    Table,
+    Id,
+    UserId,
+    EventId,
+    OptionId,
+    Kind,
+    Shares,
+    Amount,
+    MarginBefore,
+    MarginAfter,
+    Reference,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+    MarginEnabled,
+    MarginBalance,
+}
@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Databases that predate idx_user_positions_unique may hold several
+        // rows for one (user, event, option). Merge them into the oldest row,
+        // summing quantities and weighting average prices by size, so the
+        // unique index can be built and positions upserted against it.
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"
+            WITH merged AS (
+                SELECT
+                    MIN(id) AS keep_id,
+                    SUM(quantity) AS quantity,
+                    CASE
+                        WHEN SUM(quantity) = 0 OR SUM(ABS(quantity)) = 0 THEN 0
+                        ELSE SUM(average_price * ABS(quantity)) / SUM(ABS(quantity))
+                    END AS average_price,
+                    MAX(updated_at) AS updated_at
+                FROM user_positions
+                GROUP BY user_id, event_id, option_id
+                HAVING COUNT(*) > 1
+            )
+            UPDATE user_positions
+            SET quantity = merged.quantity,
+                average_price = merged.average_price,
+                updated_at = merged.updated_at
+            FROM merged
+            WHERE user_positions.id = merged.keep_id
+            "#,
+        )
+        .await?;
+
+        db.execute_unprepared(
+            r#"
+            DELETE FROM user_positions duplicate
+            USING user_positions kept
+            WHERE duplicate.user_id = kept.user_id
+              AND duplicate.event_id = kept.event_id
+              AND duplicate.option_id = kept.option_id
+              AND duplicate.id > kept.id
+            "#,
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_user_positions_unique")
+                    .table(UserPositions::Table)
+                    .col(UserPositions::UserId)
+                    .col(UserPositions::EventId)
+                    .col(UserPositions::OptionId)
+                    .unique()
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // The index belongs to the table's original migration, and merged
+        // rows can't be split again
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum UserPositions {
+    Table,
+    UserId,
+    EventId,
+    OptionId,
+}
@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Shares of the option created outside of trades, i.e. the
+        // inventory given to the market maker when liquidity is seeded
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EventOptions::Table)
+                    .add_column(
+                        ColumnDef::new(EventOptions::SharesIssued)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Issuance wasn't recorded before, so existing options start from
+        // the shares outstanding now
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                UPDATE event_options
+                SET shares_issued = COALESCE(
+                    (SELECT SUM(quantity) FROM user_positions
+                     WHERE user_positions.option_id = event_options.id),
+                    0
+                )
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EventOptions::Table)
+                    .drop_column(EventOptions::SharesIssued)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EventOptions {
+    Table,
+    SharesIssued,
+}
@@ -0,0 +1,94 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A user's two-factor settings. The TOTP secret is stored from
+        // enrollment and only enforced once a code confirms it;
+        // backup_codes is a comma-separated list of SHA-256 hashes of the
+        // unused backup codes.
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserSecurity::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserSecurity::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(UserSecurity::UserId)
+                            .integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(UserSecurity::TotpSecret).string_len(64))
+                    .col(
+                        ColumnDef::new(UserSecurity::TotpEnabled)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(ColumnDef::new(UserSecurity::TotpEnabledAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(UserSecurity::LastTotpStep).big_integer())
+                    .col(
+                        ColumnDef::new(UserSecurity::BackupCodes)
+                            .text()
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(
+                        ColumnDef::new(UserSecurity::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserSecurity::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_security_user")
+                            .from(UserSecurity::Table, UserSecurity::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserSecurity::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UserSecurity {
+    Table,
+    Id,
+    UserId,
+    TotpSecret,
+    TotpEnabled,
+    TotpEnabledAt,
+    LastTotpStep,
+    BackupCodes,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
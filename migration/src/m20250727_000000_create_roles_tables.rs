@@ -0,0 +1,210 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Staff roles granting a subset of admin permissions. Admins hold
+        // every permission without needing a role.
+        manager
+            .create_table(
+                Table::create()
+                    .table(Roles::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Roles::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Roles::Name)
+                            .string_len(50)
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(Roles::Description).string().not_null())
+                    .col(
+                        ColumnDef::new(Roles::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(RolePermissions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RolePermissions::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RolePermissions::RoleId).integer().not_null())
+                    .col(
+                        ColumnDef::new(RolePermissions::Permission)
+                            .string_len(50)
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_role_permissions_role")
+                            .from(RolePermissions::Table, RolePermissions::RoleId)
+                            .to(Roles::Table, Roles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_role_permissions_role_permission")
+                    .table(RolePermissions::Table)
+                    .col(RolePermissions::RoleId)
+                    .col(RolePermissions::Permission)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserRoles::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserRoles::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(UserRoles::UserId).integer().not_null())
+                    .col(ColumnDef::new(UserRoles::RoleId).integer().not_null())
+                    .col(ColumnDef::new(UserRoles::GrantedBy).integer())
+                    .col(
+                        ColumnDef::new(UserRoles::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_roles_user")
+                            .from(UserRoles::Table, UserRoles::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_roles_role")
+                            .from(UserRoles::Table, UserRoles::RoleId)
+                            .to(Roles::Table, Roles::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_user_roles_user_role")
+                    .table(UserRoles::Table)
+                    .col(UserRoles::UserId)
+                    .col(UserRoles::RoleId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Built-in roles for operations staff
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                INSERT INTO roles (name, description, created_at) VALUES
+                    ('event_manager', 'Creates and edits events, options and promotions', NOW()),
+                    ('settler', 'Settles events', NOW()),
+                    ('support', 'Helps users with their accounts and orders', NOW()),
+                    ('risk', 'Halts markets, runs auctions and audits positions', NOW())
+                ON CONFLICT (name) DO NOTHING;
+
+                INSERT INTO role_permissions (role_id, permission)
+                SELECT roles.id, grants.permission
+                FROM roles
+                JOIN (VALUES
+                    ('event_manager', 'events:manage'),
+                    ('event_manager', 'promotions:manage'),
+                    ('settler', 'events:settle'),
+                    ('support', 'users:support'),
+                    ('support', 'exports:read'),
+                    ('risk', 'markets:operate'),
+                    ('risk', 'audit:read'),
+                    ('risk', 'exports:read')
+                ) AS grants (role, permission) ON grants.role = roles.name
+                ON CONFLICT (role_id, permission) DO NOTHING;
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserRoles::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(RolePermissions::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Roles::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Roles {
+    Table,
+    Id,
+    Name,
+    Description,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RolePermissions {
+    Table,
+    Id,
+    RoleId,
+    Permission,
+}
+
+#[derive(DeriveIden)]
+enum UserRoles {
+    Table,
+    Id,
+    UserId,
+    RoleId,
+    GrantedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,182 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Tenant that everything created before tenants existed belongs to
+const DEFAULT_TENANT_ID: i32 = 1;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Branded sites served by this deployment
+        manager
+            .create_table(
+                Table::create()
+                    .table(Tenants::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Tenants::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Tenants::Slug)
+                            .string_len(50)
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(Tenants::Name).string_len(100).not_null())
+                    .col(ColumnDef::new(Tenants::Domain).string_len(255).unique_key())
+                    .col(
+                        ColumnDef::new(Tenants::CorsOrigins)
+                            .text()
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(
+                        ColumnDef::new(Tenants::LogoUrl)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(
+                        ColumnDef::new(Tenants::PrimaryColor)
+                            .string_len(7)
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(
+                        ColumnDef::new(Tenants::SupportEmail)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(
+                        ColumnDef::new(Tenants::AllowRegistration)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(Tenants::IsActive)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(Tenants::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Tenants::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                r#"
+                INSERT INTO tenants (id, slug, name, created_at, updated_at)
+                VALUES ({}, 'default', 'Exchange', NOW(), NOW())
+                ON CONFLICT (id) DO NOTHING;
+
+                SELECT setval(pg_get_serial_sequence('tenants', 'id'), (SELECT MAX(id) FROM tenants));
+                "#,
+                DEFAULT_TENANT_ID
+            ))
+            .await?;
+
+        // Existing users, events and orders belong to the default tenant
+        for (table, fk_name, index_name) in [
+            (Scoped::Users, "fk_users_tenant", "idx_users_tenant_id"),
+            (Scoped::Events, "fk_events_tenant", "idx_events_tenant_id"),
+            (Scoped::Orders, "fk_orders_tenant", "idx_orders_tenant_id"),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .add_column(
+                            ColumnDef::new(Scoped::TenantId)
+                                .integer()
+                                .not_null()
+                                .default(DEFAULT_TENANT_ID),
+                        )
+                        .add_foreign_key(
+                            TableForeignKey::new()
+                                .name(fk_name)
+                                .from_tbl(table)
+                                .from_col(Scoped::TenantId)
+                                .to_tbl(Tenants::Table)
+                                .to_col(Tenants::Id)
+                                .on_delete(ForeignKeyAction::Restrict)
+                                .on_update(ForeignKeyAction::Cascade),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+
+            manager
+                .create_index(
+                    Index::create()
+                        .name(index_name)
+                        .table(table)
+                        .col(Scoped::TenantId)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in [Scoped::Orders, Scoped::Events, Scoped::Users] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .drop_column(Scoped::TenantId)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        manager
+            .drop_table(Table::drop().table(Tenants::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Tenants {
+    Table,
+    Id,
+    Slug,
+    Name,
+    Domain,
+    CorsOrigins,
+    LogoUrl,
+    PrimaryColor,
+    SupportEmail,
+    AllowRegistration,
+    IsActive,
+    CreatedAt,
+    UpdatedAt,
+}
+
+/// Tables that gain a tenant, and their shared column
+#[derive(DeriveIden, Clone, Copy)]
+enum Scoped {
+    Users,
+    Events,
+    Orders,
+    TenantId,
+}
@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Operator-editable settings. A row without a tenant applies to the
+        // whole deployment; a tenant's row overrides it for that tenant.
+        manager
+            .create_table(
+                Table::create()
+                    .table(Settings::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Settings::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Settings::TenantId).integer())
+                    .col(ColumnDef::new(Settings::Key).string_len(50).not_null())
+                    .col(ColumnDef::new(Settings::Value).string().not_null())
+                    .col(ColumnDef::new(Settings::UpdatedBy).integer())
+                    .col(
+                        ColumnDef::new(Settings::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_settings_tenant")
+                            .from(Settings::Table, Settings::TenantId)
+                            .to(Tenants::Table, Tenants::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // One value per key deployment-wide and one per key per tenant. NULL
+        // tenants are distinct in a plain unique index, hence the two.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_settings_system_key
+                    ON settings (key) WHERE tenant_id IS NULL;
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_settings_tenant_key
+                    ON settings (tenant_id, key) WHERE tenant_id IS NOT NULL;
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Settings::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Settings {
+    Table,
+    Id,
+    TenantId,
+    Key,
+    Value,
+    UpdatedBy,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Tenants {
+    Table,
+    Id,
+}
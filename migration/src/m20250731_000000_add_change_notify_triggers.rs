@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Announce changes to cached tables on the `row_changes` channel so
+        // the app can invalidate its caches. The app's own connections set
+        // application_name to 'centralized-exchange' and already invalidate
+        // what they change, so only writes from elsewhere (manual fixes,
+        // other services) are announced.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                CREATE OR REPLACE FUNCTION notify_row_change() RETURNS trigger AS $$
+                DECLARE
+                    row_data jsonb;
+                BEGIN
+                    IF current_setting('application_name', true) = 'centralized-exchange' THEN
+                        RETURN NULL;
+                    END IF;
+
+                    IF TG_OP = 'DELETE' THEN
+                        row_data := to_jsonb(OLD);
+                    ELSE
+                        row_data := to_jsonb(NEW);
+                    END IF;
+
+                    PERFORM pg_notify('row_changes', json_build_object(
+                        'table', TG_TABLE_NAME,
+                        'op', TG_OP,
+                        'id', row_data->'id',
+                        'event_id', row_data->'event_id',
+                        'tenant_id', row_data->'tenant_id'
+                    )::text);
+                    RETURN NULL;
+                END;
+                $$ LANGUAGE plpgsql;
+
+                DROP TRIGGER IF EXISTS events_notify_row_change ON events;
+                CREATE TRIGGER events_notify_row_change
+                    AFTER INSERT OR UPDATE OR DELETE ON events
+                    FOR EACH ROW EXECUTE FUNCTION notify_row_change();
+
+                DROP TRIGGER IF EXISTS event_options_notify_row_change ON event_options;
+                CREATE TRIGGER event_options_notify_row_change
+                    AFTER INSERT OR UPDATE OR DELETE ON event_options
+                    FOR EACH ROW EXECUTE FUNCTION notify_row_change();
+
+                DROP TRIGGER IF EXISTS users_notify_row_change ON users;
+                CREATE TRIGGER users_notify_row_change
+                    AFTER INSERT OR UPDATE OR DELETE ON users
+                    FOR EACH ROW EXECUTE FUNCTION notify_row_change();
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                DROP TRIGGER IF EXISTS users_notify_row_change ON users;
+                DROP TRIGGER IF EXISTS event_options_notify_row_change ON event_options;
+                DROP TRIGGER IF EXISTS events_notify_row_change ON events;
+                DROP FUNCTION IF EXISTS notify_row_change();
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // unverified, pending, verified or rejected
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(
+                        ColumnDef::new(Users::KycStatus)
+                            .string_len(20)
+                            .not_null()
+                            .default("unverified"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_users_kyc_status")
+                    .table(Users::Table)
+                    .col(Users::KycStatus)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Identity documents uploaded for review, kept after review so a
+        // decision can be revisited
+        manager
+            .create_table(
+                Table::create()
+                    .table(KycDocuments::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(KycDocuments::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(KycDocuments::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(KycDocuments::DocumentType)
+                            .string_len(30)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(KycDocuments::ContentType)
+                            .string_len(50)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(KycDocuments::Content).binary().not_null())
+                    .col(ColumnDef::new(KycDocuments::SizeBytes).integer().not_null())
+                    .col(
+                        ColumnDef::new(KycDocuments::Sha256)
+                            .string_len(64)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(KycDocuments::Status)
+                            .string_len(20)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(KycDocuments::RejectionReason).string())
+                    .col(ColumnDef::new(KycDocuments::ReviewedBy).integer())
+                    .col(ColumnDef::new(KycDocuments::ReviewedAt).timestamp_with_time_zone())
+                    .col(
+                        ColumnDef::new(KycDocuments::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_kyc_documents_user")
+                            .from(KycDocuments::Table, KycDocuments::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_kyc_documents_user_id")
+                    .table(KycDocuments::Table)
+                    .col(KycDocuments::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Support staff review documents
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                INSERT INTO role_permissions (role_id, permission)
+                SELECT id, 'kyc:review' FROM roles WHERE name = 'support'
+                ON CONFLICT (role_id, permission) DO NOTHING;
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("DELETE FROM role_permissions WHERE permission = 'kyc:review';")
+            .await?;
+        manager
+            .drop_table(Table::drop().table(KycDocuments::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::KycStatus)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+    KycStatus,
+}
+
+#[derive(DeriveIden)]
+enum KycDocuments {
+    Table,
+    Id,
+    UserId,
+    DocumentType,
+    ContentType,
+    Content,
+    SizeBytes,
+    Sha256,
+    Status,
+    RejectionReason,
+    ReviewedBy,
+    ReviewedAt,
+    CreatedAt,
+}
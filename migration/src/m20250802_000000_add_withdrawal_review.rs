@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Withdrawals wait as pending until an admin approves or rejects them
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transaction::Table)
+                    .add_column(ColumnDef::new(Transaction::ReviewedBy).integer())
+                    .add_column(ColumnDef::new(Transaction::ReviewedAt).timestamp())
+                    .add_column(ColumnDef::new(Transaction::RejectionReason).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        // The admin approval queue
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                CREATE INDEX IF NOT EXISTS idx_transaction_pending_withdrawals
+                ON transaction (created_at)
+                WHERE type = 'withdraw' AND status = 'pending';
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("DROP INDEX IF EXISTS idx_transaction_pending_withdrawals;")
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transaction::Table)
+                    .drop_column(Transaction::ReviewedBy)
+                    .drop_column(Transaction::ReviewedAt)
+                    .drop_column(Transaction::RejectionReason)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Transaction {
+    Table,
+    ReviewedBy,
+    ReviewedAt,
+    RejectionReason,
+}
@@ -0,0 +1,160 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Deposits paid through the payment gateway, from checkout to credit
+        manager
+            .create_table(
+                Table::create()
+                    .table(PaymentIntents::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PaymentIntents::Id)
+                            .string_len(40)
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PaymentIntents::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(PaymentIntents::Gateway)
+                            .string_len(30)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentIntents::Amount)
+                            .decimal_len(10, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentIntents::Currency)
+                            .string_len(3)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentIntents::Status)
+                            .string_len(20)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PaymentIntents::GatewayReference).string())
+                    .col(ColumnDef::new(PaymentIntents::CheckoutUrl).string())
+                    .col(ColumnDef::new(PaymentIntents::TransactionId).integer())
+                    .col(ColumnDef::new(PaymentIntents::FailureReason).string())
+                    .col(
+                        ColumnDef::new(PaymentIntents::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PaymentIntents::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_intents_user")
+                            .from(PaymentIntents::Table, PaymentIntents::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_payment_intents_transaction")
+                            .from(PaymentIntents::Table, PaymentIntents::TransactionId)
+                            .to(Transaction::Table, Transaction::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_payment_intents_user_id")
+                    .table(PaymentIntents::Table)
+                    .col(PaymentIntents::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // The payment behind a gateway deposit, so it is credited only once
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transaction::Table)
+                    .add_column(ColumnDef::new(Transaction::Gateway).string_len(30))
+                    .add_column(ColumnDef::new(Transaction::GatewayReference).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_transaction_gateway_reference
+                ON transaction (gateway, gateway_reference)
+                WHERE gateway_reference IS NOT NULL;
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PaymentIntents::Table).to_owned())
+            .await?;
+        manager
+            .get_connection()
+            .execute_unprepared("DROP INDEX IF EXISTS idx_transaction_gateway_reference;")
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transaction::Table)
+                    .drop_column(Transaction::Gateway)
+                    .drop_column(Transaction::GatewayReference)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PaymentIntents {
+    Table,
+    Id,
+    UserId,
+    Gateway,
+    Amount,
+    Currency,
+    Status,
+    GatewayReference,
+    CheckoutUrl,
+    TransactionId,
+    FailureReason,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Transaction {
+    Table,
+    Id,
+    Gateway,
+    GatewayReference,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,224 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Currency balances held before wallets were kept per currency are moved
+/// into, matching the server's BASE_CURRENCY
+fn base_currency() -> String {
+    std::env::var("BASE_CURRENCY")
+        .map(|currency| currency.trim().to_ascii_uppercase())
+        .ok()
+        .filter(|currency| currency.len() == 3)
+        .unwrap_or_else(|| "USD".to_string())
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let base = base_currency();
+
+        // One balance per user and currency, replacing the balances on users
+        manager
+            .create_table(
+                Table::create()
+                    .table(Wallets::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Wallets::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Wallets::UserId).integer().not_null())
+                    .col(ColumnDef::new(Wallets::Currency).string_len(3).not_null())
+                    .col(
+                        ColumnDef::new(Wallets::Balance)
+                            .decimal_len(10, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Wallets::ReservedBalance)
+                            .decimal_len(10, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Wallets::MarginBalance)
+                            .decimal_len(10, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Wallets::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Wallets::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_wallets_user")
+                            .from(Wallets::Table, Wallets::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_wallets_user_currency")
+                    .table(Wallets::Table)
+                    .col(Wallets::UserId)
+                    .col(Wallets::Currency)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                r#"
+                INSERT INTO wallets
+                    (user_id, currency, balance, reserved_balance, margin_balance, created_at, updated_at)
+                SELECT id, '{base}', wallet_balance, reserved_balance, margin_balance, NOW(), NOW()
+                FROM users
+                ON CONFLICT (user_id, currency) DO NOTHING;
+                "#
+            ))
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::WalletBalance)
+                    .drop_column(Users::ReservedBalance)
+                    .drop_column(Users::MarginBalance)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Everything priced or paid is in one currency; existing rows are in
+        // the base currency
+        for table in [
+            Alias::new("events"),
+            Alias::new("orders"),
+            Alias::new("trades"),
+            Alias::new("transaction"),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .add_column(
+                            ColumnDef::new(Alias::new("currency"))
+                                .string_len(3)
+                                .not_null()
+                                .default(base.clone()),
+                        )
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let base = base_currency();
+
+        for table in [
+            Alias::new("events"),
+            Alias::new("orders"),
+            Alias::new("trades"),
+            Alias::new("transaction"),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(table)
+                        .drop_column(Alias::new("currency"))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(
+                        ColumnDef::new(Users::WalletBalance)
+                            .decimal_len(10, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(
+                        ColumnDef::new(Users::ReservedBalance)
+                            .decimal_len(10, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(
+                        ColumnDef::new(Users::MarginBalance)
+                            .decimal_len(10, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Only base currency balances have somewhere to go back to
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                r#"
+                UPDATE users SET
+                    wallet_balance = wallets.balance,
+                    reserved_balance = wallets.reserved_balance,
+                    margin_balance = wallets.margin_balance
+                FROM wallets
+                WHERE wallets.user_id = users.id AND wallets.currency = '{base}';
+                "#
+            ))
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Wallets::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Wallets {
+    Table,
+    Id,
+    UserId,
+    Currency,
+    Balance,
+    ReservedBalance,
+    MarginBalance,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+    WalletBalance,
+    ReservedBalance,
+    MarginBalance,
+}
@@ -0,0 +1,80 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Trades are written `pending` and marked `settled` once positions,
+        // balances and fills are applied; trades made before this were settled
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Trades::Table)
+                    .add_column(
+                        ColumnDef::new(Trades::SettlementStatus)
+                            .string()
+                            .not_null()
+                            .default("settled"),
+                    )
+                    .add_column(
+                        ColumnDef::new(Trades::SettlementAttempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(ColumnDef::new(Trades::SettlementError).text())
+                    .add_column(ColumnDef::new(Trades::BuyerPositionBefore).integer())
+                    .add_column(ColumnDef::new(Trades::SellerPositionBefore).integer())
+                    .add_column(ColumnDef::new(Trades::SettledAt).timestamp_with_time_zone())
+                    .to_owned(),
+            )
+            .await?;
+
+        // The retry queue
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                CREATE INDEX IF NOT EXISTS idx_trades_unsettled
+                ON trades (timestamp)
+                WHERE settlement_status <> 'settled';
+                "#,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("DROP INDEX IF EXISTS idx_trades_unsettled;")
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Trades::Table)
+                    .drop_column(Trades::SettlementStatus)
+                    .drop_column(Trades::SettlementAttempts)
+                    .drop_column(Trades::SettlementError)
+                    .drop_column(Trades::BuyerPositionBefore)
+                    .drop_column(Trades::SellerPositionBefore)
+                    .drop_column(Trades::SettledAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Trades {
+    Table,
+    SettlementStatus,
+    SettlementAttempts,
+    SettlementError,
+    BuyerPositionBefore,
+    SellerPositionBefore,
+    SettledAt,
+}
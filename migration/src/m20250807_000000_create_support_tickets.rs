@@ -0,0 +1,215 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Disputes users raise about a trade, settlement or transaction.
+        // Status is open, in_progress, resolved or closed.
+        manager
+            .create_table(
+                Table::create()
+                    .table(SupportTickets::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SupportTickets::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SupportTickets::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(SupportTickets::ReferenceType)
+                            .string_len(20)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SupportTickets::ReferenceId)
+                            .string_len(64)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SupportTickets::Subject)
+                            .string_len(200)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SupportTickets::Status)
+                            .string_len(20)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(SupportTickets::AssignedTo).integer())
+                    .col(
+                        ColumnDef::new(SupportTickets::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SupportTickets::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(SupportTickets::ResolvedAt).timestamp_with_time_zone())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_support_tickets_user")
+                            .from(SupportTickets::Table, SupportTickets::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_support_tickets_assignee")
+                            .from(SupportTickets::Table, SupportTickets::AssignedTo)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_support_tickets_user_id")
+                    .table(SupportTickets::Table)
+                    .col(SupportTickets::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // The triage queue lists tickets by status, oldest first
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_support_tickets_status_created_at")
+                    .table(SupportTickets::Table)
+                    .col(SupportTickets::Status)
+                    .col(SupportTickets::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // The conversation on a ticket between the user and support staff
+        manager
+            .create_table(
+                Table::create()
+                    .table(SupportTicketMessages::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SupportTicketMessages::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(SupportTicketMessages::TicketId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SupportTicketMessages::AuthorId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SupportTicketMessages::FromStaff)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SupportTicketMessages::Body)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SupportTicketMessages::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_support_ticket_messages_ticket")
+                            .from(
+                                SupportTicketMessages::Table,
+                                SupportTicketMessages::TicketId,
+                            )
+                            .to(SupportTickets::Table, SupportTickets::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_support_ticket_messages_author")
+                            .from(
+                                SupportTicketMessages::Table,
+                                SupportTicketMessages::AuthorId,
+                            )
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_support_ticket_messages_ticket_id")
+                    .table(SupportTicketMessages::Table)
+                    .col(SupportTicketMessages::TicketId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SupportTicketMessages::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(SupportTickets::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum SupportTickets {
+    Table,
+    Id,
+    UserId,
+    ReferenceType,
+    ReferenceId,
+    Subject,
+    Status,
+    AssignedTo,
+    CreatedAt,
+    UpdatedAt,
+    ResolvedAt,
+}
+
+#[derive(DeriveIden)]
+enum SupportTicketMessages {
+    Table,
+    Id,
+    TicketId,
+    AuthorId,
+    FromStaff,
+    Body,
+    CreatedAt,
+}
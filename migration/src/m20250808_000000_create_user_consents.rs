@@ -0,0 +1,86 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Each version of the terms of service or privacy policy a user
+        // accepted, kept as evidence of consent
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserConsents::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserConsents::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(UserConsents::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(UserConsents::Document)
+                            .string_len(30)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(UserConsents::Version).integer().not_null())
+                    .col(ColumnDef::new(UserConsents::Ip).string())
+                    .col(
+                        ColumnDef::new(UserConsents::AcceptedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_consents_user")
+                            .from(UserConsents::Table, UserConsents::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_user_consents_user_document_version")
+                    .table(UserConsents::Table)
+                    .col(UserConsents::UserId)
+                    .col(UserConsents::Document)
+                    .col(UserConsents::Version)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserConsents::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum UserConsents {
+    Table,
+    Id,
+    UserId,
+    Document,
+    Version,
+    Ip,
+    AcceptedAt,
+}
@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // What each share of the option paid when its event settled
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EventOptions::Table)
+                    .add_column(ColumnDef::new(EventOptions::PayoutPerShare).decimal_len(10, 2))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EventOptions::Table)
+                    .drop_column(EventOptions::PayoutPerShare)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EventOptions {
+    Table,
+    PayoutPerShare,
+}
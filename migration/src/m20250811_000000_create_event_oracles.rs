@@ -0,0 +1,153 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The external feed an event is resolved from: where it is, the JSON
+        // pointer of the result in it and which option each result means.
+        // Status is waiting, proposed, rejected or settled.
+        manager
+            .create_table(
+                Table::create()
+                    .table(EventOracles::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EventOracles::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(EventOracles::EventId)
+                            .integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(EventOracles::SourceUrl)
+                            .string_len(500)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EventOracles::ValuePath)
+                            .string_len(200)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EventOracles::OptionMappings)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EventOracles::AutoSettle)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(EventOracles::ChallengeWindowMinutes)
+                            .integer()
+                            .not_null()
+                            .default(60),
+                    )
+                    .col(
+                        ColumnDef::new(EventOracles::Status)
+                            .string_len(20)
+                            .not_null()
+                            .default("waiting"),
+                    )
+                    .col(ColumnDef::new(EventOracles::ProposedOptionId).integer())
+                    .col(ColumnDef::new(EventOracles::ProposedValue).string_len(200))
+                    .col(ColumnDef::new(EventOracles::ProposedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(EventOracles::SettleAfter).timestamp_with_time_zone())
+                    .col(ColumnDef::new(EventOracles::LastCheckedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(EventOracles::LastError).text())
+                    .col(ColumnDef::new(EventOracles::CreatedBy).integer().not_null())
+                    .col(
+                        ColumnDef::new(EventOracles::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EventOracles::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_event_oracles_event")
+                            .from(EventOracles::Table, EventOracles::EventId)
+                            .to(Events::Table, Events::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_event_oracles_created_by")
+                            .from(EventOracles::Table, EventOracles::CreatedBy)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Restrict)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The scheduler polls waiting oracles and settles due proposals
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_event_oracles_status")
+                    .table(EventOracles::Table)
+                    .col(EventOracles::Status)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EventOracles::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Events {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum EventOracles {
+    Table,
+    Id,
+    EventId,
+    SourceUrl,
+    ValuePath,
+    OptionMappings,
+    AutoSettle,
+    ChallengeWindowMinutes,
+    Status,
+    ProposedOptionId,
+    ProposedValue,
+    ProposedAt,
+    SettleAfter,
+    LastCheckedAt,
+    LastError,
+    CreatedBy,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,164 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Correlation id of the request that placed each order, carried onto
+        // everything the order causes so one id traces its whole life
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Orders::Table)
+                    .add_column(ColumnDef::new(Orders::CorrelationId).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Trades::Table)
+                    .add_column(ColumnDef::new(Trades::BuyCorrelationId).string().null())
+                    .add_column(ColumnDef::new(Trades::SellCorrelationId).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transaction::Table)
+                    .add_column(ColumnDef::new(Transaction::CorrelationId).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MarginLedger::Table)
+                    .add_column(ColumnDef::new(MarginLedger::CorrelationId).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_orders_correlation_id")
+                    .table(Orders::Table)
+                    .col(Orders::CorrelationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_trades_buy_correlation_id")
+                    .table(Trades::Table)
+                    .col(Trades::BuyCorrelationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_trades_sell_correlation_id")
+                    .table(Trades::Table)
+                    .col(Trades::SellCorrelationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_transaction_correlation_id")
+                    .table(Transaction::Table)
+                    .col(Transaction::CorrelationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_margin_ledger_correlation_id")
+                    .table(MarginLedger::Table)
+                    .col(MarginLedger::CorrelationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MarginLedger::Table)
+                    .drop_column(MarginLedger::CorrelationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Transaction::Table)
+                    .drop_column(Transaction::CorrelationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Trades::Table)
+                    .drop_column(Trades::BuyCorrelationId)
+                    .drop_column(Trades::SellCorrelationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Orders::Table)
+                    .drop_column(Orders::CorrelationId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    CorrelationId,
+}
+
+#[derive(DeriveIden)]
+enum Trades {
+    Table,
+    BuyCorrelationId,
+    SellCorrelationId,
+}
+
+#[derive(DeriveIden)]
+enum Transaction {
+    Table,
+    CorrelationId,
+}
+
+#[derive(DeriveIden)]
+enum MarginLedger {
+    Table,
+    CorrelationId,
+}
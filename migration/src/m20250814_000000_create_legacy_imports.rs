@@ -0,0 +1,108 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Balances and positions migrated in from another system. The
+        // caller's import id is unique within the tenant so the same file
+        // can't be applied twice, and the report is kept as it was returned.
+        manager
+            .create_table(
+                Table::create()
+                    .table(LegacyImports::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LegacyImports::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(LegacyImports::ImportId)
+                            .string_len(100)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(LegacyImports::TenantId).integer().not_null())
+                    .col(
+                        ColumnDef::new(LegacyImports::Report)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LegacyImports::CreatedBy)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LegacyImports::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_legacy_imports_tenant")
+                            .from(LegacyImports::Table, LegacyImports::TenantId)
+                            .to(Tenants::Table, Tenants::Id)
+                            .on_delete(ForeignKeyAction::Restrict)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_legacy_imports_created_by")
+                            .from(LegacyImports::Table, LegacyImports::CreatedBy)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Restrict)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_legacy_imports_tenant_import_id")
+                    .table(LegacyImports::Table)
+                    .col(LegacyImports::TenantId)
+                    .col(LegacyImports::ImportId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LegacyImports::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Tenants {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum LegacyImports {
+    Table,
+    Id,
+    ImportId,
+    TenantId,
+    Report,
+    CreatedBy,
+    CreatedAt,
+}
@@ -0,0 +1,92 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Every price the price updater gives an option, so its probability
+        // can be charted over time even when it rarely trades
+        manager
+            .create_table(
+                Table::create()
+                    .table(PriceHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PriceHistory::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PriceHistory::EventId).integer().not_null())
+                    .col(ColumnDef::new(PriceHistory::OptionId).integer().not_null())
+                    .col(
+                        ColumnDef::new(PriceHistory::Price)
+                            .decimal_len(8, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PriceHistory::PreviousPrice)
+                            .decimal_len(8, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PriceHistory::Source)
+                            .string_len(20)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PriceHistory::Timestamp)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_price_history_option")
+                            .from(PriceHistory::Table, PriceHistory::OptionId)
+                            .to(EventOptions::Table, EventOptions::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_price_history_option_timestamp")
+                    .table(PriceHistory::Table)
+                    .col(PriceHistory::OptionId)
+                    .col(PriceHistory::Timestamp)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PriceHistory::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PriceHistory {
+    Table,
+    Id,
+    EventId,
+    OptionId,
+    Price,
+    PreviousPrice,
+    Source,
+    Timestamp,
+}
+
+#[derive(DeriveIden)]
+enum EventOptions {
+    Table,
+    Id,
+}
@@ -0,0 +1,272 @@
+use crate::order_book::types::Trade;
+use crate::types::candle::{CandleInterval, OptionMarketStats};
+use chrono::{DateTime, Utc};
+use entity::candles;
+use sea_orm::{
+    prelude::{Decimal, Expr},
+    sea_query::OnConflict,
+    ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use std::collections::HashMap;
+
+/// Folds settled trades into OHLCV bars for every candle interval and reads
+/// them back for charts
+pub struct CandleService {
+    db: DatabaseConnection,
+}
+
+/// One bar's worth of trades from a single settlement batch
+struct PartialCandle {
+    event_id: i32,
+    open: Decimal,
+    high: Decimal,
+    low: Decimal,
+    close: Decimal,
+    volume: i64,
+    notional: Decimal,
+    trade_count: i32,
+    first_sequence: i64,
+    last_sequence: i64,
+}
+
+impl CandleService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Merge trades into the bars they fall in, creating bars as needed.
+    /// Returns the updated bars so they can be pushed to subscribers.
+    pub async fn record_trades(&self, trades: &[Trade]) -> Result<Vec<candles::Model>, String> {
+        let mut ordered: Vec<&Trade> = trades.iter().collect();
+        ordered.sort_by_key(|t| (t.sequence, t.timestamp));
+
+        let mut partials: HashMap<(i32, CandleInterval, DateTime<Utc>), PartialCandle> =
+            HashMap::new();
+        for trade in ordered {
+            let sequence = trade.sequence as i64;
+            for interval in CandleInterval::ALL {
+                let key = (
+                    trade.option_id,
+                    interval,
+                    interval.bucket_start(trade.timestamp),
+                );
+                let candle = partials.entry(key).or_insert_with(|| PartialCandle {
+                    event_id: trade.event_id,
+                    open: trade.price,
+                    high: trade.price,
+                    low: trade.price,
+                    close: trade.price,
+                    volume: 0,
+                    notional: Decimal::ZERO,
+                    trade_count: 0,
+                    first_sequence: sequence,
+                    last_sequence: sequence,
+                });
+                candle.high = candle.high.max(trade.price);
+                candle.low = candle.low.min(trade.price);
+                candle.close = trade.price;
+                candle.volume += i64::from(trade.quantity);
+                candle.notional += trade.total_amount;
+                candle.trade_count += 1;
+                candle.last_sequence = sequence;
+            }
+        }
+
+        let mut updated = Vec::with_capacity(partials.len());
+        for ((option_id, interval, open_time), candle) in partials {
+            let row = candles::ActiveModel {
+                event_id: Set(candle.event_id),
+                option_id: Set(option_id),
+                interval: Set(interval.as_str().to_string()),
+                open_time: Set(open_time.into()),
+                open: Set(candle.open),
+                high: Set(candle.high),
+                low: Set(candle.low),
+                close: Set(candle.close),
+                volume: Set(candle.volume),
+                notional: Set(candle.notional),
+                trade_count: Set(candle.trade_count),
+                first_sequence: Set(candle.first_sequence),
+                last_sequence: Set(candle.last_sequence),
+                ..Default::default()
+            };
+
+            // Batches can settle out of order, so open and close follow the
+            // book sequence rather than whichever batch is written last
+            let model = candles::Entity::insert(row)
+                .on_conflict(
+                    OnConflict::columns([
+                        candles::Column::OptionId,
+                        candles::Column::Interval,
+                        candles::Column::OpenTime,
+                    ])
+                    .value(
+                        candles::Column::Open,
+                        Expr::cust(
+                            "CASE WHEN EXCLUDED.first_sequence < candles.first_sequence \
+                             THEN EXCLUDED.open ELSE candles.open END",
+                        ),
+                    )
+                    .value(
+                        candles::Column::High,
+                        Expr::cust("GREATEST(candles.high, EXCLUDED.high)"),
+                    )
+                    .value(
+                        candles::Column::Low,
+                        Expr::cust("LEAST(candles.low, EXCLUDED.low)"),
+                    )
+                    .value(
+                        candles::Column::Close,
+                        Expr::cust(
+                            "CASE WHEN EXCLUDED.last_sequence >= candles.last_sequence \
+                             THEN EXCLUDED.close ELSE candles.close END",
+                        ),
+                    )
+                    .value(
+                        candles::Column::Volume,
+                        Expr::cust("candles.volume + EXCLUDED.volume"),
+                    )
+                    .value(
+                        candles::Column::Notional,
+                        Expr::cust("candles.notional + EXCLUDED.notional"),
+                    )
+                    .value(
+                        candles::Column::TradeCount,
+                        Expr::cust("candles.trade_count + EXCLUDED.trade_count"),
+                    )
+                    .value(
+                        candles::Column::FirstSequence,
+                        Expr::cust("LEAST(candles.first_sequence, EXCLUDED.first_sequence)"),
+                    )
+                    .value(
+                        candles::Column::LastSequence,
+                        Expr::cust("GREATEST(candles.last_sequence, EXCLUDED.last_sequence)"),
+                    )
+                    .to_owned(),
+                )
+                .exec_with_returning(&self.db)
+                .await
+                .map_err(|e| {
+                    format!(
+                        "Failed to save {} candle for option {}: {}",
+                        interval.as_str(),
+                        option_id,
+                        e
+                    )
+                })?;
+            updated.push(model);
+        }
+
+        Ok(updated)
+    }
+
+    /// Bars for an option in `[from, to)`, oldest first. Without a window the
+    /// most recent `limit` bars are returned.
+    pub async fn list(
+        &self,
+        event_id: i32,
+        option_id: i32,
+        interval: CandleInterval,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        limit: u64,
+    ) -> Result<Vec<candles::Model>, String> {
+        let mut query = candles::Entity::find()
+            .filter(candles::Column::EventId.eq(event_id))
+            .filter(candles::Column::OptionId.eq(option_id))
+            .filter(candles::Column::Interval.eq(interval.as_str()));
+        if let Some(from) = from {
+            query = query.filter(candles::Column::OpenTime.gte(interval.bucket_start(from)));
+        }
+        if let Some(to) = to {
+            query = query.filter(candles::Column::OpenTime.lt(to));
+        }
+
+        let mut bars = query
+            .order_by_desc(candles::Column::OpenTime)
+            .limit(limit)
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load candles: {}", e))?;
+        bars.reverse();
+        Ok(bars)
+    }
+
+    /// Bars of an interval that opened at or after `since`, oldest first
+    pub async fn since(
+        &self,
+        option_id: i32,
+        interval: CandleInterval,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<candles::Model>, String> {
+        candles::Entity::find()
+            .filter(candles::Column::OptionId.eq(option_id))
+            .filter(candles::Column::Interval.eq(interval.as_str()))
+            .filter(candles::Column::OpenTime.gte(since))
+            .order_by_asc(candles::Column::OpenTime)
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load candles: {}", e))
+    }
+
+    /// Rolling 24h summary of an option from its 5m bars, so a request reads at
+    /// most 288 rows however much has traded. The window starts at the bar
+    /// boundary, so it can reach up to five minutes further back than 24h.
+    /// `last` and the open order counts come from the live book.
+    pub async fn summary_24h(
+        &self,
+        event_id: i32,
+        option_id: i32,
+        last: Option<Decimal>,
+        (open_buy_orders, open_sell_orders): (usize, usize),
+    ) -> Result<OptionMarketStats, String> {
+        let now = Utc::now();
+        let window_start =
+            CandleInterval::FiveMinutes.bucket_start(now - chrono::Duration::hours(24));
+        let bars = self
+            .since(option_id, CandleInterval::FiveMinutes, window_start)
+            .await?;
+
+        let open = bars.first().map(|bar| bar.open);
+        let last = last.or_else(|| bars.last().map(|bar| bar.close));
+        let change = open.zip(last).map(|(open, last)| last - open);
+        let change_percent = open
+            .zip(change)
+            .filter(|(open, _)| !open.is_zero())
+            .map(|(open, change)| (change / open * Decimal::ONE_HUNDRED).round_dp(2));
+
+        Ok(OptionMarketStats {
+            event_id,
+            option_id,
+            open,
+            high: bars.iter().map(|bar| bar.high).max(),
+            low: bars.iter().map(|bar| bar.low).min(),
+            last,
+            change,
+            change_percent,
+            volume: bars.iter().map(|bar| bar.volume).sum(),
+            notional: bars.iter().map(|bar| bar.notional).sum(),
+            trade_count: bars.iter().map(|bar| i64::from(bar.trade_count)).sum(),
+            open_buy_orders,
+            open_sell_orders,
+            window_start,
+            timestamp: now,
+        })
+    }
+
+    /// The most recent bar of an interval, which is still forming if no bar
+    /// has opened after it
+    pub async fn latest(
+        &self,
+        option_id: i32,
+        interval: CandleInterval,
+    ) -> Result<Option<candles::Model>, String> {
+        candles::Entity::find()
+            .filter(candles::Column::OptionId.eq(option_id))
+            .filter(candles::Column::Interval.eq(interval.as_str()))
+            .order_by_desc(candles::Column::OpenTime)
+            .one(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load candle: {}", e))
+    }
+}
@@ -0,0 +1,173 @@
+use crate::order_book::market_stats::MarketStatsService;
+use crate::tenants::registry;
+use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
+use crate::websocket::handlers::WebSocketHandlers;
+use crate::websocket::server::{BroadcastEventsUpdate, BroadcastPortfolioUpdate, WebSocketServer};
+use actix::Addr;
+use actix_web::web;
+use deadpool_redis::Pool;
+use sea_orm::sqlx::postgres::PgListener;
+use sea_orm::DatabaseConnection;
+use serde::Deserialize;
+
+/// `application_name` of the app's database connections. The change triggers
+/// skip writes made under it, since the app invalidates what it changes.
+pub const APPLICATION_NAME: &str = "centralized-exchange";
+
+/// Channel the change triggers notify on
+const CHANNEL: &str = "row_changes";
+
+/// How long to wait before listening again after the connection fails
+const RECONNECT_DELAY_SECONDS: u64 = 5;
+
+/// A row changed outside the app, as announced by `notify_row_change()`
+#[derive(Debug, Deserialize)]
+struct RowChange {
+    table: String,
+    op: String,
+    id: Option<i32>,
+    event_id: Option<i32>,
+    tenant_id: Option<i32>,
+}
+
+/// `url` with the app's `application_name`, unless it already sets one
+pub fn with_application_name(url: &str) -> String {
+    if url.contains("application_name=") {
+        return url.to_string();
+    }
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}application_name={}", url, separator, APPLICATION_NAME)
+}
+
+/// Start a background task that listens for rows changed outside the app and
+/// invalidates their caches and refreshes WebSocket subscribers, so manual
+/// fixes and other services' writes are seen straight away
+pub fn start_change_listener(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+) {
+    log::info!("Starting database change listener");
+
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = listen(&db, &redis_pool, &ws_server).await {
+                log::error!("Database change listener failed: {}", e);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(RECONNECT_DELAY_SECONDS)).await;
+        }
+    });
+}
+
+/// Apply notifications until the listener fails. `PgListener` reconnects by
+/// itself after a dropped connection; changes made while it was down are
+/// only picked up as their caches expire.
+async fn listen(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+) -> Result<(), String> {
+    let mut listener = PgListener::connect_with(db.get_postgres_connection_pool())
+        .await
+        .map_err(|e| format!("Failed to connect: {}", e))?;
+    listener
+        .listen(CHANNEL)
+        .await
+        .map_err(|e| format!("Failed to listen on {}: {}", CHANNEL, e))?;
+
+    loop {
+        let notification = listener
+            .recv()
+            .await
+            .map_err(|e| format!("Failed to receive notification: {}", e))?;
+
+        match serde_json::from_str::<RowChange>(notification.payload()) {
+            Ok(change) => apply(db, redis_pool, ws_server, change).await,
+            Err(e) => log::warn!(
+                "Ignoring malformed change notification {:?}: {}",
+                notification.payload(),
+                e
+            ),
+        }
+    }
+}
+
+async fn apply(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    change: RowChange,
+) {
+    log::info!(
+        "{} on {} {:?} made outside the app, refreshing caches",
+        change.op,
+        change.table,
+        change.id
+    );
+
+    let event_id = match change.table.as_str() {
+        "events" => change.id,
+        "event_options" => change.event_id,
+        "users" => {
+            if let Some(user_id) = change.id {
+                invalidate_user(redis_pool, ws_server, user_id).await;
+            }
+            return;
+        }
+        _ => return,
+    };
+    let Some(event_id) = event_id else {
+        return;
+    };
+
+    if change.table == "events" && change.op != "DELETE" {
+        if let Some(tenant_id) = change.tenant_id {
+            registry::record_event(event_id, tenant_id);
+        }
+    }
+    invalidate_event(db, redis_pool, ws_server, event_id).await;
+}
+
+async fn invalidate_event(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    event_id: i32,
+) {
+    let cache_service = CacheService::new(redis_pool.get_ref().clone());
+    for key in [
+        create_cache_key(cache_keys::EVENT_PREFIX, &event_id.to_string()),
+        create_cache_key(cache_keys::OG_CARD_PREFIX, &event_id.to_string()),
+        "events:list".to_string(),
+    ] {
+        if let Err(e) = cache_service.delete(&key).await {
+            log::warn!("Failed to invalidate {}: {}", key, e);
+        }
+    }
+    MarketStatsService::new(db.get_ref().clone(), redis_pool.get_ref().clone())
+        .invalidate(event_id)
+        .await;
+
+    WebSocketHandlers::new(db.clone(), ws_server.get_ref().clone())
+        .fetch_and_broadcast_event(event_id)
+        .await;
+    ws_server.do_send(BroadcastEventsUpdate);
+}
+
+async fn invalidate_user(
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    user_id: i32,
+) {
+    let cache_service = CacheService::new(redis_pool.get_ref().clone());
+    for key in [
+        create_cache_key(cache_keys::USER_PREFIX, &user_id.to_string()),
+        format!("portfolio:{}", user_id),
+    ] {
+        if let Err(e) = cache_service.delete(&key).await {
+            log::warn!("Failed to invalidate {}: {}", key, e);
+        }
+    }
+
+    ws_server.do_send(BroadcastPortfolioUpdate { user_id });
+}
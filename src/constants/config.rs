@@ -4,6 +4,14 @@ pub fn get_database_url() -> Result<String, env::VarError> {
     env::var("DATABASE_URL")
 }
 
+/// Database for exports, reports and audits, such as a read replica.
+/// Defaults to `DATABASE_URL`.
+pub fn get_reporting_database_url() -> Option<String> {
+    env::var("REPORTING_DATABASE_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+}
+
 pub fn get_server_address() -> String {
     env::var("SERVER_ADDRESS").unwrap_or_else(|_| "127.0.0.1:8080".to_string())
 }
@@ -42,3 +50,804 @@ pub fn get_price_update_interval_seconds() -> u64 {
         .parse()
         .unwrap_or(300)
 }
+
+/// Queries a single request may issue before it is logged as over budget
+pub fn get_query_budget_per_request() -> u64 {
+    env::var("QUERY_BUDGET_PER_REQUEST")
+        .unwrap_or_else(|_| "25".to_string())
+        .parse()
+        .unwrap_or(25)
+}
+
+/// Response size, before compression, above which a route is reported as over
+/// its payload budget. Some routes have their own budget; see `payload_metrics`.
+pub fn get_response_size_budget_bytes() -> u64 {
+    env::var("RESPONSE_SIZE_BUDGET_BYTES")
+        .unwrap_or_else(|_| "102400".to_string())
+        .parse()
+        .unwrap_or(102400)
+}
+
+pub fn get_slow_request_db_time_ms() -> u64 {
+    env::var("SLOW_REQUEST_DB_TIME_MS")
+        .unwrap_or_else(|_| "250".to_string())
+        .parse()
+        .unwrap_or(250)
+}
+
+pub fn get_slow_query_threshold_ms() -> u64 {
+    env::var("SLOW_QUERY_THRESHOLD_MS")
+        .unwrap_or_else(|_| "100".to_string())
+        .parse()
+        .unwrap_or(100)
+}
+
+/// Percentage move from the rolling reference price that halts trading in an option
+pub fn get_circuit_breaker_threshold_percent() -> u64 {
+    env::var("CIRCUIT_BREAKER_THRESHOLD_PERCENT")
+        .unwrap_or_else(|_| "20".to_string())
+        .parse()
+        .unwrap_or(20)
+}
+
+/// Length of the window after which the reference price rolls forward to the last trade
+pub fn get_circuit_breaker_window_seconds() -> i64 {
+    env::var("CIRCUIT_BREAKER_WINDOW_SECONDS")
+        .unwrap_or_else(|_| "300".to_string())
+        .parse()
+        .unwrap_or(300)
+}
+
+/// How long an automatic halt lasts before matching resumes
+pub fn get_circuit_breaker_halt_seconds() -> i64 {
+    env::var("CIRCUIT_BREAKER_HALT_SECONDS")
+        .unwrap_or_else(|_| "300".to_string())
+        .parse()
+        .unwrap_or(300)
+}
+
+/// How long a position read stays cached for sell validation
+pub fn get_position_cache_ttl_ms() -> u64 {
+    env::var("POSITION_CACHE_TTL_MS")
+        .unwrap_or_else(|_| "2000".to_string())
+        .parse()
+        .unwrap_or(2000)
+}
+
+/// Length of an auction started without an explicit duration
+pub fn get_auction_default_duration_seconds() -> i64 {
+    env::var("AUCTION_DEFAULT_DURATION_SECONDS")
+        .unwrap_or_else(|_| "300".to_string())
+        .parse()
+        .unwrap_or(300)
+}
+
+/// How often the scheduler checks for auctions that are due to uncross
+pub fn get_auction_scheduler_interval_seconds() -> u64 {
+    env::var("AUCTION_SCHEDULER_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5)
+}
+
+/// Number of trade or order exports a user may start per hour
+pub fn get_export_rate_limit_per_hour() -> u64 {
+    env::var("EXPORT_RATE_LIMIT_PER_HOUR")
+        .unwrap_or_else(|_| "10".to_string())
+        .parse()
+        .unwrap_or(10)
+}
+
+/// Number of worker actors that channel broadcasts are sharded across
+pub fn get_ws_broadcast_workers() -> usize {
+    env::var("WS_BROADCAST_WORKERS")
+        .unwrap_or_else(|_| "4".to_string())
+        .parse()
+        .unwrap_or(4)
+}
+
+/// How long a WebSocket resume token keeps its subscriptions after the last change
+pub fn get_ws_resume_ttl_seconds() -> u64 {
+    env::var("WS_RESUME_TTL_SECONDS")
+        .unwrap_or_else(|_| "86400".to_string())
+        .parse()
+        .unwrap_or(86400)
+}
+
+/// How often resident order books are sampled for depth heatmaps, at least
+/// once a second
+pub fn get_book_sample_interval_seconds() -> u64 {
+    env::var("BOOK_SAMPLE_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "10".to_string())
+        .parse()
+        .unwrap_or(10)
+        .max(1)
+}
+
+/// How long depth samples are kept, which also caps the heatmap window
+pub fn get_book_sample_retention_hours() -> i64 {
+    env::var("BOOK_SAMPLE_RETENTION_HOURS")
+        .unwrap_or_else(|_| "24".to_string())
+        .parse()
+        .unwrap_or(24)
+}
+
+/// Price levels per side recorded in each depth sample
+pub fn get_book_sample_depth() -> usize {
+    env::var("BOOK_SAMPLE_DEPTH")
+        .unwrap_or_else(|_| "20".to_string())
+        .parse()
+        .unwrap_or(20)
+}
+
+/// Longest an order book with journaled changes goes without a full
+/// snapshot, at least a second
+pub fn get_order_book_snapshot_interval_seconds() -> u64 {
+    env::var("ORDER_BOOK_SNAPSHOT_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30)
+        .max(1)
+}
+
+/// How long an order book goes without changes before it is dropped from
+/// memory, to be loaded from Redis again on next use
+pub fn get_order_book_idle_seconds() -> u64 {
+    env::var("ORDER_BOOK_IDLE_SECONDS")
+        .unwrap_or_else(|_| "900".to_string())
+        .parse()
+        .unwrap_or(900)
+}
+
+/// Journal entries after which an order book is snapshotted regardless of the interval
+pub fn get_order_book_snapshot_every_entries() -> usize {
+    env::var("ORDER_BOOK_SNAPSHOT_EVERY_ENTRIES")
+        .unwrap_or_else(|_| "500".to_string())
+        .parse()
+        .unwrap_or(500)
+}
+
+/// Minimum time between full order book refreshes pushed to WebSocket subscribers
+pub fn get_order_book_ws_refresh_ms() -> u64 {
+    env::var("ORDER_BOOK_WS_REFRESH_MS")
+        .unwrap_or_else(|_| "1000".to_string())
+        .parse()
+        .unwrap_or(1000)
+}
+
+/// Hour of the day (UTC) after which due email digests are sent
+pub fn get_digest_send_hour_utc() -> u32 {
+    env::var("DIGEST_SEND_HOUR_UTC")
+        .unwrap_or_else(|_| "8".to_string())
+        .parse()
+        .unwrap_or(8)
+}
+
+/// Fills listed individually in an email digest before the rest are summarized
+pub fn get_digest_max_fills() -> usize {
+    env::var("DIGEST_MAX_FILLS")
+        .unwrap_or_else(|_| "20".to_string())
+        .parse()
+        .unwrap_or(20)
+}
+
+/// Trading fee charged to both sides of a trade, in basis points of its notional
+pub fn get_trading_fee_bps() -> u32 {
+    env::var("TRADING_FEE_BPS")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .unwrap_or(0)
+}
+
+/// Hour of the day (UTC) after which user tiers are recalculated
+pub fn get_tier_recalculation_hour_utc() -> u32 {
+    env::var("TIER_RECALCULATION_HOUR_UTC")
+        .unwrap_or_else(|_| "2".to_string())
+        .parse()
+        .unwrap_or(2)
+}
+
+/// Days of trading volume counted towards a user's tier
+pub fn get_tier_volume_window_days() -> i64 {
+    env::var("TIER_VOLUME_WINDOW_DAYS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30)
+}
+
+/// Seconds per-option market statistics are cached before being recomputed
+pub fn get_market_stats_cache_seconds() -> u64 {
+    env::var("MARKET_STATS_CACHE_SECONDS")
+        .unwrap_or_else(|_| "10".to_string())
+        .parse()
+        .unwrap_or(10)
+}
+
+/// Most ticker messages sent per second for one option; changes in between are conflated
+pub fn get_ticker_max_messages_per_second() -> u64 {
+    env::var("TICKER_MAX_MESSAGES_PER_SECOND")
+        .unwrap_or_else(|_| "4".to_string())
+        .parse()
+        .unwrap_or(4)
+}
+
+/// Requests a client IP may make to the public API per minute
+pub fn get_public_api_rate_limit_per_minute() -> i64 {
+    env::var("PUBLIC_API_RATE_LIMIT_PER_MINUTE")
+        .unwrap_or_else(|_| "60".to_string())
+        .parse()
+        .unwrap_or(60)
+}
+
+/// `max-age` sent with public API responses, so CDNs and browsers can reuse them
+pub fn get_public_api_cache_max_age_seconds() -> u64 {
+    env::var("PUBLIC_API_CACHE_MAX_AGE_SECONDS")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5)
+}
+
+/// Largest short position a margin account may hold in one option, in shares
+pub fn get_max_short_position() -> i32 {
+    env::var("MAX_SHORT_POSITION")
+        .unwrap_or_else(|_| "1000".to_string())
+        .parse()
+        .unwrap_or(1000)
+}
+
+/// How often margin accounts are checked against the maintenance requirement
+pub fn get_margin_check_interval_seconds() -> u64 {
+    env::var("MARGIN_CHECK_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30)
+}
+
+/// Equity a margin account must keep, as a percentage of the collateral its
+/// short positions would need at current prices
+pub fn get_margin_maintenance_percent() -> u32 {
+    env::var("MARGIN_MAINTENANCE_PERCENT")
+        .unwrap_or_else(|_| "50".to_string())
+        .parse()
+        .unwrap_or(50)
+}
+
+/// How long a margin account may stay below maintenance before it is liquidated
+pub fn get_margin_call_grace_seconds() -> i64 {
+    env::var("MARGIN_CALL_GRACE_SECONDS")
+        .unwrap_or_else(|_| "300".to_string())
+        .parse()
+        .unwrap_or(300)
+}
+
+/// Fee charged on liquidated notional, in basis points
+pub fn get_liquidation_fee_bps() -> u32 {
+    env::var("LIQUIDATION_FEE_BPS")
+        .unwrap_or_else(|_| "100".to_string())
+        .parse()
+        .unwrap_or(100)
+}
+
+/// How far above the current price a reducing order may buy, in percent
+pub fn get_liquidation_slippage_percent() -> u32 {
+    env::var("LIQUIDATION_SLIPPAGE_PERCENT")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5)
+}
+
+/// How long a response is kept for replay under its `Idempotency-Key`
+pub fn get_idempotency_key_ttl_seconds() -> u64 {
+    env::var("IDEMPOTENCY_KEY_TTL_SECONDS")
+        .unwrap_or_else(|_| "86400".to_string())
+        .parse()
+        .unwrap_or(86400)
+}
+
+/// Most orders accepted by one `POST /order-book/orders/batch` request
+pub fn get_max_batch_orders() -> usize {
+    env::var("MAX_BATCH_ORDERS")
+        .unwrap_or_else(|_| "20".to_string())
+        .parse()
+        .unwrap_or(20)
+}
+
+/// Account the AMM trades from; it needs a margin account to sell shares it
+/// doesn't hold
+pub fn get_amm_user_id() -> i32 {
+    env::var("AMM_USER_ID")
+        .unwrap_or_else(|_| "1".to_string())
+        .parse()
+        .unwrap_or(1)
+}
+
+/// LMSR liquidity parameter `b`, in shares. Larger values move the AMM's
+/// prices less per share traded.
+pub fn get_amm_liquidity() -> u32 {
+    env::var("AMM_LIQUIDITY")
+        .unwrap_or_else(|_| "100".to_string())
+        .parse()
+        .unwrap_or(100)
+}
+
+/// Account that takes the other side of fills reported by the external venue
+pub fn get_external_venue_user_id() -> i32 {
+    env::var("EXTERNAL_VENUE_USER_ID")
+        .unwrap_or_else(|_| "1".to_string())
+        .parse()
+        .unwrap_or(1)
+}
+
+/// Account that mints and redeems the pairs behind complementary fills; it
+/// needs a margin account to sell both sides of a pair it doesn't hold
+pub fn get_complement_user_id() -> i32 {
+    env::var("COMPLEMENT_USER_ID")
+        .unwrap_or_else(|_| "1".to_string())
+        .parse()
+        .unwrap_or(1)
+}
+
+/// Seconds of recent activity that per-market trade and change rates average over
+pub fn get_market_metrics_window_seconds() -> u64 {
+    env::var("MARKET_METRICS_WINDOW_SECONDS")
+        .unwrap_or_else(|_| "60".to_string())
+        .parse()
+        .unwrap_or(60)
+}
+
+/// Firehose WebSocket connections one client IP may hold open at once
+pub fn get_firehose_max_connections_per_ip() -> usize {
+    env::var("FIREHOSE_MAX_CONNECTIONS_PER_IP")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5)
+}
+
+/// Secret internal service tokens are signed with. Internal endpoints refuse
+/// every request while it is unset.
+pub fn get_internal_service_secret() -> Option<String> {
+    env::var("INTERNAL_SERVICE_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty())
+}
+
+/// Lifetime of an internal service token
+pub fn get_internal_token_ttl_seconds() -> i64 {
+    env::var("INTERNAL_TOKEN_TTL_SECONDS")
+        .unwrap_or_else(|_| "300".to_string())
+        .parse()
+        .unwrap_or(300)
+}
+
+/// Change in one price update, in percent, above which the jump is logged and
+/// sent as an ops event
+pub fn get_price_jump_alert_percent() -> u32 {
+    env::var("PRICE_JUMP_ALERT_PERCENT")
+        .unwrap_or_else(|_| "20".to_string())
+        .parse()
+        .unwrap_or(20)
+}
+
+/// Trades averaged by the `vwap` pricing strategy
+pub fn get_pricing_vwap_trades() -> u64 {
+    env::var("PRICING_VWAP_TRADES")
+        .unwrap_or_else(|_| "20".to_string())
+        .parse()
+        .unwrap_or(20)
+}
+
+/// Orders a user may place or amend in one burst
+pub fn get_order_rate_limit_burst() -> i64 {
+    env::var("ORDER_RATE_LIMIT_BURST")
+        .unwrap_or_else(|_| "20".to_string())
+        .parse()
+        .unwrap_or(20)
+}
+
+/// Rate at which a user's order budget refills, in orders per minute
+pub fn get_order_rate_limit_per_minute() -> i64 {
+    env::var("ORDER_RATE_LIMIT_PER_MINUTE")
+        .unwrap_or_else(|_| "120".to_string())
+        .parse()
+        .unwrap_or(120)
+}
+
+/// Login and registration attempts a client IP may make in one burst
+pub fn get_auth_rate_limit_burst() -> i64 {
+    env::var("AUTH_RATE_LIMIT_BURST")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5)
+}
+
+/// Rate at which a client IP's auth budget refills, in attempts per minute
+pub fn get_auth_rate_limit_per_minute() -> i64 {
+    env::var("AUTH_RATE_LIMIT_PER_MINUTE")
+        .unwrap_or_else(|_| "10".to_string())
+        .parse()
+        .unwrap_or(10)
+}
+
+/// Market data reads a client may make in one burst
+pub fn get_read_rate_limit_burst() -> i64 {
+    env::var("READ_RATE_LIMIT_BURST")
+        .unwrap_or_else(|_| "100".to_string())
+        .parse()
+        .unwrap_or(100)
+}
+
+/// Rate at which a client's read budget refills, in requests per minute
+pub fn get_read_rate_limit_per_minute() -> i64 {
+    env::var("READ_RATE_LIMIT_PER_MINUTE")
+        .unwrap_or_else(|_| "600".to_string())
+        .parse()
+        .unwrap_or(600)
+}
+
+/// Secret API key secrets are derived from. API keys are refused while it is
+/// unset, and changing it invalidates every key.
+pub fn get_api_key_signing_secret() -> Option<String> {
+    env::var("API_KEY_SIGNING_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty())
+}
+
+/// How far a signed request's timestamp may be from the server's clock
+pub fn get_api_key_signature_window_seconds() -> i64 {
+    env::var("API_KEY_SIGNATURE_WINDOW_SECONDS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30)
+}
+
+/// Signed requests per minute for a key created without its own limit
+pub fn get_api_key_default_rate_limit_per_minute() -> i32 {
+    env::var("API_KEY_DEFAULT_RATE_LIMIT_PER_MINUTE")
+        .unwrap_or_else(|_| "600".to_string())
+        .parse()
+        .unwrap_or(600)
+}
+
+/// Highest rate limit a user may give one of their keys
+pub fn get_api_key_max_rate_limit_per_minute() -> i32 {
+    env::var("API_KEY_MAX_RATE_LIMIT_PER_MINUTE")
+        .unwrap_or_else(|_| "1200".to_string())
+        .parse()
+        .unwrap_or(1200)
+}
+
+/// Active API keys one user may hold
+pub fn get_max_api_keys_per_user() -> u64 {
+    env::var("MAX_API_KEYS_PER_USER")
+        .unwrap_or_else(|_| "10".to_string())
+        .parse()
+        .unwrap_or(10)
+}
+
+/// Lifetime of an access token; clients renew it with their refresh token
+pub fn get_access_token_ttl_minutes() -> i64 {
+    env::var("ACCESS_TOKEN_TTL_MINUTES")
+        .unwrap_or_else(|_| "15".to_string())
+        .parse()
+        .unwrap_or(15)
+}
+
+/// How long a login session lasts without being refreshed
+pub fn get_refresh_token_ttl_days() -> i64 {
+    env::var("REFRESH_TOKEN_TTL_DAYS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30)
+}
+
+/// Issuer shown next to the account in authenticator apps
+pub fn get_totp_issuer() -> String {
+    env::var("TOTP_ISSUER").unwrap_or_else(|_| "Exchange".to_string())
+}
+
+/// Tenant that requests naming no known tenant or domain are served as
+pub fn get_default_tenant_slug() -> String {
+    env::var("DEFAULT_TENANT_SLUG").unwrap_or_else(|_| "default".to_string())
+}
+
+/// How often each instance reloads tenants and event ownership from the
+/// database, picking up changes made through other instances
+pub fn get_tenant_refresh_interval_seconds() -> u64 {
+    env::var("TENANT_REFRESH_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30)
+}
+
+/// How often each instance reloads the operator settings from the database,
+/// picking up changes made through other instances
+pub fn get_settings_refresh_interval_seconds() -> u64 {
+    env::var("SETTINGS_REFRESH_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30)
+}
+
+/// Largest identity document a user may upload, in bytes
+pub fn get_kyc_max_document_bytes() -> usize {
+    env::var("KYC_MAX_DOCUMENT_BYTES")
+        .unwrap_or_else(|_| "5242880".to_string())
+        .parse()
+        .unwrap_or(5_242_880)
+}
+
+/// Most documents a user may have uploaded but not yet had reviewed
+pub fn get_kyc_max_documents() -> u64 {
+    env::var("KYC_MAX_DOCUMENTS")
+        .unwrap_or_else(|_| "10".to_string())
+        .parse()
+        .unwrap_or(10)
+}
+
+/// Total a user may deposit in any one currency, in whole units, before
+/// deposits require a verified identity. Withdrawals always do.
+pub fn get_kyc_deposit_threshold() -> i64 {
+    env::var("KYC_DEPOSIT_THRESHOLD")
+        .unwrap_or_else(|_| "1000".to_string())
+        .parse()
+        .unwrap_or(1000)
+}
+
+/// Currency of wallets, events and deposits that don't name one. Balances
+/// held before wallets were kept per currency are in this currency.
+pub fn get_base_currency() -> String {
+    env::var("BASE_CURRENCY")
+        .map(|currency| currency.trim().to_ascii_uppercase())
+        .ok()
+        .filter(|currency| currency.len() == 3)
+        .unwrap_or_else(|| "USD".to_string())
+}
+
+/// Currencies wallets may hold and events may be listed in. The base
+/// currency is always among them.
+pub fn get_supported_currencies() -> Vec<String> {
+    let mut currencies: Vec<String> = env::var("SUPPORTED_CURRENCIES")
+        .unwrap_or_else(|_| "USD,INR".to_string())
+        .split(',')
+        .map(|currency| currency.trim().to_ascii_uppercase())
+        .filter(|currency| currency.len() == 3)
+        .collect();
+    let base = get_base_currency();
+    if !currencies.contains(&base) {
+        currencies.insert(0, base);
+    }
+    currencies
+}
+
+/// Payment service provider deposits are taken through, as named to the
+/// payment gateway relay
+pub fn get_payment_gateway() -> String {
+    env::var("PAYMENT_GATEWAY").unwrap_or_else(|_| "stripe".to_string())
+}
+
+/// Currency deposits through the payment gateway are charged in
+pub fn get_payment_currency() -> String {
+    env::var("PAYMENT_CURRENCY").unwrap_or_else(|_| "USD".to_string())
+}
+
+/// Secret the payment gateway signs its webhooks with. Webhooks are refused
+/// while it is unset.
+pub fn get_payment_webhook_secret() -> Option<String> {
+    env::var("PAYMENT_WEBHOOK_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty())
+}
+
+/// How far a webhook's signed timestamp may be from the server's clock
+pub fn get_payment_webhook_tolerance_seconds() -> i64 {
+    env::var("PAYMENT_WEBHOOK_TOLERANCE_SECONDS")
+        .unwrap_or_else(|_| "300".to_string())
+        .parse()
+        .unwrap_or(300)
+}
+
+/// Most connections the trading pool opens
+pub fn get_db_max_connections() -> u32 {
+    env::var("DB_MAX_CONNECTIONS")
+        .unwrap_or_else(|_| "20".to_string())
+        .parse()
+        .unwrap_or(20)
+}
+
+/// Connections the trading pool keeps open even when idle
+pub fn get_db_min_connections() -> u32 {
+    env::var("DB_MIN_CONNECTIONS")
+        .unwrap_or_else(|_| "2".to_string())
+        .parse()
+        .unwrap_or(2)
+}
+
+/// How long a trading query waits for a free connection before failing
+pub fn get_db_acquire_timeout_seconds() -> u64 {
+    env::var("DB_ACQUIRE_TIMEOUT_SECONDS")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5)
+}
+
+/// How long a trading pool connection may sit idle before it is closed
+pub fn get_db_idle_timeout_seconds() -> u64 {
+    env::var("DB_IDLE_TIMEOUT_SECONDS")
+        .unwrap_or_else(|_| "300".to_string())
+        .parse()
+        .unwrap_or(300)
+}
+
+/// Longest a single statement on the trading pool may run before Postgres
+/// cancels it, in milliseconds. 0 turns the limit off.
+pub fn get_db_statement_timeout_ms() -> u64 {
+    env::var("DB_STATEMENT_TIMEOUT_MS")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .unwrap_or(0)
+}
+
+/// Most connections the reporting pool opens
+pub fn get_reporting_db_max_connections() -> u32 {
+    env::var("REPORTING_DB_MAX_CONNECTIONS")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5)
+}
+
+/// Connections the reporting pool keeps open even when idle
+pub fn get_reporting_db_min_connections() -> u32 {
+    env::var("REPORTING_DB_MIN_CONNECTIONS")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .unwrap_or(0)
+}
+
+/// How long a reporting query waits for a free connection before failing
+pub fn get_reporting_db_acquire_timeout_seconds() -> u64 {
+    env::var("REPORTING_DB_ACQUIRE_TIMEOUT_SECONDS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30)
+}
+
+/// How long a reporting pool connection may sit idle before it is closed
+pub fn get_reporting_db_idle_timeout_seconds() -> u64 {
+    env::var("REPORTING_DB_IDLE_TIMEOUT_SECONDS")
+        .unwrap_or_else(|_| "60".to_string())
+        .parse()
+        .unwrap_or(60)
+}
+
+/// Longest a single statement on the reporting pool may run before Postgres
+/// cancels it, in milliseconds. 0 turns the limit off.
+pub fn get_reporting_db_statement_timeout_ms() -> u64 {
+    env::var("REPORTING_DB_STATEMENT_TIMEOUT_MS")
+        .unwrap_or_else(|_| "60000".to_string())
+        .parse()
+        .unwrap_or(60_000)
+}
+
+/// How long opening a database connection may take, for both pools
+pub fn get_db_connect_timeout_seconds() -> u64 {
+    env::var("DB_CONNECT_TIMEOUT_SECONDS")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5)
+}
+
+/// Age at which connections of both pools are replaced, so server-side
+/// memory doesn't build up on long-lived connections
+pub fn get_db_max_lifetime_seconds() -> u64 {
+    env::var("DB_MAX_LIFETIME_SECONDS")
+        .unwrap_or_else(|_| "1800".to_string())
+        .parse()
+        .unwrap_or(1800)
+}
+
+/// How often trades left unsettled are retried
+pub fn get_settlement_retry_interval_seconds() -> u64 {
+    env::var("SETTLEMENT_RETRY_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30)
+}
+
+/// How long a trade may stay pending before the retry job takes it over from
+/// the request still settling it
+pub fn get_settlement_retry_grace_seconds() -> i64 {
+    env::var("SETTLEMENT_RETRY_GRACE_SECONDS")
+        .unwrap_or_else(|_| "60".to_string())
+        .parse()
+        .unwrap_or(60)
+}
+
+/// Attempts after which the retry job leaves a trade for an admin
+pub fn get_settlement_retry_max_attempts() -> i32 {
+    env::var("SETTLEMENT_RETRY_MAX_ATTEMPTS")
+        .unwrap_or_else(|_| "10".to_string())
+        .parse()
+        .unwrap_or(10)
+}
+
+/// How often events past their end time are closed and their orders cancelled
+pub fn get_event_lifecycle_interval_seconds() -> u64 {
+    env::var("EVENT_LIFECYCLE_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30)
+}
+
+/// Puts the API in maintenance from startup, whatever is scheduled in Redis
+pub fn get_maintenance_mode() -> bool {
+    env::var("MAINTENANCE_MODE")
+        .unwrap_or_else(|_| "false".to_string())
+        .parse()
+        .unwrap_or(false)
+}
+
+/// Expected end of the maintenance `MAINTENANCE_MODE` turns on, in RFC 3339
+pub fn get_maintenance_ends_at() -> Option<chrono::DateTime<chrono::Utc>> {
+    env::var("MAINTENANCE_ENDS_AT")
+        .ok()
+        .and_then(|ends_at| chrono::DateTime::parse_from_rfc3339(&ends_at).ok())
+        .map(|ends_at| ends_at.with_timezone(&chrono::Utc))
+}
+
+/// How often the scheduled maintenance window is reloaded from Redis
+pub fn get_maintenance_refresh_interval_seconds() -> u64 {
+    env::var("MAINTENANCE_REFRESH_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5)
+}
+
+/// How often oracle feeds of ended events are requested and due oracle
+/// proposals are settled
+pub fn get_oracle_interval_seconds() -> u64 {
+    env::var("ORACLE_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "60".to_string())
+        .parse()
+        .unwrap_or(60)
+}
+
+/// How often the market maker checks its seeded markets and re-quotes them
+pub fn get_market_maker_requote_interval_seconds() -> u64 {
+    env::var("MARKET_MAKER_REQUOTE_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5)
+}
+
+/// Longest the market maker waits between checks of a market while other
+/// traders quote it tighter than it would
+pub fn get_market_maker_max_backoff_seconds() -> u64 {
+    env::var("MARKET_MAKER_MAX_BACKOFF_SECONDS")
+        .unwrap_or_else(|_| "60".to_string())
+        .parse()
+        .unwrap_or(60)
+}
+
+/// How often the market maker P&L and inventory report is refreshed
+pub fn get_market_maker_report_interval_seconds() -> u64 {
+    env::var("MARKET_MAKER_REPORT_INTERVAL_SECONDS")
+        .unwrap_or_else(|_| "300".to_string())
+        .parse()
+        .unwrap_or(300)
+}
+
+/// Most records a single legacy import may contain
+pub fn get_import_max_rows() -> usize {
+    env::var("IMPORT_MAX_ROWS")
+        .unwrap_or_else(|_| "5000".to_string())
+        .parse()
+        .unwrap_or(5000)
+}
+
+/// Largest legacy import body accepted, in bytes
+pub fn get_import_max_bytes() -> usize {
+    env::var("IMPORT_MAX_BYTES")
+        .unwrap_or_else(|_| "10485760".to_string())
+        .parse()
+        .unwrap_or(10_485_760)
+}
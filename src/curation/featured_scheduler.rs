@@ -0,0 +1,68 @@
+use crate::websocket::server::{BroadcastEventsUpdate, WebSocketServer};
+use actix::Addr;
+use actix_web::web;
+use chrono::Utc;
+use entity::events;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+};
+
+/// How often feature windows are checked for starting or ending
+const FEATURED_CHECK_INTERVAL_SECONDS: u64 = 30;
+
+/// Start a background task that refreshes events subscribers whenever a
+/// scheduled feature window opens or closes, so the homepage follows the
+/// schedule without anyone touching the event
+pub fn start_featured_scheduler(
+    db: web::Data<DatabaseConnection>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+) {
+    log::info!("Starting featured events scheduler");
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            FEATURED_CHECK_INTERVAL_SECONDS,
+        ));
+        let mut last_check = Utc::now().naive_utc();
+
+        loop {
+            interval.tick().await;
+
+            let now = Utc::now().naive_utc();
+            let boundaries = events::Entity::find()
+                .filter(events::Column::FeaturedRank.is_not_null())
+                .filter(
+                    Condition::any()
+                        .add(
+                            Condition::all()
+                                .add(events::Column::FeaturedFrom.gt(last_check))
+                                .add(events::Column::FeaturedFrom.lte(now)),
+                        )
+                        .add(
+                            Condition::all()
+                                .add(events::Column::FeaturedUntil.gt(last_check))
+                                .add(events::Column::FeaturedUntil.lte(now)),
+                        ),
+                )
+                .count(db.get_ref())
+                .await;
+
+            match boundaries {
+                Ok(0) => {}
+                Ok(count) => {
+                    log::info!(
+                        "{} feature windows opened or closed, refreshing events",
+                        count
+                    );
+                    ws_server.do_send(BroadcastEventsUpdate);
+                }
+                Err(e) => {
+                    log::error!("Failed to check feature windows: {}", e);
+                    continue;
+                }
+            }
+
+            last_check = now;
+        }
+    });
+}
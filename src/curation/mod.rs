@@ -0,0 +1 @@
+pub mod featured_scheduler;
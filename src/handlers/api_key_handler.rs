@@ -0,0 +1,185 @@
+use crate::constants::config;
+use crate::types::api_key::{
+    ApiKeyResponse, ApiKeyScope, CreateApiKeyRequest, CreatedApiKeyResponse,
+};
+use crate::utils::api_key;
+use actix_web::{web, Error, HttpResponse, Result};
+use entity::api_keys;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, Set,
+};
+use serde_json::json;
+
+/// Longest label a key may have
+const MAX_LABEL_LENGTH: usize = 100;
+
+fn parse_user_id(user_id: &web::ReqData<String>) -> Result<i32, Error> {
+    user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))
+}
+
+/// List the user's API keys, revoked ones included, newest first
+pub async fn list_api_keys(
+    db: web::Data<DatabaseConnection>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id = parse_user_id(&user_id)?;
+
+    let keys = api_keys::Entity::find()
+        .filter(api_keys::Column::UserId.eq(user_id))
+        .order_by_desc(api_keys::Column::CreatedAt)
+        .all(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "api_keys": keys.into_iter().map(ApiKeyResponse::from).collect::<Vec<_>>()
+    })))
+}
+
+/// Create an API key. Its secret is returned once and can't be retrieved
+/// again.
+pub async fn create_api_key(
+    db: web::Data<DatabaseConnection>,
+    req: web::Json<CreateApiKeyRequest>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id = parse_user_id(&user_id)?;
+    let req = req.into_inner();
+
+    let label = req.label.trim();
+    if label.is_empty() || label.chars().count() > MAX_LABEL_LENGTH {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": format!("Label must be between 1 and {} characters", MAX_LABEL_LENGTH)
+        })));
+    }
+    if req.scopes.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "At least one scope is required"
+        })));
+    }
+    let max_rate_limit = config::get_api_key_max_rate_limit_per_minute();
+    let rate_limit = req
+        .rate_limit_per_minute
+        .unwrap_or_else(config::get_api_key_default_rate_limit_per_minute);
+    if rate_limit < 1 || rate_limit > max_rate_limit {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": format!("Rate limit must be between 1 and {} requests per minute", max_rate_limit)
+        })));
+    }
+
+    let active_keys = api_keys::Entity::find()
+        .filter(api_keys::Column::UserId.eq(user_id))
+        .filter(api_keys::Column::RevokedAt.is_null())
+        .count(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+    if active_keys >= config::get_max_api_keys_per_user() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "API key limit reached; revoke a key first"
+        })));
+    }
+
+    let key_id = api_key::generate_key_id();
+    let secret = match api_key::derive_secret(&key_id) {
+        Ok(secret) => secret,
+        Err(e) => {
+            log::warn!("Refused to create API key: {}", e);
+            return Ok(HttpResponse::ServiceUnavailable().json(json!({
+                "success": false,
+                "message": "API keys are disabled"
+            })));
+        }
+    };
+
+    let mut scopes = req.scopes;
+    scopes.sort_by_key(|scope| scope.as_str());
+    scopes.dedup();
+    let key = api_keys::ActiveModel {
+        user_id: Set(user_id),
+        key_id: Set(key_id),
+        secret_hash: Set(api_key::hash_secret(&secret)),
+        label: Set(label.to_string()),
+        scopes: Set(ApiKeyScope::join(&scopes)),
+        rate_limit_per_minute: Set(rate_limit),
+        created_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    }
+    .insert(db.get_ref())
+    .await
+    .map_err(|e| {
+        log::error!("Failed to create API key: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to create API key")
+    })?;
+
+    Ok(HttpResponse::Created().json(json!({
+        "success": true,
+        "message": "API key created. Store the secret now; it won't be shown again.",
+        "api_key": CreatedApiKeyResponse {
+            key: ApiKeyResponse::from(key),
+            secret,
+        }
+    })))
+}
+
+/// Revoke one of the user's API keys. Requests signed with it are refused
+/// from then on.
+pub async fn revoke_api_key(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<String>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id = parse_user_id(&user_id)?;
+    let key_id = path.into_inner();
+
+    let key = api_keys::Entity::find()
+        .filter(api_keys::Column::KeyId.eq(&key_id))
+        .filter(api_keys::Column::UserId.eq(user_id))
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+    let key = match key {
+        Some(key) if key.revoked_at.is_none() => key,
+        Some(_) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": "API key is already revoked"
+            })));
+        }
+        None => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "success": false,
+                "message": "API key not found"
+            })));
+        }
+    };
+
+    let mut active: api_keys::ActiveModel = key.into();
+    active.revoked_at = Set(Some(chrono::Utc::now().into()));
+    let key = active.update(db.get_ref()).await.map_err(|e| {
+        log::error!("Failed to revoke API key {}: {}", key_id, e);
+        actix_web::error::ErrorInternalServerError("Failed to revoke API key")
+    })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "API key revoked",
+        "api_key": ApiKeyResponse::from(key)
+    })))
+}
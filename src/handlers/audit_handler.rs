@@ -0,0 +1,149 @@
+use crate::order_book::db_persistence::DbPersistence;
+use crate::types::audit::{AuditLogResponse, ListAuditLogsQuery};
+use crate::types::margin::MarginLedgerEntry;
+use crate::types::order_book::{OrderResponse, UnsettledTradeResponse};
+use crate::types::transaction::TransactionResponse;
+use crate::utils::db_pool::ReportingDb;
+use crate::utils::pagination::{PaginatedResponse, PaginationInfo};
+use actix_web::{web, Error, HttpResponse, Result};
+use entity::{audit_logs, margin_ledger, trades, transaction};
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde_json::json;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+/// Privileged changes, newest first
+pub async fn list_audit_logs(
+    db: web::Data<ReportingDb>,
+    query: web::Query<ListAuditLogsQuery>,
+) -> Result<HttpResponse, Error> {
+    let db = db.connection();
+    let mut logs_query = audit_logs::Entity::find();
+
+    if let Some(actor_id) = query.actor_id {
+        logs_query = logs_query.filter(audit_logs::Column::ActorId.eq(actor_id));
+    }
+    if let Some(action) = query.action {
+        logs_query = logs_query.filter(audit_logs::Column::Action.eq(action.as_str()));
+    }
+    if let Some(target_type) = query.target_type {
+        logs_query = logs_query.filter(audit_logs::Column::TargetType.eq(target_type.as_str()));
+    }
+    if let Some(target_id) = query.target_id {
+        logs_query = logs_query.filter(audit_logs::Column::TargetId.eq(target_id));
+    }
+    if let Some(from) = query.from {
+        logs_query = logs_query.filter(audit_logs::Column::CreatedAt.gte(from.fixed_offset()));
+    }
+    if let Some(to) = query.to {
+        logs_query = logs_query.filter(audit_logs::Column::CreatedAt.lt(to.fixed_offset()));
+    }
+
+    let page = query.pagination.get_page();
+    let limit = query.pagination.get_limit();
+    let offset = query.pagination.get_offset();
+
+    let total_count = logs_query
+        .to_owned()
+        .count(db)
+        .await
+        .map_err(database_error)?;
+
+    let logs: Vec<AuditLogResponse> = logs_query
+        .order_by_desc(audit_logs::Column::Id)
+        .limit(limit)
+        .offset(offset)
+        .all(db)
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(AuditLogResponse::from)
+        .collect();
+
+    let response = PaginatedResponse::new(logs, PaginationInfo::new(page, total_count, limit));
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "audit_logs": response.data,
+        "pagination": response.pagination
+    })))
+}
+
+/// Everything recorded under one request's correlation id: the orders it
+/// placed, their trades, and the wallet and margin movements they caused
+pub async fn get_correlation_trace(
+    db: web::Data<ReportingDb>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let db = db.connection();
+    let correlation_id = path.into_inner();
+
+    let orders: Vec<OrderResponse> = DbPersistence::new(db.clone())
+        .get_correlated_orders(&correlation_id)
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(OrderResponse::from)
+        .collect();
+
+    let trades: Vec<UnsettledTradeResponse> = trades::Entity::find()
+        .filter(
+            trades::Column::BuyCorrelationId
+                .eq(correlation_id.as_str())
+                .or(trades::Column::SellCorrelationId.eq(correlation_id.as_str())),
+        )
+        .order_by_asc(trades::Column::Sequence)
+        .all(db)
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(UnsettledTradeResponse::from)
+        .collect();
+
+    let transactions: Vec<TransactionResponse> = transaction::Entity::find()
+        .filter(transaction::Column::CorrelationId.eq(correlation_id.as_str()))
+        .order_by_asc(transaction::Column::Id)
+        .all(db)
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(|t| TransactionResponse {
+            id: t.id,
+            user_id: t.user_id,
+            r#type: t.r#type,
+            currency: t.currency,
+            amount: t.amount.to_string().parse::<f64>().unwrap_or(0.0),
+            balance_before: t.balance_before.to_string().parse::<f64>().unwrap_or(0.0),
+            balance_after: t.balance_after.to_string().parse::<f64>().unwrap_or(0.0),
+            status: t.status,
+            reference_id: t.reference_id,
+            created_at: chrono::DateTime::from_naive_utc_and_offset(t.created_at, chrono::Utc),
+            rejection_reason: t.rejection_reason,
+            failure_reason: t.failure_reason,
+            gateway_reference: t.gateway_reference,
+            correlation_id: t.correlation_id,
+        })
+        .collect();
+
+    let margin_ledger: Vec<MarginLedgerEntry> = margin_ledger::Entity::find()
+        .filter(margin_ledger::Column::CorrelationId.eq(correlation_id.as_str()))
+        .order_by_asc(margin_ledger::Column::Id)
+        .all(db)
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(MarginLedgerEntry::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "correlation_id": correlation_id,
+        "orders": orders,
+        "trades": trades,
+        "transactions": transactions,
+        "margin_ledger": margin_ledger
+    })))
+}
@@ -1,18 +1,43 @@
-use crate::types::auth::{LoginRequest, RegisterRequest};
-use crate::utils::jwt::create_jwt_token;
+use crate::constants::config;
+use crate::middleware::auth::AuthSession;
+use crate::types::auth::{ChangePasswordRequest, LoginRequest, RefreshRequest, RegisterRequest};
+use crate::types::kyc::{AgeStatus, KycStatus};
+use crate::types::wallet::WalletResponse;
+use crate::utils::auth_session::{self, SessionTokens};
+use crate::utils::kyc;
+use crate::wallets::balances;
 use actix_web::{web, Error, HttpResponse, Result};
 use bcrypt::{hash, verify, DEFAULT_COST};
-use entity::users;
+use deadpool_redis::Pool;
+use entity::{tenants, users};
 use sea_orm::{
     prelude::Decimal, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
     Set,
 };
 use serde_json::json;
 
+/// Start a login session for a user, mapping failures to a 500
+async fn start_session(redis_pool: &Pool, user_id: i32) -> Result<SessionTokens, Error> {
+    auth_session::start(redis_pool, user_id).await.map_err(|e| {
+        log::error!("Session creation error: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to create authentication token")
+    })
+}
+
 pub async fn register(
     db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    tenant: web::ReqData<tenants::Model>,
     req: web::Json<RegisterRequest>,
 ) -> Result<HttpResponse, Error> {
+    if !tenant.allow_registration {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "message": "Registration is closed on this site".to_string(),
+            "token": serde_json::Value::Null,
+            "user": serde_json::Value::Null,
+        })));
+    }
+
     // Validate role
     if req.role != "user" && req.role != "admin" {
         return Ok(HttpResponse::BadRequest().json(json!({
@@ -22,6 +47,17 @@ pub async fn register(
         })));
     }
 
+    if req
+        .date_of_birth
+        .is_some_and(|date_of_birth| !kyc::is_plausible_date_of_birth(date_of_birth))
+    {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "message": "date_of_birth is not a plausible date of birth".to_string(),
+            "token": serde_json::Value::Null,
+            "user": serde_json::Value::Null,
+        })));
+    }
+
     // Check if user already exists
     let existing_user = users::Entity::find()
         .filter(users::Column::Email.eq(&req.email))
@@ -71,9 +107,18 @@ pub async fn register(
         phone: Set(req.phone.clone()),
         password_hash: Set(password_hash),
         full_name: Set(req.full_name.clone()),
-        wallet_balance: Set(Decimal::from(0)),
         is_active: Set(true),
         role: Set(req.role.clone()),
+        tenant_id: Set(tenant.id),
+        kyc_status: Set(KycStatus::Unverified.as_str().to_string()),
+        date_of_birth: Set(req.date_of_birth),
+        age_status: Set(req
+            .date_of_birth
+            .map_or(AgeStatus::Unverified, |date_of_birth| {
+                kyc::declared_age_status(date_of_birth, Some(tenant.id))
+            })
+            .as_str()
+            .to_string()),
         ..Default::default()
     };
 
@@ -82,11 +127,8 @@ pub async fn register(
         actix_web::error::ErrorInternalServerError("Failed to create user")
     })?;
 
-    // Create JWT token
-    let token = create_jwt_token(&user.id.to_string()).map_err(|e| {
-        log::error!("JWT token creation error: {}", e);
-        actix_web::error::ErrorInternalServerError("Failed to create authentication token")
-    })?;
+    // Start a session with a short-lived access token and a refresh token
+    let tokens = start_session(&redis_pool, user.id).await?;
 
     let user_response = json!({
         "id": user.id,
@@ -94,22 +136,29 @@ pub async fn register(
         "email": user.email,
         "phone": user.phone,
         "full_name": user.full_name,
-        "wallet_balance": user.wallet_balance,
+        "wallet_balance": Decimal::ZERO,
+        "wallets": [],
         "is_active": user.is_active,
         "role": user.role,
+        "kyc_status": user.kyc_status,
+        "age_status": user.age_status,
         "created_at": user.created_at,
         "updated_at": user.updated_at,
     });
 
     Ok(HttpResponse::Created().json(json!({
         "message": "User registered successfully".to_string(),
-        "token": Some(token),
+        "token": Some(tokens.token),
+        "refresh_token": Some(tokens.refresh_token),
+        "expires_in": tokens.expires_in,
         "user": Some(user_response),
     })))
 }
 
 pub async fn login(
     db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    tenant: web::ReqData<tenants::Model>,
     req: web::Json<LoginRequest>,
 ) -> Result<HttpResponse, Error> {
     // Find user by email
@@ -122,7 +171,8 @@ pub async fn login(
             actix_web::error::ErrorInternalServerError("Database error occurred")
         })?;
 
-    let user = match user {
+    // Users sign in on their own tenant's site; admins on any
+    let user = match user.filter(|u| u.tenant_id == tenant.id || u.role == "admin") {
         Some(u) => u,
         None => {
             return Ok(HttpResponse::Unauthorized().json(json!({
@@ -156,11 +206,15 @@ pub async fn login(
         })));
     }
 
-    // Create JWT token
-    let token = create_jwt_token(&user.id.to_string()).map_err(|e| {
-        log::error!("JWT token creation error: {}", e);
-        actix_web::error::ErrorInternalServerError("Failed to create authentication token")
-    })?;
+    // Start a session with a short-lived access token and a refresh token
+    let tokens = start_session(&redis_pool, user.id).await?;
+
+    let wallets = balances::for_user(db.get_ref(), user.id)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
 
     let user_response = json!({
         "id": user.id,
@@ -168,16 +222,133 @@ pub async fn login(
         "email": user.email,
         "phone": user.phone,
         "full_name": user.full_name,
-        "wallet_balance": user.wallet_balance,
+        "wallet_balance": balances::balance_in(&wallets, &config::get_base_currency()),
+        "wallets": wallets.into_iter().map(WalletResponse::from).collect::<Vec<_>>(),
         "is_active": user.is_active,
         "role": user.role,
+        "kyc_status": user.kyc_status,
+        "age_status": user.age_status,
         "created_at": user.created_at,
         "updated_at": user.updated_at,
     });
 
     Ok(HttpResponse::Ok().json(json!({
         "message": "Login successful".to_string(),
-        "token": Some(token),
+        "token": Some(tokens.token),
+        "refresh_token": Some(tokens.refresh_token),
+        "expires_in": tokens.expires_in,
         "user": Some(user_response),
     })))
 }
+
+/// Exchange a refresh token for a new access token and refresh token
+pub async fn refresh(
+    redis_pool: web::Data<Pool>,
+    req: web::Json<RefreshRequest>,
+) -> Result<HttpResponse, Error> {
+    let tokens = auth_session::refresh(&redis_pool, &req.refresh_token)
+        .await
+        .map_err(|e| {
+            log::error!("Session refresh error: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to refresh session")
+        })?;
+
+    match tokens {
+        Some(tokens) => Ok(HttpResponse::Ok().json(json!({
+            "message": "Session refreshed".to_string(),
+            "token": tokens.token,
+            "refresh_token": tokens.refresh_token,
+            "expires_in": tokens.expires_in,
+        }))),
+        None => Ok(HttpResponse::Unauthorized().json(json!({
+            "message": "Invalid or revoked refresh token".to_string(),
+            "token": serde_json::Value::Null,
+        }))),
+    }
+}
+
+/// Revoke the current session. Its access and refresh tokens stop working,
+/// and WebSocket connections opened with it are closed.
+pub async fn logout(
+    redis_pool: web::Data<Pool>,
+    user_id: web::ReqData<String>,
+    session: web::ReqData<AuthSession>,
+) -> Result<HttpResponse, Error> {
+    let user_id: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    auth_session::revoke(&redis_pool, user_id, &session.0)
+        .await
+        .map_err(|e| {
+            log::error!("Logout error: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to log out")
+        })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Logged out".to_string(),
+    })))
+}
+
+/// Change the user's password. Every existing session is revoked, and the
+/// caller gets tokens for a new one.
+pub async fn change_password(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    user_id: web::ReqData<String>,
+    req: web::Json<ChangePasswordRequest>,
+) -> Result<HttpResponse, Error> {
+    let user_id: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    let user = users::Entity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("User not found"))?;
+
+    let is_valid = verify(&req.current_password, &user.password_hash).map_err(|e| {
+        log::error!("Password verification error: {}", e);
+        actix_web::error::ErrorInternalServerError("Error verifying password")
+    })?;
+    if !is_valid {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "message": "Current password is incorrect".to_string(),
+        })));
+    }
+    if req.new_password.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "message": "New password must not be empty".to_string(),
+        })));
+    }
+
+    let password_hash = hash(&req.new_password, DEFAULT_COST).map_err(|e| {
+        log::error!("Password hashing error: {}", e);
+        actix_web::error::ErrorInternalServerError("Error processing password")
+    })?;
+    let mut active: users::ActiveModel = user.into();
+    active.password_hash = Set(password_hash);
+    active.update(db.get_ref()).await.map_err(|e| {
+        log::error!("Password update error: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to change password")
+    })?;
+
+    auth_session::revoke_all(&redis_pool, user_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to revoke sessions of user {}: {}", user_id, e);
+            actix_web::error::ErrorInternalServerError("Failed to revoke existing sessions")
+        })?;
+    let tokens = start_session(&redis_pool, user_id).await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Password changed; other sessions have been signed out".to_string(),
+        "token": tokens.token,
+        "refresh_token": tokens.refresh_token,
+        "expires_in": tokens.expires_in,
+    })))
+}
@@ -0,0 +1,122 @@
+use crate::candles::service::CandleService;
+use crate::order_book::matching_service;
+use crate::types::candle::{CandleInterval, CandleResponse, CandlesQuery};
+use actix_web::{web, Error, HttpResponse, Result};
+use deadpool_redis::Pool;
+use entity::event_options;
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde_json::json;
+
+const DEFAULT_CANDLE_LIMIT: u64 = 500;
+const MAX_CANDLE_LIMIT: u64 = 1000;
+
+/// OHLCV bars for an option, oldest first, for price charts
+pub async fn get_option_candles(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<(i32, i32)>,
+    query: web::Query<CandlesQuery>,
+) -> Result<HttpResponse, Error> {
+    let (event_id, option_id) = path.into_inner();
+
+    let interval = match query.interval.as_deref() {
+        None => CandleInterval::OneMinute,
+        Some(value) => match CandleInterval::parse(value) {
+            Some(interval) => interval,
+            None => {
+                return Ok(HttpResponse::BadRequest().json(json!({
+                    "message": "interval must be one of 1m, 5m, 1h, 1d",
+                    "candles": serde_json::Value::Null,
+                })))
+            }
+        },
+    };
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "message": "from must be before to",
+                "candles": serde_json::Value::Null,
+            })));
+        }
+    }
+
+    let option = event_options::Entity::find_by_id(option_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+    if option.is_none_or(|o| o.event_id != event_id) {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "message": "Event option not found",
+            "candles": serde_json::Value::Null,
+        })));
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_CANDLE_LIMIT)
+        .clamp(1, MAX_CANDLE_LIMIT);
+    let candles = CandleService::new(db.get_ref().clone())
+        .list(event_id, option_id, interval, query.from, query.to, limit)
+        .await
+        .map_err(|e| {
+            log::error!("{}", e);
+            actix_web::error::ErrorInternalServerError("Failed to load candles")
+        })?;
+
+    let candles: Vec<CandleResponse> = candles.into_iter().map(Into::into).collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Candles retrieved successfully",
+        "interval": interval.as_str(),
+        "candles": candles,
+    })))
+}
+
+/// Rolling 24h volume, range and price change for an option, with its open
+/// order counts
+pub async fn get_option_market_stats(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, Error> {
+    let (event_id, option_id) = path.into_inner();
+
+    let option = event_options::Entity::find_by_id(option_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+    if option.is_none_or(|o| o.event_id != event_id) {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "message": "Event option not found",
+            "stats": serde_json::Value::Null,
+        })));
+    }
+
+    let (last, open_orders) =
+        matching_service::query(redis_pool.get_ref(), event_id, option_id, |book| {
+            (book.last_trade_price(), book.open_order_counts())
+        })
+        .await
+        .map_err(|e| {
+            log::error!("Failed to reach order book: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to load order book")
+        })?;
+
+    let stats = CandleService::new(db.get_ref().clone())
+        .summary_24h(event_id, option_id, last, open_orders)
+        .await
+        .map_err(|e| {
+            log::error!("{}", e);
+            actix_web::error::ErrorInternalServerError("Failed to compute market stats")
+        })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Stats retrieved successfully",
+        "stats": stats,
+    })))
+}
@@ -0,0 +1,95 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::types::consent::AcceptPolicyRequest;
+use crate::utils::audit::client_ip;
+use crate::utils::auth::get_user_id;
+use crate::utils::consent;
+use actix_web::{web, Error, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use entity::{tenants, user_consents};
+use sea_orm::{sea_query::OnConflict, DatabaseConnection, EntityTrait, Set};
+use serde_json::json;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+/// The version of each policy in force and the one the caller accepted
+pub async fn get_my_consents(
+    db: web::Data<DatabaseConnection>,
+    tenant: web::ReqData<tenants::Model>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+
+    let consents = consent::statuses(db.get_ref(), user_id, Some(tenant.id))
+        .await
+        .map_err(database_error)?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "consents": consents
+    })))
+}
+
+/// Accept the current version of a policy. Accepting it again is harmless.
+pub async fn accept_policy(
+    db: web::Data<DatabaseConnection>,
+    tenant: web::ReqData<tenants::Model>,
+    req: web::Json<AcceptPolicyRequest>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+
+    let current_version = consent::current_version(Some(tenant.id), req.document);
+    if req.version != current_version {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": format!(
+                "Version {} of the {} is not the current one",
+                req.version,
+                req.document.as_str().replace('_', " ")
+            ),
+            "current_version": current_version
+        })));
+    }
+
+    user_consents::Entity::insert(user_consents::ActiveModel {
+        user_id: Set(user_id),
+        document: Set(req.document.as_str().to_string()),
+        version: Set(req.version),
+        ip: Set(client_ip(&http_req)),
+        accepted_at: Set(Utc::now().fixed_offset()),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([
+            user_consents::Column::UserId,
+            user_consents::Column::Document,
+            user_consents::Column::Version,
+        ])
+        .do_nothing()
+        .to_owned(),
+    )
+    .exec_without_returning(db.get_ref())
+    .await
+    .map_err(database_error)?;
+
+    log::info!(
+        "User {} accepted version {} of the {}",
+        user_id,
+        req.version,
+        req.document.as_str()
+    );
+
+    let consents = consent::statuses(db.get_ref(), user_id, Some(tenant.id))
+        .await
+        .map_err(database_error)?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Policy accepted",
+        "consents": consents
+    })))
+}
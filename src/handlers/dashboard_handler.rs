@@ -0,0 +1,248 @@
+use crate::order_book::types::OrderStatus;
+use crate::types::dashboard::{DashboardPosition, DashboardResponse, WalletSummary, WatchedEvent};
+use crate::types::transaction::TransactionResponse;
+use crate::wallets::balances;
+use actix_web::{web, Error, HttpResponse, Result};
+use entity::{event_options, events, orders, transaction, user_positions, users};
+use sea_orm::{
+    prelude::Decimal, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect,
+};
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Number of positions and transactions included in the dashboard
+const DASHBOARD_ITEM_LIMIT: u64 = 5;
+
+pub async fn get_dashboard(
+    db: web::Data<DatabaseConnection>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id_str = &*user_id;
+    let user_id_int: i32 = user_id_str
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    let db = db.get_ref();
+
+    // Assemble all sections concurrently
+    let (user, wallets, positions, open_orders_count, recent_transactions, watched_events) = tokio::join!(
+        users::Entity::find_by_id(user_id_int).one(db),
+        balances::for_user(db, user_id_int),
+        fetch_positions(db, user_id_int),
+        count_open_orders(db, user_id_int),
+        fetch_recent_transactions(db, user_id_int),
+        fetch_watched_events(db, user_id_int),
+    );
+
+    user.map_err(dashboard_error)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("User not found"))?;
+    let wallets = wallets.map_err(dashboard_error)?;
+    let mut positions = positions.map_err(dashboard_error)?;
+    let open_orders_count = open_orders_count.map_err(dashboard_error)?;
+    let recent_transactions = recent_transactions.map_err(dashboard_error)?;
+    let watched_events = watched_events.map_err(dashboard_error)?;
+
+    // Balances and positions only add up within a currency
+    let mut currencies: Vec<String> = wallets.iter().map(|w| w.currency.clone()).collect();
+    let position_currencies: BTreeSet<&String> = positions.iter().map(|p| &p.currency).collect();
+    for currency in position_currencies {
+        if !currencies.contains(currency) {
+            currencies.push(currency.clone());
+        }
+    }
+    let wallet_summaries = currencies
+        .into_iter()
+        .map(|currency| {
+            let wallet_balance = balances::balance_in(&wallets, &currency);
+            let positions_value: Decimal = positions
+                .iter()
+                .filter(|p| p.currency == currency)
+                .map(|p| p.position_value)
+                .sum();
+            WalletSummary {
+                currency,
+                wallet_balance,
+                positions_value,
+                total_equity: wallet_balance + positions_value,
+            }
+        })
+        .collect();
+
+    // Largest positions by current value first
+    positions.sort_by_key(|p| std::cmp::Reverse(p.position_value));
+    positions.truncate(DASHBOARD_ITEM_LIMIT as usize);
+
+    let dashboard = DashboardResponse {
+        wallets: wallet_summaries,
+        top_positions: positions,
+        open_orders_count,
+        recent_transactions,
+        watched_events,
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "dashboard": dashboard
+    })))
+}
+
+fn dashboard_error(e: DbErr) -> Error {
+    log::error!("Failed to build dashboard: {}", e);
+    actix_web::error::ErrorInternalServerError("Failed to retrieve dashboard")
+}
+
+/// Load all open positions valued at the current option prices
+async fn fetch_positions(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Vec<DashboardPosition>, DbErr> {
+    let positions = user_positions::Entity::find()
+        .filter(user_positions::Column::UserId.eq(user_id))
+        .filter(user_positions::Column::Quantity.gt(0))
+        .all(db)
+        .await?;
+
+    if positions.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let event_ids: Vec<i32> = positions.iter().map(|p| p.event_id).collect();
+    let currencies: HashMap<i32, String> = events::Entity::find()
+        .select_only()
+        .column(events::Column::Id)
+        .column(events::Column::Currency)
+        .filter(events::Column::Id.is_in(event_ids))
+        .into_tuple::<(i32, String)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+
+    let option_ids: Vec<i32> = positions.iter().map(|p| p.option_id).collect();
+    let options: HashMap<i32, event_options::Model> = event_options::Entity::find()
+        .filter(event_options::Column::Id.is_in(option_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|o| (o.id, o))
+        .collect();
+
+    Ok(positions
+        .into_iter()
+        .filter_map(|position| {
+            let option = options.get(&position.option_id)?;
+            let currency = currencies.get(&position.event_id)?;
+            let quantity = Decimal::from(position.quantity);
+            let position_value = option.current_price * quantity;
+            Some(DashboardPosition {
+                event_id: position.event_id,
+                option_id: position.option_id,
+                option_text: option.option_text.clone(),
+                currency: currency.clone(),
+                quantity: position.quantity,
+                avg_price: position.average_price,
+                current_price: option.current_price,
+                position_value,
+                pnl: position_value - position.average_price * quantity,
+            })
+        })
+        .collect())
+}
+
+async fn count_open_orders(db: &DatabaseConnection, user_id: i32) -> Result<u64, DbErr> {
+    orders::Entity::find()
+        .filter(orders::Column::UserId.eq(user_id))
+        .filter(orders::Column::Status.is_in([
+            OrderStatus::Pending.to_string(),
+            OrderStatus::PartiallyFilled.to_string(),
+        ]))
+        .count(db)
+        .await
+}
+
+async fn fetch_recent_transactions(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Vec<TransactionResponse>, DbErr> {
+    let transactions = transaction::Entity::find()
+        .filter(transaction::Column::UserId.eq(user_id))
+        .order_by_desc(transaction::Column::CreatedAt)
+        .limit(DASHBOARD_ITEM_LIMIT)
+        .all(db)
+        .await?;
+
+    Ok(transactions
+        .into_iter()
+        .map(|t| TransactionResponse {
+            id: t.id,
+            user_id: t.user_id,
+            r#type: t.r#type,
+            currency: t.currency,
+            amount: t.amount.to_string().parse::<f64>().unwrap_or(0.0),
+            balance_before: t.balance_before.to_string().parse::<f64>().unwrap_or(0.0),
+            balance_after: t.balance_after.to_string().parse::<f64>().unwrap_or(0.0),
+            status: t.status,
+            reference_id: t.reference_id,
+            created_at: chrono::DateTime::from_naive_utc_and_offset(t.created_at, chrono::Utc),
+            rejection_reason: t.rejection_reason,
+            failure_reason: t.failure_reason,
+            gateway_reference: t.gateway_reference,
+            correlation_id: t.correlation_id,
+        })
+        .collect())
+}
+
+/// Events the user holds a position in or has open orders on
+async fn fetch_watched_events(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Vec<WatchedEvent>, DbErr> {
+    let position_event_ids: Vec<i32> = user_positions::Entity::find()
+        .select_only()
+        .column(user_positions::Column::EventId)
+        .filter(user_positions::Column::UserId.eq(user_id))
+        .filter(user_positions::Column::Quantity.gt(0))
+        .distinct()
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let order_event_ids: Vec<i32> = orders::Entity::find()
+        .select_only()
+        .column(orders::Column::EventId)
+        .filter(orders::Column::UserId.eq(user_id))
+        .filter(orders::Column::Status.is_in([
+            OrderStatus::Pending.to_string(),
+            OrderStatus::PartiallyFilled.to_string(),
+        ]))
+        .distinct()
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let event_ids: HashSet<i32> = position_event_ids
+        .into_iter()
+        .chain(order_event_ids)
+        .collect();
+
+    if event_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let events = events::Entity::find()
+        .filter(events::Column::Id.is_in(event_ids))
+        .order_by_asc(events::Column::EndTime)
+        .all(db)
+        .await?;
+
+    Ok(events
+        .into_iter()
+        .map(|e| WatchedEvent {
+            id: e.id,
+            title: e.title,
+            status: e.status,
+            end_time: e.end_time,
+        })
+        .collect())
+}
@@ -1,32 +1,45 @@
 use crate::middleware::auth::AuthenticatedUser;
-use crate::types::event::{CreateEventRequest, EventResponse, ListEventsQuery, UpdateEventRequest};
-use crate::utils::auth::{check_admin_role, get_user_id};
-use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
+use crate::order_book::limits::validate_limit_config;
+use crate::order_book::market_stats::MarketStatsService;
+use crate::order_book::types::OrderStatus;
+use crate::settings::registry as settings;
+use crate::tenants::registry;
+use crate::types::audit::AuditAction;
+use crate::types::event::{
+    featured_condition, CreateEventRequest, EventResponse, FeatureEventRequest, ListEventsQuery,
+    OgCardFormat, OgCardOption, OgCardQuery, OgCardResponse, UpdateEventRequest,
+};
+use crate::types::settings::SettingKey;
+use crate::utils::audit::{self, AuditEntry};
+use crate::utils::auth::get_user_id;
+use crate::utils::cache::{cache_keys, create_cache_key, tenant_cache_key, CacheService};
+use crate::utils::fields;
+use crate::utils::og_card;
 use crate::utils::pagination::{PaginatedResponse, PaginationInfo};
+use crate::wallets::balances;
 use crate::websocket::server::WebSocketServer;
 use actix::Addr;
-use actix_web::{web, Error, HttpResponse, Result};
+use actix_web::{web, Error, HttpRequest, HttpResponse, Result};
 use chrono::Utc;
 use deadpool_redis::Pool;
-use entity::{event_options, events};
+use entity::{event_options, events, orders, tenants};
 use sea_orm::{
     prelude::Decimal, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
-    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
 };
 use serde_json::json;
 
+/// How long a share card is cached, both in Redis and by link preview crawlers
+const OG_CARD_CACHE_SECONDS: u64 = 60;
+
 pub async fn create_event(
     db: web::Data<DatabaseConnection>,
     redis_pool: web::Data<Pool>,
     ws_server: web::Data<Addr<WebSocketServer>>,
+    tenant: web::ReqData<tenants::Model>,
     req: web::Json<CreateEventRequest>,
     auth_user: web::ReqData<AuthenticatedUser>,
 ) -> Result<HttpResponse, Error> {
-    // Check if user is admin
-    if let Err(response) = check_admin_role(&auth_user) {
-        return Ok(response);
-    }
-
     let creator_id = get_user_id(&auth_user)?;
 
     log::info!("Creating event for admin user: {}", creator_id);
@@ -40,6 +53,29 @@ pub async fn create_event(
         })));
     }
 
+    let currency = match balances::resolve_currency(req.currency.as_deref()) {
+        Ok(currency) => currency,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "message": message,
+                "event": serde_json::Value::Null,
+            })))
+        }
+    };
+
+    let min_price = req.min_price.unwrap_or_else(|| Decimal::new(1, 2)); // 0.01
+    let max_price = req.max_price.unwrap_or_else(|| Decimal::new(9999, 2)); // 99.99
+    let tick_size = req.tick_size.unwrap_or_else(|| Decimal::new(1, 2)); // 0.01
+    let max_order_quantity = req.max_order_quantity.unwrap_or(10000);
+
+    if let Err(message) = validate_limit_config(min_price, max_price, tick_size, max_order_quantity)
+    {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "message": message,
+            "event": serde_json::Value::Null,
+        })));
+    }
+
     let new_event = events::ActiveModel {
         title: Set(req.title.clone()),
         description: Set(req.description.clone().unwrap_or_default()),
@@ -49,10 +85,12 @@ pub async fn create_event(
             .unwrap_or_else(|| "general".to_string())),
         status: Set("draft".to_string()),
         end_time: Set(req.end_time.naive_utc()),
-        min_bet_amount: Set(req.min_bet_amount.unwrap_or_else(|| Decimal::new(1000, 2))), // 10.00
-        max_bet_amount: Set(req
-            .max_bet_amount
-            .unwrap_or_else(|| Decimal::new(100000, 2))), // 1000.00
+        min_bet_amount: Set(req.min_bet_amount.unwrap_or_else(|| {
+            settings::decimal(Some(tenant.id), SettingKey::DefaultMinBetAmount)
+        })),
+        max_bet_amount: Set(req.max_bet_amount.unwrap_or_else(|| {
+            settings::decimal(Some(tenant.id), SettingKey::DefaultMaxBetAmount)
+        })),
         total_volume: Set(Decimal::new(0, 2)),
         image_url: Set(req.image_url.clone().unwrap_or_default()),
         created_by: Set(creator_id),
@@ -60,6 +98,19 @@ pub async fn create_event(
         winning_option_id: Set(0),    // Using 0 as default for nullable int fields
         resolution_note: Set("".to_string()),
         resolved_at: Set(chrono::Utc::now().naive_utc()), // Using current time as default, will be properly set when resolved
+        min_price: Set(min_price),
+        max_price: Set(max_price),
+        tick_size: Set(tick_size),
+        max_order_quantity: Set(max_order_quantity),
+        execution_venue: Set(req.execution_venue.unwrap_or_default().as_str().to_string()),
+        pricing_strategy: Set(req
+            .pricing_strategy
+            .unwrap_or_default()
+            .as_str()
+            .to_string()),
+        complement_pricing: Set(req.complement_pricing.unwrap_or(false)),
+        currency: Set(currency),
+        tenant_id: Set(tenant.id),
         ..Default::default()
     };
 
@@ -67,6 +118,7 @@ pub async fn create_event(
         log::error!("Event creation error: {}", e);
         actix_web::error::ErrorInternalServerError("Failed to create event")
     })?;
+    registry::record_event(event.id, event.tenant_id);
 
     // Fetch options for the event (will be empty for new events)
     let options = event_options::Entity::find()
@@ -114,12 +166,10 @@ pub async fn update_event(
     ws_server: web::Data<Addr<WebSocketServer>>,
     event_id: web::Path<i32>,
     req: web::Json<UpdateEventRequest>,
+    http_req: HttpRequest,
     auth_user: web::ReqData<AuthenticatedUser>,
 ) -> Result<HttpResponse, Error> {
-    // Check if user is admin
-    if let Err(response) = check_admin_role(&auth_user) {
-        return Ok(response);
-    }
+    let admin_id = get_user_id(&auth_user)?;
 
     // Find the event
     let event = events::Entity::find_by_id(*event_id)
@@ -160,6 +210,43 @@ pub async fn update_event(
         }
     }
 
+    if let Err(message) = validate_limit_config(
+        req.min_price.unwrap_or(event.min_price),
+        req.max_price.unwrap_or(event.max_price),
+        req.tick_size.unwrap_or(event.tick_size),
+        req.max_order_quantity.unwrap_or(event.max_order_quantity),
+    ) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "message": message,
+            "event": serde_json::Value::Null,
+        })));
+    }
+
+    // Orders resting on one venue can't be moved to another
+    if let Some(execution_venue) = &req.execution_venue {
+        if execution_venue.as_str() != event.execution_venue {
+            let open_orders = orders::Entity::find()
+                .filter(orders::Column::EventId.eq(event.id))
+                .filter(orders::Column::Status.is_in([
+                    OrderStatus::Pending.to_string(),
+                    OrderStatus::PartiallyFilled.to_string(),
+                ]))
+                .count(db.get_ref())
+                .await
+                .map_err(|e| {
+                    log::error!("Database error: {}", e);
+                    actix_web::error::ErrorInternalServerError("Database error occurred")
+                })?;
+            if open_orders > 0 {
+                return Ok(HttpResponse::BadRequest().json(json!({
+                    "message": "Cannot change the execution venue while the event has open orders",
+                    "event": serde_json::Value::Null,
+                })));
+            }
+        }
+    }
+
+    let before = audit::event_snapshot(&event);
     let mut active_event: events::ActiveModel = event.into();
 
     // Update fields if provided
@@ -203,12 +290,60 @@ pub async fn update_event(
     if let Some(image_url) = &req.image_url {
         active_event.image_url = Set(image_url.clone());
     }
+    if let Some(min_price) = &req.min_price {
+        active_event.min_price = Set(*min_price);
+    }
+    if let Some(max_price) = &req.max_price {
+        active_event.max_price = Set(*max_price);
+    }
+    if let Some(tick_size) = &req.tick_size {
+        active_event.tick_size = Set(*tick_size);
+    }
+    if let Some(max_order_quantity) = &req.max_order_quantity {
+        active_event.max_order_quantity = Set(*max_order_quantity);
+    }
+    if let Some(execution_venue) = &req.execution_venue {
+        active_event.execution_venue = Set(execution_venue.as_str().to_string());
+    }
+    if let Some(pricing_strategy) = &req.pricing_strategy {
+        active_event.pricing_strategy = Set(pricing_strategy.as_str().to_string());
+    }
+    if let Some(complement_pricing) = req.complement_pricing {
+        active_event.complement_pricing = Set(complement_pricing);
+    }
 
-    let updated_event = active_event.update(db.get_ref()).await.map_err(|e| {
+    let txn = db.begin().await.map_err(|e| {
+        log::error!("Failed to start transaction: {}", e);
+        actix_web::error::ErrorInternalServerError("Database transaction failed")
+    })?;
+
+    let updated_event = active_event.update(&txn).await.map_err(|e| {
         log::error!("Event update error: {}", e);
         actix_web::error::ErrorInternalServerError("Failed to update event")
     })?;
 
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::EventUpdate,
+            target_id: updated_event.id,
+            before: Some(before),
+            after: Some(audit::event_snapshot(&updated_event)),
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(|e| {
+        log::error!("{}", e);
+        actix_web::error::ErrorInternalServerError("Failed to update event")
+    })?;
+
+    txn.commit().await.map_err(|e| {
+        log::error!("Failed to commit transaction: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to commit transaction")
+    })?;
+
     // Fetch options for the updated event
     let options = event_options::Entity::find()
         .filter(event_options::Column::EventId.eq(updated_event.id))
@@ -255,25 +390,45 @@ pub async fn update_event(
 pub async fn list_events(
     db: web::Data<DatabaseConnection>,
     redis_pool: web::Data<Pool>,
+    tenant: web::ReqData<tenants::Model>,
     query: web::Query<ListEventsQuery>,
 ) -> Result<HttpResponse, Error> {
+    let selected = match fields::parse(query.fields.as_deref(), EventResponse::FIELDS) {
+        Ok(selected) => selected,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "message": message,
+                "status": "error",
+            })));
+        }
+    };
+
     let cache_service = CacheService::new(redis_pool.get_ref().clone());
 
-    // Create cache key based on query parameters
-    let cache_key = format!(
-        "events:list:{}:{}:{}:{}",
-        query.status.as_deref().unwrap_or("all"),
-        query.category.as_deref().unwrap_or("all"),
-        query.pagination.get_page(),
-        query.pagination.get_limit()
+    // Create cache key based on query parameters; the full listing is cached
+    // and projected per request so field selections share one entry
+    let cache_key = tenant_cache_key(
+        tenant.id,
+        &format!(
+            "events:list:{}:{}:{}:{}:{}",
+            query.status.as_deref().unwrap_or("all"),
+            query.category.as_deref().unwrap_or("all"),
+            query
+                .featured
+                .map(|f| f.to_string())
+                .as_deref()
+                .unwrap_or("all"),
+            query.pagination.get_page(),
+            query.pagination.get_limit()
+        ),
     );
 
     // Try to get from cache first
     if let Ok(Some(cached_response)) = cache_service.get::<serde_json::Value>(&cache_key).await {
-        return Ok(HttpResponse::Ok().json(cached_response));
+        return Ok(HttpResponse::Ok().json(project_listing(cached_response, selected.as_deref())));
     }
 
-    let mut events_query = events::Entity::find();
+    let mut events_query = events::Entity::find().filter(events::Column::TenantId.eq(tenant.id));
 
     // Apply filters
     if let Some(status) = &query.status {
@@ -282,6 +437,16 @@ pub async fn list_events(
     if let Some(category) = &query.category {
         events_query = events_query.filter(events::Column::Category.eq(category));
     }
+    let featured = featured_condition(Utc::now().naive_utc());
+    match query.featured {
+        Some(true) => {
+            events_query = events_query
+                .filter(featured)
+                .order_by_asc(events::Column::FeaturedRank)
+        }
+        Some(false) => events_query = events_query.filter(featured.not()),
+        None => {}
+    }
 
     // Apply pagination
     let page = query.pagination.get_page();
@@ -335,12 +500,25 @@ pub async fn list_events(
         "pagination": response.pagination,
     });
 
-    // Cache the response for 5 minutes
-    if let Err(e) = cache_service.set(&cache_key, &response_json, 300).await {
+    // Cache the response for 5 minutes, or 1 minute when it depends on feature
+    // windows so scheduled changes show up promptly
+    let ttl = if query.featured.is_some() { 60 } else { 300 };
+    if let Err(e) = cache_service.set(&cache_key, &response_json, ttl).await {
         log::warn!("Failed to cache events list: {}", e);
     }
 
-    Ok(HttpResponse::Ok().json(response_json))
+    Ok(HttpResponse::Ok().json(project_listing(response_json, selected.as_deref())))
+}
+
+/// Apply a field selection to the `data` array of an events listing
+fn project_listing(
+    mut response: serde_json::Value,
+    selected: Option<&[String]>,
+) -> serde_json::Value {
+    if let Some(data) = response.get_mut("data") {
+        *data = fields::project(data.take(), selected);
+    }
+    response
 }
 
 pub async fn get_event(
@@ -398,3 +576,206 @@ pub async fn get_event(
         "event": Some(event_response),
     })))
 }
+
+/// Shareable summary of an event for link previews, as JSON or an SVG image
+pub async fn get_event_og_card(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    event_id: web::Path<i32>,
+    query: web::Query<OgCardQuery>,
+) -> Result<HttpResponse, Error> {
+    let cache_service = CacheService::new(redis_pool.get_ref().clone());
+    let cache_key = create_cache_key(cache_keys::OG_CARD_PREFIX, &event_id.to_string());
+
+    let card = match cache_service.get::<OgCardResponse>(&cache_key).await {
+        Ok(Some(card)) => card,
+        _ => {
+            let event = events::Entity::find_by_id(*event_id)
+                .one(db.get_ref())
+                .await
+                .map_err(|e| {
+                    log::error!("Database error: {}", e);
+                    actix_web::error::ErrorInternalServerError("Database error occurred")
+                })?;
+
+            let Some(event) = event else {
+                return Ok(HttpResponse::NotFound().json(json!({
+                    "message": "Event not found",
+                    "card": serde_json::Value::Null,
+                })));
+            };
+
+            let options = event_options::Entity::find()
+                .filter(event_options::Column::EventId.eq(event.id))
+                .all(db.get_ref())
+                .await
+                .map_err(|e| {
+                    log::error!("Database error: {}", e);
+                    actix_web::error::ErrorInternalServerError("Database error occurred")
+                })?;
+
+            // 24h volume is a nice-to-have on a preview, so don't fail without it
+            let volume_24h =
+                MarketStatsService::new(db.get_ref().clone(), redis_pool.get_ref().clone())
+                    .for_event(event.id)
+                    .await
+                    .map(|stats| stats.values().map(|s| s.volume_24h).sum())
+                    .unwrap_or_else(|e| {
+                        log::error!("Failed to compute market stats: {}", e);
+                        Decimal::ZERO
+                    });
+
+            // A winning_option_id of 0 means the event hasn't been resolved
+            let leader = match event.winning_option_id {
+                0 => options.iter().max_by_key(|o| o.current_price),
+                winner => options.iter().find(|o| o.id == winner),
+            };
+
+            let card = OgCardResponse {
+                event_id: event.id,
+                title: event.title,
+                category: event.category,
+                status: event.status,
+                image_url: event.image_url,
+                end_time: event.end_time,
+                leading_option: leader.map(|o| OgCardOption {
+                    id: o.id,
+                    option_text: o.option_text.clone(),
+                    price: o.current_price,
+                    implied_probability: (o.current_price / Decimal::ONE_HUNDRED).round_dp(4),
+                }),
+                total_volume: event.total_volume,
+                volume_24h,
+                generated_at: Utc::now(),
+            };
+
+            if let Err(e) = cache_service
+                .set(&cache_key, &card, OG_CARD_CACHE_SECONDS)
+                .await
+            {
+                log::warn!("Failed to cache og card: {}", e);
+            }
+            card
+        }
+    };
+
+    let cache_control = (
+        "Cache-Control",
+        format!("public, max-age={}", OG_CARD_CACHE_SECONDS),
+    );
+    match query.format.unwrap_or_default() {
+        OgCardFormat::Svg => Ok(HttpResponse::Ok()
+            .content_type("image/svg+xml")
+            .insert_header(cache_control)
+            .body(og_card::render_svg(&card))),
+        OgCardFormat::Json => Ok(HttpResponse::Ok().insert_header(cache_control).json(json!({
+            "message": "Card generated successfully",
+            "card": card,
+        }))),
+    }
+}
+
+/// Pin an event to the homepage at a rank, optionally for a scheduled window
+pub async fn feature_event(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    event_id: web::Path<i32>,
+    req: web::Json<FeatureEventRequest>,
+) -> Result<HttpResponse, Error> {
+    if let Err(message) = req.validate() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "message": message,
+            "event": serde_json::Value::Null,
+        })));
+    }
+
+    set_featuring(
+        db,
+        redis_pool,
+        ws_server,
+        *event_id,
+        Some(req.rank),
+        req.featured_from.map(|t| t.naive_utc()),
+        req.featured_until.map(|t| t.naive_utc()),
+    )
+    .await
+}
+
+/// Take an event off the homepage
+pub async fn unfeature_event(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    event_id: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    set_featuring(db, redis_pool, ws_server, *event_id, None, None, None).await
+}
+
+async fn set_featuring(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    event_id: i32,
+    rank: Option<i32>,
+    featured_from: Option<chrono::NaiveDateTime>,
+    featured_until: Option<chrono::NaiveDateTime>,
+) -> Result<HttpResponse, Error> {
+    let event = events::Entity::find_by_id(event_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let event = match event {
+        Some(e) => e,
+        None => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "message": "Event not found",
+                "event": serde_json::Value::Null,
+            })))
+        }
+    };
+
+    let mut active_event: events::ActiveModel = event.into();
+    active_event.featured_rank = Set(rank);
+    active_event.featured_from = Set(featured_from);
+    active_event.featured_until = Set(featured_until);
+    active_event.updated_at = Set(Utc::now().naive_utc());
+
+    let updated_event = active_event.update(db.get_ref()).await.map_err(|e| {
+        log::error!("Event featuring update error: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to update event")
+    })?;
+
+    let options = event_options::Entity::find()
+        .filter(event_options::Column::EventId.eq(updated_event.id))
+        .all(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let event_response = EventResponse::from((updated_event, options));
+
+    let cache_service = CacheService::new(redis_pool.get_ref().clone());
+    let event_cache_key = create_cache_key(cache_keys::EVENT_PREFIX, &event_id.to_string());
+    if let Err(e) = cache_service.delete(&event_cache_key).await {
+        log::warn!("Failed to invalidate event cache: {}", e);
+    }
+
+    let handlers =
+        crate::websocket::handlers::WebSocketHandlers::new(db.clone(), ws_server.get_ref().clone());
+    tokio::spawn(async move {
+        handlers.fetch_and_broadcast_event(event_id).await;
+    });
+    ws_server.do_send(crate::websocket::server::BroadcastEventsUpdate);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": if rank.is_some() { "Event featured successfully" } else { "Event unfeatured successfully" },
+        "event": Some(event_response),
+    })))
+}
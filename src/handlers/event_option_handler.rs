@@ -1,22 +1,30 @@
+use crate::constants::config;
 use crate::middleware::auth::AuthenticatedUser;
+use crate::order_book::conservation;
+use crate::order_book::market_stats::MarketStatsService;
 use crate::order_book::{MarketMaker, MarketMakerConfig};
 use crate::types::event_option::{
-    CreateEventOptionRequest, EventOptionResponse, UpdateEventOptionRequest,
+    CreateEventOptionRequest, EventOptionResponse, OptionStats, PriceHistoryPoint,
+    PriceHistoryQuery, UpdateEventOptionRequest,
 };
-use crate::utils::auth::{check_admin_role, get_user_id};
+use crate::utils::auth::get_user_id;
 use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
+use crate::utils::db_pool::ReportingDb;
 use crate::utils::pagination::{PaginatedResponse, PaginationInfo, PaginationQuery};
 use crate::websocket::server::WebSocketServer;
 use actix::prelude::*;
 use actix_web::{web, Error, HttpResponse, Result};
 use deadpool_redis::Pool;
-use entity::{event_options, events};
+use entity::{event_options, events, price_history};
 use sea_orm::{
     prelude::Decimal, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
     PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
 };
 use serde_json::json;
 
+const DEFAULT_PRICE_HISTORY_LIMIT: u64 = 500;
+const MAX_PRICE_HISTORY_LIMIT: u64 = 5000;
+
 pub async fn create_event_option(
     db: web::Data<DatabaseConnection>,
     redis_pool: web::Data<Pool>,
@@ -24,11 +32,6 @@ pub async fn create_event_option(
     req: web::Json<CreateEventOptionRequest>,
     auth_user: web::ReqData<AuthenticatedUser>,
 ) -> Result<HttpResponse, Error> {
-    // Check if user is admin
-    if let Err(response) = check_admin_role(&auth_user) {
-        return Ok(response);
-    }
-
     let creator_id = get_user_id(&auth_user)?;
 
     log::info!("Creating event option for admin user: {}", creator_id);
@@ -89,27 +92,17 @@ pub async fn create_event_option(
 
     // Seed initial liquidity if requested
     if req.seed_liquidity.unwrap_or(false) {
-        let mut config = MarketMakerConfig {
-            market_maker_user_id: creator_id,
-            initial_price: option.current_price,
-            ..Default::default()
-        };
+        let mut config = MarketMakerConfig::from_settings(
+            Some(event.tenant_id),
+            creator_id,
+            option.current_price,
+        );
 
         // Apply custom liquidity configuration if provided
         if let Some(liquidity_config) = &req.liquidity_config {
-            if let Some(spread) = liquidity_config.spread_percentage {
-                config.spread_percentage = spread;
-            }
-            if let Some(levels) = liquidity_config.depth_levels {
-                config.depth_levels = levels;
-            }
-            if let Some(quantity) = liquidity_config.level_quantity {
-                config.level_quantity = quantity;
-            }
-            if let Some(step) = liquidity_config.price_step {
-                config.price_step = step;
-            }
+            liquidity_config.apply_to(&mut config);
         }
+        let seeded_shares = config.seeded_shares();
 
         // Create market maker and seed liquidity
         let market_maker =
@@ -125,6 +118,12 @@ pub async fn create_event_option(
                     order_ids.len(),
                     option.id
                 );
+                if let Err(e) = market_maker
+                    .start_quoting(option.event_id, option.id, order_ids, seeded_shares)
+                    .await
+                {
+                    log::error!("Failed to start re-quoting seeded liquidity: {}", e);
+                }
             }
             Err(e) => {
                 log::error!("Failed to seed initial liquidity: {}", e);
@@ -133,8 +132,11 @@ pub async fn create_event_option(
         }
     }
 
-    // Return success response
-    let option_response = EventOptionResponse::from(option.clone());
+    // Return success response, with the seeded liquidity in its stats
+    let stats_service = MarketStatsService::new(db.get_ref().clone(), redis_pool.get_ref().clone());
+    stats_service.invalidate(option.event_id).await;
+    let stats = option_stats(&stats_service, option.event_id, option.id).await;
+    let option_response = EventOptionResponse::from((option.clone(), stats));
 
     // Broadcast the updated event to all subscribers
     let handlers =
@@ -174,13 +176,7 @@ pub async fn update_event_option(
     ws_server: web::Data<Addr<WebSocketServer>>,
     option_id: web::Path<i32>,
     req: web::Json<UpdateEventOptionRequest>,
-    auth_user: web::ReqData<AuthenticatedUser>,
 ) -> Result<HttpResponse, Error> {
-    // Check if user is admin
-    if let Err(response) = check_admin_role(&auth_user) {
-        return Ok(response);
-    }
-
     // Find the event option
     let option = event_options::Entity::find_by_id(*option_id)
         .one(db.get_ref())
@@ -257,7 +253,9 @@ pub async fn update_event_option(
         actix_web::error::ErrorInternalServerError("Failed to update event option")
     })?;
 
-    let option_response = EventOptionResponse::from(updated_option);
+    let stats_service = MarketStatsService::new(db.get_ref().clone(), redis_pool.get_ref().clone());
+    let stats = option_stats(&stats_service, updated_option.event_id, updated_option.id).await;
+    let option_response = EventOptionResponse::from((updated_option, stats));
 
     // Invalidate relevant caches
     let cache_service = CacheService::new(redis_pool.get_ref().clone());
@@ -360,8 +358,20 @@ pub async fn list_event_options(
             actix_web::error::ErrorInternalServerError("Database error occurred")
         })?;
 
-    let options_response: Vec<EventOptionResponse> =
-        options.into_iter().map(EventOptionResponse::from).collect();
+    let mut stats = MarketStatsService::new(db.get_ref().clone(), redis_pool.get_ref().clone())
+        .for_event(*event_id)
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Failed to compute market stats: {}", e);
+            Default::default()
+        });
+    let options_response: Vec<EventOptionResponse> = options
+        .into_iter()
+        .map(|option| {
+            let option_stats = stats.remove(&option.id).unwrap_or_default();
+            EventOptionResponse::from((option, option_stats))
+        })
+        .collect();
 
     let pagination_info = PaginationInfo::new(page, total_count, limit);
     let response = PaginatedResponse::new(options_response, pagination_info);
@@ -373,8 +383,15 @@ pub async fn list_event_options(
         "pagination": response.pagination,
     });
 
-    // Cache the response for 10 minutes
-    if let Err(e) = cache_service.set(&cache_key, &response_json, 600).await {
+    // Cache the response only as long as the market stats in it
+    if let Err(e) = cache_service
+        .set(
+            &cache_key,
+            &response_json,
+            config::get_market_stats_cache_seconds(),
+        )
+        .await
+    {
         log::warn!("Failed to cache event options list: {}", e);
     }
 
@@ -415,10 +432,19 @@ pub async fn get_event_option(
         }
     };
 
-    let option_response = EventOptionResponse::from(option);
-
-    // Cache the option for 10 minutes
-    if let Err(e) = cache_service.set(&cache_key, &option_response, 600).await {
+    let stats_service = MarketStatsService::new(db.get_ref().clone(), redis_pool.get_ref().clone());
+    let stats = option_stats(&stats_service, option.event_id, option.id).await;
+    let option_response = EventOptionResponse::from((option, stats));
+
+    // Cache the option only as long as the market stats in it
+    if let Err(e) = cache_service
+        .set(
+            &cache_key,
+            &option_response,
+            config::get_market_stats_cache_seconds(),
+        )
+        .await
+    {
         log::warn!("Failed to cache event option: {}", e);
     }
 
@@ -427,3 +453,173 @@ pub async fn get_event_option(
         "option": Some(option_response),
     })))
 }
+
+/// Number of holders of an option and how their position sizes are spread
+pub async fn get_option_holders(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    option_id: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let option = event_options::Entity::find_by_id(*option_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let option = match option {
+        Some(o) => o,
+        None => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "message": "Event option not found",
+                "holders": serde_json::Value::Null,
+            })))
+        }
+    };
+
+    let distribution = MarketStatsService::new(db.get_ref().clone(), redis_pool.get_ref().clone())
+        .holder_distribution(option.event_id, option.id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to compute holder distribution: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to compute holders")
+        })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Holders retrieved successfully",
+        "holders": distribution,
+    })))
+}
+
+/// Prices an option has had, oldest first, for charting its probability over
+/// time. Without `from` and `to` returns the most recent `limit` prices.
+pub async fn get_option_price_history(
+    db: web::Data<DatabaseConnection>,
+    option_id: web::Path<i32>,
+    query: web::Query<PriceHistoryQuery>,
+) -> Result<HttpResponse, Error> {
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "message": "from must be before to",
+                "history": serde_json::Value::Null,
+            })));
+        }
+    }
+
+    let option = event_options::Entity::find_by_id(*option_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+    let Some(option) = option else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "message": "Event option not found",
+            "history": serde_json::Value::Null,
+        })));
+    };
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PRICE_HISTORY_LIMIT)
+        .clamp(1, MAX_PRICE_HISTORY_LIMIT);
+    let mut history =
+        price_history::Entity::find().filter(price_history::Column::OptionId.eq(option.id));
+    if let Some(from) = query.from {
+        history = history.filter(price_history::Column::Timestamp.gte(from));
+    }
+    if let Some(to) = query.to {
+        history = history.filter(price_history::Column::Timestamp.lt(to));
+    }
+    let mut history = history
+        .order_by_desc(price_history::Column::Timestamp)
+        .order_by_desc(price_history::Column::Id)
+        .limit(limit)
+        .all(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to load price history: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to load price history")
+        })?;
+    history.reverse();
+
+    let history: Vec<PriceHistoryPoint> = history.into_iter().map(Into::into).collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Price history retrieved successfully",
+        "event_id": option.event_id,
+        "option_id": option.id,
+        "current_price": option.current_price,
+        "history": history,
+    })))
+}
+
+/// Admin audit that an option's positions net to the shares issued for it
+pub async fn get_option_conservation(
+    db: web::Data<ReportingDb>,
+    option_id: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let db = db.connection();
+    let option = event_options::Entity::find_by_id(*option_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let option = match option {
+        Some(o) => o,
+        None => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "success": false,
+                "message": "Event option not found"
+            })))
+        }
+    };
+
+    let event = events::Entity::find_by_id(option.event_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    // Settling or voiding closes every position, so there is nothing left to balance
+    if event.is_some_and(|e| e.status == "resolved" || e.status == "cancelled") {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "Positions of a resolved or cancelled event have been closed"
+        })));
+    }
+
+    let report = conservation::audit(db, &option).await.map_err(|e| {
+        log::error!("Failed to audit share conservation: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to audit share conservation")
+    })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "conservation": report
+    })))
+}
+
+/// Stats for an option, or empty stats if they can't be computed; they're
+/// informational, so a failure shouldn't fail the request
+async fn option_stats(
+    stats_service: &MarketStatsService,
+    event_id: i32,
+    option_id: i32,
+) -> OptionStats {
+    stats_service
+        .for_option(event_id, option_id)
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Failed to compute market stats: {}", e);
+            OptionStats::default()
+        })
+}
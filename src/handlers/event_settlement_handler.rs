@@ -1,16 +1,29 @@
+use crate::handlers::order_book_handler::cancel_event_orders;
 use crate::middleware::auth::AuthenticatedUser;
-use crate::types::event::{SettleEventRequest, SettlementPayout, SettlementResponse};
-use crate::utils::auth::{check_admin_role, get_user_id};
+use crate::notifications::dispatcher::{self, Notification};
+use crate::order_book::{margin, payouts, position_cache};
+use crate::types::audit::AuditAction;
+use crate::types::event::{
+    OptionPayout, SettleEventRequest, SettlementPayout, SettlementResponse, VoidEventRequest,
+    VoidEventResponse, VoidRefund,
+};
+use crate::types::order_book::settlement_status;
+use crate::types::preferences::NotificationCategory;
+use crate::types::websocket::{OpsEventKind, OpsSeverity};
+use crate::utils::audit::{self, AuditEntry};
+use crate::utils::auth::get_user_id;
 use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
+use crate::wallets::balances;
+use crate::websocket::ops;
 use crate::websocket::server::WebSocketServer;
 use actix::Addr;
-use actix_web::{web, Error, HttpResponse, Result};
+use actix_web::{web, Error, HttpRequest, HttpResponse, Result};
 use chrono::Utc;
 use deadpool_redis::Pool;
-use entity::{event_options, events, transaction, user_positions, users};
+use entity::{event_options, events, trades, transaction, user_positions, users};
 use sea_orm::{
-    prelude::Decimal, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
-    Set, TransactionTrait,
+    prelude::Decimal, ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction,
+    EntityTrait, PaginatorTrait, QueryFilter, QuerySelect, Set, TransactionTrait,
 };
 use serde_json::json;
 use uuid::Uuid;
@@ -21,15 +34,33 @@ pub async fn settle_event(
     ws_server: web::Data<Addr<WebSocketServer>>,
     event_id: web::Path<i32>,
     req: web::Json<SettleEventRequest>,
+    http_req: HttpRequest,
     auth_user: web::ReqData<AuthenticatedUser>,
 ) -> Result<HttpResponse, Error> {
-    // Check if user is admin
-    if let Err(response) = check_admin_role(&auth_user) {
-        return Ok(response);
-    }
-
     let resolver_id = get_user_id(&auth_user)?;
+    settle(
+        &db,
+        &redis_pool,
+        &ws_server,
+        event_id.into_inner(),
+        &req,
+        resolver_id,
+        audit::client_ip(&http_req),
+    )
+    .await
+}
 
+/// Settle an event on behalf of `resolver_id`, paying out every position
+/// under the requested mode. Shared by admin settlement and the oracle.
+pub async fn settle(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    event_id: i32,
+    req: &SettleEventRequest,
+    resolver_id: i32,
+    ip: Option<String>,
+) -> Result<HttpResponse, Error> {
     log::info!("Settling event {} by admin {}", event_id, resolver_id);
 
     // Start database transaction
@@ -38,8 +69,9 @@ pub async fn settle_event(
         actix_web::error::ErrorInternalServerError("Transaction error")
     })?;
 
-    // Get the event
-    let event = events::Entity::find_by_id(*event_id)
+    // Get the event, locked so a concurrent settlement or void waits for this one
+    let event = events::Entity::find_by_id(event_id)
+        .lock_exclusive()
         .one(&txn)
         .await
         .map_err(|e| {
@@ -59,6 +91,9 @@ pub async fn settle_event(
 
     // Admin users can settle any event
 
+    // Payouts are made in the event's currency
+    let currency = event.currency.clone();
+
     // Check if event is already resolved
     if event.status == "resolved" {
         return Ok(HttpResponse::BadRequest().json(json!({
@@ -67,6 +102,14 @@ pub async fn settle_event(
         })));
     }
 
+    // A voided event has already refunded its positions
+    if event.status == "cancelled" {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "message": "Event has been cancelled and can't be settled",
+            "settlement": serde_json::Value::Null,
+        })));
+    }
+
     // Check if event has ended
     if event.end_time > Utc::now().naive_utc() && event.status != "ended" {
         return Ok(HttpResponse::BadRequest().json(json!({
@@ -75,28 +118,31 @@ pub async fn settle_event(
         })));
     }
 
-    // Validate winning option exists and belongs to this event
-    let winning_option = event_options::Entity::find_by_id(req.winning_option_id)
-        .one(&txn)
+    // Positions are only final once every trade has moved them
+    let unsettled_trades = trades::Entity::find()
+        .filter(trades::Column::EventId.eq(event_id))
+        .filter(
+            trades::Column::SettlementStatus
+                .is_in([settlement_status::PENDING, settlement_status::FAILED]),
+        )
+        .count(&txn)
         .await
         .map_err(|e| {
             log::error!("Database error: {}", e);
             actix_web::error::ErrorInternalServerError("Database error occurred")
         })?;
+    if unsettled_trades > 0 {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "message": format!(
+                "Event has {} unsettled trades; settle or retry them before settling the event",
+                unsettled_trades
+            ),
+            "settlement": serde_json::Value::Null,
+        })));
+    }
 
-    let winning_option = match winning_option {
-        Some(o) if o.event_id == *event_id => o,
-        _ => {
-            return Ok(HttpResponse::BadRequest().json(json!({
-                "message": "Invalid winning option ID",
-                "settlement": serde_json::Value::Null,
-            })))
-        }
-    };
-
-    // Get all event options to mark losers
     let all_options = event_options::Entity::find()
-        .filter(event_options::Column::EventId.eq(*event_id))
+        .filter(event_options::Column::EventId.eq(event_id))
         .all(&txn)
         .await
         .map_err(|e| {
@@ -104,20 +150,49 @@ pub async fn settle_event(
             actix_web::error::ErrorInternalServerError("Database error occurred")
         })?;
 
-    // Mark winning and losing options
+    // Work out what each option pays under the requested mode
+    let option_ids: Vec<i32> = all_options.iter().map(|option| option.id).collect();
+    let schedule = match payouts::schedule(req, &option_ids, event.settlement_rules.as_deref()) {
+        Ok(schedule) => schedule,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "message": message,
+                "settlement": serde_json::Value::Null,
+            })))
+        }
+    };
+    let payout_of = |option_id: i32| schedule.get(&option_id).copied().unwrap_or_default();
+
+    // The option paying the most is recorded as the event's winner
+    let winning_option = all_options
+        .iter()
+        .filter(|option| payout_of(option.id) > Decimal::ZERO)
+        .max_by_key(|option| (payout_of(option.id), -option.id))
+        .cloned();
+
+    // Record what each option pays; every option paying anything wins
+    let mut option_payouts = Vec::new();
     for option in all_options {
-        let mut active_option: event_options::ActiveModel = option.clone().into();
-        active_option.is_winning_option = Set(Some(option.id == req.winning_option_id));
+        let payout_per_share = payout_of(option.id);
+        option_payouts.push(OptionPayout {
+            option_id: option.id,
+            option_text: option.option_text.clone(),
+            payout_per_share,
+        });
+
+        let mut active_option: event_options::ActiveModel = option.into();
+        active_option.is_winning_option = Set(Some(payout_per_share > Decimal::ZERO));
+        active_option.payout_per_share = Set(Some(payout_per_share));
         active_option.update(&txn).await.map_err(|e| {
             log::error!("Failed to update option: {}", e);
             actix_web::error::ErrorInternalServerError("Failed to update option")
         })?;
     }
 
-    // Get all user positions for this event
+    // Get all user positions for this event, long and short
     let positions = user_positions::Entity::find()
-        .filter(user_positions::Column::EventId.eq(*event_id))
-        .filter(user_positions::Column::Quantity.gt(0))
+        .filter(user_positions::Column::EventId.eq(event_id))
+        .filter(user_positions::Column::Quantity.ne(0))
         .all(&txn)
         .await
         .map_err(|e| {
@@ -125,8 +200,6 @@ pub async fn settle_event(
             actix_web::error::ErrorInternalServerError("Database error occurred")
         })?;
 
-    // Calculate payouts
-    let payout_per_share = Decimal::new(100, 2); // 1.00 per winning share
     let mut settlement_payouts = Vec::new();
     let mut total_payouts = Decimal::new(0, 2);
 
@@ -151,33 +224,39 @@ pub async fn settle_event(
             })?
             .ok_or_else(|| actix_web::error::ErrorInternalServerError("Option not found"))?;
 
-        let is_winner = position.option_id == req.winning_option_id;
-        let payout = if is_winner {
-            payout_per_share * Decimal::from(position.quantity)
-        } else {
-            Decimal::new(0, 2)
-        };
+        // Short positions have a negative quantity, so they pay out a negative
+        // amount when their option pays and their proceeds count as a
+        // negative investment
+        let payout_per_share = payout_of(position.option_id);
+        let payout = payout_per_share * Decimal::from(position.quantity);
 
         let invested = position.average_price * Decimal::from(position.quantity);
         let profit_loss = payout - invested;
 
-        // Update user balance if they won
-        if is_winner && payout > Decimal::new(0, 2) {
-            let mut active_user: users::ActiveModel = user.clone().into();
-            active_user.wallet_balance = Set(active_user.wallet_balance.as_ref() + payout);
-            active_user.updated_at = Set(Utc::now().naive_utc());
-            active_user.update(&txn).await.map_err(|e| {
-                log::error!("Failed to update user balance: {}", e);
-                actix_web::error::ErrorInternalServerError("Failed to update balance")
-            })?;
+        // Update user balance if their option paid
+        if payout > Decimal::new(0, 2) {
+            let wallet = balances::lock(&txn, position.user_id, &currency)
+                .await
+                .map_err(|e| {
+                    log::error!("Database error: {}", e);
+                    actix_web::error::ErrorInternalServerError("Database error occurred")
+                })?;
+            let balance_before = wallet.balance;
+            balances::set_balance(&txn, wallet, balance_before + payout)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to update user balance: {}", e);
+                    actix_web::error::ErrorInternalServerError("Failed to update balance")
+                })?;
 
             // Create payout transaction record
             let transaction_record = transaction::ActiveModel {
                 user_id: Set(position.user_id),
                 r#type: Set("event_payout".to_string()),
                 amount: Set(payout),
-                balance_before: Set(user.wallet_balance),
-                balance_after: Set(user.wallet_balance + payout),
+                currency: Set(currency.clone()),
+                balance_before: Set(balance_before),
+                balance_after: Set(balance_before + payout),
                 status: Set("completed".to_string()),
                 reference_id: Set(format!("event_{}_{}", event_id, Uuid::new_v4())),
                 created_at: Set(Utc::now().naive_utc()),
@@ -200,17 +279,17 @@ pub async fn settle_event(
             actix_web::error::ErrorInternalServerError("Failed to close position")
         })?;
 
+        if position.quantity < 0 {
+            settle_short(&txn, &position, &currency, payout_per_share, event_id).await?;
+        }
+
         settlement_payouts.push(SettlementPayout {
             user_id: position.user_id,
             username: user.username,
             option_id: position.option_id,
             option_text: option.option_text.clone(),
             shares_held: position.quantity,
-            payout_per_share: if is_winner {
-                payout_per_share
-            } else {
-                Decimal::new(0, 2)
-            },
+            payout_per_share,
             total_payout: payout,
             profit_loss,
         });
@@ -220,7 +299,7 @@ pub async fn settle_event(
     let mut active_event: events::ActiveModel = event.clone().into();
     active_event.status = Set("resolved".to_string());
     active_event.resolved_by = Set(resolver_id);
-    active_event.winning_option_id = Set(req.winning_option_id);
+    active_event.winning_option_id = Set(winning_option.as_ref().map_or(0, |option| option.id));
     active_event.resolution_note = Set(req.resolution_note.clone().unwrap_or_default());
     active_event.resolved_at = Set(Utc::now().naive_utc());
     active_event.updated_at = Set(Utc::now().naive_utc());
@@ -230,24 +309,88 @@ pub async fn settle_event(
         actix_web::error::ErrorInternalServerError("Failed to update event")
     })?;
 
+    let mut after = audit::event_snapshot(&updated_event);
+    after["mode"] = json!(req.mode);
+    after["payout_per_share"] = json!(schedule);
+    after["total_payouts"] = json!(total_payouts);
+    after["total_positions_settled"] = json!(settlement_payouts.len());
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: resolver_id,
+            action: AuditAction::EventSettle,
+            target_id: updated_event.id,
+            before: Some(audit::event_snapshot(&event)),
+            after: Some(after),
+            ip,
+        },
+    )
+    .await
+    .map_err(|e| {
+        log::error!("{}", e);
+        actix_web::error::ErrorInternalServerError("Failed to update event")
+    })?;
+
     // Commit transaction
     txn.commit().await.map_err(|e| {
         log::error!("Failed to commit transaction: {}", e);
+        ops::publish(
+            OpsEventKind::SettlementFailed,
+            OpsSeverity::Critical,
+            format!("Settlement of event {} failed to commit", event_id),
+            json!({ "event_id": event_id, "error": e.to_string() }),
+        );
         actix_web::error::ErrorInternalServerError("Transaction commit failed")
     })?;
 
+    // Settlement zeroes every position in the event
+    position_cache::invalidate_event(event_id);
+
+    // Nothing can trade on a resolved event, so cancel its resting orders and
+    // release the funds they hold
+    if let Err(e) = cancel_event_orders(db, redis_pool, ws_server, event_id).await {
+        log::error!(
+            "Failed to cancel open orders of settled event {}: {}",
+            event_id,
+            e
+        );
+    }
+
     // Prepare response
     let settlement_response = SettlementResponse {
         event_id: updated_event.id,
         event_title: updated_event.title.clone(),
-        winning_option_id: req.winning_option_id,
-        winning_option_text: winning_option.option_text,
+        mode: req.mode,
+        winning_option_id: winning_option.as_ref().map(|option| option.id),
+        winning_option_text: winning_option.map(|option| option.option_text),
+        option_payouts,
+        currency: updated_event.currency.clone(),
         total_payouts,
         total_positions_settled: settlement_payouts.len(),
         payouts: settlement_payouts,
         settlement_timestamp: updated_event.resolved_at,
     };
 
+    ops::publish(
+        OpsEventKind::SettlementCompleted,
+        OpsSeverity::Info,
+        format!(
+            "Event {} settled by admin {}: {} positions, {} paid out",
+            event_id,
+            resolver_id,
+            settlement_response.total_positions_settled,
+            settlement_response.total_payouts
+        ),
+        json!({
+            "event_id": event_id,
+            "mode": req.mode,
+            "winning_option_id": settlement_response.winning_option_id,
+            "resolver_id": resolver_id,
+            "total_positions_settled": settlement_response.total_positions_settled,
+            "total_payouts": settlement_response.total_payouts
+        }),
+    );
+
     // Invalidate caches
     let cache_service = CacheService::new(redis_pool.get_ref().clone());
     let event_cache_key = create_cache_key(cache_keys::EVENT_PREFIX, &event_id.to_string());
@@ -264,7 +407,7 @@ pub async fn settle_event(
     let handlers =
         crate::websocket::handlers::WebSocketHandlers::new(db.clone(), ws_server.get_ref().clone());
 
-    let event_id_for_broadcast = *event_id;
+    let event_id_for_broadcast = event_id;
     tokio::spawn(async move {
         handlers
             .fetch_and_broadcast_event(event_id_for_broadcast)
@@ -275,7 +418,7 @@ pub async fn settle_event(
 
     // Notify affected users about their payouts
     for payout in &settlement_response.payouts {
-        if payout.total_payout > Decimal::new(0, 2) {
+        if payout.total_payout > Decimal::new(0, 2) || payout.shares_held < 0 {
             ws_server.do_send(crate::websocket::server::BroadcastTransactionsUpdate {
                 user_id: payout.user_id,
             });
@@ -285,8 +428,484 @@ pub async fn settle_event(
         }
     }
 
+    let notifications: Vec<(i32, Notification)> = settlement_response
+        .payouts
+        .iter()
+        .map(|payout| {
+            (
+                payout.user_id,
+                Notification {
+                    category: NotificationCategory::Settlements,
+                    title: format!("{} has settled", settlement_response.event_title),
+                    body: format!(
+                        "Your {} shares of {} paid out {} (profit/loss {})",
+                        payout.shares_held,
+                        payout.option_text,
+                        payout.total_payout,
+                        payout.profit_loss
+                    ),
+                },
+            )
+        })
+        .collect();
+    let (db_for_notify, redis_for_notify, ws_for_notify) =
+        (db.clone(), redis_pool.clone(), ws_server.clone());
+    tokio::spawn(async move {
+        for (user_id, notification) in notifications {
+            if let Err(e) = dispatcher::dispatch(
+                db_for_notify.get_ref(),
+                redis_for_notify.get_ref(),
+                ws_for_notify.get_ref(),
+                user_id,
+                notification,
+            )
+            .await
+            {
+                log::warn!("Failed to notify user {} of settlement: {}", user_id, e);
+            }
+        }
+    });
+
     Ok(HttpResponse::Ok().json(json!({
         "message": "Event settled successfully",
         "settlement": settlement_response,
     })))
 }
+
+/// Void an event whose question can no longer be answered. Every position is
+/// unwound at the price it was taken on, market-maker inventory included,
+/// and resting orders are cancelled with their holds released.
+pub async fn void_event(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    event_id: web::Path<i32>,
+    req: web::Json<VoidEventRequest>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+
+    let reason = req.reason.trim().to_string();
+    if reason.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "message": "A reason for voiding the event is required",
+            "void": serde_json::Value::Null,
+        })));
+    }
+
+    log::info!("Voiding event {} by admin {}", event_id, admin_id);
+
+    let txn = db.get_ref().begin().await.map_err(|e| {
+        log::error!("Failed to start transaction: {}", e);
+        actix_web::error::ErrorInternalServerError("Transaction error")
+    })?;
+
+    // Locked so a concurrent settlement or void waits for this one
+    let event = events::Entity::find_by_id(*event_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let event = match event {
+        Some(e) => e,
+        None => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "message": "Event not found",
+                "void": serde_json::Value::Null,
+            })))
+        }
+    };
+
+    // Refunds are made in the event's currency
+    let currency = event.currency.clone();
+
+    if event.status == "resolved" {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "message": "Event is already resolved",
+            "void": serde_json::Value::Null,
+        })));
+    }
+
+    // Positions are only final once every trade has moved them
+    let unsettled_trades = trades::Entity::find()
+        .filter(trades::Column::EventId.eq(*event_id))
+        .filter(
+            trades::Column::SettlementStatus
+                .is_in([settlement_status::PENDING, settlement_status::FAILED]),
+        )
+        .count(&txn)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+    if unsettled_trades > 0 {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "message": format!(
+                "Event has {} unsettled trades; settle or retry them before voiding",
+                unsettled_trades
+            ),
+            "void": serde_json::Value::Null,
+        })));
+    }
+
+    let positions = user_positions::Entity::find()
+        .filter(user_positions::Column::EventId.eq(*event_id))
+        .filter(user_positions::Column::Quantity.ne(0))
+        .all(&txn)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let mut refunds = Vec::new();
+    let mut total_refunds = Decimal::new(0, 2);
+
+    for position in positions {
+        let user = users::Entity::find_by_id(position.user_id)
+            .one(&txn)
+            .await
+            .map_err(|e| {
+                log::error!("Database error: {}", e);
+                actix_web::error::ErrorInternalServerError("Database error occurred")
+            })?
+            .ok_or_else(|| actix_web::error::ErrorInternalServerError("User not found"))?;
+
+        let option = event_options::Entity::find_by_id(position.option_id)
+            .one(&txn)
+            .await
+            .map_err(|e| {
+                log::error!("Database error: {}", e);
+                actix_web::error::ErrorInternalServerError("Database error occurred")
+            })?
+            .ok_or_else(|| actix_web::error::ErrorInternalServerError("Option not found"))?;
+
+        // What the position cost, negative for a short's sale proceeds. The
+        // market maker's seeded inventory has no cost, so it is simply removed.
+        let refund = position.average_price * Decimal::from(position.quantity);
+
+        // Close the position first, so a short's collateral is no longer required
+        let mut active_position: user_positions::ActiveModel = position.clone().into();
+        active_position.quantity = Set(0);
+        active_position.updated_at = Set(Utc::now().into());
+        active_position.update(&txn).await.map_err(|e| {
+            log::error!("Failed to close position: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to close position")
+        })?;
+
+        if position.quantity < 0 {
+            margin::release(
+                &txn,
+                (position.user_id, position.event_id, position.option_id),
+                &currency,
+                -position.quantity,
+                &format!("event_{}", event_id),
+                None,
+            )
+            .await
+            .map_err(|e| {
+                log::error!("Failed to release short collateral: {}", e);
+                actix_web::error::ErrorInternalServerError("Failed to release collateral")
+            })?;
+        }
+
+        if !refund.is_zero() {
+            let (kind, amount) = if refund > Decimal::ZERO {
+                ("event_refund", refund)
+            } else {
+                ("short_refund", -refund)
+            };
+            // A short whose proceeds have been spent is left with a negative
+            // wallet, as when it loses at settlement
+            let wallet = balances::lock(&txn, position.user_id, &currency)
+                .await
+                .map_err(|e| {
+                    log::error!("Database error: {}", e);
+                    actix_web::error::ErrorInternalServerError("Database error occurred")
+                })?;
+            let balance_before = wallet.balance;
+            balances::set_balance(&txn, wallet, balance_before + refund)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to update user balance: {}", e);
+                    actix_web::error::ErrorInternalServerError("Failed to update balance")
+                })?;
+
+            transaction::ActiveModel {
+                user_id: Set(position.user_id),
+                r#type: Set(kind.to_string()),
+                amount: Set(amount),
+                currency: Set(currency.clone()),
+                balance_before: Set(balance_before),
+                balance_after: Set(balance_before + refund),
+                status: Set("completed".to_string()),
+                reference_id: Set(format!("event_{}_{}", event_id, Uuid::new_v4())),
+                created_at: Set(Utc::now().naive_utc()),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to create transaction record: {}", e);
+                actix_web::error::ErrorInternalServerError("Failed to create transaction")
+            })?;
+
+            total_refunds += refund;
+        }
+
+        refunds.push(VoidRefund {
+            user_id: position.user_id,
+            username: user.username,
+            option_id: position.option_id,
+            option_text: option.option_text,
+            shares_held: position.quantity,
+            average_price: position.average_price,
+            refund,
+        });
+    }
+
+    let mut active_event: events::ActiveModel = event.clone().into();
+    active_event.status = Set("cancelled".to_string());
+    active_event.resolved_by = Set(admin_id);
+    active_event.winning_option_id = Set(0);
+    active_event.resolution_note = Set(reason.clone());
+    active_event.resolved_at = Set(Utc::now().naive_utc());
+    active_event.updated_at = Set(Utc::now().naive_utc());
+
+    let updated_event = active_event.update(&txn).await.map_err(|e| {
+        log::error!("Failed to update event: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to update event")
+    })?;
+
+    let mut after = audit::event_snapshot(&updated_event);
+    after["total_refunds"] = json!(total_refunds);
+    after["total_positions_refunded"] = json!(refunds.len());
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::EventVoid,
+            target_id: updated_event.id,
+            before: Some(audit::event_snapshot(&event)),
+            after: Some(after),
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(|e| {
+        log::error!("{}", e);
+        actix_web::error::ErrorInternalServerError("Failed to update event")
+    })?;
+
+    txn.commit().await.map_err(|e| {
+        log::error!("Failed to commit transaction: {}", e);
+        ops::publish(
+            OpsEventKind::SettlementFailed,
+            OpsSeverity::Critical,
+            format!("Voiding event {} failed to commit", event_id),
+            json!({ "event_id": *event_id, "error": e.to_string() }),
+        );
+        actix_web::error::ErrorInternalServerError("Transaction commit failed")
+    })?;
+
+    position_cache::invalidate_event(*event_id);
+
+    // Cancelling the resting orders releases the funds and shares they hold
+    if let Err(e) = cancel_event_orders(&db, &redis_pool, &ws_server, *event_id).await {
+        log::error!(
+            "Failed to cancel open orders of voided event {}: {}",
+            event_id,
+            e
+        );
+    }
+
+    let void_response = VoidEventResponse {
+        event_id: updated_event.id,
+        event_title: updated_event.title.clone(),
+        reason,
+        currency: updated_event.currency.clone(),
+        total_refunds,
+        total_positions_refunded: refunds.len(),
+        refunds,
+        void_timestamp: updated_event.resolved_at,
+    };
+
+    ops::publish(
+        OpsEventKind::EventVoided,
+        OpsSeverity::Warning,
+        format!(
+            "Event {} voided by admin {}: {} positions, {} refunded",
+            event_id, admin_id, void_response.total_positions_refunded, void_response.total_refunds
+        ),
+        json!({
+            "event_id": *event_id,
+            "admin_id": admin_id,
+            "reason": void_response.reason,
+            "total_positions_refunded": void_response.total_positions_refunded,
+            "total_refunds": void_response.total_refunds
+        }),
+    );
+
+    let cache_service = CacheService::new(redis_pool.get_ref().clone());
+    let event_cache_key = create_cache_key(cache_keys::EVENT_PREFIX, &event_id.to_string());
+    let _ = cache_service.delete(&event_cache_key).await;
+    let _ = cache_service.delete("events:list").await;
+    for refund in &void_response.refunds {
+        let portfolio_cache_key = format!("portfolio:{}", refund.user_id);
+        let _ = cache_service.delete(&portfolio_cache_key).await;
+    }
+
+    let handlers =
+        crate::websocket::handlers::WebSocketHandlers::new(db.clone(), ws_server.get_ref().clone());
+    let event_id_for_broadcast = *event_id;
+    tokio::spawn(async move {
+        handlers
+            .fetch_and_broadcast_event(event_id_for_broadcast)
+            .await;
+    });
+
+    ws_server.do_send(crate::websocket::server::BroadcastEventsUpdate);
+
+    for refund in &void_response.refunds {
+        if !refund.refund.is_zero() {
+            ws_server.do_send(crate::websocket::server::BroadcastTransactionsUpdate {
+                user_id: refund.user_id,
+            });
+        }
+        ws_server.do_send(crate::websocket::server::BroadcastPortfolioUpdate {
+            user_id: refund.user_id,
+        });
+    }
+
+    let notifications: Vec<(i32, Notification)> = void_response
+        .refunds
+        .iter()
+        .map(|refund| {
+            (
+                refund.user_id,
+                Notification {
+                    category: NotificationCategory::Settlements,
+                    title: format!("{} has been voided", void_response.event_title),
+                    body: format!(
+                        "Your {} shares of {} were refunded {} at cost: {}",
+                        refund.shares_held, refund.option_text, refund.refund, void_response.reason
+                    ),
+                },
+            )
+        })
+        .collect();
+    let (db_for_notify, redis_for_notify, ws_for_notify) =
+        (db.clone(), redis_pool.clone(), ws_server.clone());
+    tokio::spawn(async move {
+        for (user_id, notification) in notifications {
+            if let Err(e) = dispatcher::dispatch(
+                db_for_notify.get_ref(),
+                redis_for_notify.get_ref(),
+                ws_for_notify.get_ref(),
+                user_id,
+                notification,
+            )
+            .await
+            {
+                log::warn!("Failed to notify user {} of voided event: {}", user_id, e);
+            }
+        }
+    });
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Event voided and positions refunded",
+        "void": void_response,
+    })))
+}
+
+/// Close out a short position once it has been zeroed. Its collateral covers
+/// the most it can lose, `PAYOUT_PER_SHARE` less the price the shares were
+/// sold at. The position owes `payout_per_share` on each share: as much of
+/// that as the collateral covers is forfeited and the rest of the collateral
+/// returned, and whatever remains, normally the sale proceeds, is taken from
+/// the wallet in the event's `currency`.
+async fn settle_short(
+    txn: &DatabaseTransaction,
+    position: &user_positions::Model,
+    currency: &str,
+    payout_per_share: Decimal,
+    event_id: i32,
+) -> Result<(), Error> {
+    let shares = -position.quantity;
+    let collateral = margin::collateral(position.average_price, shares);
+    let key = (position.user_id, position.event_id, position.option_id);
+    let reference = format!("event_{}", event_id);
+    let payout = payout_per_share * Decimal::from(shares);
+
+    let forfeited = if payout.is_zero() {
+        Decimal::ZERO
+    } else {
+        margin::forfeit(
+            txn,
+            key,
+            currency,
+            shares,
+            payout.min(collateral),
+            &reference,
+        )
+        .await
+        .map_err(|e| {
+            log::error!("Failed to forfeit short collateral: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to settle collateral")
+        })?
+    };
+    margin::release(txn, key, currency, shares, &reference, None)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to release short collateral: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to release collateral")
+        })?;
+
+    let owed = payout - forfeited;
+    if owed <= Decimal::ZERO {
+        return Ok(());
+    }
+
+    // A wallet that has been spent below what is owed is left negative and
+    // blocks new orders and withdrawals
+    let wallet = balances::lock(txn, position.user_id, currency)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+    let balance_before = wallet.balance;
+    balances::set_balance(txn, wallet, balance_before - owed)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to update user balance: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to update balance")
+        })?;
+
+    transaction::ActiveModel {
+        user_id: Set(position.user_id),
+        r#type: Set("short_settlement".to_string()),
+        amount: Set(owed),
+        currency: Set(currency.to_string()),
+        balance_before: Set(balance_before),
+        balance_after: Set(balance_before - owed),
+        status: Set("completed".to_string()),
+        reference_id: Set(format!("event_{}_{}", event_id, Uuid::new_v4())),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await
+    .map_err(|e| {
+        log::error!("Failed to create transaction record: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to create transaction")
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,614 @@
+use crate::constants::config;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::types::audit::AuditAction;
+use crate::types::export::{
+    ArchiveQuery, ArchiveRecord, ExportCursor, ExportFormat, ExportQuery, ExportRecord,
+    OrderEventArchiveRow, OrderExportRow, SettlementArchiveRow, TradeExportRow, ARCHIVE_SCHEMA,
+    ARCHIVE_SCHEMA_VERSION,
+};
+use crate::utils::auth::get_user_id;
+use crate::utils::cache::CacheService;
+use crate::utils::db_pool::ReportingDb;
+use actix_web::{web, web::Bytes, Error, HttpResponse, Result};
+use chrono::{DateTime, Utc};
+use deadpool_redis::Pool;
+use entity::{audit_logs, order_events, orders, trades};
+use futures::{stream, Future, Stream, StreamExt};
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Rows fetched from the database per streamed chunk
+const EXPORT_PAGE_SIZE: u64 = 1000;
+
+/// Filters shared by every export, resolved from the query and the caller's role
+#[derive(Clone)]
+struct ExportFilter {
+    user_id: Option<i32>,
+    event_id: Option<i32>,
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+pub async fn export_my_trades(
+    db: web::Data<ReportingDb>,
+    redis_pool: web::Data<Pool>,
+    query: web::Query<ExportQuery>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+    export_trades(
+        db.connection().clone(),
+        redis_pool,
+        query.into_inner(),
+        user_id,
+        Some(user_id),
+    )
+    .await
+}
+
+pub async fn export_my_orders(
+    db: web::Data<ReportingDb>,
+    redis_pool: web::Data<Pool>,
+    query: web::Query<ExportQuery>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+    export_orders(
+        db.connection().clone(),
+        redis_pool,
+        query.into_inner(),
+        user_id,
+        Some(user_id),
+    )
+    .await
+}
+
+pub async fn export_all_trades(
+    db: web::Data<ReportingDb>,
+    redis_pool: web::Data<Pool>,
+    query: web::Query<ExportQuery>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let query = query.into_inner();
+    let user_id = query.user_id;
+    export_trades(
+        db.connection().clone(),
+        redis_pool,
+        query,
+        admin_id,
+        user_id,
+    )
+    .await
+}
+
+pub async fn export_all_orders(
+    db: web::Data<ReportingDb>,
+    redis_pool: web::Data<Pool>,
+    query: web::Query<ExportQuery>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let query = query.into_inner();
+    let user_id = query.user_id;
+    export_orders(
+        db.connection().clone(),
+        redis_pool,
+        query,
+        admin_id,
+        user_id,
+    )
+    .await
+}
+
+async fn export_trades(
+    db: DatabaseConnection,
+    redis_pool: web::Data<Pool>,
+    query: ExportQuery,
+    requester_id: i32,
+    user_id: Option<i32>,
+) -> Result<HttpResponse, Error> {
+    if let Some(response) = check_export_rate_limit(&redis_pool, requester_id).await {
+        return Ok(response);
+    }
+
+    let cursor = match &query.after_id {
+        Some(after_id) => {
+            let trade = trades::Entity::find_by_id(after_id.clone())
+                .one(&db)
+                .await
+                .map_err(export_error)?;
+            match trade {
+                Some(t) => Some((t.timestamp, t.id)),
+                None => return Ok(unknown_cursor_response()),
+            }
+        }
+        None => None,
+    };
+
+    let format = query.format.unwrap_or_default();
+    let filter = ExportFilter {
+        user_id,
+        event_id: query.event_id,
+        from: query.from,
+        to: query.to,
+    };
+
+    let body = export_stream(format, cursor, move |cursor| {
+        fetch_trade_page(db.clone(), filter.clone(), cursor)
+    });
+
+    Ok(streaming_response(format, "trades", body))
+}
+
+async fn export_orders(
+    db: DatabaseConnection,
+    redis_pool: web::Data<Pool>,
+    query: ExportQuery,
+    requester_id: i32,
+    user_id: Option<i32>,
+) -> Result<HttpResponse, Error> {
+    if let Some(response) = check_export_rate_limit(&redis_pool, requester_id).await {
+        return Ok(response);
+    }
+
+    let cursor = match &query.after_id {
+        Some(after_id) => {
+            let order = orders::Entity::find_by_id(after_id.clone())
+                .one(&db)
+                .await
+                .map_err(export_error)?;
+            match order {
+                Some(o) => Some((o.created_at, o.id)),
+                None => return Ok(unknown_cursor_response()),
+            }
+        }
+        None => None,
+    };
+
+    let format = query.format.unwrap_or_default();
+    let filter = ExportFilter {
+        user_id,
+        event_id: query.event_id,
+        from: query.from,
+        to: query.to,
+    };
+
+    let body = export_stream(format, cursor, move |cursor| {
+        fetch_order_page(db.clone(), filter.clone(), cursor)
+    });
+
+    Ok(streaming_response(format, "orders", body))
+}
+
+/// Stream the order event log and the settlement runs of a time range as a
+/// versioned ndjson archive, for offline audits and record keeping (staff)
+pub async fn export_archive(
+    db: web::Data<ReportingDb>,
+    redis_pool: web::Data<Pool>,
+    query: web::Query<ArchiveQuery>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let ArchiveQuery { from, to } = query.into_inner();
+    if to <= from {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "to must be after from"
+        })));
+    }
+    if let Some(response) = check_export_rate_limit(&redis_pool, admin_id).await {
+        return Ok(response);
+    }
+
+    let db = db.connection().clone();
+    let order_event_count = Arc::new(AtomicU64::new(0));
+    let settlement_count = Arc::new(AtomicU64::new(0));
+
+    let header = ArchiveRecord::Header {
+        schema: ARCHIVE_SCHEMA,
+        schema_version: ARCHIVE_SCHEMA_VERSION,
+        from,
+        to,
+        generated_at: Utc::now(),
+    };
+    let order_events = archive_section(order_event_count.clone(), {
+        let db = db.clone();
+        move |after_id| fetch_order_event_page(db.clone(), from, to, after_id)
+    });
+    let settlements = archive_section(settlement_count.clone(), move |after_id| {
+        fetch_settlement_page(db.clone(), from, to, after_id)
+    });
+    // Written last, so offline tools can tell a complete archive from a
+    // truncated one
+    let trailer = stream::once(async move {
+        archive_line(&ArchiveRecord::Trailer {
+            order_events: order_event_count.load(Ordering::Relaxed),
+            settlements: settlement_count.load(Ordering::Relaxed),
+        })
+    });
+
+    let body = stream::once(async move { archive_line(&header) })
+        .chain(order_events)
+        .chain(settlements)
+        .chain(trailer);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ExportFormat::Ndjson.content_type())
+        .insert_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"archive-{}-{}.ndjson\"",
+                from.format("%Y%m%dT%H%M%SZ"),
+                to.format("%Y%m%dT%H%M%SZ")
+            ),
+        ))
+        .insert_header((
+            "X-Archive-Schema-Version",
+            ARCHIVE_SCHEMA_VERSION.to_string(),
+        ))
+        .streaming(body))
+}
+
+/// Count the export against the user's hourly allowance, returning a 429 once it's used up
+async fn check_export_rate_limit(redis_pool: &Pool, user_id: i32) -> Option<HttpResponse> {
+    let cache_service = CacheService::new(redis_pool.clone());
+    let key = format!("export_rate:{}", user_id);
+
+    let count = match cache_service.increment(&key, 1).await {
+        Ok(count) => count,
+        Err(e) => {
+            // Exports stay available if Redis is down
+            log::warn!("Failed to check export rate limit: {}", e);
+            return None;
+        }
+    };
+    if count == 1 {
+        if let Err(e) = cache_service.expire(&key, 3600).await {
+            log::warn!("Failed to set export rate limit window: {}", e);
+        }
+    }
+
+    let limit = config::get_export_rate_limit_per_hour();
+    (count > limit as i64).then(|| {
+        HttpResponse::TooManyRequests().json(json!({
+            "success": false,
+            "message": format!("Export limit of {} per hour reached", limit)
+        }))
+    })
+}
+
+fn unknown_cursor_response() -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "message": "after_id does not match an exported row"
+    }))
+}
+
+fn export_error(e: DbErr) -> Error {
+    log::error!("Export query failed: {}", e);
+    actix_web::error::ErrorInternalServerError("Export failed")
+}
+
+fn streaming_response(
+    format: ExportFormat,
+    name: &str,
+    body: impl Stream<Item = Result<Bytes, Error>> + 'static,
+) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}.{}\"", name, format.extension()),
+        ))
+        .streaming(body)
+}
+
+/// Stream rows page by page using keyset pagination, so an export is never
+/// held in memory beyond a single page
+fn export_stream<R, F, Fut>(
+    format: ExportFormat,
+    cursor: Option<ExportCursor>,
+    fetch_page: F,
+) -> impl Stream<Item = Result<Bytes, Error>>
+where
+    R: ExportRecord,
+    F: FnMut(Option<ExportCursor>) -> Fut,
+    Fut: Future<Output = Result<Vec<R>, DbErr>>,
+{
+    stream::unfold(Some((fetch_page, cursor, true)), move |state| async move {
+        let (mut fetch_page, cursor, first) = state?;
+
+        let rows = match fetch_page(cursor).await {
+            Ok(rows) => rows,
+            Err(e) => return Some((Err(export_error(e)), None)),
+        };
+
+        let mut chunk = String::new();
+        if first && format == ExportFormat::Csv {
+            chunk.push_str(R::CSV_HEADER);
+            chunk.push('\n');
+        }
+        for row in &rows {
+            match format {
+                ExportFormat::Csv => chunk.push_str(&row.csv_record()),
+                ExportFormat::Ndjson => match serde_json::to_string(row) {
+                    Ok(line) => chunk.push_str(&line),
+                    Err(e) => {
+                        log::error!("Failed to serialize export row: {}", e);
+                        return Some((
+                            Err(actix_web::error::ErrorInternalServerError("Export failed")),
+                            None,
+                        ));
+                    }
+                },
+            }
+            chunk.push('\n');
+        }
+
+        if chunk.is_empty() {
+            return None;
+        }
+
+        let next = if rows.len() < EXPORT_PAGE_SIZE as usize {
+            None
+        } else {
+            rows.last()
+                .map(|row| (fetch_page, Some(row.cursor()), false))
+        };
+
+        Some((Ok(Bytes::from(chunk)), next))
+    })
+}
+
+fn archive_line(record: &ArchiveRecord) -> Result<Bytes, Error> {
+    let mut line = serde_json::to_string(record).map_err(|e| {
+        log::error!("Failed to serialize archive record: {}", e);
+        actix_web::error::ErrorInternalServerError("Export failed")
+    })?;
+    line.push('\n');
+    Ok(Bytes::from(line))
+}
+
+/// Stream one kind of archive record page by page in id order, counting the
+/// records written
+fn archive_section<F, Fut>(
+    count: Arc<AtomicU64>,
+    fetch_page: F,
+) -> impl Stream<Item = Result<Bytes, Error>>
+where
+    F: FnMut(Option<i64>) -> Fut,
+    Fut: Future<Output = Result<Vec<(i64, ArchiveRecord)>, DbErr>>,
+{
+    stream::unfold(Some((fetch_page, None)), move |state| {
+        let count = count.clone();
+        async move {
+            let (mut fetch_page, after_id) = state?;
+
+            let records = match fetch_page(after_id).await {
+                Ok(records) => records,
+                Err(e) => return Some((Err(export_error(e)), None)),
+            };
+            let last_id = records.last().map(|(id, _)| *id)?;
+
+            let mut chunk = Vec::new();
+            for (_, record) in &records {
+                match archive_line(record) {
+                    Ok(line) => chunk.extend_from_slice(&line),
+                    Err(e) => return Some((Err(e), None)),
+                }
+            }
+            count.fetch_add(records.len() as u64, Ordering::Relaxed);
+
+            let next =
+                (records.len() == EXPORT_PAGE_SIZE as usize).then_some((fetch_page, Some(last_id)));
+            Some((Ok(Bytes::from(chunk)), next))
+        }
+    })
+}
+
+async fn fetch_order_event_page(
+    db: DatabaseConnection,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    after_id: Option<i64>,
+) -> Result<Vec<(i64, ArchiveRecord)>, DbErr> {
+    let mut query = order_events::Entity::find()
+        .find_also_related(orders::Entity)
+        .filter(order_events::Column::CreatedAt.gte(from))
+        .filter(order_events::Column::CreatedAt.lt(to));
+    if let Some(after_id) = after_id {
+        query = query.filter(order_events::Column::Id.gt(after_id));
+    }
+
+    let rows = query
+        .order_by_asc(order_events::Column::Id)
+        .limit(EXPORT_PAGE_SIZE)
+        .all(&db)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(e, order)| {
+            let row = OrderEventArchiveRow {
+                id: e.id,
+                order_id: e.order_id,
+                user_id: order.as_ref().map(|o| o.user_id),
+                event_id: order.as_ref().map(|o| o.event_id),
+                option_id: order.as_ref().map(|o| o.option_id),
+                side: order.map(|o| o.side),
+                kind: e.kind,
+                status: e.status,
+                price: e.price,
+                quantity: e.quantity,
+                filled_quantity: e.filled_quantity,
+                trade_id: e.trade_id,
+                created_at: e.created_at,
+            };
+            (row.id, ArchiveRecord::OrderEvent(row))
+        })
+        .collect())
+}
+
+async fn fetch_settlement_page(
+    db: DatabaseConnection,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    after_id: Option<i64>,
+) -> Result<Vec<(i64, ArchiveRecord)>, DbErr> {
+    let mut query = audit_logs::Entity::find()
+        .filter(audit_logs::Column::Action.is_in([
+            AuditAction::EventSettle.as_str(),
+            AuditAction::EventVoid.as_str(),
+        ]))
+        .filter(audit_logs::Column::CreatedAt.gte(from))
+        .filter(audit_logs::Column::CreatedAt.lt(to));
+    if let Some(after_id) = after_id {
+        query = query.filter(audit_logs::Column::Id.gt(after_id));
+    }
+
+    let rows = query
+        .order_by_asc(audit_logs::Column::Id)
+        .limit(EXPORT_PAGE_SIZE)
+        .all(&db)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|entry| {
+            let row = SettlementArchiveRow {
+                id: entry.id,
+                action: entry.action,
+                event_id: entry.target_id,
+                actor_id: entry.actor_id,
+                before: entry.before,
+                after: entry.after,
+                created_at: entry.created_at,
+            };
+            (row.id, ArchiveRecord::Settlement(row))
+        })
+        .collect())
+}
+
+async fn fetch_trade_page(
+    db: DatabaseConnection,
+    filter: ExportFilter,
+    cursor: Option<ExportCursor>,
+) -> Result<Vec<TradeExportRow>, DbErr> {
+    let mut query = trades::Entity::find();
+
+    if let Some(user_id) = filter.user_id {
+        query = query.filter(
+            Condition::any()
+                .add(trades::Column::BuyerId.eq(user_id))
+                .add(trades::Column::SellerId.eq(user_id)),
+        );
+    }
+    if let Some(event_id) = filter.event_id {
+        query = query.filter(trades::Column::EventId.eq(event_id));
+    }
+    if let Some(from) = filter.from {
+        query = query.filter(trades::Column::Timestamp.gte(from));
+    }
+    if let Some(to) = filter.to {
+        query = query.filter(trades::Column::Timestamp.lt(to));
+    }
+    if let Some((timestamp, id)) = cursor {
+        query = query.filter(
+            Condition::any()
+                .add(trades::Column::Timestamp.gt(timestamp))
+                .add(
+                    Condition::all()
+                        .add(trades::Column::Timestamp.eq(timestamp))
+                        .add(trades::Column::Id.gt(id)),
+                ),
+        );
+    }
+
+    let trades = query
+        .order_by_asc(trades::Column::Timestamp)
+        .order_by_asc(trades::Column::Id)
+        .limit(EXPORT_PAGE_SIZE)
+        .all(&db)
+        .await?;
+
+    Ok(trades
+        .into_iter()
+        .map(|t| TradeExportRow {
+            id: t.id,
+            event_id: t.event_id,
+            option_id: t.option_id,
+            buyer_id: t.buyer_id,
+            seller_id: t.seller_id,
+            buy_order_id: t.buy_order_id,
+            sell_order_id: t.sell_order_id,
+            price: t.price,
+            quantity: t.quantity,
+            total_amount: t.total_amount,
+            timestamp: t.timestamp,
+        })
+        .collect())
+}
+
+async fn fetch_order_page(
+    db: DatabaseConnection,
+    filter: ExportFilter,
+    cursor: Option<ExportCursor>,
+) -> Result<Vec<OrderExportRow>, DbErr> {
+    let mut query = orders::Entity::find();
+
+    if let Some(user_id) = filter.user_id {
+        query = query.filter(orders::Column::UserId.eq(user_id));
+    }
+    if let Some(event_id) = filter.event_id {
+        query = query.filter(orders::Column::EventId.eq(event_id));
+    }
+    if let Some(from) = filter.from {
+        query = query.filter(orders::Column::CreatedAt.gte(from));
+    }
+    if let Some(to) = filter.to {
+        query = query.filter(orders::Column::CreatedAt.lt(to));
+    }
+    if let Some((created_at, id)) = cursor {
+        query = query.filter(
+            Condition::any()
+                .add(orders::Column::CreatedAt.gt(created_at))
+                .add(
+                    Condition::all()
+                        .add(orders::Column::CreatedAt.eq(created_at))
+                        .add(orders::Column::Id.gt(id)),
+                ),
+        );
+    }
+
+    let orders = query
+        .order_by_asc(orders::Column::CreatedAt)
+        .order_by_asc(orders::Column::Id)
+        .limit(EXPORT_PAGE_SIZE)
+        .all(&db)
+        .await?;
+
+    Ok(orders
+        .into_iter()
+        .map(|o| OrderExportRow {
+            id: o.id,
+            user_id: o.user_id,
+            event_id: o.event_id,
+            option_id: o.option_id,
+            side: o.side,
+            order_type: o.order_type,
+            time_in_force: o.time_in_force,
+            price: o.price,
+            quantity: o.quantity,
+            filled_quantity: o.filled_quantity,
+            status: o.status,
+            created_at: o.created_at,
+            updated_at: o.updated_at,
+        })
+        .collect())
+}
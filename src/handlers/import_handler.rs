@@ -0,0 +1,195 @@
+use crate::constants::config;
+use crate::imports::service;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::types::import::{ImportQuery, ImportReport, ImportRequest};
+use crate::utils::audit;
+use crate::utils::auth::get_user_id;
+use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
+use crate::websocket::server::WebSocketServer;
+use actix::Addr;
+use actix_web::{http::header, web, Error, HttpRequest, HttpResponse, Result};
+use deadpool_redis::Pool;
+use entity::{legacy_imports, tenants};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde_json::json;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+fn bad_request(message: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "message": message
+    }))
+}
+
+/// An earlier import with this id in the tenant
+async fn find_import(
+    db: &DatabaseConnection,
+    tenant_id: i32,
+    import_id: &str,
+) -> Result<Option<legacy_imports::Model>, Error> {
+    legacy_imports::Entity::find()
+        .filter(legacy_imports::Column::ImportId.eq(import_id))
+        .filter(legacy_imports::Column::TenantId.eq(tenant_id))
+        .one(db)
+        .await
+        .map_err(database_error)
+}
+
+/// Import users, balances and positions from another system, as CSV with a
+/// header row or as JSON. Validates every record and applies nothing unless
+/// all of them pass; `dry_run` only validates.
+#[allow(clippy::too_many_arguments)]
+pub async fn import_accounts(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    tenant: web::ReqData<tenants::Model>,
+    query: web::Query<ImportQuery>,
+    body: web::Bytes,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let query = query.into_inner();
+
+    if query.import_id.is_empty()
+        || query.import_id.len() > 64
+        || !query
+            .import_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Ok(bad_request(
+            "import_id must be 1 to 64 letters, digits, dashes or underscores",
+        ));
+    }
+
+    let content_type = http_req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    let records = match content_type.as_str() {
+        "text/csv" => {
+            let Ok(text) = std::str::from_utf8(&body) else {
+                return Ok(bad_request("CSV must be UTF-8"));
+            };
+            match service::parse_csv(text) {
+                Ok(records) => records,
+                Err(message) => return Ok(bad_request(&message)),
+            }
+        }
+        "application/json" => match serde_json::from_slice::<ImportRequest>(&body) {
+            Ok(request) => request.records,
+            Err(e) => return Ok(bad_request(&format!("Invalid import: {}", e))),
+        },
+        _ => {
+            return Ok(bad_request(
+                "Content-Type must be text/csv or application/json",
+            ))
+        }
+    };
+    if records.is_empty() {
+        return Ok(bad_request("Import has no records"));
+    }
+    let max_rows = config::get_import_max_rows();
+    if records.len() > max_rows {
+        return Ok(bad_request(&format!(
+            "Import has {} records; at most {} are allowed",
+            records.len(),
+            max_rows
+        )));
+    }
+
+    if find_import(&db, tenant.id, &query.import_id)
+        .await?
+        .is_some()
+    {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": format!("Import {} has already been applied", query.import_id)
+        })));
+    }
+
+    let (report, user_ids) = service::import(
+        &db,
+        tenant.id,
+        admin_id,
+        audit::client_ip(&http_req),
+        &query.import_id,
+        query.dry_run,
+        records,
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Import {} failed: {}", query.import_id, e);
+        actix_web::error::ErrorInternalServerError("Failed to import")
+    })?;
+
+    if !report.errors.is_empty() {
+        return Ok(HttpResponse::UnprocessableEntity().json(json!({
+            "success": false,
+            "message": format!("{} rows can't be imported; nothing was applied", report.errors.len()),
+            "report": report
+        })));
+    }
+
+    if report.applied {
+        log::warn!(
+            "User {} imported {} users ({} created) with {} positions as {}",
+            admin_id,
+            user_ids.len(),
+            report.users_created,
+            report.positions_created,
+            report.import_id
+        );
+
+        let cache_service = CacheService::new(redis_pool.get_ref().clone());
+        for user_id in user_ids {
+            let user_cache_key = create_cache_key(cache_keys::USER_PREFIX, &user_id.to_string());
+            if let Err(e) = cache_service.delete(&user_cache_key).await {
+                log::warn!("Failed to invalidate user cache: {}", e);
+            }
+            let _ = cache_service
+                .delete(&format!("portfolio:{}", user_id))
+                .await;
+            ws_server.do_send(crate::websocket::server::BroadcastTransactionsUpdate { user_id });
+            ws_server.do_send(crate::websocket::server::BroadcastPortfolioUpdate { user_id });
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "report": report
+    })))
+}
+
+/// The report of an applied import
+pub async fn get_import(
+    db: web::Data<DatabaseConnection>,
+    tenant: web::ReqData<tenants::Model>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let import_id = path.into_inner();
+
+    let Some(import) = find_import(&db, tenant.id, &import_id).await? else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Import not found"
+        })));
+    };
+    let report: ImportReport = serde_json::from_value(import.report).map_err(database_error)?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "report": report,
+        "created_by": import.created_by,
+        "created_at": import.created_at
+    })))
+}
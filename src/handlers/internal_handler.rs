@@ -0,0 +1,65 @@
+use crate::middleware::internal_auth::InternalService;
+use crate::tiers::service::TierService;
+use crate::websocket::server::{
+    BroadcastEventsUpdate, BroadcastPortfolioUpdate, BroadcastRiskUpdate,
+    BroadcastTransactionsUpdate, WebSocketServer,
+};
+use actix::Addr;
+use actix_web::{web, Error, HttpResponse, Result};
+use sea_orm::DatabaseConnection;
+use serde_json::json;
+
+/// Refresh every `events` subscriber's listing (internal)
+pub async fn broadcast_events(
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    service: web::ReqData<InternalService>,
+) -> Result<HttpResponse, Error> {
+    log::info!("{} triggered an events broadcast", service.0);
+    ws_server.do_send(BroadcastEventsUpdate);
+
+    Ok(HttpResponse::Accepted().json(json!({
+        "success": true,
+        "message": "Events broadcast queued"
+    })))
+}
+
+/// Push fresh portfolio, transactions and risk data to a user's subscribed
+/// sessions (internal)
+pub async fn broadcast_user(
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    user_id: web::Path<i32>,
+    service: web::ReqData<InternalService>,
+) -> Result<HttpResponse, Error> {
+    let user_id = user_id.into_inner();
+    log::info!("{} triggered a broadcast for user {}", service.0, user_id);
+    ws_server.do_send(BroadcastPortfolioUpdate { user_id });
+    ws_server.do_send(BroadcastTransactionsUpdate { user_id });
+    ws_server.do_send(BroadcastRiskUpdate { user_id });
+
+    Ok(HttpResponse::Accepted().json(json!({
+        "success": true,
+        "message": "User broadcast queued"
+    })))
+}
+
+/// Recalculate every user's tier now instead of waiting for the nightly run
+/// (internal)
+pub async fn run_tier_recalculation(
+    db: web::Data<DatabaseConnection>,
+    service: web::ReqData<InternalService>,
+) -> Result<HttpResponse, Error> {
+    log::info!("{} triggered a tier recalculation", service.0);
+    let changed = TierService::new(db.get_ref().clone())
+        .recalculate_all(chrono::Utc::now())
+        .await
+        .map_err(|e| {
+            log::error!("Tier recalculation failed: {}", e);
+            actix_web::error::ErrorInternalServerError("Tier recalculation failed")
+        })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Tier recalculation finished",
+        "changed": changed
+    })))
+}
@@ -0,0 +1,680 @@
+use crate::constants::config;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::types::audit::AuditAction;
+use crate::types::kyc::{
+    document_status, AgeStatus, KycDocumentResponse, KycStatus, KycUserResponse, ListKycQuery,
+    OverrideAgeRequest, RejectKycRequest, SetDateOfBirthRequest, UploadKycDocumentQuery,
+    ALLOWED_CONTENT_TYPES,
+};
+use crate::utils::audit::{self, AuditEntry};
+use crate::utils::auth::get_user_id;
+use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
+use crate::utils::kyc;
+use crate::utils::pagination::{PaginatedResponse, PaginationInfo};
+use actix_web::{http::header, web, Error, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use deadpool_redis::Pool;
+use entity::{kyc_documents, users};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction,
+    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+fn bad_request(message: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "message": message
+    }))
+}
+
+fn user_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "message": "User not found"
+    }))
+}
+
+fn status_of(user: &users::Model) -> KycStatus {
+    KycStatus::parse(&user.kyc_status).unwrap_or(KycStatus::Unverified)
+}
+
+fn age_status_of(user: &users::Model) -> AgeStatus {
+    AgeStatus::parse(&user.age_status).unwrap_or(AgeStatus::Unverified)
+}
+
+/// The user, locked for the rest of the transaction
+async fn lock_user(txn: &DatabaseTransaction, user_id: i32) -> Result<Option<users::Model>, Error> {
+    users::Entity::find_by_id(user_id)
+        .lock_exclusive()
+        .one(txn)
+        .await
+        .map_err(database_error)
+}
+
+/// A user's documents without their content, newest first
+async fn list_documents(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Vec<KycDocumentResponse>, Error> {
+    Ok(kyc_documents::Entity::find()
+        .select_only()
+        .columns([
+            kyc_documents::Column::Id,
+            kyc_documents::Column::UserId,
+            kyc_documents::Column::DocumentType,
+            kyc_documents::Column::ContentType,
+            kyc_documents::Column::SizeBytes,
+            kyc_documents::Column::Sha256,
+            kyc_documents::Column::Status,
+            kyc_documents::Column::RejectionReason,
+            kyc_documents::Column::ReviewedBy,
+            kyc_documents::Column::ReviewedAt,
+            kyc_documents::Column::CreatedAt,
+        ])
+        .column_as(Expr::cust("''::bytea"), "content")
+        .filter(kyc_documents::Column::UserId.eq(user_id))
+        .order_by_desc(kyc_documents::Column::Id)
+        .all(db)
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(KycDocumentResponse::from)
+        .collect())
+}
+
+async fn invalidate_user_cache(redis_pool: &Pool, user_id: i32) {
+    let cache_service = CacheService::new(redis_pool.clone());
+    let user_cache_key = create_cache_key(cache_keys::USER_PREFIX, &user_id.to_string());
+    if let Err(e) = cache_service.delete(&user_cache_key).await {
+        log::warn!("Failed to invalidate user cache: {}", e);
+    }
+}
+
+/// The caller's verification status and documents
+pub async fn get_my_kyc(
+    db: web::Data<DatabaseConnection>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+
+    let Some(user) = users::Entity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(user_not_found());
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "kyc_status": status_of(&user),
+        "date_of_birth": user.date_of_birth,
+        "age_status": age_status_of(&user),
+        "minimum_age": kyc::minimum_age(Some(user.tenant_id)),
+        "documents": list_documents(db.get_ref(), user_id).await?
+    })))
+}
+
+/// Give or correct the caller's date of birth. It can't be changed once
+/// identity verification has been submitted, or after the age check has been
+/// settled either way.
+pub async fn set_date_of_birth(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    req: web::Json<SetDateOfBirthRequest>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+    let date_of_birth = req.date_of_birth;
+    if !kyc::is_plausible_date_of_birth(date_of_birth) {
+        return Ok(bad_request(
+            "date_of_birth is not a plausible date of birth",
+        ));
+    }
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let Some(user) = lock_user(&txn, user_id).await? else {
+        return Ok(user_not_found());
+    };
+    let age_status = age_status_of(&user);
+    if !age_status.can_declare() || !status_of(&user).can_submit() {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "Your date of birth can no longer be changed; contact support to correct it"
+        })));
+    }
+
+    let tenant_id = Some(user.tenant_id);
+    let age_status = kyc::declared_age_status(date_of_birth, tenant_id);
+    let mut active_user: users::ActiveModel = user.into();
+    active_user.date_of_birth = Set(Some(date_of_birth));
+    active_user.age_status = Set(age_status.as_str().to_string());
+    active_user.updated_at = Set(Utc::now().naive_utc());
+    active_user.update(&txn).await.map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    log::info!("User {} declared their date of birth", user_id);
+    invalidate_user_cache(redis_pool.get_ref(), user_id).await;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "date_of_birth": date_of_birth,
+        "age_status": age_status,
+        "minimum_age": kyc::minimum_age(tenant_id)
+    })))
+}
+
+/// Upload one identity document as the raw request body. Documents wait as
+/// `uploaded` until the user submits them for review.
+pub async fn upload_kyc_document(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<UploadKycDocumentQuery>,
+    body: web::Bytes,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+
+    let content_type = http_req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    if !ALLOWED_CONTENT_TYPES.contains(&content_type.as_str()) {
+        return Ok(bad_request(
+            "Content-Type must be image/jpeg, image/png or application/pdf",
+        ));
+    }
+    if body.is_empty() {
+        return Ok(bad_request("Document is empty"));
+    }
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let Some(user) = lock_user(&txn, user_id).await? else {
+        return Ok(user_not_found());
+    };
+    let status = status_of(&user);
+    if !status.can_submit() {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": format!("Documents can't be uploaded while verification is {}", status.as_str())
+        })));
+    }
+
+    let uploaded = kyc_documents::Entity::find()
+        .filter(kyc_documents::Column::UserId.eq(user_id))
+        .filter(kyc_documents::Column::Status.eq(document_status::UPLOADED))
+        .count(&txn)
+        .await
+        .map_err(database_error)?;
+    if uploaded >= config::get_kyc_max_documents() {
+        return Ok(bad_request(
+            "Too many documents awaiting submission; submit them for review first",
+        ));
+    }
+
+    let document = kyc_documents::ActiveModel {
+        user_id: Set(user_id),
+        document_type: Set(query.document_type.as_str().to_string()),
+        content_type: Set(content_type),
+        size_bytes: Set(body.len() as i32),
+        sha256: Set(hex::encode(Sha256::digest(&body))),
+        content: Set(body.to_vec()),
+        status: Set(document_status::UPLOADED.to_string()),
+        created_at: Set(Utc::now().fixed_offset()),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    log::info!(
+        "User {} uploaded {} document {}",
+        user_id,
+        document.document_type,
+        document.id
+    );
+
+    Ok(HttpResponse::Created().json(json!({
+        "success": true,
+        "document": KycDocumentResponse::from(document)
+    })))
+}
+
+/// Send the caller's uploaded documents for review
+pub async fn submit_kyc(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let Some(user) = lock_user(&txn, user_id).await? else {
+        return Ok(user_not_found());
+    };
+    let status = status_of(&user);
+    if !status.can_submit() {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": format!("Verification is already {}", status.as_str())
+        })));
+    }
+    // Reviewers check the date of birth against the documents
+    if user.date_of_birth.is_none() {
+        return Ok(bad_request(
+            "Give your date of birth before submitting documents",
+        ));
+    }
+
+    let submitted = kyc_documents::Entity::update_many()
+        .col_expr(
+            kyc_documents::Column::Status,
+            Expr::value(document_status::PENDING),
+        )
+        .filter(kyc_documents::Column::UserId.eq(user_id))
+        .filter(kyc_documents::Column::Status.eq(document_status::UPLOADED))
+        .exec(&txn)
+        .await
+        .map_err(database_error)?
+        .rows_affected;
+    if submitted == 0 {
+        return Ok(bad_request(
+            "Upload at least one document before submitting",
+        ));
+    }
+
+    let mut active_user: users::ActiveModel = user.into();
+    active_user.kyc_status = Set(KycStatus::Pending.as_str().to_string());
+    active_user.updated_at = Set(Utc::now().naive_utc());
+    active_user.update(&txn).await.map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    log::info!(
+        "User {} submitted {} KYC documents for review",
+        user_id,
+        submitted
+    );
+    invalidate_user_cache(redis_pool.get_ref(), user_id).await;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "kyc_status": KycStatus::Pending,
+        "documents_submitted": submitted
+    })))
+}
+
+/// Users by verification status, longest waiting first
+pub async fn list_kyc_users(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<ListKycQuery>,
+) -> Result<HttpResponse, Error> {
+    let status = query.status.unwrap_or(KycStatus::Pending);
+    let users_query = users::Entity::find().filter(users::Column::KycStatus.eq(status.as_str()));
+
+    let page = query.pagination.get_page();
+    let limit = query.pagination.get_limit();
+    let offset = query.pagination.get_offset();
+
+    let total_count = users_query
+        .to_owned()
+        .count(db.get_ref())
+        .await
+        .map_err(database_error)?;
+
+    let users: Vec<KycUserResponse> = users_query
+        .order_by_asc(users::Column::UpdatedAt)
+        .limit(limit)
+        .offset(offset)
+        .all(db.get_ref())
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(KycUserResponse::from)
+        .collect();
+
+    let response = PaginatedResponse::new(users, PaginationInfo::new(page, total_count, limit));
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "users": response.data,
+        "pagination": response.pagination
+    })))
+}
+
+/// A user's verification status and every document they've uploaded
+pub async fn get_user_kyc(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let user_id = path.into_inner();
+
+    let Some(user) = users::Entity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(user_not_found());
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "user": KycUserResponse::from(user),
+        "documents": list_documents(db.get_ref(), user_id).await?
+    })))
+}
+
+/// A document's content, as uploaded
+pub async fn get_kyc_document_content(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let reviewer_id = get_user_id(&auth_user)?;
+    let document_id = path.into_inner();
+
+    let Some(document) = kyc_documents::Entity::find_by_id(document_id)
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Document not found"
+        })));
+    };
+
+    log::info!(
+        "User {} viewed KYC document {} of user {}",
+        reviewer_id,
+        document.id,
+        document.user_id
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type(document.content_type)
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"kyc-{}-{}\"",
+                document.id, document.document_type
+            ),
+        ))
+        .body(document.content))
+}
+
+/// Approve or reject a user's pending submission, recording the decision on
+/// each submitted document
+async fn review(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    reviewer_id: i32,
+    user_id: i32,
+    approved: bool,
+    reason: Option<String>,
+    ip: Option<String>,
+) -> Result<HttpResponse, Error> {
+    if user_id == reviewer_id {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "message": "You can't review your own verification"
+        })));
+    }
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let Some(user) = lock_user(&txn, user_id).await? else {
+        return Ok(user_not_found());
+    };
+    if status_of(&user) != KycStatus::Pending {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "User has no verification awaiting review"
+        })));
+    }
+
+    let now = Utc::now();
+    let age_status_before = age_status_of(&user);
+    // Approval confirms the date of birth the documents were checked against
+    let age_status = match user.date_of_birth {
+        Some(date_of_birth) if approved && age_status_before == AgeStatus::Declared => {
+            if kyc::age(date_of_birth) >= kyc::minimum_age(Some(user.tenant_id)) {
+                AgeStatus::Verified
+            } else {
+                AgeStatus::Rejected
+            }
+        }
+        _ => age_status_before,
+    };
+    let (status, document_status) = if approved {
+        (KycStatus::Verified, document_status::APPROVED)
+    } else {
+        (KycStatus::Rejected, document_status::REJECTED)
+    };
+
+    kyc_documents::Entity::update_many()
+        .col_expr(kyc_documents::Column::Status, Expr::value(document_status))
+        .col_expr(
+            kyc_documents::Column::RejectionReason,
+            Expr::value(reason.clone()),
+        )
+        .col_expr(kyc_documents::Column::ReviewedBy, Expr::value(reviewer_id))
+        .col_expr(
+            kyc_documents::Column::ReviewedAt,
+            Expr::value(now.fixed_offset()),
+        )
+        .filter(kyc_documents::Column::UserId.eq(user_id))
+        .filter(kyc_documents::Column::Status.eq(document_status::PENDING))
+        .exec(&txn)
+        .await
+        .map_err(database_error)?;
+
+    let mut active_user: users::ActiveModel = user.into();
+    active_user.kyc_status = Set(status.as_str().to_string());
+    active_user.age_status = Set(age_status.as_str().to_string());
+    active_user.updated_at = Set(now.naive_utc());
+    active_user.update(&txn).await.map_err(database_error)?;
+
+    let mut after = json!({ "kyc_status": status, "age_status": age_status });
+    if let Some(reason) = reason {
+        after["reason"] = json!(reason);
+    }
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: reviewer_id,
+            action: if approved {
+                AuditAction::KycApprove
+            } else {
+                AuditAction::KycReject
+            },
+            target_id: user_id,
+            before: Some(json!({
+                "kyc_status": KycStatus::Pending,
+                "age_status": age_status_before
+            })),
+            after: Some(after),
+            ip,
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    log::warn!(
+        "User {} {} the verification of user {}",
+        reviewer_id,
+        if approved { "approved" } else { "rejected" },
+        user_id
+    );
+    invalidate_user_cache(redis_pool, user_id).await;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "user_id": user_id,
+        "kyc_status": status,
+        "age_status": age_status
+    })))
+}
+
+/// Mark a user's identity as verified
+pub async fn approve_kyc(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let reviewer_id = get_user_id(&auth_user)?;
+
+    review(
+        db.get_ref(),
+        redis_pool.get_ref(),
+        reviewer_id,
+        path.into_inner(),
+        true,
+        None,
+        audit::client_ip(&http_req),
+    )
+    .await
+}
+
+/// Turn down a user's submission; they may upload new documents and resubmit
+pub async fn reject_kyc(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    req: web::Json<RejectKycRequest>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let reviewer_id = get_user_id(&auth_user)?;
+    let reason = req.into_inner().reason;
+    if reason.trim().is_empty() {
+        return Ok(bad_request("reason is required"));
+    }
+
+    review(
+        db.get_ref(),
+        redis_pool.get_ref(),
+        reviewer_id,
+        path.into_inner(),
+        false,
+        Some(reason.trim().to_string()),
+        audit::client_ip(&http_req),
+    )
+    .await
+}
+
+/// Set a user's age status by hand, for example when their documents show a
+/// different date of birth than they gave
+pub async fn override_age(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    req: web::Json<OverrideAgeRequest>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let reviewer_id = get_user_id(&auth_user)?;
+    let user_id = path.into_inner();
+    let req = req.into_inner();
+
+    let reason = req.reason.trim().to_string();
+    if reason.is_empty() {
+        return Ok(bad_request("reason is required"));
+    }
+    if req
+        .date_of_birth
+        .is_some_and(|date_of_birth| !kyc::is_plausible_date_of_birth(date_of_birth))
+    {
+        return Ok(bad_request(
+            "date_of_birth is not a plausible date of birth",
+        ));
+    }
+    if user_id == reviewer_id {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "message": "You can't override your own age verification"
+        })));
+    }
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let Some(user) = lock_user(&txn, user_id).await? else {
+        return Ok(user_not_found());
+    };
+
+    // Clearing the status lets the user give their date of birth again
+    let date_of_birth = match req.age_status {
+        AgeStatus::Unverified => None,
+        _ => req.date_of_birth.or(user.date_of_birth),
+    };
+    if req.age_status == AgeStatus::Declared && date_of_birth.is_none() {
+        return Ok(bad_request("A declared age status needs a date_of_birth"));
+    }
+
+    let before = json!({
+        "age_status": age_status_of(&user),
+        "date_of_birth": user.date_of_birth
+    });
+
+    let mut active_user: users::ActiveModel = user.into();
+    active_user.date_of_birth = Set(date_of_birth);
+    active_user.age_status = Set(req.age_status.as_str().to_string());
+    active_user.updated_at = Set(Utc::now().naive_utc());
+    active_user.update(&txn).await.map_err(database_error)?;
+
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: reviewer_id,
+            action: AuditAction::AgeOverride,
+            target_id: user_id,
+            before: Some(before),
+            after: Some(json!({
+                "age_status": req.age_status,
+                "date_of_birth": date_of_birth,
+                "reason": reason
+            })),
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    log::warn!(
+        "User {} set the age status of user {} to {}",
+        reviewer_id,
+        user_id,
+        req.age_status.as_str()
+    );
+    invalidate_user_cache(redis_pool.get_ref(), user_id).await;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "user_id": user_id,
+        "date_of_birth": date_of_birth,
+        "age_status": req.age_status
+    })))
+}
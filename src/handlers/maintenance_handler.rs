@@ -0,0 +1,143 @@
+use crate::maintenance::registry;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::types::audit::AuditAction;
+use crate::types::maintenance::{
+    MaintenanceStatusResponse, MaintenanceWindow, ScheduleMaintenanceRequest, DEFAULT_MESSAGE,
+};
+use crate::utils::audit::{self, AuditEntry};
+use crate::utils::auth::get_user_id;
+use actix_web::{web, Error, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use deadpool_redis::Pool;
+use sea_orm::DatabaseConnection;
+use serde_json::json;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+fn cache_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Maintenance window error: {}", e);
+    actix_web::error::ErrorInternalServerError("Failed to update the maintenance window")
+}
+
+fn status() -> MaintenanceStatusResponse {
+    MaintenanceStatusResponse {
+        active: registry::active().is_some(),
+        window: registry::current(),
+    }
+}
+
+/// Get the maintenance window, if one is scheduled or in force
+pub async fn get_maintenance() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": status()
+    })))
+}
+
+/// Schedule a maintenance window, replacing any already scheduled (admin
+/// only). Clients are notified on the next maintenance refresh.
+pub async fn schedule_maintenance(
+    http_req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    req: web::Json<ScheduleMaintenanceRequest>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse> {
+    let admin_id = get_user_id(&auth_user)?;
+    let req = req.into_inner();
+    let now = Utc::now();
+
+    let message = req
+        .message
+        .map(|message| message.trim().to_string())
+        .filter(|message| !message.is_empty())
+        .unwrap_or_else(|| DEFAULT_MESSAGE.to_string());
+    let starts_at = req.starts_at.unwrap_or(now);
+    if req
+        .ends_at
+        .is_some_and(|ends_at| ends_at <= starts_at.max(now))
+    {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "ends_at must be after starts_at and in the future"
+        })));
+    }
+
+    let before = registry::current();
+    let window = MaintenanceWindow {
+        message,
+        starts_at,
+        ends_at: req.ends_at,
+    };
+    registry::schedule(redis_pool.get_ref(), window.clone())
+        .await
+        .map_err(cache_error)?;
+
+    audit::record(
+        db.get_ref(),
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::MaintenanceUpdate,
+            target_id: 0,
+            before: before.map(|window| json!(window)),
+            after: Some(json!(window)),
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    log::warn!(
+        "Maintenance scheduled by admin {} from {} until {:?}",
+        admin_id,
+        window.starts_at,
+        window.ends_at
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Maintenance scheduled",
+        "data": status()
+    })))
+}
+
+/// Clear the scheduled maintenance window, making the API writable again
+/// unless `MAINTENANCE_MODE` is set (admin only)
+pub async fn clear_maintenance(
+    http_req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse> {
+    let admin_id = get_user_id(&auth_user)?;
+
+    let before = registry::current();
+    registry::clear(redis_pool.get_ref())
+        .await
+        .map_err(cache_error)?;
+
+    audit::record(
+        db.get_ref(),
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::MaintenanceUpdate,
+            target_id: 0,
+            before: before.map(|window| json!(window)),
+            after: None,
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    log::warn!("Maintenance cleared by admin {}", admin_id);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Maintenance cleared",
+        "data": status()
+    })))
+}
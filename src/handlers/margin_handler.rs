@@ -0,0 +1,182 @@
+use crate::order_book::margin;
+use crate::settings::registry as settings;
+use crate::types::margin::{
+    MarginAccountResponse, MarginBalanceResponse, MarginLedgerEntry, SetMarginRequest,
+    ShortPositionResponse,
+};
+use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
+use crate::wallets::balances;
+use actix_web::{web, Error, HttpResponse, Result};
+use deadpool_redis::Pool;
+use entity::{events, margin_ledger, user_positions, users};
+use sea_orm::{
+    prelude::Decimal, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set,
+};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Ledger entries returned with the margin account
+const LEDGER_LIMIT: u64 = 50;
+
+pub async fn get_margin_account(
+    db: web::Data<DatabaseConnection>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    let user = users::Entity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("User not found"))?;
+
+    let positions = short_positions(db.get_ref(), user_id).await?;
+    let event_ids: Vec<i32> = positions.iter().map(|p| p.event_id).collect();
+    let currencies: HashMap<i32, String> = events::Entity::find()
+        .select_only()
+        .column(events::Column::Id)
+        .column(events::Column::Currency)
+        .filter(events::Column::Id.is_in(event_ids))
+        .into_tuple::<(i32, String)>()
+        .all(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?
+        .into_iter()
+        .collect();
+
+    let short_positions: Vec<ShortPositionResponse> = positions
+        .into_iter()
+        .map(|position| {
+            let shares = -position.quantity;
+            ShortPositionResponse {
+                event_id: position.event_id,
+                option_id: position.option_id,
+                currency: currencies
+                    .get(&position.event_id)
+                    .cloned()
+                    .unwrap_or_default(),
+                shares,
+                average_price: position.average_price,
+                collateral: margin::collateral(position.average_price, shares),
+            }
+        })
+        .collect();
+
+    let ledger = margin_ledger::Entity::find()
+        .filter(margin_ledger::Column::UserId.eq(user_id))
+        .order_by_desc(margin_ledger::Column::CreatedAt)
+        .order_by_desc(margin_ledger::Column::Id)
+        .limit(LEDGER_LIMIT)
+        .all(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    // Collateral is held per currency, in the wallet of the events shorted
+    let wallets = balances::for_user(db.get_ref(), user_id)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+    let margin_balances = wallets
+        .into_iter()
+        .map(|wallet| {
+            let requirement: Decimal = short_positions
+                .iter()
+                .filter(|p| p.currency == wallet.currency)
+                .map(|p| p.collateral)
+                .sum();
+            MarginBalanceResponse {
+                currency: wallet.currency,
+                margin_balance: wallet.margin_balance,
+                requirement,
+            }
+        })
+        .filter(|b| !b.margin_balance.is_zero() || !b.requirement.is_zero())
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "margin": MarginAccountResponse {
+            enabled: user.margin_enabled,
+            balances: margin_balances,
+            max_short_position: settings::max_short_position(),
+            short_positions,
+            ledger: ledger.into_iter().map(MarginLedgerEntry::from).collect(),
+        }
+    })))
+}
+
+pub async fn set_margin_account(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    req: web::Json<SetMarginRequest>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    let user = users::Entity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("User not found"))?;
+
+    // Collateral stays locked until the shorts are closed, so they must be first
+    if !req.enabled && !short_positions(db.get_ref(), user_id).await?.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Close all short positions before disabling margin trading"
+        })));
+    }
+
+    let mut active_user: users::ActiveModel = user.into();
+    active_user.margin_enabled = Set(req.enabled);
+    active_user.updated_at = Set(chrono::Utc::now().naive_utc());
+    active_user.update(db.get_ref()).await.map_err(|e| {
+        log::error!("Failed to update margin account: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to update margin account")
+    })?;
+
+    let cache_service = CacheService::new(redis_pool.get_ref().clone());
+    let user_cache_key = create_cache_key(cache_keys::USER_PREFIX, &user_id.to_string());
+    if let Err(e) = cache_service.delete(&user_cache_key).await {
+        log::warn!("Failed to invalidate user cache: {}", e);
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "margin_enabled": req.enabled
+    })))
+}
+
+async fn short_positions(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Vec<user_positions::Model>, Error> {
+    user_positions::Entity::find()
+        .filter(user_positions::Column::UserId.eq(user_id))
+        .filter(user_positions::Column::Quantity.lt(0))
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })
+}
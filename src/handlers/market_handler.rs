@@ -0,0 +1,194 @@
+use crate::constants::config;
+use crate::order_book::book_sampler::{self, DepthSample};
+use crate::types::market::{DepthHeatmapResponse, HeatmapQuery};
+use crate::utils::cache::CacheService;
+use actix_web::{web, Error, HttpResponse, Result};
+use chrono::{DateTime, Utc};
+use deadpool_redis::Pool;
+use entity::event_options;
+use sea_orm::{prelude::Decimal, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde_json::json;
+use std::collections::BTreeSet;
+
+/// Columns in a heatmap regardless of window length
+const HEATMAP_BUCKETS: i64 = 60;
+
+const DEFAULT_HEATMAP_WINDOW: &str = "1h";
+
+pub async fn get_depth_heatmap(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+    query: web::Query<HeatmapQuery>,
+) -> Result<HttpResponse, Error> {
+    let (event_id, option_id) = path.into_inner();
+
+    let window = query.window.as_deref().unwrap_or(DEFAULT_HEATMAP_WINDOW);
+    let max_window_seconds = config::get_book_sample_retention_hours() * 3600;
+    let window_seconds = match parse_window(window) {
+        Some(seconds) if seconds > 0 && seconds <= max_window_seconds => seconds,
+        _ => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": format!(
+                    "window must be a duration like 15m, 1h or 24h, up to {}h",
+                    max_window_seconds / 3600
+                )
+            })));
+        }
+    };
+
+    let cache_service = CacheService::new(redis_pool.get_ref().clone());
+    let cache_key = format!("heatmap:{}:{}:{}", event_id, option_id, window_seconds);
+    if let Ok(Some(cached_response)) = cache_service.get::<serde_json::Value>(&cache_key).await {
+        return Ok(HttpResponse::Ok().json(cached_response));
+    }
+
+    let option = event_options::Entity::find_by_id(option_id)
+        .filter(event_options::Column::EventId.eq(event_id))
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    if option.is_none() {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Event option not found"
+        })));
+    }
+
+    // Buckets are never finer than the sampler, or most of them would be empty
+    let sample_interval = config::get_book_sample_interval_seconds() as i64;
+    let bucket_seconds = (window_seconds / HEATMAP_BUCKETS)
+        .max(sample_interval)
+        .max(1);
+    let now_ms = Utc::now().timestamp_millis();
+    let from_ms = now_ms - window_seconds * 1000;
+
+    let samples = book_sampler::load_samples(redis_pool.get_ref(), event_id, option_id, from_ms)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to load depth samples: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to load depth samples")
+        })?;
+
+    let heatmap = build_heatmap(
+        event_id,
+        option_id,
+        window_seconds,
+        bucket_seconds,
+        from_ms,
+        &samples,
+    );
+
+    let response_json = json!({
+        "success": true,
+        "heatmap": heatmap
+    });
+
+    // Samples only change once per sampler tick
+    if let Err(e) = cache_service
+        .set(&cache_key, &response_json, sample_interval.max(1) as u64)
+        .await
+    {
+        log::warn!("Failed to cache heatmap: {}", e);
+    }
+
+    Ok(HttpResponse::Ok().json(response_json))
+}
+
+/// Parse a window like `90s`, `15m`, `1h` or `1d` into seconds
+fn parse_window(window: &str) -> Option<i64> {
+    let unit = window.chars().last()?;
+    let multiplier = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        _ => return None,
+    };
+    window[..window.len() - 1]
+        .parse::<i64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+}
+
+/// Average the samples in each bucket onto a shared price axis. A bucket with
+/// no samples repeats the previous bucket, since the book didn't change shape
+/// in a way the sampler saw.
+fn build_heatmap(
+    event_id: i32,
+    option_id: i32,
+    window_seconds: i64,
+    bucket_seconds: i64,
+    from_ms: i64,
+    samples: &[DepthSample],
+) -> DepthHeatmapResponse {
+    let prices: Vec<Decimal> = samples
+        .iter()
+        .flat_map(|s| s.bids.iter().chain(&s.asks).map(|(price, _)| *price))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let price_index = |price: &Decimal| prices.binary_search(price).ok();
+
+    let bucket_count = ((window_seconds + bucket_seconds - 1) / bucket_seconds) as usize;
+    let bucket_ms = bucket_seconds * 1000;
+
+    let mut bid_totals = vec![vec![0i64; prices.len()]; bucket_count];
+    let mut ask_totals = vec![vec![0i64; prices.len()]; bucket_count];
+    let mut sample_counts = vec![0i64; bucket_count];
+
+    for sample in samples {
+        let bucket = ((sample.timestamp_ms - from_ms).max(0) / bucket_ms) as usize;
+        let Some(count) = sample_counts.get_mut(bucket) else {
+            continue;
+        };
+        *count += 1;
+        for (price, quantity) in &sample.bids {
+            if let Some(i) = price_index(price) {
+                bid_totals[bucket][i] += *quantity as i64;
+            }
+        }
+        for (price, quantity) in &sample.asks {
+            if let Some(i) = price_index(price) {
+                ask_totals[bucket][i] += *quantity as i64;
+            }
+        }
+    }
+
+    let mut timestamps = Vec::with_capacity(bucket_count);
+    let mut bid_depth: Vec<Vec<i32>> = Vec::with_capacity(bucket_count);
+    let mut ask_depth: Vec<Vec<i32>> = Vec::with_capacity(bucket_count);
+
+    for bucket in 0..bucket_count {
+        let start_ms = from_ms + bucket as i64 * bucket_ms;
+        timestamps.push(DateTime::from_timestamp_millis(start_ms).unwrap_or_default());
+
+        let count = sample_counts[bucket];
+        if count == 0 {
+            let empty = vec![0; prices.len()];
+            bid_depth.push(bid_depth.last().cloned().unwrap_or_else(|| empty.clone()));
+            ask_depth.push(ask_depth.last().cloned().unwrap_or(empty));
+            continue;
+        }
+
+        let average = |totals: &Vec<i64>| totals.iter().map(|t| (t / count) as i32).collect();
+        bid_depth.push(average(&bid_totals[bucket]));
+        ask_depth.push(average(&ask_totals[bucket]));
+    }
+
+    DepthHeatmapResponse {
+        event_id,
+        option_id,
+        window_seconds,
+        bucket_seconds,
+        prices,
+        timestamps,
+        bid_depth,
+        ask_depth,
+    }
+}
@@ -0,0 +1,462 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::order_book::market_maker::{self, QuotingState};
+use crate::order_book::market_maker_report;
+use crate::order_book::market_stats::MarketStatsService;
+use crate::order_book::{matching_service, MarketMaker, MarketMakerConfig};
+use crate::types::audit::AuditAction;
+use crate::types::event_option::LiquidityConfig;
+use crate::types::market_maker::{MarketMakerReportQuery, ReseedMarketMakerRequest};
+use crate::types::order_book::OrderResponse;
+use crate::utils::audit::{self, AuditEntry};
+use crate::utils::auth::get_user_id;
+use actix_web::{web, Error, HttpRequest, HttpResponse, Result};
+use deadpool_redis::Pool;
+use entity::{event_options, events};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde_json::json;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+fn market_maker_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Market maker error: {}", e);
+    actix_web::error::ErrorInternalServerError("Failed to update the market maker")
+}
+
+/// The option and its event, if the option belongs to the event
+async fn load_option(
+    db: &DatabaseConnection,
+    event_id: i32,
+    option_id: i32,
+) -> Result<Option<(events::Model, event_options::Model)>, Error> {
+    let option = event_options::Entity::find_by_id(option_id)
+        .filter(event_options::Column::EventId.eq(event_id))
+        .one(db)
+        .await
+        .map_err(database_error)?;
+    let Some(option) = option else {
+        return Ok(None);
+    };
+    let event = events::Entity::find_by_id(event_id)
+        .one(db)
+        .await
+        .map_err(database_error)?;
+    Ok(event.map(|event| (event, option)))
+}
+
+fn option_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "message": "Event option not found"
+    }))
+}
+
+fn not_quoted() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "message": "Market maker has not quoted this option"
+    }))
+}
+
+/// The config and on/off state of a market, for audit log payloads
+fn state_snapshot(state: &QuotingState) -> serde_json::Value {
+    json!({
+        "enabled": state.enabled,
+        "config": state.config,
+        "seeded_shares": state.seeded_shares,
+    })
+}
+
+async fn record_change(
+    db: &DatabaseConnection,
+    http_req: &HttpRequest,
+    admin_id: i32,
+    event_id: i32,
+    before: Option<&QuotingState>,
+    after: Option<&QuotingState>,
+) -> Result<(), Error> {
+    audit::record(
+        db,
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::MarketMakerUpdate,
+            target_id: event_id,
+            before: before.map(state_snapshot),
+            after: after.map(state_snapshot),
+            ip: audit::client_ip(http_req),
+        },
+    )
+    .await
+    .map_err(database_error)
+}
+
+/// The market maker's config, resting quotes and P&L on an option (staff)
+pub async fn get_market_maker(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, Error> {
+    let (event_id, option_id) = path.into_inner();
+    if load_option(db.get_ref(), event_id, option_id)
+        .await?
+        .is_none()
+    {
+        return Ok(option_not_found());
+    }
+    let Some(state) = market_maker::quoting_state(&redis_pool, event_id, option_id)
+        .await
+        .map_err(market_maker_error)?
+    else {
+        return Ok(not_quoted());
+    };
+
+    let order_ids = state.quotes.order_ids.clone();
+    let quotes: Vec<OrderResponse> =
+        matching_service::query(&redis_pool, event_id, option_id, move |book| {
+            let (_, _, orders, _) = book.get_internal_state();
+            order_ids
+                .iter()
+                .filter_map(|id| orders.get(id).cloned())
+                .collect::<Vec<_>>()
+        })
+        .await
+        .map_err(market_maker_error)?
+        .into_iter()
+        .map(OrderResponse::from)
+        .collect();
+
+    let market_maker = MarketMaker::new(
+        state.config.clone(),
+        db.get_ref().clone(),
+        redis_pool.get_ref().clone(),
+    );
+    let pnl = market_maker
+        .pnl(event_id, option_id, state.seeded_shares)
+        .await
+        .map_err(market_maker_error)?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "enabled": state.enabled,
+        "config": state.config,
+        "reference_price": state.quotes.reference_price,
+        "quotes": quotes,
+        "pnl": pnl
+    })))
+}
+
+/// Start quoting an option, with its previous config or else the `mm_*`
+/// settings (staff)
+pub async fn enable_market_maker(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let (event_id, option_id) = path.into_inner();
+    let Some((event, option)) = load_option(db.get_ref(), event_id, option_id).await? else {
+        return Ok(option_not_found());
+    };
+
+    let before = market_maker::quoting_state(&redis_pool, event_id, option_id)
+        .await
+        .map_err(market_maker_error)?;
+    if before.as_ref().is_some_and(|state| state.enabled) {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "Market maker is already quoting this option"
+        })));
+    }
+
+    let (config, seeded_shares) = match &before {
+        Some(state) => (state.config.clone(), state.seeded_shares),
+        None => (
+            MarketMakerConfig::from_settings(Some(event.tenant_id), admin_id, option.current_price),
+            0,
+        ),
+    };
+    MarketMaker::new(config, db.get_ref().clone(), redis_pool.get_ref().clone())
+        .start_quoting(event_id, option_id, Vec::new(), seeded_shares)
+        .await
+        .map_err(market_maker_error)?;
+    let after = market_maker::quoting_state(&redis_pool, event_id, option_id)
+        .await
+        .map_err(market_maker_error)?;
+
+    record_change(
+        db.get_ref(),
+        &http_req,
+        admin_id,
+        event_id,
+        before.as_ref(),
+        after.as_ref(),
+    )
+    .await?;
+
+    log::info!(
+        "Market maker enabled on event {} option {} by admin {}",
+        event_id,
+        option_id,
+        admin_id
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Market maker enabled"
+    })))
+}
+
+/// Stop quoting an option and cancel its resting quotes (staff)
+pub async fn disable_market_maker(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let (event_id, option_id) = path.into_inner();
+
+    let Some(before) = market_maker::quoting_state(&redis_pool, event_id, option_id)
+        .await
+        .map_err(market_maker_error)?
+    else {
+        return Ok(not_quoted());
+    };
+    if !before.enabled {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "Market maker is already disabled on this option"
+        })));
+    }
+
+    let market_maker = MarketMaker::new(
+        before.config.clone(),
+        db.get_ref().clone(),
+        redis_pool.get_ref().clone(),
+    );
+    market_maker
+        .disable(event_id, option_id)
+        .await
+        .map_err(market_maker_error)?;
+    let after = market_maker::quoting_state(&redis_pool, event_id, option_id)
+        .await
+        .map_err(market_maker_error)?;
+
+    record_change(
+        db.get_ref(),
+        &http_req,
+        admin_id,
+        event_id,
+        Some(&before),
+        after.as_ref(),
+    )
+    .await?;
+
+    log::info!(
+        "Market maker disabled on event {} option {} by admin {}",
+        event_id,
+        option_id,
+        admin_id
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Market maker disabled"
+    })))
+}
+
+/// Change the spread, depth, quantities or inventory limit the market maker
+/// quotes an option with (staff)
+pub async fn update_market_maker_config(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+    req: web::Json<LiquidityConfig>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let (event_id, option_id) = path.into_inner();
+
+    let Some(before) = market_maker::quoting_state(&redis_pool, event_id, option_id)
+        .await
+        .map_err(market_maker_error)?
+    else {
+        return Ok(not_quoted());
+    };
+
+    let mut config = before.config.clone();
+    req.apply_to(&mut config);
+    if let Err(message) = config.validate() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": message
+        })));
+    }
+
+    MarketMaker::new(
+        config.clone(),
+        db.get_ref().clone(),
+        redis_pool.get_ref().clone(),
+    )
+    .reconfigure(event_id, option_id)
+    .await
+    .map_err(market_maker_error)?;
+    let after = market_maker::quoting_state(&redis_pool, event_id, option_id)
+        .await
+        .map_err(market_maker_error)?;
+
+    record_change(
+        db.get_ref(),
+        &http_req,
+        admin_id,
+        event_id,
+        Some(&before),
+        after.as_ref(),
+    )
+    .await?;
+
+    log::info!(
+        "Market maker config for event {} option {} changed by admin {}",
+        event_id,
+        option_id,
+        admin_id
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Market maker config updated",
+        "config": config
+    })))
+}
+
+/// Cancel the market maker's quotes on an option, mint and list a fresh
+/// seeding of liquidity, and quote from it (staff)
+pub async fn reseed_market_maker(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+    req: web::Json<ReseedMarketMakerRequest>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let (event_id, option_id) = path.into_inner();
+    let Some((event, option)) = load_option(db.get_ref(), event_id, option_id).await? else {
+        return Ok(option_not_found());
+    };
+
+    if ["ended", "resolved", "cancelled"].contains(&event.status.as_str()) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Cannot seed liquidity for an event that no longer trades"
+        })));
+    }
+    let initial_price = req.initial_price.unwrap_or(option.current_price);
+    if initial_price < event.min_price || initial_price > event.max_price {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": format!(
+                "Initial price must be between {} and {}",
+                event.min_price, event.max_price
+            )
+        })));
+    }
+
+    let before = market_maker::quoting_state(&redis_pool, event_id, option_id)
+        .await
+        .map_err(market_maker_error)?;
+    let (mut config, seeded_shares) = match &before {
+        Some(state) => (state.config.clone(), state.seeded_shares),
+        None => (
+            MarketMakerConfig::from_settings(Some(event.tenant_id), admin_id, initial_price),
+            0,
+        ),
+    };
+    config.initial_price = initial_price;
+    if let Err(message) = config.validate() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": message
+        })));
+    }
+    let seeded_shares = seeded_shares + config.seeded_shares();
+
+    let market_maker = MarketMaker::new(config, db.get_ref().clone(), redis_pool.get_ref().clone());
+    market_maker
+        .disable(event_id, option_id)
+        .await
+        .map_err(market_maker_error)?;
+    let order_ids = market_maker
+        .seed_initial_liquidity(event_id, option_id)
+        .await
+        .map_err(market_maker_error)?;
+    let orders_placed = order_ids.len();
+    market_maker
+        .start_quoting(event_id, option_id, order_ids, seeded_shares)
+        .await
+        .map_err(market_maker_error)?;
+    let after = market_maker::quoting_state(&redis_pool, event_id, option_id)
+        .await
+        .map_err(market_maker_error)?;
+
+    MarketStatsService::new(db.get_ref().clone(), redis_pool.get_ref().clone())
+        .invalidate(event_id)
+        .await;
+
+    record_change(
+        db.get_ref(),
+        &http_req,
+        admin_id,
+        event_id,
+        before.as_ref(),
+        after.as_ref(),
+    )
+    .await?;
+
+    log::info!(
+        "Market maker re-seeded event {} option {} around {} by admin {}",
+        event_id,
+        option_id,
+        initial_price,
+        admin_id
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Liquidity re-seeded",
+        "orders_placed": orders_placed
+    })))
+}
+
+/// The market maker's P&L, inventory and fees on every market it has quoted,
+/// as the scheduled job last worked them out (staff)
+pub async fn get_market_maker_report(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    query: web::Query<MarketMakerReportQuery>,
+) -> Result<HttpResponse, Error> {
+    let cached = if query.refresh {
+        None
+    } else {
+        market_maker_report::load_report(&redis_pool)
+            .await
+            .map_err(market_maker_error)?
+    };
+    let report = match cached {
+        Some(report) => report,
+        None => market_maker_report::refresh_report(&db, &redis_pool)
+            .await
+            .map_err(market_maker_error)?,
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "report": report
+    })))
+}
@@ -0,0 +1,47 @@
+use crate::constants::config;
+use crate::order_book::performance::market_performance;
+use crate::types::metrics::{MarketPerformanceQuery, QueryMetricsResponse};
+use crate::utils::db_pool::{pool_metrics, ReportingDb, Workload};
+use crate::utils::payload_metrics::route_payload_metrics;
+use crate::utils::query_metrics::route_metrics;
+use actix_web::{web, HttpResponse, Result};
+use sea_orm::DatabaseConnection;
+use serde_json::json;
+
+pub async fn get_metrics(
+    db: web::Data<DatabaseConnection>,
+    reporting_db: web::Data<ReportingDb>,
+) -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(QueryMetricsResponse {
+        query_budget_per_request: config::get_query_budget_per_request(),
+        slow_request_db_time_ms: config::get_slow_request_db_time_ms(),
+        routes: route_metrics(),
+        payloads: route_payload_metrics(),
+        pools: vec![
+            pool_metrics(db.get_ref(), Workload::Trading),
+            pool_metrics(reporting_db.connection(), Workload::Reporting),
+        ],
+        markets: market_performance(),
+    }))
+}
+
+/// Matching latency, trade rate and book changes of every resident order
+/// book, slowest recent changes first
+pub async fn get_market_performance(
+    query: web::Query<MarketPerformanceQuery>,
+) -> Result<HttpResponse> {
+    let markets: Vec<_> = market_performance()
+        .into_iter()
+        .filter(|market| {
+            query
+                .event_id
+                .is_none_or(|event_id| market.event_id == event_id)
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "window_seconds": config::get_market_metrics_window_seconds(),
+        "markets": markets
+    })))
+}
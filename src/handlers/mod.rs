@@ -1,10 +1,42 @@
+pub mod api_key_handler;
+pub mod audit_handler;
 pub mod auth_handler;
+pub mod candle_handler;
+pub mod consent_handler;
+pub mod dashboard_handler;
 pub mod event_handler;
 pub mod event_option_handler;
 pub mod event_settlement_handler;
+pub mod export_handler;
 pub mod health;
+pub mod import_handler;
+pub mod internal_handler;
+pub mod kyc_handler;
+pub mod maintenance_handler;
+pub mod margin_handler;
+pub mod market_handler;
+pub mod market_maker_handler;
+pub mod metrics_handler;
+pub mod moderation_handler;
+pub mod oracle_handler;
 pub mod order_book_handler;
+pub mod payment_handler;
 pub mod portfolio_handler;
 pub mod position_handler;
+pub mod preferences_handler;
+pub mod promotion_handler;
+pub mod risk_handler;
+pub mod role_handler;
+pub mod schema_handler;
+pub mod settings_handler;
+pub mod settlement_rules_handler;
+pub mod support_handler;
+pub mod tenant_handler;
+pub mod tier_handler;
+pub mod trade_settlement_handler;
 pub mod transaction_handler;
+pub mod two_factor_handler;
 pub mod user_handler;
+pub mod venue_handler;
+pub mod wallet_handler;
+pub mod withdrawal_handler;
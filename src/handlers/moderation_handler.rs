@@ -0,0 +1,298 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::types::audit::AuditAction;
+use crate::types::user::{AdjustBalanceRequest, BanUserRequest};
+use crate::utils::audit::{self, AuditEntry};
+use crate::utils::auth::get_user_id;
+use crate::utils::auth_session;
+use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
+use crate::wallets::balances;
+use crate::websocket::server::WebSocketServer;
+use actix::Addr;
+use actix_web::{web, Error, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use deadpool_redis::Pool;
+use entity::{transaction, users};
+use sea_orm::{
+    ActiveModelTrait, DatabaseConnection, DatabaseTransaction, EntityTrait, QuerySelect, Set,
+    TransactionTrait,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+fn bad_request(message: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "message": message
+    }))
+}
+
+fn user_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "message": "User not found"
+    }))
+}
+
+/// The user, locked for the rest of the transaction
+async fn lock_user(txn: &DatabaseTransaction, user_id: i32) -> Result<Option<users::Model>, Error> {
+    users::Entity::find_by_id(user_id)
+        .lock_exclusive()
+        .one(txn)
+        .await
+        .map_err(database_error)
+}
+
+async fn invalidate_user_cache(redis_pool: &Pool, user_id: i32) {
+    let cache_service = CacheService::new(redis_pool.clone());
+    let user_cache_key = create_cache_key(cache_keys::USER_PREFIX, &user_id.to_string());
+    if let Err(e) = cache_service.delete(&user_cache_key).await {
+        log::warn!("Failed to invalidate user cache: {}", e);
+    }
+}
+
+/// Credit or debit a user's wallet by hand, recording it as an `adjustment`
+/// transaction. Debits can't take the wallet below what open orders hold.
+pub async fn adjust_balance(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    path: web::Path<i32>,
+    req: web::Json<AdjustBalanceRequest>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let user_id = path.into_inner();
+    let req = req.into_inner();
+
+    if req.amount.is_zero() || req.amount.scale() > 2 {
+        return Ok(bad_request(
+            "amount must be non-zero with at most 2 decimal places",
+        ));
+    }
+    if req.reason.trim().is_empty() {
+        return Ok(bad_request("reason is required"));
+    }
+    let currency = match balances::resolve_currency(req.currency.as_deref()) {
+        Ok(currency) => currency,
+        Err(message) => return Ok(bad_request(&message)),
+    };
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    if lock_user(&txn, user_id).await?.is_none() {
+        return Ok(user_not_found());
+    }
+    let wallet = balances::lock(&txn, user_id, &currency)
+        .await
+        .map_err(database_error)?;
+
+    let balance_before = wallet.balance;
+    let balance_after = balance_before + req.amount;
+    if balance_after < wallet.reserved_balance {
+        return Ok(bad_request(
+            "Adjustment would leave less than the balance held for open orders",
+        ));
+    }
+
+    balances::set_balance(&txn, wallet, balance_after)
+        .await
+        .map_err(database_error)?;
+
+    let reference_id = Uuid::new_v4().to_string();
+    transaction::ActiveModel {
+        user_id: Set(user_id),
+        r#type: Set("adjustment".to_string()),
+        amount: Set(req.amount),
+        currency: Set(currency.clone()),
+        balance_before: Set(balance_before),
+        balance_after: Set(balance_after),
+        status: Set("completed".to_string()),
+        reference_id: Set(reference_id.clone()),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(database_error)?;
+
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::BalanceAdjust,
+            target_id: user_id,
+            before: Some(json!({ "currency": currency, "wallet_balance": balance_before })),
+            after: Some(json!({
+                "currency": currency,
+                "wallet_balance": balance_after,
+                "amount": req.amount,
+                "reason": req.reason,
+                "reference_id": reference_id
+            })),
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    log::warn!(
+        "User {} adjusted the {} balance of user {} by {}",
+        admin_id,
+        currency,
+        user_id,
+        req.amount
+    );
+
+    invalidate_user_cache(&redis_pool, user_id).await;
+    let _ = CacheService::new(redis_pool.get_ref().clone())
+        .delete(&format!("portfolio:{}", user_id))
+        .await;
+    ws_server.do_send(crate::websocket::server::BroadcastTransactionsUpdate { user_id });
+    ws_server.do_send(crate::websocket::server::BroadcastPortfolioUpdate { user_id });
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "user_id": user_id,
+        "currency": currency,
+        "amount": req.amount,
+        "balance_before": balance_before,
+        "balance_after": balance_after,
+        "reference_id": reference_id
+    })))
+}
+
+/// Set whether a user's account is active, recording why
+async fn set_banned(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    admin_id: i32,
+    user_id: i32,
+    banned: bool,
+    reason: Option<String>,
+    ip: Option<String>,
+) -> Result<HttpResponse, Error> {
+    if user_id == admin_id {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "message": "You can't ban or unban yourself"
+        })));
+    }
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let Some(user) = lock_user(&txn, user_id).await? else {
+        return Ok(user_not_found());
+    };
+    if user.is_active != banned {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": if banned { "User is already banned" } else { "User is not banned" }
+        })));
+    }
+
+    let mut active_user: users::ActiveModel = user.into();
+    active_user.is_active = Set(!banned);
+    active_user.updated_at = Set(Utc::now().naive_utc());
+    active_user.update(&txn).await.map_err(database_error)?;
+
+    let mut after = json!({ "is_active": !banned });
+    if let Some(reason) = reason {
+        after["reason"] = json!(reason);
+    }
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: admin_id,
+            action: if banned {
+                AuditAction::UserBan
+            } else {
+                AuditAction::UserUnban
+            },
+            target_id: user_id,
+            before: Some(json!({ "is_active": banned })),
+            after: Some(after),
+            ip,
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    if banned {
+        log::warn!("User {} banned user {}", admin_id, user_id);
+        if let Err(e) = auth_session::revoke_all(redis_pool, user_id).await {
+            log::error!(
+                "Failed to revoke sessions of banned user {}: {}",
+                user_id,
+                e
+            );
+        }
+    } else {
+        log::warn!("User {} unbanned user {}", admin_id, user_id);
+    }
+    invalidate_user_cache(redis_pool, user_id).await;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "user_id": user_id,
+        "is_active": !banned
+    })))
+}
+
+/// Deactivate a user's account and sign them out everywhere
+pub async fn ban_user(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    req: web::Json<BanUserRequest>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let reason = req.into_inner().reason;
+    if reason.trim().is_empty() {
+        return Ok(bad_request("reason is required"));
+    }
+
+    set_banned(
+        db.get_ref(),
+        redis_pool.get_ref(),
+        admin_id,
+        path.into_inner(),
+        true,
+        Some(reason),
+        audit::client_ip(&http_req),
+    )
+    .await
+}
+
+/// Reactivate a banned user's account
+pub async fn unban_user(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+
+    set_banned(
+        db.get_ref(),
+        redis_pool.get_ref(),
+        admin_id,
+        path.into_inner(),
+        false,
+        None,
+        audit::client_ip(&http_req),
+    )
+    .await
+}
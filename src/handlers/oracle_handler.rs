@@ -0,0 +1,467 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::middleware::internal_auth::InternalService;
+use crate::oracle::proposals::{self, FeedOutcome};
+use crate::types::audit::AuditAction;
+use crate::types::oracle::{
+    ConfigureOracleRequest, OracleResponse, OracleStatus, RejectProposalRequest,
+    MAX_CHALLENGE_WINDOW_MINUTES, MAX_VALUE_LENGTH,
+};
+use crate::utils::audit::{self, AuditEntry};
+use crate::utils::auth::get_user_id;
+use crate::websocket::server::WebSocketServer;
+use actix::Addr;
+use actix_web::{web, Error, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use deadpool_redis::Pool;
+use entity::{event_options, event_oracles, events};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait, TryIntoModel,
+};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+/// Longest feed URL, matching the column width
+const MAX_SOURCE_URL_LENGTH: usize = 500;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+fn bad_request(message: impl Into<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "message": message.into()
+    }))
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "message": "Event has no oracle"
+    }))
+}
+
+/// Whether an event can still be settled
+fn is_open(event: &events::Model) -> bool {
+    event.status != "resolved" && event.status != "cancelled"
+}
+
+async fn find_oracle(
+    db: &DatabaseConnection,
+    event_id: i32,
+) -> Result<Option<event_oracles::Model>, Error> {
+    event_oracles::Entity::find()
+        .filter(event_oracles::Column::EventId.eq(event_id))
+        .one(db)
+        .await
+        .map_err(database_error)
+}
+
+/// Get the oracle of an event (admin only)
+pub async fn get_oracle(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let Some(oracle) = find_oracle(db.get_ref(), path.into_inner()).await? else {
+        return Ok(not_found());
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": OracleResponse::from(oracle)
+    })))
+}
+
+/// Configure the feed an event is resolved from, replacing any oracle it
+/// has and discarding its proposal (admin only)
+pub async fn configure_oracle(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+    req: web::Json<ConfigureOracleRequest>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let event_id = path.into_inner();
+    let req = req.into_inner();
+
+    let source_url = req.source_url.trim().to_string();
+    if !(source_url.starts_with("https://") || source_url.starts_with("http://"))
+        || source_url.len() > MAX_SOURCE_URL_LENGTH
+    {
+        return Ok(bad_request(format!(
+            "source_url must be an http(s) URL of at most {} characters",
+            MAX_SOURCE_URL_LENGTH
+        )));
+    }
+    let value_path = req.value_path.trim().to_string();
+    if !value_path.starts_with('/') || value_path.len() > 200 {
+        return Ok(bad_request(
+            "value_path must be a JSON pointer such as /result/winner",
+        ));
+    }
+    if req.option_mappings.is_empty()
+        || req
+            .option_mappings
+            .keys()
+            .any(|value| value.is_empty() || value.len() > MAX_VALUE_LENGTH)
+    {
+        return Ok(bad_request(format!(
+            "option_mappings must map at least one value of 1 to {} characters",
+            MAX_VALUE_LENGTH
+        )));
+    }
+    let challenge_window_minutes = req.challenge_window_minutes.unwrap_or(60);
+    if !(0..=MAX_CHALLENGE_WINDOW_MINUTES).contains(&challenge_window_minutes) {
+        return Ok(bad_request(format!(
+            "challenge_window_minutes must be between 0 and {}",
+            MAX_CHALLENGE_WINDOW_MINUTES
+        )));
+    }
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let Some(event) = events::Entity::find_by_id(event_id)
+        .one(&txn)
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Event not found"
+        })));
+    };
+    if !is_open(&event) {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "Event is already resolved or cancelled"
+        })));
+    }
+
+    let option_ids: HashSet<i32> = event
+        .find_related(event_options::Entity)
+        .all(&txn)
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(|option| option.id)
+        .collect();
+    if let Some(option_id) = req
+        .option_mappings
+        .values()
+        .find(|option_id| !option_ids.contains(option_id))
+    {
+        return Ok(bad_request(format!(
+            "Option {} does not belong to event {}",
+            option_id, event_id
+        )));
+    }
+
+    let existing = event_oracles::Entity::find()
+        .filter(event_oracles::Column::EventId.eq(event_id))
+        .one(&txn)
+        .await
+        .map_err(database_error)?;
+    if existing
+        .as_ref()
+        .is_some_and(|oracle| oracle.status == OracleStatus::Settled.as_str())
+    {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "The oracle has already settled this event"
+        })));
+    }
+
+    let now = Utc::now();
+    let mut active = match &existing {
+        Some(oracle) => oracle.clone().into(),
+        None => event_oracles::ActiveModel {
+            event_id: Set(event_id),
+            created_at: Set(now.into()),
+            ..Default::default()
+        },
+    };
+    active.source_url = Set(source_url);
+    active.value_path = Set(value_path);
+    active.option_mappings = Set(json!(req.option_mappings));
+    active.auto_settle = Set(req.auto_settle.unwrap_or(false));
+    active.challenge_window_minutes = Set(challenge_window_minutes);
+    active.status = Set(OracleStatus::Waiting.as_str().to_string());
+    active.proposed_option_id = Set(None);
+    active.proposed_value = Set(None);
+    active.proposed_at = Set(None);
+    active.settle_after = Set(None);
+    active.last_error = Set(None);
+    active.created_by = Set(admin_id);
+    active.updated_at = Set(now.into());
+    let oracle = active.save(&txn).await.map_err(database_error)?;
+    let oracle = oracle.try_into_model().map_err(database_error)?;
+
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::OracleUpdate,
+            target_id: event_id,
+            before: existing.map(|oracle| json!(OracleResponse::from(oracle))),
+            after: Some(json!(OracleResponse::from(oracle.clone()))),
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    log::info!(
+        "Oracle of event {} configured by admin {}",
+        event_id,
+        admin_id
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Oracle configured",
+        "data": OracleResponse::from(oracle)
+    })))
+}
+
+/// Remove the oracle of an event, so it is settled by hand (admin only)
+pub async fn delete_oracle(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let event_id = path.into_inner();
+
+    let txn = db.begin().await.map_err(database_error)?;
+    let Some(oracle) = event_oracles::Entity::find()
+        .filter(event_oracles::Column::EventId.eq(event_id))
+        .one(&txn)
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(not_found());
+    };
+
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::OracleUpdate,
+            target_id: event_id,
+            before: Some(json!(OracleResponse::from(oracle.clone()))),
+            after: None,
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    oracle.delete(&txn).await.map_err(database_error)?;
+    txn.commit().await.map_err(database_error)?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Oracle removed"
+    })))
+}
+
+/// List the oracle proposals waiting for their challenge window to pass or
+/// for confirmation, oldest first (admin only)
+pub async fn list_proposals(db: web::Data<DatabaseConnection>) -> Result<HttpResponse, Error> {
+    let proposals: Vec<OracleResponse> = event_oracles::Entity::find()
+        .filter(event_oracles::Column::Status.eq(OracleStatus::Proposed.as_str()))
+        .order_by_asc(event_oracles::Column::ProposedAt)
+        .all(db.get_ref())
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(OracleResponse::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": proposals
+    })))
+}
+
+/// Settle an event with its oracle's proposed option now, without waiting
+/// for the challenge window (admin only)
+pub async fn confirm_proposal(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    path: web::Path<i32>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let event_id = path.into_inner();
+
+    let Some(oracle) = find_oracle(db.get_ref(), event_id).await? else {
+        return Ok(not_found());
+    };
+    if oracle.status != OracleStatus::Proposed.as_str() {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "The oracle has no proposal to confirm"
+        })));
+    }
+
+    log::info!(
+        "Admin {} confirmed oracle proposal of event {}",
+        admin_id,
+        event_id
+    );
+    let settled = proposals::settle_proposal(
+        &db,
+        &redis_pool,
+        &ws_server,
+        &oracle,
+        admin_id,
+        audit::client_ip(&http_req),
+    )
+    .await?;
+    if let Some(response) = settled {
+        return Ok(response);
+    }
+
+    // Either the proposal was settled or rejected meanwhile, or settlement
+    // refused it and recorded why
+    let oracle = find_oracle(db.get_ref(), event_id).await?;
+    Ok(HttpResponse::Conflict().json(json!({
+        "success": false,
+        "message": oracle
+            .as_ref()
+            .and_then(|oracle| oracle.last_error.clone())
+            .unwrap_or_else(|| "The oracle has no proposal to confirm".to_string()),
+        "data": oracle.map(OracleResponse::from)
+    })))
+}
+
+/// Reject an oracle's proposed option, so it is never settled with it and
+/// the event is settled by hand (admin only)
+pub async fn reject_proposal(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+    req: web::Json<RejectProposalRequest>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let event_id = path.into_inner();
+    let reason = req.into_inner().reason.trim().to_string();
+    if reason.is_empty() {
+        return Ok(bad_request("A reason is required"));
+    }
+
+    let txn = db.begin().await.map_err(database_error)?;
+    let Some(oracle) = event_oracles::Entity::find()
+        .filter(event_oracles::Column::EventId.eq(event_id))
+        .filter(event_oracles::Column::Status.eq(OracleStatus::Proposed.as_str()))
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "The oracle has no proposal to reject"
+        })));
+    };
+
+    let mut active: event_oracles::ActiveModel = oracle.clone().into();
+    active.status = Set(OracleStatus::Rejected.as_str().to_string());
+    active.settle_after = Set(None);
+    active.last_error = Set(Some(reason.clone()));
+    active.updated_at = Set(Utc::now().into());
+    let updated = active.update(&txn).await.map_err(database_error)?;
+
+    let mut after = json!(OracleResponse::from(updated.clone()));
+    after["reason"] = json!(reason);
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::OracleUpdate,
+            target_id: event_id,
+            before: Some(json!(OracleResponse::from(oracle))),
+            after: Some(after),
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    log::info!(
+        "Admin {} rejected oracle proposal of event {}: {}",
+        admin_id,
+        event_id,
+        reason
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Proposal rejected",
+        "data": OracleResponse::from(updated)
+    })))
+}
+
+/// Report the feed the oracle relay fetched for an event
+pub async fn report_feed(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+    feed: web::Json<Value>,
+    service: web::ReqData<InternalService>,
+) -> Result<HttpResponse, Error> {
+    let event_id = path.into_inner();
+    log::info!("{} reported oracle feed of event {}", service.0, event_id);
+
+    let Some((oracle, Some(event))) = event_oracles::Entity::find()
+        .find_also_related(events::Entity)
+        .filter(event_oracles::Column::EventId.eq(event_id))
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(not_found());
+    };
+    if oracle.status != OracleStatus::Waiting.as_str() || !is_open(&event) {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "The oracle is not waiting for a result"
+        })));
+    }
+    if event.status != "ended" && event.end_time > Utc::now().naive_utc() {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "Event has not ended yet"
+        })));
+    }
+
+    match proposals::record_feed(db.get_ref(), oracle, &feed)
+        .await
+        .map_err(database_error)?
+    {
+        FeedOutcome::Pending => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": "The feed has no result yet"
+        }))),
+        FeedOutcome::Unusable(message) => Ok(HttpResponse::UnprocessableEntity().json(json!({
+            "success": false,
+            "message": message
+        }))),
+        FeedOutcome::Proposed(oracle) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": "Result proposed",
+            "data": OracleResponse::from(*oracle)
+        }))),
+    }
+}
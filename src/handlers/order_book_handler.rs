@@ -1,27 +1,76 @@
+use crate::candles::service::CandleService;
+use crate::constants::config;
+use crate::middleware::auth::{AuthSession, AuthenticatedUser};
+use crate::middleware::correlation::CorrelationId;
+use crate::middleware::idempotency;
+use crate::notifications::dispatcher;
 use crate::order_book::types::OrderStatus;
 use crate::order_book::{
-    db_persistence::DbPersistence, position_tracker::PositionTracker,
-    redis_persistence::RedisOrderBookPersistence, Order, OrderSide, OrderType, TimeInForce,
+    amm::{AMM_LIQUIDITY_REJECTION, AMM_PRICE_REJECTION},
+    complement,
+    db_persistence::DbPersistence,
+    engine::{
+        OrderBookEngine, AUCTION_REJECTION, FILL_OR_KILL_REJECTION, POST_ONLY_REJECTION,
+        SELF_TRADE_REJECTION, TRADING_HALTED_REJECTION,
+    },
+    fees::{self, FeeWaiver, TradeFees},
+    limits::validate_order_limits,
+    margin, matching_service,
+    position_tracker::PositionTracker,
+    redis_persistence::RedisOrderBookPersistence,
+    reservations, risk, router, settlement,
+    types::{AuctionState, Trade, TradingHalt},
+    Order, OrderSide, OrderType, TimeInForce,
 };
+use crate::promotions::service::PromotionService;
+use crate::settings::registry as settings;
+use crate::tiers::service::TierService;
 use crate::types::order_book::{
-    CancelOrderRequest, MarketDepthResponse, OrderBookResponse, OrderResponse, PlaceOrderRequest,
-    PlaceOrderResponse, TradeResponse,
+    AmendOrderRequest, BatchOrderResult, CancelAllOrdersQuery, CancelOrderRequest,
+    CompactOrderBookResponse, EventOrderBooksResponse, FieldsQuery, HaltTradingRequest,
+    ImpliedOrderBookResponse, MarketDepthResponse, OrderBookResponse, OrderEventResponse,
+    OrderHistoryResponse, OrderPreview, OrderResponse, PlaceOrderBatchRequest, PlaceOrderRequest,
+    PlaceOrderResponse, StartAuctionRequest, TradeResponse,
 };
+use crate::types::rbac::Permission;
+use crate::types::settings::SettingKey;
+use crate::types::websocket::{
+    OpsEventKind, OpsSeverity, PriceUpdateSource, SubscriptionChannel, WebSocketMessage,
+};
+use crate::utils::auth::get_user_id;
 use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
-use crate::websocket::server::WebSocketServer;
+use crate::utils::fields;
+use crate::utils::permissions;
+use crate::wallets::balances;
+use crate::websocket::server::{Broadcast, WebSocketServer};
+use crate::websocket::{market_data, ops};
 use actix::Addr;
 use actix_web::{web, Error, HttpResponse, Result};
 use deadpool_redis::Pool;
-use entity::{event_options, events, users};
-use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set, TransactionTrait};
+use entity::{event_options, events, tenants, users, wallets};
+use sea_orm::{
+    prelude::Decimal, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
 use serde_json::json;
 
-// Removed static ORDER_BOOKS - now using Redis for all order book storage
+// Order books live in memory in matching_service; Redis holds their snapshots
+
+/// Longest `client_order_id` accepted, matching the column width
+const MAX_CLIENT_ORDER_ID_LENGTH: usize = 64;
+
+fn valid_client_order_id(client_order_id: &str) -> bool {
+    !client_order_id.is_empty()
+        && client_order_id.len() <= MAX_CLIENT_ORDER_ID_LENGTH
+        && client_order_id.bytes().all(|b| b.is_ascii_graphic())
+}
 
 pub async fn place_order(
     db: web::Data<DatabaseConnection>,
     redis_pool: web::Data<Pool>,
     ws_server: web::Data<Addr<WebSocketServer>>,
+    tenant: web::ReqData<tenants::Model>,
+    correlation_id: web::ReqData<CorrelationId>,
     req: web::Json<PlaceOrderRequest>,
     user_id: web::ReqData<String>,
 ) -> Result<HttpResponse, Error> {
@@ -30,10 +79,270 @@ pub async fn place_order(
         .parse()
         .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
 
-    // Initialize persistence layers
-    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
+    place_user_order(
+        &db,
+        &redis_pool,
+        &ws_server,
+        user_id_int,
+        tenant.id,
+        &correlation_id.0,
+        &req,
+    )
+    .await
+}
+
+/// Place up to `MAX_BATCH_ORDERS` orders in one request. Every order is
+/// validated before any is placed; they are then placed one after another,
+/// each seeing the fills and holds of those before it. They share the
+/// request's correlation id.
+pub async fn place_order_batch(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    tenant: web::ReqData<tenants::Model>,
+    correlation_id: web::ReqData<CorrelationId>,
+    req: web::Json<PlaceOrderBatchRequest>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id_int: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    let max_batch_orders = config::get_max_batch_orders();
+    if req.orders.is_empty() || req.orders.len() > max_batch_orders {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": format!("A batch must contain 1 to {} orders", max_batch_orders)
+        })));
+    }
+
+    let mut client_order_ids = std::collections::HashSet::new();
+    if req
+        .orders
+        .iter()
+        .filter_map(|order| order.client_order_id.as_deref())
+        .any(|id| !client_order_ids.insert(id))
+    {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "client_order_id must be unique within the batch"
+        })));
+    }
+
+    let db_persistence = DbPersistence::new(db.get_ref().clone());
+    let mut rejections = Vec::new();
+    for (index, order) in req.orders.iter().enumerate() {
+        if let Some(rejection) =
+            validate_new_order(&db, &db_persistence, user_id_int, tenant.id, order).await?
+        {
+            rejections.push(batch_result(index, Ok(rejection)).await);
+        }
+    }
+    if !rejections.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "No orders were placed because some failed validation",
+            "results": rejections
+        })));
+    }
+
+    let mut results = Vec::with_capacity(req.orders.len());
+    for (index, order) in req.orders.iter().enumerate() {
+        let placed = place_user_order(
+            &db,
+            &redis_pool,
+            &ws_server,
+            user_id_int,
+            tenant.id,
+            &correlation_id.0,
+            order,
+        )
+        .await;
+        results.push(batch_result(index, placed).await);
+    }
+    let placed = results
+        .iter()
+        .filter(|result| (200..300).contains(&result.status))
+        .count();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "placed": placed,
+        "results": results
+    })))
+}
+
+/// Run an order through every check placement makes and preview its fills,
+/// without placing it. Rejections are the responses placement would give.
+pub async fn validate_order(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    tenant: web::ReqData<tenants::Model>,
+    req: web::Json<PlaceOrderRequest>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id_int: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
     let db_persistence = DbPersistence::new(db.get_ref().clone());
+    if let Some(rejection) =
+        validate_new_order(&db, &db_persistence, user_id_int, tenant.id, &req).await?
+    {
+        return Ok(rejection);
+    }
+
+    let user = users::Entity::find_by_id(user_id_int)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+    let Some(user) = user else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "User not found"
+        })));
+    };
+    let (currency, max_price) = order_terms(&db, req.event_id).await?;
+    let hold_price = hold_price(&req, max_price);
+    let wallet = balances::find(db.get_ref(), user_id_int, &currency)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
     let position_tracker = PositionTracker::new(db.get_ref().clone());
+    let short_quantity =
+        match validate_order_funds(&position_tracker, &user, wallet.as_ref(), &req, hold_price)
+            .await?
+        {
+            Ok(short_quantity) => short_quantity,
+            Err(rejection) => return Ok(rejection),
+        };
+
+    let mut order = order_from_request(user_id_int, &currency, hold_price, &req);
+    order.short_quantity = short_quantity;
+    let preview = router::preview(db.get_ref(), redis_pool.get_ref(), order)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to preview order: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to get order book")
+        })?;
+
+    let preview = match preview {
+        Some(preview) => {
+            let fills = match preview.result {
+                Ok(fills) => fills,
+                Err(e) if is_rejection_reason(&e) => {
+                    return Ok(HttpResponse::BadRequest().json(json!({
+                        "success": false,
+                        "rejection_reason": e
+                    })));
+                }
+                Err(e) => {
+                    return Ok(HttpResponse::BadRequest().json(json!({
+                        "success": false,
+                        "message": e
+                    })));
+                }
+            };
+            let filled_quantity: i32 = fills.iter().map(|(_, quantity)| quantity).sum();
+            let notional: Decimal = fills
+                .iter()
+                .map(|(price, quantity)| price * Decimal::from(*quantity))
+                .sum();
+            let benefits = TierService::new(db.get_ref().clone())
+                .benefits(user_id_int)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to resolve user tier: {}", e);
+                    actix_web::error::ErrorInternalServerError("Failed to resolve user tier")
+                })?;
+            Some(OrderPreview {
+                venue: preview.venue,
+                filled_quantity,
+                average_price: (filled_quantity > 0)
+                    .then(|| (notional / Decimal::from(filled_quantity)).round_dp(2)),
+                notional,
+                estimated_fee: fees::trading_fee(
+                    notional,
+                    settings::trading_fee_bps(Some(tenant.id)),
+                    benefits.fee_discount_percent,
+                ),
+                resting_quantity: preview.resting_quantity,
+                unfilled_quantity: req.quantity - filled_quantity - preview.resting_quantity,
+            })
+        }
+        None => None,
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Order would be accepted",
+        "currency": currency,
+        "preview": preview
+    })))
+}
+
+/// Turn the response for one order of a batch into its result entry
+async fn batch_result(index: usize, response: Result<HttpResponse, Error>) -> BatchOrderResult {
+    let response = response.unwrap_or_else(|e| e.error_response());
+    let status = response.status().as_u16();
+    let bytes = actix_web::body::to_bytes(response.into_body())
+        .await
+        .unwrap_or_default();
+    // Errors raised with a plain message aren't JSON, so wrap them like the rest
+    let response = serde_json::from_slice(&bytes).unwrap_or_else(|_| {
+        json!({
+            "success": false,
+            "message": String::from_utf8_lossy(&bytes)
+        })
+    });
+
+    BatchOrderResult {
+        index,
+        status,
+        response,
+    }
+}
+
+/// Check a new order against its event and option, the user's tier limits
+/// and their client order ids. Events of tenants other than `tenant_id`
+/// are treated as missing. Returns the response rejecting it, if any.
+async fn validate_new_order(
+    db: &web::Data<DatabaseConnection>,
+    db_persistence: &DbPersistence,
+    user_id_int: i32,
+    tenant_id: i32,
+    req: &PlaceOrderRequest,
+) -> Result<Option<HttpResponse>, Error> {
+    // Client order ids must be well formed and not already used by the user
+    if let Some(client_order_id) = &req.client_order_id {
+        if !valid_client_order_id(client_order_id) {
+            return Ok(Some(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": format!(
+                    "client_order_id must be 1 to {} printable ASCII characters",
+                    MAX_CLIENT_ORDER_ID_LENGTH
+                )
+            }))));
+        }
+        let existing = db_persistence
+            .get_order_by_client_id(user_id_int, client_order_id)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to look up client order id: {}", e);
+                actix_web::error::ErrorInternalServerError("Database error occurred")
+            })?;
+        if existing.is_some() {
+            return Ok(Some(HttpResponse::Conflict().json(json!({
+                "success": false,
+                "message": "client_order_id is already used by another order"
+            }))));
+        }
+    }
 
     // Validate event exists and is active
     let event = events::Entity::find_by_id(req.event_id)
@@ -44,29 +353,59 @@ pub async fn place_order(
             actix_web::error::ErrorInternalServerError("Database error occurred")
         })?;
 
-    let event = match event {
+    let event = match event.filter(|e| e.tenant_id == tenant_id) {
         Some(e) => e,
         None => {
-            return Ok(HttpResponse::BadRequest().json(json!({
+            return Ok(Some(HttpResponse::BadRequest().json(json!({
                 "success": false,
                 "message": "Event not found"
-            })));
+            }))));
         }
     };
 
     // Check if event is active and not ended
     if event.status != "active" {
-        return Ok(HttpResponse::BadRequest().json(json!({
+        return Ok(Some(HttpResponse::BadRequest().json(json!({
             "success": false,
             "message": "Event is not active for trading"
-        })));
+        }))));
     }
 
     if event.end_time <= chrono::Utc::now().naive_utc() {
-        return Ok(HttpResponse::BadRequest().json(json!({
+        return Ok(Some(HttpResponse::BadRequest().json(json!({
             "success": false,
             "message": "Event has already ended"
-        })));
+        }))));
+    }
+
+    // No new orders in the last minutes before the event ends
+    let freeze_minutes = settings::integer(Some(tenant_id), SettingKey::TradingFreezeMinutes);
+    if event.end_time - chrono::Duration::minutes(freeze_minutes) <= chrono::Utc::now().naive_utc()
+    {
+        return Ok(Some(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Trading is frozen ahead of the event's end"
+        }))));
+    }
+
+    // Enforce the event's price band, tick size and order size limit
+    let benefits = TierService::new(db.get_ref().clone())
+        .benefits(user_id_int)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to resolve user tier: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to resolve user tier")
+        })?;
+    if let Err(message) = validate_order_limits(
+        &event,
+        req.price,
+        req.quantity,
+        benefits.order_size_multiplier,
+    ) {
+        return Ok(Some(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": message
+        }))));
     }
 
     // Validate option exists and belongs to event
@@ -81,90 +420,236 @@ pub async fn place_order(
     let option = match option {
         Some(o) => o,
         None => {
-            return Ok(HttpResponse::BadRequest().json(json!({
+            return Ok(Some(HttpResponse::BadRequest().json(json!({
                 "success": false,
                 "message": "Option not found"
-            })));
+            }))));
         }
     };
 
     if option.event_id != req.event_id {
-        return Ok(HttpResponse::BadRequest().json(json!({
+        return Ok(Some(HttpResponse::BadRequest().json(json!({
             "success": false,
             "message": "Option does not belong to the specified event"
-        })));
+        }))));
     }
 
-    // Get user's current balance
-    let user = users::Entity::find_by_id(user_id_int)
+    Ok(None)
+}
+
+/// Currency an event's orders are priced and paid in, and the highest price
+/// they can trade at
+async fn order_terms(
+    db: &web::Data<DatabaseConnection>,
+    event_id: i32,
+) -> Result<(String, Option<Decimal>), Error> {
+    let terms: Option<(String, Decimal)> = events::Entity::find_by_id(event_id)
+        .select_only()
+        .column(events::Column::Currency)
+        .column(events::Column::MaxPrice)
+        .into_tuple()
         .one(db.get_ref())
         .await
         .map_err(|e| {
             log::error!("Database error: {}", e);
             actix_web::error::ErrorInternalServerError("Database error occurred")
         })?;
+    Ok(match terms {
+        Some((currency, max_price)) => (currency, Some(max_price)),
+        None => (config::get_base_currency(), None),
+    })
+}
 
-    let user = match user {
-        Some(u) => u,
-        None => {
-            return Ok(HttpResponse::BadRequest().json(json!({
-                "success": false,
-                "message": "User not found"
-            })));
-        }
-    };
+/// Price the funds of an order are held at when it can fill above its own
+/// price: market buy orders hold at the event's `max_price`
+fn hold_price(req: &PlaceOrderRequest, max_price: Option<Decimal>) -> Option<Decimal> {
+    if req.side == OrderSide::Buy && req.order_type == OrderType::Market {
+        max_price
+    } else {
+        None
+    }
+}
+
+/// Check the user can pay for a buy order held at `hold_price` from
+/// `wallet`, or hold or may short the shares of a sell order. Returns the
+/// number of shares the order sells short, or the response rejecting it.
+async fn validate_order_funds(
+    position_tracker: &PositionTracker,
+    user: &users::Model,
+    wallet: Option<&wallets::Model>,
+    req: &PlaceOrderRequest,
+    hold_price: Option<Decimal>,
+) -> Result<Result<i32, HttpResponse>, Error> {
+    let available = balances::available_in(wallet);
 
     // Validate based on order side
     match req.side {
         OrderSide::Buy => {
-            // Check balance for buy orders
-            let required_amount = req.price * sea_orm::prelude::Decimal::from(req.quantity);
-            if user.wallet_balance < required_amount {
-                return Ok(HttpResponse::BadRequest().json(json!({
+            // Funds held for the user's other open buy orders can't be spent
+            // again; saving the order reserves these atomically
+            let required_amount =
+                reservations::hold_amount(hold_price.unwrap_or(req.price), req.quantity);
+            if available < required_amount {
+                return Ok(Err(HttpResponse::BadRequest().json(json!({
                     "success": false,
                     "message": "Insufficient balance"
-                })));
+                }))));
             }
+            Ok(Ok(0))
         }
         OrderSide::Sell => {
-            // Check position for sell orders
-            let has_shares = position_tracker
-                .validate_sell_order(user_id_int, req.event_id, req.option_id, req.quantity)
+            // Check position for sell orders; margin accounts may go short
+            let position = position_tracker
+                .get_user_position(user.id, req.event_id, req.option_id)
                 .await
                 .map_err(|e| {
                     log::error!("Position validation error: {}", e);
                     actix_web::error::ErrorInternalServerError("Failed to validate position")
                 })?;
 
-            if !has_shares {
-                return Ok(HttpResponse::BadRequest().json(json!({
+            let covered = position_tracker
+                .complementary_holdings(user.id, req.event_id, req.option_id)
+                .await
+                .map_err(|e| {
+                    log::error!("Position validation error: {}", e);
+                    actix_web::error::ErrorInternalServerError("Failed to validate position")
+                })?;
+
+            if let Err(message) = margin::validate_sell(
+                user,
+                available,
+                position.quantity,
+                covered,
+                req.quantity,
+                req.price,
+            ) {
+                return Ok(Err(HttpResponse::BadRequest().json(json!({
                     "success": false,
-                    "message": "Insufficient shares to sell"
-                })));
+                    "message": message
+                }))));
             }
+            Ok(Ok(margin::shares_shorted(position.quantity, req.quantity)))
         }
     }
+}
 
-    // Create the order
-    let time_in_force = req.time_in_force.clone().unwrap_or(TimeInForce::GTC);
-    let order = Order::new(
+/// The order `req` asks `user_id_int` to place, in the event's `currency`,
+/// holding funds at `hold_price` if given
+fn order_from_request(
+    user_id_int: i32,
+    currency: &str,
+    hold_price: Option<Decimal>,
+    req: &PlaceOrderRequest,
+) -> Order {
+    let mut order = Order::new(
         user_id_int,
         req.event_id,
         req.option_id,
         req.side.clone(),
         req.order_type.clone(),
-        time_in_force.clone(),
+        req.time_in_force.clone().unwrap_or(TimeInForce::GTC),
         req.price,
         req.quantity,
+        currency.to_string(),
     );
+    order.post_only = req.post_only.unwrap_or(false);
+    order.self_trade_prevention = req.self_trade_prevention.unwrap_or_default();
+    order.client_order_id = req.client_order_id.clone();
+    order.hold_price = hold_price;
+    order
+}
+
+/// Whether a venue rejected an order as a normal outcome, reported to the
+/// client as its `rejection_reason` rather than an error
+fn is_rejection_reason(e: &str) -> bool {
+    e == POST_ONLY_REJECTION
+        || e == TRADING_HALTED_REJECTION
+        || e == AUCTION_REJECTION
+        || e == AMM_PRICE_REJECTION
+        || e == AMM_LIQUIDITY_REJECTION
+        || e == FILL_OR_KILL_REJECTION
+        || e.starts_with(SELF_TRADE_REJECTION)
+}
+
+/// Validate, place and settle one order for `user_id_int` on `tenant_id`'s site
+async fn place_user_order(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    user_id_int: i32,
+    tenant_id: i32,
+    correlation_id: &str,
+    req: &PlaceOrderRequest,
+) -> Result<HttpResponse, Error> {
+    // Initialize persistence layers
+    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
+    let db_persistence = DbPersistence::new(db.get_ref().clone());
+    let position_tracker = PositionTracker::new(db.get_ref().clone());
+
+    if let Some(rejection) =
+        validate_new_order(db, &db_persistence, user_id_int, tenant_id, req).await?
+    {
+        return Ok(rejection);
+    }
+
+    // Get user's current balance
+    let user = users::Entity::find_by_id(user_id_int)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let user = match user {
+        Some(u) => u,
+        None => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": "User not found"
+            })));
+        }
+    };
+
+    // Orders are priced and paid in their event's currency
+    let (currency, max_price) = order_terms(db, req.event_id).await?;
+    let hold_price = hold_price(req, max_price);
+    let wallet = balances::find(db.get_ref(), user_id_int, &currency)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+    let short_quantity =
+        match validate_order_funds(&position_tracker, &user, wallet.as_ref(), req, hold_price)
+            .await?
+        {
+            Ok(short_quantity) => short_quantity,
+            Err(rejection) => return Ok(rejection),
+        };
+
+    // Create the order, holding the collateral of any shares it sells short
+    let mut order = order_from_request(user_id_int, &currency, hold_price, req);
+    order.short_quantity = short_quantity;
+    order.correlation_id = Some(correlation_id.to_string());
+    let time_in_force = order.time_in_force.clone();
+    let self_trade_prevention = order.self_trade_prevention;
 
     let order_id = order.id.clone();
 
     // Save order to database first
     if let Err(e) = db_persistence.save_order(&order).await {
+        // Another order spent the funds since the balance check above
+        if e == reservations::INSUFFICIENT_BALANCE {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": "Insufficient balance"
+            })));
+        }
+        // The order and its hold were rolled back, so the request can be retried
         log::error!("Failed to save order to database: {}", e);
-        return Err(actix_web::error::ErrorInternalServerError(
-            "Failed to save order",
+        return Ok(idempotency::nothing_persisted(
+            actix_web::error::ErrorInternalServerError("Failed to save order"),
         ));
     }
 
@@ -173,20 +658,31 @@ pub async fn place_order(
         log::error!("Failed to save order to Redis: {}", e);
     }
 
-    // Get or create order book from Redis
-    let mut order_book = redis_persistence
-        .get_or_create_order_book(req.event_id, req.option_id)
+    // Submit order to the event's execution venue
+    let execution = router::submit(db.get_ref(), redis_pool.get_ref(), order.clone())
         .await
         .map_err(|e| {
-            log::error!("Failed to get order book from Redis: {}", e);
+            log::error!("Failed to reach execution venue: {}", e);
             actix_web::error::ErrorInternalServerError("Failed to get order book")
         })?;
-
-    // Submit order to order book
-    let trades = match order_book.submit_order(order) {
+    let router::Execution {
+        result: submission,
+        order: final_order,
+        self_trade_prevented,
+        triggered_halt,
+        sequence,
+        ..
+    } = execution;
+
+    let trades = match submission {
         Ok(trades) => trades,
         Err(e) => {
-            log::error!("Order submission error: {}", e);
+            log::error!(
+                "Order {} submission error (correlation {}): {}",
+                order_id,
+                correlation_id,
+                e
+            );
             // Update order status as rejected in database
             let rejected_order = Order {
                 id: order_id.clone(),
@@ -200,10 +696,37 @@ pub async fn place_order(
                 quantity: req.quantity,
                 filled_quantity: 0,
                 status: OrderStatus::Rejected,
+                post_only: req.post_only.unwrap_or(false),
+                self_trade_prevention,
+                client_order_id: req.client_order_id.clone(),
+                currency: currency.clone(),
+                correlation_id: Some(correlation_id.to_string()),
+                hold_price,
+                short_quantity: order.short_quantity,
                 created_at: chrono::Utc::now(),
                 updated_at: chrono::Utc::now(),
             };
             let _ = db_persistence.update_order(&rejected_order).await;
+
+            // Post-only, self-trade, halt, auction and AMM quote rejections are normal outcomes, report them with a distinct reason
+            if is_rejection_reason(&e) {
+                if let Err(e) = redis_persistence
+                    .update_order_status(&order_id, OrderStatus::Rejected, 0)
+                    .await
+                {
+                    log::error!("Failed to update order status in Redis: {}", e);
+                }
+                return Ok(HttpResponse::BadRequest().json(PlaceOrderResponse {
+                    success: false,
+                    order_id,
+                    client_order_id: req.client_order_id.clone(),
+                    trades: vec![],
+                    currency,
+                    wallet_balance: wallet.map(|wallet| wallet.balance).unwrap_or_default(),
+                    rejection_reason: Some(e),
+                }));
+            }
+
             return Ok(HttpResponse::BadRequest().json(json!({
                 "success": false,
                 "message": e
@@ -211,184 +734,925 @@ pub async fn place_order(
         }
     };
 
-    // Save updated order book back to Redis
-    if let Err(e) = redis_persistence
-        .save_full_order_book(req.event_id, req.option_id, &order_book)
-        .await
-    {
-        log::error!("Failed to save order book to Redis: {}", e);
+    // Process trades in a database transaction
+    let trade_fees = if trades.is_empty() {
+        Vec::new()
+    } else {
+        settle_trades(
+            db.get_ref(),
+            &db_persistence,
+            &redis_persistence,
+            &position_tracker,
+            &trades,
+        )
+        .await?
+    };
+    if !trades.is_empty() {
+        market_data::publish_trades(&trades, Some(req.side.clone()));
+        dispatcher::notify_fills(
+            db.get_ref(),
+            redis_pool.get_ref(),
+            ws_server.get_ref(),
+            &trades,
+        );
+        risk::publish_for_trades(ws_server.get_ref(), &trades);
+    }
+    let updated_balance = wallet_after_trades(
+        db.get_ref(),
+        &user,
+        &currency,
+        wallet.as_ref(),
+        &trades,
+        &trade_fees,
+    )
+    .await;
+
+    // Persisted after settlement so fill updates don't overwrite the prevention outcome
+    persist_self_trade_prevented(&db_persistence, &redis_persistence, &self_trade_prevented).await;
+    let mut rejection_reason = self_trade_prevented
+        .iter()
+        .any(|o| o.id == order_id)
+        .then(|| format!("{}:{}", SELF_TRADE_REJECTION, self_trade_prevention));
+
+    // An order that didn't rest is closed: an IOC or market remainder, a
+    // halted sweep or a filled order. Recording that releases what is left of
+    // its hold. A resting order's fills are recorded by settlement as it trades.
+    let closed_order = final_order.filter(|o| !o.is_open());
+    if let Some(closed_order) = &closed_order {
+        if let Err(e) = db_persistence.update_order(closed_order).await {
+            log::error!("Failed to update order {} in database: {}", order_id, e);
+        }
+        if let Err(e) = redis_persistence.save_order(closed_order).await {
+            log::error!("Failed to update order {} in Redis: {}", order_id, e);
+        }
     }
 
-    // Process trades in a database transaction
-    let updated_balance = if !trades.is_empty() {
-        let txn = db.get_ref().begin().await.map_err(|e| {
-            log::error!("Failed to start transaction: {}", e);
-            actix_web::error::ErrorInternalServerError("Transaction error")
-        })?;
-
-        let mut current_balance = user.wallet_balance;
-
-        for trade in &trades {
-            // Validate seller has shares before processing the trade
-            let seller_has_shares = match position_tracker
-                .validate_sell_order(
-                    trade.seller_id,
-                    trade.event_id,
-                    trade.option_id,
-                    trade.quantity,
-                )
-                .await
-            {
-                Ok(has_shares) => has_shares,
-                Err(e) => {
-                    log::error!("Position validation error: {}", e);
-                    let _ = txn.rollback().await;
-                    return Err(actix_web::error::ErrorInternalServerError(
-                        "Failed to validate position",
-                    ));
-                }
-            };
+    // The circuit breaker cancels whatever the incoming order hadn't filled when it tripped
+    if let Some(halt) = triggered_halt {
+        report_circuit_breaker(req.event_id, req.option_id, &halt);
+        if closed_order.is_some_and(|o| o.status == OrderStatus::Cancelled) {
+            rejection_reason.get_or_insert_with(|| TRADING_HALTED_REJECTION.to_string());
+        }
+        broadcast_trading_status(
+            ws_server,
+            req.event_id,
+            req.option_id,
+            sequence,
+            Some(halt),
+            None,
+        );
+    }
 
-            if !seller_has_shares {
-                log::error!(
-                    "Seller {} doesn't have enough shares for trade {}",
-                    trade.seller_id,
-                    trade.id
-                );
-                let _ = txn.rollback().await;
-                return Err(actix_web::error::ErrorInternalServerError(
-                    "Trade execution failed: seller has insufficient shares",
-                ));
-            }
+    // Complement pricing can also fill the order against the other option's book
+    let mut traded_options: Vec<(i32, PriceUpdateSource)> = trades
+        .iter()
+        .map(|t| (t.option_id, PriceUpdateSource::Trade))
+        .chain([(req.option_id, PriceUpdateSource::BookImbalance)])
+        .collect();
+    // Trade sorts first, so dedup keeps it for options that traded
+    traded_options.sort_unstable_by_key(|(option_id, source)| {
+        (*option_id, *source != PriceUpdateSource::Trade)
+    });
+    traded_options.dedup_by_key(|(option_id, _)| *option_id);
 
-            // Save trade to database
-            if let Err(e) = db_persistence.save_trade(trade).await {
-                log::error!("Failed to save trade to database: {}", e);
-                let _ = txn.rollback().await;
-                return Err(actix_web::error::ErrorInternalServerError(
-                    "Failed to save trade",
-                ));
-            }
+    // Convert trades to response format
+    let trade_responses: Vec<TradeResponse> = trades
+        .into_iter()
+        .map(|t| TradeResponse {
+            id: t.id,
+            event_id: t.event_id,
+            option_id: t.option_id,
+            buyer_id: t.buyer_id,
+            seller_id: t.seller_id,
+            price: t.price,
+            quantity: t.quantity,
+            total_amount: t.total_amount,
+            timestamp: t.timestamp,
+            sequence: t.sequence,
+        })
+        .collect();
 
-            // Save trade to Redis
-            if let Err(e) = redis_persistence.save_trade(trade).await {
-                log::error!("Failed to save trade to Redis: {}", e);
-            }
+    // Update event option price immediately based on order book (event-driven)
+    for (option_id, source) in traded_options {
+        let db_clone = db.clone();
+        let redis_pool_clone = redis_pool.clone();
+        let ws_server_clone = ws_server.clone();
+        let event_id = req.event_id;
+        tokio::spawn(async move {
+            crate::order_book::price_updater::update_option_price_immediately(
+                db_clone,
+                redis_pool_clone,
+                ws_server_clone,
+                event_id,
+                option_id,
+                source,
+            )
+            .await;
+        });
+    }
 
-            // Update positions
-            if let Err(e) = position_tracker.update_positions_from_trade(trade).await {
-                log::error!("Failed to update positions: {}", e);
-                let _ = txn.rollback().await;
-                return Err(actix_web::error::ErrorInternalServerError(
-                    "Failed to update positions",
-                ));
-            }
+    // Invalidate caches
+    let cache_service = CacheService::new(redis_pool.get_ref().clone());
+    let event_cache_key = create_cache_key(cache_keys::EVENT_PREFIX, &req.event_id.to_string());
+    let option_cache_key = format!("event_option:{}", req.option_id);
+    let event_order_books_cache_key = format!("order_books:event:{}", req.event_id);
 
-            // Update user balances in the database
-            // Update buyer's balance (decrease)
-            let buyer = users::Entity::find_by_id(trade.buyer_id)
-                .one(&txn)
-                .await
-                .map_err(|e| {
-                    log::error!("Failed to find buyer: {}", e);
-                    actix_web::error::ErrorInternalServerError("Database error")
-                })?
-                .ok_or_else(|| actix_web::error::ErrorInternalServerError("Buyer not found"))?;
-
-            let mut active_buyer: users::ActiveModel = buyer.into();
-            let new_buyer_balance = active_buyer.wallet_balance.as_ref() - trade.total_amount;
-            if new_buyer_balance < sea_orm::prelude::Decimal::new(0, 2) {
-                let _ = txn.rollback().await;
-                return Err(actix_web::error::ErrorBadRequest(
-                    "Insufficient buyer balance",
-                ));
-            }
-            active_buyer.wallet_balance = Set(new_buyer_balance);
-            active_buyer.updated_at = Set(chrono::Utc::now().naive_utc());
-            if let Err(e) = active_buyer.update(&txn).await {
-                log::error!("Failed to update buyer balance: {}", e);
-                let _ = txn.rollback().await;
-                return Err(actix_web::error::ErrorInternalServerError(
-                    "Failed to update balance",
-                ));
+    if let Err(e) = cache_service.delete(&event_cache_key).await {
+        log::warn!("Failed to invalidate event cache: {}", e);
+    }
+    if let Err(e) = cache_service.delete(&option_cache_key).await {
+        log::warn!("Failed to invalidate option cache: {}", e);
+    }
+    if let Err(e) = cache_service.delete(&event_order_books_cache_key).await {
+        log::warn!("Failed to invalidate event order books cache: {}", e);
+    }
+
+    Ok(HttpResponse::Ok().json(PlaceOrderResponse {
+        success: true,
+        order_id,
+        client_order_id: req.client_order_id.clone(),
+        trades: trade_responses,
+        currency,
+        wallet_balance: updated_balance,
+        rejection_reason,
+    }))
+}
+
+/// Change in `user_id`'s wallet balance from the given trades
+fn net_balance_change(trades: &[Trade], fees: &[TradeFees], user_id: i32) -> Decimal {
+    trades
+        .iter()
+        .zip(fees)
+        .map(|(trade, fees)| {
+            let mut change = Decimal::ZERO;
+            if trade.buyer_id == user_id {
+                change -= trade.total_amount + fees.buyer_fee;
             }
+            if trade.seller_id == user_id {
+                change += trade.total_amount - fees.seller_fee;
+            }
+            change
+        })
+        .sum()
+}
 
-            // Update seller's balance (increase)
-            let seller = users::Entity::find_by_id(trade.seller_id)
-                .one(&txn)
-                .await
-                .map_err(|e| {
-                    log::error!("Failed to find seller: {}", e);
-                    actix_web::error::ErrorInternalServerError("Database error")
-                })?
-                .ok_or_else(|| actix_web::error::ErrorInternalServerError("Seller not found"))?;
-
-            let mut active_seller: users::ActiveModel = seller.into();
-            let new_seller_balance = active_seller.wallet_balance.as_ref() + trade.total_amount;
-            active_seller.wallet_balance = Set(new_seller_balance);
-            active_seller.updated_at = Set(chrono::Utc::now().naive_utc());
-            if let Err(e) = active_seller.update(&txn).await {
-                log::error!("Failed to update seller balance: {}", e);
-                let _ = txn.rollback().await;
-                return Err(actix_web::error::ErrorInternalServerError(
-                    "Failed to update balance",
-                ));
+/// Balance of `user`'s `currency` wallet after the given trades. Margin
+/// accounts also move collateral in and out of the wallet as they trade, so
+/// theirs is reloaded.
+async fn wallet_after_trades(
+    db: &DatabaseConnection,
+    user: &users::Model,
+    currency: &str,
+    wallet: Option<&wallets::Model>,
+    trades: &[Trade],
+    fees: &[TradeFees],
+) -> Decimal {
+    let estimate = wallet.map(|wallet| wallet.balance).unwrap_or_default()
+        + net_balance_change(trades, fees, user.id);
+    if !user.margin_enabled || trades.is_empty() {
+        return estimate;
+    }
+    match balances::find(db, user.id, currency).await {
+        Ok(Some(reloaded)) => reloaded.balance,
+        Ok(None) => estimate,
+        Err(e) => {
+            log::warn!("Failed to reload balance of user {}: {}", user.id, e);
+            estimate
+        }
+    }
+}
+
+/// Persist executed trades and move balances between buyers and sellers, charging
+/// each side its tier-discounted trading fee. Returns the fees in trade order.
+///
+/// A trade that is saved but can't be settled is marked failed and settled
+/// later by the retry job; it doesn't fail the others.
+pub async fn settle_trades(
+    db: &DatabaseConnection,
+    db_persistence: &DbPersistence,
+    redis_persistence: &RedisOrderBookPersistence,
+    position_tracker: &PositionTracker,
+    trades: &[Trade],
+) -> Result<Vec<TradeFees>, Error> {
+    // Validate every seller has the shares for all their trades before processing any
+    match position_tracker.validate_trade_sellers(trades).await {
+        Ok(None) => {}
+        Ok(Some(trade)) => {
+            log::error!(
+                "Seller {} doesn't have enough shares for trade {}",
+                trade.seller_id,
+                trade.id
+            );
+            return Err(actix_web::error::ErrorInternalServerError(
+                "Trade execution failed: seller has insufficient shares",
+            ));
+        }
+        Err(e) => {
+            log::error!("Position validation error: {}", e);
+            return Err(actix_web::error::ErrorInternalServerError(
+                "Failed to validate position",
+            ));
+        }
+    }
+
+    let mut traders: Vec<i32> = trades
+        .iter()
+        .flat_map(|t| [t.buyer_id, t.seller_id])
+        .collect();
+    traders.sort_unstable();
+    traders.dedup();
+    let benefits = TierService::new(db.clone())
+        .benefits_for_users(&traders)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to resolve trader tiers: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to resolve user tier")
+        })?;
+    let standard_fees: Vec<TradeFees> = trades
+        .iter()
+        .map(|trade| fees::fees_for_trade(trade, &benefits))
+        .collect();
+
+    // Trades on events under a fee-free promotion are not charged; the fee that
+    // would have been charged is recorded against the promotion instead
+    let mut event_ids: Vec<i32> = trades.iter().map(|t| t.event_id).collect();
+    event_ids.sort_unstable();
+    event_ids.dedup();
+    let fee_free = PromotionService::new(db.clone())
+        .fee_free_promotions(&event_ids, chrono::Utc::now())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to resolve fee-free promotions: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to resolve promotions")
+        })?;
+    let trade_fees: Vec<TradeFees> = trades
+        .iter()
+        .zip(&standard_fees)
+        .map(|(trade, standard)| {
+            if fee_free.contains_key(&trade.event_id) {
+                TradeFees::default()
+            } else {
+                *standard
             }
+        })
+        .collect();
+
+    // Every trade is recorded, pending settlement, before any is settled, so
+    // one that fails to settle is left for the retry job rather than lost
+    for ((trade, fees), standard) in trades.iter().zip(&trade_fees).zip(&standard_fees) {
+        let waiver = fee_free.get(&trade.event_id).map(|promotion| FeeWaiver {
+            promotion_id: promotion.id,
+            waived: *standard,
+        });
+        if let Err(e) = db_persistence
+            .save_trade(trade, fees, waiver.as_ref())
+            .await
+        {
+            log::error!("Failed to save trade to database: {}", e);
+            return Err(actix_web::error::ErrorInternalServerError(
+                "Failed to save trade",
+            ));
+        }
+
+        if let Err(e) = redis_persistence.save_trade(trade).await {
+            log::error!("Failed to save trade to Redis: {}", e);
+        }
+    }
+
+    for (trade, fees) in trades.iter().zip(&trade_fees) {
+        if let Err(e) =
+            settlement::settle_or_record_failure(db, position_tracker, trade, fees).await
+        {
+            ops::publish(
+                OpsEventKind::TradeSettlementFailed,
+                OpsSeverity::Warning,
+                format!("Trade {} failed to settle and will be retried", trade.id),
+                json!({ "trade_id": trade.id, "error": e }),
+            );
+        }
+    }
+
+    // Charts are derived data; a failure here must not fail settled trades
+    match CandleService::new(db.clone()).record_trades(trades).await {
+        Ok(candles) => market_data::publish_candles(candles),
+        Err(e) => log::error!("{}", e),
+    }
+
+    Ok(trade_fees)
+}
+
+/// Write orders cancelled or decremented by self-trade prevention to the database and Redis
+pub async fn persist_self_trade_prevented(
+    db_persistence: &DbPersistence,
+    redis_persistence: &RedisOrderBookPersistence,
+    orders: &[Order],
+) {
+    for order in orders {
+        if let Err(e) = db_persistence.amend_order(order).await {
+            log::error!(
+                "Failed to update self-trade prevented order in database: {}",
+                e
+            );
+        }
+        if let Err(e) = redis_persistence.save_order(order).await {
+            log::error!(
+                "Failed to update self-trade prevented order in Redis: {}",
+                e
+            );
+        }
+    }
+}
+
+pub async fn cancel_order(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    req: web::Json<CancelOrderRequest>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id_str = &*user_id;
+    let user_id_int: i32 = user_id_str
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    cancel_user_order(&db, &redis_pool, &ws_server, user_id_int, &req.order_id).await
+}
+
+/// Cancel an order by the client order id its owner gave it
+pub async fn cancel_order_by_client_id(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    client_order_id: web::Path<String>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id_int: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    let order = DbPersistence::new(db.get_ref().clone())
+        .get_order_by_client_id(user_id_int, &client_order_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to load order: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to load order")
+        })?;
+
+    match order {
+        Some(order) => {
+            cancel_user_order(&db, &redis_pool, &ws_server, user_id_int, &order.id).await
+        }
+        None => Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Order not found"
+        }))),
+    }
+}
 
-            // Track balance changes for response
-            if trade.buyer_id == user_id_int {
-                current_balance -= trade.total_amount;
-            } else if trade.seller_id == user_id_int {
-                current_balance += trade.total_amount;
+/// Cancel `order_id` on its order book, provided it belongs to `user_id`
+async fn cancel_user_order(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    user_id_int: i32,
+    order_id: &str,
+) -> Result<HttpResponse, Error> {
+    let db_persistence = DbPersistence::new(db.get_ref().clone());
+    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
+
+    // Load the order to find which order book it belongs to
+    let order_to_cancel = redis_persistence.load_order(order_id).await.map_err(|e| {
+        log::error!("Failed to load order: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to load order")
+    })?;
+
+    let order_to_cancel = match order_to_cancel {
+        Some(order) => {
+            // Verify the order belongs to the user
+            if order.user_id != user_id_int {
+                return Ok(HttpResponse::Forbidden().json(json!({
+                    "success": false,
+                    "message": "You can only cancel your own orders"
+                })));
             }
+            order
+        }
+        None => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "success": false,
+                "message": "Order not found"
+            })));
+        }
+    };
 
-            // Update order statuses in database
-            let buy_order = Order {
-                id: trade.buy_order_id.clone(),
-                user_id: trade.buyer_id,
-                event_id: trade.event_id,
-                option_id: trade.option_id,
-                side: OrderSide::Buy,
-                order_type: OrderType::Limit,
-                time_in_force: TimeInForce::GTC,
-                price: trade.price,
-                quantity: 0,
-                filled_quantity: trade.quantity,
-                status: OrderStatus::Filled,
-                created_at: chrono::Utc::now(),
-                updated_at: chrono::Utc::now(),
-            };
-            let _ = db_persistence.update_order(&buy_order).await;
-
-            let sell_order = Order {
-                id: trade.sell_order_id.clone(),
-                user_id: trade.seller_id,
-                event_id: trade.event_id,
-                option_id: trade.option_id,
-                side: OrderSide::Sell,
-                order_type: OrderType::Limit,
-                time_in_force: TimeInForce::GTC,
-                price: trade.price,
-                quantity: 0,
-                filled_quantity: trade.quantity,
-                status: OrderStatus::Filled,
-                created_at: chrono::Utc::now(),
-                updated_at: chrono::Utc::now(),
-            };
-            let _ = db_persistence.update_order(&sell_order).await;
+    let event_id = order_to_cancel.event_id;
+    let option_id = order_to_cancel.option_id;
+
+    // Cancel the order on the event's execution venue
+    let cancelled_order = router::cancel(db.get_ref(), redis_pool.get_ref(), order_to_cancel)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to reach execution venue: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to get order book")
+        })?
+        .map_err(|e| {
+            log::error!("Failed to cancel order: {}", e);
+            actix_web::error::ErrorInternalServerError(e)
+        })?;
+
+    persist_cancelled_order(&db_persistence, &redis_persistence, &cancelled_order).await;
+    refresh_order_book(db, redis_pool, ws_server, event_id, option_id).await;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Order cancelled successfully",
+        "order": OrderResponse::from(cancelled_order)
+    })))
+}
+
+/// Store a cancelled order's status in the database and Redis
+async fn persist_cancelled_order(
+    db_persistence: &DbPersistence,
+    redis_persistence: &RedisOrderBookPersistence,
+    cancelled_order: &Order,
+) {
+    if let Err(e) = db_persistence.update_order(cancelled_order).await {
+        log::error!("Failed to update order status in database: {}", e);
+    }
+
+    if let Err(e) = redis_persistence
+        .update_order_status(
+            &cancelled_order.id,
+            OrderStatus::Cancelled,
+            cancelled_order.filled_quantity,
+        )
+        .await
+    {
+        log::error!("Failed to update order status in Redis: {}", e);
+    }
+}
+
+/// Reprice an option and drop its cached order books after orders leave it
+async fn refresh_order_book(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    event_id: i32,
+    option_id: i32,
+) {
+    // Update event option price immediately based on order book (event-driven)
+    let db_clone = db.clone();
+    let redis_pool_clone = redis_pool.clone();
+    let ws_server_clone = ws_server.clone();
+    tokio::spawn(async move {
+        crate::order_book::price_updater::update_option_price_immediately(
+            db_clone,
+            redis_pool_clone,
+            ws_server_clone,
+            event_id,
+            option_id,
+            PriceUpdateSource::BookImbalance,
+        )
+        .await;
+    });
+
+    // Invalidate caches
+    let cache_service = CacheService::new(redis_pool.get_ref().clone());
+    let event_order_books_cache_key = format!("order_books:event:{}", event_id);
+
+    if let Err(e) = cache_service.delete(&event_order_books_cache_key).await {
+        log::warn!("Failed to invalidate event order books cache: {}", e);
+    }
+}
+
+/// Cancel every open order of the user, optionally only those in one event
+/// or option, returning the ids of the orders cancelled
+pub async fn cancel_all_orders(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    query: web::Query<CancelAllOrdersQuery>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id_int: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    let cancelled_order_ids = cancel_open_orders(
+        &db,
+        &redis_pool,
+        &ws_server,
+        user_id_int,
+        query.event_id,
+        query.option_id,
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Failed to load open orders: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to load orders")
+    })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": format!("{} orders cancelled", cancelled_order_ids.len()),
+        "cancelled_order_ids": cancelled_order_ids
+    })))
+}
+
+/// Cancel a user's open orders on their venues, optionally only those in one
+/// event or option, returning the ids of the orders cancelled. An order that
+/// fills or is cancelled elsewhere meanwhile is skipped.
+pub async fn cancel_open_orders(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    user_id: i32,
+    event_id: Option<i32>,
+    option_id: Option<i32>,
+) -> Result<Vec<String>, String> {
+    let db_persistence = DbPersistence::new(db.get_ref().clone());
+    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
+
+    let open_orders = db_persistence.get_open_orders(user_id).await?;
+
+    let mut cancelled_order_ids = Vec::new();
+    let mut books = Vec::new();
+    for order in open_orders.into_iter().filter(|o| {
+        event_id.is_none_or(|id| o.event_id == id) && option_id.is_none_or(|id| o.option_id == id)
+    }) {
+        let (order_id, book) = (order.id.clone(), (order.event_id, order.option_id));
+        let cancelled_order = match router::cancel(db.get_ref(), redis_pool.get_ref(), order).await
+        {
+            Ok(Ok(cancelled_order)) => cancelled_order,
+            Ok(Err(e)) => {
+                log::warn!("Skipped cancelling order {}: {}", order_id, e);
+                continue;
+            }
+            Err(e) => {
+                log::error!("Failed to reach execution venue: {}", e);
+                continue;
+            }
+        };
+
+        persist_cancelled_order(&db_persistence, &redis_persistence, &cancelled_order).await;
+        cancelled_order_ids.push(order_id);
+        if !books.contains(&book) {
+            books.push(book);
+        }
+    }
+
+    for (event_id, option_id) in books {
+        refresh_order_book(db, redis_pool, ws_server, event_id, option_id).await;
+    }
+
+    Ok(cancelled_order_ids)
+}
+
+/// Cancel every open order of an event that can no longer trade, across all
+/// its options, returning the ids of the orders cancelled. An order its venue
+/// no longer holds is closed out anyway, since nothing can fill it now.
+pub async fn cancel_event_orders(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    event_id: i32,
+) -> Result<Vec<String>, String> {
+    let db_persistence = DbPersistence::new(db.get_ref().clone());
+    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
+
+    let open_orders = db_persistence.get_event_open_orders(event_id).await?;
+
+    let mut cancelled_order_ids = Vec::new();
+    let mut option_ids = Vec::new();
+    for mut order in open_orders {
+        let (order_id, option_id) = (order.id.clone(), order.option_id);
+        match router::cancel(db.get_ref(), redis_pool.get_ref(), order.clone()).await {
+            Ok(Ok(cancelled_order)) => order = cancelled_order,
+            Ok(Err(_)) => order.cancel(),
+            Err(e) => {
+                log::error!(
+                    "Failed to reach execution venue to cancel {}: {}",
+                    order_id,
+                    e
+                );
+                order.cancel();
+            }
+        }
+
+        persist_cancelled_order(&db_persistence, &redis_persistence, &order).await;
+        cancelled_order_ids.push(order_id);
+        if !option_ids.contains(&option_id) {
+            option_ids.push(option_id);
+        }
+    }
+
+    for option_id in option_ids {
+        refresh_order_book(db, redis_pool, ws_server, event_id, option_id).await;
+    }
+
+    Ok(cancelled_order_ids)
+}
+
+pub async fn amend_order(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    order_id: web::Path<String>,
+    req: web::Json<AmendOrderRequest>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id_str = &*user_id;
+    let user_id_int: i32 = user_id_str
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+    let order_id = order_id.into_inner();
+
+    if req.price.is_none() && req.quantity.is_none() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Price or quantity is required"
+        })));
+    }
+
+    let db_persistence = DbPersistence::new(db.get_ref().clone());
+    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
+    let position_tracker = PositionTracker::new(db.get_ref().clone());
+
+    // Load the order to find which order book it belongs to
+    let existing_order = redis_persistence.load_order(&order_id).await.map_err(|e| {
+        log::error!("Failed to load order: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to load order")
+    })?;
+
+    let existing_order = match existing_order {
+        Some(order) => {
+            // Verify the order belongs to the user
+            if order.user_id != user_id_int {
+                return Ok(HttpResponse::Forbidden().json(json!({
+                    "success": false,
+                    "message": "You can only amend your own orders"
+                })));
+            }
+            order
         }
+        None => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "success": false,
+                "message": "Order not found"
+            })));
+        }
+    };
+
+    let event_id = existing_order.event_id;
+    let option_id = existing_order.option_id;
+
+    // Amendments are only allowed while the event is open for trading
+    let event = events::Entity::find_by_id(event_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
 
-        txn.commit().await.map_err(|e| {
-            log::error!("Failed to commit transaction: {}", e);
-            actix_web::error::ErrorInternalServerError("Transaction error")
+    let event = match event {
+        Some(e) if e.status == "active" && e.end_time > chrono::Utc::now().naive_utc() => e,
+        _ => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": "Event is not active for trading"
+            })));
+        }
+    };
+
+    // Only orders resting on the internal order book can be amended in place
+    if router::ExecutionVenue::parse(&event.execution_venue) != router::ExecutionVenue::Internal {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Orders on this event's execution venue can't be amended; cancel and replace them instead"
+        })));
+    }
+
+    let user = users::Entity::find_by_id(user_id_int)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?
+        .ok_or_else(|| actix_web::error::ErrorBadRequest("User not found"))?;
+    let wallet = balances::find(db.get_ref(), user_id_int, &event.currency)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let new_price = req.price.unwrap_or(existing_order.price);
+    let new_quantity = req.quantity.unwrap_or(existing_order.quantity);
+    let new_remaining = new_quantity - existing_order.filled_quantity;
+
+    let benefits = TierService::new(db.get_ref().clone())
+        .benefits(user_id_int)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to resolve user tier: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to resolve user tier")
+        })?;
+    if let Err(message) = validate_order_limits(
+        &event,
+        new_price,
+        new_quantity,
+        benefits.order_size_multiplier,
+    ) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": message
+        })));
+    }
+
+    // Validate the amended order the same way as a new one
+    match existing_order.side {
+        OrderSide::Buy => {
+            // The order's current hold is released as the new one is taken
+            let required_amount = reservations::hold_amount(
+                existing_order.hold_price.unwrap_or(new_price),
+                new_remaining,
+            );
+            let available = balances::available_in(wallet.as_ref())
+                + reservations::order_reservation(&existing_order);
+            if available < required_amount {
+                return Ok(HttpResponse::BadRequest().json(json!({
+                    "success": false,
+                    "message": "Insufficient balance"
+                })));
+            }
+        }
+        OrderSide::Sell => {
+            if new_quantity > existing_order.quantity || new_price < existing_order.price {
+                let position = position_tracker
+                    .get_user_position(user_id_int, event_id, option_id)
+                    .await
+                    .map_err(|e| {
+                        log::error!("Position validation error: {}", e);
+                        actix_web::error::ErrorInternalServerError("Failed to validate position")
+                    })?;
+
+                let covered = position_tracker
+                    .complementary_holdings(user_id_int, event_id, option_id)
+                    .await
+                    .map_err(|e| {
+                        log::error!("Position validation error: {}", e);
+                        actix_web::error::ErrorInternalServerError("Failed to validate position")
+                    })?;
+
+                // The order's current collateral hold is released as the new
+                // one is taken
+                if let Err(message) = margin::validate_sell(
+                    &user,
+                    balances::available_in(wallet.as_ref())
+                        + reservations::order_reservation(&existing_order),
+                    position.quantity,
+                    covered,
+                    new_remaining,
+                    new_price,
+                ) {
+                    return Ok(HttpResponse::BadRequest().json(json!({
+                        "success": false,
+                        "message": message
+                    })));
+                }
+            }
+        }
+    }
+
+    // Amend the order on the resident order book
+    let (amendment, self_trade_prevented, triggered_halt, sequence) =
+        matching_service::execute(&redis_pool, event_id, option_id, {
+            let order_id = order_id.clone();
+            let (price, quantity) = (req.price, req.quantity);
+            move |order_book| {
+                let amendment = order_book.amend_order(&order_id, price, quantity);
+                (
+                    amendment,
+                    order_book.take_self_trade_prevented(),
+                    order_book.take_triggered_halt(),
+                    order_book.sequence(),
+                )
+            }
+        })
+        .await
+        .map_err(|e| {
+            log::error!("Failed to reach order book: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to get order book")
         })?;
 
-        current_balance
+    let (mut amended_order, trades) = match amendment {
+        Ok(result) => result,
+        Err(e) => {
+            log::warn!("Order amendment rejected: {}", e);
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": e
+            })));
+        }
+    };
+
+    // Record the new price and quantity before the amendment's trades settle,
+    // so their fills count against the amended quantity, and take any larger
+    // hold, which the balance may no longer cover
+    let filled_now: i32 = trades
+        .iter()
+        .filter(|t| t.buy_order_id == order_id || t.sell_order_id == order_id)
+        .map(|t| t.quantity)
+        .sum();
+    let mut amendment = amended_order.clone();
+    amendment.filled_quantity -= filled_now;
+    amendment.status = if amendment.filled_quantity > 0 {
+        OrderStatus::PartiallyFilled
+    } else {
+        OrderStatus::Pending
+    };
+    let mut message = "Order amended successfully".to_string();
+    if let Err(e) = db_persistence.amend_order(&amendment).await {
+        log::error!("Failed to amend order {} in database: {}", order_id, e);
+        // The order can't rest without its hold, so what is left of it is cancelled
+        if amended_order.is_open() {
+            let cancelled = matching_service::execute(&redis_pool, event_id, option_id, {
+                let order_id = order_id.clone();
+                move |order_book| order_book.cancel_order(&order_id)
+            })
+            .await;
+            if let Err(e) = cancelled.and_then(|result| result.map(|_| ())) {
+                log::error!("Failed to cancel unheld order {}: {}", order_id, e);
+            }
+            amended_order.cancel();
+        }
+        message = if e == reservations::INSUFFICIENT_BALANCE {
+            "Insufficient balance for the amended order; its remainder was cancelled".to_string()
+        } else {
+            "Failed to record the amendment; the order's remainder was cancelled".to_string()
+        };
+    }
+
+    // Update order in Redis
+    if let Err(e) = redis_persistence.save_order(&amended_order).await {
+        log::error!("Failed to save amended order to Redis: {}", e);
+    }
+
+    // A price amendment that crosses the spread trades immediately
+    let trade_fees = if trades.is_empty() {
+        Vec::new()
     } else {
-        user.wallet_balance
+        settle_trades(
+            db.get_ref(),
+            &db_persistence,
+            &redis_persistence,
+            &position_tracker,
+            &trades,
+        )
+        .await?
     };
+    if !trades.is_empty() {
+        market_data::publish_trades(&trades, Some(amended_order.side.clone()));
+        dispatcher::notify_fills(
+            db.get_ref(),
+            redis_pool.get_ref(),
+            ws_server.get_ref(),
+            &trades,
+        );
+        risk::publish_for_trades(ws_server.get_ref(), &trades);
+    }
+    let updated_balance = wallet_after_trades(
+        db.get_ref(),
+        &user,
+        &event.currency,
+        wallet.as_ref(),
+        &trades,
+        &trade_fees,
+    )
+    .await;
+
+    persist_self_trade_prevented(&db_persistence, &redis_persistence, &self_trade_prevented).await;
+
+    // An amendment the circuit breaker or self-trade prevention stopped, or
+    // that couldn't be held, is closed along with the rest of its hold
+    if !amended_order.is_open() {
+        if let Err(e) = db_persistence.update_order(&amended_order).await {
+            log::error!("Failed to update amended order in database: {}", e);
+        }
+    }
 
-    // Convert trades to response format
+    if let Some(halt) = triggered_halt {
+        report_circuit_breaker(event_id, option_id, &halt);
+        broadcast_trading_status(&ws_server, event_id, option_id, sequence, Some(halt), None);
+    }
+
+    let price_source = if trades.is_empty() {
+        PriceUpdateSource::BookImbalance
+    } else {
+        PriceUpdateSource::Trade
+    };
     let trade_responses: Vec<TradeResponse> = trades
         .into_iter()
         .map(|t| TradeResponse {
@@ -401,6 +1665,7 @@ pub async fn place_order(
             quantity: t.quantity,
             total_amount: t.total_amount,
             timestamp: t.timestamp,
+            sequence: t.sequence,
         })
         .collect();
 
@@ -408,8 +1673,6 @@ pub async fn place_order(
     let db_clone = db.clone();
     let redis_pool_clone = redis_pool.clone();
     let ws_server_clone = ws_server.clone();
-    let event_id = req.event_id;
-    let option_id = req.option_id;
     tokio::spawn(async move {
         crate::order_book::price_updater::update_option_price_immediately(
             db_clone,
@@ -417,179 +1680,418 @@ pub async fn place_order(
             ws_server_clone,
             event_id,
             option_id,
+            price_source,
         )
         .await;
     });
 
     // Invalidate caches
     let cache_service = CacheService::new(redis_pool.get_ref().clone());
-    let event_cache_key = create_cache_key(cache_keys::EVENT_PREFIX, &req.event_id.to_string());
-    let option_cache_key = format!("event_option:{}", req.option_id);
-    let order_book_cache_key = format!("order_book:{}:{}", req.event_id, req.option_id);
+    let event_order_books_cache_key = format!("order_books:event:{}", event_id);
 
-    if let Err(e) = cache_service.delete(&event_cache_key).await {
-        log::warn!("Failed to invalidate event cache: {}", e);
+    if let Err(e) = cache_service.delete(&event_order_books_cache_key).await {
+        log::warn!("Failed to invalidate event order books cache: {}", e);
     }
-    if let Err(e) = cache_service.delete(&option_cache_key).await {
-        log::warn!("Failed to invalidate option cache: {}", e);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": message,
+        "order": OrderResponse::from(amended_order),
+        "trades": trade_responses,
+        "currency": event.currency,
+        "wallet_balance": updated_balance
+    })))
+}
+
+/// Let on-call admins know the circuit breaker halted an option
+pub fn report_circuit_breaker(event_id: i32, option_id: i32, halt: &TradingHalt) {
+    ops::publish(
+        OpsEventKind::CircuitBreakerTripped,
+        OpsSeverity::Warning,
+        format!(
+            "Circuit breaker halted event {} option {}: {}",
+            event_id, option_id, halt.reason
+        ),
+        json!({ "event_id": event_id, "option_id": option_id, "halt": halt }),
+    );
+}
+
+/// Broadcast the halt and auction state of an option on the event channel, as
+/// of order book change `sequence`
+pub fn broadcast_trading_status(
+    ws_server: &Addr<WebSocketServer>,
+    event_id: i32,
+    option_id: i32,
+    sequence: u64,
+    halt: Option<TradingHalt>,
+    auction: Option<AuctionState>,
+) {
+    ws_server.do_send(Broadcast {
+        channel: SubscriptionChannel::Event(event_id),
+        message: WebSocketMessage::TradingStatus {
+            event_id,
+            option_id,
+            sequence,
+            halted: halt.is_some(),
+            halt,
+            auction,
+            timestamp: chrono::Utc::now(),
+        },
+    });
+}
+
+/// Check the event and option of a book exist, so looking one up never
+/// creates a book. Returns the `404` response if either is missing.
+async fn find_book_option(
+    db: &web::Data<DatabaseConnection>,
+    event_id: i32,
+    option_id: i32,
+) -> Result<Option<HttpResponse>, Error> {
+    let event = events::Entity::find_by_id(event_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    if event.is_none() {
+        return Ok(Some(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Event not found"
+        }))));
     }
-    if let Err(e) = cache_service.delete(&order_book_cache_key).await {
-        log::warn!("Failed to invalidate order book cache: {}", e);
+
+    let option = event_options::Entity::find_by_id(option_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    if option.is_none_or(|option| option.event_id != event_id) {
+        return Ok(Some(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Option not found"
+        }))));
+    }
+
+    Ok(None)
+}
+
+pub async fn get_trading_status(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, Error> {
+    let (event_id, option_id) = path.into_inner();
+
+    if let Some(not_found) = find_book_option(&db, event_id, option_id).await? {
+        return Ok(not_found);
     }
 
-    Ok(HttpResponse::Ok().json(PlaceOrderResponse {
-        success: true,
-        order_id,
-        trades: trade_responses,
-        wallet_balance: updated_balance,
-    }))
+    let (halt, auction, indicative_uncross) =
+        matching_service::query(&redis_pool, event_id, option_id, |order_book| {
+            let halt = order_book.current_halt().cloned();
+            let auction = order_book.auction().cloned();
+            let indicative_uncross = auction
+                .as_ref()
+                .and_then(|_| order_book.calculate_uncrossing_price());
+            (halt, auction, indicative_uncross)
+        })
+        .await
+        .map_err(|e| {
+            log::error!("Failed to reach order book: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to get order book")
+        })?;
+    let indicative_price =
+        indicative_uncross.map(|(price, volume)| json!({ "price": price, "volume": volume }));
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "event_id": event_id,
+        "option_id": option_id,
+        "halted": halt.is_some(),
+        "halt": halt,
+        "auction": auction,
+        "indicative_uncross": indicative_price
+    })))
+}
+
+pub async fn start_auction(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    path: web::Path<(i32, i32)>,
+    req: web::Json<StartAuctionRequest>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let (event_id, option_id) = path.into_inner();
+
+    let duration_seconds = req
+        .duration_seconds
+        .unwrap_or_else(config::get_auction_default_duration_seconds);
+    if duration_seconds <= 0 {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Auction duration must be positive"
+        })));
+    }
+
+    let option = event_options::Entity::find_by_id(option_id)
+        .filter(event_options::Column::EventId.eq(event_id))
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    if option.is_none() {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Event option not found"
+        })));
+    }
+
+    let (started, halt, sequence) =
+        matching_service::execute(&redis_pool, event_id, option_id, move |order_book| {
+            let started = order_book.start_auction(chrono::Duration::seconds(duration_seconds));
+            (
+                started,
+                order_book.current_halt().cloned(),
+                order_book.sequence(),
+            )
+        })
+        .await
+        .map_err(|e| {
+            log::error!("Failed to reach order book: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to get order book")
+        })?;
+
+    let auction = match started {
+        Ok(auction) => auction,
+        Err(e) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": e
+            })));
+        }
+    };
+
+    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
+    redis_persistence
+        .schedule_auction(event_id, option_id, &auction)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to schedule auction: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to start auction")
+        })?;
+
+    log::info!(
+        "Auction started for event {} option {} by admin {}, ends at {}",
+        event_id,
+        option_id,
+        admin_id,
+        auction.ends_at
+    );
+
+    broadcast_trading_status(
+        &ws_server,
+        event_id,
+        option_id,
+        sequence,
+        halt,
+        Some(auction.clone()),
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Auction started",
+        "auction": auction
+    })))
+}
+
+pub async fn halt_trading(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    path: web::Path<(i32, i32)>,
+    req: web::Json<HaltTradingRequest>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let (event_id, option_id) = path.into_inner();
+
+    if req.duration_seconds.is_some_and(|seconds| seconds <= 0) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Halt duration must be positive"
+        })));
+    }
+
+    let option = event_options::Entity::find_by_id(option_id)
+        .filter(event_options::Column::EventId.eq(event_id))
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    if option.is_none() {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Event option not found"
+        })));
+    }
+
+    let reason = req
+        .reason
+        .clone()
+        .unwrap_or_else(|| "Halted by administrator".to_string());
+    let duration = req.duration_seconds.map(chrono::Duration::seconds);
+    let (halt, auction, sequence) =
+        matching_service::execute(&redis_pool, event_id, option_id, move |order_book| {
+            let halt = order_book.halt_trading(reason, duration);
+            (halt, order_book.auction().cloned(), order_book.sequence())
+        })
+        .await
+        .map_err(|e| {
+            log::error!("Failed to reach order book: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to get order book")
+        })?;
+
+    log::info!(
+        "Trading halted for event {} option {} by admin {}: {}",
+        event_id,
+        option_id,
+        admin_id,
+        halt.reason
+    );
+
+    broadcast_trading_status(
+        &ws_server,
+        event_id,
+        option_id,
+        sequence,
+        Some(halt.clone()),
+        auction,
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Trading halted",
+        "halt": halt
+    })))
 }
 
-pub async fn cancel_order(
-    db: web::Data<DatabaseConnection>,
+pub async fn resume_trading(
     redis_pool: web::Data<Pool>,
     ws_server: web::Data<Addr<WebSocketServer>>,
-    req: web::Json<CancelOrderRequest>,
-    user_id: web::ReqData<String>,
+    path: web::Path<(i32, i32)>,
+    auth_user: web::ReqData<AuthenticatedUser>,
 ) -> Result<HttpResponse, Error> {
-    let user_id_str = &*user_id;
-    let user_id_int: i32 = user_id_str
-        .parse()
-        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
-
-    let db_persistence = DbPersistence::new(db.get_ref().clone());
-    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
+    let admin_id = get_user_id(&auth_user)?;
+    let (event_id, option_id) = path.into_inner();
 
-    // Load the order to find which order book it belongs to
-    let order_to_cancel = redis_persistence
-        .load_order(&req.order_id)
+    let (resumed, auction, sequence) =
+        matching_service::execute(&redis_pool, event_id, option_id, |order_book| {
+            (
+                order_book.resume_trading(),
+                order_book.auction().cloned(),
+                order_book.sequence(),
+            )
+        })
         .await
         .map_err(|e| {
-            log::error!("Failed to load order: {}", e);
-            actix_web::error::ErrorInternalServerError("Failed to load order")
+            log::error!("Failed to reach order book: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to get order book")
         })?;
 
-    let order_to_cancel = match order_to_cancel {
-        Some(order) => {
-            // Verify the order belongs to the user
-            if order.user_id != user_id_int {
-                return Ok(HttpResponse::Forbidden().json(json!({
-                    "success": false,
-                    "message": "You can only cancel your own orders"
-                })));
-            }
-            order
-        }
-        None => {
-            return Ok(HttpResponse::NotFound().json(json!({
-                "success": false,
-                "message": "Order not found"
-            })));
-        }
-    };
+    if let Err(e) = resumed {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": e
+        })));
+    }
 
-    let event_id = order_to_cancel.event_id;
-    let option_id = order_to_cancel.option_id;
+    log::info!(
+        "Trading resumed for event {} option {} by admin {}",
+        event_id,
+        option_id,
+        admin_id
+    );
 
-    // Load the order book
-    let mut order_book = redis_persistence
-        .get_or_create_order_book(event_id, option_id)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to get order book from Redis: {}", e);
-            actix_web::error::ErrorInternalServerError("Failed to get order book")
-        })?;
+    broadcast_trading_status(&ws_server, event_id, option_id, sequence, None, auction);
 
-    // Cancel the order
-    let cancelled_order = order_book.cancel_order(&req.order_id).map_err(|e| {
-        log::error!("Failed to cancel order: {}", e);
-        actix_web::error::ErrorInternalServerError(e)
-    })?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Trading resumed"
+    })))
+}
 
-    // Save updated order book back to Redis
-    if let Err(e) = redis_persistence
-        .save_full_order_book(event_id, option_id, &order_book)
-        .await
-    {
-        log::error!("Failed to save updated order book to Redis: {}", e);
-    }
+/// The top of the book as its owning task last published it, which is
+/// replaced after every change. Books not loaded since their view expired
+/// are loaded, which publishes a fresh view.
+pub async fn get_order_book(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, Error> {
+    let (event_id, option_id) = path.into_inner();
 
-    // Update order status in database
-    if let Err(e) = db_persistence.update_order(&cancelled_order).await {
-        log::error!("Failed to update order status in database: {}", e);
+    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
+    match redis_persistence.load_book_view(event_id, option_id).await {
+        Ok(Some(order_book)) => {
+            return Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "order_book": order_book
+            })))
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to read order book view: {}", e),
     }
 
-    // Update order status in Redis
-    if let Err(e) = redis_persistence
-        .update_order_status(
-            &cancelled_order.id,
-            OrderStatus::Cancelled,
-            cancelled_order.filled_quantity,
-        )
-        .await
-    {
-        log::error!("Failed to update order status in Redis: {}", e);
+    if let Some(not_found) = find_book_option(&db, event_id, option_id).await? {
+        return Ok(not_found);
     }
 
-    // Update event option price immediately based on order book (event-driven)
-    let db_clone = db.clone();
-    let redis_pool_clone = redis_pool.clone();
-    let ws_server_clone = ws_server.clone();
-    tokio::spawn(async move {
-        crate::order_book::price_updater::update_option_price_immediately(
-            db_clone,
-            redis_pool_clone,
-            ws_server_clone,
-            event_id,
-            option_id,
-        )
-        .await;
-    });
-
-    // Invalidate caches
-    let cache_service = CacheService::new(redis_pool.get_ref().clone());
-    let order_book_cache_key = format!("order_book:{}:{}", event_id, option_id);
+    let (snapshot, predicted_price) =
+        matching_service::query(&redis_pool, event_id, option_id, |order_book| {
+            (order_book.get_snapshot(), order_book.get_predicted_price())
+        })
+        .await
+        .map_err(|e| {
+            log::error!("Failed to reach order book: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to get order book")
+        })?;
 
-    if let Err(e) = cache_service.delete(&order_book_cache_key).await {
-        log::warn!("Failed to invalidate order book cache: {}", e);
-    }
+    let response = OrderBookResponse::from_snapshot(snapshot, predicted_price);
 
     Ok(HttpResponse::Ok().json(json!({
         "success": true,
-        "message": "Order cancelled successfully",
-        "order": OrderResponse {
-            id: cancelled_order.id,
-            user_id: cancelled_order.user_id,
-            event_id: cancelled_order.event_id,
-            option_id: cancelled_order.option_id,
-            side: cancelled_order.side,
-            order_type: cancelled_order.order_type,
-            time_in_force: cancelled_order.time_in_force,
-            price: cancelled_order.price,
-            quantity: cancelled_order.quantity,
-            filled_quantity: cancelled_order.filled_quantity,
-            status: cancelled_order.status,
-            created_at: cancelled_order.created_at,
-            updated_at: cancelled_order.updated_at,
-        }
+        "order_book": response
     })))
 }
 
-pub async fn get_order_book(
+pub async fn get_event_order_books(
     db: web::Data<DatabaseConnection>,
     redis_pool: web::Data<Pool>,
-    path: web::Path<(i32, i32)>,
+    event_id: web::Path<i32>,
 ) -> Result<HttpResponse, Error> {
-    let (event_id, option_id) = path.into_inner();
+    let event_id = event_id.into_inner();
     let cache_service = CacheService::new(redis_pool.get_ref().clone());
-    let cache_key = format!("order_book:{}:{}", event_id, option_id);
+    let cache_key = format!("order_books:event:{}", event_id);
 
     // Try to get from cache first
     if let Ok(Some(cached_response)) = cache_service.get::<serde_json::Value>(&cache_key).await {
         return Ok(HttpResponse::Ok().json(cached_response));
     }
 
-    // Verify the event and option exist
+    // Verify the event exists
     let event = events::Entity::find_by_id(event_id)
         .one(db.get_ref())
         .await
@@ -605,53 +2107,56 @@ pub async fn get_order_book(
         })));
     }
 
-    let option = event_options::Entity::find_by_id(option_id)
-        .one(db.get_ref())
+    let options = event_options::Entity::find()
+        .filter(event_options::Column::EventId.eq(event_id))
+        .order_by_asc(event_options::Column::Id)
+        .all(db.get_ref())
         .await
         .map_err(|e| {
             log::error!("Database error: {}", e);
             actix_web::error::ErrorInternalServerError("Database error occurred")
         })?;
 
-    if option.is_none() || option.as_ref().unwrap().event_id != event_id {
-        return Ok(HttpResponse::NotFound().json(json!({
-            "success": false,
-            "message": "Option not found"
-        })));
-    }
-
-    // Get order book from Redis
     let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
-    let order_book = redis_persistence
-        .get_or_create_order_book(event_id, option_id)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to get order book from Redis: {}", e);
-            actix_web::error::ErrorInternalServerError("Failed to get order book")
-        })?;
-
-    let snapshot = order_book.get_snapshot();
-    let predicted_price = order_book.get_predicted_price();
-
-    let response = OrderBookResponse {
-        event_id: snapshot.event_id,
-        option_id: snapshot.option_id,
-        bids: snapshot.bids.into_iter().map(|l| l.into()).collect(),
-        asks: snapshot.asks.into_iter().map(|l| l.into()).collect(),
-        last_trade_price: snapshot.last_trade_price,
-        mid_price: snapshot.mid_price,
-        spread: snapshot.spread,
-        predicted_price,
-    };
+    let mut order_books = Vec::with_capacity(options.len());
+
+    for option in options {
+        // Read the latest snapshots rather than loading every option's book into
+        // memory; options without an order book yet are reported with empty levels
+        let order_book = redis_persistence
+            .load_full_order_book(event_id, option.id)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to get order book from Redis: {}", e);
+                actix_web::error::ErrorInternalServerError("Failed to get order book")
+            })?
+            .unwrap_or_else(|| OrderBookEngine::new(event_id, option.id));
+
+        let snapshot = order_book.get_snapshot_with_depth(5); // Top 5 levels
+
+        order_books.push(CompactOrderBookResponse {
+            option_id: option.id,
+            option_text: option.option_text,
+            bids: snapshot.bids.into_iter().map(|l| l.into()).collect(),
+            asks: snapshot.asks.into_iter().map(|l| l.into()).collect(),
+            last_trade_price: snapshot.last_trade_price,
+            mid_price: snapshot.mid_price,
+            spread: snapshot.spread,
+            sequence: snapshot.sequence,
+        });
+    }
 
     let response_json = json!({
         "success": true,
-        "order_book": response
+        "event_order_books": EventOrderBooksResponse {
+            event_id,
+            order_books,
+        }
     });
 
     // Cache the response for 30 seconds
     if let Err(e) = cache_service.set(&cache_key, &response_json, 30).await {
-        log::warn!("Failed to cache order book: {}", e);
+        log::warn!("Failed to cache event order books: {}", e);
     }
 
     Ok(HttpResponse::Ok().json(response_json))
@@ -695,18 +2200,16 @@ pub async fn get_market_depth(
         })));
     }
 
-    // Get order book from Redis
-    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
-    let order_book = redis_persistence
-        .get_or_create_order_book(event_id, option_id)
+    let (depth, sequence) =
+        matching_service::query(&redis_pool, event_id, option_id, |order_book| {
+            (order_book.get_market_depth(20), order_book.sequence()) // Get top 20 levels
+        })
         .await
         .map_err(|e| {
-            log::error!("Failed to get order book from Redis: {}", e);
+            log::error!("Failed to reach order book: {}", e);
             actix_web::error::ErrorInternalServerError("Failed to get order book")
         })?;
 
-    let depth = order_book.get_market_depth(20); // Get top 20 levels
-
     let total_bid_volume: i32 = depth.iter().map(|d| d.buy_quantity).sum();
     let total_ask_volume: i32 = depth.iter().map(|d| d.sell_quantity).sum();
 
@@ -716,6 +2219,7 @@ pub async fn get_market_depth(
         depth,
         total_bid_volume,
         total_ask_volume,
+        sequence,
     };
 
     Ok(HttpResponse::Ok().json(json!({
@@ -724,16 +2228,112 @@ pub async fn get_market_depth(
     })))
 }
 
+/// Levels an option gets from the other option's book when its event uses
+/// complement pricing
+pub async fn get_implied_order_book(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, Error> {
+    let (event_id, option_id) = path.into_inner();
+
+    let event = events::Entity::find_by_id(event_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let event = match event {
+        Some(event) => event,
+        None => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "success": false,
+                "message": "Event not found"
+            })));
+        }
+    };
+
+    let option = event_options::Entity::find_by_id(option_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    if option.is_none_or(|option| option.event_id != event_id) {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Option not found"
+        })));
+    }
+
+    let complement_id = if event.complement_pricing {
+        PositionTracker::new(db.get_ref().clone())
+            .complementary_option(event_id, option_id)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to load complementary option: {}", e);
+                actix_web::error::ErrorInternalServerError("Database error occurred")
+            })?
+    } else {
+        None
+    };
+    let Some(complement_id) = complement_id else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Event does not use complement pricing"
+        })));
+    };
+
+    let snapshot = matching_service::query(&redis_pool, event_id, complement_id, |order_book| {
+        order_book.get_snapshot()
+    })
+    .await
+    .map_err(|e| {
+        log::error!("Failed to reach order book: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to get order book")
+    })?;
+
+    let (bids, asks) = complement::implied_levels(&snapshot);
+    let response = ImpliedOrderBookResponse {
+        event_id,
+        option_id,
+        complement_option_id: complement_id,
+        bids: bids.into_iter().map(|l| l.into()).collect(),
+        asks: asks.into_iter().map(|l| l.into()).collect(),
+        sequence: snapshot.sequence,
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "implied_book": response
+    })))
+}
+
 pub async fn get_user_orders(
     db: web::Data<DatabaseConnection>,
     _redis_pool: web::Data<Pool>,
     user_id: web::ReqData<String>,
+    query: web::Query<FieldsQuery>,
 ) -> Result<HttpResponse, Error> {
     let user_id_str = &*user_id;
     let user_id_int: i32 = user_id_str
         .parse()
         .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
 
+    let selected = match fields::parse(query.fields.as_deref(), OrderResponse::FIELDS) {
+        Ok(selected) => selected,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": message
+            })));
+        }
+    };
+
     let db_persistence = DbPersistence::new(db.get_ref().clone());
 
     // Get user's orders from database
@@ -745,28 +2345,114 @@ pub async fn get_user_orders(
             actix_web::error::ErrorInternalServerError("Failed to retrieve orders")
         })?;
 
-    let order_responses: Vec<OrderResponse> = orders
-        .into_iter()
-        .map(|order| OrderResponse {
-            id: order.id,
-            user_id: order.user_id,
-            event_id: order.event_id,
-            option_id: order.option_id,
-            side: order.side,
-            order_type: order.order_type,
-            time_in_force: order.time_in_force,
-            price: order.price,
-            quantity: order.quantity,
-            filled_quantity: order.filled_quantity,
-            status: order.status,
-            created_at: order.created_at,
-            updated_at: order.updated_at,
-        })
-        .collect();
+    let order_responses: Vec<OrderResponse> = orders.into_iter().map(OrderResponse::from).collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "orders": fields::project(json!(order_responses), selected.as_deref())
+    })))
+}
+
+/// Look up one of the user's orders by the client order id they gave it
+pub async fn get_order_by_client_id(
+    db: web::Data<DatabaseConnection>,
+    client_order_id: web::Path<String>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id_int: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    let order = DbPersistence::new(db.get_ref().clone())
+        .get_order_by_client_id(user_id_int, &client_order_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to load order: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to load order")
+        })?;
+
+    match order {
+        Some(order) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "order": OrderResponse::from(order)
+        }))),
+        None => Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Order not found"
+        }))),
+    }
+}
+
+/// Every recorded transition of an order, for its owner or support staff
+pub async fn get_order_history(
+    db: web::Data<DatabaseConnection>,
+    order_id: web::Path<String>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+    session: Option<web::ReqData<AuthSession>>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+    let order_id = order_id.into_inner();
+    let db_persistence = DbPersistence::new(db.get_ref().clone());
+
+    let order = db_persistence.get_order(&order_id).await.map_err(|e| {
+        log::error!("Failed to load order: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to load order")
+    })?;
+
+    let visible = match &order {
+        Some(order) if order.user_id == user_id => true,
+        Some(_) => permissions::has_permission(
+            db.get_ref(),
+            &auth_user,
+            session.is_some(),
+            Permission::SupportUsers,
+        )
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?,
+        None => false,
+    };
+
+    // Other users' orders are reported as missing rather than forbidden
+    let order = match order {
+        Some(order) if visible => order,
+        _ => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "success": false,
+                "message": "Order not found"
+            })));
+        }
+    };
+
+    let events = db_persistence
+        .get_order_events(&order_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get order events: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to retrieve order history")
+        })?;
+
+    let history = OrderHistoryResponse {
+        order: OrderResponse::from(order),
+        events: events
+            .into_iter()
+            .map(|e| OrderEventResponse {
+                kind: e.kind,
+                status: e.status,
+                price: e.price,
+                quantity: e.quantity,
+                filled_quantity: e.filled_quantity,
+                trade_id: e.trade_id,
+                timestamp: e.timestamp,
+            })
+            .collect(),
+    };
 
     Ok(HttpResponse::Ok().json(json!({
         "success": true,
-        "orders": order_responses
+        "history": history
     })))
 }
 
@@ -774,9 +2460,20 @@ pub async fn get_trade_history(
     db: web::Data<DatabaseConnection>,
     _redis_pool: web::Data<Pool>,
     path: web::Path<(i32, i32)>,
+    query: web::Query<FieldsQuery>,
 ) -> Result<HttpResponse, Error> {
     let (event_id, option_id) = path.into_inner();
 
+    let selected = match fields::parse(query.fields.as_deref(), TradeResponse::FIELDS) {
+        Ok(selected) => selected,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": message
+            })));
+        }
+    };
+
     // Verify the event and option exist
     let event = events::Entity::find_by_id(event_id)
         .one(db.get_ref())
@@ -831,11 +2528,12 @@ pub async fn get_trade_history(
             quantity: trade.quantity,
             total_amount: trade.total_amount,
             timestamp: trade.timestamp,
+            sequence: trade.sequence,
         })
         .collect();
 
     Ok(HttpResponse::Ok().json(json!({
         "success": true,
-        "trades": trade_responses
+        "trades": fields::project(json!(trade_responses), selected.as_deref())
     })))
 }
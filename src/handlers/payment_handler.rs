@@ -0,0 +1,474 @@
+use crate::constants::config;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::middleware::internal_auth::InternalService;
+use crate::payments::gateway;
+use crate::payments::service::{move_intent_deposit, PaymentService, WebhookOutcome};
+use crate::types::payment::{
+    intent_status, webhook_event, CreateDepositIntentRequest, ListPaymentIntentsQuery,
+    PaymentIntentResponse, PaymentWebhook, ReportIntentRequest,
+};
+use crate::types::transaction::transaction_status;
+use crate::types::websocket::{OpsEventKind, OpsSeverity};
+use crate::utils::auth::get_user_id;
+use crate::utils::kyc;
+use crate::utils::pagination::{PaginatedResponse, PaginationInfo};
+use crate::wallets::{balances, transactions};
+use crate::websocket::ops;
+use crate::websocket::server::WebSocketServer;
+use actix::Addr;
+use actix_web::{web, Error, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use deadpool_redis::Pool;
+use entity::{payment_intents, transaction, users};
+use sea_orm::{
+    prelude::Decimal, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use serde_json::json;
+
+const STATUSES: [&str; 4] = [
+    intent_status::CREATED,
+    intent_status::PENDING,
+    intent_status::SUCCEEDED,
+    intent_status::FAILED,
+];
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+fn bad_request(message: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "message": message
+    }))
+}
+
+fn intent_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "message": "Payment intent not found"
+    }))
+}
+
+/// Start a deposit through the payment gateway. The intent is `created`
+/// until the gateway relay opens a checkout for it, after which it carries
+/// the `checkout_url` the user pays at.
+pub async fn create_deposit_intent(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    req: web::Json<CreateDepositIntentRequest>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+    let amount = req.amount;
+    if amount <= Decimal::ZERO {
+        return Ok(bad_request("Amount must be greater than 0"));
+    }
+    if amount.round_dp(2) != amount {
+        return Ok(bad_request("Amount can have at most 2 decimal places"));
+    }
+    let currency = match req.currency.as_deref() {
+        Some(requested) => match balances::resolve_currency(Some(requested)) {
+            Ok(currency) => currency,
+            Err(message) => return Ok(bad_request(&message)),
+        },
+        None => config::get_payment_currency(),
+    };
+
+    let Some(user) = users::Entity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "User not found"
+        })));
+    };
+    if !user.is_active {
+        return Ok(bad_request("User account is deactivated"));
+    }
+    if let Some(message) = kyc::deposit_refusal(db.get_ref(), &user, &currency, amount)
+        .await
+        .map_err(database_error)?
+    {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "message": message,
+            "kyc_required": true
+        })));
+    }
+
+    let balance = balances::find(db.get_ref(), user_id, &currency)
+        .await
+        .map_err(database_error)?
+        .map(|wallet| wallet.balance)
+        .unwrap_or_default();
+
+    let now = Utc::now().fixed_offset();
+    let intent_id = gateway::generate_intent_id();
+    let gateway_name = config::get_payment_gateway();
+    let txn = db.begin().await.map_err(database_error)?;
+
+    // The deposit is in the user's transactions from the start; it is
+    // credited when the gateway confirms the payment
+    let deposit = transaction::ActiveModel {
+        user_id: Set(user_id),
+        r#type: Set("deposit".to_string()),
+        amount: Set(amount),
+        currency: Set(currency.clone()),
+        balance_before: Set(balance),
+        balance_after: Set(balance),
+        status: Set(transaction_status::PENDING.to_string()),
+        reference_id: Set(intent_id.clone()),
+        created_at: Set(now.naive_utc()),
+        gateway: Set(Some(gateway_name.clone())),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(database_error)?;
+
+    let intent = payment_intents::ActiveModel {
+        id: Set(intent_id),
+        user_id: Set(user_id),
+        gateway: Set(gateway_name),
+        amount: Set(amount),
+        currency: Set(currency),
+        status: Set(intent_status::CREATED.to_string()),
+        gateway_reference: Set(None),
+        checkout_url: Set(None),
+        transaction_id: Set(Some(deposit.id)),
+        failure_reason: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(&txn)
+    .await
+    .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    if let Err(e) = gateway::request_checkout(redis_pool.get_ref(), &intent).await {
+        log::error!("{}", e);
+        let failure_reason = "Payment gateway unavailable".to_string();
+        let txn = db.begin().await.map_err(database_error)?;
+        move_intent_deposit(
+            &txn,
+            &intent,
+            transaction_status::FAILED,
+            Some(failure_reason.clone()),
+        )
+        .await
+        .map_err(database_error)?;
+        let mut active: payment_intents::ActiveModel = intent.into();
+        active.status = Set(intent_status::FAILED.to_string());
+        active.failure_reason = Set(Some(failure_reason));
+        active.updated_at = Set(Utc::now().fixed_offset());
+        active.update(&txn).await.map_err(database_error)?;
+        txn.commit().await.map_err(database_error)?;
+
+        return Ok(HttpResponse::ServiceUnavailable().json(json!({
+            "success": false,
+            "message": "Payment gateway unavailable, try again later"
+        })));
+    }
+
+    log::info!(
+        "User {} started deposit {} of {} {}",
+        user_id,
+        intent.id,
+        intent.amount,
+        intent.currency
+    );
+
+    Ok(HttpResponse::Created().json(json!({
+        "success": true,
+        "payment_intent": PaymentIntentResponse::from(intent)
+    })))
+}
+
+/// The user's gateway deposits, newest first
+pub async fn list_deposit_intents(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<ListPaymentIntentsQuery>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+
+    let mut intents_query =
+        payment_intents::Entity::find().filter(payment_intents::Column::UserId.eq(user_id));
+    if let Some(status) = query.status.as_deref() {
+        if !STATUSES.contains(&status) {
+            return Ok(bad_request(
+                "status must be created, pending, succeeded or failed",
+            ));
+        }
+        intents_query = intents_query.filter(payment_intents::Column::Status.eq(status));
+    }
+
+    let page = query.pagination.get_page();
+    let limit = query.pagination.get_limit();
+    let offset = query.pagination.get_offset();
+
+    let total_count = intents_query
+        .to_owned()
+        .count(db.get_ref())
+        .await
+        .map_err(database_error)?;
+
+    let intents: Vec<PaymentIntentResponse> = intents_query
+        .order_by_desc(payment_intents::Column::CreatedAt)
+        .limit(limit)
+        .offset(offset)
+        .all(db.get_ref())
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(PaymentIntentResponse::from)
+        .collect();
+
+    let response = PaginatedResponse::new(intents, PaginationInfo::new(page, total_count, limit));
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "payment_intents": response.data,
+        "pagination": response.pagination
+    })))
+}
+
+/// One of the user's gateway deposits, polled while its checkout is open
+pub async fn get_deposit_intent(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<String>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+
+    let Some(intent) = payment_intents::Entity::find_by_id(path.into_inner())
+        .filter(payment_intents::Column::UserId.eq(user_id))
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(intent_not_found());
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "payment_intent": PaymentIntentResponse::from(intent)
+    })))
+}
+
+/// Payment results from the gateway, signed with `PAYMENT_WEBHOOK_SECRET`.
+/// Deliveries may repeat; a payment is credited only by the first.
+pub async fn payment_webhook(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    http_req: HttpRequest,
+    body: web::Bytes,
+) -> Result<HttpResponse, Error> {
+    let Some(secret) = config::get_payment_webhook_secret() else {
+        return Ok(HttpResponse::ServiceUnavailable().json(json!({
+            "success": false,
+            "message": "Payment webhooks are disabled"
+        })));
+    };
+
+    let signature = http_req
+        .headers()
+        .get("X-Payment-Signature")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if let Err(e) = gateway::verify_webhook(
+        &secret,
+        signature,
+        &body,
+        Utc::now().timestamp(),
+        config::get_payment_webhook_tolerance_seconds(),
+    ) {
+        log::warn!("Refused payment webhook: {}", e);
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "success": false,
+            "message": "Invalid signature"
+        })));
+    }
+
+    let webhook: PaymentWebhook = match serde_json::from_slice(&body) {
+        Ok(webhook) => webhook,
+        Err(e) => return Ok(bad_request(&format!("Invalid webhook body: {}", e))),
+    };
+
+    let service = PaymentService::new(db.get_ref().clone());
+    let outcome = match webhook.event.as_str() {
+        webhook_event::PAYMENT_SUCCEEDED => service.confirm_deposit(&webhook.data).await,
+        webhook_event::PAYMENT_FAILED => service.fail_deposit(&webhook.data).await,
+        _ => {
+            return Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "message": "Event ignored"
+            })))
+        }
+    }
+    .map_err(database_error)?;
+
+    let message = match outcome {
+        WebhookOutcome::Credited {
+            user_id,
+            amount,
+            bonus,
+            deposit,
+        } => {
+            log::info!(
+                "Credited deposit {} of {} to user {} (bonus {})",
+                webhook.data.intent_id,
+                amount,
+                user_id,
+                bonus.unwrap_or_default()
+            );
+
+            transactions::publish_status_change(
+                db.get_ref(),
+                redis_pool.get_ref(),
+                ws_server.get_ref(),
+                &deposit,
+            );
+
+            "Deposit credited"
+        }
+        WebhookOutcome::Failed { user_id, deposit } => {
+            log::info!(
+                "Deposit {} of user {} failed at the gateway",
+                webhook.data.intent_id,
+                user_id
+            );
+            if let Some(deposit) = deposit {
+                transactions::publish_status_change(
+                    db.get_ref(),
+                    redis_pool.get_ref(),
+                    ws_server.get_ref(),
+                    &deposit,
+                );
+            }
+            "Deposit marked failed"
+        }
+        WebhookOutcome::AlreadyProcessed => "Already processed",
+        WebhookOutcome::UnknownIntent => {
+            log::warn!(
+                "Payment webhook for unknown intent {}",
+                webhook.data.intent_id
+            );
+            return Ok(intent_not_found());
+        }
+        WebhookOutcome::Mismatch(message) => {
+            // Acknowledged so the gateway stops retrying; the money has been
+            // taken, so someone has to settle it by hand
+            ops::publish(
+                OpsEventKind::PaymentMismatch,
+                OpsSeverity::Critical,
+                message,
+                json!({
+                    "intent_id": webhook.data.intent_id,
+                    "gateway_reference": webhook.data.gateway_reference,
+                    "amount": webhook.data.amount,
+                    "currency": webhook.data.currency
+                }),
+            );
+            "Payment held for review"
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": message
+    })))
+}
+
+/// The checkout the gateway relay opened for an intent, or why it couldn't
+/// (internal)
+pub async fn report_intent(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    path: web::Path<String>,
+    req: web::Json<ReportIntentRequest>,
+    service: web::ReqData<InternalService>,
+) -> Result<HttpResponse, Error> {
+    let intent_id = path.into_inner();
+    let req = req.into_inner();
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let Some(intent) = payment_intents::Entity::find_by_id(intent_id.clone())
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(intent_not_found());
+    };
+    if intent.status != intent_status::CREATED {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": format!("Payment intent is already {}", intent.status)
+        })));
+    }
+
+    let (deposit, mut active) = match (req.error, req.gateway_reference, req.checkout_url) {
+        (Some(error), _, _) => {
+            log::warn!(
+                "{} couldn't open a checkout for {}: {}",
+                service.0,
+                intent_id,
+                error
+            );
+            let deposit = move_intent_deposit(
+                &txn,
+                &intent,
+                transaction_status::FAILED,
+                Some(error.clone()),
+            )
+            .await
+            .map_err(database_error)?;
+            let mut active: payment_intents::ActiveModel = intent.into();
+            active.status = Set(intent_status::FAILED.to_string());
+            active.failure_reason = Set(Some(error));
+            (deposit, active)
+        }
+        (None, Some(gateway_reference), Some(checkout_url)) => {
+            let deposit = move_intent_deposit(&txn, &intent, transaction_status::PROCESSING, None)
+                .await
+                .map_err(database_error)?;
+            let mut active: payment_intents::ActiveModel = intent.into();
+            active.status = Set(intent_status::PENDING.to_string());
+            active.gateway_reference = Set(Some(gateway_reference));
+            active.checkout_url = Set(Some(checkout_url));
+            (deposit, active)
+        }
+        _ => {
+            return Ok(bad_request(
+                "gateway_reference and checkout_url are required without an error",
+            ))
+        }
+    };
+    active.updated_at = Set(Utc::now().fixed_offset());
+    let intent = active.update(&txn).await.map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    if let Some(deposit) = deposit {
+        transactions::publish_status_change(
+            db.get_ref(),
+            redis_pool.get_ref(),
+            ws_server.get_ref(),
+            &deposit,
+        );
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "payment_intent": PaymentIntentResponse::from(intent)
+    })))
+}
@@ -1,20 +1,26 @@
+use crate::constants::config;
 use crate::order_book::position_tracker::PositionTracker;
+use crate::types::wallet::WalletResponse;
 use crate::utils::cache::CacheService;
+use crate::wallets::balances;
 use actix_web::{web, Error, HttpResponse, Result};
 use deadpool_redis::Pool;
 use entity::{event_options, events, users};
 use sea_orm::prelude::Decimal;
 use sea_orm::DatabaseConnection;
-use sea_orm::EntityTrait;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use serde::Serialize;
 use serde_json::json;
+use std::collections::HashMap;
 
 #[derive(Serialize)]
 pub struct PortfolioResponse {
     pub total_invested: Decimal,
     pub current_value: Decimal,
     pub total_pnl: Decimal,
+    /// Balance of the base currency wallet
     pub wallet_balance: Decimal,
+    pub wallets: Vec<WalletResponse>,
     pub active_positions: Vec<EventPositionGroup>,
 }
 
@@ -23,6 +29,7 @@ pub struct EventPositionGroup {
     pub event_id: i32,
     pub event_title: String,
     pub event_status: String,
+    pub currency: String,
     pub invested: Decimal,
     pub current_value: Decimal,
     pub pnl: Decimal,
@@ -76,18 +83,15 @@ pub async fn get_portfolio(
             actix_web::error::ErrorInternalServerError("Failed to retrieve user")
         })?
         .ok_or_else(|| actix_web::error::ErrorNotFound("User not found"))?;
-
-    let position_tracker = PositionTracker::new(db.get_ref().clone());
-
-    // Get all user positions
-    let _positions = position_tracker
-        .get_user_positions(user_id_int)
+    let wallets = balances::for_user(db.get_ref(), user.id)
         .await
         .map_err(|e| {
-            log::error!("Failed to get user positions: {}", e);
-            actix_web::error::ErrorInternalServerError("Failed to retrieve positions")
+            log::error!("Failed to get wallets: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to retrieve user")
         })?;
 
+    let position_tracker = PositionTracker::new(db.get_ref().clone());
+
     // Group positions by event
     let grouped_positions = position_tracker
         .get_portfolio_positions(user_id_int)
@@ -97,22 +101,45 @@ pub async fn get_portfolio(
             actix_web::error::ErrorInternalServerError("Failed to retrieve portfolio")
         })?;
 
+    // Fetch every referenced event and option in one query each
+    let event_ids: Vec<i32> = grouped_positions.keys().copied().collect();
+    let option_ids: Vec<i32> = grouped_positions
+        .values()
+        .flatten()
+        .map(|p| p.option_id)
+        .collect();
+
+    let events_by_id: HashMap<i32, events::Model> = events::Entity::find()
+        .filter(events::Column::Id.is_in(event_ids))
+        .all(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get events: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to retrieve event")
+        })?
+        .into_iter()
+        .map(|e| (e.id, e))
+        .collect();
+
+    let options_by_id: HashMap<i32, event_options::Model> = event_options::Entity::find()
+        .filter(event_options::Column::Id.is_in(option_ids))
+        .all(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get options: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to retrieve option")
+        })?
+        .into_iter()
+        .map(|o| (o.id, o))
+        .collect();
+
     let mut total_invested = Decimal::new(0, 2);
     let mut current_value = Decimal::new(0, 2);
     let mut active_positions = Vec::new();
 
     // Process each event group
     for (event_id, event_positions) in grouped_positions {
-        // Get event details
-        let event = events::Entity::find_by_id(event_id)
-            .one(db.get_ref())
-            .await
-            .map_err(|e| {
-                log::error!("Failed to get event: {}", e);
-                actix_web::error::ErrorInternalServerError("Failed to retrieve event")
-            })?;
-
-        let event = match event {
+        let event = match events_by_id.get(&event_id) {
             Some(e) => e,
             None => continue, // Skip if event not found
         };
@@ -123,16 +150,7 @@ pub async fn get_portfolio(
 
         // Process each position in the event
         for position in event_positions {
-            // Get option details
-            let option = event_options::Entity::find_by_id(position.option_id)
-                .one(db.get_ref())
-                .await
-                .map_err(|e| {
-                    log::error!("Failed to get option: {}", e);
-                    actix_web::error::ErrorInternalServerError("Failed to retrieve option")
-                })?;
-
-            let option = match option {
+            let option = match options_by_id.get(&position.option_id) {
                 Some(o) => o,
                 None => continue, // Skip if option not found
             };
@@ -145,7 +163,7 @@ pub async fn get_portfolio(
 
             position_details.push(PositionDetail {
                 option_id: position.option_id,
-                option_text: option.option_text,
+                option_text: option.option_text.clone(),
                 quantity: position.quantity,
                 avg_price: position.average_price,
                 current_price: option.current_price,
@@ -160,8 +178,9 @@ pub async fn get_portfolio(
 
         active_positions.push(EventPositionGroup {
             event_id: event.id,
-            event_title: event.title,
-            event_status: event.status,
+            event_title: event.title.clone(),
+            event_status: event.status.clone(),
+            currency: event.currency.clone(),
             invested: event_invested,
             current_value: event_current_value,
             pnl: event_pnl,
@@ -175,7 +194,8 @@ pub async fn get_portfolio(
         total_invested,
         current_value,
         total_pnl,
-        wallet_balance: user.wallet_balance,
+        wallet_balance: balances::balance_in(&wallets, &config::get_base_currency()),
+        wallets: wallets.into_iter().map(WalletResponse::from).collect(),
         active_positions,
     };
 
@@ -212,48 +232,17 @@ pub async fn get_portfolio_summary(
 
     let position_tracker = PositionTracker::new(db.get_ref().clone());
 
-    // Get all user positions
-    let positions = position_tracker
-        .get_user_positions(user_id_int)
+    // Aggregate cost and value in the database instead of per-position lookups
+    let totals = position_tracker
+        .get_portfolio_totals(user_id_int)
         .await
         .map_err(|e| {
-            log::error!("Failed to get user positions: {}", e);
-            actix_web::error::ErrorInternalServerError("Failed to retrieve positions")
-        })?;
-
-    // Group positions by event
-    let grouped_positions = position_tracker
-        .get_portfolio_positions(user_id_int)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to get portfolio positions: {}", e);
+            log::error!("Failed to get portfolio totals: {}", e);
             actix_web::error::ErrorInternalServerError("Failed to retrieve portfolio")
         })?;
 
-    let mut total_invested = Decimal::new(0, 2);
-    let mut current_value = Decimal::new(0, 2);
-
-    // Calculate totals
-    for event_positions in grouped_positions.values() {
-        for position in event_positions {
-            // Get option details for current price
-            let option = event_options::Entity::find_by_id(position.option_id)
-                .one(db.get_ref())
-                .await
-                .map_err(|e| {
-                    log::error!("Failed to get option: {}", e);
-                    actix_web::error::ErrorInternalServerError("Failed to retrieve option")
-                })?;
-
-            if let Some(option) = option {
-                let position_cost = position.average_price * Decimal::from(position.quantity);
-                let position_value = option.current_price * Decimal::from(position.quantity);
-
-                total_invested += position_cost;
-                current_value += position_value;
-            }
-        }
-    }
+    let total_invested = totals.total_invested;
+    let current_value = totals.current_value;
 
     let total_pnl = current_value - total_invested;
     let pnl_percentage = if total_invested > Decimal::new(0, 2) {
@@ -263,8 +252,8 @@ pub async fn get_portfolio_summary(
     };
 
     let summary = PortfolioSummary {
-        total_positions: positions.len(),
-        active_events: grouped_positions.len(),
+        total_positions: totals.total_positions as usize,
+        active_events: totals.active_events as usize,
         total_invested,
         current_value,
         total_pnl,
@@ -0,0 +1,135 @@
+use crate::types::preferences::{PreferencesResponse, UpdatePreferencesRequest};
+use actix_web::{web, Error, HttpResponse, Result};
+use entity::user_preferences;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
+use serde_json::json;
+
+pub async fn get_preferences(
+    db: web::Data<DatabaseConnection>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    let preferences = user_preferences::Entity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let preferences = preferences
+        .map(PreferencesResponse::from)
+        .unwrap_or_else(PreferencesResponse::defaults);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "preferences": preferences
+    })))
+}
+
+pub async fn update_preferences(
+    db: web::Data<DatabaseConnection>,
+    req: web::Json<UpdatePreferencesRequest>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    if let Err(message) = req.validate() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": message
+        })));
+    }
+
+    let existing = user_preferences::Entity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let now = chrono::Utc::now();
+    let is_new = existing.is_none();
+    let mut active: user_preferences::ActiveModel = match existing {
+        Some(existing) => existing.into(),
+        None => {
+            let defaults = PreferencesResponse::defaults();
+            user_preferences::ActiveModel {
+                user_id: Set(user_id),
+                email_digest_enabled: Set(defaults.email_digest.enabled),
+                digest_frequency: Set(defaults.email_digest.frequency.as_str().to_string()),
+                last_digest_sent_at: Set(None),
+                created_at: Set(now.into()),
+                updated_at: Set(now.into()),
+                email_enabled: Set(defaults.channels.email),
+                push_enabled: Set(defaults.channels.push),
+                ws_enabled: Set(defaults.channels.ws),
+                fills_enabled: Set(defaults.categories.fills),
+                settlements_enabled: Set(defaults.categories.settlements),
+                marketing_enabled: Set(defaults.categories.marketing),
+                locale: Set(defaults.locale),
+                display_currency: Set(defaults.display_currency),
+            }
+        }
+    };
+
+    if let Some(channels) = &req.channels {
+        if let Some(email) = channels.email {
+            active.email_enabled = Set(email);
+        }
+        if let Some(push) = channels.push {
+            active.push_enabled = Set(push);
+        }
+        if let Some(ws) = channels.ws {
+            active.ws_enabled = Set(ws);
+        }
+    }
+    if let Some(categories) = &req.categories {
+        if let Some(fills) = categories.fills {
+            active.fills_enabled = Set(fills);
+        }
+        if let Some(settlements) = categories.settlements {
+            active.settlements_enabled = Set(settlements);
+        }
+        if let Some(marketing) = categories.marketing {
+            active.marketing_enabled = Set(marketing);
+        }
+    }
+    if let Some(digest) = &req.email_digest {
+        if let Some(enabled) = digest.enabled {
+            active.email_digest_enabled = Set(enabled);
+        }
+        if let Some(frequency) = digest.frequency {
+            active.digest_frequency = Set(frequency.as_str().to_string());
+        }
+    }
+    if let Some(locale) = &req.locale {
+        active.locale = Set(locale.clone());
+    }
+    if let Some(currency) = &req.display_currency {
+        active.display_currency = Set(currency.clone());
+    }
+    active.updated_at = Set(now.into());
+
+    let result = if is_new {
+        active.insert(db.get_ref()).await
+    } else {
+        active.update(db.get_ref()).await
+    };
+
+    let preferences = result.map_err(|e| {
+        log::error!("Failed to save user preferences: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to save user preferences")
+    })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "preferences": PreferencesResponse::from(preferences)
+    })))
+}
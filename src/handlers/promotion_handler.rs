@@ -0,0 +1,236 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::promotions::service::PromotionService;
+use crate::types::promotion::{
+    validate_match_percent, validate_window, CreatePromotionRequest, PromotionKind,
+    PromotionReportResponse, PromotionResponse, UpdatePromotionRequest,
+};
+use crate::utils::auth::get_user_id;
+use crate::utils::db_pool::ReportingDb;
+use actix_web::{web, Error, HttpResponse, Result};
+use chrono::Utc;
+use entity::{events, promotions};
+use sea_orm::{
+    prelude::Decimal, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    QueryOrder, Set,
+};
+use serde_json::json;
+
+/// Promotions running right now, for banners and market badges
+pub async fn list_active_promotions(
+    db: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, Error> {
+    let now = Utc::now();
+    let promotions = promotions::Entity::find()
+        .filter(promotions::Column::IsActive.eq(true))
+        .filter(promotions::Column::StartsAt.lte(now))
+        .filter(promotions::Column::EndsAt.gt(now))
+        .order_by_asc(promotions::Column::EndsAt)
+        .all(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let promotions: Vec<PromotionResponse> = promotions.into_iter().map(Into::into).collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "promotions": promotions
+    })))
+}
+
+/// Every promotion, past and scheduled included
+pub async fn list_promotions(db: web::Data<DatabaseConnection>) -> Result<HttpResponse, Error> {
+    let promotions = promotions::Entity::find()
+        .order_by_desc(promotions::Column::StartsAt)
+        .all(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let promotions: Vec<PromotionResponse> = promotions.into_iter().map(Into::into).collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "promotions": promotions
+    })))
+}
+
+pub async fn create_promotion(
+    db: web::Data<DatabaseConnection>,
+    req: web::Json<CreatePromotionRequest>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+
+    if let Err(message) = req.validate() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": message
+        })));
+    }
+
+    if let Some(event_id) = req.event_id {
+        let event = events::Entity::find_by_id(event_id)
+            .one(db.get_ref())
+            .await
+            .map_err(|e| {
+                log::error!("Database error: {}", e);
+                actix_web::error::ErrorInternalServerError("Database error occurred")
+            })?;
+        if event.is_none() {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": "Event not found"
+            })));
+        }
+    }
+
+    let now = Utc::now();
+    let promotion = promotions::ActiveModel {
+        name: Set(req.name.trim().to_string()),
+        kind: Set(req.kind.as_str().to_string()),
+        event_id: Set(req.event_id),
+        match_percent: Set(req.match_percent),
+        max_reward_per_user: Set(req.max_reward_per_user),
+        starts_at: Set(req.starts_at.into()),
+        ends_at: Set(req.ends_at.into()),
+        is_active: Set(true),
+        created_by: Set(admin_id),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    }
+    .insert(db.get_ref())
+    .await
+    .map_err(|e| {
+        log::error!("Failed to create promotion: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to create promotion")
+    })?;
+
+    log::info!(
+        "Admin {} created {} promotion {} ({})",
+        admin_id,
+        promotion.kind,
+        promotion.id,
+        promotion.name
+    );
+
+    Ok(HttpResponse::Created().json(json!({
+        "success": true,
+        "promotion": PromotionResponse::from(promotion)
+    })))
+}
+
+pub async fn update_promotion(
+    db: web::Data<DatabaseConnection>,
+    promotion_id: web::Path<i32>,
+    req: web::Json<UpdatePromotionRequest>,
+) -> Result<HttpResponse, Error> {
+    let Some(existing) = find_promotion(db.get_ref(), *promotion_id).await? else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Promotion not found"
+        })));
+    };
+
+    let starts_at = req.starts_at.unwrap_or(existing.starts_at.into());
+    let ends_at = req.ends_at.unwrap_or(existing.ends_at.into());
+    let mut validation = validate_window(starts_at, ends_at);
+    if req.match_percent.is_some() {
+        validation = validation.and_then(|_| match PromotionKind::parse(&existing.kind) {
+            Some(PromotionKind::DepositMatch) => validate_match_percent(req.match_percent),
+            _ => Err("Only deposit matches take a match_percent".to_string()),
+        });
+    }
+    if req
+        .max_reward_per_user
+        .is_some_and(|cap| cap <= Decimal::ZERO)
+    {
+        validation = validation.and(Err("max_reward_per_user must be positive".to_string()));
+    }
+    if let Err(message) = validation {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": message
+        })));
+    }
+
+    let mut active: promotions::ActiveModel = existing.into();
+    if let Some(name) = &req.name {
+        active.name = Set(name.trim().to_string());
+    }
+    if req.match_percent.is_some() {
+        active.match_percent = Set(req.match_percent);
+    }
+    if req.max_reward_per_user.is_some() {
+        active.max_reward_per_user = Set(req.max_reward_per_user);
+    }
+    if let Some(is_active) = req.is_active {
+        active.is_active = Set(is_active);
+    }
+    active.starts_at = Set(starts_at.into());
+    active.ends_at = Set(ends_at.into());
+    active.updated_at = Set(Utc::now().into());
+
+    let promotion = active.update(db.get_ref()).await.map_err(|e| {
+        log::error!("Failed to update promotion: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to update promotion")
+    })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "promotion": PromotionResponse::from(promotion)
+    })))
+}
+
+/// How many users used a promotion and what it cost
+pub async fn get_promotion_report(
+    db: web::Data<ReportingDb>,
+    promotion_id: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let db = db.connection();
+    let Some(promotion) = find_promotion(db, *promotion_id).await? else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Promotion not found"
+        })));
+    };
+
+    let users = PromotionService::new(db.clone())
+        .usage_by_user(promotion.id)
+        .await
+        .map_err(|e| {
+            log::error!("{}", e);
+            actix_web::error::ErrorInternalServerError("Failed to build promotion report")
+        })?;
+
+    let report = PromotionReportResponse {
+        promotion: promotion.into(),
+        unique_users: users.len(),
+        usage_count: users.iter().map(|u| u.usage_count).sum(),
+        total_reward: users.iter().map(|u| u.total_reward).sum(),
+        users,
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "report": report
+    })))
+}
+
+async fn find_promotion(
+    db: &DatabaseConnection,
+    promotion_id: i32,
+) -> Result<Option<promotions::Model>, Error> {
+    promotions::Entity::find_by_id(promotion_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })
+}
@@ -0,0 +1,25 @@
+use crate::order_book::risk;
+use actix_web::{web, Error, HttpResponse, Result};
+use sea_orm::DatabaseConnection;
+use serde_json::json;
+
+/// The user's exposure, collateral usage and distance to their limits at
+/// current prices, in each currency
+pub async fn get_risk_summary(
+    db: web::Data<DatabaseConnection>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    let summaries = risk::summary(db.get_ref(), user_id).await.map_err(|e| {
+        log::error!("Failed to compute risk summary: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to compute risk summary")
+    })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "risk": summaries
+    })))
+}
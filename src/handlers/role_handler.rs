@@ -0,0 +1,202 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::types::rbac::{AssignRoleRequest, Permission, RoleResponse, UserRoleResponse};
+use crate::utils::auth::get_user_id;
+use actix_web::{web, Error, HttpResponse, Result};
+use chrono::Utc;
+use entity::{role_permissions, roles, user_roles, users};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter,
+    QueryOrder, Set,
+};
+use serde_json::json;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+async fn find_role(db: &DatabaseConnection, name: &str) -> Result<Option<roles::Model>, Error> {
+    roles::Entity::find()
+        .filter(roles::Column::Name.eq(name))
+        .one(db)
+        .await
+        .map_err(database_error)
+}
+
+/// Every staff role and the permissions it grants
+pub async fn list_roles(db: web::Data<DatabaseConnection>) -> Result<HttpResponse, Error> {
+    let roles = roles::Entity::find()
+        .order_by_asc(roles::Column::Name)
+        .find_with_related(role_permissions::Entity)
+        .all(db.get_ref())
+        .await
+        .map_err(database_error)?;
+
+    let roles: Vec<RoleResponse> = roles
+        .into_iter()
+        .map(|(role, grants)| RoleResponse {
+            name: role.name,
+            description: role.description,
+            permissions: grants
+                .iter()
+                .filter_map(|grant| Permission::parse(&grant.permission))
+                .collect(),
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "roles": roles
+    })))
+}
+
+/// The roles a user holds
+pub async fn list_user_roles(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let user_id = path.into_inner();
+
+    let held = user_roles::Entity::find()
+        .filter(user_roles::Column::UserId.eq(user_id))
+        .find_also_related(roles::Entity)
+        .all(db.get_ref())
+        .await
+        .map_err(database_error)?;
+
+    let roles: Vec<UserRoleResponse> = held
+        .into_iter()
+        .filter_map(|(grant, role)| {
+            role.map(|role| UserRoleResponse {
+                role: role.name,
+                granted_by: grant.granted_by,
+                granted_at: grant.created_at.with_timezone(&Utc),
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "user_id": user_id,
+        "roles": roles
+    })))
+}
+
+/// Give a user a staff role. Nobody can grant roles to themselves.
+pub async fn assign_role(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+    req: web::Json<AssignRoleRequest>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let granter_id = get_user_id(&auth_user)?;
+    let user_id = path.into_inner();
+
+    if user_id == granter_id {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "message": "You can't change your own roles"
+        })));
+    }
+
+    let Some(role) = find_role(db.get_ref(), &req.role).await? else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Role not found"
+        })));
+    };
+
+    let user = users::Entity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?;
+    if user.is_none() {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "User not found"
+        })));
+    }
+
+    let existing = user_roles::Entity::find()
+        .filter(user_roles::Column::UserId.eq(user_id))
+        .filter(user_roles::Column::RoleId.eq(role.id))
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?;
+    if existing.is_some() {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "User already has this role"
+        })));
+    }
+
+    user_roles::ActiveModel {
+        user_id: Set(user_id),
+        role_id: Set(role.id),
+        granted_by: Set(Some(granter_id)),
+        created_at: Set(Utc::now().fixed_offset()),
+        ..Default::default()
+    }
+    .insert(db.get_ref())
+    .await
+    .map_err(database_error)?;
+
+    log::warn!(
+        "User {} granted role {} to user {}",
+        granter_id,
+        role.name,
+        user_id
+    );
+
+    Ok(HttpResponse::Created().json(json!({
+        "success": true,
+        "message": format!("Role {} assigned", role.name)
+    })))
+}
+
+/// Take a staff role away from a user
+pub async fn remove_role(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<(i32, String)>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let granter_id = get_user_id(&auth_user)?;
+    let (user_id, role_name) = path.into_inner();
+
+    if user_id == granter_id {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "message": "You can't change your own roles"
+        })));
+    }
+
+    let grant = match find_role(db.get_ref(), &role_name).await? {
+        Some(role) => user_roles::Entity::find()
+            .filter(user_roles::Column::UserId.eq(user_id))
+            .filter(user_roles::Column::RoleId.eq(role.id))
+            .one(db.get_ref())
+            .await
+            .map_err(database_error)?,
+        None => None,
+    };
+    let Some(grant) = grant else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "User doesn't have this role"
+        })));
+    };
+
+    grant.delete(db.get_ref()).await.map_err(database_error)?;
+
+    log::warn!(
+        "User {} removed role {} from user {}",
+        granter_id,
+        role_name,
+        user_id
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": format!("Role {} removed", role_name)
+    })))
+}
@@ -0,0 +1,14 @@
+use crate::types::schema::{self, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION, REST_TYPES};
+use actix_web::{HttpResponse, Result};
+use serde_json::json;
+
+/// Versions of the types the API sends, so clients can check what they
+/// understand before relying on it
+pub async fn get_schema() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(json!({
+        "protocol_version": PROTOCOL_VERSION,
+        "min_protocol_version": MIN_PROTOCOL_VERSION,
+        "websocket": schema::ws::ALL,
+        "rest": REST_TYPES,
+    })))
+}
@@ -0,0 +1,282 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::settings::registry;
+use crate::types::audit::AuditAction;
+use crate::types::settings::{
+    SettingKey, SettingResponse, SettingSource, SettingsScopeQuery, UpdateSettingRequest,
+};
+use crate::utils::audit::{self, AuditEntry};
+use crate::utils::auth::get_user_id;
+use actix_web::{web, Error, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use entity::{settings, tenants};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, EntityTrait,
+    ModelTrait, QueryFilter, Set, TransactionTrait,
+};
+use serde_json::json;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+fn bad_request(message: impl Into<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "message": message.into()
+    }))
+}
+
+/// A setting as it applies to the deployment, or to a tenant
+fn describe(tenant_id: Option<i32>, key: SettingKey) -> SettingResponse {
+    let own = tenant_id
+        .filter(|_| key.per_tenant())
+        .and_then(|tenant_id| registry::stored(Some(tenant_id), key));
+    let (row, source) = match own {
+        Some(row) => (Some(row), SettingSource::Tenant),
+        None => match registry::stored(None, key) {
+            Some(row) => (Some(row), SettingSource::System),
+            None => (None, SettingSource::Default),
+        },
+    };
+
+    SettingResponse {
+        key,
+        kind: key.kind(),
+        per_tenant: key.per_tenant(),
+        value: row
+            .as_ref()
+            .map(|row| row.value.clone())
+            .unwrap_or_else(|| key.default_value()),
+        source,
+        updated_by: row.as_ref().and_then(|row| row.updated_by),
+        updated_at: row.map(|row| row.updated_at.with_timezone(&Utc)),
+    }
+}
+
+/// The key named in the path and the tenant named in the query, or the
+/// response refusing them
+async fn resolve_scope(
+    db: &DatabaseConnection,
+    key: &str,
+    tenant_id: Option<i32>,
+) -> Result<Result<SettingKey, HttpResponse>, Error> {
+    let Some(key) = SettingKey::parse(key) else {
+        return Ok(Err(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Unknown setting"
+        }))));
+    };
+    let Some(tenant_id) = tenant_id else {
+        return Ok(Ok(key));
+    };
+
+    if !key.per_tenant() {
+        return Ok(Err(bad_request(format!(
+            "{} applies to every tenant and can't be set for one",
+            key.as_str()
+        ))));
+    }
+    let tenant = tenants::Entity::find_by_id(tenant_id)
+        .one(db)
+        .await
+        .map_err(database_error)?;
+    if tenant.is_none() {
+        return Ok(Err(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Tenant not found"
+        }))));
+    }
+    Ok(Ok(key))
+}
+
+/// The row storing a setting for exactly this tenant, or deployment-wide
+async fn find_row(
+    txn: &DatabaseTransaction,
+    tenant_id: Option<i32>,
+    key: SettingKey,
+) -> Result<Option<settings::Model>, Error> {
+    let tenant_filter = match tenant_id {
+        Some(tenant_id) => settings::Column::TenantId.eq(tenant_id),
+        None => settings::Column::TenantId.is_null(),
+    };
+    settings::Entity::find()
+        .filter(tenant_filter)
+        .filter(settings::Column::Key.eq(key.as_str()))
+        .one(txn)
+        .await
+        .map_err(database_error)
+}
+
+fn audit_payload(row: &settings::Model) -> serde_json::Value {
+    json!({
+        "key": row.key,
+        "tenant_id": row.tenant_id,
+        "value": row.value
+    })
+}
+
+/// Every setting as it applies to the deployment, or to `tenant_id`
+pub async fn list_settings(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<SettingsScopeQuery>,
+) -> Result<HttpResponse, Error> {
+    if let Some(tenant_id) = query.tenant_id {
+        let tenant = tenants::Entity::find_by_id(tenant_id)
+            .one(db.get_ref())
+            .await
+            .map_err(database_error)?;
+        if tenant.is_none() {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "success": false,
+                "message": "Tenant not found"
+            })));
+        }
+    }
+
+    let settings: Vec<SettingResponse> = SettingKey::ALL
+        .into_iter()
+        .map(|key| describe(query.tenant_id, key))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "tenant_id": query.tenant_id,
+        "settings": settings
+    })))
+}
+
+/// Set a setting deployment-wide, or override it for `tenant_id`
+pub async fn update_setting(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<String>,
+    query: web::Query<SettingsScopeQuery>,
+    req: web::Json<UpdateSettingRequest>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let tenant_id = query.tenant_id;
+
+    let key = match resolve_scope(db.get_ref(), &path, tenant_id).await? {
+        Ok(key) => key,
+        Err(response) => return Ok(response),
+    };
+    let value = match key.validate(&req.value) {
+        Ok(value) => value,
+        Err(message) => return Ok(bad_request(message)),
+    };
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let existing = find_row(&txn, tenant_id, key).await?;
+    let before = existing.as_ref().map(audit_payload);
+    let now = Utc::now().fixed_offset();
+    let row = match existing {
+        Some(row) => {
+            let mut active: settings::ActiveModel = row.into();
+            active.value = Set(value);
+            active.updated_by = Set(Some(admin_id));
+            active.updated_at = Set(now);
+            active.update(&txn).await.map_err(database_error)?
+        }
+        None => settings::ActiveModel {
+            tenant_id: Set(tenant_id),
+            key: Set(key.as_str().to_string()),
+            value: Set(value),
+            updated_by: Set(Some(admin_id)),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .map_err(database_error)?,
+    };
+
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::SettingUpdate,
+            target_id: row.id,
+            before,
+            after: Some(audit_payload(&row)),
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    log::warn!(
+        "User {} set {} to {} for {}",
+        admin_id,
+        row.key,
+        row.value,
+        tenant_id.map_or("every tenant".to_string(), |id| format!("tenant {}", id))
+    );
+    registry::upsert(key, row);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "setting": describe(tenant_id, key)
+    })))
+}
+
+/// Remove a tenant's override, or the deployment-wide value, so the setting
+/// falls back to the next one
+pub async fn delete_setting(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<String>,
+    query: web::Query<SettingsScopeQuery>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let tenant_id = query.tenant_id;
+
+    let key = match resolve_scope(db.get_ref(), &path, tenant_id).await? {
+        Ok(key) => key,
+        Err(response) => return Ok(response),
+    };
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let Some(row) = find_row(&txn, tenant_id, key).await? else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Setting is not set here"
+        })));
+    };
+
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::SettingUpdate,
+            target_id: row.id,
+            before: Some(audit_payload(&row)),
+            after: None,
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    row.delete(&txn).await.map_err(database_error)?;
+    txn.commit().await.map_err(database_error)?;
+
+    log::warn!(
+        "User {} reset {} for {}",
+        admin_id,
+        key.as_str(),
+        tenant_id.map_or("every tenant".to_string(), |id| format!("tenant {}", id))
+    );
+    registry::remove(tenant_id, key);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "setting": describe(tenant_id, key)
+    })))
+}
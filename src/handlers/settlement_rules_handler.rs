@@ -0,0 +1,307 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::order_book::payouts;
+use crate::order_book::settlement_rules::{SettlementRules, MAX_SCRIPT_LENGTH};
+use crate::types::audit::AuditAction;
+use crate::types::event::{OptionPayout, PreviewSettlementRulesRequest, SettlementRulesRequest};
+use crate::utils::audit::{self, AuditEntry};
+use crate::utils::auth::get_user_id;
+use actix_web::{web, Error, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use entity::{event_options, events, user_positions};
+use sea_orm::{
+    prelude::Decimal, ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection,
+    EntityTrait, ModelTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+use serde_json::json;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+fn bad_request(message: impl Into<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "message": message.into()
+    }))
+}
+
+fn event_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "message": "Event not found"
+    }))
+}
+
+fn no_rules() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "message": "Event has no settlement rules"
+    }))
+}
+
+/// Whether an event can still be settled
+fn is_open(event: &events::Model) -> bool {
+    event.status != "resolved" && event.status != "cancelled"
+}
+
+async fn load_options<C: ConnectionTrait>(
+    db: &C,
+    event: &events::Model,
+) -> Result<Vec<event_options::Model>, Error> {
+    event
+        .find_related(event_options::Entity)
+        .order_by_asc(event_options::Column::Id)
+        .all(db)
+        .await
+        .map_err(database_error)
+}
+
+/// Parse a script and check it pays out on exactly the event's options
+fn check_script(script: &str, options: &[event_options::Model]) -> Result<SettlementRules, String> {
+    let rules = SettlementRules::parse(script)?;
+    let option_ids: Vec<i32> = options.iter().map(|option| option.id).collect();
+    rules.check_options(&option_ids)?;
+    Ok(rules)
+}
+
+/// Get the settlement rules of an event and the inputs they need (admin only)
+pub async fn get_settlement_rules(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let event_id = path.into_inner();
+
+    let Some(event) = events::Entity::find_by_id(event_id)
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(event_not_found());
+    };
+    let Some(script) = event.settlement_rules else {
+        return Ok(no_rules());
+    };
+    let inputs = SettlementRules::parse(&script)
+        .map(|rules| rules.inputs().to_vec())
+        .unwrap_or_default();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "data": {
+            "event_id": event_id,
+            "script": script,
+            "inputs": inputs
+        }
+    })))
+}
+
+/// Attach settlement rules to an event, replacing any it has (admin only)
+pub async fn set_settlement_rules(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+    req: web::Json<SettlementRulesRequest>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let event_id = path.into_inner();
+    let script = req.into_inner().script;
+    if script.trim().is_empty() || script.len() > MAX_SCRIPT_LENGTH {
+        return Ok(bad_request(format!(
+            "script must be 1 to {} characters",
+            MAX_SCRIPT_LENGTH
+        )));
+    }
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let Some(event) = events::Entity::find_by_id(event_id)
+        .one(&txn)
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(event_not_found());
+    };
+    if !is_open(&event) {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "Event is already resolved or cancelled"
+        })));
+    }
+
+    let options = load_options(&txn, &event).await?;
+    let rules = match check_script(&script, &options) {
+        Ok(rules) => rules,
+        Err(message) => return Ok(bad_request(message)),
+    };
+
+    let before = event.settlement_rules.clone();
+    let mut active: events::ActiveModel = event.into();
+    active.settlement_rules = Set(Some(script.clone()));
+    active.updated_at = Set(Utc::now().naive_utc());
+    active.update(&txn).await.map_err(database_error)?;
+
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::SettlementRulesUpdate,
+            target_id: event_id,
+            before: before.map(|script| json!({ "script": script })),
+            after: Some(json!({ "script": script })),
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    log::info!(
+        "Settlement rules of event {} set by admin {}",
+        event_id,
+        admin_id
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Settlement rules saved",
+        "data": {
+            "event_id": event_id,
+            "script": script,
+            "inputs": rules.inputs()
+        }
+    })))
+}
+
+/// Remove the settlement rules of an event (admin only)
+pub async fn delete_settlement_rules(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let event_id = path.into_inner();
+
+    let txn = db.begin().await.map_err(database_error)?;
+    let Some(event) = events::Entity::find_by_id(event_id)
+        .one(&txn)
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(event_not_found());
+    };
+    let Some(script) = event.settlement_rules.clone() else {
+        return Ok(no_rules());
+    };
+
+    let mut active: events::ActiveModel = event.into();
+    active.settlement_rules = Set(None);
+    active.updated_at = Set(Utc::now().naive_utc());
+    active.update(&txn).await.map_err(database_error)?;
+
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::SettlementRulesUpdate,
+            target_id: event_id,
+            before: Some(json!({ "script": script })),
+            after: None,
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Settlement rules removed"
+    })))
+}
+
+/// Work out what settling an event with rules would pay, without settling
+/// it. Uses the script given, or else the one attached to the event
+/// (admin only)
+pub async fn preview_settlement_rules(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+    req: web::Json<PreviewSettlementRulesRequest>,
+) -> Result<HttpResponse, Error> {
+    let event_id = path.into_inner();
+    let req = req.into_inner();
+
+    let Some(event) = events::Entity::find_by_id(event_id)
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(event_not_found());
+    };
+    let Some(script) = req.script.or_else(|| event.settlement_rules.clone()) else {
+        return Ok(no_rules());
+    };
+    if script.len() > MAX_SCRIPT_LENGTH {
+        return Ok(bad_request(format!(
+            "script must be at most {} characters",
+            MAX_SCRIPT_LENGTH
+        )));
+    }
+
+    let options = load_options(db.get_ref(), &event).await?;
+    let rules = match check_script(&script, &options) {
+        Ok(rules) => rules,
+        Err(message) => return Ok(bad_request(message)),
+    };
+    let option_ids: Vec<i32> = options.iter().map(|option| option.id).collect();
+    let schedule = match payouts::from_rules(&rules, &req.inputs, &option_ids) {
+        Ok(schedule) => schedule,
+        Err(message) => return Ok(bad_request(message)),
+    };
+    let payout_of = |option_id: i32| schedule.get(&option_id).copied().unwrap_or_default();
+
+    let option_payouts: Vec<OptionPayout> = options
+        .into_iter()
+        .map(|option| OptionPayout {
+            option_id: option.id,
+            payout_per_share: payout_of(option.id),
+            option_text: option.option_text,
+        })
+        .collect();
+
+    // What open positions would receive, and what shorts would owe
+    let positions = user_positions::Entity::find()
+        .filter(user_positions::Column::EventId.eq(event_id))
+        .filter(user_positions::Column::Quantity.ne(0))
+        .all(db.get_ref())
+        .await
+        .map_err(database_error)?;
+    let mut total_payouts = Decimal::new(0, 2);
+    let mut short_obligations = Decimal::new(0, 2);
+    for position in &positions {
+        let payout = payout_of(position.option_id) * Decimal::from(position.quantity);
+        if payout > Decimal::ZERO {
+            total_payouts += payout;
+        } else {
+            short_obligations -= payout;
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "dry_run": true,
+        "data": {
+            "event_id": event_id,
+            "currency": event.currency,
+            "inputs": rules.inputs(),
+            "option_payouts": option_payouts,
+            "total_payouts": total_payouts,
+            "short_obligations": short_obligations,
+            "positions": positions.len()
+        }
+    })))
+}
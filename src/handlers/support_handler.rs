@@ -0,0 +1,687 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::notifications::dispatcher::{self, Notification};
+use crate::types::audit::{AuditAction, AuditLogResponse, AuditTarget};
+use crate::types::preferences::NotificationCategory;
+use crate::types::rbac::Permission;
+use crate::types::support::{
+    AdminTicketDetailResponse, CreateTicketRequest, ListMyTicketsQuery, ListTicketsQuery,
+    PostTicketMessageRequest, TicketDetailResponse, TicketMessageResponse, TicketReferenceType,
+    TicketResponse, TicketStatus, UpdateTicketRequest, MAX_MESSAGE_LENGTH, MAX_SUBJECT_LENGTH,
+};
+use crate::utils::audit::{self, AuditEntry};
+use crate::utils::auth::get_user_id;
+use crate::utils::pagination::{PaginatedResponse, PaginationInfo};
+use crate::utils::permissions;
+use crate::websocket::server::WebSocketServer;
+use actix::Addr;
+use actix_web::{web, Error, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use deadpool_redis::Pool;
+use entity::{
+    audit_logs, events, support_ticket_messages, support_tickets, trades, transaction,
+    user_positions, users,
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    TransactionTrait,
+};
+use serde_json::json;
+
+/// Most audit log entries shown with a ticket
+const AUDIT_CONTEXT_LIMIT: u64 = 50;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+fn bad_request(message: &str) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "message": message
+    }))
+}
+
+fn ticket_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "success": false,
+        "message": "Ticket not found"
+    }))
+}
+
+fn ticket_closed() -> HttpResponse {
+    HttpResponse::Conflict().json(json!({
+        "success": false,
+        "message": "Ticket is closed"
+    }))
+}
+
+/// A message's body, trimmed, or why it can't be posted
+fn message_body(body: &str) -> Result<String, &'static str> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Err("Message is required");
+    }
+    if body.chars().count() > MAX_MESSAGE_LENGTH {
+        return Err("Message is too long");
+    }
+    Ok(body.to_string())
+}
+
+fn status_of(ticket: &support_tickets::Model) -> TicketStatus {
+    TicketStatus::parse(&ticket.status).unwrap_or(TicketStatus::Open)
+}
+
+/// The ticket, locked for the rest of the transaction
+async fn lock_ticket(
+    txn: &DatabaseTransaction,
+    ticket_id: i32,
+) -> Result<Option<support_tickets::Model>, Error> {
+    support_tickets::Entity::find_by_id(ticket_id)
+        .lock_exclusive()
+        .one(txn)
+        .await
+        .map_err(database_error)
+}
+
+/// Whether the trade, settlement or transaction a ticket disputes is one the
+/// user took part in. A settlement is the user's if they held a position in
+/// the resolved event.
+async fn is_users_reference(
+    db: &DatabaseConnection,
+    user_id: i32,
+    reference_type: TicketReferenceType,
+    reference_id: &str,
+) -> Result<bool, Error> {
+    match reference_type {
+        TicketReferenceType::Trade => Ok(trades::Entity::find_by_id(reference_id.to_string())
+            .one(db)
+            .await
+            .map_err(database_error)?
+            .is_some_and(|trade| trade.buyer_id == user_id || trade.seller_id == user_id)),
+        TicketReferenceType::Settlement => {
+            let Ok(event_id) = reference_id.parse::<i32>() else {
+                return Ok(false);
+            };
+            let resolved = events::Entity::find_by_id(event_id)
+                .one(db)
+                .await
+                .map_err(database_error)?
+                .is_some_and(|event| event.status == "resolved");
+            if !resolved {
+                return Ok(false);
+            }
+            let positions = user_positions::Entity::find()
+                .filter(user_positions::Column::UserId.eq(user_id))
+                .filter(user_positions::Column::EventId.eq(event_id))
+                .count(db)
+                .await
+                .map_err(database_error)?;
+            Ok(positions > 0)
+        }
+        TicketReferenceType::Transaction => {
+            let Ok(transaction_id) = reference_id.parse::<i32>() else {
+                return Ok(false);
+            };
+            Ok(transaction::Entity::find_by_id(transaction_id)
+                .one(db)
+                .await
+                .map_err(database_error)?
+                .is_some_and(|transaction| transaction.user_id == user_id))
+        }
+    }
+}
+
+async fn insert_message<C: ConnectionTrait>(
+    conn: &C,
+    ticket_id: i32,
+    author_id: i32,
+    from_staff: bool,
+    body: String,
+) -> Result<support_ticket_messages::Model, Error> {
+    support_ticket_messages::ActiveModel {
+        ticket_id: Set(ticket_id),
+        author_id: Set(author_id),
+        from_staff: Set(from_staff),
+        body: Set(body),
+        created_at: Set(Utc::now().fixed_offset()),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await
+    .map_err(database_error)
+}
+
+/// A ticket with its conversation, oldest message first
+async fn ticket_detail(
+    db: &DatabaseConnection,
+    ticket: support_tickets::Model,
+) -> Result<TicketDetailResponse, Error> {
+    let messages = support_ticket_messages::Entity::find()
+        .filter(support_ticket_messages::Column::TicketId.eq(ticket.id))
+        .order_by_asc(support_ticket_messages::Column::Id)
+        .all(db)
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(TicketMessageResponse::from)
+        .collect();
+
+    Ok(TicketDetailResponse {
+        ticket: TicketResponse::from(ticket),
+        messages,
+    })
+}
+
+/// Audit log entries staff need to judge a ticket: changes to the ticket, to
+/// the user, and to the event or transaction it disputes, newest first
+async fn audit_context(
+    db: &DatabaseConnection,
+    ticket: &support_tickets::Model,
+) -> Result<Vec<AuditLogResponse>, Error> {
+    let mut targets = vec![
+        (AuditTarget::Ticket, ticket.id),
+        (AuditTarget::User, ticket.user_id),
+    ];
+    match ticket.reference_type.as_str() {
+        "trade" => {
+            if let Some(trade) = trades::Entity::find_by_id(ticket.reference_id.clone())
+                .one(db)
+                .await
+                .map_err(database_error)?
+            {
+                targets.push((AuditTarget::Event, trade.event_id));
+            }
+        }
+        "settlement" => {
+            if let Ok(event_id) = ticket.reference_id.parse() {
+                targets.push((AuditTarget::Event, event_id));
+            }
+        }
+        "transaction" => {
+            if let Ok(transaction_id) = ticket.reference_id.parse() {
+                targets.push((AuditTarget::Transaction, transaction_id));
+            }
+        }
+        _ => {}
+    }
+
+    let condition =
+        targets
+            .into_iter()
+            .fold(Condition::any(), |condition, (target_type, target_id)| {
+                condition.add(
+                    Condition::all()
+                        .add(audit_logs::Column::TargetType.eq(target_type.as_str()))
+                        .add(audit_logs::Column::TargetId.eq(target_id)),
+                )
+            });
+
+    Ok(audit_logs::Entity::find()
+        .filter(condition)
+        .order_by_desc(audit_logs::Column::Id)
+        .limit(AUDIT_CONTEXT_LIMIT)
+        .all(db)
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(AuditLogResponse::from)
+        .collect())
+}
+
+/// Tell a ticket's owner about a reply or status change in the background
+fn notify_user(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    ws_server: &Addr<WebSocketServer>,
+    ticket: &support_tickets::Model,
+    body: String,
+) {
+    let (db, redis_pool, ws_server) = (db.clone(), redis_pool.clone(), ws_server.clone());
+    let user_id = ticket.user_id;
+    let notification = Notification {
+        category: NotificationCategory::Support,
+        title: format!("Support ticket #{}", ticket.id),
+        body,
+    };
+    tokio::spawn(async move {
+        if let Err(e) =
+            dispatcher::dispatch(&db, &redis_pool, &ws_server, user_id, notification).await
+        {
+            log::warn!("Failed to notify user {} of ticket update: {}", user_id, e);
+        }
+    });
+}
+
+/// Open a ticket disputing one of the caller's trades, settlements or
+/// transactions. A reference can only have one ticket that isn't closed.
+pub async fn create_ticket(
+    db: web::Data<DatabaseConnection>,
+    req: web::Json<CreateTicketRequest>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+    let req = req.into_inner();
+
+    let subject = req.subject.trim();
+    if subject.is_empty() {
+        return Ok(bad_request("Subject is required"));
+    }
+    if subject.chars().count() > MAX_SUBJECT_LENGTH {
+        return Ok(bad_request("Subject is too long"));
+    }
+    let body = match message_body(&req.message) {
+        Ok(body) => body,
+        Err(message) => return Ok(bad_request(message)),
+    };
+    let reference_id = req.reference_id.trim();
+    if !is_users_reference(db.get_ref(), user_id, req.reference_type, reference_id).await? {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": format!("No {} {} of yours was found", req.reference_type.as_str(), reference_id)
+        })));
+    }
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    // The user row serialises tickets opened at once about the same reference
+    users::Entity::find_by_id(user_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(database_error)?;
+    let existing = support_tickets::Entity::find()
+        .filter(support_tickets::Column::UserId.eq(user_id))
+        .filter(support_tickets::Column::ReferenceType.eq(req.reference_type.as_str()))
+        .filter(support_tickets::Column::ReferenceId.eq(reference_id))
+        .filter(support_tickets::Column::Status.ne(TicketStatus::Closed.as_str()))
+        .one(&txn)
+        .await
+        .map_err(database_error)?;
+    if let Some(existing) = existing {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": format!("Ticket #{} about this {} is still open", existing.id, existing.reference_type),
+            "ticket_id": existing.id
+        })));
+    }
+
+    let now = Utc::now().fixed_offset();
+    let ticket = support_tickets::ActiveModel {
+        user_id: Set(user_id),
+        reference_type: Set(req.reference_type.as_str().to_string()),
+        reference_id: Set(reference_id.to_string()),
+        subject: Set(subject.to_string()),
+        status: Set(TicketStatus::Open.as_str().to_string()),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(database_error)?;
+    insert_message(&txn, ticket.id, user_id, false, body).await?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    log::info!(
+        "User {} opened ticket {} about {} {}",
+        user_id,
+        ticket.id,
+        ticket.reference_type,
+        ticket.reference_id
+    );
+
+    Ok(HttpResponse::Created().json(json!({
+        "success": true,
+        "message": "Ticket opened",
+        "ticket": ticket_detail(db.get_ref(), ticket).await?
+    })))
+}
+
+/// The caller's tickets, most recently updated first
+pub async fn list_my_tickets(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<ListMyTicketsQuery>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+
+    let mut tickets_query =
+        support_tickets::Entity::find().filter(support_tickets::Column::UserId.eq(user_id));
+    if let Some(status) = query.status {
+        tickets_query = tickets_query.filter(support_tickets::Column::Status.eq(status.as_str()));
+    }
+
+    let page = query.pagination.get_page();
+    let limit = query.pagination.get_limit();
+    let offset = query.pagination.get_offset();
+
+    let total_count = tickets_query
+        .to_owned()
+        .count(db.get_ref())
+        .await
+        .map_err(database_error)?;
+
+    let tickets: Vec<TicketResponse> = tickets_query
+        .order_by_desc(support_tickets::Column::UpdatedAt)
+        .order_by_desc(support_tickets::Column::Id)
+        .limit(limit)
+        .offset(offset)
+        .all(db.get_ref())
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(TicketResponse::from)
+        .collect();
+
+    let response = PaginatedResponse::new(tickets, PaginationInfo::new(page, total_count, limit));
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "tickets": response.data,
+        "pagination": response.pagination
+    })))
+}
+
+/// One of the caller's tickets with its conversation
+pub async fn get_my_ticket(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+
+    let Some(ticket) = support_tickets::Entity::find_by_id(path.into_inner())
+        .filter(support_tickets::Column::UserId.eq(user_id))
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(ticket_not_found());
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "ticket": ticket_detail(db.get_ref(), ticket).await?
+    })))
+}
+
+/// Reply on one of the caller's tickets. Replying to a resolved ticket
+/// reopens it.
+pub async fn post_my_ticket_message(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+    req: web::Json<PostTicketMessageRequest>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let user_id = get_user_id(&auth_user)?;
+    let body = match message_body(&req.body) {
+        Ok(body) => body,
+        Err(message) => return Ok(bad_request(message)),
+    };
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let ticket = match lock_ticket(&txn, path.into_inner()).await? {
+        Some(ticket) if ticket.user_id == user_id => ticket,
+        _ => return Ok(ticket_not_found()),
+    };
+    let status = status_of(&ticket);
+    if status == TicketStatus::Closed {
+        return Ok(ticket_closed());
+    }
+
+    insert_message(&txn, ticket.id, user_id, false, body).await?;
+
+    let mut active_ticket: support_tickets::ActiveModel = ticket.into();
+    if status == TicketStatus::Resolved {
+        active_ticket.status = Set(TicketStatus::Open.as_str().to_string());
+        active_ticket.resolved_at = Set(None);
+    }
+    active_ticket.updated_at = Set(Utc::now().fixed_offset());
+    let ticket = active_ticket.update(&txn).await.map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Reply posted",
+        "ticket": ticket_detail(db.get_ref(), ticket).await?
+    })))
+}
+
+/// The triage queue, oldest first. Without a status it holds the open and
+/// in-progress tickets.
+pub async fn list_tickets(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<ListTicketsQuery>,
+) -> Result<HttpResponse, Error> {
+    let statuses = match query.status {
+        Some(status) => vec![status.as_str()],
+        None => vec![
+            TicketStatus::Open.as_str(),
+            TicketStatus::InProgress.as_str(),
+        ],
+    };
+    let mut tickets_query =
+        support_tickets::Entity::find().filter(support_tickets::Column::Status.is_in(statuses));
+    if let Some(reference_type) = query.reference_type {
+        tickets_query = tickets_query
+            .filter(support_tickets::Column::ReferenceType.eq(reference_type.as_str()));
+    }
+
+    let page = query.pagination.get_page();
+    let limit = query.pagination.get_limit();
+    let offset = query.pagination.get_offset();
+
+    let total_count = tickets_query
+        .to_owned()
+        .count(db.get_ref())
+        .await
+        .map_err(database_error)?;
+
+    let tickets: Vec<TicketResponse> = tickets_query
+        .order_by_asc(support_tickets::Column::CreatedAt)
+        .order_by_asc(support_tickets::Column::Id)
+        .limit(limit)
+        .offset(offset)
+        .all(db.get_ref())
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(TicketResponse::from)
+        .collect();
+
+    let response = PaginatedResponse::new(tickets, PaginationInfo::new(page, total_count, limit));
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "tickets": response.data,
+        "pagination": response.pagination
+    })))
+}
+
+/// A ticket with its conversation and the audit trail around what it disputes
+pub async fn get_ticket(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let Some(ticket) = support_tickets::Entity::find_by_id(path.into_inner())
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(ticket_not_found());
+    };
+
+    let audit_logs = audit_context(db.get_ref(), &ticket).await?;
+    let response = AdminTicketDetailResponse {
+        ticket: ticket_detail(db.get_ref(), ticket).await?,
+        audit_logs,
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "ticket": response
+    })))
+}
+
+/// Reply to a user on their ticket. An open ticket moves to in progress and,
+/// if nobody has it yet, is assigned to the replier.
+pub async fn post_staff_ticket_message(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    path: web::Path<i32>,
+    req: web::Json<PostTicketMessageRequest>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let staff_id = get_user_id(&auth_user)?;
+    let body = match message_body(&req.body) {
+        Ok(body) => body,
+        Err(message) => return Ok(bad_request(message)),
+    };
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let Some(ticket) = lock_ticket(&txn, path.into_inner()).await? else {
+        return Ok(ticket_not_found());
+    };
+    let status = status_of(&ticket);
+    if status == TicketStatus::Closed {
+        return Ok(ticket_closed());
+    }
+
+    insert_message(&txn, ticket.id, staff_id, true, body).await?;
+
+    let unassigned = ticket.assigned_to.is_none();
+    let mut active_ticket: support_tickets::ActiveModel = ticket.into();
+    if status == TicketStatus::Open {
+        active_ticket.status = Set(TicketStatus::InProgress.as_str().to_string());
+    }
+    if unassigned {
+        active_ticket.assigned_to = Set(Some(staff_id));
+    }
+    active_ticket.updated_at = Set(Utc::now().fixed_offset());
+    let ticket = active_ticket.update(&txn).await.map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    notify_user(
+        db.get_ref(),
+        redis_pool.get_ref(),
+        ws_server.get_ref(),
+        &ticket,
+        format!("Support replied to \"{}\"", ticket.subject),
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Reply posted",
+        "ticket": ticket_detail(db.get_ref(), ticket).await?
+    })))
+}
+
+/// Move a ticket through triage or hand it to another staff member. The
+/// assignee must be able to handle tickets.
+pub async fn update_ticket(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    path: web::Path<i32>,
+    req: web::Json<UpdateTicketRequest>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let staff_id = get_user_id(&auth_user)?;
+    let req = req.into_inner();
+    if req.status.is_none() && req.assigned_to.is_none() {
+        return Ok(bad_request("Nothing to update"));
+    }
+
+    if let Some(assignee_id) = req.assigned_to {
+        let assignee = users::Entity::find_by_id(assignee_id)
+            .one(db.get_ref())
+            .await
+            .map_err(database_error)?;
+        let can_handle = match assignee {
+            Some(assignee) if assignee.role == "admin" => true,
+            Some(_) => permissions::granted_by_roles(db.get_ref(), assignee_id)
+                .await
+                .map_err(database_error)?
+                .contains(&Permission::SupportUsers),
+            None => false,
+        };
+        if !can_handle {
+            return Ok(bad_request("Tickets can only be assigned to support staff"));
+        }
+    }
+
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let Some(ticket) = lock_ticket(&txn, path.into_inner()).await? else {
+        return Ok(ticket_not_found());
+    };
+    let status = status_of(&ticket);
+    if let Some(to) = req.status {
+        if !status.can_become(to) {
+            return Ok(HttpResponse::Conflict().json(json!({
+                "success": false,
+                "message": format!("Ticket is {} and can't become {}", status.as_str(), to.as_str())
+            })));
+        }
+    }
+
+    let before = audit::ticket_snapshot(&ticket);
+    let mut active_ticket: support_tickets::ActiveModel = ticket.into();
+    if let Some(to) = req.status {
+        active_ticket.status = Set(to.as_str().to_string());
+        active_ticket.resolved_at = Set(match to {
+            TicketStatus::Resolved | TicketStatus::Closed => Some(Utc::now().fixed_offset()),
+            TicketStatus::Open | TicketStatus::InProgress => None,
+        });
+    }
+    if let Some(assignee_id) = req.assigned_to {
+        active_ticket.assigned_to = Set(Some(assignee_id));
+    }
+    active_ticket.updated_at = Set(Utc::now().fixed_offset());
+    let ticket = active_ticket.update(&txn).await.map_err(database_error)?;
+
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: staff_id,
+            action: AuditAction::TicketUpdate,
+            target_id: ticket.id,
+            before: Some(before),
+            after: Some(audit::ticket_snapshot(&ticket)),
+            ip: audit::client_ip(&http_req),
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    if let Some(to) = req.status {
+        notify_user(
+            db.get_ref(),
+            redis_pool.get_ref(),
+            ws_server.get_ref(),
+            &ticket,
+            format!(
+                "\"{}\" is now {}",
+                ticket.subject,
+                to.as_str().replace('_', " ")
+            ),
+        );
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Ticket updated",
+        "ticket": TicketResponse::from(ticket)
+    })))
+}
@@ -0,0 +1,251 @@
+use crate::constants::config;
+use crate::tenants::registry;
+use crate::types::tenant::{
+    CreateTenantRequest, TenantDetailResponse, TenantResponse, UpdateTenantRequest,
+};
+use actix_web::{web, Error, HttpResponse, Result};
+use chrono::Utc;
+use entity::tenants;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+use serde_json::json;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+fn valid_slug(slug: &str) -> bool {
+    (1..=50).contains(&slug.len())
+        && slug
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+fn valid_color(color: &str) -> bool {
+    color.is_empty()
+        || (color.len() == 7
+            && color.starts_with('#')
+            && color[1..].bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// Origins as stored in `tenants.cors_origins`, or an error naming the first
+/// that isn't an http(s) origin
+fn join_origins(origins: &[String]) -> Result<String, String> {
+    let origins: Vec<&str> = origins.iter().map(|o| o.trim()).collect();
+    if let Some(bad) = origins
+        .iter()
+        .find(|o| !(o.starts_with("https://") || o.starts_with("http://")) || o.contains(','))
+    {
+        return Err(format!("Invalid CORS origin: {}", bad));
+    }
+    Ok(origins.join(","))
+}
+
+/// A tenant other than `except` already using a slug or domain
+async fn find_conflict(
+    db: &DatabaseConnection,
+    column: tenants::Column,
+    value: &str,
+    except: Option<i32>,
+) -> Result<bool, Error> {
+    let mut query = tenants::Entity::find().filter(column.eq(value));
+    if let Some(id) = except {
+        query = query.filter(tenants::Column::Id.ne(id));
+    }
+    Ok(query.one(db).await.map_err(database_error)?.is_some())
+}
+
+fn bad_request(message: impl Into<String>) -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "success": false,
+        "message": message.into()
+    }))
+}
+
+/// Branding and settings of the site the request was made to
+pub async fn get_current_tenant(
+    tenant: web::ReqData<tenants::Model>,
+) -> Result<HttpResponse, Error> {
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "tenant": TenantResponse::from(tenant.into_inner())
+    })))
+}
+
+/// Every tenant, active or not
+pub async fn list_tenants(db: web::Data<DatabaseConnection>) -> Result<HttpResponse, Error> {
+    let tenants: Vec<TenantDetailResponse> = tenants::Entity::find()
+        .order_by_asc(tenants::Column::Id)
+        .all(db.get_ref())
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(TenantDetailResponse::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "tenants": tenants
+    })))
+}
+
+/// Add a branded site
+pub async fn create_tenant(
+    db: web::Data<DatabaseConnection>,
+    req: web::Json<CreateTenantRequest>,
+) -> Result<HttpResponse, Error> {
+    let req = req.into_inner();
+    if !valid_slug(&req.slug) {
+        return Ok(bad_request(
+            "slug must be 1 to 50 lowercase letters, digits or '-'",
+        ));
+    }
+    if req.name.trim().is_empty() || req.name.len() > 100 {
+        return Ok(bad_request("name must be 1 to 100 characters"));
+    }
+    let primary_color = req.primary_color.unwrap_or_default();
+    if !valid_color(&primary_color) {
+        return Ok(bad_request("primary_color must look like #1a2b3c"));
+    }
+    let cors_origins = match join_origins(&req.cors_origins.unwrap_or_default()) {
+        Ok(origins) => origins,
+        Err(message) => return Ok(bad_request(message)),
+    };
+    let domain = req
+        .domain
+        .map(|d| d.trim().to_ascii_lowercase())
+        .filter(|d| !d.is_empty());
+
+    if find_conflict(db.get_ref(), tenants::Column::Slug, &req.slug, None).await? {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "A tenant with this slug already exists"
+        })));
+    }
+    if let Some(domain) = &domain {
+        if find_conflict(db.get_ref(), tenants::Column::Domain, domain, None).await? {
+            return Ok(HttpResponse::Conflict().json(json!({
+                "success": false,
+                "message": "Another tenant already uses this domain"
+            })));
+        }
+    }
+
+    let now = Utc::now().fixed_offset();
+    let tenant = tenants::ActiveModel {
+        slug: Set(req.slug),
+        name: Set(req.name.trim().to_string()),
+        domain: Set(domain),
+        cors_origins: Set(cors_origins),
+        logo_url: Set(req.logo_url.unwrap_or_default()),
+        primary_color: Set(primary_color),
+        support_email: Set(req.support_email.unwrap_or_default()),
+        allow_registration: Set(req.allow_registration.unwrap_or(true)),
+        is_active: Set(true),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(db.get_ref())
+    .await
+    .map_err(database_error)?;
+
+    log::warn!("Tenant {} created", tenant.slug);
+    registry::upsert(tenant.clone());
+
+    Ok(HttpResponse::Created().json(json!({
+        "success": true,
+        "tenant": TenantDetailResponse::from(tenant)
+    })))
+}
+
+/// Change a tenant's branding, settings or domain, or deactivate its site
+pub async fn update_tenant(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<i32>,
+    req: web::Json<UpdateTenantRequest>,
+) -> Result<HttpResponse, Error> {
+    let tenant_id = path.into_inner();
+    let req = req.into_inner();
+
+    let Some(tenant) = tenants::Entity::find_by_id(tenant_id)
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Tenant not found"
+        })));
+    };
+
+    if req.is_active == Some(false) && tenant.slug == config::get_default_tenant_slug() {
+        return Ok(bad_request("The default tenant can't be deactivated"));
+    }
+
+    let mut active: tenants::ActiveModel = tenant.into();
+    if let Some(name) = req.name {
+        if name.trim().is_empty() || name.len() > 100 {
+            return Ok(bad_request("name must be 1 to 100 characters"));
+        }
+        active.name = Set(name.trim().to_string());
+    }
+    if let Some(domain) = req.domain {
+        let domain = domain.trim().to_ascii_lowercase();
+        if domain.is_empty() {
+            active.domain = Set(None);
+        } else {
+            if find_conflict(
+                db.get_ref(),
+                tenants::Column::Domain,
+                &domain,
+                Some(tenant_id),
+            )
+            .await?
+            {
+                return Ok(HttpResponse::Conflict().json(json!({
+                    "success": false,
+                    "message": "Another tenant already uses this domain"
+                })));
+            }
+            active.domain = Set(Some(domain));
+        }
+    }
+    if let Some(origins) = req.cors_origins {
+        match join_origins(&origins) {
+            Ok(origins) => active.cors_origins = Set(origins),
+            Err(message) => return Ok(bad_request(message)),
+        }
+    }
+    if let Some(color) = req.primary_color {
+        if !valid_color(&color) {
+            return Ok(bad_request("primary_color must look like #1a2b3c"));
+        }
+        active.primary_color = Set(color);
+    }
+    if let Some(logo_url) = req.logo_url {
+        active.logo_url = Set(logo_url);
+    }
+    if let Some(support_email) = req.support_email {
+        active.support_email = Set(support_email);
+    }
+    if let Some(allow_registration) = req.allow_registration {
+        active.allow_registration = Set(allow_registration);
+    }
+    if let Some(is_active) = req.is_active {
+        active.is_active = Set(is_active);
+    }
+    active.updated_at = Set(Utc::now().fixed_offset());
+
+    let tenant = active.update(db.get_ref()).await.map_err(database_error)?;
+
+    log::warn!("Tenant {} updated", tenant.slug);
+    registry::upsert(tenant.clone());
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "tenant": TenantDetailResponse::from(tenant)
+    })))
+}
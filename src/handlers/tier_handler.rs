@@ -0,0 +1,26 @@
+use crate::tiers::service::TierService;
+use actix_web::{web, Error, HttpResponse, Result};
+use sea_orm::DatabaseConnection;
+use serde_json::json;
+
+pub async fn get_my_tier(
+    db: web::Data<DatabaseConnection>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    let tier = TierService::new(db.get_ref().clone())
+        .get_tier(user_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to load user tier: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to load user tier")
+        })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "tier": tier
+    })))
+}
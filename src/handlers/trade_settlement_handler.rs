@@ -0,0 +1,127 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::order_book::db_persistence::trade_from_model;
+use crate::order_book::position_tracker::PositionTracker;
+use crate::order_book::settlement;
+use crate::types::order_book::{
+    settlement_status, ListUnsettledTradesQuery, UnsettledTradeResponse,
+};
+use crate::utils::auth::get_user_id;
+use crate::utils::pagination::{PaginatedResponse, PaginationInfo};
+use actix_web::{web, Error, HttpResponse, Result};
+use entity::trades;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use serde_json::json;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+/// Trades not yet settled, oldest first. Without a status, both pending and
+/// failed trades are listed.
+pub async fn list_unsettled_trades(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<ListUnsettledTradesQuery>,
+) -> Result<HttpResponse, Error> {
+    let statuses = match query.status.as_deref() {
+        None => vec![settlement_status::PENDING, settlement_status::FAILED],
+        Some(status)
+            if status == settlement_status::PENDING || status == settlement_status::FAILED =>
+        {
+            vec![status]
+        }
+        Some(_) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": "status must be pending or failed"
+            })));
+        }
+    };
+
+    let trades_query =
+        trades::Entity::find().filter(trades::Column::SettlementStatus.is_in(statuses));
+
+    let page = query.pagination.get_page();
+    let limit = query.pagination.get_limit();
+    let offset = query.pagination.get_offset();
+
+    let total_count = trades_query
+        .to_owned()
+        .count(db.get_ref())
+        .await
+        .map_err(database_error)?;
+
+    let trades: Vec<UnsettledTradeResponse> = trades_query
+        .order_by_asc(trades::Column::Timestamp)
+        .order_by_asc(trades::Column::Sequence)
+        .limit(limit)
+        .offset(offset)
+        .all(db.get_ref())
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(UnsettledTradeResponse::from)
+        .collect();
+
+    let response = PaginatedResponse::new(trades, PaginationInfo::new(page, total_count, limit));
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "trades": response.data,
+        "pagination": response.pagination
+    })))
+}
+
+/// Settle an unsettled trade now, whatever its attempts so far
+pub async fn retry_trade_settlement(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<String>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let trade_id = path.into_inner();
+
+    let Some(db_trade) = trades::Entity::find_by_id(trade_id.clone())
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Trade not found"
+        })));
+    };
+    if db_trade.settlement_status == settlement_status::SETTLED {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "Trade is already settled"
+        })));
+    }
+
+    log::warn!(
+        "User {} retried settlement of trade {} after {} attempts",
+        admin_id,
+        trade_id,
+        db_trade.settlement_attempts
+    );
+
+    let fees = settlement::recorded_fees(&db_trade);
+    let trade = trade_from_model(db_trade);
+    let position_tracker = PositionTracker::new(db.get_ref().clone());
+    if let Err(e) =
+        settlement::settle_or_record_failure(db.get_ref(), &position_tracker, &trade, &fees).await
+    {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": format!("Trade could not be settled: {}", e)
+        })));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Trade settled"
+    })))
+}
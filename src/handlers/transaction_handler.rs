@@ -1,6 +1,17 @@
-use crate::types::transaction::{DepositRequest, TransactionResponse, WithdrawRequest};
+use crate::middleware::idempotency;
+use crate::order_book::reservations;
+use crate::promotions::service::PromotionService;
+use crate::tiers::service::TierService;
+use crate::types::kyc::KycStatus;
+use crate::types::transaction::{
+    transaction_status, DepositRequest, TransactionResponse, WithdrawRequest,
+};
+use crate::types::websocket::{OpsEventKind, OpsSeverity};
 use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
+use crate::utils::kyc;
 use crate::utils::pagination::{PaginatedResponse, PaginationInfo, PaginationQuery};
+use crate::wallets::{balances, transactions};
+use crate::websocket::ops;
 use crate::websocket::server::WebSocketServer;
 use actix::Addr;
 use actix_web::{web, Error, HttpResponse, Result};
@@ -8,12 +19,22 @@ use deadpool_redis::Pool;
 use entity::{transaction, users};
 use rust_decimal::Decimal as RustDecimal;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
-    QueryOrder, QuerySelect, Set, TransactionTrait,
+    prelude::Expr, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
 };
 use serde_json::json;
 use uuid::Uuid;
 
+/// Refusal of a deposit or withdrawal the user's verification status doesn't
+/// allow
+fn kyc_required(message: &str) -> HttpResponse {
+    HttpResponse::Forbidden().json(json!({
+        "message": message,
+        "status": "error",
+        "kyc_required": true
+    }))
+}
+
 pub async fn deposit_money(
     db: web::Data<DatabaseConnection>,
     redis_pool: web::Data<Pool>,
@@ -33,12 +54,26 @@ pub async fn deposit_money(
             "status": "error"
         })));
     }
+    let currency = match balances::resolve_currency(req.currency.as_deref()) {
+        Ok(currency) => currency,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "message": message,
+                "status": "error"
+            })))
+        }
+    };
 
     // Start a database transaction
-    let txn = db.begin().await.map_err(|e| {
-        log::error!("Failed to start transaction: {}", e);
-        actix_web::error::ErrorInternalServerError("Database transaction failed")
-    })?;
+    let txn = match db.begin().await {
+        Ok(txn) => txn,
+        Err(e) => {
+            log::error!("Failed to start transaction: {}", e);
+            return Ok(idempotency::nothing_persisted(
+                actix_web::error::ErrorInternalServerError("Database transaction failed"),
+            ));
+        }
+    };
 
     // Get current user
     let user = users::Entity::find_by_id(user_id)
@@ -66,32 +101,47 @@ pub async fn deposit_money(
         })));
     }
 
-    let balance_before = user
-        .wallet_balance
-        .to_string()
-        .parse::<f64>()
-        .unwrap_or(0.0);
-    let balance_after = balance_before + amount;
+    // Deposits past the threshold, counted over the account's lifetime,
+    // need a verified identity
+    let requested = RustDecimal::try_from(amount).unwrap_or_default();
+    if let Some(message) = kyc::deposit_refusal(&txn, &user, &currency, requested)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?
+    {
+        return Ok(kyc_required(&message));
+    }
 
-    // Update user balance
-    let mut user_active_model: users::ActiveModel = user.into();
-    user_active_model.wallet_balance = Set(RustDecimal::try_from(balance_after).unwrap());
-    user_active_model.updated_at = Set(chrono::Utc::now().naive_utc());
+    let wallet = balances::lock(&txn, user_id, &currency)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+    let balance_before = wallet.balance.to_string().parse::<f64>().unwrap_or(0.0);
+    let balance_after = balance_before + amount;
 
-    let _updated_user = user_active_model.update(&txn).await.map_err(|e| {
-        log::error!("Failed to update user balance: {}", e);
-        actix_web::error::ErrorInternalServerError("Failed to update balance")
-    })?;
+    // Update wallet balance
+    let updated_wallet =
+        balances::set_balance(&txn, wallet, RustDecimal::try_from(balance_after).unwrap())
+            .await
+            .map_err(|e| {
+                log::error!("Failed to update wallet balance: {}", e);
+                actix_web::error::ErrorInternalServerError("Failed to update balance")
+            })?;
 
     // Create transaction record
     let reference_id = Uuid::new_v4().to_string();
     let transaction = transaction::ActiveModel {
         user_id: Set(user_id),
         r#type: Set("deposit".to_string()),
+        currency: Set(currency.clone()),
         amount: Set(RustDecimal::try_from(amount).unwrap()),
         balance_before: Set(RustDecimal::try_from(balance_before).unwrap()),
         balance_after: Set(RustDecimal::try_from(balance_after).unwrap()),
-        status: Set("completed".to_string()),
+        status: Set(transaction_status::COMPLETED.to_string()),
         reference_id: Set(reference_id.clone()),
         created_at: Set(chrono::Utc::now().naive_utc()),
         ..Default::default()
@@ -102,6 +152,31 @@ pub async fn deposit_money(
         actix_web::error::ErrorInternalServerError("Failed to create transaction")
     })?;
 
+    // Top the deposit up from a running deposit match, as its own transaction
+    let (updated_wallet, bonus) = PromotionService::credit_deposit_match(
+        &txn,
+        updated_wallet,
+        RustDecimal::try_from(amount).unwrap(),
+        chrono::Utc::now(),
+    )
+    .await
+    .map_err(|e| {
+        log::error!("{}", e);
+        actix_web::error::ErrorInternalServerError("Failed to apply promotions")
+    })?;
+
+    let (balance_after, bonus_amount) = match bonus {
+        Some(bonus) => (
+            updated_wallet
+                .balance
+                .to_string()
+                .parse::<f64>()
+                .unwrap_or(balance_after),
+            bonus.to_string().parse::<f64>().unwrap_or(0.0),
+        ),
+        None => (balance_after, 0.0),
+    };
+
     // Commit transaction
     txn.commit().await.map_err(|e| {
         log::error!("Failed to commit transaction: {}", e);
@@ -142,8 +217,10 @@ pub async fn deposit_money(
         "status": "success",
         "data": {
             "amount": amount,
+            "currency": currency,
             "balance_before": balance_before,
             "balance_after": balance_after,
+            "bonus": bonus_amount,
             "reference_id": reference_id
         }
     })))
@@ -168,12 +245,26 @@ pub async fn withdraw_money(
             "status": "error"
         })));
     }
+    let currency = match balances::resolve_currency(req.currency.as_deref()) {
+        Ok(currency) => currency,
+        Err(message) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "message": message,
+                "status": "error"
+            })))
+        }
+    };
 
     // Start a database transaction
-    let txn = db.begin().await.map_err(|e| {
-        log::error!("Failed to start transaction: {}", e);
-        actix_web::error::ErrorInternalServerError("Database transaction failed")
-    })?;
+    let txn = match db.begin().await {
+        Ok(txn) => txn,
+        Err(e) => {
+            log::error!("Failed to start transaction: {}", e);
+            return Ok(idempotency::nothing_persisted(
+                actix_web::error::ErrorInternalServerError("Database transaction failed"),
+            ));
+        }
+    };
 
     // Get current user
     let user = users::Entity::find_by_id(user_id)
@@ -201,46 +292,116 @@ pub async fn withdraw_money(
         })));
     }
 
-    let balance_before = user
-        .wallet_balance
+    if user.kyc_status != KycStatus::Verified.as_str() {
+        return Ok(kyc_required(
+            "Identity verification is required to withdraw",
+        ));
+    }
+
+    let wallet = balances::find(&txn, user_id, &currency)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+    let balance_before = wallet
+        .as_ref()
+        .map(|wallet| wallet.balance)
+        .unwrap_or_default()
         .to_string()
         .parse::<f64>()
         .unwrap_or(0.0);
 
-    if balance_before < amount {
+    // Funds held for open buy orders can't be withdrawn
+    let available = balances::available_in(wallet.as_ref())
+        .to_string()
+        .parse::<f64>()
+        .unwrap_or(0.0);
+    if available < amount {
         return Ok(HttpResponse::BadRequest().json(json!({
             "message": "Insufficient balance".to_string(),
             "status": "error"
         })));
     }
 
-    let balance_after = balance_before - amount;
+    // Tier benefits set how much can leave the account each UTC day
+    let benefits = TierService::new(db.get_ref().clone())
+        .benefits(user_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to resolve user tier: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to resolve user tier")
+        })?;
 
-    // Update user balance
-    let mut user_active_model: users::ActiveModel = user.into();
-    user_active_model.wallet_balance = Set(RustDecimal::try_from(balance_after).unwrap());
-    user_active_model.updated_at = Set(chrono::Utc::now().naive_utc());
+    if let Some(daily_limit) = benefits.daily_withdrawal_limit {
+        let start_of_day = chrono::Utc::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .unwrap_or_default();
+        let withdrawn_today: Option<RustDecimal> = transaction::Entity::find()
+            .select_only()
+            .column_as(
+                Expr::cust("COALESCE(SUM(transaction.amount), 0)"),
+                "withdrawn",
+            )
+            .filter(transaction::Column::UserId.eq(user_id))
+            .filter(transaction::Column::Type.eq("withdraw"))
+            .filter(transaction::Column::Currency.eq(&currency))
+            // Withdrawals awaiting approval or payout count against the limit too
+            .filter(transaction::Column::Status.is_in([
+                transaction_status::PENDING,
+                transaction_status::PROCESSING,
+                transaction_status::COMPLETED,
+            ]))
+            .filter(transaction::Column::CreatedAt.gte(start_of_day))
+            .into_tuple()
+            .one(&txn)
+            .await
+            .map_err(|e| {
+                log::error!("Database error: {}", e);
+                actix_web::error::ErrorInternalServerError("Database error occurred")
+            })?;
+
+        let requested = RustDecimal::try_from(amount).unwrap_or_default();
+        if withdrawn_today.unwrap_or_default() + requested > daily_limit {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "message": format!("Daily withdrawal limit of {} {} exceeded for your tier", daily_limit, currency),
+                "status": "error"
+            })));
+        }
+    }
 
-    let _updated_user = user_active_model.update(&txn).await.map_err(|e| {
-        log::error!("Failed to update user balance: {}", e);
-        actix_web::error::ErrorInternalServerError("Failed to update balance")
-    })?;
+    // Hold the funds until the withdrawal is paid out, fails or is rejected
+    let requested = RustDecimal::try_from(amount).unwrap_or_default();
+    if let Err(e) = reservations::hold(&txn, user_id, &currency, requested).await {
+        if e == reservations::INSUFFICIENT_BALANCE {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "message": "Insufficient balance".to_string(),
+                "status": "error"
+            })));
+        }
+        log::error!("{}", e);
+        return Err(actix_web::error::ErrorInternalServerError(
+            "Failed to reserve funds",
+        ));
+    }
 
-    // Create transaction record
+    // Create transaction record; the wallet is only debited once paid out
     let reference_id = Uuid::new_v4().to_string();
     let transaction = transaction::ActiveModel {
         user_id: Set(user_id),
         r#type: Set("withdraw".to_string()),
-        amount: Set(RustDecimal::try_from(amount).unwrap()),
+        currency: Set(currency.clone()),
+        amount: Set(requested),
         balance_before: Set(RustDecimal::try_from(balance_before).unwrap()),
-        balance_after: Set(RustDecimal::try_from(balance_after).unwrap()),
-        status: Set("completed".to_string()),
+        balance_after: Set(RustDecimal::try_from(balance_before).unwrap()),
+        status: Set(transaction_status::PENDING.to_string()),
         reference_id: Set(reference_id.clone()),
         created_at: Set(chrono::Utc::now().naive_utc()),
         ..Default::default()
     };
 
-    let _transaction = transaction.insert(&txn).await.map_err(|e| {
+    let transaction = transaction.insert(&txn).await.map_err(|e| {
         log::error!("Failed to create transaction record: {}", e);
         actix_web::error::ErrorInternalServerError("Failed to create transaction")
     })?;
@@ -251,43 +412,40 @@ pub async fn withdraw_money(
         actix_web::error::ErrorInternalServerError("Failed to commit transaction")
     })?;
 
-    // Invalidate relevant caches
-    let cache_service = CacheService::new(redis_pool.get_ref().clone());
-    let user_cache_key = create_cache_key(cache_keys::USER_PREFIX, &user_id.to_string());
-    let portfolio_cache_key = format!("portfolio:{}", user_id);
-
-    if let Err(e) = cache_service.delete(&user_cache_key).await {
-        log::warn!("Failed to invalidate user cache: {}", e);
-    }
-    if let Err(e) = cache_service.delete(&portfolio_cache_key).await {
-        log::warn!("Failed to invalidate portfolio cache: {}", e);
-    }
-
-    // Notify subscribers via WebSocket
-    let user_id_int = user_id_str.parse::<i32>().unwrap_or(0);
-
-    // Send personalized updates to WebSocket subscribers
-    ws_server.do_send(crate::websocket::server::BroadcastTransactionsUpdate {
-        user_id: user_id_int,
-    });
-
-    // Broadcast portfolio update (no pagination, so use existing method)
-    let handlers =
-        crate::websocket::handlers::WebSocketHandlers::new(db.clone(), ws_server.get_ref().clone());
+    // Let admins know there is a withdrawal to review
+    ops::publish(
+        OpsEventKind::WithdrawalRequested,
+        OpsSeverity::Info,
+        format!(
+            "User {} requested a withdrawal of {} {}",
+            user_id, transaction.amount, transaction.currency
+        ),
+        json!({
+            "transaction_id": transaction.id,
+            "user_id": user_id,
+            "currency": transaction.currency,
+            "amount": transaction.amount,
+            "priority": benefits.priority_withdrawals
+        }),
+    );
 
-    tokio::spawn(async move {
-        // Update portfolio data for the user (since balance changed)
-        handlers.fetch_and_broadcast_portfolio(user_id_int).await;
-    });
+    transactions::publish_status_change(
+        db.get_ref(),
+        redis_pool.get_ref(),
+        ws_server.get_ref(),
+        &transaction,
+    );
 
-    Ok(HttpResponse::Ok().json(json!({
-        "message": "Withdrawal successful".to_string(),
+    Ok(HttpResponse::Accepted().json(json!({
+        "message": "Withdrawal requested; it will be paid out once approved".to_string(),
         "status": "success",
         "data": {
+            "transaction_id": transaction.id,
             "amount": amount,
-            "balance_before": balance_before,
-            "balance_after": balance_after,
-            "reference_id": reference_id
+            "currency": currency,
+            "transaction_status": transaction.status,
+            "reference_id": reference_id,
+            "priority": benefits.priority_withdrawals
         }
     })))
 }
@@ -351,12 +509,17 @@ pub async fn get_transaction_history(
             id: t.id,
             user_id: t.user_id,
             r#type: t.r#type,
+            currency: t.currency,
             amount: t.amount.to_string().parse::<f64>().unwrap_or(0.0),
             balance_before: t.balance_before.to_string().parse::<f64>().unwrap_or(0.0),
             balance_after: t.balance_after.to_string().parse::<f64>().unwrap_or(0.0),
             status: t.status,
             reference_id: t.reference_id,
             created_at: chrono::DateTime::from_naive_utc_and_offset(t.created_at, chrono::Utc),
+            rejection_reason: t.rejection_reason,
+            failure_reason: t.failure_reason,
+            gateway_reference: t.gateway_reference,
+            correlation_id: t.correlation_id,
         })
         .collect();
 
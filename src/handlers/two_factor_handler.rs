@@ -0,0 +1,333 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::types::two_factor::{
+    BackupCodesResponse, TwoFactorCodeRequest, TwoFactorEnrollmentResponse, TwoFactorStatusResponse,
+};
+use crate::utils::auth::get_user_id;
+use crate::utils::totp;
+use crate::utils::two_factor::{self, CodeCheck};
+use actix_web::{web, Error, HttpResponse, Result};
+use chrono::Utc;
+use deadpool_redis::Pool;
+use entity::{user_security, users};
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
+use serde_json::json;
+
+fn parse_user_id(user_id: &web::ReqData<String>) -> Result<i32, Error> {
+    user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))
+}
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+/// The user's settings if two-factor authentication is on
+async fn find_enabled(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Option<user_security::Model>, Error> {
+    Ok(two_factor::find(db, user_id)
+        .await
+        .map_err(database_error)?
+        .filter(|security| security.totp_enabled))
+}
+
+/// Check a code for one of the endpoints below, or the response refusing it
+async fn require_code(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    security: &user_security::Model,
+    code: &str,
+    allow_backup_code: bool,
+) -> Result<Option<HttpResponse>, Error> {
+    let check = two_factor::check_code(db, Some(redis_pool), security, code, allow_backup_code)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to check two-factor code: {}", e);
+            actix_web::error::ErrorInternalServerError("Failed to check two-factor code")
+        })?;
+    Ok(match check {
+        CodeCheck::Valid => {
+            two_factor::clear_failures(redis_pool, security.user_id).await;
+            None
+        }
+        CodeCheck::Invalid => Some(HttpResponse::Unauthorized().json(json!({
+            "success": false,
+            "message": "Invalid two-factor code"
+        }))),
+        CodeCheck::Locked => Some(HttpResponse::TooManyRequests().json(json!({
+            "success": false,
+            "message": "Too many invalid two-factor codes, try again later"
+        }))),
+    })
+}
+
+/// New backup codes, and their hashes as stored
+fn new_backup_codes() -> (Vec<String>, String) {
+    let codes = totp::generate_backup_codes();
+    let hashes: Vec<String> = codes.iter().map(|c| totp::hash_backup_code(c)).collect();
+    (codes, totp::join_backup_hashes(&hashes))
+}
+
+/// Whether two-factor authentication is on, and how many backup codes are left
+pub async fn get_two_factor_status(
+    db: web::Data<DatabaseConnection>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id = parse_user_id(&user_id)?;
+    let security = find_enabled(db.get_ref(), user_id).await?;
+
+    let status = match security {
+        Some(security) => TwoFactorStatusResponse {
+            enabled: true,
+            enabled_at: security.totp_enabled_at.map(|t| t.with_timezone(&Utc)),
+            backup_codes_remaining: totp::split_backup_hashes(&security.backup_codes).len(),
+        },
+        None => TwoFactorStatusResponse {
+            enabled: false,
+            enabled_at: None,
+            backup_codes_remaining: 0,
+        },
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "two_factor": status
+    })))
+}
+
+/// Start enrolling: generate a secret for the user's authenticator app. It
+/// isn't enforced until `confirm_two_factor` sees a code from it, and starting
+/// again replaces it.
+pub async fn enroll_two_factor(
+    db: web::Data<DatabaseConnection>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id = parse_user_id(&user_id)?;
+
+    let user = users::Entity::find_by_id(user_id)
+        .one(db.get_ref())
+        .await
+        .map_err(database_error)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("User not found"))?;
+
+    let existing = two_factor::find(db.get_ref(), user_id)
+        .await
+        .map_err(database_error)?;
+    if existing.as_ref().is_some_and(|s| s.totp_enabled) {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "Two-factor authentication is already enabled"
+        })));
+    }
+
+    let secret = totp::generate_secret();
+    let now = Utc::now().fixed_offset();
+    match existing {
+        Some(security) => {
+            let mut active: user_security::ActiveModel = security.into();
+            active.totp_secret = Set(Some(secret.clone()));
+            active.last_totp_step = Set(None);
+            active.updated_at = Set(now);
+            active.update(db.get_ref()).await.map_err(database_error)?;
+        }
+        None => {
+            user_security::ActiveModel {
+                user_id: Set(user_id),
+                totp_secret: Set(Some(secret.clone())),
+                totp_enabled: Set(false),
+                backup_codes: Set(String::new()),
+                created_at: Set(now),
+                updated_at: Set(now),
+                ..Default::default()
+            }
+            .insert(db.get_ref())
+            .await
+            .map_err(database_error)?;
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "enrollment": TwoFactorEnrollmentResponse {
+            otpauth_url: totp::provisioning_uri(&user.email, &secret),
+            secret,
+        }
+    })))
+}
+
+/// Finish enrolling with a code from the authenticator app, turning
+/// two-factor authentication on and issuing backup codes
+pub async fn confirm_two_factor(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    req: web::Json<TwoFactorCodeRequest>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id = parse_user_id(&user_id)?;
+
+    let security = two_factor::find(db.get_ref(), user_id)
+        .await
+        .map_err(database_error)?
+        .filter(|security| security.totp_secret.is_some());
+    let Some(security) = security else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Start enrollment before confirming it"
+        })));
+    };
+    if security.totp_enabled {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": "Two-factor authentication is already enabled"
+        })));
+    }
+
+    if let Some(response) =
+        require_code(db.get_ref(), &redis_pool, &security, &req.code, false).await?
+    {
+        return Ok(response);
+    }
+
+    let (codes, hashes) = new_backup_codes();
+    let now = Utc::now().fixed_offset();
+    let mut active: user_security::ActiveModel = security.into();
+    active.totp_enabled = Set(true);
+    active.totp_enabled_at = Set(Some(now));
+    active.backup_codes = Set(hashes);
+    active.updated_at = Set(now);
+    active.update(db.get_ref()).await.map_err(database_error)?;
+
+    log::info!("User {} enabled two-factor authentication", user_id);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Two-factor authentication enabled",
+        "backup": BackupCodesResponse { backup_codes: codes }
+    })))
+}
+
+/// Replace the user's backup codes, invalidating the old ones
+pub async fn regenerate_backup_codes(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    req: web::Json<TwoFactorCodeRequest>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id = parse_user_id(&user_id)?;
+
+    let Some(security) = find_enabled(db.get_ref(), user_id).await? else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Two-factor authentication is not enabled"
+        })));
+    };
+
+    if let Some(response) =
+        require_code(db.get_ref(), &redis_pool, &security, &req.code, true).await?
+    {
+        return Ok(response);
+    }
+
+    // Reload, as a backup code presented above was just struck off
+    let security = find_enabled(db.get_ref(), user_id)
+        .await?
+        .ok_or_else(|| actix_web::error::ErrorConflict("Two-factor authentication was disabled"))?;
+
+    let (codes, hashes) = new_backup_codes();
+    let mut active: user_security::ActiveModel = security.into();
+    active.backup_codes = Set(hashes);
+    active.updated_at = Set(Utc::now().fixed_offset());
+    active.update(db.get_ref()).await.map_err(database_error)?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "backup": BackupCodesResponse { backup_codes: codes }
+    })))
+}
+
+/// Turn two-factor authentication off with a current or backup code
+pub async fn disable_two_factor(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    req: web::Json<TwoFactorCodeRequest>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id = parse_user_id(&user_id)?;
+
+    let Some(security) = find_enabled(db.get_ref(), user_id).await? else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Two-factor authentication is not enabled"
+        })));
+    };
+
+    if let Some(response) =
+        require_code(db.get_ref(), &redis_pool, &security, &req.code, true).await?
+    {
+        return Ok(response);
+    }
+
+    clear(db.get_ref(), security).await?;
+    log::info!("User {} disabled two-factor authentication", user_id);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Two-factor authentication disabled"
+    })))
+}
+
+/// Support recovery for a user who lost both their authenticator and backup
+/// codes: turn two-factor authentication off so they can enroll again.
+/// Nobody can reset their own.
+pub async fn reset_two_factor(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    path: web::Path<i32>,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let user_id = path.into_inner();
+
+    if user_id == admin_id {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "message": "Someone else must reset your two-factor authentication"
+        })));
+    }
+
+    let Some(security) = find_enabled(db.get_ref(), user_id).await? else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Two-factor authentication is not enabled for this user"
+        })));
+    };
+
+    clear(db.get_ref(), security).await?;
+    two_factor::clear_failures(&redis_pool, user_id).await;
+    log::warn!(
+        "User {} reset two-factor authentication for user {}",
+        admin_id,
+        user_id
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Two-factor authentication reset"
+    })))
+}
+
+/// Turn two-factor authentication off, forgetting the secret and backup codes
+async fn clear(db: &DatabaseConnection, security: user_security::Model) -> Result<(), Error> {
+    let mut active: user_security::ActiveModel = security.into();
+    active.totp_secret = Set(None);
+    active.totp_enabled = Set(false);
+    active.totp_enabled_at = Set(None);
+    active.last_totp_step = Set(None);
+    active.backup_codes = Set(String::new());
+    active.updated_at = Set(Utc::now().fixed_offset());
+    active.update(db).await.map_err(database_error)?;
+    Ok(())
+}
@@ -1,6 +1,9 @@
+use crate::constants::config;
 use crate::types::user::{ListUsersQuery, UserResponse};
+use crate::types::wallet::WalletResponse;
 use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
 use crate::utils::pagination::{PaginatedResponse, PaginationInfo};
+use crate::wallets::balances;
 use actix_web::{web, Error, HttpResponse, Result};
 use deadpool_redis::Pool;
 use entity::users;
@@ -47,7 +50,21 @@ pub async fn list_users(
             actix_web::error::ErrorInternalServerError("Database error occurred")
         })?;
 
-    let users_response: Vec<UserResponse> = users.into_iter().map(UserResponse::from).collect();
+    let user_ids: Vec<i32> = users.iter().map(|user| user.id).collect();
+    let mut wallets = balances::for_users(db.get_ref(), &user_ids)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    let users_response: Vec<UserResponse> = users
+        .into_iter()
+        .map(|user| {
+            let wallets = wallets.remove(&user.id).unwrap_or_default();
+            UserResponse::new(user, wallets)
+        })
+        .collect();
 
     let pagination_info = PaginationInfo::new(page, total_count, limit);
     let response = PaginatedResponse::new(users_response, pagination_info);
@@ -103,15 +120,25 @@ pub async fn get_user_details(
         })));
     }
 
+    let wallets = balances::for_user(db.get_ref(), user.id)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
     let user_response = json!({
         "id": user.id,
         "username": user.username,
         "email": user.email,
         "phone": user.phone,
         "full_name": user.full_name,
-        "wallet_balance": user.wallet_balance,
+        "wallet_balance": balances::balance_in(&wallets, &config::get_base_currency()),
+        "wallets": wallets.into_iter().map(WalletResponse::from).collect::<Vec<_>>(),
         "is_active": user.is_active,
         "role": user.role,
+        "kyc_status": user.kyc_status,
+        "age_status": user.age_status,
         "created_at": user.created_at,
         "updated_at": user.updated_at,
     });
@@ -175,15 +202,25 @@ pub async fn get_current_user_details(
         })));
     }
 
+    let wallets = balances::for_user(db.get_ref(), user.id)
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
     let user_response = json!({
         "id": user.id,
         "username": user.username,
         "email": user.email,
         "phone": user.phone,
         "full_name": user.full_name,
-        "wallet_balance": user.wallet_balance,
+        "wallet_balance": balances::balance_in(&wallets, &config::get_base_currency()),
+        "wallets": wallets.into_iter().map(WalletResponse::from).collect::<Vec<_>>(),
         "is_active": user.is_active,
         "role": user.role,
+        "kyc_status": user.kyc_status,
+        "age_status": user.age_status,
         "created_at": user.created_at,
         "updated_at": user.updated_at,
     });
@@ -0,0 +1,158 @@
+use crate::constants::config;
+use crate::handlers::order_book_handler::settle_trades;
+use crate::notifications::dispatcher;
+use crate::order_book::{
+    db_persistence::DbPersistence,
+    position_tracker::PositionTracker,
+    redis_persistence::RedisOrderBookPersistence,
+    reservations, risk,
+    router::{self, ExecutionVenue},
+    OrderSide,
+};
+use crate::types::order_book::{ExternalFillRequest, TradeResponse};
+use crate::types::websocket::PriceUpdateSource;
+use crate::websocket::market_data;
+use crate::websocket::server::WebSocketServer;
+use actix::Addr;
+use actix_web::{web, Error, HttpResponse, Result};
+use deadpool_redis::Pool;
+use sea_orm::{prelude::Decimal, DatabaseConnection};
+use serde_json::json;
+
+/// Record a fill the external venue gateway reports for an order routed to
+/// it, settling it against the external venue account
+pub async fn report_external_fill(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    req: web::Json<ExternalFillRequest>,
+) -> Result<HttpResponse, Error> {
+    let db_persistence = DbPersistence::new(db.get_ref().clone());
+    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
+    let position_tracker = PositionTracker::new(db.get_ref().clone());
+
+    let order = db_persistence.get_order(&req.order_id).await.map_err(|e| {
+        log::error!("Failed to load order: {}", e);
+        actix_web::error::ErrorInternalServerError("Failed to load order")
+    })?;
+    let order = match order {
+        Some(order) => order,
+        None => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "success": false,
+                "message": "Order not found"
+            })));
+        }
+    };
+
+    let venue = router::venue_for_event(db.get_ref(), order.event_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to load execution venue: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+    if venue != ExecutionVenue::External {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Order was not routed to the external venue"
+        })));
+    }
+    if !order.is_open() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Order is not open"
+        })));
+    }
+
+    let remaining = order.quantity - order.filled_quantity;
+    if req.quantity <= 0 || req.quantity > remaining {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": format!("Fill quantity must be between 1 and {}", remaining)
+        })));
+    }
+    let within_limit = match order.side {
+        OrderSide::Buy => req.price <= order.price,
+        OrderSide::Sell => req.price >= order.price,
+    };
+    if req.price <= Decimal::ZERO || !within_limit {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "Fill price is outside the order's limit"
+        })));
+    }
+
+    let trade = match router::house_trade(
+        db.get_ref(),
+        &order,
+        config::get_external_venue_user_id(),
+        req.price,
+        req.quantity,
+    )
+    .await
+    {
+        Ok(trade) => trade,
+        Err(e) if e == reservations::INSUFFICIENT_BALANCE => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "message": "External venue account can't cover this fill"
+            })));
+        }
+        Err(e) => {
+            log::error!("Failed to record external fill: {}", e);
+            return Err(actix_web::error::ErrorInternalServerError(
+                "Failed to record fill",
+            ));
+        }
+    };
+
+    let trades = vec![trade];
+    settle_trades(
+        db.get_ref(),
+        &db_persistence,
+        &redis_persistence,
+        &position_tracker,
+        &trades,
+    )
+    .await?;
+    market_data::publish_trades(&trades, Some(order.side.clone()));
+    dispatcher::notify_fills(
+        db.get_ref(),
+        redis_pool.get_ref(),
+        ws_server.get_ref(),
+        &trades,
+    );
+    risk::publish_for_trades(ws_server.get_ref(), &trades);
+
+    let (event_id, option_id) = (order.event_id, order.option_id);
+    tokio::spawn(async move {
+        crate::order_book::price_updater::update_option_price_immediately(
+            db,
+            redis_pool,
+            ws_server,
+            event_id,
+            option_id,
+            PriceUpdateSource::Trade,
+        )
+        .await;
+    });
+
+    let trade = trades.into_iter().next().map(|t| TradeResponse {
+        id: t.id,
+        event_id: t.event_id,
+        option_id: t.option_id,
+        buyer_id: t.buyer_id,
+        seller_id: t.seller_id,
+        price: t.price,
+        quantity: t.quantity,
+        total_amount: t.total_amount,
+        timestamp: t.timestamp,
+        sequence: t.sequence,
+    });
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Fill recorded",
+        "trade": trade
+    })))
+}
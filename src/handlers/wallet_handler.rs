@@ -0,0 +1,91 @@
+use crate::order_book::types::{OrderSide, OrderStatus};
+use crate::types::transaction::transaction_status;
+use crate::types::wallet::{BalanceResponse, OrderHoldResponse, WithdrawalHoldResponse};
+use crate::wallets::balances;
+use actix_web::{web, Error, HttpResponse, Result};
+use entity::{orders, transaction};
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use serde_json::json;
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+/// The user's balance in each currency: the total, what open orders and
+/// unfinished withdrawals hold, and what is left to spend, with the holds listed
+pub async fn get_my_balance(
+    db: web::Data<DatabaseConnection>,
+    user_id: web::ReqData<String>,
+) -> Result<HttpResponse, Error> {
+    let user_id: i32 = user_id
+        .parse()
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid user ID"))?;
+
+    let wallets = balances::for_user(db.get_ref(), user_id)
+        .await
+        .map_err(database_error)?;
+
+    // Buy orders hold their cost and sell orders the collateral of the shares
+    // they sell short
+    let open_orders = orders::Entity::find()
+        .filter(orders::Column::UserId.eq(user_id))
+        .filter(
+            Condition::any()
+                .add(orders::Column::Side.eq(OrderSide::Buy.to_string()))
+                .add(orders::Column::ShortQuantity.gt(0)),
+        )
+        .filter(orders::Column::Status.is_in([
+            OrderStatus::Pending.to_string(),
+            OrderStatus::PartiallyFilled.to_string(),
+        ]))
+        .order_by_asc(orders::Column::CreatedAt)
+        .all(db.get_ref())
+        .await
+        .map_err(database_error)?;
+
+    // Withdrawals hold their funds until they are paid out, fail or are rejected
+    let unfinished_withdrawals = transaction::Entity::find()
+        .filter(transaction::Column::UserId.eq(user_id))
+        .filter(transaction::Column::Type.eq("withdraw"))
+        .filter(
+            transaction::Column::Status
+                .is_in([transaction_status::PENDING, transaction_status::PROCESSING]),
+        )
+        .order_by_asc(transaction::Column::CreatedAt)
+        .all(db.get_ref())
+        .await
+        .map_err(database_error)?;
+
+    let balances: Vec<BalanceResponse> = wallets
+        .into_iter()
+        .map(|wallet| {
+            let orders: Vec<OrderHoldResponse> = open_orders
+                .iter()
+                .filter(|order| order.currency == wallet.currency)
+                .map(OrderHoldResponse::from)
+                .filter(|hold| !hold.amount.is_zero())
+                .collect();
+            let withdrawals: Vec<WithdrawalHoldResponse> = unfinished_withdrawals
+                .iter()
+                .filter(|withdrawal| withdrawal.currency == wallet.currency)
+                .map(WithdrawalHoldResponse::from)
+                .collect();
+            BalanceResponse {
+                available: balances::available(&wallet),
+                currency: wallet.currency,
+                total: wallet.balance,
+                reserved: wallet.reserved_balance,
+                reserved_by_orders: orders.iter().map(|hold| hold.amount).sum(),
+                reserved_by_withdrawals: withdrawals.iter().map(|hold| hold.amount).sum(),
+                orders,
+                withdrawals,
+            }
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "balances": balances
+    })))
+}
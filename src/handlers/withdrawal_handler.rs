@@ -0,0 +1,414 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::middleware::internal_auth::InternalService;
+use crate::order_book::reservations;
+use crate::payments::payouts;
+use crate::types::audit::AuditAction;
+use crate::types::transaction::{
+    transaction_status, ListWithdrawalsQuery, RejectWithdrawalRequest, ReportPayoutRequest,
+    WithdrawalResponse,
+};
+use crate::utils::audit::{self, AuditEntry};
+use crate::utils::auth::get_user_id;
+use crate::utils::pagination::{PaginatedResponse, PaginationInfo};
+use crate::wallets::{balances, transactions};
+use crate::websocket::server::WebSocketServer;
+use actix::Addr;
+use actix_web::{web, Error, HttpRequest, HttpResponse, Result};
+use chrono::Utc;
+use deadpool_redis::Pool;
+use entity::{transaction, users};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use serde_json::json;
+
+const STATUSES: [&str; 5] = [
+    transaction_status::PENDING,
+    transaction_status::PROCESSING,
+    transaction_status::COMPLETED,
+    transaction_status::FAILED,
+    transaction_status::REJECTED,
+];
+
+fn database_error(e: impl std::fmt::Display) -> Error {
+    log::error!("Database error: {}", e);
+    actix_web::error::ErrorInternalServerError("Database error occurred")
+}
+
+/// Withdrawals by status, oldest first so the queue is worked in order
+pub async fn list_withdrawals(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<ListWithdrawalsQuery>,
+) -> Result<HttpResponse, Error> {
+    let status = query
+        .status
+        .as_deref()
+        .unwrap_or(transaction_status::PENDING);
+    if !STATUSES.contains(&status) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "status must be pending, processing, completed, failed or rejected"
+        })));
+    }
+
+    let withdrawals_query = transaction::Entity::find()
+        .filter(transaction::Column::Type.eq("withdraw"))
+        .filter(transaction::Column::Status.eq(status));
+
+    let page = query.pagination.get_page();
+    let limit = query.pagination.get_limit();
+    let offset = query.pagination.get_offset();
+
+    let total_count = withdrawals_query
+        .to_owned()
+        .count(db.get_ref())
+        .await
+        .map_err(database_error)?;
+
+    let withdrawals: Vec<WithdrawalResponse> = withdrawals_query
+        .order_by_asc(transaction::Column::CreatedAt)
+        .order_by_asc(transaction::Column::Id)
+        .limit(limit)
+        .offset(offset)
+        .all(db.get_ref())
+        .await
+        .map_err(database_error)?
+        .into_iter()
+        .map(WithdrawalResponse::from)
+        .collect();
+
+    let response =
+        PaginatedResponse::new(withdrawals, PaginationInfo::new(page, total_count, limit));
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "withdrawals": response.data,
+        "pagination": response.pagination
+    })))
+}
+
+/// Approve or reject a pending withdrawal. Approving it hands it to the
+/// payout relay, still holding its funds; rejecting it releases the hold.
+#[allow(clippy::too_many_arguments)]
+async fn review(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    ws_server: &Addr<WebSocketServer>,
+    admin_id: i32,
+    transaction_id: i32,
+    approved: bool,
+    reason: Option<String>,
+    ip: Option<String>,
+) -> Result<HttpResponse, Error> {
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let withdrawal = transaction::Entity::find_by_id(transaction_id)
+        .filter(transaction::Column::Type.eq("withdraw"))
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(database_error)?;
+    let Some(withdrawal) = withdrawal else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Withdrawal not found"
+        })));
+    };
+    if withdrawal.status != transaction_status::PENDING {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": format!("Withdrawal is already {}", withdrawal.status)
+        })));
+    }
+    if withdrawal.user_id == admin_id {
+        return Ok(HttpResponse::Forbidden().json(json!({
+            "success": false,
+            "message": "You can't review your own withdrawal"
+        })));
+    }
+
+    let user_id = withdrawal.user_id;
+    let amount = withdrawal.amount;
+    let currency = withdrawal.currency.clone();
+    if !approved {
+        reservations::release(&txn, user_id, &currency, amount)
+            .await
+            .map_err(database_error)?;
+    }
+
+    let status = if approved {
+        transaction_status::PROCESSING
+    } else {
+        transaction_status::REJECTED
+    };
+    let mut active_withdrawal = transactions::transition(withdrawal, status)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    active_withdrawal.reviewed_by = Set(Some(admin_id));
+    active_withdrawal.reviewed_at = Set(Some(Utc::now().naive_utc()));
+    active_withdrawal.rejection_reason = Set(reason.clone());
+    let withdrawal = active_withdrawal
+        .update(&txn)
+        .await
+        .map_err(database_error)?;
+
+    let mut after = json!({
+        "status": status,
+        "user_id": user_id,
+        "currency": currency,
+        "amount": amount
+    });
+    if let Some(reason) = reason {
+        after["reason"] = json!(reason);
+    }
+    audit::record(
+        &txn,
+        AuditEntry {
+            actor_id: admin_id,
+            action: if approved {
+                AuditAction::WithdrawalApprove
+            } else {
+                AuditAction::WithdrawalReject
+            },
+            target_id: transaction_id,
+            before: Some(json!({ "status": transaction_status::PENDING })),
+            after: Some(after),
+            ip,
+        },
+    )
+    .await
+    .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    log::warn!(
+        "User {} {} withdrawal {} of {} {} for user {}",
+        admin_id,
+        if approved { "approved" } else { "rejected" },
+        transaction_id,
+        amount,
+        currency,
+        user_id
+    );
+
+    if approved {
+        if let Err(e) = payouts::request_payout(redis_pool, &withdrawal).await {
+            log::error!("{}", e);
+            // Nothing will pay it out, so it fails and its funds are released
+            finish_payout(
+                db,
+                redis_pool,
+                ws_server,
+                transaction_id,
+                Some("Payout relay unavailable".to_string()),
+            )
+            .await?;
+            return Ok(HttpResponse::ServiceUnavailable().json(json!({
+                "success": false,
+                "message": "Payout relay unavailable; the withdrawal has failed and its funds were released"
+            })));
+        }
+    }
+
+    transactions::publish_status_change(db, redis_pool, ws_server, &withdrawal);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "withdrawal": WithdrawalResponse::from(withdrawal)
+    })))
+}
+
+/// Close a withdrawal the payout relay has handled. Paid out, the wallet is
+/// debited by its amount; failed, the funds go back to the available
+/// balance. Either way the hold is released.
+async fn finish_payout(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    ws_server: &Addr<WebSocketServer>,
+    transaction_id: i32,
+    error: Option<String>,
+) -> Result<HttpResponse, Error> {
+    let txn = db.begin().await.map_err(database_error)?;
+
+    let Some(withdrawal) = transaction::Entity::find_by_id(transaction_id)
+        .filter(transaction::Column::Type.eq("withdraw"))
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(database_error)?
+    else {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "Withdrawal not found"
+        })));
+    };
+    if withdrawal.status != transaction_status::PROCESSING {
+        return Ok(HttpResponse::Conflict().json(json!({
+            "success": false,
+            "message": format!("Withdrawal is {}, not processing", withdrawal.status)
+        })));
+    }
+
+    let user_id = withdrawal.user_id;
+    let amount = withdrawal.amount;
+    let currency = withdrawal.currency.clone();
+    if users::Entity::find_by_id(user_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(database_error)?
+        .is_none()
+    {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "success": false,
+            "message": "User not found"
+        })));
+    };
+
+    reservations::release(&txn, user_id, &currency, amount)
+        .await
+        .map_err(database_error)?;
+    let wallet = balances::lock(&txn, user_id, &currency)
+        .await
+        .map_err(database_error)?;
+
+    let balance_before = wallet.balance;
+    let status = match &error {
+        None => transaction_status::COMPLETED,
+        Some(_) => transaction_status::FAILED,
+    };
+    let balance_after = if error.is_none() {
+        let balance_after = balance_before - amount;
+        balances::set_balance(&txn, wallet, balance_after)
+            .await
+            .map_err(database_error)?;
+        balance_after
+    } else {
+        balance_before
+    };
+
+    let mut active_withdrawal = transactions::transition(withdrawal, status)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    active_withdrawal.balance_before = Set(balance_before);
+    active_withdrawal.balance_after = Set(balance_after);
+    active_withdrawal.failure_reason = Set(error.clone());
+    let withdrawal = active_withdrawal
+        .update(&txn)
+        .await
+        .map_err(database_error)?;
+
+    txn.commit().await.map_err(database_error)?;
+
+    match &error {
+        None => log::info!(
+            "Paid out withdrawal {} of {} {} for user {}",
+            transaction_id,
+            amount,
+            currency,
+            user_id
+        ),
+        Some(error) => log::warn!(
+            "Withdrawal {} of {} {} for user {} failed: {}",
+            transaction_id,
+            amount,
+            currency,
+            user_id,
+            error
+        ),
+    }
+
+    transactions::publish_status_change(db, redis_pool, ws_server, &withdrawal);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "withdrawal": WithdrawalResponse::from(withdrawal)
+    })))
+}
+
+/// Approve a pending withdrawal for payout
+pub async fn approve_withdrawal(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    path: web::Path<i32>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+
+    review(
+        db.get_ref(),
+        redis_pool.get_ref(),
+        ws_server.get_ref(),
+        admin_id,
+        path.into_inner(),
+        true,
+        None,
+        audit::client_ip(&http_req),
+    )
+    .await
+}
+
+/// Reject a pending withdrawal, returning its funds to the user's available
+/// balance
+pub async fn reject_withdrawal(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    path: web::Path<i32>,
+    req: web::Json<RejectWithdrawalRequest>,
+    http_req: HttpRequest,
+    auth_user: web::ReqData<AuthenticatedUser>,
+) -> Result<HttpResponse, Error> {
+    let admin_id = get_user_id(&auth_user)?;
+    let reason = req.into_inner().reason;
+    if reason.trim().is_empty() {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": "reason is required"
+        })));
+    }
+
+    review(
+        db.get_ref(),
+        redis_pool.get_ref(),
+        ws_server.get_ref(),
+        admin_id,
+        path.into_inner(),
+        false,
+        Some(reason.trim().to_string()),
+        audit::client_ip(&http_req),
+    )
+    .await
+}
+
+/// Whether the payout relay paid out an approved withdrawal (internal)
+pub async fn report_payout(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    path: web::Path<i32>,
+    req: web::Json<ReportPayoutRequest>,
+    service: web::ReqData<InternalService>,
+) -> Result<HttpResponse, Error> {
+    let transaction_id = path.into_inner();
+    let error = req
+        .into_inner()
+        .error
+        .map(|error| error.trim().to_string())
+        .filter(|error| !error.is_empty());
+    log::info!(
+        "{} reported payout of withdrawal {}",
+        service.0,
+        transaction_id
+    );
+
+    finish_payout(
+        db.get_ref(),
+        redis_pool.get_ref(),
+        ws_server.get_ref(),
+        transaction_id,
+        error,
+    )
+    .await
+}
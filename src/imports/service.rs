@@ -0,0 +1,687 @@
+use crate::order_book::position_cache;
+use crate::types::audit::AuditAction;
+use crate::types::import::{ImportRecord, ImportReport, ImportRowError};
+use crate::types::kyc::{AgeStatus, KycStatus};
+use crate::utils::audit::{self, AuditEntry};
+use crate::wallets::balances;
+use bcrypt::HashParts;
+use chrono::Utc;
+use entity::{event_options, events, legacy_imports, transaction, user_positions, users};
+use sea_orm::{
+    prelude::{Decimal, Expr},
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, EntityTrait,
+    QueryFilter, Set, TransactionTrait,
+};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Largest balance a transaction row can hold
+const MAX_BALANCE: i64 = 100_000_000;
+
+/// Columns a CSV import may have, named as in a JSON record
+const CSV_COLUMNS: [&str; 10] = [
+    "email",
+    "username",
+    "full_name",
+    "password_hash",
+    "currency",
+    "balance",
+    "event_id",
+    "option_id",
+    "quantity",
+    "average_price",
+];
+
+/// Split CSV text into rows of fields. Fields may be quoted, with `""` for a
+/// quote inside them; blank lines are skipped.
+fn split_csv(body: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                if row.iter().any(|f| !f.is_empty()) {
+                    rows.push(std::mem::take(&mut row));
+                }
+                row.clear();
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("CSV ends inside a quoted field".to_string());
+    }
+    row.push(field);
+    if row.iter().any(|f| !f.is_empty()) {
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn parse_cell<T: FromStr>(value: &str, column: &str, row: usize) -> Result<Option<T>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("Row {}: {} is not valid: {}", row, column, value))
+}
+
+/// Records from a CSV import with a header row naming its columns. Empty
+/// cells are treated as missing.
+pub fn parse_csv(body: &str) -> Result<Vec<ImportRecord>, String> {
+    let mut rows = split_csv(body)?.into_iter();
+    let header: Vec<String> = rows
+        .next()
+        .ok_or("CSV has no header row")?
+        .into_iter()
+        .map(|column| column.trim().to_ascii_lowercase())
+        .collect();
+    if let Some(unknown) = header.iter().find(|c| !CSV_COLUMNS.contains(&c.as_str())) {
+        return Err(format!("Unknown CSV column: {}", unknown));
+    }
+    if !header.iter().any(|c| c == "email") {
+        return Err("CSV has no email column".to_string());
+    }
+
+    rows.enumerate()
+        .map(|(index, fields)| {
+            let row = index + 1;
+            if fields.len() != header.len() {
+                return Err(format!(
+                    "Row {}: expected {} fields, found {}",
+                    row,
+                    header.len(),
+                    fields.len()
+                ));
+            }
+            let mut record = ImportRecord::default();
+            for (column, value) in header.iter().zip(fields) {
+                let value = value.trim();
+                let text = (!value.is_empty()).then(|| value.to_string());
+                match column.as_str() {
+                    "email" => record.email = value.to_string(),
+                    "username" => record.username = text,
+                    "full_name" => record.full_name = text,
+                    "password_hash" => record.password_hash = text,
+                    "currency" => record.currency = text,
+                    "balance" => record.balance = parse_cell(value, column, row)?,
+                    "event_id" => record.event_id = parse_cell(value, column, row)?,
+                    "option_id" => record.option_id = parse_cell(value, column, row)?,
+                    "quantity" => record.quantity = parse_cell(value, column, row)?,
+                    "average_price" => record.average_price = parse_cell(value, column, row)?,
+                    _ => {}
+                }
+            }
+            Ok(record)
+        })
+        .collect()
+}
+
+/// A position a row opens
+struct PlannedPosition {
+    event_id: i32,
+    option_id: i32,
+    quantity: i32,
+    average_price: Decimal,
+}
+
+/// A balance a row credits
+struct PlannedBalance {
+    row: usize,
+    currency: String,
+    amount: Decimal,
+}
+
+/// Everything an import does to one user
+struct PlannedUser {
+    email: String,
+    username: Option<String>,
+    full_name: Option<String>,
+    password_hash: Option<String>,
+    existing: Option<users::Model>,
+    balances: Vec<PlannedBalance>,
+    positions: Vec<PlannedPosition>,
+}
+
+/// What a single row asks for, checked on its own
+fn check_record(record: &ImportRecord) -> Result<(), String> {
+    if !record.email.contains('@') {
+        return Err("email is required".to_string());
+    }
+    if let Some(username) = &record.username {
+        if username.len() > 50 {
+            return Err("username is longer than 50 characters".to_string());
+        }
+    }
+    if let Some(password_hash) = &record.password_hash {
+        if HashParts::from_str(password_hash).is_err() {
+            return Err("password_hash is not a bcrypt hash".to_string());
+        }
+    }
+    if record.balance.is_some_and(|balance| {
+        balance <= Decimal::ZERO || balance.scale() > 2 || balance >= Decimal::from(MAX_BALANCE)
+    }) {
+        return Err(format!(
+            "balance must be positive, below {} and have at most 2 decimal places",
+            MAX_BALANCE
+        ));
+    }
+    if record.balance.is_none() && record.currency.is_some() {
+        return Err("currency is given without a balance".to_string());
+    }
+    let position_fields = [
+        record.event_id.is_some(),
+        record.option_id.is_some(),
+        record.quantity.is_some(),
+        record.average_price.is_some(),
+    ];
+    if position_fields.contains(&true) && position_fields.contains(&false) {
+        return Err("a position needs event_id, option_id, quantity and average_price".to_string());
+    }
+    if record.quantity.is_some_and(|quantity| quantity <= 0) {
+        return Err("quantity must be positive".to_string());
+    }
+    if record
+        .average_price
+        .is_some_and(|price| price < Decimal::ZERO || price.scale() > 8)
+    {
+        return Err(
+            "average_price can't be negative or have more than 8 decimal places".to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// `Some` field of a later row for a user, checked against the first row
+fn merge_field(
+    first: &mut Option<String>,
+    later: &Option<String>,
+    column: &str,
+    first_row: usize,
+) -> Result<(), String> {
+    match (first.as_ref(), later) {
+        (Some(a), Some(b)) if a != b => Err(format!("{} differs from row {}", column, first_row)),
+        (None, Some(b)) => {
+            *first = Some(b.clone());
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Validate every record against the tenant's users, events and positions,
+/// returning the users to import in the order they first appear
+async fn plan(
+    db: &DatabaseConnection,
+    tenant_id: i32,
+    records: Vec<ImportRecord>,
+    errors: &mut Vec<ImportRowError>,
+) -> Result<Vec<PlannedUser>, String> {
+    let mut planned: Vec<PlannedUser> = Vec::new();
+    let mut first_rows: Vec<usize> = Vec::new();
+    let mut by_email: HashMap<String, usize> = HashMap::new();
+    let mut record_rows: Vec<(usize, usize, ImportRecord)> = Vec::new();
+
+    for (index, mut record) in records.into_iter().enumerate() {
+        let row = index + 1;
+        record.email = record.email.trim().to_string();
+        if let Err(message) = check_record(&record) {
+            errors.push(ImportRowError { row, message });
+            continue;
+        }
+
+        let user_index = match by_email.get(&record.email) {
+            Some(&user_index) => {
+                let user = &mut planned[user_index];
+                let first_row = first_rows[user_index];
+                let merged =
+                    merge_field(&mut user.username, &record.username, "username", first_row)
+                        .and_then(|_| {
+                            merge_field(
+                                &mut user.full_name,
+                                &record.full_name,
+                                "full_name",
+                                first_row,
+                            )
+                        })
+                        .and_then(|_| {
+                            merge_field(
+                                &mut user.password_hash,
+                                &record.password_hash,
+                                "password_hash",
+                                first_row,
+                            )
+                        });
+                if let Err(message) = merged {
+                    errors.push(ImportRowError { row, message });
+                    continue;
+                }
+                user_index
+            }
+            None => {
+                by_email.insert(record.email.clone(), planned.len());
+                first_rows.push(row);
+                planned.push(PlannedUser {
+                    email: record.email.clone(),
+                    username: record.username.clone(),
+                    full_name: record.full_name.clone(),
+                    password_hash: record.password_hash.clone(),
+                    existing: None,
+                    balances: Vec::new(),
+                    positions: Vec::new(),
+                });
+                planned.len() - 1
+            }
+        };
+        record_rows.push((row, user_index, record));
+    }
+
+    let emails: Vec<String> = planned.iter().map(|user| user.email.clone()).collect();
+    let existing: HashMap<String, users::Model> = users::Entity::find()
+        .filter(users::Column::Email.is_in(emails))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load users: {}", e))?
+        .into_iter()
+        .map(|user| (user.email.clone(), user))
+        .collect();
+
+    let new_usernames: Vec<String> = planned
+        .iter()
+        .filter(|user| !existing.contains_key(&user.email))
+        .filter_map(|user| user.username.clone())
+        .collect();
+    let taken_usernames: Vec<String> = users::Entity::find()
+        .filter(users::Column::Username.is_in(new_usernames))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load users: {}", e))?
+        .into_iter()
+        .map(|user| user.username)
+        .collect();
+
+    let mut usernames: HashMap<String, usize> = HashMap::new();
+    for (user_index, user) in planned.iter_mut().enumerate() {
+        let row = first_rows[user_index];
+        if let Some(found) = existing.get(&user.email) {
+            if found.tenant_id != tenant_id {
+                errors.push(ImportRowError {
+                    row,
+                    message: "email belongs to a user of another tenant".to_string(),
+                });
+            }
+            user.existing = Some(found.clone());
+            continue;
+        }
+        let Some(username) = &user.username else {
+            errors.push(ImportRowError {
+                row,
+                message: "username is required for a new user".to_string(),
+            });
+            continue;
+        };
+        if taken_usernames.contains(username) {
+            errors.push(ImportRowError {
+                row,
+                message: format!("username {} is already taken", username),
+            });
+        } else if let Some(other) = usernames.insert(username.clone(), row) {
+            errors.push(ImportRowError {
+                row,
+                message: format!("username {} is also used by row {}", username, other),
+            });
+        }
+    }
+
+    let option_ids: Vec<i32> = record_rows
+        .iter()
+        .filter_map(|(_, _, record)| record.option_id)
+        .collect();
+    let options: HashMap<i32, event_options::Model> = event_options::Entity::find()
+        .filter(event_options::Column::Id.is_in(option_ids.clone()))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load options: {}", e))?
+        .into_iter()
+        .map(|option| (option.id, option))
+        .collect();
+    let event_ids: Vec<i32> = record_rows
+        .iter()
+        .filter_map(|(_, _, record)| record.event_id)
+        .collect();
+    let events: HashMap<i32, events::Model> = events::Entity::find()
+        .filter(events::Column::Id.is_in(event_ids))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load events: {}", e))?
+        .into_iter()
+        .map(|event| (event.id, event))
+        .collect();
+    let existing_ids: Vec<i32> = planned
+        .iter()
+        .filter_map(|user| user.existing.as_ref().map(|u| u.id))
+        .collect();
+    let held: Vec<(i32, i32)> = user_positions::Entity::find()
+        .filter(user_positions::Column::UserId.is_in(existing_ids))
+        .filter(user_positions::Column::OptionId.is_in(option_ids))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load positions: {}", e))?
+        .into_iter()
+        .map(|position| (position.user_id, position.option_id))
+        .collect();
+
+    let mut balance_rows: HashMap<(usize, String), usize> = HashMap::new();
+    let mut position_rows: HashMap<(usize, i32), usize> = HashMap::new();
+    for (row, user_index, record) in record_rows {
+        if let Some(amount) = record.balance {
+            match balances::resolve_currency(record.currency.as_deref()) {
+                Err(message) => errors.push(ImportRowError { row, message }),
+                Ok(currency) => {
+                    if let Some(other) = balance_rows.insert((user_index, currency.clone()), row) {
+                        errors.push(ImportRowError {
+                            row,
+                            message: format!("{} balance is also set by row {}", currency, other),
+                        });
+                    } else {
+                        planned[user_index].balances.push(PlannedBalance {
+                            row,
+                            currency,
+                            amount,
+                        });
+                    }
+                }
+            }
+        }
+
+        let (Some(event_id), Some(option_id), Some(quantity), Some(average_price)) = (
+            record.event_id,
+            record.option_id,
+            record.quantity,
+            record.average_price,
+        ) else {
+            continue;
+        };
+        let message = match (events.get(&event_id), options.get(&option_id)) {
+            (Some(event), _) if event.tenant_id != tenant_id => Some("event not found".to_string()),
+            (None, _) => Some("event not found".to_string()),
+            (_, None) => Some("option not found".to_string()),
+            (_, Some(option)) if option.event_id != event_id => {
+                Some("option does not belong to the event".to_string())
+            }
+            (Some(event), _) if ["resolved", "cancelled"].contains(&event.status.as_str()) => {
+                Some(format!("event is {}", event.status))
+            }
+            _ => None,
+        };
+        if let Some(message) = message {
+            errors.push(ImportRowError { row, message });
+            continue;
+        }
+        let user = &mut planned[user_index];
+        if user
+            .existing
+            .as_ref()
+            .is_some_and(|u| held.contains(&(u.id, option_id)))
+        {
+            errors.push(ImportRowError {
+                row,
+                message: "user already holds a position in the option".to_string(),
+            });
+        } else if let Some(other) = position_rows.insert((user_index, option_id), row) {
+            errors.push(ImportRowError {
+                row,
+                message: format!("position is also set by row {}", other),
+            });
+        } else {
+            user.positions.push(PlannedPosition {
+                event_id,
+                option_id,
+                quantity,
+                average_price,
+            });
+        }
+    }
+
+    errors.sort_by_key(|error| error.row);
+    Ok(planned)
+}
+
+/// Create or reuse the user and apply their balances and positions, with an
+/// audit entry for the user
+async fn apply_user(
+    txn: &DatabaseTransaction,
+    tenant_id: i32,
+    admin_id: i32,
+    ip: Option<String>,
+    import_id: &str,
+    user: &PlannedUser,
+) -> Result<i32, String> {
+    let (user_id, created) = match &user.existing {
+        Some(existing) => (existing.id, false),
+        None => {
+            // Users without a hash from the old system get a random password
+            // nobody knows. The cheapest cost keeps large imports quick.
+            let password_hash = match &user.password_hash {
+                Some(password_hash) => password_hash.clone(),
+                None => bcrypt::hash(Uuid::new_v4().to_string(), 4)
+                    .map_err(|e| format!("Failed to hash password: {}", e))?,
+            };
+            let created = users::ActiveModel {
+                username: Set(user.username.clone().unwrap_or_default()),
+                email: Set(user.email.clone()),
+                password_hash: Set(password_hash),
+                full_name: Set(user.full_name.clone()),
+                is_active: Set(true),
+                role: Set("user".to_string()),
+                tenant_id: Set(tenant_id),
+                kyc_status: Set(KycStatus::Unverified.as_str().to_string()),
+                age_status: Set(AgeStatus::Unverified.as_str().to_string()),
+                ..Default::default()
+            }
+            .insert(txn)
+            .await
+            .map_err(|e| format!("Failed to create user {}: {}", user.email, e))?;
+            (created.id, true)
+        }
+    };
+
+    let mut credited = Vec::new();
+    for balance in &user.balances {
+        let wallet = balances::lock(txn, user_id, &balance.currency)
+            .await
+            .map_err(|e| format!("Failed to lock wallet: {}", e))?;
+        let balance_before = wallet.balance;
+        let balance_after = balance_before + balance.amount;
+        balances::set_balance(txn, wallet, balance_after)
+            .await
+            .map_err(|e| format!("Failed to credit wallet: {}", e))?;
+
+        let reference_id = format!("import:{}:{}", import_id, balance.row);
+        transaction::ActiveModel {
+            user_id: Set(user_id),
+            r#type: Set("import".to_string()),
+            amount: Set(balance.amount),
+            currency: Set(balance.currency.clone()),
+            balance_before: Set(balance_before),
+            balance_after: Set(balance_after),
+            status: Set("completed".to_string()),
+            reference_id: Set(reference_id.clone()),
+            created_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .insert(txn)
+        .await
+        .map_err(|e| format!("Failed to record import transaction: {}", e))?;
+
+        credited.push(json!({
+            "currency": balance.currency,
+            "amount": balance.amount,
+            "balance_before": balance_before,
+            "balance_after": balance_after,
+            "reference_id": reference_id
+        }));
+    }
+
+    let mut opened = Vec::new();
+    for position in &user.positions {
+        let now = Utc::now().fixed_offset();
+        user_positions::ActiveModel {
+            user_id: Set(user_id),
+            event_id: Set(position.event_id),
+            option_id: Set(position.option_id),
+            quantity: Set(position.quantity),
+            average_price: Set(position.average_price),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(txn)
+        .await
+        .map_err(|e| format!("Failed to create position: {}", e))?;
+
+        // Imported shares are new to this exchange, so they count as issued
+        event_options::Entity::update_many()
+            .col_expr(
+                event_options::Column::SharesIssued,
+                Expr::col(event_options::Column::SharesIssued).add(position.quantity),
+            )
+            .filter(event_options::Column::Id.eq(position.option_id))
+            .exec(txn)
+            .await
+            .map_err(|e| format!("Failed to update shares issued: {}", e))?;
+
+        opened.push(json!({
+            "event_id": position.event_id,
+            "option_id": position.option_id,
+            "quantity": position.quantity,
+            "average_price": position.average_price
+        }));
+    }
+
+    audit::record(
+        txn,
+        AuditEntry {
+            actor_id: admin_id,
+            action: AuditAction::UserImport,
+            target_id: user_id,
+            before: None,
+            after: Some(json!({
+                "import_id": import_id,
+                "created": created,
+                "balances": credited,
+                "positions": opened
+            })),
+            ip,
+        },
+    )
+    .await?;
+
+    Ok(user_id)
+}
+
+/// Import users, balances and positions migrated from another system. Every
+/// record is validated first; nothing is applied on a dry run or when any
+/// record fails, and otherwise everything is applied in one transaction.
+/// Returns the report and the ids of the users changed.
+pub async fn import(
+    db: &DatabaseConnection,
+    tenant_id: i32,
+    admin_id: i32,
+    ip: Option<String>,
+    import_id: &str,
+    dry_run: bool,
+    records: Vec<ImportRecord>,
+) -> Result<(ImportReport, Vec<i32>), String> {
+    let mut report = ImportReport {
+        import_id: import_id.to_string(),
+        dry_run,
+        rows: records.len(),
+        ..Default::default()
+    };
+
+    let planned = plan(db, tenant_id, records, &mut report.errors).await?;
+
+    let mut balances_credited: BTreeMap<String, Decimal> = BTreeMap::new();
+    for user in &planned {
+        if user.existing.is_some() {
+            report.users_matched += 1;
+        } else {
+            report.users_created += 1;
+        }
+        for balance in &user.balances {
+            *balances_credited
+                .entry(balance.currency.clone())
+                .or_default() += balance.amount;
+        }
+        report.positions_created += user.positions.len();
+        report.shares_issued += user
+            .positions
+            .iter()
+            .map(|position| i64::from(position.quantity))
+            .sum::<i64>();
+    }
+    report.balances_credited = balances_credited;
+
+    if dry_run || !report.errors.is_empty() {
+        return Ok((report, Vec::new()));
+    }
+
+    let txn = db
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let mut user_ids = Vec::with_capacity(planned.len());
+    for user in &planned {
+        user_ids.push(apply_user(&txn, tenant_id, admin_id, ip.clone(), import_id, user).await?);
+    }
+
+    report.applied = true;
+    legacy_imports::ActiveModel {
+        import_id: Set(import_id.to_string()),
+        tenant_id: Set(tenant_id),
+        report: Set(json!(report)),
+        created_by: Set(admin_id),
+        created_at: Set(Utc::now().fixed_offset()),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(|e| format!("Failed to record import: {}", e))?;
+
+    txn.commit()
+        .await
+        .map_err(|e| format!("Failed to commit import: {}", e))?;
+
+    for (user_id, user) in user_ids.iter().zip(&planned) {
+        for position in &user.positions {
+            position_cache::invalidate(*user_id, position.event_id, position.option_id);
+        }
+    }
+
+    Ok((report, user_ids))
+}
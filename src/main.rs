@@ -5,14 +5,28 @@ use deadpool_redis::{Config, Pool, Runtime};
 use dotenv::dotenv;
 use migration::sea_orm::{Database, DatabaseConnection};
 use migration::MigratorTrait;
+use utils::db_pool::{ReportingDb, Workload};
 
+mod candles;
+mod change_feed;
 mod constants;
+mod curation;
 mod handlers;
+mod imports;
+mod maintenance;
 mod middleware;
+mod notifications;
+mod oracle;
 mod order_book;
+mod payments;
+mod promotions;
 mod routes;
+mod settings;
+mod tenants;
+mod tiers;
 mod types;
 mod utils;
+mod wallets;
 mod websocket;
 
 // Import the migration module
@@ -27,17 +41,51 @@ async fn main() -> std::io::Result<()> {
     let database_url = constants::config::get_database_url()
         .expect("DATABASE_URL environment variable is required");
 
-    let db: DatabaseConnection = Database::connect(&database_url)
-        .await
-        .expect("Failed to connect to database");
+    // Tag the app's connections so the change triggers skip its own writes
+    let database_url = change_feed::listener::with_application_name(&database_url);
 
-    // Run database migrations
+    // Run database migrations on their own connection, free of the pools'
+    // statement timeouts
     println!("🔄 Running database migrations...");
-    Migrator::up(&db, None)
+    let migration_db = Database::connect(&database_url)
+        .await
+        .expect("Failed to connect to database");
+    Migrator::up(&migration_db, None)
         .await
         .expect("Failed to run database migrations");
+    let _ = migration_db.close().await;
     println!("✅ Database migrations completed successfully");
 
+    // Separate pools for trading and for exports and reports, so heavy
+    // reads can't starve order placement of connections
+    let mut db: DatabaseConnection = utils::db_pool::connect(&database_url, Workload::Trading)
+        .await
+        .expect("Failed to connect to database");
+    let reporting_database_url = constants::config::get_reporting_database_url()
+        .map(|url| change_feed::listener::with_application_name(&url))
+        .unwrap_or_else(|| database_url.clone());
+    let mut reporting_db = utils::db_pool::connect(&reporting_database_url, Workload::Reporting)
+        .await
+        .expect("Failed to connect to reporting database");
+
+    // Count queries per request to surface N+1 hotspots in /metrics
+    db.set_metric_callback(utils::query_metrics::record_query);
+    reporting_db.set_metric_callback(utils::query_metrics::record_query);
+    let reporting_db = ReportingDb::new(reporting_db);
+
+    // Load tenants before serving, since every request is resolved to one
+    tenants::registry::reload(&db)
+        .await
+        .expect("Failed to load tenants");
+
+    // Fees and limits read the settings, so they must be loaded too
+    settings::registry::reload(&db)
+        .await
+        .expect("Failed to load settings");
+
+    // Order books are only loaded for options that exist
+    order_book::matching_service::init(db.clone());
+
     // Redis connection pool
     let redis_url = constants::config::get_redis_url();
     let redis_config = Config::from_url(&redis_url);
@@ -45,12 +93,23 @@ async fn main() -> std::io::Result<()> {
         .create_pool(Some(Runtime::Tokio1))
         .expect("Failed to create Redis pool");
 
+    // Load the maintenance window before serving; the refresh task retries
+    // if Redis isn't reachable yet
+    if let Err(e) = maintenance::registry::reload(&redis_pool).await {
+        log::warn!("{}", e);
+    }
+
     // Start WebSocket server
     let ws_server = websocket::server::WebSocketServer::with_handlers(
         web::Data::new(db.clone()),
         web::Data::new(redis_pool.clone()),
     )
     .start();
+    websocket::ops::init(ws_server.clone());
+    websocket::market_data::init(ws_server.clone());
+    websocket::order_updates::init(ws_server.clone());
+    websocket::ticker::init(ws_server.clone(), db.clone(), redis_pool.clone());
+    websocket::firehose::init(redis_pool.clone(), redis_url.clone());
 
     // Start the price updater background task
     order_book::price_updater::start_price_updater(
@@ -59,6 +118,86 @@ async fn main() -> std::io::Result<()> {
         web::Data::new(ws_server.clone()),
     );
 
+    // Start the auction scheduler background task
+    order_book::auction::start_auction_scheduler(
+        web::Data::new(db.clone()),
+        web::Data::new(redis_pool.clone()),
+        web::Data::new(ws_server.clone()),
+    );
+
+    // Start the margin liquidation monitor background task
+    order_book::liquidation::start_liquidation_monitor(
+        web::Data::new(db.clone()),
+        web::Data::new(redis_pool.clone()),
+        web::Data::new(ws_server.clone()),
+    );
+
+    // Start the event lifecycle scheduler background task
+    order_book::event_lifecycle::start_event_lifecycle_scheduler(
+        web::Data::new(db.clone()),
+        web::Data::new(redis_pool.clone()),
+        web::Data::new(ws_server.clone()),
+    );
+
+    // Start the oracle scheduler background task
+    oracle::scheduler::start_oracle_scheduler(
+        web::Data::new(db.clone()),
+        web::Data::new(redis_pool.clone()),
+        web::Data::new(ws_server.clone()),
+    );
+
+    // Start the trade settlement retry background task
+    order_book::settlement::start_settlement_retry_job(web::Data::new(db.clone()));
+
+    // Start the order book sampler background task
+    order_book::book_sampler::start_book_sampler(web::Data::new(redis_pool.clone()));
+
+    // Resume re-quoting the markets the market maker seeded
+    order_book::market_maker::start_market_makers(
+        web::Data::new(db.clone()),
+        web::Data::new(redis_pool.clone()),
+    );
+
+    // Start the market maker report background task
+    order_book::market_maker_report::start_market_maker_report_job(
+        web::Data::new(db.clone()),
+        web::Data::new(redis_pool.clone()),
+    );
+
+    // Start the email digest background task
+    notifications::digest::start_digest_job(
+        web::Data::new(db.clone()),
+        web::Data::new(redis_pool.clone()),
+    );
+
+    // Start the featured events scheduler background task
+    curation::featured_scheduler::start_featured_scheduler(
+        web::Data::new(db.clone()),
+        web::Data::new(ws_server.clone()),
+    );
+
+    // Start the nightly tier recalculation background task
+    tiers::recalculation::start_tier_recalculation_job(web::Data::new(db.clone()));
+
+    // Start the tenant registry refresh background task
+    tenants::registry::start_tenant_refresh(web::Data::new(db.clone()));
+
+    // Start the settings refresh background task
+    settings::registry::start_settings_refresh(web::Data::new(db.clone()));
+
+    // Start the maintenance refresh background task
+    maintenance::registry::start_maintenance_refresh(
+        web::Data::new(redis_pool.clone()),
+        web::Data::new(ws_server.clone()),
+    );
+
+    // Start the database change listener background task
+    change_feed::listener::start_change_listener(
+        web::Data::new(db.clone()),
+        web::Data::new(redis_pool.clone()),
+        web::Data::new(ws_server.clone()),
+    );
+
     let server_address = constants::config::get_server_address();
     println!("🚀 Starting Centralized Exchange API server...");
     println!("📊 Database connected successfully");
@@ -71,15 +210,36 @@ async fn main() -> std::io::Result<()> {
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(reporting_db.clone()))
             .app_data(web::Data::new(redis_pool.clone()))
             .app_data(web::Data::new(ws_server.clone()))
+            .wrap(middleware::maintenance::Maintenance)
+            .wrap(middleware::tenant::TenantResolver)
+            .wrap(middleware::query_metrics::QueryMetrics)
+            .wrap(middleware::payload_metrics::PayloadMetrics)
+            .wrap(middleware::correlation::CorrelationIds)
             .wrap(
+                // CORS_ORIGIN plus every tenant site's origins
                 Cors::default()
-                    .allowed_origin(&constants::config::get_cors_origin())
+                    .allowed_origin_fn(|origin, _| {
+                        let origin = origin.to_str().unwrap_or_default();
+                        origin == constants::config::get_cors_origin()
+                            || tenants::registry::allows_origin(origin)
+                    })
                     .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
-                    .allowed_headers(vec!["Content-Type", "Authorization"])
+                    .allowed_headers(vec![
+                        "Content-Type",
+                        "Authorization",
+                        "Idempotency-Key",
+                        "X-2FA-Code",
+                        "X-Tenant",
+                        "X-Correlation-Id",
+                    ])
+                    .expose_headers(vec!["X-Correlation-Id"])
                     .max_age(3600),
             )
+            // Outermost, so payload metrics see uncompressed sizes
+            .wrap(actix_web::middleware::Compress::default())
             .service(routes::api::configure_routes())
     })
     .bind(&server_address)?
@@ -0,0 +1,135 @@
+use crate::constants::config;
+use crate::types::maintenance::{MaintenanceWindow, DEFAULT_MESSAGE};
+use crate::types::websocket::{OpsEventKind, OpsSeverity, WebSocketMessage};
+use crate::utils::cache::CacheService;
+use crate::websocket::ops;
+use crate::websocket::server::{BroadcastToAll, WebSocketServer};
+use actix::Addr;
+use actix_web::web;
+use chrono::{DateTime, Utc};
+use deadpool_redis::Pool;
+use lazy_static::lazy_static;
+use serde_json::json;
+use std::sync::{OnceLock, RwLock};
+
+/// Redis key the scheduled window is shared between instances under
+const WINDOW_KEY: &str = "maintenance:window";
+
+lazy_static! {
+    /// The window scheduled in Redis, as last loaded
+    static ref WINDOW: RwLock<Option<MaintenanceWindow>> = RwLock::new(None);
+}
+
+/// When this instance started, the start of the maintenance `MAINTENANCE_MODE`
+/// turns on
+static STARTED_AT: OnceLock<DateTime<Utc>> = OnceLock::new();
+
+/// The window `MAINTENANCE_MODE` forces on, if it is set
+fn configured() -> Option<MaintenanceWindow> {
+    config::get_maintenance_mode().then(|| MaintenanceWindow {
+        message: DEFAULT_MESSAGE.to_string(),
+        starts_at: *STARTED_AT.get_or_init(Utc::now),
+        ends_at: config::get_maintenance_ends_at(),
+    })
+}
+
+/// Load the scheduled window from Redis, replacing what was held
+pub async fn reload(redis_pool: &Pool) -> Result<(), String> {
+    let window = CacheService::new(redis_pool.clone())
+        .get::<MaintenanceWindow>(WINDOW_KEY)
+        .await
+        .map_err(|e| format!("Failed to load maintenance window: {}", e))?;
+
+    *WINDOW.write().unwrap() = window;
+    Ok(())
+}
+
+/// The window announced to clients: the configured one, else the scheduled
+/// one, whether or not it has started
+pub fn current() -> Option<MaintenanceWindow> {
+    configured().or_else(|| WINDOW.read().unwrap().clone())
+}
+
+/// The window the API is read-only under right now
+pub fn active() -> Option<MaintenanceWindow> {
+    current().filter(|window| window.is_active_at(Utc::now()))
+}
+
+/// Schedule a window for every instance, replacing any already scheduled
+pub async fn schedule(redis_pool: &Pool, window: MaintenanceWindow) -> Result<(), String> {
+    CacheService::new(redis_pool.clone())
+        .set_persistent(WINDOW_KEY, &window)
+        .await
+        .map_err(|e| format!("Failed to store maintenance window: {}", e))?;
+
+    *WINDOW.write().unwrap() = Some(window);
+    Ok(())
+}
+
+/// Clear the scheduled window for every instance
+pub async fn clear(redis_pool: &Pool) -> Result<(), String> {
+    CacheService::new(redis_pool.clone())
+        .delete(WINDOW_KEY)
+        .await
+        .map_err(|e| format!("Failed to clear maintenance window: {}", e))?;
+
+    *WINDOW.write().unwrap() = None;
+    Ok(())
+}
+
+/// The notice WebSocket clients are sent about a window
+pub fn notice(window: Option<MaintenanceWindow>) -> WebSocketMessage {
+    let now = Utc::now();
+    WebSocketMessage::Maintenance {
+        active: window.as_ref().is_some_and(|w| w.is_active_at(now)),
+        window,
+        timestamp: now,
+    }
+}
+
+/// Start a background task that reloads the scheduled window periodically
+/// and tells every WebSocket client of this instance when it is scheduled,
+/// changed, starts or is cleared
+pub fn start_maintenance_refresh(
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+) {
+    log::info!("Starting maintenance refresh");
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            config::get_maintenance_refresh_interval_seconds().max(1),
+        ));
+        let mut announced: Option<(Option<MaintenanceWindow>, bool)> = None;
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = reload(redis_pool.get_ref()).await {
+                log::error!("Failed to refresh maintenance window: {}", e);
+                ops::publish(
+                    OpsEventKind::JobFailed,
+                    OpsSeverity::Warning,
+                    "Maintenance refresh could not load the maintenance window",
+                    json!({ "job": "maintenance_refresh", "error": e }),
+                );
+                continue;
+            }
+
+            let window = current();
+            let active = active().is_some();
+            let state = Some((window.clone(), active));
+            // Clients connecting later are sent the notice on connect, so
+            // the state at startup needs no broadcast
+            if announced.is_some() && announced != state {
+                log::info!(
+                    "Maintenance window changed (active: {}), notifying clients",
+                    active
+                );
+                ws_server.do_send(BroadcastToAll {
+                    message: notice(window),
+                });
+            }
+            announced = state;
+        }
+    });
+}
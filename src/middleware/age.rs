@@ -0,0 +1,100 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::utils::kyc;
+use actix_web::{
+    body::{self, BoxBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    web, Error, HttpMessage, HttpResponse,
+};
+use entity::users;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde_json::json;
+use std::rc::Rc;
+
+/// Requires the user to have given a date of birth that meets the minimum
+/// age of their tenant, and not to have been found under it. Must run after
+/// `AuthMiddleware`.
+pub struct RequireMinimumAge;
+
+impl<S, B> Transform<S, ServiceRequest> for RequireMinimumAge
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequireMinimumAgeService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireMinimumAgeService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequireMinimumAgeService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireMinimumAgeService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let user_id = req
+                .extensions()
+                .get::<AuthenticatedUser>()
+                .and_then(|user| user.id.parse::<i32>().ok());
+            let Some(user_id) = user_id else {
+                return Ok(req.into_response(HttpResponse::Unauthorized().json(json!({
+                    "success": false,
+                    "message": "Authentication required"
+                }))));
+            };
+            let Some(db) = req.app_data::<web::Data<DatabaseConnection>>().cloned() else {
+                return Err(actix_web::error::ErrorInternalServerError(
+                    "Database unavailable",
+                ));
+            };
+
+            let user = users::Entity::find_by_id(user_id)
+                .one(db.get_ref())
+                .await
+                .map_err(|e| {
+                    log::error!("Database error: {}", e);
+                    actix_web::error::ErrorInternalServerError("Database error occurred")
+                })?;
+            let Some(user) = user else {
+                return Ok(req.into_response(HttpResponse::Unauthorized().json(json!({
+                    "success": false,
+                    "message": "Authentication required"
+                }))));
+            };
+
+            if let Some(message) = kyc::age_refusal(&user) {
+                return Ok(req.into_response(HttpResponse::Forbidden().json(json!({
+                    "success": false,
+                    "message": message,
+                    "age_verification_required": true,
+                    "age_status": user.age_status
+                }))));
+            }
+
+            Ok(svc.call(req).await?.map_into_boxed_body())
+        })
+    }
+}
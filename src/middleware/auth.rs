@@ -1,22 +1,40 @@
-use crate::utils::jwt::verify_jwt_token;
+use crate::constants::config;
+use crate::middleware::rate_limit;
+use crate::types::api_key::ApiKeyScope;
+use crate::utils::{api_key, auth_session, jwt::validate_jwt_token};
 use actix_web::{
-    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    error::ErrorUnauthorized,
-    http::header,
-    web, Error, HttpMessage,
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    error::{ErrorForbidden, ErrorUnauthorized, InternalError},
+    http::{header, Method},
+    web, Error, HttpMessage, HttpResponse,
 };
-use entity::users;
+use deadpool_redis::{redis, Pool};
+use entity::{api_keys, tenants, users};
 use futures_util::future::{ready, LocalBoxFuture, Ready};
-use sea_orm::{DatabaseConnection, EntityTrait};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::rc::Rc;
 
+const API_KEY_HEADER: &str = "X-API-Key";
+const API_TIMESTAMP_HEADER: &str = "X-API-Timestamp";
+const API_SIGNATURE_HEADER: &str = "X-API-Signature";
+
+/// How stale `api_keys.last_used_at` may get before a request refreshes it
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthenticatedUser {
     pub id: String,
     pub role: String,
 }
 
+/// Login session a JWT-authenticated request belongs to
+#[derive(Debug, Clone)]
+pub struct AuthSession(pub String);
+
+/// Authenticates a request with either a `Bearer` JWT or, for bots, an API key
+/// and an HMAC signature of the request (see `authenticate_api_key`)
 pub struct AuthMiddleware;
 
 impl<S, B> Transform<S, ServiceRequest> for AuthMiddleware
@@ -54,10 +72,17 @@ where
 
     forward_ready!(service);
 
-    fn call(&self, req: ServiceRequest) -> Self::Future {
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
         let svc = self.service.clone();
 
         Box::pin(async move {
+            if req.headers().contains_key(API_KEY_HEADER) {
+                let auth_user = authenticate_api_key(&mut req).await?;
+                req.extensions_mut().insert(auth_user.id.clone());
+                req.extensions_mut().insert(auth_user);
+                return svc.call(req).await;
+            }
+
             let auth_header = req
                 .headers()
                 .get(header::AUTHORIZATION)
@@ -66,8 +91,12 @@ where
 
             match auth_header {
                 Some(token) => {
-                    match verify_jwt_token(token) {
-                        Ok(user_id) => {
+                    match validate_jwt_token(token) {
+                        Ok(claims) => {
+                            check_session(&req, &claims.sid).await?;
+                            let user_id = claims.sub;
+                            req.extensions_mut().insert(AuthSession(claims.sid));
+
                             // Get database connection from request data
                             let db = req.app_data::<web::Data<DatabaseConnection>>();
 
@@ -86,6 +115,11 @@ where
                                                 "User account is deactivated",
                                             ));
                                         }
+                                        if !belongs_to_tenant(&req, &user) {
+                                            return Err(ErrorUnauthorized(
+                                                "User does not belong to this site",
+                                            ));
+                                        }
 
                                         let auth_user = AuthenticatedUser {
                                             id: user_id.clone(),
@@ -116,3 +150,208 @@ where
         })
     }
 }
+
+/// Refuse access tokens whose session was revoked by logout or a password
+/// change. Fails closed if the session store can't be reached.
+async fn check_session(req: &ServiceRequest, session_id: &str) -> Result<(), Error> {
+    let Some(pool) = req.app_data::<web::Data<Pool>>() else {
+        return Ok(());
+    };
+    match auth_session::is_active(pool, session_id).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(ErrorUnauthorized("Session has been revoked")),
+        Err(e) => {
+            log::error!("Failed to check session {}: {}", session_id, e);
+            Err(ErrorUnauthorized("Unable to verify session"))
+        }
+    }
+}
+
+/// Whether a user may act on the tenant's site the request was made to.
+/// Admins run the whole deployment, so they may act on any.
+fn belongs_to_tenant(req: &ServiceRequest, user: &users::Model) -> bool {
+    match req.extensions().get::<tenants::Model>() {
+        Some(tenant) => user.tenant_id == tenant.id || user.role == "admin",
+        None => true,
+    }
+}
+
+/// The scope an API key needs for this request, or `None` if keys can't be
+/// used for it at all. Managing keys, deposits and other account changes
+/// need a JWT.
+fn required_scope(req: &ServiceRequest) -> Option<ApiKeyScope> {
+    let path = req.path();
+    if path.starts_with("/users/me/api-keys") || path.starts_with("/users/me/2fa") {
+        return None;
+    }
+    if path == "/wallet/withdraw" {
+        return Some(ApiKeyScope::Withdraw);
+    }
+    if req.method() == Method::GET || req.method() == Method::HEAD {
+        return Some(ApiKeyScope::Read);
+    }
+    if path.starts_with("/order-book/orders") {
+        return Some(ApiKeyScope::Trade);
+    }
+    None
+}
+
+/// Authenticate a request signed with an API key. It must carry the key id
+/// in `X-API-Key`, the current time in milliseconds in `X-API-Timestamp`, and
+/// in `X-API-Signature` the hex HMAC-SHA256, keyed by the secret, of the
+/// timestamp, method, path with query and body concatenated. Keys act with a
+/// user's permissions even if their owner is an admin.
+async fn authenticate_api_key(req: &mut ServiceRequest) -> Result<AuthenticatedUser, Error> {
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|h| h.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| ErrorUnauthorized(format!("Missing {} header", name)))
+    };
+    let key_id = header(API_KEY_HEADER)?;
+    let timestamp = header(API_TIMESTAMP_HEADER)?;
+    let signature = header(API_SIGNATURE_HEADER)?;
+
+    let window_ms = config::get_api_key_signature_window_seconds() * 1000;
+    let sent_at: i64 = timestamp
+        .parse()
+        .map_err(|_| ErrorUnauthorized("Invalid request timestamp"))?;
+    if (chrono::Utc::now().timestamp_millis() - sent_at).abs() > window_ms {
+        return Err(ErrorUnauthorized(
+            "Request timestamp is outside the allowed window",
+        ));
+    }
+
+    let db = req
+        .app_data::<web::Data<DatabaseConnection>>()
+        .cloned()
+        .ok_or_else(|| ErrorUnauthorized("API keys are unavailable"))?;
+    let key = api_keys::Entity::find()
+        .filter(api_keys::Column::KeyId.eq(&key_id))
+        .filter(api_keys::Column::RevokedAt.is_null())
+        .one(db.get_ref())
+        .await
+        .map_err(|_| ErrorUnauthorized("Database error"))?
+        .ok_or_else(|| ErrorUnauthorized("Invalid API key"))?;
+
+    let secret = api_key::derive_secret(&key.key_id).map_err(ErrorUnauthorized)?;
+    if api_key::hash_secret(&secret) != key.secret_hash {
+        log::warn!(
+            "API key {} no longer matches the signing secret",
+            key.key_id
+        );
+        return Err(ErrorUnauthorized("Invalid API key"));
+    }
+
+    // The body is read here to check the signature, then put back for the
+    // handler
+    let body = req.extract::<web::Bytes>().await?;
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| req.path().to_string());
+    let signed = api_key::verify_request(
+        &secret,
+        &timestamp,
+        req.method().as_str(),
+        &path,
+        &body,
+        &signature,
+    );
+    req.set_payload(Payload::from(body));
+    if !signed {
+        return Err(ErrorUnauthorized("Invalid signature"));
+    }
+
+    let scope = required_scope(req)
+        .ok_or_else(|| ErrorForbidden("API keys can't be used for this endpoint"))?;
+    if !api_key::has_scope(&key, scope) {
+        return Err(ErrorForbidden(format!(
+            "API key lacks the {} scope",
+            scope.as_str()
+        )));
+    }
+
+    let user = users::Entity::find_by_id(key.user_id)
+        .one(db.get_ref())
+        .await
+        .map_err(|_| ErrorUnauthorized("Database error"))?
+        .ok_or_else(|| ErrorUnauthorized("User not found"))?;
+    if !user.is_active {
+        return Err(ErrorUnauthorized("User account is deactivated"));
+    }
+    if !belongs_to_tenant(req, &user) {
+        return Err(ErrorUnauthorized("User does not belong to this site"));
+    }
+
+    if let Some(pool) = req.app_data::<web::Data<Pool>>() {
+        if !claim_signature(pool, &signature, window_ms * 2).await {
+            return Err(ErrorUnauthorized("Signature has already been used"));
+        }
+        if let Some(retry_after) =
+            rate_limit::take_api_key_token(pool, &key.key_id, key.rate_limit_per_minute.into())
+                .await
+        {
+            log::warn!("API key {} exceeded its rate limit", key.key_id);
+            let response = HttpResponse::TooManyRequests()
+                .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                .json(json!({
+                    "success": false,
+                    "message": "API key rate limit exceeded, try again later"
+                }));
+            return Err(
+                InternalError::from_response("API key rate limit exceeded", response).into(),
+            );
+        }
+    }
+
+    let stale = key.last_used_at.is_none_or(|t| {
+        chrono::Utc::now().signed_duration_since(t).num_seconds() >= LAST_USED_RESOLUTION_SECONDS
+    });
+    if stale {
+        let key_id = key.key_id.clone();
+        let mut active: api_keys::ActiveModel = key.into();
+        active.last_used_at = Set(Some(chrono::Utc::now().into()));
+        tokio::spawn(async move {
+            if let Err(e) = active.update(db.get_ref()).await {
+                log::error!("Failed to record use of API key {}: {}", key_id, e);
+            }
+        });
+    }
+
+    Ok(AuthenticatedUser {
+        id: user.id.to_string(),
+        role: "user".to_string(),
+    })
+}
+
+/// Remember a signature until its timestamp can no longer be accepted, so a
+/// captured request can't be replayed. Returns false if it was already used;
+/// if Redis is unavailable the request is let through.
+async fn claim_signature(pool: &Pool, signature: &str, ttl_ms: i64) -> bool {
+    let key = format!("api_key:signature:{}", signature.to_ascii_lowercase());
+    let mut conn = match pool.get().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Failed to check API key signature replay: {}", e);
+            return true;
+        }
+    };
+    let claimed: redis::RedisResult<Option<String>> = redis::cmd("SET")
+        .arg(&key)
+        .arg(1)
+        .arg("NX")
+        .arg("PX")
+        .arg(ttl_ms)
+        .query_async(&mut conn)
+        .await;
+    match claimed {
+        Ok(claimed) => claimed.is_some(),
+        Err(e) => {
+            log::error!("Failed to check API key signature replay: {}", e);
+            true
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::utils::consent;
+use actix_web::{
+    body::{self, BoxBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    web, Error, HttpMessage, HttpResponse,
+};
+use entity::tenants;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use sea_orm::DatabaseConnection;
+use serde_json::json;
+use std::rc::Rc;
+
+/// Requires the user to have accepted the current version of the terms of
+/// service and privacy policy of the tenant the request is for. Must run
+/// after `AuthMiddleware`.
+pub struct RequireConsent;
+
+impl<S, B> Transform<S, ServiceRequest> for RequireConsent
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequireConsentService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireConsentService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequireConsentService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireConsentService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let user_id = req
+                .extensions()
+                .get::<AuthenticatedUser>()
+                .and_then(|user| user.id.parse::<i32>().ok());
+            let Some(user_id) = user_id else {
+                return Ok(req.into_response(HttpResponse::Unauthorized().json(json!({
+                    "success": false,
+                    "message": "Authentication required"
+                }))));
+            };
+            let Some(db) = req.app_data::<web::Data<DatabaseConnection>>().cloned() else {
+                return Err(actix_web::error::ErrorInternalServerError(
+                    "Database unavailable",
+                ));
+            };
+            let tenant_id = req.extensions().get::<tenants::Model>().map(|t| t.id);
+
+            let outstanding = consent::outstanding(db.get_ref(), user_id, tenant_id)
+                .await
+                .map_err(|e| {
+                    log::error!("Database error: {}", e);
+                    actix_web::error::ErrorInternalServerError("Database error occurred")
+                })?;
+            if !outstanding.is_empty() {
+                return Ok(req.into_response(HttpResponse::Forbidden().json(json!({
+                    "success": false,
+                    "message": "Accept the current terms of service and privacy policy to continue",
+                    "consent_required": true,
+                    "outstanding": outstanding
+                }))));
+            }
+
+            Ok(svc.call(req).await?.map_into_boxed_body())
+        })
+    }
+}
@@ -0,0 +1,91 @@
+use actix_web::{
+    body,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderName, HeaderValue},
+    Error, HttpMessage,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+
+/// Header carrying a request's correlation id, both ways
+pub const CORRELATION_HEADER: &str = "X-Correlation-Id";
+
+/// Longest correlation id accepted from a client
+const MAX_CORRELATION_ID_LENGTH: usize = 64;
+
+/// Id tying a request to the orders it places and everything they cause
+#[derive(Debug, Clone)]
+pub struct CorrelationId(pub String);
+
+/// Gives every request a correlation id and stores it in the request
+/// extensions: the client's `X-Correlation-Id` when it is up to 64 letters,
+/// digits, `-`, `_`, `.` or `:`, else a new UUID. The id is echoed in the
+/// response's `X-Correlation-Id` header.
+pub struct CorrelationIds;
+
+impl<S, B> Transform<S, ServiceRequest> for CorrelationIds
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CorrelationIdsService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CorrelationIdsService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct CorrelationIdsService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for CorrelationIdsService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let correlation_id = req
+                .headers()
+                .get(CORRELATION_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .filter(|id| is_valid(id))
+                .map(str::to_string)
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+            req.extensions_mut()
+                .insert(CorrelationId(correlation_id.clone()));
+
+            let mut res = svc.call(req).await?;
+            if let Ok(value) = HeaderValue::from_str(&correlation_id) {
+                res.headers_mut()
+                    .insert(HeaderName::from_static("x-correlation-id"), value);
+            }
+            Ok(res)
+        })
+    }
+}
+
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_CORRELATION_ID_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
@@ -0,0 +1,113 @@
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header, Method, StatusCode},
+    Error,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+/// Tags successful GET responses with a weak `ETag` hashed from the body and
+/// answers a matching `If-None-Match` with an empty `304 Not Modified`, so
+/// polling clients and proxies skip unchanged payloads
+pub struct ETag;
+
+impl<S, B> Transform<S, ServiceRequest> for ETag
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ETagService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ETagService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct ETagService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for ETagService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let if_none_match = if_none_match(&req);
+            let res = svc.call(req).await?;
+            apply(res, if_none_match).await
+        })
+    }
+}
+
+/// The `If-None-Match` header of a GET request; other methods are never
+/// answered with a 304
+pub(crate) fn if_none_match(req: &ServiceRequest) -> Option<String> {
+    if req.method() != Method::GET {
+        return None;
+    }
+    req.headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Add an `ETag` to a 200 response, replacing it with a 304 when it matches
+/// `if_none_match`. Other responses pass through untouched.
+pub(crate) async fn apply<B: MessageBody + 'static>(
+    res: ServiceResponse<B>,
+    if_none_match: Option<String>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    if res.status() != StatusCode::OK || res.request().method() != Method::GET {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (request, response) = res.into_parts();
+    let (mut head, body) = response.into_parts();
+    let bytes = body::to_bytes(body)
+        .await
+        .map_err(|_| actix_web::error::ErrorInternalServerError("Failed to read response"))?;
+
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    let tag = format!("\"{:016x}\"", hasher.finish());
+    head.headers_mut().insert(
+        header::ETAG,
+        header::HeaderValue::from_str(&format!("W/{}", tag)).expect("etag is a valid header"),
+    );
+
+    // Weak comparison, as RFC 9110 requires for If-None-Match
+    let not_modified = if_none_match.is_some_and(|tags| {
+        tags.split(',')
+            .map(|t| t.trim())
+            .any(|t| t == "*" || t.trim_start_matches("W/") == tag)
+    });
+    let response = if not_modified {
+        *head.status_mut() = StatusCode::NOT_MODIFIED;
+        head.set_body(BoxBody::new(()))
+    } else {
+        head.set_body(BoxBody::new(bytes))
+    };
+
+    Ok(ServiceResponse::new(request, response))
+}
@@ -0,0 +1,342 @@
+use crate::constants::config;
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header, StatusCode},
+    web, Error, HttpMessage, HttpResponse,
+};
+use deadpool_redis::{
+    redis::{self, AsyncCommands},
+    Pool,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::rc::Rc;
+
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+const REPLAYED_HEADER: &str = "Idempotent-Replayed";
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Makes a POST safe to retry: a request carrying an `Idempotency-Key` header
+/// runs once per user and key, and repeating it within
+/// `IDEMPOTENCY_KEY_TTL_SECONDS` replays the stored response instead of
+/// running it again. Must be wrapped inside `AuthMiddleware`, since keys are
+/// scoped to the authenticated user.
+pub struct Idempotency;
+
+impl<S, B> Transform<S, ServiceRequest> for Idempotency
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = IdempotencyService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IdempotencyService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+/// Marks a response to a request that persisted nothing, so its idempotency
+/// key is freed for a retry instead of replaying the response
+#[derive(Clone, Copy)]
+pub struct NothingPersisted;
+
+/// The response to `error` for a request that persisted nothing
+pub fn nothing_persisted(error: Error) -> HttpResponse {
+    let mut response = error.error_response();
+    response.extensions_mut().insert(NothingPersisted);
+    response
+}
+
+pub struct IdempotencyService<S> {
+    service: Rc<S>,
+}
+
+/// What is stored under an idempotency key: the fingerprint of the request
+/// that claimed it and, once it has finished, the response to replay
+#[derive(Serialize, Deserialize)]
+struct StoredRequest {
+    request_hash: String,
+    response: Option<StoredResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredResponse {
+    status: u16,
+    content_type: Option<String>,
+    body: String,
+}
+
+impl<S, B> Service<ServiceRequest> for IdempotencyService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+                Some(value) => value.to_str().ok().map(str::to_string),
+                None => return Ok(svc.call(req).await?.map_into_boxed_body()),
+            };
+            let key = match key.filter(|key| valid_key(key)) {
+                Some(key) => key,
+                None => {
+                    let response = HttpResponse::BadRequest().json(json!({
+                        "success": false,
+                        "message": format!(
+                            "{} must be 1 to {} printable ASCII characters",
+                            IDEMPOTENCY_KEY_HEADER, MAX_KEY_LENGTH
+                        )
+                    }));
+                    return Ok(req.into_response(response));
+                }
+            };
+
+            let pool = match req.app_data::<web::Data<Pool>>() {
+                Some(pool) => pool.get_ref().clone(),
+                None => return Ok(svc.call(req).await?.map_into_boxed_body()),
+            };
+            let user_id = req
+                .extensions()
+                .get::<String>()
+                .cloned()
+                .unwrap_or_default();
+
+            // The body is read here to fingerprint the request, then put back
+            // for the handler
+            let bytes = req.extract::<web::Bytes>().await?;
+            let request_hash = request_hash(&req, &bytes);
+            req.set_payload(Payload::from(bytes));
+
+            let redis_key = format!(
+                "idempotency:{}:{}:{}:{}",
+                user_id,
+                req.method(),
+                req.path(),
+                key
+            );
+            let ttl = config::get_idempotency_key_ttl_seconds();
+
+            let claimed = match claim(&pool, &redis_key, &request_hash, ttl).await {
+                Ok(claimed) => claimed,
+                Err(e) => {
+                    log::error!("Idempotency store unavailable: {}", e);
+                    return Ok(svc.call(req).await?.map_into_boxed_body());
+                }
+            };
+
+            if !claimed {
+                let stored = match load(&pool, &redis_key).await {
+                    Ok(stored) => stored,
+                    Err(e) => {
+                        log::error!("Failed to load idempotency key {}: {}", redis_key, e);
+                        None
+                    }
+                };
+                let response = match stored {
+                    Some(stored) if stored.request_hash != request_hash => {
+                        HttpResponse::UnprocessableEntity().json(json!({
+                            "success": false,
+                            "message": format!(
+                                "{} was already used for a different request",
+                                IDEMPOTENCY_KEY_HEADER
+                            )
+                        }))
+                    }
+                    Some(StoredRequest {
+                        response: Some(stored),
+                        ..
+                    }) => replay(stored),
+                    _ => HttpResponse::Conflict().json(json!({
+                        "success": false,
+                        "message": "A request with this idempotency key is still in progress"
+                    })),
+                };
+                return Ok(req.into_response(response));
+            }
+
+            // Server errors are stored like any other response, since the
+            // request may have placed an order or moved funds before failing.
+            // Only a handler that reports it persisted nothing frees the key.
+            let res = match svc.call(req).await {
+                Ok(res) => res,
+                Err(e) => {
+                    let response = e.error_response();
+                    let content_type = content_type(response.headers());
+                    if let Ok(bytes) = body::to_bytes(response.into_body()).await {
+                        let stored = StoredResponse {
+                            status: e.as_response_error().status_code().as_u16(),
+                            content_type,
+                            body: String::from_utf8_lossy(&bytes).into_owned(),
+                        };
+                        store(&pool, &redis_key, request_hash, stored, ttl).await;
+                    }
+                    return Err(e);
+                }
+            };
+
+            if res
+                .response()
+                .extensions()
+                .get::<NothingPersisted>()
+                .is_some()
+            {
+                release(&pool, &redis_key).await;
+                return Ok(res.map_into_boxed_body());
+            }
+
+            let (request, response) = res.into_parts();
+            let (head, body) = response.into_parts();
+            let bytes = match body::to_bytes(body).await {
+                Ok(bytes) => bytes,
+                Err(_) => {
+                    let stored = StoredResponse {
+                        status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                        content_type: None,
+                        body: "Failed to read response".to_string(),
+                    };
+                    store(&pool, &redis_key, request_hash, stored, ttl).await;
+                    return Err(actix_web::error::ErrorInternalServerError(
+                        "Failed to read response",
+                    ));
+                }
+            };
+
+            let stored = StoredResponse {
+                status: head.status().as_u16(),
+                content_type: content_type(head.headers()),
+                body: String::from_utf8_lossy(&bytes).into_owned(),
+            };
+            store(&pool, &redis_key, request_hash, stored, ttl).await;
+
+            let response = head.set_body(BoxBody::new(bytes));
+            Ok(ServiceResponse::new(request, response))
+        })
+    }
+}
+
+fn valid_key(key: &str) -> bool {
+    !key.is_empty() && key.len() <= MAX_KEY_LENGTH && key.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// Fingerprint of the request's method, path, query and body, so a key
+/// reused for a different request can be told apart from a retry. SHA-256
+/// gives the same fingerprint across builds, so stored keys still match
+/// after an upgrade.
+fn request_hash(req: &ServiceRequest, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        req.method().as_str().as_bytes(),
+        req.path().as_bytes(),
+        req.query_string().as_bytes(),
+    ] {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+fn content_type(headers: &header::HeaderMap) -> Option<String> {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_string)
+}
+
+fn replay(stored: StoredResponse) -> HttpResponse {
+    let status = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    let mut response = HttpResponse::build(status);
+    response.insert_header((REPLAYED_HEADER, "true"));
+    if let Some(content_type) = stored.content_type {
+        response.insert_header((header::CONTENT_TYPE, content_type));
+    }
+    response.body(stored.body)
+}
+
+/// Claim `key` for a request in progress. Returns `false` if another request
+/// already holds it.
+async fn claim(
+    pool: &Pool,
+    key: &str,
+    request_hash: &str,
+    ttl: u64,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let pending = serde_json::to_string(&StoredRequest {
+        request_hash: request_hash.to_string(),
+        response: None,
+    })?;
+    let mut conn = pool.get().await?;
+    let set: Option<String> = redis::cmd("SET")
+        .arg(key)
+        .arg(pending)
+        .arg("NX")
+        .arg("EX")
+        .arg(ttl)
+        .query_async(&mut conn)
+        .await?;
+    Ok(set.is_some())
+}
+
+async fn load(
+    pool: &Pool,
+    key: &str,
+) -> Result<Option<StoredRequest>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut conn = pool.get().await?;
+    let value: Option<String> = conn.get(key).await?;
+    Ok(match value {
+        Some(value) => Some(serde_json::from_str(&value)?),
+        None => None,
+    })
+}
+
+async fn save(
+    pool: &Pool,
+    key: &str,
+    stored: &StoredRequest,
+    ttl: u64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut conn = pool.get().await?;
+    conn.set_ex::<_, _, ()>(key, serde_json::to_string(stored)?, ttl)
+        .await?;
+    Ok(())
+}
+
+/// Store the response to replay under `key`, in place of the claim
+async fn store(pool: &Pool, key: &str, request_hash: String, response: StoredResponse, ttl: u64) {
+    let stored = StoredRequest {
+        request_hash,
+        response: Some(response),
+    };
+    if let Err(e) = save(pool, key, &stored, ttl).await {
+        log::error!("Failed to store idempotent response {}: {}", key, e);
+    }
+}
+
+async fn release(pool: &Pool, key: &str) {
+    let result = match pool.get().await {
+        Ok(mut conn) => conn.del::<_, ()>(key).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        log::error!("Failed to release idempotency key {}: {}", key, e);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::utils::internal_token::verify_internal_token;
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::ErrorUnauthorized,
+    Error, HttpMessage,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+
+const INTERNAL_TOKEN_HEADER: &str = "X-Internal-Token";
+
+/// The service that called an internal endpoint
+#[derive(Debug, Clone)]
+pub struct InternalService(pub String);
+
+/// Restricts a scope to other services of the platform. Requests must carry
+/// an `X-Internal-Token` signed with `INTERNAL_SERVICE_SECRET`; user tokens
+/// are refused, whatever the user's role.
+pub struct InternalAuth;
+
+impl<S, B> Transform<S, ServiceRequest> for InternalAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = InternalAuthService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(InternalAuthService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct InternalAuthService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for InternalAuthService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let token = req
+                .headers()
+                .get(INTERNAL_TOKEN_HEADER)
+                .and_then(|h| h.to_str().ok())
+                .ok_or_else(|| ErrorUnauthorized("Missing internal token"))?;
+
+            let service = verify_internal_token(token).map_err(|e| {
+                log::warn!("Rejected internal request to {}: {}", req.path(), e);
+                ErrorUnauthorized(e)
+            })?;
+
+            req.extensions_mut().insert(InternalService(service));
+            svc.call(req).await
+        })
+    }
+}
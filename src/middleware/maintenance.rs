@@ -0,0 +1,91 @@
+use crate::maintenance::registry;
+use actix_web::{
+    body::{self, BoxBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header, Method},
+    Error, HttpResponse,
+};
+use chrono::Utc;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use serde_json::json;
+use std::rc::Rc;
+
+/// Paths that keep accepting writes during maintenance: staff running it,
+/// internal services, payment gateway callbacks, and signing in and out
+const EXEMPT_PREFIXES: [&str; 6] = [
+    "/admin",
+    "/internal",
+    "/payments/webhook",
+    "/auth/login",
+    "/auth/refresh",
+    "/auth/logout",
+];
+
+/// Makes the API read-only while a maintenance window is in force: reads go
+/// through, every other request gets `503` with the window and a
+/// `Retry-After` when its end is known
+pub struct Maintenance;
+
+impl<S, B> Transform<S, ServiceRequest> for Maintenance
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = MaintenanceService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MaintenanceService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct MaintenanceService<S> {
+    service: Rc<S>,
+}
+
+fn is_read(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+impl<S, B> Service<ServiceRequest> for MaintenanceService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let exempt = is_read(req.method())
+                || EXEMPT_PREFIXES
+                    .iter()
+                    .any(|prefix| req.path().starts_with(prefix));
+            if let Some(window) = registry::active().filter(|_| !exempt) {
+                let mut response = HttpResponse::ServiceUnavailable();
+                if let Some(retry_after) = window.retry_after(Utc::now()) {
+                    response.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+                }
+                return Ok(req.into_response(response.json(json!({
+                    "success": false,
+                    "message": window.message,
+                    "maintenance": window
+                }))));
+            }
+
+            Ok(svc.call(req).await?.map_into_boxed_body())
+        })
+    }
+}
@@ -1 +1,15 @@
+pub mod age;
 pub mod auth;
+pub mod consent;
+pub mod correlation;
+pub mod etag;
+pub mod idempotency;
+pub mod internal_auth;
+pub mod maintenance;
+pub mod payload_metrics;
+pub mod permission;
+pub mod public_api;
+pub mod query_metrics;
+pub mod rate_limit;
+pub mod tenant;
+pub mod two_factor;
@@ -0,0 +1,91 @@
+use crate::types::websocket::{OpsEventKind, OpsSeverity};
+use crate::utils::payload_metrics::record_response;
+use crate::websocket::ops;
+use actix_web::{
+    body::{BodySize, MessageBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use serde_json::json;
+use std::rc::Rc;
+
+/// Records the uncompressed size of each response per route, and alerts the
+/// ops channel when a route goes over its payload budget. Streamed responses
+/// have no known size and aren't counted.
+pub struct PayloadMetrics;
+
+impl<S, B> Transform<S, ServiceRequest> for PayloadMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = PayloadMetricsService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(PayloadMetricsService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct PayloadMetricsService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for PayloadMetricsService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let res = svc.call(req).await?;
+
+            let BodySize::Sized(bytes) = res.response().body().size() else {
+                return Ok(res);
+            };
+
+            // Aggregate by route pattern so path parameters don't split the stats
+            let request = res.request();
+            let route = format!(
+                "{} {}",
+                request.method(),
+                request
+                    .match_pattern()
+                    .unwrap_or_else(|| request.path().to_string())
+            );
+
+            if let Some(budget) = record_response(&route, bytes) {
+                log::warn!(
+                    "Response for {} was {} bytes, over its {} byte budget",
+                    route,
+                    bytes,
+                    budget
+                );
+                ops::publish(
+                    OpsEventKind::PayloadOverBudget,
+                    OpsSeverity::Warning,
+                    format!("{} responses are over their payload budget", route),
+                    json!({ "route": route, "bytes": bytes, "budget_bytes": budget }),
+                );
+            }
+
+            Ok(res)
+        })
+    }
+}
@@ -0,0 +1,98 @@
+use crate::middleware::auth::{AuthSession, AuthenticatedUser};
+use crate::types::rbac::Permission;
+use crate::utils::permissions;
+use actix_web::{
+    body::{self, BoxBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    web, Error, HttpMessage, HttpResponse,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use sea_orm::DatabaseConnection;
+use serde_json::json;
+use std::rc::Rc;
+
+/// Lets a request through only if its user is an admin or holds a role
+/// granting the permission. Must run after `AuthMiddleware`.
+pub struct RequirePermission(pub Permission);
+
+impl<S, B> Transform<S, ServiceRequest> for RequirePermission
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequirePermissionService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequirePermissionService {
+            service: Rc::new(service),
+            permission: self.0,
+        }))
+    }
+}
+
+pub struct RequirePermissionService<S> {
+    service: Rc<S>,
+    permission: Permission,
+}
+
+impl<S, B> Service<ServiceRequest> for RequirePermissionService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let permission = self.permission;
+
+        Box::pin(async move {
+            let auth_user = req.extensions().get::<AuthenticatedUser>().cloned();
+            let via_session = req.extensions().get::<AuthSession>().is_some();
+            let Some(auth_user) = auth_user else {
+                return Ok(req.into_response(HttpResponse::Unauthorized().json(json!({
+                    "success": false,
+                    "message": "Authentication required"
+                }))));
+            };
+            let Some(db) = req.app_data::<web::Data<DatabaseConnection>>().cloned() else {
+                return Err(actix_web::error::ErrorInternalServerError(
+                    "Database unavailable",
+                ));
+            };
+
+            let allowed =
+                permissions::has_permission(db.get_ref(), &auth_user, via_session, permission)
+                    .await
+                    .map_err(|e| {
+                        log::error!("Database error: {}", e);
+                        actix_web::error::ErrorInternalServerError("Database error occurred")
+                    })?;
+
+            if !allowed {
+                log::warn!(
+                    "User {} lacks the {} permission for {}",
+                    auth_user.id,
+                    permission.as_str(),
+                    req.path()
+                );
+                return Ok(req.into_response(HttpResponse::Forbidden().json(json!({
+                    "success": false,
+                    "message": format!("This action needs the {} permission", permission.as_str())
+                }))));
+            }
+
+            Ok(svc.call(req).await?.map_into_boxed_body())
+        })
+    }
+}
@@ -0,0 +1,144 @@
+use crate::constants::config;
+use crate::middleware::etag;
+use actix_web::{
+    body::{self, BoxBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header, StatusCode},
+    web, Error, HttpResponse,
+};
+use chrono::Utc;
+use deadpool_redis::{redis::AsyncCommands, Pool};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use serde_json::json;
+use std::rc::Rc;
+
+/// Guards the public read-only API: limits each client IP to
+/// `PUBLIC_API_RATE_LIMIT_PER_MINUTE` requests, and makes successful responses
+/// cacheable with `Cache-Control` and an `ETag` that `If-None-Match` can match
+pub struct PublicApi;
+
+impl<S, B> Transform<S, ServiceRequest> for PublicApi
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = PublicApiService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(PublicApiService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct PublicApiService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for PublicApiService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let limit = config::get_public_api_rate_limit_per_minute();
+            let client = req
+                .connection_info()
+                .realip_remote_addr()
+                .unwrap_or("unknown")
+                .to_string();
+
+            let used = match req.app_data::<web::Data<Pool>>() {
+                Some(pool) => count_request(pool, &client).await,
+                None => None,
+            };
+            if let Some(used) = used.filter(|used| *used > limit) {
+                log::warn!("Public API rate limit exceeded by {} ({})", client, used);
+                let response = HttpResponse::TooManyRequests()
+                    .insert_header((header::RETRY_AFTER, seconds_to_next_window().to_string()))
+                    .insert_header(("X-RateLimit-Limit", limit.to_string()))
+                    .insert_header(("X-RateLimit-Remaining", "0"))
+                    .json(json!({
+                        "success": false,
+                        "message": "Rate limit exceeded, try again later"
+                    }));
+                return Ok(req.into_response(response));
+            }
+
+            let if_none_match = etag::if_none_match(&req);
+            let mut res = svc.call(req).await?;
+
+            let remaining = used.map_or(limit, |used| (limit - used).max(0));
+            let headers = res.headers_mut();
+            headers.insert(
+                header::HeaderName::from_static("x-ratelimit-limit"),
+                header::HeaderValue::from(limit),
+            );
+            headers.insert(
+                header::HeaderName::from_static("x-ratelimit-remaining"),
+                header::HeaderValue::from(remaining),
+            );
+
+            let mut res = etag::apply(res, if_none_match).await?;
+            if matches!(res.status(), StatusCode::OK | StatusCode::NOT_MODIFIED) {
+                res.headers_mut().insert(
+                    header::CACHE_CONTROL,
+                    header::HeaderValue::from_str(&format!(
+                        "public, max-age={}",
+                        config::get_public_api_cache_max_age_seconds()
+                    ))
+                    .expect("cache control is a valid header"),
+                );
+            }
+
+            Ok(res)
+        })
+    }
+}
+
+/// Count a request against the client's current one-minute window. Returns
+/// `None` if Redis is unavailable, in which case the request is let through.
+async fn count_request(pool: &Pool, client: &str) -> Option<i64> {
+    let window = Utc::now().timestamp() / 60;
+    let key = format!("ratelimit:public:{}:{}", client, window);
+
+    let mut conn = match pool.get().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Public API rate limiter unavailable: {}", e);
+            return None;
+        }
+    };
+    let used: i64 = match conn.incr(&key, 1).await {
+        Ok(used) => used,
+        Err(e) => {
+            log::error!("Public API rate limiter unavailable: {}", e);
+            return None;
+        }
+    };
+    if used == 1 {
+        if let Err(e) = conn.expire::<_, ()>(&key, 60).await {
+            log::error!("Failed to expire rate limit window {}: {}", key, e);
+        }
+    }
+    Some(used)
+}
+
+fn seconds_to_next_window() -> i64 {
+    60 - Utc::now().timestamp().rem_euclid(60)
+}
@@ -0,0 +1,91 @@
+use crate::constants::config;
+use crate::utils::query_metrics::{record_request, RequestQueryStats, REQUEST_QUERY_STATS};
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error, HttpMessage,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Counts database queries issued while serving each request and logs requests
+/// that exceed the query budget or database time threshold
+pub struct QueryMetrics;
+
+impl<S, B> Transform<S, ServiceRequest> for QueryMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = QueryMetricsService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(QueryMetricsService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct QueryMetricsService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for QueryMetricsService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let stats = Arc::new(RequestQueryStats::default());
+
+        Box::pin(async move {
+            let res = REQUEST_QUERY_STATS
+                .scope(stats.clone(), svc.call(req))
+                .await?;
+
+            // Aggregate by route pattern so path parameters don't split the stats
+            let request = res.request();
+            let route = format!(
+                "{} {}",
+                request.method(),
+                request
+                    .match_pattern()
+                    .unwrap_or_else(|| request.path().to_string())
+            );
+
+            let over_budget = record_request(&route, &stats);
+            let db_time = stats.db_time();
+            let slow = db_time.as_millis() as u64 >= config::get_slow_request_db_time_ms();
+
+            if over_budget || slow {
+                let user_id = request
+                    .extensions()
+                    .get::<String>()
+                    .cloned()
+                    .unwrap_or_else(|| "anonymous".to_string());
+                log::warn!(
+                    "Request {} by user {} issued {} queries taking {} ms",
+                    route,
+                    user_id,
+                    stats.queries(),
+                    db_time.as_millis()
+                );
+            }
+
+            Ok(res)
+        })
+    }
+}
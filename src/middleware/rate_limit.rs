@@ -0,0 +1,234 @@
+use crate::constants::config;
+use crate::middleware::auth::AuthenticatedUser;
+use actix_web::{
+    body::{self, BoxBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header,
+    web, Error, HttpMessage, HttpResponse,
+};
+use chrono::Utc;
+use deadpool_redis::{
+    redis::{self, Script},
+    Pool,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use lazy_static::lazy_static;
+use serde_json::json;
+use std::rc::Rc;
+
+lazy_static! {
+    /// Takes a token from the bucket in KEYS[1], refilling it first for the
+    /// time since it was last touched. ARGV is the capacity, the refill rate
+    /// in tokens per minute and the current time in milliseconds. Returns
+    /// whether the request is allowed, the whole tokens left and, when it
+    /// isn't, the milliseconds until a token is available.
+    static ref TAKE_TOKEN_SCRIPT: Script = Script::new(
+        r#"
+        local capacity = tonumber(ARGV[1])
+        local per_ms = tonumber(ARGV[2]) / 60000
+        local now = tonumber(ARGV[3])
+
+        local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+        local tokens = tonumber(bucket[1]) or capacity
+        local updated_at = tonumber(bucket[2]) or now
+        tokens = math.min(capacity, tokens + math.max(0, now - updated_at) * per_ms)
+
+        local allowed = 0
+        local retry_after = 0
+        if tokens >= 1 then
+            tokens = tokens - 1
+            allowed = 1
+        else
+            retry_after = math.ceil((1 - tokens) / per_ms)
+        end
+
+        redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now)
+        redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / per_ms))
+        return { allowed, math.floor(tokens), retry_after }
+        "#
+    );
+}
+
+/// Which budget a route draws from. Each has its own bucket per client.
+#[derive(Debug, Clone, Copy)]
+pub enum RateLimitBudget {
+    /// Placing, batching and amending orders
+    Orders,
+    /// Login and registration
+    Auth,
+    /// General market data reads
+    Reads,
+}
+
+impl RateLimitBudget {
+    fn as_str(self) -> &'static str {
+        match self {
+            RateLimitBudget::Orders => "orders",
+            RateLimitBudget::Auth => "auth",
+            RateLimitBudget::Reads => "reads",
+        }
+    }
+
+    /// Largest burst a client may send, and tokens refilled per minute
+    fn limits(self) -> (i64, i64) {
+        match self {
+            RateLimitBudget::Orders => (
+                config::get_order_rate_limit_burst(),
+                config::get_order_rate_limit_per_minute(),
+            ),
+            RateLimitBudget::Auth => (
+                config::get_auth_rate_limit_burst(),
+                config::get_auth_rate_limit_per_minute(),
+            ),
+            RateLimitBudget::Reads => (
+                config::get_read_rate_limit_burst(),
+                config::get_read_rate_limit_per_minute(),
+            ),
+        }
+    }
+}
+
+/// Token bucket rate limit over one budget, kept in Redis so every instance
+/// shares it. Requests are counted per user when `AuthMiddleware` has run
+/// first, and per client IP otherwise.
+pub struct RateLimit(pub RateLimitBudget);
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitService {
+            service: Rc::new(service),
+            budget: self.0,
+        }))
+    }
+}
+
+pub struct RateLimitService<S> {
+    service: Rc<S>,
+    budget: RateLimitBudget,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let budget = self.budget;
+
+        Box::pin(async move {
+            let (burst, per_minute) = budget.limits();
+            let user_id = req
+                .extensions()
+                .get::<AuthenticatedUser>()
+                .map(|user| user.id.clone());
+            let client = match user_id {
+                Some(user_id) => format!("user:{}", user_id),
+                None => format!(
+                    "ip:{}",
+                    req.connection_info()
+                        .realip_remote_addr()
+                        .unwrap_or("unknown")
+                ),
+            };
+
+            let decision = match req.app_data::<web::Data<Pool>>() {
+                Some(pool) => {
+                    let key = format!("ratelimit:{}:{}", budget.as_str(), client);
+                    take_token(pool, &key, burst, per_minute).await
+                }
+                None => None,
+            };
+
+            if let Some((false, _, retry_after_ms)) = decision {
+                log::warn!("{} rate limit exceeded by {}", budget.as_str(), client);
+                let retry_after = ((retry_after_ms + 999) / 1000).max(1);
+                let response = HttpResponse::TooManyRequests()
+                    .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                    .insert_header(("X-RateLimit-Limit", burst.to_string()))
+                    .insert_header(("X-RateLimit-Remaining", "0"))
+                    .json(json!({
+                        "success": false,
+                        "message": "Rate limit exceeded, try again later"
+                    }));
+                return Ok(req.into_response(response));
+            }
+
+            let mut res = svc.call(req).await?.map_into_boxed_body();
+
+            let remaining = decision.map_or(burst, |(_, remaining, _)| remaining);
+            let headers = res.headers_mut();
+            headers.insert(
+                header::HeaderName::from_static("x-ratelimit-limit"),
+                header::HeaderValue::from(burst),
+            );
+            headers.insert(
+                header::HeaderName::from_static("x-ratelimit-remaining"),
+                header::HeaderValue::from(remaining),
+            );
+
+            Ok(res)
+        })
+    }
+}
+
+/// Take a token from an API key's own bucket, which holds a minute's worth of
+/// requests. Returns the seconds to wait if it is empty, and `None` if the
+/// request may go ahead or Redis is unavailable.
+pub async fn take_api_key_token(pool: &Pool, key_id: &str, per_minute: i64) -> Option<i64> {
+    let key = format!("ratelimit:api_key:{}", key_id);
+    match take_token(pool, &key, per_minute, per_minute).await {
+        Some((false, _, retry_after_ms)) => Some(((retry_after_ms + 999) / 1000).max(1)),
+        _ => None,
+    }
+}
+
+/// Take a token from the bucket at `key`, returning whether the request is
+/// allowed, the tokens left and the milliseconds until the next one. Returns
+/// `None` if Redis is unavailable, in which case the request is let through.
+async fn take_token(
+    pool: &Pool,
+    key: &str,
+    burst: i64,
+    per_minute: i64,
+) -> Option<(bool, i64, i64)> {
+    let mut conn = match pool.get().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Rate limiter unavailable: {}", e);
+            return None;
+        }
+    };
+    let result: redis::RedisResult<(i64, i64, i64)> = TAKE_TOKEN_SCRIPT
+        .key(key)
+        .arg(burst.max(1))
+        .arg(per_minute.max(1))
+        .arg(Utc::now().timestamp_millis())
+        .invoke_async(&mut conn)
+        .await;
+    match result {
+        Ok((allowed, remaining, retry_after_ms)) => Some((allowed == 1, remaining, retry_after_ms)),
+        Err(e) => {
+            log::error!("Rate limiter unavailable: {}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use crate::tenants::registry;
+use actix_web::{
+    body::{self, BoxBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header,
+    web, Error, HttpMessage, HttpResponse,
+};
+use entity::tenants;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use sea_orm::DatabaseConnection;
+use serde_json::json;
+use std::rc::Rc;
+
+/// Header naming the tenant a request is for, by slug
+pub const TENANT_HEADER: &str = "X-Tenant";
+
+/// Works out which tenant's site a request is for and stores the tenant in
+/// the request extensions: the one named by `X-Tenant`, else the one whose
+/// domain the request was sent to, else the default tenant. Unknown or
+/// deactivated tenants are refused with a 404.
+pub struct TenantResolver;
+
+impl<S, B> Transform<S, ServiceRequest> for TenantResolver
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TenantResolverService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TenantResolverService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct TenantResolverService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for TenantResolverService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let Some(tenant) = resolve(&req) else {
+                return Ok(req.into_response(HttpResponse::NotFound().json(json!({
+                    "success": false,
+                    "message": "Unknown tenant"
+                }))));
+            };
+            req.extensions_mut().insert(tenant);
+
+            let mut res = svc.call(req).await?.map_into_boxed_body();
+            // Responses differ per tenant, so shared caches must key on it
+            res.headers_mut().append(
+                header::VARY,
+                header::HeaderValue::from_static(TENANT_HEADER),
+            );
+            Ok(res)
+        })
+    }
+}
+
+fn resolve(req: &ServiceRequest) -> Option<tenants::Model> {
+    if let Some(slug) = req.headers().get(TENANT_HEADER) {
+        return slug.to_str().ok().and_then(registry::by_slug);
+    }
+
+    let host = req.connection_info().host().to_string();
+    let domain = host.split(':').next().unwrap_or_default();
+    registry::by_domain(domain).or_else(registry::default_tenant)
+}
+
+/// Hides events of other tenants from a route addressing one by its
+/// `event_id`, or failing that its `option_id`, path segment. Must run after
+/// `TenantResolver`; routes that address neither are let through.
+pub struct TenantEvent;
+
+impl<S, B> Transform<S, ServiceRequest> for TenantEvent
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TenantEventService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TenantEventService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct TenantEventService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for TenantEventService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let tenant_id = req.extensions().get::<tenants::Model>().map(|t| t.id);
+            let db = req.app_data::<web::Data<DatabaseConnection>>().cloned();
+            let event_id = req
+                .match_info()
+                .get("event_id")
+                .and_then(|id| id.parse().ok());
+            let option_id = req
+                .match_info()
+                .get("option_id")
+                .and_then(|id| id.parse().ok());
+
+            if let (Some(tenant_id), Some(db)) = (tenant_id, db) {
+                let owner = match (event_id, option_id) {
+                    (Some(event_id), _) => registry::resolve_event_tenant(&db, event_id).await,
+                    (None, Some(option_id)) => {
+                        registry::resolve_option_tenant(&db, option_id).await
+                    }
+                    (None, None) => Ok(None),
+                }
+                .map_err(|e| {
+                    log::error!("Database error: {}", e);
+                    actix_web::error::ErrorInternalServerError("Database error occurred")
+                })?;
+
+                // Unknown events fall through to the handler's own not found
+                if owner.is_some_and(|owner| owner != tenant_id) {
+                    return Ok(req.into_response(HttpResponse::NotFound().json(json!({
+                        "success": false,
+                        "message": "Event not found"
+                    }))));
+                }
+            }
+
+            Ok(svc.call(req).await?.map_into_boxed_body())
+        })
+    }
+}
@@ -0,0 +1,137 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::utils::two_factor::{self, CodeCheck};
+use actix_web::{
+    body::{self, BoxBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    web, Error, HttpMessage, HttpResponse,
+};
+use deadpool_redis::Pool;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use sea_orm::DatabaseConnection;
+use serde_json::json;
+use std::rc::Rc;
+
+pub const TWO_FACTOR_CODE_HEADER: &str = "X-2FA-Code";
+
+/// Requires the user to have two-factor authentication enabled and to send a
+/// current authenticator code, or one of their backup codes, in
+/// `X-2FA-Code`. Must run after `AuthMiddleware`.
+pub struct TwoFactor;
+
+impl<S, B> Transform<S, ServiceRequest> for TwoFactor
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TwoFactorService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TwoFactorService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct TwoFactorService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for TwoFactorService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let user_id = req
+                .extensions()
+                .get::<AuthenticatedUser>()
+                .and_then(|user| user.id.parse::<i32>().ok());
+            let Some(user_id) = user_id else {
+                return Ok(req.into_response(HttpResponse::Unauthorized().json(json!({
+                    "success": false,
+                    "message": "Authentication required"
+                }))));
+            };
+            let Some(db) = req.app_data::<web::Data<DatabaseConnection>>().cloned() else {
+                return Err(actix_web::error::ErrorInternalServerError(
+                    "Database unavailable",
+                ));
+            };
+            let redis_pool = req.app_data::<web::Data<Pool>>().cloned();
+
+            let security = two_factor::find(db.get_ref(), user_id)
+                .await
+                .map_err(|e| {
+                    log::error!("Database error: {}", e);
+                    actix_web::error::ErrorInternalServerError("Database error occurred")
+                })?
+                .filter(|security| security.totp_enabled);
+            let Some(security) = security else {
+                return Ok(req.into_response(HttpResponse::Forbidden().json(json!({
+                    "success": false,
+                    "message": "Two-factor authentication must be enabled for this action",
+                    "two_factor_required": true
+                }))));
+            };
+
+            let code = req
+                .headers()
+                .get(TWO_FACTOR_CODE_HEADER)
+                .and_then(|h| h.to_str().ok())
+                .map(str::to_string);
+            let Some(code) = code else {
+                return Ok(req.into_response(HttpResponse::Unauthorized().json(json!({
+                    "success": false,
+                    "message": format!("Send a two-factor code in {}", TWO_FACTOR_CODE_HEADER),
+                    "two_factor_required": true
+                }))));
+            };
+
+            let check = two_factor::check_code(
+                db.get_ref(),
+                redis_pool.as_ref().map(|pool| pool.get_ref()),
+                &security,
+                &code,
+                true,
+            )
+            .await
+            .map_err(|e| {
+                log::error!("Failed to check two-factor code: {}", e);
+                actix_web::error::ErrorInternalServerError("Failed to check two-factor code")
+            })?;
+
+            match check {
+                CodeCheck::Valid => Ok(svc.call(req).await?.map_into_boxed_body()),
+                CodeCheck::Invalid => {
+                    log::warn!("Invalid two-factor code from user {}", user_id);
+                    Ok(req.into_response(HttpResponse::Unauthorized().json(json!({
+                        "success": false,
+                        "message": "Invalid two-factor code",
+                        "two_factor_required": true
+                    }))))
+                }
+                CodeCheck::Locked => Ok(req.into_response(HttpResponse::TooManyRequests().json(
+                    json!({
+                        "success": false,
+                        "message": "Too many invalid two-factor codes, try again later"
+                    }),
+                ))),
+            }
+        })
+    }
+}
@@ -0,0 +1,289 @@
+use super::email::{self, EmailMessage};
+use crate::constants::config;
+use crate::order_book::position_tracker::{PortfolioTotals, PositionTracker};
+use crate::types::preferences::DigestFrequency;
+use actix_web::web;
+use chrono::{DateTime, Duration, Timelike, Utc};
+use deadpool_redis::Pool;
+use entity::{event_options, events, trades, user_positions, user_preferences, users};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set,
+};
+use std::collections::HashMap;
+
+const DIGEST_TEMPLATE: &str = include_str!("templates/digest.txt");
+
+/// How often the job looks for digests that are due
+const DIGEST_CHECK_INTERVAL_SECONDS: u64 = 300;
+
+/// How far ahead event expiries are listed
+const UPCOMING_EXPIRY_DAYS: i64 = 7;
+
+/// Everything reported in one user's digest
+struct Digest {
+    fills: Vec<String>,
+    fill_count: usize,
+    totals: PortfolioTotals,
+    expiries: Vec<String>,
+}
+
+impl Digest {
+    fn is_empty(&self) -> bool {
+        self.fill_count == 0 && self.totals.total_positions == 0 && self.expiries.is_empty()
+    }
+}
+
+/// Start a background task that emails opted-in users a summary of their fills,
+/// PnL and upcoming event expiries once per day or week
+pub fn start_digest_job(db: web::Data<DatabaseConnection>, redis_pool: web::Data<Pool>) {
+    log::info!(
+        "Starting email digest job, sending after {:02}:00 UTC",
+        config::get_digest_send_hour_utc()
+    );
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            DIGEST_CHECK_INTERVAL_SECONDS,
+        ));
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = send_due_digests(db.get_ref(), redis_pool.get_ref()).await {
+                log::error!("Email digest job failed: {}", e);
+            }
+        }
+    });
+}
+
+async fn send_due_digests(db: &DatabaseConnection, redis_pool: &Pool) -> Result<(), String> {
+    let now = Utc::now();
+    if now.hour() < config::get_digest_send_hour_utc() {
+        return Ok(());
+    }
+
+    let subscriptions = user_preferences::Entity::find()
+        .filter(user_preferences::Column::EmailDigestEnabled.eq(true))
+        .filter(user_preferences::Column::EmailEnabled.eq(true))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load user preferences: {}", e))?;
+
+    for preferences in subscriptions {
+        let frequency = DigestFrequency::parse(&preferences.digest_frequency);
+        let last_sent: Option<DateTime<Utc>> = preferences.last_digest_sent_at.map(Into::into);
+
+        // Due once per calendar day or week, so the send time doesn't drift
+        let today = now.date_naive();
+        let due = match last_sent {
+            Some(sent) => sent.date_naive() <= today - Duration::days(frequency.period_days()),
+            None => true,
+        };
+        if !due {
+            continue;
+        }
+
+        let user_id = preferences.user_id;
+        let since = last_sent.unwrap_or(now - Duration::days(frequency.period_days()));
+        if let Err(e) = send_digest(db, redis_pool, user_id, frequency, since, now).await {
+            log::error!("Failed to send email digest to user {}: {}", user_id, e);
+            continue;
+        }
+
+        let mut active: user_preferences::ActiveModel = preferences.into();
+        active.last_digest_sent_at = Set(Some(now.into()));
+        if let Err(e) = active.update(db).await {
+            log::error!("Failed to record email digest for user {}: {}", user_id, e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_digest(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    user_id: i32,
+    frequency: DigestFrequency,
+    since: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let user = users::Entity::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|e| format!("Failed to load user: {}", e))?;
+    let Some(user) = user.filter(|u| u.is_active) else {
+        return Ok(());
+    };
+
+    let digest = build_digest(db, user_id, since, now).await?;
+
+    // Nothing happened and nothing is held, so there is nothing worth an email
+    if digest.is_empty() {
+        return Ok(());
+    }
+
+    let name = user.full_name.clone().unwrap_or(user.username.clone());
+    let body = render(&[
+        ("name", name),
+        ("frequency", frequency.as_str().to_string()),
+        ("period_start", since.format("%Y-%m-%d %H:%M").to_string()),
+        ("period_end", now.format("%Y-%m-%d %H:%M").to_string()),
+        ("fill_count", digest.fill_count.to_string()),
+        (
+            "fills",
+            bullet_list(&digest.fills, "No fills in this period."),
+        ),
+        ("open_positions", digest.totals.total_positions.to_string()),
+        (
+            "total_invested",
+            digest.totals.total_invested.round_dp(2).to_string(),
+        ),
+        (
+            "current_value",
+            digest.totals.current_value.round_dp(2).to_string(),
+        ),
+        (
+            "total_pnl",
+            (digest.totals.current_value - digest.totals.total_invested)
+                .round_dp(2)
+                .to_string(),
+        ),
+        ("expiry_days", UPCOMING_EXPIRY_DAYS.to_string()),
+        (
+            "expiries",
+            bullet_list(&digest.expiries, "None of your events end soon."),
+        ),
+    ]);
+
+    let subject = match frequency {
+        DigestFrequency::Daily => "Your daily trading summary",
+        DigestFrequency::Weekly => "Your weekly trading summary",
+    };
+
+    email::queue_email(
+        redis_pool,
+        &EmailMessage {
+            to: user.email,
+            subject: subject.to_string(),
+            body,
+            created_at: now,
+        },
+    )
+    .await
+}
+
+async fn build_digest(
+    db: &DatabaseConnection,
+    user_id: i32,
+    since: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<Digest, String> {
+    let fills = trades::Entity::find()
+        .filter(
+            Condition::any()
+                .add(trades::Column::BuyerId.eq(user_id))
+                .add(trades::Column::SellerId.eq(user_id)),
+        )
+        .filter(trades::Column::Timestamp.gte(since))
+        .filter(trades::Column::Timestamp.lt(now))
+        .order_by_asc(trades::Column::Timestamp)
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load fills: {}", e))?;
+
+    let option_ids: Vec<i32> = fills.iter().map(|t| t.option_id).collect();
+    let option_names: HashMap<i32, String> = event_options::Entity::find()
+        .filter(event_options::Column::Id.is_in(option_ids))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load options: {}", e))?
+        .into_iter()
+        .map(|o| (o.id, o.option_text))
+        .collect();
+
+    let max_fills = config::get_digest_max_fills();
+    let mut fill_lines: Vec<String> = fills
+        .iter()
+        .take(max_fills)
+        .map(|t| {
+            let side = if t.buyer_id == user_id {
+                "Bought"
+            } else {
+                "Sold"
+            };
+            let option = option_names
+                .get(&t.option_id)
+                .map(String::as_str)
+                .unwrap_or("Unknown option");
+            format!(
+                "{} {} {} @ {} ({})",
+                t.timestamp.format("%Y-%m-%d %H:%M"),
+                side,
+                t.quantity,
+                t.price.round_dp(2),
+                option
+            )
+        })
+        .collect();
+    if fills.len() > max_fills {
+        fill_lines.push(format!("...and {} more", fills.len() - max_fills));
+    }
+
+    let totals = PositionTracker::new(db.clone())
+        .get_portfolio_totals(user_id)
+        .await?;
+
+    let held_event_ids: Vec<i32> = user_positions::Entity::find()
+        .select_only()
+        .column(user_positions::Column::EventId)
+        .distinct()
+        .filter(user_positions::Column::UserId.eq(user_id))
+        .filter(user_positions::Column::Quantity.gt(0))
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load held events: {}", e))?;
+
+    let expiries = events::Entity::find()
+        .filter(events::Column::Id.is_in(held_event_ids))
+        .filter(events::Column::Status.eq("active"))
+        .filter(events::Column::EndTime.gt(now.naive_utc()))
+        .filter(
+            events::Column::EndTime.lte((now + Duration::days(UPCOMING_EXPIRY_DAYS)).naive_utc()),
+        )
+        .order_by_asc(events::Column::EndTime)
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load upcoming expiries: {}", e))?
+        .into_iter()
+        .map(|e| format!("{} ends {}", e.title, e.end_time.format("%Y-%m-%d %H:%M")))
+        .collect();
+
+    Ok(Digest {
+        fills: fill_lines,
+        fill_count: fills.len(),
+        totals,
+        expiries,
+    })
+}
+
+/// Fill `{{name}}` placeholders in the digest template
+fn render(values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(DIGEST_TEMPLATE.to_string(), |body, (name, value)| {
+            body.replace(&format!("{{{{{}}}}}", name), value)
+        })
+}
+
+fn bullet_list(lines: &[String], empty: &str) -> String {
+    if lines.is_empty() {
+        return format!("  {}", empty);
+    }
+    lines
+        .iter()
+        .map(|line| format!("  - {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
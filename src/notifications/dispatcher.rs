@@ -0,0 +1,149 @@
+use super::email::{self, EmailMessage};
+use crate::order_book::types::Trade;
+use crate::types::preferences::{NotificationCategory, PreferencesResponse};
+use crate::types::websocket::WebSocketMessage;
+use crate::websocket::server::{SendToUser, WebSocketServer};
+use actix::Addr;
+use chrono::{DateTime, Utc};
+use deadpool_redis::{redis::AsyncCommands, Pool};
+use entity::{user_preferences, users};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde::Serialize;
+
+/// Redis list of outgoing push notifications, drained by the push gateway
+const PUSH_OUTBOX_KEY: &str = "push:outbox";
+
+pub struct Notification {
+    pub category: NotificationCategory,
+    pub title: String,
+    pub body: String,
+}
+
+#[derive(Serialize)]
+struct PushMessage<'a> {
+    user_id: i32,
+    category: NotificationCategory,
+    title: &'a str,
+    body: &'a str,
+    created_at: DateTime<Utc>,
+}
+
+/// Deliver a notification to a user on every channel they enabled, provided
+/// they haven't opted out of its category
+pub async fn dispatch(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    ws_server: &Addr<WebSocketServer>,
+    user_id: i32,
+    notification: Notification,
+) -> Result<(), String> {
+    let preferences = user_preferences::Entity::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|e| format!("Failed to load user preferences: {}", e))?
+        .map(PreferencesResponse::from)
+        .unwrap_or_else(PreferencesResponse::defaults);
+
+    if !preferences.category_enabled(notification.category) {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+
+    if preferences.channels.ws {
+        ws_server.do_send(SendToUser {
+            user_id,
+            message: WebSocketMessage::Notification {
+                category: notification.category,
+                title: notification.title.clone(),
+                body: notification.body.clone(),
+                timestamp: now,
+            },
+        });
+    }
+
+    if preferences.channels.push {
+        let message = PushMessage {
+            user_id,
+            category: notification.category,
+            title: &notification.title,
+            body: &notification.body,
+            created_at: now,
+        };
+        queue_push(redis_pool, &message).await?;
+    }
+
+    if preferences.channels.email {
+        let user = users::Entity::find_by_id(user_id)
+            .one(db)
+            .await
+            .map_err(|e| format!("Failed to load user: {}", e))?;
+        if let Some(user) = user.filter(|u| u.is_active) {
+            email::queue_email(
+                redis_pool,
+                &EmailMessage {
+                    to: user.email,
+                    subject: notification.title,
+                    body: notification.body,
+                    created_at: now,
+                },
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Notify the buyer and seller of each trade in the background
+pub fn notify_fills(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    ws_server: &Addr<WebSocketServer>,
+    trades: &[Trade],
+) {
+    if trades.is_empty() {
+        return;
+    }
+
+    let db = db.clone();
+    let redis_pool = redis_pool.clone();
+    let ws_server = ws_server.clone();
+    let trades = trades.to_vec();
+
+    tokio::spawn(async move {
+        for trade in &trades {
+            for (user_id, side) in [(trade.buyer_id, "Bought"), (trade.seller_id, "Sold")] {
+                let notification = Notification {
+                    category: NotificationCategory::Fills,
+                    title: "Order filled".to_string(),
+                    body: format!(
+                        "{} {} shares of option {} in event {} at {}",
+                        side, trade.quantity, trade.option_id, trade.event_id, trade.price
+                    ),
+                };
+                if let Err(e) = dispatch(&db, &redis_pool, &ws_server, user_id, notification).await
+                {
+                    log::warn!("Failed to notify user {} of fill: {}", user_id, e);
+                }
+            }
+        }
+    });
+}
+
+async fn queue_push(redis_pool: &Pool, message: &PushMessage<'_>) -> Result<(), String> {
+    let mut conn = redis_pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+
+    let serialized = serde_json::to_string(message)
+        .map_err(|e| format!("Failed to serialize push notification: {}", e))?;
+
+    let _: () = conn
+        .lpush(PUSH_OUTBOX_KEY, serialized)
+        .await
+        .map_err(|e| format!("Failed to queue push notification: {}", e))?;
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use deadpool_redis::{redis::AsyncCommands, Pool};
+use serde::{Deserialize, Serialize};
+
+/// Redis list of outgoing emails. The mail relay pops from the tail, so
+/// messages go out in the order they were queued.
+const EMAIL_OUTBOX_KEY: &str = "email:outbox";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Queue an email for delivery by the mail relay
+pub async fn queue_email(redis_pool: &Pool, message: &EmailMessage) -> Result<(), String> {
+    let mut conn = redis_pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+
+    let serialized =
+        serde_json::to_string(message).map_err(|e| format!("Failed to serialize email: {}", e))?;
+
+    let _: () = conn
+        .lpush(EMAIL_OUTBOX_KEY, serialized)
+        .await
+        .map_err(|e| format!("Failed to queue email: {}", e))?;
+
+    Ok(())
+}
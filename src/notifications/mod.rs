@@ -0,0 +1,3 @@
+pub mod digest;
+pub mod dispatcher;
+pub mod email;
@@ -0,0 +1,63 @@
+use crate::types::oracle::MAX_VALUE_LENGTH;
+use deadpool_redis::{redis::AsyncCommands, Pool};
+use entity::event_oracles;
+use serde_json::{json, Value};
+
+/// Redis list the oracle relay drains, oldest request at the tail. The relay
+/// fetches each feed and reports its JSON document back through the internal
+/// oracle feed endpoint.
+pub const OUTBOX_KEY: &str = "oracle:outbox";
+
+/// Queue a fetch of an oracle's feed for the relay
+pub async fn request_feed(redis_pool: &Pool, oracle: &event_oracles::Model) -> Result<(), String> {
+    let message = json!({
+        "action": "fetch_feed",
+        "oracle": {
+            "id": oracle.id,
+            "event_id": oracle.event_id,
+            "source_url": oracle.source_url,
+        },
+        "timestamp": chrono::Utc::now(),
+    });
+    let mut conn = redis_pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to reach oracle outbox: {}", e))?;
+    conn.lpush::<_, _, ()>(OUTBOX_KEY, message.to_string())
+        .await
+        .map_err(|e| format!("Failed to queue oracle feed fetch: {}", e))
+}
+
+/// The result a feed gives at an oracle's value path and the option it maps
+/// to. `Ok(None)` while the feed has no result there yet; an error when it
+/// has one that maps to no option.
+pub fn resolve(
+    oracle: &event_oracles::Model,
+    feed: &Value,
+) -> Result<Option<(String, i32)>, String> {
+    let value = match feed.pointer(&oracle.value_path) {
+        None | Some(Value::Null) => return Ok(None),
+        Some(Value::String(value)) => value.clone(),
+        Some(value @ (Value::Number(_) | Value::Bool(_))) => value.to_string(),
+        Some(_) => {
+            return Err(format!(
+                "Feed value at {} is not a string, number or boolean",
+                oracle.value_path
+            ))
+        }
+    };
+    if value.len() > MAX_VALUE_LENGTH {
+        return Err(format!(
+            "Feed value at {} is longer than {} characters",
+            oracle.value_path, MAX_VALUE_LENGTH
+        ));
+    }
+
+    let option_id = oracle
+        .option_mappings
+        .get(&value)
+        .and_then(Value::as_i64)
+        .and_then(|option_id| i32::try_from(option_id).ok())
+        .ok_or_else(|| format!("Feed value {:?} maps to no option", value))?;
+    Ok(Some((value, option_id)))
+}
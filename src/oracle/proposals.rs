@@ -0,0 +1,201 @@
+use super::feed;
+use crate::handlers::event_settlement_handler;
+use crate::types::event::{SettleEventRequest, SettlementMode};
+use crate::types::oracle::OracleStatus;
+use crate::types::websocket::{OpsEventKind, OpsSeverity};
+use crate::websocket::ops;
+use crate::websocket::server::WebSocketServer;
+use actix::Addr;
+use actix_web::{body::MessageBody, web, Error, HttpResponse};
+use chrono::{Duration, Utc};
+use deadpool_redis::Pool;
+use entity::event_oracles;
+use sea_orm::{
+    prelude::Expr, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set,
+};
+use serde_json::{json, Value};
+
+/// What a feed report did to an oracle
+pub enum FeedOutcome {
+    /// The feed has no result yet
+    Pending,
+    /// The feed's result was proposed
+    Proposed(Box<event_oracles::Model>),
+    /// The feed's result couldn't be used, and why
+    Unusable(String),
+}
+
+/// Apply a feed the relay fetched to a waiting oracle, proposing the option
+/// its result maps to. Auto-settling oracles settle once their challenge
+/// window passes; the others wait for an admin to confirm.
+pub async fn record_feed(
+    db: &DatabaseConnection,
+    oracle: event_oracles::Model,
+    feed: &Value,
+) -> Result<FeedOutcome, String> {
+    let now = Utc::now();
+    let mut active: event_oracles::ActiveModel = oracle.clone().into();
+    active.updated_at = Set(now.into());
+
+    let unusable = match feed::resolve(&oracle, feed) {
+        Ok(None) => {
+            active.last_error = Set(None);
+            None
+        }
+        Err(message) => {
+            active.last_error = Set(Some(message.clone()));
+            Some(message)
+        }
+        Ok(Some((value, option_id))) => {
+            let settle_after = oracle
+                .auto_settle
+                .then(|| now + Duration::minutes(oracle.challenge_window_minutes.into()));
+            active.status = Set(OracleStatus::Proposed.as_str().to_string());
+            active.proposed_option_id = Set(Some(option_id));
+            active.proposed_value = Set(Some(value));
+            active.proposed_at = Set(Some(now.into()));
+            active.settle_after = Set(settle_after.map(Into::into));
+            active.last_error = Set(None);
+            None
+        }
+    };
+
+    let updated = active
+        .update(db)
+        .await
+        .map_err(|e| format!("Failed to update oracle: {}", e))?;
+
+    if let Some(message) = unusable {
+        return Ok(FeedOutcome::Unusable(message));
+    }
+    if updated.status != OracleStatus::Proposed.as_str() {
+        return Ok(FeedOutcome::Pending);
+    }
+
+    ops::publish(
+        OpsEventKind::OracleProposed,
+        OpsSeverity::Info,
+        format!(
+            "Oracle proposed option {} for event {}",
+            updated.proposed_option_id.unwrap_or_default(),
+            updated.event_id
+        ),
+        json!({
+            "event_id": updated.event_id,
+            "option_id": updated.proposed_option_id,
+            "value": updated.proposed_value,
+            "settle_after": updated.settle_after,
+        }),
+    );
+    Ok(FeedOutcome::Proposed(Box::new(updated)))
+}
+
+/// Settle an event with its oracle's proposed option on behalf of
+/// `resolver_id`. The proposal is claimed first, so it is settled once even
+/// if an admin confirms it while the scheduler settles it; a settlement that
+/// doesn't succeed hands it back with the reason in `last_error`.
+pub async fn settle_proposal(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    oracle: &event_oracles::Model,
+    resolver_id: i32,
+    ip: Option<String>,
+) -> Result<Option<HttpResponse>, Error> {
+    let Some(option_id) = oracle.proposed_option_id else {
+        return Ok(None);
+    };
+
+    let claimed = event_oracles::Entity::update_many()
+        .col_expr(
+            event_oracles::Column::Status,
+            Expr::value(OracleStatus::Settled.as_str()),
+        )
+        .col_expr(event_oracles::Column::UpdatedAt, Expr::value(Utc::now()))
+        .filter(event_oracles::Column::Id.eq(oracle.id))
+        .filter(event_oracles::Column::Status.eq(OracleStatus::Proposed.as_str()))
+        .exec(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?
+        .rows_affected;
+    if claimed == 0 {
+        return Ok(None);
+    }
+
+    let req = SettleEventRequest {
+        mode: SettlementMode::WinnerTakeAll,
+        winning_option_id: Some(option_id),
+        winning_option_ids: None,
+        option_values: None,
+        inputs: None,
+        resolution_note: Some(format!(
+            "Resolved by oracle from {}: {}",
+            oracle.source_url,
+            oracle.proposed_value.as_deref().unwrap_or_default()
+        )),
+    };
+    // Responses aren't Send, so the reason a settlement failed is taken out
+    // of it before anything else is awaited
+    let (message, failed) = match event_settlement_handler::settle(
+        db,
+        redis_pool,
+        ws_server,
+        oracle.event_id,
+        &req,
+        resolver_id,
+        ip,
+    )
+    .await
+    {
+        Ok(response) if response.status().is_success() => {
+            log::info!(
+                "Settled event {} with oracle option {}",
+                oracle.event_id,
+                option_id
+            );
+            return Ok(Some(response));
+        }
+        Ok(response) => {
+            let status = response.status();
+            let message = response
+                .into_body()
+                .try_into_bytes()
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok())
+                .and_then(|body| body["message"].as_str().map(str::to_string))
+                .unwrap_or_else(|| format!("Settlement returned {}", status));
+            (message, false)
+        }
+        Err(e) => (e.to_string(), true),
+    };
+    log::warn!(
+        "Oracle settlement of event {} failed: {}",
+        oracle.event_id,
+        message
+    );
+    event_oracles::Entity::update_many()
+        .col_expr(
+            event_oracles::Column::Status,
+            Expr::value(OracleStatus::Proposed.as_str()),
+        )
+        .col_expr(
+            event_oracles::Column::LastError,
+            Expr::value(message.clone()),
+        )
+        .col_expr(event_oracles::Column::UpdatedAt, Expr::value(Utc::now()))
+        .filter(event_oracles::Column::Id.eq(oracle.id))
+        .exec(db.get_ref())
+        .await
+        .map_err(|e| {
+            log::error!("Database error: {}", e);
+            actix_web::error::ErrorInternalServerError("Database error occurred")
+        })?;
+
+    if failed {
+        return Err(actix_web::error::ErrorInternalServerError(message));
+    }
+    Ok(None)
+}
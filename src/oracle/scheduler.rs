@@ -0,0 +1,123 @@
+use super::{feed, proposals};
+use crate::constants::config;
+use crate::types::oracle::OracleStatus;
+use crate::types::websocket::{OpsEventKind, OpsSeverity};
+use crate::websocket::ops;
+use crate::websocket::server::WebSocketServer;
+use actix::Addr;
+use actix_web::web;
+use chrono::Utc;
+use deadpool_redis::Pool;
+use entity::{event_oracles, events};
+use sea_orm::{
+    prelude::Expr, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter,
+};
+use serde_json::json;
+
+/// Statuses of events an oracle can no longer resolve
+const FINAL_STATUSES: [&str; 2] = ["resolved", "cancelled"];
+
+/// Ask the relay for the feeds of waiting oracles whose event has ended
+async fn poll_feeds(db: &DatabaseConnection, redis_pool: &Pool) -> Result<(), String> {
+    let waiting = event_oracles::Entity::find()
+        .find_also_related(events::Entity)
+        .filter(event_oracles::Column::Status.eq(OracleStatus::Waiting.as_str()))
+        .filter(
+            Condition::any()
+                .add(events::Column::Status.eq("ended"))
+                .add(events::Column::EndTime.lte(Utc::now().naive_utc())),
+        )
+        .filter(events::Column::Status.is_not_in(FINAL_STATUSES))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load waiting oracles: {}", e))?;
+
+    for (oracle, _) in waiting {
+        if let Err(e) = feed::request_feed(redis_pool, &oracle).await {
+            log::warn!("Failed to request feed of oracle {}: {}", oracle.id, e);
+            continue;
+        }
+        event_oracles::Entity::update_many()
+            .col_expr(
+                event_oracles::Column::LastCheckedAt,
+                Expr::value(Utc::now()),
+            )
+            .filter(event_oracles::Column::Id.eq(oracle.id))
+            .exec(db)
+            .await
+            .map_err(|e| format!("Failed to update oracle: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Settle auto-settling proposals whose challenge window has passed, on
+/// behalf of the admin who configured the oracle
+async fn settle_due_proposals(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+) -> Result<(), String> {
+    let due = event_oracles::Entity::find()
+        .filter(event_oracles::Column::Status.eq(OracleStatus::Proposed.as_str()))
+        .filter(event_oracles::Column::AutoSettle.eq(true))
+        .filter(event_oracles::Column::SettleAfter.lte(Utc::now()))
+        .all(db.get_ref())
+        .await
+        .map_err(|e| format!("Failed to load due oracle proposals: {}", e))?;
+
+    for oracle in due {
+        // A failed settlement is kept in the oracle's last_error and retried
+        // on the next run
+        if let Err(e) =
+            proposals::settle_proposal(db, redis_pool, ws_server, &oracle, oracle.created_by, None)
+                .await
+        {
+            log::error!(
+                "Failed to settle oracle proposal of event {}: {}",
+                oracle.event_id,
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Start a background task that requests the feeds of oracles whose events
+/// have ended and settles their proposals once the challenge window passes
+pub fn start_oracle_scheduler(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+) {
+    let interval_seconds = config::get_oracle_interval_seconds();
+
+    log::info!(
+        "Starting oracle scheduler with {}-second interval",
+        interval_seconds
+    );
+
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(interval_seconds));
+
+        loop {
+            interval.tick().await;
+
+            let result = match poll_feeds(db.get_ref(), redis_pool.get_ref()).await {
+                Ok(()) => settle_due_proposals(&db, &redis_pool, &ws_server).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                log::error!("Oracle scheduler failed: {}", e);
+                ops::publish(
+                    OpsEventKind::JobFailed,
+                    OpsSeverity::Warning,
+                    "Oracle scheduler could not poll feeds or settle proposals",
+                    json!({ "job": "oracle_scheduler", "error": e }),
+                );
+            }
+        }
+    });
+}
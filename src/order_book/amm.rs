@@ -0,0 +1,182 @@
+use super::{
+    engine::POST_ONLY_REJECTION,
+    limits,
+    margin::PAYOUT_PER_SHARE,
+    reservations, router,
+    types::{Order, OrderSide, Trade},
+};
+use crate::constants::config;
+use entity::{event_options, events, user_positions};
+use rust_decimal::prelude::FromPrimitive;
+use sea_orm::{prelude::Decimal, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use std::collections::HashMap;
+
+/// Rejection for an order whose limit price doesn't cover the AMM's quote
+pub const AMM_PRICE_REJECTION: &str = "amm_quote_outside_limit";
+
+/// Rejection when the AMM account can't fund its side of the trade
+pub const AMM_LIQUIDITY_REJECTION: &str = "amm_insufficient_liquidity";
+
+/// An event's options as the AMM sees them: shares of each sold to traders,
+/// net of what it has bought back
+struct Market {
+    event: events::Model,
+    outstanding: HashMap<i32, f64>,
+    liquidity: f64,
+}
+
+impl Market {
+    /// LMSR cost function, C(q) = b * ln(sum(exp(q_j / b))), computed around
+    /// the largest term so large inventories don't overflow
+    fn cost(&self, option_id: i32, delta: f64) -> f64 {
+        let b = self.liquidity;
+        let shares: Vec<f64> = self
+            .outstanding
+            .iter()
+            .map(|(id, q)| if *id == option_id { q + delta } else { *q } / b)
+            .collect();
+        let max = shares.iter().cloned().fold(f64::MIN, f64::max);
+        b * (max + shares.iter().map(|s| (s - max).exp()).sum::<f64>().ln())
+    }
+
+    /// Instantaneous price of an option, as a fraction of the payout
+    fn marginal(&self, option_id: i32) -> f64 {
+        let b = self.liquidity;
+        let max = self.outstanding.values().cloned().fold(f64::MIN, f64::max);
+        let weight = |q: f64| ((q - max) / b).exp();
+        let total: f64 = self.outstanding.values().map(|q| weight(*q)).sum();
+        self.outstanding
+            .get(&option_id)
+            .map(|q| weight(*q) / total)
+            .unwrap_or(0.0)
+    }
+
+    /// Average price per share for a trader buying or selling `quantity`,
+    /// rounded against the trader to a valid tick
+    fn quote(&self, option_id: i32, side: &OrderSide, quantity: i32) -> Decimal {
+        let n = f64::from(quantity);
+        let cost = match side {
+            OrderSide::Buy => self.cost(option_id, n) - self.cost(option_id, 0.0),
+            OrderSide::Sell => self.cost(option_id, 0.0) - self.cost(option_id, -n),
+        };
+        let price = Decimal::from_f64(cost / n).unwrap_or(Decimal::ZERO) * PAYOUT_PER_SHARE;
+
+        let tick = self.event.tick_size;
+        let price = if tick > Decimal::ZERO {
+            match side {
+                OrderSide::Buy => (price / tick).ceil() * tick,
+                OrderSide::Sell => (price / tick).floor() * tick,
+            }
+        } else {
+            price
+        };
+        price.clamp(self.event.min_price, self.event.max_price)
+    }
+}
+
+async fn load_market(db: &DatabaseConnection, event_id: i32) -> Result<Market, String> {
+    let event = events::Entity::find_by_id(event_id)
+        .one(db)
+        .await
+        .map_err(|e| format!("Failed to load event: {}", e))?
+        .ok_or_else(|| format!("Event {} not found", event_id))?;
+
+    let options = event_options::Entity::find()
+        .filter(event_options::Column::EventId.eq(event_id))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load event options: {}", e))?;
+
+    let inventory = user_positions::Entity::find()
+        .filter(user_positions::Column::UserId.eq(config::get_amm_user_id()))
+        .filter(user_positions::Column::EventId.eq(event_id))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load AMM inventory: {}", e))?;
+
+    let outstanding = options
+        .iter()
+        .map(|option| {
+            let held = inventory
+                .iter()
+                .find(|p| p.option_id == option.id)
+                .map(|p| p.quantity)
+                .unwrap_or(0);
+            (option.id, -f64::from(held))
+        })
+        .collect();
+
+    Ok(Market {
+        event,
+        outstanding,
+        liquidity: f64::from(config::get_amm_liquidity().max(1)),
+    })
+}
+
+/// Average price the AMM would fill all of an order at, or why it would
+/// reject it
+pub async fn quote_order(
+    db: &DatabaseConnection,
+    order: &Order,
+) -> Result<Result<Decimal, String>, String> {
+    if order.post_only {
+        return Ok(Err(POST_ONLY_REJECTION.to_string()));
+    }
+    if order.user_id == config::get_amm_user_id() {
+        return Ok(Err("The AMM account can't trade with itself".to_string()));
+    }
+
+    let market = load_market(db, order.event_id).await?;
+    if !market.outstanding.contains_key(&order.option_id) {
+        return Ok(Err("Option does not belong to this event".to_string()));
+    }
+
+    let price = market.quote(order.option_id, &order.side, order.quantity);
+    let within_limit = match order.side {
+        OrderSide::Buy => price <= order.price,
+        OrderSide::Sell => price >= order.price,
+    };
+    if !within_limit {
+        return Ok(Err(AMM_PRICE_REJECTION.to_string()));
+    }
+    Ok(Ok(price))
+}
+
+/// Fill an order in full against the AMM at its quoted average price, or
+/// reject it. AMM orders never rest, so there is nothing left to cancel.
+pub async fn execute(
+    db: &DatabaseConnection,
+    order: Order,
+) -> Result<Result<Vec<Trade>, String>, String> {
+    let price = match quote_order(db, &order).await? {
+        Ok(price) => price,
+        Err(rejection) => return Ok(Err(rejection)),
+    };
+
+    let amm_user_id = config::get_amm_user_id();
+    match router::house_trade(db, &order, amm_user_id, price, order.quantity).await {
+        Ok(trade) => Ok(Ok(vec![trade])),
+        Err(e) if e == reservations::INSUFFICIENT_BALANCE => {
+            Ok(Err(AMM_LIQUIDITY_REJECTION.to_string()))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// The AMM's current price for an option, rounded to the event's tick
+pub async fn marginal_price(
+    db: &DatabaseConnection,
+    event_id: i32,
+    option_id: i32,
+) -> Result<Option<Decimal>, String> {
+    let market = load_market(db, event_id).await?;
+    if !market.outstanding.contains_key(&option_id) {
+        return Ok(None);
+    }
+    let price =
+        Decimal::from_f64(market.marginal(option_id)).unwrap_or(Decimal::ZERO) * PAYOUT_PER_SHARE;
+    Ok(Some(
+        limits::align_to_tick(&market.event, price)
+            .clamp(market.event.min_price, market.event.max_price),
+    ))
+}
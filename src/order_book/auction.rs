@@ -0,0 +1,203 @@
+use super::{
+    db_persistence::DbPersistence, matching_service, position_tracker::PositionTracker,
+    redis_persistence::RedisOrderBookPersistence, risk, types::AuctionResult,
+};
+use crate::constants::config;
+use crate::handlers::order_book_handler::{broadcast_trading_status, settle_trades};
+use crate::notifications::dispatcher;
+use crate::types::websocket::{OpsEventKind, OpsSeverity, PriceUpdateSource};
+use crate::utils::cache::CacheService;
+use crate::websocket::server::WebSocketServer;
+use crate::websocket::{market_data, ops};
+use actix::Addr;
+use actix_web::web;
+use deadpool_redis::Pool;
+use sea_orm::DatabaseConnection;
+use serde_json::json;
+
+enum Uncross {
+    Halted,
+    NoAuction,
+    Uncrossed(AuctionResult),
+}
+
+/// Uncross an ended auction: execute crossing orders at the auction price, settle
+/// the trades and return the option to continuous matching
+pub async fn uncross_auction(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+    event_id: i32,
+    option_id: i32,
+) -> Result<(), String> {
+    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
+    let db_persistence = DbPersistence::new(db.get_ref().clone());
+    let position_tracker = PositionTracker::new(db.get_ref().clone());
+
+    let outcome = matching_service::execute(&redis_pool, event_id, option_id, |order_book| {
+        if order_book.current_halt().is_some() {
+            Ok(Uncross::Halted)
+        } else if order_book.auction().is_none() {
+            Ok(Uncross::NoAuction)
+        } else {
+            order_book.uncross_auction().map(Uncross::Uncrossed)
+        }
+    })
+    .await??;
+
+    let result = match outcome {
+        // Left on the schedule so it uncrosses once the halt lifts
+        Uncross::Halted => return Ok(()),
+        Uncross::NoAuction => {
+            return redis_persistence
+                .unschedule_auction(event_id, option_id)
+                .await
+        }
+        Uncross::Uncrossed(result) => result,
+    };
+
+    redis_persistence
+        .unschedule_auction(event_id, option_id)
+        .await?;
+
+    match result.price {
+        Some(price) => log::info!(
+            "Auction for event {} option {} uncrossed at {} with {} trades",
+            event_id,
+            option_id,
+            price,
+            result.trades.len()
+        ),
+        None => log::info!(
+            "Auction for event {} option {} ended without crossing orders",
+            event_id,
+            option_id
+        ),
+    }
+
+    if !result.trades.is_empty() {
+        settle_trades(
+            db.get_ref(),
+            &db_persistence,
+            &redis_persistence,
+            &position_tracker,
+            &result.trades,
+        )
+        .await
+        .map_err(|e| format!("Failed to settle auction trades: {}", e))?;
+        market_data::publish_trades(&result.trades, None);
+        dispatcher::notify_fills(
+            db.get_ref(),
+            redis_pool.get_ref(),
+            ws_server.get_ref(),
+            &result.trades,
+        );
+        risk::publish_for_trades(ws_server.get_ref(), &result.trades);
+    }
+
+    // Written after settlement so each row reflects the order's own fill state
+    for order in &result.orders {
+        if let Err(e) = db_persistence.update_order(order).await {
+            log::error!("Failed to update auction order in database: {}", e);
+        }
+        if let Err(e) = redis_persistence.save_order(order).await {
+            log::error!("Failed to update auction order in Redis: {}", e);
+        }
+    }
+
+    broadcast_trading_status(&ws_server, event_id, option_id, result.sequence, None, None);
+
+    let cache_service = CacheService::new(redis_pool.get_ref().clone());
+    let event_order_books_cache_key = format!("order_books:event:{}", event_id);
+    if let Err(e) = cache_service.delete(&event_order_books_cache_key).await {
+        log::warn!("Failed to invalidate event order books cache: {}", e);
+    }
+
+    let price_source = if result.trades.is_empty() {
+        PriceUpdateSource::BookImbalance
+    } else {
+        PriceUpdateSource::Trade
+    };
+    crate::order_book::price_updater::update_option_price_immediately(
+        db,
+        redis_pool,
+        ws_server,
+        event_id,
+        option_id,
+        price_source,
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Start a background task that uncrosses auctions once they end
+pub fn start_auction_scheduler(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+) {
+    let interval_seconds = config::get_auction_scheduler_interval_seconds();
+
+    log::info!(
+        "Starting auction scheduler with {}-second interval",
+        interval_seconds
+    );
+
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(interval_seconds));
+
+        loop {
+            interval.tick().await;
+
+            let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
+            let due = match redis_persistence.get_due_auctions().await {
+                Ok(due) => due,
+                Err(e) => {
+                    log::error!("Failed to load due auctions: {}", e);
+                    ops::publish(
+                        OpsEventKind::RedisDegraded,
+                        OpsSeverity::Critical,
+                        "Auction scheduler could not load due auctions",
+                        json!({ "error": e }),
+                    );
+                    continue;
+                }
+            };
+
+            for (event_id, option_id) in due {
+                if let Err(e) = uncross_auction(
+                    db.clone(),
+                    redis_pool.clone(),
+                    ws_server.clone(),
+                    event_id,
+                    option_id,
+                )
+                .await
+                {
+                    log::error!(
+                        "Failed to uncross auction for event {} option {}: {}",
+                        event_id,
+                        option_id,
+                        e
+                    );
+                    ops::publish(
+                        OpsEventKind::JobFailed,
+                        OpsSeverity::Critical,
+                        format!(
+                            "Auction for event {} option {} failed to uncross",
+                            event_id, option_id
+                        ),
+                        json!({
+                            "job": "auction_scheduler",
+                            "event_id": event_id,
+                            "option_id": option_id,
+                            "error": e
+                        }),
+                    );
+                }
+            }
+        }
+    });
+}
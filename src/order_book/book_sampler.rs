@@ -0,0 +1,136 @@
+use super::matching_service;
+use crate::constants::config;
+use actix_web::web;
+use chrono::Utc;
+use deadpool_redis::{redis::AsyncCommands, Pool};
+use sea_orm::prelude::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Resting quantity per price on each side of a book at one point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepthSample {
+    pub timestamp_ms: i64,
+    pub bids: Vec<(Decimal, i32)>,
+    pub asks: Vec<(Decimal, i32)>,
+}
+
+/// Sorted set of serialized samples scored by sample time in milliseconds
+fn samples_key(event_id: i32, option_id: i32) -> String {
+    format!("depth_samples:{}:{}", event_id, option_id)
+}
+
+/// Load the samples taken for a book since `from_ms`, oldest first
+pub async fn load_samples(
+    redis_pool: &Pool,
+    event_id: i32,
+    option_id: i32,
+    from_ms: i64,
+) -> Result<Vec<DepthSample>, String> {
+    let mut conn = redis_pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+
+    let samples: Vec<String> = conn
+        .zrangebyscore(samples_key(event_id, option_id), from_ms, "+inf")
+        .await
+        .map_err(|e| format!("Failed to load depth samples: {}", e))?;
+
+    Ok(samples
+        .iter()
+        .filter_map(|s| serde_json::from_str(s).ok())
+        .collect())
+}
+
+async fn record_sample(
+    redis_pool: &Pool,
+    event_id: i32,
+    option_id: i32,
+    sample: &DepthSample,
+) -> Result<(), String> {
+    let mut conn = redis_pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+
+    let key = samples_key(event_id, option_id);
+    let serialized =
+        serde_json::to_string(sample).map_err(|e| format!("Failed to serialize sample: {}", e))?;
+    let retention_seconds = config::get_book_sample_retention_hours() * 3600;
+    let cutoff_ms = sample.timestamp_ms - retention_seconds * 1000;
+
+    let _: () = deadpool_redis::redis::pipe()
+        .zadd(&key, serialized, sample.timestamp_ms)
+        .ignore()
+        .zrembyscore(&key, "-inf", cutoff_ms)
+        .ignore()
+        .expire(&key, retention_seconds)
+        .ignore()
+        .query_async(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to record depth sample: {}", e))?;
+
+    Ok(())
+}
+
+/// Sample the depth of every resident order book once
+async fn sample_books(redis_pool: &Pool) {
+    let depth = config::get_book_sample_depth();
+
+    for (event_id, option_id) in matching_service::resident_books().await {
+        let levels = match matching_service::query(redis_pool, event_id, option_id, move |book| {
+            book.get_market_depth(depth)
+        })
+        .await
+        {
+            Ok(levels) => levels,
+            Err(e) => {
+                log::warn!(
+                    "Failed to sample order book {}:{}: {}",
+                    event_id,
+                    option_id,
+                    e
+                );
+                continue;
+            }
+        };
+
+        let sample = DepthSample {
+            timestamp_ms: Utc::now().timestamp_millis(),
+            bids: levels
+                .iter()
+                .filter(|l| l.buy_quantity > 0)
+                .map(|l| (l.price, l.buy_quantity))
+                .collect(),
+            asks: levels
+                .iter()
+                .filter(|l| l.sell_quantity > 0)
+                .map(|l| (l.price, l.sell_quantity))
+                .collect(),
+        };
+
+        if let Err(e) = record_sample(redis_pool, event_id, option_id, &sample).await {
+            log::warn!("{}", e);
+        }
+    }
+}
+
+/// Start a background task that periodically samples order book depth for heatmaps
+pub fn start_book_sampler(redis_pool: web::Data<Pool>) {
+    let interval_seconds = config::get_book_sample_interval_seconds();
+
+    log::info!(
+        "Starting order book sampler with {}-second interval",
+        interval_seconds
+    );
+
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(interval_seconds));
+
+        loop {
+            interval.tick().await;
+            sample_books(redis_pool.get_ref()).await;
+        }
+    });
+}
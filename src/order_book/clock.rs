@@ -0,0 +1,27 @@
+use chrono::{DateTime, Duration, SubsecRound, Utc};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+lazy_static! {
+    /// Last trade timestamp handed out for each (event, option)
+    static ref LAST_TRADE_TIMESTAMPS: Mutex<HashMap<(i32, i32), DateTime<Utc>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Timestamp for a new trade in an option, strictly later than every one
+/// handed out for it before, even if the system clock stalls or steps back.
+/// Timestamps are truncated to microseconds, the precision trades are stored
+/// at, so the order survives a round trip through the database. Trades on
+/// different instances can still share a timestamp; their book `sequence`
+/// breaks the tie.
+pub fn trade_timestamp(event_id: i32, option_id: i32) -> DateTime<Utc> {
+    let now = Utc::now().trunc_subsecs(6);
+    let mut last = LAST_TRADE_TIMESTAMPS.lock().unwrap();
+    let timestamp = match last.get(&(event_id, option_id)) {
+        Some(previous) if now <= *previous => *previous + Duration::microseconds(1),
+        _ => now,
+    };
+    last.insert((event_id, option_id), timestamp);
+    timestamp
+}
@@ -0,0 +1,464 @@
+use super::{
+    db_persistence::DbPersistence,
+    margin::{self, PAYOUT_PER_SHARE},
+    matching_service,
+    position_tracker::PositionTracker,
+    router::{self, Execution, ExecutionVenue},
+    types::{Order, OrderBookSnapshot, OrderSide, OrderStatus, PriceLevel, TimeInForce, Trade},
+    OrderType,
+};
+use crate::constants::config;
+use crate::handlers::order_book_handler::report_circuit_breaker;
+use crate::wallets::balances;
+use deadpool_redis::Pool;
+use entity::{events, users};
+use sea_orm::{prelude::Decimal, DatabaseConnection, EntityTrait, QuerySelect};
+
+/// Price of the other option of a binary event that is equivalent to `price`,
+/// since one share of each is worth `PAYOUT_PER_SHARE` together
+pub fn complement_price(price: Decimal) -> Decimal {
+    PAYOUT_PER_SHARE - price
+}
+
+/// The other option of `order`'s event when the order can also match that
+/// option's book: the event is binary with complement pricing on, and the
+/// order is a limit order that may take liquidity and fill in part
+pub async fn complement_for(db: &DatabaseConnection, order: &Order) -> Result<Option<i32>, String> {
+    if order.order_type != OrderType::Limit
+        || order.post_only
+        || order.time_in_force == TimeInForce::FOK
+        || complement_price(order.price) <= Decimal::ZERO
+    {
+        return Ok(None);
+    }
+
+    let complement_pricing: Option<bool> = events::Entity::find_by_id(order.event_id)
+        .select_only()
+        .column(events::Column::ComplementPricing)
+        .into_tuple()
+        .one(db)
+        .await
+        .map_err(|e| format!("Failed to load complement pricing: {}", e))?;
+    if !complement_pricing.unwrap_or(false) {
+        return Ok(None);
+    }
+
+    PositionTracker::new(db.clone())
+        .complementary_option(order.event_id, order.option_id)
+        .await
+}
+
+/// Execute an order against its own book and the complementary book as one.
+///
+/// The other option's resting orders are liquidity at the complementary
+/// price: buying one option at `p` takes bids for the other at
+/// `PAYOUT_PER_SHARE - p` or better, and selling takes asks at
+/// `PAYOUT_PER_SHARE - p` or better. The order takes the best price either
+/// book offers, its own book first at equal prices, alternating between them
+/// until it fills or neither crosses. A good-till-cancelled remainder then
+/// rests on the order's own book.
+pub async fn submit(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    order: Order,
+    complement_id: i32,
+) -> Result<Execution, String> {
+    let (event_id, option_id) = (order.event_id, order.option_id);
+    let mut order = order;
+
+    let implied = implied_best(redis_pool, &order, complement_id).await?;
+    let (sweep, mut execution) = own_pass(redis_pool, &order, implied).await?;
+    let Ok(trades) = &execution.result else {
+        return Ok(execution);
+    };
+    if !sweep || execution.triggered_halt.is_some() {
+        return Ok(execution);
+    }
+    let trades = trades.clone();
+    if !record_pass(&mut order, &trades, &mut execution.self_trade_prevented) {
+        return Ok(execution);
+    }
+
+    while order.is_open() {
+        let own_best = own_best(redis_pool, &order).await?;
+        let complement_trades = fill_from_complement(
+            db,
+            redis_pool,
+            &order,
+            complement_id,
+            bounded_price(&order, own_best),
+            &mut execution,
+        )
+        .await?;
+        let complement_filled = filled_by(&order, &complement_trades);
+        record_pass(&mut order, &complement_trades, &mut Vec::new());
+        if let Ok(trades) = &mut execution.result {
+            trades.extend(complement_trades);
+        }
+        if !order.is_open() {
+            break;
+        }
+
+        let implied = implied_best(redis_pool, &order, complement_id).await?;
+        let (sweep, pass) = own_pass(redis_pool, &order, implied).await?;
+        let own_trades = pass.result.unwrap_or_default();
+        let own_filled = filled_by(&order, &own_trades);
+        let mut self_trade_prevented = pass.self_trade_prevented;
+        let open = record_pass(&mut order, &own_trades, &mut self_trade_prevented);
+        if let Ok(trades) = &mut execution.result {
+            trades.extend(own_trades);
+        }
+        execution.self_trade_prevented.extend(self_trade_prevented);
+        execution.sequence = pass.sequence;
+        execution.order = pass.order;
+        if pass.triggered_halt.is_some() {
+            execution.triggered_halt = pass.triggered_halt;
+            return Ok(execution);
+        }
+        // An auction started since the first pass took the order as it was
+        if !sweep || !open {
+            return Ok(execution);
+        }
+        if complement_filled + own_filled == 0 {
+            break;
+        }
+    }
+
+    if order.is_open() && order.time_in_force == TimeInForce::GTC {
+        let remainder = order.clone();
+        let rested = matching_service::execute(redis_pool, event_id, option_id, move |book| {
+            // A halt tripped since the first pass cancels the remainder, as it
+            // would have then
+            if let Some(halt) = book.current_halt().cloned() {
+                return (
+                    Ok(Vec::new()),
+                    Vec::new(),
+                    Some(halt),
+                    book.sequence(),
+                    None,
+                );
+            }
+            let result = book.submit_order(remainder);
+            (
+                result,
+                book.take_self_trade_prevented(),
+                book.take_triggered_halt(),
+                book.sequence(),
+                book.take_submitted(),
+            )
+        })
+        .await?;
+        let (result, self_trade_prevented, triggered_halt, sequence, submitted) = rested;
+        let submitted = match result {
+            Ok(rest_trades) => {
+                if let Ok(trades) = &mut execution.result {
+                    trades.extend(rest_trades);
+                }
+                submitted
+            }
+            Err(e) => {
+                log::warn!(
+                    "Remainder of order on event {} option {} did not rest: {}",
+                    event_id,
+                    option_id,
+                    e
+                );
+                None
+            }
+        };
+        execution.self_trade_prevented.extend(self_trade_prevented);
+        execution.triggered_halt = triggered_halt;
+        execution.sequence = sequence;
+        // A remainder that didn't rest is cancelled
+        execution.order = Some(submitted.unwrap_or_else(|| {
+            order.cancel();
+            order
+        }));
+        return Ok(execution);
+    }
+
+    // Nothing of an IOC order rests
+    if order.is_open() {
+        order.cancel();
+    }
+    execution.order = Some(order);
+    Ok(execution)
+}
+
+/// Take what the order's own book offers up to `bound`, the best price the
+/// complementary book offers, as an IOC probe. Returns whether the probe
+/// swept the book, which it doesn't while an auction collects orders: the
+/// order is then submitted as it is, to rest until the auction uncrosses.
+async fn own_pass(
+    redis_pool: &Pool,
+    order: &Order,
+    bound: Option<Decimal>,
+) -> Result<(bool, Execution), String> {
+    let mut probe = order.clone();
+    let price = bounded_price(order, bound);
+    matching_service::execute(redis_pool, order.event_id, order.option_id, move |book| {
+        let sweep = book.auction().is_none();
+        if sweep {
+            probe.time_in_force = TimeInForce::IOC;
+            probe.price = price;
+        }
+        let result = book.submit_order(probe);
+        let execution = Execution {
+            venue: ExecutionVenue::Internal,
+            result,
+            order: book.take_submitted(),
+            self_trade_prevented: book.take_self_trade_prevented(),
+            triggered_halt: book.take_triggered_halt(),
+            sequence: book.sequence(),
+        };
+        (sweep, execution)
+    })
+    .await
+}
+
+/// Apply a pass's fills of `order` to it, and what self-trade prevention did
+/// to its probe. The probe's price is put back on the order as recorded.
+/// Returns `false` if self-trade prevention cancelled the order.
+fn record_pass(order: &mut Order, trades: &[Trade], self_trade_prevented: &mut [Order]) -> bool {
+    if let Some(prevented) = self_trade_prevented.iter_mut().find(|o| o.id == order.id) {
+        prevented.price = order.price;
+        if prevented.status == OrderStatus::Cancelled {
+            return false;
+        }
+        order.quantity = prevented.quantity;
+    }
+    order.fill(filled_by(order, trades));
+    true
+}
+
+/// Shares of `order` filled by `trades`
+fn filled_by(order: &Order, trades: &[Trade]) -> i32 {
+    trades
+        .iter()
+        .filter(|t| t.buy_order_id == order.id || t.sell_order_id == order.id)
+        .map(|t| t.quantity)
+        .sum()
+}
+
+/// `order`'s price, or `bound` if that is better for the other side, so a
+/// pass on one book stops where the other book offers a better price
+fn bounded_price(order: &Order, bound: Option<Decimal>) -> Decimal {
+    match (bound, &order.side) {
+        (Some(bound), OrderSide::Buy) => order.price.min(bound),
+        (Some(bound), OrderSide::Sell) => order.price.max(bound),
+        (None, _) => order.price,
+    }
+}
+
+/// Best price `order`'s own book offers it
+async fn own_best(redis_pool: &Pool, order: &Order) -> Result<Option<Decimal>, String> {
+    let side = order.side.clone();
+    matching_service::query(
+        redis_pool,
+        order.event_id,
+        order.option_id,
+        move |book| match side {
+            OrderSide::Buy => book.get_best_ask_price(),
+            OrderSide::Sell => book.get_best_bid_price(),
+        },
+    )
+    .await
+}
+
+/// Best price the complementary book offers `order`, at the complementary
+/// price: its best bid as an ask for a buy order, its best ask as a bid for a
+/// sell order
+async fn implied_best(
+    redis_pool: &Pool,
+    order: &Order,
+    complement_id: i32,
+) -> Result<Option<Decimal>, String> {
+    let side = order.side.clone();
+    let best =
+        matching_service::query(
+            redis_pool,
+            order.event_id,
+            complement_id,
+            move |book| match side {
+                OrderSide::Buy => book.get_best_bid_price(),
+                OrderSide::Sell => book.get_best_ask_price(),
+            },
+        )
+        .await?;
+    Ok(best.map(complement_price))
+}
+
+/// Fill what is left of `order` from resting orders on the complementary
+/// book at up to `limit`, through the `COMPLEMENT_USER_ID` account.
+///
+/// Matching a buy against bids on the other option mints pairs: the house
+/// sells one share of each option and collects `PAYOUT_PER_SHARE` for the pair. Matching
+/// a sell against asks on the other option buys them back: the house buys
+/// one share of each and holds the pair until settlement. Each side gets an
+/// ordinary trade on the option it ordered, so positions and fees follow the
+/// usual settlement path. Returns no trades when the house can't fund the
+/// pairs or its orders can't be saved.
+async fn fill_from_complement(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    order: &Order,
+    complement_id: i32,
+    limit: Decimal,
+    execution: &mut Execution,
+) -> Result<Vec<Trade>, String> {
+    let house_user_id = config::get_complement_user_id();
+    if order.user_id == house_user_id {
+        return Ok(Vec::new());
+    }
+    let quantity = order.remaining_quantity();
+    let price = complement_price(limit);
+
+    let house_user = users::Entity::find_by_id(house_user_id)
+        .one(db)
+        .await
+        .map_err(|e| format!("Failed to load complement account: {}", e))?
+        .ok_or_else(|| format!("Complement account {} not found", house_user_id))?;
+    let available = balances::available_in(
+        balances::find(db, house_user_id, &order.currency)
+            .await
+            .map_err(|e| format!("Failed to load complement account wallet: {}", e))?
+            .as_ref(),
+    );
+    // Minting collateralises and redeeming pays for both shares of each pair
+    let mut fundable = available >= PAYOUT_PER_SHARE * Decimal::from(quantity);
+    if fundable && order.side == OrderSide::Buy {
+        let position_tracker = PositionTracker::new(db.clone());
+        for (option_id, sell_price) in [(complement_id, price), (order.option_id, limit)] {
+            let held = position_tracker
+                .get_user_position(house_user_id, order.event_id, option_id)
+                .await?
+                .quantity;
+            fundable &=
+                margin::validate_sell(&house_user, available, held, 0, quantity, sell_price)
+                    .is_ok();
+        }
+    }
+    if !fundable {
+        log::warn!(
+            "Complement account {} can't fund {} pairs on event {}",
+            house_user_id,
+            quantity,
+            order.event_id
+        );
+        return Ok(Vec::new());
+    }
+
+    // Both house orders are saved before anything trades, so every trade the
+    // complementary book makes has orders to settle against. The house's own
+    // side is priced at `limit`, so with the other side at `price` the two
+    // hold the `PAYOUT_PER_SHARE` a pair costs.
+    let mut house_order = Order::new(
+        house_user_id,
+        order.event_id,
+        complement_id,
+        match order.side {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        },
+        OrderType::Limit,
+        TimeInForce::IOC,
+        price,
+        quantity,
+        order.currency.clone(),
+    );
+    house_order.correlation_id = order.correlation_id.clone();
+    let mut own_house_order = router::house_order(order, house_user_id, limit, quantity);
+    let db_persistence = DbPersistence::new(db.clone());
+    if let Err(e) = db_persistence.save_order(&house_order).await {
+        log::warn!("Failed to save complement order {}: {}", house_order.id, e);
+        return Ok(Vec::new());
+    }
+    if let Err(e) = db_persistence.save_order(&own_house_order).await {
+        log::warn!(
+            "Failed to save complement order {}: {}",
+            own_house_order.id,
+            e
+        );
+        close_house_order(&db_persistence, &mut house_order, 0).await;
+        return Ok(Vec::new());
+    }
+
+    let submitted = house_order.clone();
+    let executed =
+        matching_service::execute(redis_pool, order.event_id, complement_id, move |book| {
+            (
+                book.submit_order(submitted),
+                book.take_self_trade_prevented(),
+                book.take_triggered_halt(),
+            )
+        })
+        .await;
+    let (result, self_trade_prevented, triggered_halt) = match executed {
+        Ok(executed) => executed,
+        Err(e) => {
+            close_house_order(&db_persistence, &mut house_order, 0).await;
+            close_house_order(&db_persistence, &mut own_house_order, 0).await;
+            return Err(e);
+        }
+    };
+    execution.self_trade_prevented.extend(self_trade_prevented);
+    if let Some(halt) = triggered_halt {
+        report_circuit_breaker(order.event_id, complement_id, &halt);
+    }
+    // A halted book or one in auction has no liquidity to offer
+    let complement_trades = result.unwrap_or_default();
+    let filled: i32 = complement_trades.iter().map(|t| t.quantity).sum();
+
+    // The house orders are cut down to what they filled, which settlement
+    // marks as filled, releasing the rest of their holds
+    close_house_order(&db_persistence, &mut house_order, filled).await;
+    close_house_order(&db_persistence, &mut own_house_order, filled).await;
+
+    let mut trades = Vec::with_capacity(complement_trades.len() * 2);
+    for complement_trade in complement_trades {
+        let trade = router::trade_with_house(
+            order,
+            &own_house_order,
+            complement_price(complement_trade.price),
+            complement_trade.quantity,
+        );
+        trades.push(complement_trade);
+        trades.push(trade);
+    }
+    Ok(trades)
+}
+
+/// Reduce a saved house order to the `filled` shares it traded, cancelling
+/// it if it traded none. Its trades settle whether or not this is recorded,
+/// so a failure only leaves the unfilled part of its hold in place.
+async fn close_house_order(db_persistence: &DbPersistence, house_order: &mut Order, filled: i32) {
+    let result = if filled == 0 {
+        house_order.cancel();
+        db_persistence.update_order(house_order).await
+    } else {
+        house_order.quantity = filled;
+        db_persistence.amend_order(house_order).await
+    };
+    if let Err(e) = result {
+        log::error!(
+            "Failed to release the unfilled part of complement order {}: {}",
+            house_order.id,
+            e
+        );
+    }
+}
+
+/// The other option's book as liquidity for this one: its bids become asks
+/// and its asks become bids at the complementary price
+pub fn implied_levels(snapshot: &OrderBookSnapshot) -> (Vec<PriceLevel>, Vec<PriceLevel>) {
+    let mirror = |levels: &[PriceLevel]| {
+        levels
+            .iter()
+            .map(|level| PriceLevel {
+                price: complement_price(level.price),
+                ..level.clone()
+            })
+            .collect()
+    };
+    (mirror(&snapshot.asks), mirror(&snapshot.bids))
+}
@@ -0,0 +1,140 @@
+use crate::types::event_option::{ShareConservationReport, UntradedHolding};
+use crate::types::websocket::{OpsEventKind, OpsSeverity};
+use crate::websocket::ops;
+use entity::{event_options, trades, user_positions};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QuerySelect,
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Check that an option's positions add up to the shares issued for it.
+/// Every trade moves shares from seller to buyer, so positions net to the
+/// issued shares unless the matching or position pipeline created or
+/// destroyed some. Accounts whose position their trades don't explain are
+/// listed to help find where.
+pub async fn audit<C: ConnectionTrait>(
+    conn: &C,
+    option: &event_options::Model,
+) -> Result<ShareConservationReport, String> {
+    let positions: Vec<(i32, i32)> = user_positions::Entity::find()
+        .select_only()
+        .column(user_positions::Column::UserId)
+        .column(user_positions::Column::Quantity)
+        .filter(user_positions::Column::OptionId.eq(option.id))
+        .into_tuple()
+        .all(conn)
+        .await
+        .map_err(|e| format!("Failed to load positions: {}", e))?;
+
+    let bought = traded_by(conn, option.id, trades::Column::BuyerId).await?;
+    let sold = traded_by(conn, option.id, trades::Column::SellerId).await?;
+
+    let (trade_count, traded_shares): (i64, i64) = trades::Entity::find()
+        .select_only()
+        .column_as(Expr::cust("COUNT(*)"), "trades")
+        .column_as(
+            Expr::cust("COALESCE(SUM(trades.quantity), 0)::BIGINT"),
+            "shares",
+        )
+        .filter(trades::Column::OptionId.eq(option.id))
+        .into_tuple()
+        .one(conn)
+        .await
+        .map_err(|e| format!("Failed to count trades: {}", e))?
+        .unwrap_or((0, 0));
+
+    // Positions and trade totals per account, including accounts that
+    // traded but no longer have a position row
+    let mut accounts: BTreeMap<i32, (i32, i64, i64)> = BTreeMap::new();
+    for (user_id, quantity) in &positions {
+        accounts.entry(*user_id).or_default().0 += quantity;
+    }
+    for (user_id, shares) in &bought {
+        accounts.entry(*user_id).or_default().1 += shares;
+    }
+    for (user_id, shares) in &sold {
+        accounts.entry(*user_id).or_default().2 += shares;
+    }
+
+    let untraded_holdings: Vec<UntradedHolding> = accounts
+        .into_iter()
+        .map(|(user_id, (position, bought, sold))| UntradedHolding {
+            user_id,
+            position,
+            bought,
+            sold,
+            untraded: i64::from(position) - (bought - sold),
+        })
+        .filter(|holding| holding.untraded != 0)
+        .collect();
+
+    let total_long: i64 = positions
+        .iter()
+        .map(|(_, quantity)| i64::from((*quantity).max(0)))
+        .sum();
+    let total_short: i64 = positions
+        .iter()
+        .map(|(_, quantity)| i64::from((-*quantity).max(0)))
+        .sum();
+    let net_position = total_long - total_short;
+    let shares_issued = i64::from(option.shares_issued);
+    let discrepancy = net_position - shares_issued;
+
+    if discrepancy != 0 {
+        let message = format!(
+            "Option {} positions net to {} shares but {} were issued",
+            option.id, net_position, shares_issued
+        );
+        log::warn!("{}", message);
+        ops::publish(
+            OpsEventKind::ShareConservationDiscrepancy,
+            OpsSeverity::Critical,
+            message,
+            json!({
+                "event_id": option.event_id,
+                "option_id": option.id,
+                "shares_issued": shares_issued,
+                "net_position": net_position,
+                "discrepancy": discrepancy,
+                "untraded_accounts": untraded_holdings.len()
+            }),
+        );
+    }
+
+    Ok(ShareConservationReport {
+        event_id: option.event_id,
+        option_id: option.id,
+        shares_issued,
+        total_long,
+        total_short,
+        net_position,
+        discrepancy,
+        balanced: discrepancy == 0,
+        trades: trade_count,
+        traded_shares,
+        untraded_holdings,
+    })
+}
+
+/// Shares of an option traded per account on one side, given by the buyer
+/// or seller column
+async fn traded_by<C: ConnectionTrait>(
+    conn: &C,
+    option_id: i32,
+    side: trades::Column,
+) -> Result<Vec<(i32, i64)>, String> {
+    trades::Entity::find()
+        .select_only()
+        .column(side)
+        .column_as(
+            Expr::cust("COALESCE(SUM(trades.quantity), 0)::BIGINT"),
+            "shares",
+        )
+        .filter(trades::Column::OptionId.eq(option_id))
+        .group_by(side)
+        .into_tuple()
+        .all(conn)
+        .await
+        .map_err(|e| format!("Failed to sum traded shares: {}", e))
+}
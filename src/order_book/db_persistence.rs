@@ -1,8 +1,17 @@
-use super::types::{Order, OrderSide, OrderStatus, OrderType, TimeInForce, Trade};
-use entity::{orders, trades};
+use super::fees::{FeeWaiver, TradeFees};
+use super::reservations;
+use super::types::{
+    Order, OrderEvent, OrderEventKind, OrderSide, OrderStatus, OrderType, SelfTradePrevention,
+    TimeInForce, Trade,
+};
+use crate::promotions::service::PromotionService;
+use crate::tenants::registry;
+use crate::types::order_book::settlement_status;
+use crate::websocket::order_updates;
+use entity::{order_events, orders, trades};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
-    QuerySelect, Set, TransactionTrait,
+    prelude::Decimal, ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
 };
 
 pub struct DbPersistence {
@@ -14,8 +23,15 @@ impl DbPersistence {
         Self { db }
     }
 
-    /// Save an order to the database
+    /// Save an order to the database along with its `created` event, holding
+    /// the funds a buy order needs. Fails with `reservations::INSUFFICIENT_BALANCE`
+    /// if the user's available balance can't cover them.
     pub async fn save_order(&self, order: &Order) -> Result<(), String> {
+        // Orders belong to the tenant whose event they trade
+        let tenant_id = registry::resolve_event_tenant(&self.db, order.event_id)
+            .await?
+            .ok_or_else(|| format!("Event {} not found", order.event_id))?;
+
         let new_order = orders::ActiveModel {
             id: Set(order.id.clone()),
             user_id: Set(order.user_id),
@@ -28,41 +44,153 @@ impl DbPersistence {
             quantity: Set(order.quantity),
             filled_quantity: Set(order.filled_quantity),
             status: Set(order.status.to_string()),
+            client_order_id: Set(order.client_order_id.clone()),
+            currency: Set(order.currency.clone()),
+            tenant_id: Set(tenant_id),
             created_at: Set(order.created_at.into()),
             updated_at: Set(order.updated_at.into()),
+            correlation_id: Set(order.correlation_id.clone()),
+            hold_price: Set(order.hold_price),
+            short_quantity: Set(order.short_quantity),
         };
 
-        new_order
-            .insert(&self.db)
+        let txn = self.begin().await?;
+        let saved = new_order
+            .insert(&txn)
             .await
             .map_err(|e| format!("Failed to save order to database: {}", e))?;
-
+        // Dropping the transaction on failure rolls the order back with the hold
+        reservations::hold(
+            &txn,
+            saved.user_id,
+            &saved.currency,
+            reservations::reservation_for(&saved),
+        )
+        .await?;
+        record_order_event(&txn, &saved, OrderEventKind::Created, None).await?;
+        commit(txn).await?;
+        order_updates::publish(order_from_model(saved), OrderEventKind::Created, None);
         Ok(())
     }
 
-    /// Update order status and filled quantity
+    /// Update order status and filled quantity, recording the transition if
+    /// either changed
     pub async fn update_order(&self, order: &Order) -> Result<(), String> {
-        let db_order = orders::Entity::find_by_id(order.id.clone())
-            .one(&self.db)
-            .await
-            .map_err(|e| format!("Failed to find order: {}", e))?
-            .ok_or("Order not found")?;
+        let txn = self.begin().await?;
+        let db_order = find_order(&txn, &order.id).await?;
+        let unchanged = db_order.status == order.status.to_string()
+            && db_order.filled_quantity == order.filled_quantity;
 
-        let mut active_order: orders::ActiveModel = db_order.into();
+        let mut active_order: orders::ActiveModel = db_order.clone().into();
         active_order.filled_quantity = Set(order.filled_quantity);
         active_order.status = Set(order.status.to_string());
         active_order.updated_at = Set(order.updated_at.into());
 
-        active_order
-            .update(&self.db)
+        let updated = active_order
+            .update(&txn)
             .await
             .map_err(|e| format!("Failed to update order: {}", e))?;
+        if unchanged {
+            return commit(txn).await;
+        }
+        reservations::rebalance(&txn, &db_order, &updated).await?;
+        let kind = OrderEventKind::for_status(&order.status);
+        record_order_event(&txn, &updated, kind, None).await?;
+        commit(txn).await?;
+        order_updates::publish(order_from_model(updated), kind, None);
+        Ok(())
+    }
+
+    /// Update price and quantity of an amended order along with its fill
+    /// state. A larger hold fails with `reservations::INSUFFICIENT_BALANCE`
+    /// when the balance can't cover it, leaving the order as it was.
+    pub async fn amend_order(&self, order: &Order) -> Result<(), String> {
+        let txn = self.begin().await?;
+        let db_order = find_order(&txn, &order.id).await?;
 
+        let mut active_order: orders::ActiveModel = db_order.clone().into();
+        active_order.price = Set(order.price);
+        active_order.quantity = Set(order.quantity);
+        active_order.filled_quantity = Set(order.filled_quantity);
+        active_order.status = Set(order.status.to_string());
+        active_order.updated_at = Set(order.updated_at.into());
+
+        let updated = active_order
+            .update(&txn)
+            .await
+            .map_err(|e| format!("Failed to amend order: {}", e))?;
+        reservations::rebalance_checked(&txn, &db_order, &updated).await?;
+
+        // Self-trade prevention amends orders too, and may close them outright
+        let kind = match order.status {
+            OrderStatus::Pending | OrderStatus::PartiallyFilled => OrderEventKind::Amended,
+            ref status => OrderEventKind::for_status(status),
+        };
+        record_order_event(&txn, &updated, kind, None).await?;
+        commit(txn).await?;
+        order_updates::publish(order_from_model(updated), kind, None);
         Ok(())
     }
 
-    /// Save a trade to the database
-    pub async fn save_trade(&self, trade: &Trade) -> Result<(), String> {
+    /// Get an order by id
+    pub async fn get_order(&self, order_id: &str) -> Result<Option<Order>, String> {
+        let db_order = orders::Entity::find_by_id(order_id.to_string())
+            .one(&self.db)
+            .await
+            .map_err(|e| format!("Failed to get order: {}", e))?;
+
+        Ok(db_order.map(order_from_model))
+    }
+
+    /// Get a user's order by the client order id they gave it
+    pub async fn get_order_by_client_id(
+        &self,
+        user_id: i32,
+        client_order_id: &str,
+    ) -> Result<Option<Order>, String> {
+        let db_order = orders::Entity::find()
+            .filter(orders::Column::UserId.eq(user_id))
+            .filter(orders::Column::ClientOrderId.eq(client_order_id))
+            .one(&self.db)
+            .await
+            .map_err(|e| format!("Failed to get order: {}", e))?;
+
+        Ok(db_order.map(order_from_model))
+    }
+
+    /// Get the history of an order, oldest first
+    pub async fn get_order_events(&self, order_id: &str) -> Result<Vec<OrderEvent>, String> {
+        let events = order_events::Entity::find()
+            .filter(order_events::Column::OrderId.eq(order_id))
+            .order_by_asc(order_events::Column::Id)
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to get order events: {}", e))?;
+
+        Ok(events
+            .into_iter()
+            .map(|e| OrderEvent {
+                id: e.id,
+                order_id: e.order_id,
+                kind: parse_order_event_kind(&e.kind),
+                status: parse_order_status(&e.status),
+                price: e.price,
+                quantity: e.quantity,
+                filled_quantity: e.filled_quantity,
+                trade_id: e.trade_id,
+                timestamp: e.created_at.into(),
+            })
+            .collect())
+    }
+
+    /// Save a trade to the database, pending settlement, along with the usage
+    /// of any promotion that waived its fees
+    pub async fn save_trade(
+        &self,
+        trade: &Trade,
+        fees: &TradeFees,
+        waiver: Option<&FeeWaiver>,
+    ) -> Result<(), String> {
         let new_trade = trades::ActiveModel {
             id: Set(trade.id.clone()),
             event_id: Set(trade.event_id),
@@ -74,15 +202,47 @@ impl DbPersistence {
             price: Set(trade.price),
             quantity: Set(trade.quantity),
             total_amount: Set(trade.total_amount),
+            currency: Set(trade.currency.clone()),
             timestamp: Set(trade.timestamp.into()),
+            sequence: Set(trade.sequence as i64),
+            buyer_fee: Set(fees.buyer_fee),
+            seller_fee: Set(fees.seller_fee),
+            settlement_status: Set(settlement_status::PENDING.to_string()),
+            settlement_attempts: Set(0),
+            settlement_error: Set(None),
+            buyer_position_before: Set(None),
+            seller_position_before: Set(None),
+            settled_at: Set(None),
+            buy_correlation_id: Set(trade.buy_correlation_id.clone()),
+            sell_correlation_id: Set(trade.sell_correlation_id.clone()),
         };
 
+        let txn = self.begin().await?;
         new_trade
-            .insert(&self.db)
+            .insert(&txn)
             .await
             .map_err(|e| format!("Failed to save trade to database: {}", e))?;
 
-        Ok(())
+        if let Some(waiver) = waiver {
+            for (user_id, waived) in [
+                (trade.buyer_id, waiver.waived.buyer_fee),
+                (trade.seller_id, waiver.waived.seller_fee),
+            ] {
+                if waived <= Decimal::ZERO {
+                    continue;
+                }
+                PromotionService::record_usage(
+                    &txn,
+                    waiver.promotion_id,
+                    user_id,
+                    waived,
+                    &trade.id,
+                )
+                .await?;
+            }
+        }
+
+        commit(txn).await
     }
 
     /// Get user's orders
@@ -105,24 +265,50 @@ impl DbPersistence {
             .await
             .map_err(|e| format!("Failed to get user orders: {}", e))?;
 
-        Ok(db_orders
-            .into_iter()
-            .map(|o| Order {
-                id: o.id,
-                user_id: o.user_id,
-                event_id: o.event_id,
-                option_id: o.option_id,
-                side: parse_order_side(&o.side),
-                order_type: parse_order_type(&o.order_type),
-                time_in_force: parse_time_in_force(&o.time_in_force),
-                price: o.price,
-                quantity: o.quantity,
-                filled_quantity: o.filled_quantity,
-                status: parse_order_status(&o.status),
-                created_at: o.created_at.into(),
-                updated_at: o.updated_at.into(),
-            })
-            .collect())
+        Ok(db_orders.into_iter().map(order_from_model).collect())
+    }
+
+    /// A user's orders that are still resting or partially filled, newest first
+    pub async fn get_open_orders(&self, user_id: i32) -> Result<Vec<Order>, String> {
+        let db_orders = orders::Entity::find()
+            .filter(orders::Column::UserId.eq(user_id))
+            .filter(orders::Column::Status.is_in([
+                OrderStatus::Pending.to_string(),
+                OrderStatus::PartiallyFilled.to_string(),
+            ]))
+            .order_by_desc(orders::Column::CreatedAt)
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to get open orders: {}", e))?;
+
+        Ok(db_orders.into_iter().map(order_from_model).collect())
+    }
+
+    /// Orders placed by the request with `correlation_id`, oldest first
+    pub async fn get_correlated_orders(&self, correlation_id: &str) -> Result<Vec<Order>, String> {
+        let db_orders = orders::Entity::find()
+            .filter(orders::Column::CorrelationId.eq(correlation_id))
+            .order_by_asc(orders::Column::CreatedAt)
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to get correlated orders: {}", e))?;
+
+        Ok(db_orders.into_iter().map(order_from_model).collect())
+    }
+
+    /// Orders in an event that are still resting or partially filled
+    pub async fn get_event_open_orders(&self, event_id: i32) -> Result<Vec<Order>, String> {
+        let db_orders = orders::Entity::find()
+            .filter(orders::Column::EventId.eq(event_id))
+            .filter(orders::Column::Status.is_in([
+                OrderStatus::Pending.to_string(),
+                OrderStatus::PartiallyFilled.to_string(),
+            ]))
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to get open orders: {}", e))?;
+
+        Ok(db_orders.into_iter().map(order_from_model).collect())
     }
 
     /// Get trades for an event option
@@ -136,27 +322,13 @@ impl DbPersistence {
             .filter(trades::Column::EventId.eq(event_id))
             .filter(trades::Column::OptionId.eq(option_id))
             .order_by_desc(trades::Column::Timestamp)
+            .order_by_desc(trades::Column::Sequence)
             .limit(limit)
             .all(&self.db)
             .await
             .map_err(|e| format!("Failed to get trades: {}", e))?;
 
-        Ok(db_trades
-            .into_iter()
-            .map(|t| Trade {
-                id: t.id,
-                event_id: t.event_id,
-                option_id: t.option_id,
-                buyer_id: t.buyer_id,
-                seller_id: t.seller_id,
-                buy_order_id: t.buy_order_id,
-                sell_order_id: t.sell_order_id,
-                price: t.price,
-                quantity: t.quantity,
-                total_amount: t.total_amount,
-                timestamp: t.timestamp.into(),
-            })
-            .collect())
+        Ok(db_trades.into_iter().map(trade_from_model).collect())
     }
 
     /// Get user's trades
@@ -169,27 +341,20 @@ impl DbPersistence {
                     .or(trades::Column::SellerId.eq(user_id)),
             )
             .order_by_desc(trades::Column::Timestamp)
+            .order_by_desc(trades::Column::Sequence)
             .limit(limit)
             .all(&self.db)
             .await
             .map_err(|e| format!("Failed to get user trades: {}", e))?;
 
-        Ok(db_trades
-            .into_iter()
-            .map(|t| Trade {
-                id: t.id,
-                event_id: t.event_id,
-                option_id: t.option_id,
-                buyer_id: t.buyer_id,
-                seller_id: t.seller_id,
-                buy_order_id: t.buy_order_id,
-                sell_order_id: t.sell_order_id,
-                price: t.price,
-                quantity: t.quantity,
-                total_amount: t.total_amount,
-                timestamp: t.timestamp.into(),
-            })
-            .collect())
+        Ok(db_trades.into_iter().map(trade_from_model).collect())
+    }
+
+    async fn begin(&self) -> Result<DatabaseTransaction, String> {
+        self.db
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to begin transaction: {}", e))
     }
 
     /// Execute a batch of database operations in a transaction
@@ -225,6 +390,132 @@ impl DbPersistence {
     }
 }
 
+async fn commit(txn: DatabaseTransaction) -> Result<(), String> {
+    txn.commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))
+}
+
+async fn find_order<C: ConnectionTrait>(conn: &C, order_id: &str) -> Result<orders::Model, String> {
+    orders::Entity::find_by_id(order_id.to_string())
+        .one(conn)
+        .await
+        .map_err(|e| format!("Failed to find order: {}", e))?
+        .ok_or_else(|| "Order not found".to_string())
+}
+
+/// Add a trade's quantity to an order's fills and record the resulting
+/// partial or complete fill. Returns the order as written, to `publish_fill`
+/// once `conn` commits.
+pub async fn apply_fill<C: ConnectionTrait>(
+    conn: &C,
+    order_id: &str,
+    quantity: i32,
+    trade_id: &str,
+) -> Result<(orders::Model, OrderEventKind), String> {
+    let db_order = find_order(conn, order_id).await?;
+
+    let filled_quantity = db_order.filled_quantity + quantity;
+    let status = if filled_quantity >= db_order.quantity {
+        OrderStatus::Filled
+    } else {
+        OrderStatus::PartiallyFilled
+    };
+
+    let mut active_order: orders::ActiveModel = db_order.clone().into();
+    active_order.filled_quantity = Set(filled_quantity);
+    active_order.status = Set(status.to_string());
+    active_order.updated_at = Set(chrono::Utc::now().into());
+
+    let updated = active_order
+        .update(conn)
+        .await
+        .map_err(|e| format!("Failed to record fill: {}", e))?;
+    // The filled part of a buy order's hold becomes the debit made at settlement
+    reservations::rebalance(conn, &db_order, &updated).await?;
+    let kind = OrderEventKind::for_status(&status);
+    record_order_event(conn, &updated, kind, Some(trade_id.to_string())).await?;
+    Ok((updated, kind))
+}
+
+/// Announce a fill written by `apply_fill`
+pub fn publish_fill(order: orders::Model, kind: OrderEventKind, trade_id: &str) {
+    order_updates::publish(order_from_model(order), kind, Some(trade_id.to_string()));
+}
+
+/// Append an event capturing `order` as just written; callers pass the
+/// transaction that wrote it so the two are committed together
+async fn record_order_event<C: ConnectionTrait>(
+    conn: &C,
+    order: &orders::Model,
+    kind: OrderEventKind,
+    trade_id: Option<String>,
+) -> Result<(), String> {
+    let event = order_events::ActiveModel {
+        order_id: Set(order.id.clone()),
+        kind: Set(kind.to_string()),
+        status: Set(order.status.clone()),
+        price: Set(order.price),
+        quantity: Set(order.quantity),
+        filled_quantity: Set(order.filled_quantity),
+        trade_id: Set(trade_id),
+        created_at: Set(order.updated_at),
+        ..Default::default()
+    };
+
+    event
+        .insert(conn)
+        .await
+        .map_err(|e| format!("Failed to record order event: {}", e))?;
+
+    Ok(())
+}
+
+pub fn trade_from_model(t: trades::Model) -> Trade {
+    Trade {
+        id: t.id,
+        event_id: t.event_id,
+        option_id: t.option_id,
+        buyer_id: t.buyer_id,
+        seller_id: t.seller_id,
+        buy_order_id: t.buy_order_id,
+        sell_order_id: t.sell_order_id,
+        price: t.price,
+        quantity: t.quantity,
+        total_amount: t.total_amount,
+        timestamp: t.timestamp.into(),
+        sequence: t.sequence as u64,
+        currency: t.currency,
+        buy_correlation_id: t.buy_correlation_id,
+        sell_correlation_id: t.sell_correlation_id,
+    }
+}
+
+fn order_from_model(o: orders::Model) -> Order {
+    Order {
+        id: o.id,
+        user_id: o.user_id,
+        event_id: o.event_id,
+        option_id: o.option_id,
+        side: parse_order_side(&o.side),
+        order_type: parse_order_type(&o.order_type),
+        time_in_force: parse_time_in_force(&o.time_in_force),
+        price: o.price,
+        quantity: o.quantity,
+        filled_quantity: o.filled_quantity,
+        status: parse_order_status(&o.status),
+        post_only: false,
+        self_trade_prevention: SelfTradePrevention::default(),
+        client_order_id: o.client_order_id,
+        currency: o.currency,
+        correlation_id: o.correlation_id,
+        hold_price: o.hold_price,
+        short_quantity: o.short_quantity,
+        created_at: o.created_at.into(),
+        updated_at: o.updated_at.into(),
+    }
+}
+
 // Helper functions to parse enums from strings
 fn parse_order_side(s: &str) -> OrderSide {
     match s {
@@ -262,6 +553,18 @@ fn parse_order_status(s: &str) -> OrderStatus {
     }
 }
 
+fn parse_order_event_kind(s: &str) -> OrderEventKind {
+    match s {
+        "created" => OrderEventKind::Created,
+        "amended" => OrderEventKind::Amended,
+        "partially_filled" => OrderEventKind::PartiallyFilled,
+        "filled" => OrderEventKind::Filled,
+        "cancelled" => OrderEventKind::Cancelled,
+        "rejected" => OrderEventKind::Rejected,
+        _ => OrderEventKind::Amended, // Default
+    }
+}
+
 // Extension methods for enum to string conversion
 impl std::fmt::Display for OrderSide {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -291,6 +594,17 @@ impl std::fmt::Display for TimeInForce {
     }
 }
 
+impl std::fmt::Display for SelfTradePrevention {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelfTradePrevention::CancelNewest => write!(f, "CancelNewest"),
+            SelfTradePrevention::CancelOldest => write!(f, "CancelOldest"),
+            SelfTradePrevention::CancelBoth => write!(f, "CancelBoth"),
+            SelfTradePrevention::Decrement => write!(f, "Decrement"),
+        }
+    }
+}
+
 impl std::fmt::Display for OrderStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -302,3 +616,16 @@ impl std::fmt::Display for OrderStatus {
         }
     }
 }
+
+impl std::fmt::Display for OrderEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderEventKind::Created => write!(f, "created"),
+            OrderEventKind::Amended => write!(f, "amended"),
+            OrderEventKind::PartiallyFilled => write!(f, "partially_filled"),
+            OrderEventKind::Filled => write!(f, "filled"),
+            OrderEventKind::Cancelled => write!(f, "cancelled"),
+            OrderEventKind::Rejected => write!(f, "rejected"),
+        }
+    }
+}
@@ -1,23 +1,79 @@
+use super::clock;
 use super::types::{
-    MarketDepth, Order, OrderBookSnapshot, OrderSide, OrderStatus, OrderType, PriceLevel,
-    TimeInForce, Trade,
+    AuctionResult, AuctionState, BookJournalEntry, LevelChange, MarketDepth, Order,
+    OrderBookSnapshot, OrderSide, OrderStatus, OrderType, PriceLevel, Quote, SelfTradePrevention,
+    TimeInForce, Trade, TradingHalt,
 };
-use chrono::Utc;
+use crate::constants::config;
+use chrono::{DateTime, Duration, Utc};
 use sea_orm::prelude::Decimal;
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+/// Rejection reason returned when a post-only order would take liquidity
+pub const POST_ONLY_REJECTION: &str = "post_only_would_take_liquidity";
+
+/// Rejection reason prefix for orders stopped by self-trade prevention, followed by the policy
+pub const SELF_TRADE_REJECTION: &str = "self_trade_prevented";
+
+/// Rejection reason returned when an order arrives while trading in the option is halted
+pub const TRADING_HALTED_REJECTION: &str = "trading_halted";
+
+/// Rejection reason for market, IOC and FOK orders, which can't rest during an auction
+pub const AUCTION_REJECTION: &str = "auction_in_progress";
+
+/// Rejection reason for an order in a different currency from the orders resting on the book
+pub const CURRENCY_REJECTION: &str = "currency_mismatch";
+
+/// Rejection reason for a fill-or-kill order the book can't fill in full
+pub const FILL_OR_KILL_REJECTION: &str = "fill_or_kill_unfillable";
+
+/// Resting quantity and order count at a price level
+#[derive(Debug, Clone, Copy, Default)]
+struct LevelTotal {
+    quantity: i32,
+    orders: usize,
+}
 
 #[derive(Clone)]
 pub struct OrderBookEngine {
     event_id: i32,
     option_id: i32,
+    // Currency the book trades in, fixed by the first order to rest on it
+    currency: Option<String>,
     // Price -> Orders at that price level (buy orders sorted desc, sell orders sorted asc)
     buy_orders: BTreeMap<Decimal, VecDeque<Order>>,
     sell_orders: BTreeMap<Decimal, VecDeque<Order>>,
+    // Totals of each price level, kept up to date as levels change so depth
+    // reads don't walk the resting orders
+    bid_totals: BTreeMap<Decimal, LevelTotal>,
+    ask_totals: BTreeMap<Decimal, LevelTotal>,
     // Order ID -> Order for quick lookup
     orders_map: HashMap<String, Order>,
     // Recent trades
     trades: VecDeque<Trade>,
+    // Trades executed since the book was loaded
+    trades_executed: u64,
     last_trade_price: Option<Decimal>,
+    // Orders cancelled or decremented by self-trade prevention during the last match
+    self_trade_prevented: Vec<Order>,
+    // Incoming order as the last submission left it
+    submitted: Option<Order>,
+    // Circuit breaker: price at the start of the current window and when that window opened
+    reference_price: Option<Decimal>,
+    reference_set_at: Option<DateTime<Utc>>,
+    circuit_breaker_threshold: Decimal,
+    circuit_breaker_window: Duration,
+    circuit_breaker_halt: Duration,
+    halt: Option<TradingHalt>,
+    // Halt tripped by the circuit breaker during the last match
+    triggered_halt: Option<TradingHalt>,
+    auction: Option<AuctionState>,
+    // Price levels changed since the last journal entry was taken
+    changed_bids: BTreeSet<Decimal>,
+    changed_asks: BTreeSet<Decimal>,
+    // Advanced once per command that changes the book; see `begin_command`
+    sequence: u64,
+    sequence_advanced: bool,
 }
 
 impl OrderBookEngine {
@@ -25,20 +81,62 @@ impl OrderBookEngine {
         Self {
             event_id,
             option_id,
+            currency: None,
             buy_orders: BTreeMap::new(),
             sell_orders: BTreeMap::new(),
+            bid_totals: BTreeMap::new(),
+            ask_totals: BTreeMap::new(),
             orders_map: HashMap::new(),
             trades: VecDeque::with_capacity(1000),
+            trades_executed: 0,
             last_trade_price: None,
+            self_trade_prevented: Vec::new(),
+            submitted: None,
+            reference_price: None,
+            reference_set_at: None,
+            circuit_breaker_threshold: Decimal::from(
+                config::get_circuit_breaker_threshold_percent(),
+            ),
+            circuit_breaker_window: Duration::seconds(config::get_circuit_breaker_window_seconds()),
+            circuit_breaker_halt: Duration::seconds(config::get_circuit_breaker_halt_seconds()),
+            halt: None,
+            triggered_halt: None,
+            auction: None,
+            changed_bids: BTreeSet::new(),
+            changed_asks: BTreeSet::new(),
+            sequence: 0,
+            sequence_advanced: false,
         }
     }
 
-    /// Submit a new order to the order book
+    /// Submit a new order to the order book. The order as it left the book,
+    /// resting, filled, cancelled or rejected, is kept for `take_submitted`.
     pub fn submit_order(&mut self, mut order: Order) -> Result<Vec<Trade>, String> {
+        let result = self.place_order(&mut order);
+        self.submitted = Some(order);
+        result
+    }
+
+    fn place_order(&mut self, order: &mut Order) -> Result<Vec<Trade>, String> {
         if order.event_id != self.event_id || order.option_id != self.option_id {
             return Err("Order doesn't match this order book".to_string());
         }
 
+        if self
+            .currency
+            .as_ref()
+            .is_some_and(|currency| *currency != order.currency)
+        {
+            order.reject();
+            return Err(CURRENCY_REJECTION.to_string());
+        }
+
+        if self.current_halt().is_some() {
+            order.reject();
+            return Err(TRADING_HALTED_REJECTION.to_string());
+        }
+        self.roll_reference_price();
+
         if order.quantity <= 0 {
             return Err("Order quantity must be positive".to_string());
         }
@@ -47,29 +145,63 @@ impl OrderBookEngine {
             return Err("Order price must be positive".to_string());
         }
 
-        // For Fill-Or-Kill orders, check if we can fill the entire order
-        if order.time_in_force == TimeInForce::FOK && !self.can_fill_entire_order(&order) {
-            order.reject();
+        // During an auction orders accumulate on the book and only trade when it uncrosses
+        if self.auction.is_some() {
+            if order.order_type == OrderType::Market || order.time_in_force != TimeInForce::GTC {
+                order.reject();
+                return Err(AUCTION_REJECTION.to_string());
+            }
+            self.add_order_to_book(order.clone());
             return Ok(vec![]);
         }
 
+        // Post-only orders must rest on the book, so reject any that would cross the spread
+        if order.post_only {
+            if order.order_type == OrderType::Market {
+                return Err("Post-only is only supported for limit orders".to_string());
+            }
+            if self.would_take_liquidity(order) {
+                order.reject();
+                return Err(POST_ONLY_REJECTION.to_string());
+            }
+        }
+
+        // For Fill-Or-Kill orders, check if we can fill the entire order before any of it trades
+        if order.time_in_force == TimeInForce::FOK {
+            match self.can_fill_entire_order(order) {
+                Ok(true) => {}
+                Ok(false) => {
+                    order.reject();
+                    return Err(FILL_OR_KILL_REJECTION.to_string());
+                }
+                Err(e) => {
+                    order.reject();
+                    return Err(e);
+                }
+            }
+        }
+
         // Market orders get the best available price
         let trades = if order.order_type == OrderType::Market {
-            self.execute_market_order(&mut order)?
+            self.execute_market_order(order)?
         } else {
             // Try to match limit orders immediately
-            self.match_order(&mut order)?
+            self.match_order(order)?
         };
 
         // Handle post-match logic based on time in force
         match order.time_in_force {
             TimeInForce::FOK => {
-                // For FOK orders, if not fully filled, reject and reverse trades
+                // The pre-check should rule out a partial fill, but trades that
+                // executed stand, so only the unfilled remainder is cancelled
                 if !order.is_filled() {
-                    // In a real system, we would need to reverse the trades
-                    // For now, this shouldn't happen as we pre-checked
-                    order.reject();
-                    return Err("FOK order could not be fully filled".to_string());
+                    log::error!(
+                        "FOK order {} filled {} of {} shares despite its pre-check",
+                        order.id,
+                        order.filled_quantity,
+                        order.quantity
+                    );
+                    order.cancel();
                 }
             }
             TimeInForce::IOC => {
@@ -79,9 +211,14 @@ impl OrderBookEngine {
                 }
             }
             TimeInForce::GTC => {
-                // For GTC orders, add unfilled portion to the book
-                if !order.is_filled() && order.status != OrderStatus::Cancelled {
-                    self.add_order_to_book(order);
+                // For GTC orders, add unfilled portion to the book unless matching was halted,
+                // in which case the remainder would cross the book and is cancelled instead
+                if order.is_open() {
+                    if self.triggered_halt.is_some() {
+                        order.cancel();
+                    } else {
+                        self.add_order_to_book(order.clone());
+                    }
                 }
             }
         }
@@ -89,42 +226,59 @@ impl OrderBookEngine {
         Ok(trades)
     }
 
-    /// Check if an order can be filled entirely
-    fn can_fill_entire_order(&self, order: &Order) -> bool {
-        let mut remaining = order.quantity;
-
+    /// Check if an order would match immediately against the opposite side
+    pub fn would_take_liquidity(&self, order: &Order) -> bool {
         match order.side {
-            OrderSide::Buy => {
-                // Check sell orders (asks)
-                for (price, orders) in &self.sell_orders {
-                    if *price > order.price {
-                        break; // Price too high
-                    }
-                    for sell_order in orders {
-                        remaining -= sell_order.remaining_quantity().min(remaining);
-                        if remaining == 0 {
-                            return true;
-                        }
-                    }
-                }
+            OrderSide::Buy => self
+                .get_best_ask_price()
+                .is_some_and(|best_ask| order.price >= best_ask),
+            OrderSide::Sell => self
+                .get_best_bid_price()
+                .is_some_and(|best_bid| order.price <= best_bid),
+        }
+    }
+
+    /// Check if an order can be filled entirely. The user's own resting
+    /// orders don't count towards the fill: unless self-trade prevention
+    /// cancels them out of the way, reaching one stops the order, which fails
+    /// with the self-trade rejection before anything has traded. Nor do levels
+    /// past the circuit breaker band, including one set by the order's own
+    /// first trade when the book has no reference price yet.
+    fn can_fill_entire_order(&self, order: &Order) -> Result<bool, String> {
+        let mut remaining = order.remaining_quantity();
+        let mut reference = self.reference_price;
+        let levels: Box<dyn Iterator<Item = (&Decimal, &VecDeque<Order>)>> = match order.side {
+            OrderSide::Buy => Box::new(self.sell_orders.iter()),
+            OrderSide::Sell => Box::new(self.buy_orders.iter().rev()),
+        };
+
+        for (price, orders) in levels {
+            let crosses = match order.side {
+                OrderSide::Buy => *price <= order.price,
+                OrderSide::Sell => *price >= order.price,
+            };
+            if !crosses || self.breaches_band(reference, *price) {
+                break;
             }
-            OrderSide::Sell => {
-                // Check buy orders (bids)
-                for (price, orders) in self.buy_orders.iter().rev() {
-                    if *price < order.price {
-                        break; // Price too low
-                    }
-                    for buy_order in orders {
-                        remaining -= buy_order.remaining_quantity().min(remaining);
-                        if remaining == 0 {
-                            return true;
-                        }
+            for resting in orders {
+                if resting.user_id == order.user_id {
+                    if order.self_trade_prevention == SelfTradePrevention::CancelOldest {
+                        continue;
                     }
+                    return Err(format!(
+                        "{}:{}",
+                        SELF_TRADE_REJECTION, order.self_trade_prevention
+                    ));
+                }
+                reference.get_or_insert(*price);
+                remaining -= resting.remaining_quantity().min(remaining);
+                if remaining == 0 {
+                    return Ok(true);
                 }
             }
         }
 
-        remaining == 0
+        Ok(false)
     }
 
     /// Cancel an existing order
@@ -132,6 +286,7 @@ impl OrderBookEngine {
         let mut order = self.orders_map.remove(order_id).ok_or("Order not found")?;
 
         order.cancel();
+        self.mark_level_changed(&order.side, order.price);
 
         // Remove from price level
         match order.side {
@@ -152,10 +307,104 @@ impl OrderBookEngine {
                 }
             }
         }
+        self.recount_level(&order.side, order.price);
 
         Ok(order)
     }
 
+    /// Amend the price and/or quantity of a resting order.
+    ///
+    /// Reducing quantity at the same price keeps the order's place in the queue.
+    /// Changing the price or increasing quantity re-queues it at the back of the
+    /// new price level, matching first if the new price crosses the spread.
+    pub fn amend_order(
+        &mut self,
+        order_id: &str,
+        new_price: Option<Decimal>,
+        new_quantity: Option<i32>,
+    ) -> Result<(Order, Vec<Trade>), String> {
+        let existing = self.orders_map.get(order_id).ok_or("Order not found")?;
+
+        let price = new_price.unwrap_or(existing.price);
+        let quantity = new_quantity.unwrap_or(existing.quantity);
+
+        if price <= Decimal::new(0, 2) {
+            return Err("Order price must be positive".to_string());
+        }
+
+        if quantity <= existing.filled_quantity {
+            return Err("Order quantity must exceed the filled quantity".to_string());
+        }
+
+        if price == existing.price && quantity == existing.quantity {
+            return Err("Amendment does not change the order".to_string());
+        }
+
+        // Quantity reduction at the same price keeps queue priority
+        if price == existing.price && quantity < existing.quantity {
+            let side = existing.side.clone();
+            self.mark_level_changed(&side, price);
+            let level = match side {
+                OrderSide::Buy => self.buy_orders.get_mut(&price),
+                OrderSide::Sell => self.sell_orders.get_mut(&price),
+            };
+            let now = Utc::now();
+            if let Some(queued) =
+                level.and_then(|orders| orders.iter_mut().find(|o| o.id == order_id))
+            {
+                queued.quantity = quantity;
+                queued.updated_at = now;
+            }
+            self.recount_level(&side, price);
+
+            let order = self.orders_map.get_mut(order_id).ok_or("Order not found")?;
+            order.quantity = quantity;
+            order.updated_at = now;
+
+            return Ok((order.clone(), vec![]));
+        }
+
+        if self.current_halt().is_some() {
+            return Err(TRADING_HALTED_REJECTION.to_string());
+        }
+        self.roll_reference_price();
+
+        let existing = self.orders_map.get(order_id).ok_or("Order not found")?;
+        let mut amended = existing.clone();
+        amended.price = price;
+        amended.quantity = quantity;
+
+        if amended.post_only && self.auction.is_none() && self.would_take_liquidity(&amended) {
+            return Err(POST_ONLY_REJECTION.to_string());
+        }
+
+        // Price change or quantity increase loses priority
+        let mut order = self.cancel_order(order_id)?;
+        order.price = price;
+        order.quantity = quantity;
+        order.status = if order.filled_quantity > 0 {
+            OrderStatus::PartiallyFilled
+        } else {
+            OrderStatus::Pending
+        };
+        order.updated_at = Utc::now();
+
+        let trades = if self.auction.is_some() {
+            vec![]
+        } else {
+            self.match_order(&mut order)?
+        };
+        if order.is_open() {
+            if self.triggered_halt.is_some() {
+                order.cancel();
+            } else {
+                self.add_order_to_book(order.clone());
+            }
+        }
+
+        Ok((order, trades))
+    }
+
     /// Execute a market order
     fn execute_market_order(&mut self, order: &mut Order) -> Result<Vec<Trade>, String> {
         // let mut trades: Vec<Trade> = Vec::new();
@@ -228,6 +477,9 @@ impl OrderBookEngine {
     /// Match an order against the order book
     fn match_order(&mut self, order: &mut Order) -> Result<Vec<Trade>, String> {
         let mut trades = Vec::new();
+        let original_quantity = order.quantity;
+        self.self_trade_prevented.clear();
+        self.triggered_halt = None;
 
         match order.side {
             OrderSide::Buy => {
@@ -240,15 +492,32 @@ impl OrderBookEngine {
                     .collect();
 
                 for price in prices_to_remove {
-                    if order.is_filled() {
+                    if !order.is_open() || self.halt.is_some() {
                         break;
                     }
 
                     if let Some(mut orders_at_price) = self.sell_orders.remove(&price) {
+                        self.touch();
+                        self.changed_asks.insert(price);
                         let mut remaining_orders = VecDeque::new();
 
                         while let Some(mut counter_order) = orders_at_price.pop_front() {
-                            if order.is_filled() {
+                            if !order.is_open() || self.halt.is_some() {
+                                remaining_orders.push_back(counter_order);
+                                continue;
+                            }
+
+                            if counter_order.user_id == order.user_id {
+                                self.prevent_self_trade(
+                                    order,
+                                    counter_order,
+                                    &mut remaining_orders,
+                                );
+                                continue;
+                            }
+
+                            if self.breaches_circuit_breaker(price) {
+                                self.trip_circuit_breaker(price);
                                 remaining_orders.push_back(counter_order);
                                 continue;
                             }
@@ -269,6 +538,7 @@ impl OrderBookEngine {
                         if !remaining_orders.is_empty() {
                             self.sell_orders.insert(price, remaining_orders);
                         }
+                        self.recount_level(&OrderSide::Sell, price);
                     }
                 }
             }
@@ -283,15 +553,32 @@ impl OrderBookEngine {
                     .collect();
 
                 for price in prices_to_remove {
-                    if order.is_filled() {
+                    if !order.is_open() || self.halt.is_some() {
                         break;
                     }
 
                     if let Some(mut orders_at_price) = self.buy_orders.remove(&price) {
+                        self.touch();
+                        self.changed_bids.insert(price);
                         let mut remaining_orders = VecDeque::new();
 
                         while let Some(mut counter_order) = orders_at_price.pop_front() {
-                            if order.is_filled() {
+                            if !order.is_open() || self.halt.is_some() {
+                                remaining_orders.push_back(counter_order);
+                                continue;
+                            }
+
+                            if counter_order.user_id == order.user_id {
+                                self.prevent_self_trade(
+                                    order,
+                                    counter_order,
+                                    &mut remaining_orders,
+                                );
+                                continue;
+                            }
+
+                            if self.breaches_circuit_breaker(price) {
+                                self.trip_circuit_breaker(price);
                                 remaining_orders.push_back(counter_order);
                                 continue;
                             }
@@ -312,14 +599,336 @@ impl OrderBookEngine {
                         if !remaining_orders.is_empty() {
                             self.buy_orders.insert(price, remaining_orders);
                         }
+                        self.recount_level(&OrderSide::Buy, price);
                     }
                 }
             }
         }
 
+        // Report the incoming order too if self-trade prevention stopped or shrank it
+        if order.status == OrderStatus::Cancelled || order.quantity != original_quantity {
+            self.self_trade_prevented.push(order.clone());
+        }
+
         Ok(trades)
     }
 
+    /// Apply the incoming order's self-trade prevention policy against a resting
+    /// order from the same user, re-queueing the resting order if it stays open
+    fn prevent_self_trade(
+        &mut self,
+        order: &mut Order,
+        mut resting: Order,
+        remaining_orders: &mut VecDeque<Order>,
+    ) {
+        match order.self_trade_prevention {
+            SelfTradePrevention::CancelNewest => order.cancel(),
+            SelfTradePrevention::CancelOldest => resting.cancel(),
+            SelfTradePrevention::CancelBoth => {
+                order.cancel();
+                resting.cancel();
+            }
+            SelfTradePrevention::Decrement => {
+                let quantity = order.remaining_quantity().min(resting.remaining_quantity());
+                order.decrement(quantity);
+                resting.decrement(quantity);
+            }
+        }
+
+        if order.self_trade_prevention != SelfTradePrevention::CancelNewest {
+            self.self_trade_prevented.push(resting.clone());
+        }
+
+        if resting.is_open() {
+            remaining_orders.push_back(resting.clone());
+            self.orders_map.insert(resting.id.clone(), resting);
+        } else {
+            self.orders_map.remove(&resting.id);
+        }
+    }
+
+    /// Take the orders changed by self-trade prevention during the last match,
+    /// including the incoming order when it was cancelled or decremented
+    pub fn take_self_trade_prevented(&mut self) -> Vec<Order> {
+        std::mem::take(&mut self.self_trade_prevented)
+    }
+
+    /// Current halt on this option, lifting an automatic halt once its resume time has passed
+    pub fn current_halt(&mut self) -> Option<&TradingHalt> {
+        let expired = self
+            .halt
+            .as_ref()
+            .and_then(|halt| halt.resume_at)
+            .is_some_and(|resume_at| resume_at <= Utc::now());
+        if expired {
+            self.resume_matching();
+        }
+        self.halt.as_ref()
+    }
+
+    /// Halt matching until resumed, or until `duration` elapses when given
+    pub fn halt_trading(&mut self, reason: String, duration: Option<Duration>) -> TradingHalt {
+        let now = Utc::now();
+        let halt = TradingHalt {
+            reason,
+            manual: true,
+            halted_at: now,
+            resume_at: duration.map(|d| now + d),
+        };
+        self.touch();
+        self.halt = Some(halt.clone());
+        halt
+    }
+
+    /// Lift a halt, whether manual or tripped by the circuit breaker
+    pub fn resume_trading(&mut self) -> Result<(), String> {
+        if self.current_halt().is_none() {
+            return Err("Trading is not halted".to_string());
+        }
+        self.resume_matching();
+        Ok(())
+    }
+
+    /// Take the incoming order of the last submission as it left the book
+    pub fn take_submitted(&mut self) -> Option<Order> {
+        self.submitted.take()
+    }
+
+    /// Take the halt tripped by the circuit breaker during the last match, if any
+    pub fn take_triggered_halt(&mut self) -> Option<TradingHalt> {
+        self.triggered_halt.take()
+    }
+
+    /// Clear the halt and restart the reference window from the last trade so
+    /// the move that caused the halt doesn't immediately trip it again
+    fn resume_matching(&mut self) {
+        self.touch();
+        self.halt = None;
+        self.reference_price = self.last_trade_price;
+        self.reference_set_at = Some(Utc::now());
+    }
+
+    /// Roll the reference price forward to the last trade once its window has elapsed
+    fn roll_reference_price(&mut self) {
+        let now = Utc::now();
+        let expired = self
+            .reference_set_at
+            .is_none_or(|set_at| now - set_at >= self.circuit_breaker_window);
+        if expired {
+            self.reference_price = self.last_trade_price;
+            self.reference_set_at = Some(now);
+        }
+    }
+
+    /// Whether trading at `price` would move more than the threshold away from the reference price
+    fn breaches_circuit_breaker(&self, price: Decimal) -> bool {
+        self.breaches_band(self.reference_price, price)
+    }
+
+    /// Whether trading at `price` would move more than the threshold away from `reference`
+    fn breaches_band(&self, reference: Option<Decimal>, price: Decimal) -> bool {
+        match reference {
+            Some(reference) if reference > Decimal::ZERO => {
+                (price - reference).abs() / reference * Decimal::from(100)
+                    > self.circuit_breaker_threshold
+            }
+            _ => false,
+        }
+    }
+
+    fn trip_circuit_breaker(&mut self, price: Decimal) {
+        let now = Utc::now();
+        let halt = TradingHalt {
+            reason: format!(
+                "Price {} moved more than {}% from reference {}",
+                price,
+                self.circuit_breaker_threshold,
+                self.reference_price.unwrap_or_default()
+            ),
+            manual: false,
+            halted_at: now,
+            resume_at: Some(now + self.circuit_breaker_halt),
+        };
+        log::warn!(
+            "Circuit breaker halted event {} option {}: {}",
+            self.event_id,
+            self.option_id,
+            halt.reason
+        );
+        self.touch();
+        self.halt = Some(halt.clone());
+        self.triggered_halt = Some(halt);
+    }
+
+    /// Current auction on this option, if any
+    pub fn auction(&self) -> Option<&AuctionState> {
+        self.auction.as_ref()
+    }
+
+    /// Stop continuous matching and collect orders until the auction is uncrossed
+    pub fn start_auction(&mut self, duration: Duration) -> Result<AuctionState, String> {
+        if self.auction.is_some() {
+            return Err("An auction is already in progress".to_string());
+        }
+        let now = Utc::now();
+        let auction = AuctionState {
+            started_at: now,
+            ends_at: now + duration,
+        };
+        self.touch();
+        self.auction = Some(auction.clone());
+        Ok(auction)
+    }
+
+    /// Price that would maximize matched volume if the auction uncrossed now, with that volume.
+    ///
+    /// Ties are broken by the smallest imbalance between the two sides, then by the
+    /// price closest to the last trade, then by the lower price.
+    pub fn calculate_uncrossing_price(&self) -> Option<(Decimal, i32)> {
+        let mut candidates: Vec<Decimal> = self
+            .buy_orders
+            .keys()
+            .chain(self.sell_orders.keys())
+            .copied()
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+
+        let mut best: Option<(Decimal, i32, i32)> = None;
+        for price in candidates {
+            let buy_volume: i32 = self
+                .buy_orders
+                .range(price..)
+                .flat_map(|(_, orders)| orders.iter())
+                .map(|o| o.remaining_quantity())
+                .sum();
+            let sell_volume: i32 = self
+                .sell_orders
+                .range(..=price)
+                .flat_map(|(_, orders)| orders.iter())
+                .map(|o| o.remaining_quantity())
+                .sum();
+            let matched = buy_volume.min(sell_volume);
+            if matched == 0 {
+                continue;
+            }
+            let imbalance = (buy_volume - sell_volume).abs();
+
+            let better = match best {
+                None => true,
+                Some((best_price, best_matched, best_imbalance)) => {
+                    matched > best_matched
+                        || (matched == best_matched && imbalance < best_imbalance)
+                        || (matched == best_matched
+                            && imbalance == best_imbalance
+                            && self.last_trade_price.is_some_and(|last| {
+                                (price - last).abs() < (best_price - last).abs()
+                            }))
+                }
+            };
+            if better {
+                best = Some((price, matched, imbalance));
+            }
+        }
+
+        best.map(|(price, matched, _)| (price, matched))
+    }
+
+    /// End the auction and execute every crossing order at the uncrossing price.
+    ///
+    /// Orders trade in price-time priority. When both sides of a pairing belong to
+    /// the same user the newer order is cancelled instead.
+    pub fn uncross_auction(&mut self) -> Result<AuctionResult, String> {
+        if self.auction.take().is_none() {
+            return Err("No auction in progress".to_string());
+        }
+        self.touch();
+
+        let Some((price, _)) = self.calculate_uncrossing_price() else {
+            return Ok(AuctionResult {
+                price: None,
+                trades: vec![],
+                orders: vec![],
+                sequence: self.sequence,
+            });
+        };
+
+        let buy_prices: Vec<Decimal> = self.buy_orders.range(price..).map(|(p, _)| *p).collect();
+        self.changed_bids.extend(&buy_prices);
+        let mut buys: Vec<Order> = buy_prices
+            .iter()
+            .rev()
+            .filter_map(|p| self.buy_orders.remove(p))
+            .flatten()
+            .collect();
+        let sell_prices: Vec<Decimal> = self.sell_orders.range(..=price).map(|(p, _)| *p).collect();
+        self.changed_asks.extend(&sell_prices);
+        let mut sells: Vec<Order> = sell_prices
+            .iter()
+            .filter_map(|p| self.sell_orders.remove(p))
+            .flatten()
+            .collect();
+        for price in &buy_prices {
+            self.recount_level(&OrderSide::Buy, *price);
+        }
+        for price in &sell_prices {
+            self.recount_level(&OrderSide::Sell, *price);
+        }
+
+        let mut trades = Vec::new();
+        let (mut b, mut s) = (0, 0);
+        while b < buys.len() && s < sells.len() {
+            if !buys[b].is_open() {
+                b += 1;
+                continue;
+            }
+            if !sells[s].is_open() {
+                s += 1;
+                continue;
+            }
+
+            if buys[b].user_id == sells[s].user_id {
+                if buys[b].created_at > sells[s].created_at {
+                    buys[b].cancel();
+                } else {
+                    sells[s].cancel();
+                }
+                continue;
+            }
+
+            let trade = self.execute_trade(&mut buys[b], &mut sells[s], price)?;
+            trades.push(trade);
+        }
+
+        // Return unfilled orders to the book in their original priority and report the rest
+        let traded: HashSet<&str> = trades
+            .iter()
+            .flat_map(|t| [t.buy_order_id.as_str(), t.sell_order_id.as_str()])
+            .collect();
+        let mut orders = Vec::new();
+        for order in buys.into_iter().chain(sells) {
+            if traded.contains(order.id.as_str()) || order.status == OrderStatus::Cancelled {
+                orders.push(order.clone());
+            }
+            if order.is_open() {
+                self.add_order_to_book(order);
+            } else {
+                self.orders_map.remove(&order.id);
+            }
+        }
+
+        // The auction price is the new reference for the circuit breaker
+        self.reference_price = Some(price);
+        self.reference_set_at = Some(Utc::now());
+
+        Ok(AuctionResult {
+            price: Some(price),
+            trades,
+            orders,
+            sequence: self.sequence,
+        })
+    }
+
     /// Execute a trade between two orders
     fn execute_trade(
         &mut self,
@@ -334,9 +943,13 @@ impl OrderBookEngine {
         if quantity <= 0 {
             return Err("Invalid trade quantity".to_string());
         }
+        if buy_order.currency != sell_order.currency {
+            return Err(CURRENCY_REJECTION.to_string());
+        }
 
         buy_order.fill(quantity);
         sell_order.fill(quantity);
+        self.touch();
 
         let trade = Trade {
             id: uuid::Uuid::new_v4().to_string(),
@@ -349,11 +962,20 @@ impl OrderBookEngine {
             price,
             quantity,
             total_amount: price * Decimal::from(quantity),
-            timestamp: Utc::now(),
+            timestamp: clock::trade_timestamp(self.event_id, self.option_id),
+            sequence: self.sequence,
+            currency: buy_order.currency.clone(),
+            buy_correlation_id: buy_order.correlation_id.clone(),
+            sell_correlation_id: sell_order.correlation_id.clone(),
         };
 
         self.last_trade_price = Some(price);
+        if self.reference_price.is_none() {
+            self.reference_price = Some(price);
+            self.reference_set_at = Some(trade.timestamp);
+        }
         self.trades.push_back(trade.clone());
+        self.trades_executed += 1;
 
         // Keep only last 1000 trades
         if self.trades.len() > 1000 {
@@ -367,6 +989,8 @@ impl OrderBookEngine {
     fn add_order_to_book(&mut self, order: Order) {
         let price = order.price;
         let order_id = order.id.clone();
+        self.mark_level_changed(&order.side, price);
+        self.currency.get_or_insert_with(|| order.currency.clone());
 
         match order.side {
             OrderSide::Buy => {
@@ -382,14 +1006,26 @@ impl OrderBookEngine {
                     .push_back(order.clone());
             }
         }
+        self.add_to_level_total(&order.side, price, order.remaining_quantity());
 
         self.orders_map.insert(order_id, order);
     }
 
     /// Get the current order book snapshot
     pub fn get_snapshot(&self) -> OrderBookSnapshot {
-        let bids = self.get_bid_levels();
-        let asks = self.get_ask_levels();
+        self.get_snapshot_with_depth(10) // Top 10 levels
+    }
+
+    /// Get a snapshot of every price level, consistent with the level deltas
+    /// published to market data subscribers
+    pub fn get_full_snapshot(&self) -> OrderBookSnapshot {
+        self.get_snapshot_with_depth(usize::MAX)
+    }
+
+    /// Get an order book snapshot limited to the given number of price levels per side
+    pub fn get_snapshot_with_depth(&self, depth: usize) -> OrderBookSnapshot {
+        let bids = self.get_bid_levels(depth);
+        let asks = self.get_ask_levels(depth);
         let mid_price = self.calculate_mid_price();
         let spread = self.calculate_spread();
 
@@ -401,39 +1037,34 @@ impl OrderBookEngine {
             last_trade_price: self.last_trade_price,
             mid_price,
             spread,
+            sequence: self.sequence,
             timestamp: Utc::now(),
         }
     }
 
     /// Get bid price levels
-    fn get_bid_levels(&self) -> Vec<PriceLevel> {
-        self.buy_orders
+    fn get_bid_levels(&self, depth: usize) -> Vec<PriceLevel> {
+        self.bid_totals
             .iter()
             .rev()
-            .take(10) // Top 10 levels
-            .map(|(price, orders)| {
-                let quantity: i32 = orders.iter().map(|o| o.remaining_quantity()).sum();
-                PriceLevel {
-                    price: *price,
-                    quantity,
-                    order_count: orders.len(),
-                }
+            .take(depth)
+            .map(|(price, total)| PriceLevel {
+                price: *price,
+                quantity: total.quantity,
+                order_count: total.orders,
             })
             .collect()
     }
 
     /// Get ask price levels
-    fn get_ask_levels(&self) -> Vec<PriceLevel> {
-        self.sell_orders
+    fn get_ask_levels(&self, depth: usize) -> Vec<PriceLevel> {
+        self.ask_totals
             .iter()
-            .take(10) // Top 10 levels
-            .map(|(price, orders)| {
-                let quantity: i32 = orders.iter().map(|o| o.remaining_quantity()).sum();
-                PriceLevel {
-                    price: *price,
-                    quantity,
-                    order_count: orders.len(),
-                }
+            .take(depth)
+            .map(|(price, total)| PriceLevel {
+                price: *price,
+                quantity: total.quantity,
+                order_count: total.orders,
             })
             .collect()
     }
@@ -490,40 +1121,39 @@ impl OrderBookEngine {
         }
     }
 
-    /// Get market depth at different price levels
+    /// Get market depth at different price levels, read from the level
+    /// totals so it costs the number of levels rather than resting orders
     pub fn get_market_depth(&self, levels: usize) -> Vec<MarketDepth> {
         let mut depth_map: BTreeMap<Decimal, MarketDepth> = BTreeMap::new();
 
         // Add buy orders
-        for (price, orders) in self.buy_orders.iter().rev().take(levels) {
-            let quantity: i32 = orders.iter().map(|o| o.remaining_quantity()).sum();
+        for (price, total) in self.bid_totals.iter().rev().take(levels) {
             depth_map.insert(
                 *price,
                 MarketDepth {
                     price: *price,
-                    buy_quantity: quantity,
+                    buy_quantity: total.quantity,
                     sell_quantity: 0,
-                    buy_orders: orders.len(),
+                    buy_orders: total.orders,
                     sell_orders: 0,
                 },
             );
         }
 
         // Add sell orders
-        for (price, orders) in self.sell_orders.iter().take(levels) {
-            let quantity: i32 = orders.iter().map(|o| o.remaining_quantity()).sum();
+        for (price, total) in self.ask_totals.iter().take(levels) {
             depth_map
                 .entry(*price)
                 .and_modify(|d| {
-                    d.sell_quantity = quantity;
-                    d.sell_orders = orders.len();
+                    d.sell_quantity = total.quantity;
+                    d.sell_orders = total.orders;
                 })
                 .or_insert(MarketDepth {
                     price: *price,
                     buy_quantity: 0,
-                    sell_quantity: quantity,
+                    sell_quantity: total.quantity,
                     buy_orders: 0,
-                    sell_orders: orders.len(),
+                    sell_orders: total.orders,
                 });
         }
 
@@ -533,8 +1163,8 @@ impl OrderBookEngine {
     /// Get the predicted price based on order book imbalance
     pub fn get_predicted_price(&self) -> Option<Decimal> {
         // Simple prediction based on order book imbalance
-        let bid_levels = self.get_bid_levels();
-        let ask_levels = self.get_ask_levels();
+        let bid_levels = self.get_bid_levels(5);
+        let ask_levels = self.get_ask_levels(5);
 
         if bid_levels.is_empty() || ask_levels.is_empty() {
             return self.last_trade_price;
@@ -583,6 +1213,8 @@ impl OrderBookEngine {
     pub fn add_order_directly(&mut self, order: Order) {
         let order_id = order.id.clone();
         let price = order.price;
+        self.mark_level_changed(&order.side, price);
+        self.currency.get_or_insert_with(|| order.currency.clone());
 
         match order.side {
             OrderSide::Buy => {
@@ -598,12 +1230,202 @@ impl OrderBookEngine {
                     .push_back(order.clone());
             }
         }
+        self.add_to_level_total(&order.side, price, order.remaining_quantity());
 
         self.orders_map.insert(order_id, order);
     }
 
+    /// Get the circuit breaker state for persistence
+    pub fn get_circuit_breaker_state(
+        &self,
+    ) -> (Option<Decimal>, Option<DateTime<Utc>>, Option<&TradingHalt>) {
+        (
+            self.reference_price,
+            self.reference_set_at,
+            self.halt.as_ref(),
+        )
+    }
+
+    /// Restore the circuit breaker state (used for reconstruction from Redis)
+    pub fn restore_circuit_breaker_state(
+        &mut self,
+        reference_price: Option<Decimal>,
+        reference_set_at: Option<DateTime<Utc>>,
+        halt: Option<TradingHalt>,
+    ) {
+        self.reference_price = reference_price;
+        self.reference_set_at = reference_set_at;
+        self.halt = halt;
+    }
+
+    /// Restore an auction in progress (used for reconstruction from Redis)
+    pub fn restore_auction(&mut self, auction: Option<AuctionState>) {
+        self.auction = auction;
+    }
+
+    /// Best bid and ask, mid and last trade price
+    pub fn quote(&self) -> Quote {
+        Quote {
+            best_bid: self.get_best_bid_price(),
+            best_ask: self.get_best_ask_price(),
+            mid_price: self.calculate_mid_price(),
+            last_trade_price: self.last_trade_price,
+        }
+    }
+
+    /// Number of resting buy and sell orders
+    pub fn open_order_counts(&self) -> (usize, usize) {
+        (
+            self.buy_orders.values().map(VecDeque::len).sum(),
+            self.sell_orders.values().map(VecDeque::len).sum(),
+        )
+    }
+
+    /// Price of the most recent trade, if the book has traded
+    pub fn last_trade_price(&self) -> Option<Decimal> {
+        self.last_trade_price
+    }
+
     /// Set the last trade price (used for reconstruction from Redis)
     pub fn set_last_trade_price(&mut self, price: Decimal) {
         self.last_trade_price = Some(price);
     }
+
+    fn mark_level_changed(&mut self, side: &OrderSide, price: Decimal) {
+        self.touch();
+        match side {
+            OrderSide::Buy => self.changed_bids.insert(price),
+            OrderSide::Sell => self.changed_asks.insert(price),
+        };
+    }
+
+    /// Count an order added to the back of a level
+    fn add_to_level_total(&mut self, side: &OrderSide, price: Decimal, quantity: i32) {
+        let totals = match side {
+            OrderSide::Buy => &mut self.bid_totals,
+            OrderSide::Sell => &mut self.ask_totals,
+        };
+        let total = totals.entry(price).or_default();
+        total.quantity += quantity;
+        total.orders += 1;
+    }
+
+    /// Recompute a level's total after its orders were changed in place, or
+    /// drop it if the level is gone
+    fn recount_level(&mut self, side: &OrderSide, price: Decimal) {
+        let (book, totals) = match side {
+            OrderSide::Buy => (&self.buy_orders, &mut self.bid_totals),
+            OrderSide::Sell => (&self.sell_orders, &mut self.ask_totals),
+        };
+        match book.get(&price) {
+            Some(orders) => {
+                totals.insert(
+                    price,
+                    LevelTotal {
+                        quantity: orders.iter().map(Order::remaining_quantity).sum(),
+                        orders: orders.len(),
+                    },
+                );
+            }
+            None => {
+                totals.remove(&price);
+            }
+        }
+    }
+
+    /// Take the changes since the last entry: the full contents of every price
+    /// level that changed (empty if the level is gone) plus the current metadata
+    pub fn take_journal_entry(&mut self) -> BookJournalEntry {
+        let level = |side: OrderSide, price: Decimal, book: &BTreeMap<Decimal, VecDeque<Order>>| {
+            LevelChange {
+                side,
+                price,
+                orders: book.get(&price).cloned().unwrap_or_default(),
+            }
+        };
+
+        let mut levels: Vec<LevelChange> = std::mem::take(&mut self.changed_bids)
+            .into_iter()
+            .map(|price| level(OrderSide::Buy, price, &self.buy_orders))
+            .collect();
+        levels.extend(
+            std::mem::take(&mut self.changed_asks)
+                .into_iter()
+                .map(|price| level(OrderSide::Sell, price, &self.sell_orders)),
+        );
+
+        BookJournalEntry {
+            levels,
+            last_trade_price: self.last_trade_price,
+            reference_price: self.reference_price,
+            reference_set_at: self.reference_set_at,
+            halt: self.halt.clone(),
+            auction: self.auction.clone(),
+            sequence: self.sequence,
+        }
+    }
+
+    /// Replay a journal entry on top of a restored snapshot
+    pub fn apply_journal_entry(&mut self, entry: BookJournalEntry) {
+        // Drop every order on the old levels first, so an order that moved
+        // between two changed levels isn't removed after being re-added
+        for change in &entry.levels {
+            let book = match change.side {
+                OrderSide::Buy => &mut self.buy_orders,
+                OrderSide::Sell => &mut self.sell_orders,
+            };
+            for order in book.remove(&change.price).unwrap_or_default() {
+                self.orders_map.remove(&order.id);
+            }
+        }
+
+        for change in entry.levels {
+            if !change.orders.is_empty() {
+                for order in &change.orders {
+                    self.currency.get_or_insert_with(|| order.currency.clone());
+                    self.orders_map.insert(order.id.clone(), order.clone());
+                }
+                match change.side {
+                    OrderSide::Buy => self.buy_orders.insert(change.price, change.orders),
+                    OrderSide::Sell => self.sell_orders.insert(change.price, change.orders),
+                };
+            }
+            self.recount_level(&change.side, change.price);
+        }
+
+        self.last_trade_price = entry.last_trade_price;
+        self.reference_price = entry.reference_price;
+        self.reference_set_at = entry.reference_set_at;
+        self.halt = entry.halt;
+        self.auction = entry.auction;
+        self.restore_sequence(entry.sequence);
+    }
+
+    /// Start a command against the book. The first change the command makes
+    /// advances the sequence; later changes in the same command share it.
+    pub fn begin_command(&mut self) {
+        self.sequence_advanced = false;
+    }
+
+    /// Trades executed since the book was loaded
+    pub fn trades_executed(&self) -> u64 {
+        self.trades_executed
+    }
+
+    /// Sequence number of the latest change to the book
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    pub fn restore_sequence(&mut self, sequence: u64) {
+        self.sequence = sequence;
+        self.sequence_advanced = true;
+    }
+
+    fn touch(&mut self) {
+        if !self.sequence_advanced {
+            self.sequence += 1;
+            self.sequence_advanced = true;
+        }
+    }
 }
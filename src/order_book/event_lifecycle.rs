@@ -0,0 +1,161 @@
+use super::types::OrderStatus;
+use crate::constants::config;
+use crate::handlers::order_book_handler::cancel_event_orders;
+use crate::types::websocket::{OpsEventKind, OpsSeverity};
+use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
+use crate::websocket::ops;
+use crate::websocket::server::{BroadcastEventsUpdate, WebSocketServer};
+use actix::Addr;
+use actix_web::web;
+use chrono::Utc;
+use deadpool_redis::Pool;
+use entity::{events, orders};
+use sea_orm::{
+    prelude::Expr, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter,
+    QuerySelect,
+};
+use serde_json::json;
+
+/// Statuses of events that can no longer trade
+const CLOSED_STATUSES: [&str; 3] = ["ended", "resolved", "cancelled"];
+
+/// Mark active events whose end time has passed as ended, returning their ids
+async fn end_expired_events(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+) -> Result<Vec<i32>, String> {
+    let expired: Vec<i32> = events::Entity::find()
+        .select_only()
+        .column(events::Column::Id)
+        .filter(events::Column::Status.eq("active"))
+        .filter(events::Column::EndTime.lte(Utc::now().naive_utc()))
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load expired events: {}", e))?;
+    if expired.is_empty() {
+        return Ok(expired);
+    }
+
+    events::Entity::update_many()
+        .col_expr(events::Column::Status, Expr::value("ended"))
+        .col_expr(
+            events::Column::UpdatedAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .filter(events::Column::Id.is_in(expired.clone()))
+        .filter(events::Column::Status.eq("active"))
+        .exec(db)
+        .await
+        .map_err(|e| format!("Failed to end expired events: {}", e))?;
+
+    let cache_service = CacheService::new(redis_pool.clone());
+    for event_id in &expired {
+        let event_cache_key = create_cache_key(cache_keys::EVENT_PREFIX, &event_id.to_string());
+        if let Err(e) = cache_service.delete(&event_cache_key).await {
+            log::warn!("Failed to invalidate event cache: {}", e);
+        }
+    }
+    if let Err(e) = cache_service.delete("events:list").await {
+        log::warn!("Failed to invalidate events list cache: {}", e);
+    }
+
+    Ok(expired)
+}
+
+/// Events that can no longer trade but still have open orders
+async fn closed_events_with_open_orders(db: &DatabaseConnection) -> Result<Vec<i32>, String> {
+    let with_open_orders: Vec<i32> = orders::Entity::find()
+        .select_only()
+        .column(orders::Column::EventId)
+        .distinct()
+        .filter(orders::Column::Status.is_in([
+            OrderStatus::Pending.to_string(),
+            OrderStatus::PartiallyFilled.to_string(),
+        ]))
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load events with open orders: {}", e))?;
+    if with_open_orders.is_empty() {
+        return Ok(with_open_orders);
+    }
+
+    events::Entity::find()
+        .select_only()
+        .column(events::Column::Id)
+        .filter(events::Column::Id.is_in(with_open_orders))
+        .filter(
+            Condition::any()
+                .add(events::Column::Status.is_in(CLOSED_STATUSES))
+                .add(events::Column::EndTime.lte(Utc::now().naive_utc())),
+        )
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load closed events: {}", e))
+}
+
+/// End expired events and cancel the orders left resting on closed ones
+async fn close_events(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+) -> Result<(), String> {
+    let ended = end_expired_events(db.get_ref(), redis_pool.get_ref()).await?;
+    if !ended.is_empty() {
+        log::info!("Ended {} events past their end time", ended.len());
+        ws_server.do_send(BroadcastEventsUpdate);
+    }
+
+    for event_id in closed_events_with_open_orders(db.get_ref()).await? {
+        match cancel_event_orders(db, redis_pool, ws_server, event_id).await {
+            Ok(cancelled) => log::info!(
+                "Cancelled {} open orders of closed event {}",
+                cancelled.len(),
+                event_id
+            ),
+            Err(e) => log::error!(
+                "Failed to cancel open orders of closed event {}: {}",
+                event_id,
+                e
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Start a background task that ends events once their end time passes and
+/// cancels the open orders of events that can no longer trade
+pub fn start_event_lifecycle_scheduler(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+) {
+    let interval_seconds = config::get_event_lifecycle_interval_seconds();
+
+    log::info!(
+        "Starting event lifecycle scheduler with {}-second interval",
+        interval_seconds
+    );
+
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(interval_seconds));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = close_events(&db, &redis_pool, &ws_server).await {
+                log::error!("Failed to close ended events: {}", e);
+                ops::publish(
+                    OpsEventKind::JobFailed,
+                    OpsSeverity::Critical,
+                    "Event lifecycle scheduler could not close ended events",
+                    json!({ "job": "event_lifecycle", "error": e }),
+                );
+            }
+        }
+    });
+}
@@ -0,0 +1,42 @@
+use super::types::{Order, Trade};
+use deadpool_redis::{redis::AsyncCommands, Pool};
+use serde_json::json;
+
+/// Redis list the external venue gateway drains, oldest request at the tail
+pub const OUTBOX_KEY: &str = "venue:external:outbox";
+
+async fn push(redis_pool: &Pool, action: &str, order: &Order) -> Result<(), String> {
+    let message = json!({
+        "action": action,
+        "order": order,
+        "timestamp": chrono::Utc::now(),
+    });
+    let mut conn = redis_pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to reach external venue outbox: {}", e))?;
+    conn.lpush::<_, _, ()>(OUTBOX_KEY, message.to_string())
+        .await
+        .map_err(|e| format!("Failed to queue order for external venue: {}", e))
+}
+
+/// Queue an order for the external venue. It stays open until the gateway
+/// reports fills through the venue fills endpoint or it is cancelled.
+pub async fn forward(
+    redis_pool: &Pool,
+    order: Order,
+) -> Result<Result<Vec<Trade>, String>, String> {
+    push(redis_pool, "new", &order).await?;
+    Ok(Ok(Vec::new()))
+}
+
+/// Queue a cancel for an open order and return it as cancelled. Fills the
+/// venue reports after this are rejected, since the order is no longer open.
+pub async fn cancel(redis_pool: &Pool, mut order: Order) -> Result<Result<Order, String>, String> {
+    if !order.is_open() {
+        return Ok(Err("Order is not open".to_string()));
+    }
+    push(redis_pool, "cancel", &order).await?;
+    order.cancel();
+    Ok(Ok(order))
+}
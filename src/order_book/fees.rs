@@ -0,0 +1,51 @@
+use super::types::Trade;
+use crate::constants::config;
+use crate::settings::registry as settings;
+use crate::tenants::registry as tenants;
+use crate::types::tier::TierBenefits;
+use sea_orm::prelude::Decimal;
+use std::collections::HashMap;
+
+/// Fee charged to each side of a trade
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TradeFees {
+    pub buyer_fee: Decimal,
+    pub seller_fee: Decimal,
+}
+
+/// Fees a fee-free promotion waived on a trade, recorded against the promotion
+#[derive(Debug, Clone, Copy)]
+pub struct FeeWaiver {
+    pub promotion_id: i32,
+    pub waived: TradeFees,
+}
+
+/// Fee on a trade's notional at `fee_bps`, less the tier discount
+pub fn trading_fee(amount: Decimal, fee_bps: u32, fee_discount_percent: Decimal) -> Decimal {
+    let rate = Decimal::from(fee_bps) / Decimal::from(10_000);
+    let discount = (Decimal::ONE_HUNDRED - fee_discount_percent) / Decimal::ONE_HUNDRED;
+    (amount * rate * discount).round_dp(8)
+}
+
+/// The undiscounted fee at `TRADING_FEE_BPS`, which no tenant's fee setting
+/// may exceed, used to check a buyer can afford an order before it trades
+pub fn max_trading_fee(amount: Decimal) -> Decimal {
+    trading_fee(amount, config::get_trading_fee_bps(), Decimal::ZERO)
+}
+
+/// Fees for a trade given the tier benefits of both sides
+pub fn fees_for_trade(trade: &Trade, benefits: &HashMap<i32, TierBenefits>) -> TradeFees {
+    let discount = |user_id: i32| {
+        benefits
+            .get(&user_id)
+            .map(|b| b.fee_discount_percent)
+            .unwrap_or(Decimal::ZERO)
+    };
+
+    let fee_bps = settings::trading_fee_bps(tenants::event_tenant(trade.event_id));
+
+    TradeFees {
+        buyer_fee: trading_fee(trade.total_amount, fee_bps, discount(trade.buyer_id)),
+        seller_fee: trading_fee(trade.total_amount, fee_bps, discount(trade.seller_id)),
+    }
+}
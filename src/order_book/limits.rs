@@ -0,0 +1,69 @@
+use super::margin::PAYOUT_PER_SHARE;
+use entity::events;
+use sea_orm::prelude::Decimal;
+
+/// Check an order's price and quantity against the event's trading limits.
+/// `size_multiplier` scales the maximum order quantity for higher tiers.
+pub fn validate_order_limits(
+    event: &events::Model,
+    price: Decimal,
+    quantity: i32,
+    size_multiplier: i32,
+) -> Result<(), String> {
+    if price < event.min_price || price > event.max_price {
+        return Err(format!(
+            "Price must be between {} and {}",
+            event.min_price, event.max_price
+        ));
+    }
+
+    if event.tick_size > Decimal::ZERO && !(price % event.tick_size).is_zero() {
+        return Err(format!(
+            "Price must be a multiple of the tick size {}",
+            event.tick_size
+        ));
+    }
+
+    let max_order_quantity = event
+        .max_order_quantity
+        .saturating_mul(size_multiplier.max(1));
+    if quantity > max_order_quantity {
+        return Err(format!(
+            "Quantity exceeds the maximum of {} per order",
+            max_order_quantity
+        ));
+    }
+
+    Ok(())
+}
+
+/// Round a price to the nearest valid tick for the event
+pub fn align_to_tick(event: &events::Model, price: Decimal) -> Decimal {
+    if event.tick_size <= Decimal::ZERO {
+        return price;
+    }
+    (price / event.tick_size).round() * event.tick_size
+}
+
+/// Check that an event's trading limit configuration is coherent
+pub fn validate_limit_config(
+    min_price: Decimal,
+    max_price: Decimal,
+    tick_size: Decimal,
+    max_order_quantity: i32,
+) -> Result<(), String> {
+    if min_price <= Decimal::ZERO || min_price >= max_price {
+        return Err("min_price must be positive and below max_price".to_string());
+    }
+    // A share never trades above what it pays out
+    if max_price > PAYOUT_PER_SHARE {
+        return Err(format!("max_price can't be above {}", PAYOUT_PER_SHARE));
+    }
+    if tick_size <= Decimal::ZERO {
+        return Err("tick_size must be positive".to_string());
+    }
+    if max_order_quantity <= 0 {
+        return Err("max_order_quantity must be positive".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,622 @@
+use super::{
+    db_persistence::DbPersistence,
+    limits::align_to_tick,
+    margin,
+    position_tracker::PositionTracker,
+    redis_persistence::RedisOrderBookPersistence,
+    reservations, risk,
+    router::{self, ExecutionVenue},
+    types::{Order, OrderSide, OrderStatus, OrderType, Trade},
+    TimeInForce,
+};
+use crate::constants::config;
+use crate::handlers::order_book_handler::{
+    broadcast_trading_status, persist_self_trade_prevented, report_circuit_breaker, settle_trades,
+};
+use crate::notifications::dispatcher::{self, Notification};
+use crate::types::preferences::NotificationCategory;
+use crate::types::websocket::{OpsEventKind, OpsSeverity, PriceUpdateSource};
+use crate::utils::cache::CacheService;
+use crate::wallets::balances;
+use crate::websocket::server::WebSocketServer;
+use crate::websocket::{market_data, ops};
+use actix::Addr;
+use actix_web::web;
+use chrono::{DateTime, Duration, Utc};
+use deadpool_redis::Pool;
+use entity::{event_options, events, transaction, user_positions, wallets};
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::{
+    prelude::Decimal, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    QuerySelect, Set, TransactionTrait,
+};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// A short position valued at its option's current price
+struct Short {
+    event: events::Model,
+    option_id: i32,
+    shares: i32,
+    average_price: Decimal,
+    current_price: Decimal,
+}
+
+/// A margin account's short positions in active events listed in one
+/// currency, valued at current prices. Only the wallet in that currency
+/// backs them.
+struct Assessment {
+    user_id: i32,
+    currency: String,
+    wallet: Option<wallets::Model>,
+    shorts: Vec<Short>,
+}
+
+impl Assessment {
+    /// What buying back every short would cost at current prices
+    fn exposure(&self) -> Decimal {
+        self.shorts
+            .iter()
+            .map(|s| s.current_price * Decimal::from(s.shares))
+            .sum()
+    }
+
+    /// Available balance and collateral left after buying back every short
+    fn equity(&self) -> Decimal {
+        let margin_balance = self
+            .wallet
+            .as_ref()
+            .map(|w| w.margin_balance)
+            .unwrap_or(Decimal::ZERO);
+        balances::available_in(self.wallet.as_ref()) + margin_balance - self.exposure()
+    }
+
+    /// Equity the account has to keep: `MARGIN_MAINTENANCE_PERCENT` of the
+    /// collateral the shorts would need if sold at current prices
+    fn maintenance(&self) -> Decimal {
+        let collateral: Decimal = self
+            .shorts
+            .iter()
+            .map(|s| margin::collateral(s.current_price, s.shares))
+            .sum();
+        collateral * margin::maintenance_rate()
+    }
+
+    fn shortfall(&self) -> Decimal {
+        (self.maintenance() - self.equity()).max(Decimal::ZERO)
+    }
+
+    /// The short that costs most to buy back, which is reduced first
+    fn largest_short(&self) -> Option<&Short> {
+        self.shorts
+            .iter()
+            .max_by_key(|s| s.current_price * Decimal::from(s.shares))
+    }
+}
+
+/// Value every short position in an active event, grouped by account and
+/// currency
+async fn load_assessments(db: &DatabaseConnection) -> Result<Vec<Assessment>, String> {
+    let positions = user_positions::Entity::find()
+        .filter(user_positions::Column::Quantity.lt(0))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load short positions: {}", e))?;
+    if positions.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let option_ids: Vec<i32> = positions.iter().map(|p| p.option_id).collect();
+    let prices: HashMap<i32, Decimal> = event_options::Entity::find()
+        .select_only()
+        .column(event_options::Column::Id)
+        .column(event_options::Column::CurrentPrice)
+        .filter(event_options::Column::Id.is_in(option_ids))
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load option prices: {}", e))?
+        .into_iter()
+        .collect();
+
+    let event_ids: Vec<i32> = positions.iter().map(|p| p.event_id).collect();
+    let active_events: HashMap<i32, events::Model> = events::Entity::find()
+        .filter(events::Column::Id.is_in(event_ids))
+        .filter(events::Column::Status.eq("active"))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load events: {}", e))?
+        .into_iter()
+        .map(|e| (e.id, e))
+        .collect();
+
+    let user_ids: Vec<i32> = positions.iter().map(|p| p.user_id).collect();
+    let mut wallets: HashMap<(i32, String), wallets::Model> = wallets::Entity::find()
+        .filter(wallets::Column::UserId.is_in(user_ids))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load margin accounts: {}", e))?
+        .into_iter()
+        .map(|wallet| ((wallet.user_id, wallet.currency.clone()), wallet))
+        .collect();
+
+    let mut assessments: HashMap<(i32, String), Assessment> = HashMap::new();
+    for position in positions {
+        let (Some(event), Some(price)) = (
+            active_events.get(&position.event_id),
+            prices.get(&position.option_id),
+        ) else {
+            continue;
+        };
+        let key = (position.user_id, event.currency.clone());
+        let assessment = assessments
+            .entry(key.clone())
+            .or_insert_with(|| Assessment {
+                user_id: position.user_id,
+                currency: event.currency.clone(),
+                wallet: wallets.remove(&key),
+                shorts: Vec::new(),
+            });
+        assessment.shorts.push(Short {
+            event: event.clone(),
+            option_id: position.option_id,
+            shares: -position.quantity,
+            average_price: position.average_price,
+            current_price: *price,
+        });
+    }
+
+    Ok(assessments
+        .into_values()
+        .filter(|a| !a.shorts.is_empty())
+        .collect())
+}
+
+/// Check every margin account against the maintenance requirement. An account
+/// that falls short gets a margin call; one still short once the grace period
+/// is over has its largest short reduced on each pass until it recovers.
+async fn check_margin_accounts(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    breaches: &mut HashMap<(i32, String), DateTime<Utc>>,
+) -> Result<(), String> {
+    let assessments = load_assessments(db.get_ref()).await?;
+    let now = Utc::now();
+    let grace = Duration::seconds(config::get_margin_call_grace_seconds());
+
+    // Accounts that recovered start a fresh grace period if they fall short again
+    breaches.retain(|(user_id, currency), _| {
+        assessments.iter().any(|a| {
+            a.user_id == *user_id && a.currency == *currency && a.shortfall() > Decimal::ZERO
+        })
+    });
+
+    for assessment in &assessments {
+        let shortfall = assessment.shortfall();
+        let Some(short) = assessment.largest_short() else {
+            continue;
+        };
+        if shortfall <= Decimal::ZERO {
+            continue;
+        }
+
+        let user_id = assessment.user_id;
+        let breach = (user_id, assessment.currency.clone());
+        let result = match breaches.get(&breach) {
+            None => {
+                breaches.insert(breach, now);
+                margin_call(db, redis_pool, ws_server, user_id, short, shortfall, grace).await
+            }
+            Some(since) if now - *since >= grace => {
+                liquidate(db, redis_pool, ws_server, assessment, short, shortfall).await
+            }
+            Some(_) => Ok(()),
+        };
+        if let Err(e) = result {
+            log::error!("Failed to handle margin breach of user {}: {}", user_id, e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn margin_call(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    user_id: i32,
+    short: &Short,
+    shortfall: Decimal,
+    grace: Duration,
+) -> Result<(), String> {
+    let key = (user_id, short.event.id, short.option_id);
+    let reference = format!("margin_call_{}", uuid::Uuid::new_v4());
+    let currency = &short.event.currency;
+    margin::record_margin_call(db.get_ref(), key, currency, shortfall, &reference).await?;
+
+    log::warn!(
+        "Margin call for user {}: {} {} below maintenance",
+        user_id,
+        shortfall,
+        currency
+    );
+
+    dispatcher::dispatch(
+        db.get_ref(),
+        redis_pool.get_ref(),
+        ws_server.get_ref(),
+        user_id,
+        Notification {
+            category: NotificationCategory::Margin,
+            title: "Margin call".to_string(),
+            body: format!(
+                "Your {} margin account is {} below its maintenance requirement. Deposit funds or buy back short positions within {} seconds, or they will be bought back automatically.",
+                currency,
+                shortfall.round_dp(2),
+                grace.num_seconds()
+            ),
+        },
+    )
+    .await
+}
+
+/// Buy back enough of `short` to cover `shortfall`, as far as the account can
+/// pay for it. The collateral of the shares being bought back is freed first
+/// so it can fund the order, and whatever the order leaves uncovered is locked
+/// again from the available balance afterwards.
+async fn liquidate(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    assessment: &Assessment,
+    short: &Short,
+    shortfall: Decimal,
+) -> Result<(), String> {
+    let user_id = assessment.user_id;
+    let currency = &short.event.currency;
+    let key = (user_id, short.event.id, short.option_id);
+
+    // Each share bought back lowers the requirement by its maintenance share
+    let relief_per_share =
+        margin::collateral_per_share(short.current_price) * margin::maintenance_rate();
+    let needed = if relief_per_share > Decimal::ZERO {
+        (shortfall / relief_per_share)
+            .ceil()
+            .to_i32()
+            .unwrap_or(short.shares)
+    } else {
+        short.shares
+    };
+    let mut shares = needed.clamp(1, short.shares);
+
+    let price = liquidation_price(short);
+    let hold_per_share = reservations::hold_amount(price, 1);
+    let freed_per_share = margin::collateral_per_share(short.average_price);
+    if hold_per_share > freed_per_share {
+        let available = balances::available_in(assessment.wallet.as_ref()).max(Decimal::ZERO);
+        let affordable = (available / (hold_per_share - freed_per_share))
+            .floor()
+            .to_i32()
+            .unwrap_or(0);
+        shares = shares.min(affordable);
+    }
+
+    if shares == 0 {
+        ops::publish(
+            OpsEventKind::MarginLiquidation,
+            OpsSeverity::Critical,
+            format!(
+                "Margin account of user {} is {} short and can't pay to buy back any shares",
+                user_id, shortfall
+            ),
+            json!({
+                "user_id": user_id,
+                "event_id": short.event.id,
+                "option_id": short.option_id,
+                "shortfall": shortfall
+            }),
+        );
+        return Err("Nothing affordable to liquidate".to_string());
+    }
+
+    let order = Order::new(
+        user_id,
+        short.event.id,
+        short.option_id,
+        OrderSide::Buy,
+        OrderType::Limit,
+        TimeInForce::IOC,
+        price,
+        shares,
+        currency.clone(),
+    );
+
+    let txn = db
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    margin::unlock_for_liquidation(
+        &txn,
+        key,
+        currency,
+        shares,
+        margin::collateral(short.average_price, shares),
+        &order.id,
+        order.correlation_id.as_deref(),
+    )
+    .await?;
+    txn.commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    let submission = submit_reducing_order(db, redis_pool, ws_server, order.clone()).await;
+
+    // Lock again whatever collateral the order didn't use, even if it failed
+    let txn = db
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    margin::top_up(
+        &txn,
+        key,
+        currency,
+        &order.id,
+        order.correlation_id.as_deref(),
+    )
+    .await?;
+    txn.commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    let trades = submission?;
+    let filled: i32 = trades.iter().map(|t| t.quantity).sum();
+    let notional: Decimal = trades.iter().map(|t| t.total_amount).sum();
+    let fee = charge_liquidation_fee(db.get_ref(), user_id, currency, notional, &order).await?;
+
+    ops::publish(
+        OpsEventKind::MarginLiquidation,
+        OpsSeverity::Warning,
+        format!(
+            "Liquidated {} of {} shares short by user {} in event {} option {}",
+            filled, shares, user_id, short.event.id, short.option_id
+        ),
+        json!({
+            "user_id": user_id,
+            "event_id": short.event.id,
+            "option_id": short.option_id,
+            "order_id": order.id,
+            "shares": shares,
+            "filled": filled,
+            "price": price,
+            "fee": fee,
+            "shortfall": shortfall
+        }),
+    );
+
+    dispatcher::dispatch(
+        db.get_ref(),
+        redis_pool.get_ref(),
+        ws_server.get_ref(),
+        user_id,
+        Notification {
+            category: NotificationCategory::Margin,
+            title: "Short position liquidated".to_string(),
+            body: format!(
+                "{} shares of option {} in event {} were bought back to cover a margin shortfall. Liquidation fee: {}",
+                filled, short.option_id, short.event.id, fee
+            ),
+        },
+    )
+    .await
+}
+
+/// Limit price for a reducing order: `LIQUIDATION_SLIPPAGE_PERCENT` above the
+/// current price, within the event's price band
+fn liquidation_price(short: &Short) -> Decimal {
+    let slippage = Decimal::from(config::get_liquidation_slippage_percent()) / Decimal::ONE_HUNDRED;
+    let price = align_to_tick(
+        &short.event,
+        short.current_price * (Decimal::ONE + slippage),
+    );
+    price.min(short.event.max_price).max(short.event.min_price)
+}
+
+/// Take the liquidation fee on `notional` from the user's wallet in
+/// `currency`, as far as the wallet covers it, returning the amount charged
+async fn charge_liquidation_fee(
+    db: &DatabaseConnection,
+    user_id: i32,
+    currency: &str,
+    notional: Decimal,
+    order: &Order,
+) -> Result<Decimal, String> {
+    let rate = Decimal::from(config::get_liquidation_fee_bps()) / Decimal::from(10_000);
+    let txn = db
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let wallet = balances::lock(&txn, user_id, currency)
+        .await
+        .map_err(|e| format!("Failed to find wallet: {}", e))?;
+    let fee = (notional * rate)
+        .round_dp(2)
+        .min(wallet.balance.max(Decimal::ZERO));
+    if fee <= Decimal::ZERO {
+        return Ok(Decimal::ZERO);
+    }
+
+    let balance_before = wallet.balance;
+    balances::set_balance(&txn, wallet, balance_before - fee)
+        .await
+        .map_err(|e| format!("Failed to charge liquidation fee: {}", e))?;
+
+    transaction::ActiveModel {
+        user_id: Set(user_id),
+        r#type: Set("liquidation_fee".to_string()),
+        amount: Set(fee),
+        currency: Set(currency.to_string()),
+        balance_before: Set(balance_before),
+        balance_after: Set(balance_before - fee),
+        status: Set("completed".to_string()),
+        reference_id: Set(format!("liquidation_{}", order.id)),
+        correlation_id: Set(order.correlation_id.clone()),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(|e| format!("Failed to record liquidation fee: {}", e))?;
+
+    txn.commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    Ok(fee)
+}
+
+/// Place an IOC order on the event's execution venue and settle what it fills,
+/// returning its trades. The unfilled rest is cancelled, releasing its hold.
+async fn submit_reducing_order(
+    db: &web::Data<DatabaseConnection>,
+    redis_pool: &web::Data<Pool>,
+    ws_server: &web::Data<Addr<WebSocketServer>>,
+    order: Order,
+) -> Result<Vec<Trade>, String> {
+    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
+    let db_persistence = DbPersistence::new(db.get_ref().clone());
+    let position_tracker = PositionTracker::new(db.get_ref().clone());
+    let (event_id, option_id) = (order.event_id, order.option_id);
+
+    db_persistence.save_order(&order).await?;
+    if let Err(e) = redis_persistence.save_order(&order).await {
+        log::error!("Failed to save liquidation order to Redis: {}", e);
+    }
+
+    let router::Execution {
+        venue,
+        result: submission,
+        self_trade_prevented,
+        triggered_halt,
+        sequence,
+        ..
+    } = router::submit(db.get_ref(), redis_pool.get_ref(), order.clone()).await?;
+
+    let trades = match submission {
+        Ok(trades) => trades,
+        Err(e) => {
+            let mut rejected_order = order;
+            rejected_order.reject();
+            if let Err(e) = db_persistence.update_order(&rejected_order).await {
+                log::error!("Failed to reject liquidation order in database: {}", e);
+            }
+            if let Err(e) = redis_persistence
+                .update_order_status(&rejected_order.id, OrderStatus::Rejected, 0)
+                .await
+            {
+                log::error!("Failed to update order status in Redis: {}", e);
+            }
+            return Err(format!("Liquidation order rejected: {}", e));
+        }
+    };
+
+    if !trades.is_empty() {
+        settle_trades(
+            db.get_ref(),
+            &db_persistence,
+            &redis_persistence,
+            &position_tracker,
+            &trades,
+        )
+        .await
+        .map_err(|e| format!("Failed to settle liquidation trades: {}", e))?;
+        market_data::publish_trades(&trades, Some(OrderSide::Buy));
+        dispatcher::notify_fills(
+            db.get_ref(),
+            redis_pool.get_ref(),
+            ws_server.get_ref(),
+            &trades,
+        );
+        risk::publish_for_trades(ws_server.get_ref(), &trades);
+    }
+    persist_self_trade_prevented(&db_persistence, &redis_persistence, &self_trade_prevented).await;
+
+    // An external venue reports fills later, so its order stays open until then
+    let filled_quantity: i32 = trades.iter().map(|t| t.quantity).sum();
+    if filled_quantity < order.quantity && venue != ExecutionVenue::External {
+        let mut cancelled_order = order;
+        cancelled_order.filled_quantity = filled_quantity;
+        cancelled_order.cancel();
+        if let Err(e) = db_persistence.update_order(&cancelled_order).await {
+            log::error!("Failed to cancel rest of liquidation order: {}", e);
+        }
+        if let Err(e) = redis_persistence.save_order(&cancelled_order).await {
+            log::error!("Failed to update liquidation order in Redis: {}", e);
+        }
+    }
+
+    if let Some(halt) = triggered_halt {
+        report_circuit_breaker(event_id, option_id, &halt);
+        broadcast_trading_status(ws_server, event_id, option_id, sequence, Some(halt), None);
+    }
+
+    let cache_service = CacheService::new(redis_pool.get_ref().clone());
+    let event_order_books_cache_key = format!("order_books:event:{}", event_id);
+    if let Err(e) = cache_service.delete(&event_order_books_cache_key).await {
+        log::warn!("Failed to invalidate event order books cache: {}", e);
+    }
+
+    crate::order_book::price_updater::update_option_price_immediately(
+        db.clone(),
+        redis_pool.clone(),
+        ws_server.clone(),
+        event_id,
+        option_id,
+        if trades.is_empty() {
+            PriceUpdateSource::BookImbalance
+        } else {
+            PriceUpdateSource::Trade
+        },
+    )
+    .await;
+
+    Ok(trades)
+}
+
+/// Start a background task that checks margin accounts after price moves and
+/// liquidates those left below maintenance past the grace period
+pub fn start_liquidation_monitor(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+    ws_server: web::Data<Addr<WebSocketServer>>,
+) {
+    let interval_seconds = config::get_margin_check_interval_seconds();
+
+    log::info!(
+        "Starting liquidation monitor with {}-second interval",
+        interval_seconds
+    );
+
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(interval_seconds));
+        // When each account was found below maintenance. Kept in memory, so a
+        // restart gives accounts in breach a fresh grace period.
+        let mut breaches: HashMap<(i32, String), DateTime<Utc>> = HashMap::new();
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = check_margin_accounts(&db, &redis_pool, &ws_server, &mut breaches).await
+            {
+                log::error!("Failed to check margin accounts: {}", e);
+                ops::publish(
+                    OpsEventKind::JobFailed,
+                    OpsSeverity::Critical,
+                    "Liquidation monitor could not check margin accounts",
+                    json!({ "job": "liquidation_monitor", "error": e }),
+                );
+            }
+        }
+    });
+}
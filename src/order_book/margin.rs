@@ -0,0 +1,362 @@
+use super::position_cache::PositionKey;
+use crate::constants::config;
+use crate::types::margin::MarginLedgerKind;
+use crate::wallets::balances;
+use entity::{events, margin_ledger, user_positions, users, wallets};
+use sea_orm::{
+    prelude::Decimal, sea_query::Query, ActiveModelTrait, ColumnTrait, ConnectionTrait,
+    EntityTrait, QueryFilter, QuerySelect, Set,
+};
+
+/// What a winning share pays at settlement
+pub const PAYOUT_PER_SHARE: Decimal = Decimal::from_parts(10000, 0, 0, false, 2);
+
+/// Reason `lock` fails when the user's available balance can't cover it
+pub const INSUFFICIENT_COLLATERAL: &str = "insufficient_collateral";
+
+/// Collateral for one share sold short at `price`: the most the seller can
+/// lose, since they owe `PAYOUT_PER_SHARE` if the option wins
+pub fn collateral_per_share(price: Decimal) -> Decimal {
+    (PAYOUT_PER_SHARE - price).max(Decimal::ZERO)
+}
+
+/// Collateral for `shares` sold short at `price`
+pub fn collateral(price: Decimal, shares: i32) -> Decimal {
+    collateral_per_share(price) * Decimal::from(shares.max(0))
+}
+
+/// `MARGIN_MAINTENANCE_PERCENT` as a fraction
+pub fn maintenance_rate() -> Decimal {
+    Decimal::from(config::get_margin_maintenance_percent()) / Decimal::ONE_HUNDRED
+}
+
+/// Shares of a `quantity` sale that go short, given the `held` position before it
+pub fn shares_shorted(held: i32, quantity: i32) -> i32 {
+    (quantity - held.max(0)).max(0)
+}
+
+/// Shares of a `quantity` purchase that buy back a short, given the `held`
+/// position before it
+pub fn shares_covered(held: i32, quantity: i32) -> i32 {
+    quantity.min((-held).max(0))
+}
+
+/// Check that `user` may sell `quantity` at `price` from a position of `held`
+/// shares, with `covered` shares of the other option of a binary event.
+/// Selling beyond the position is covered by those shares, and beyond them
+/// needs a margin account and stays within `MAX_SHORT_POSITION`. Every share
+/// sold beyond the position needs `available` balance in the event's
+/// currency for its collateral, covered or not, since the other option's
+/// shares pay nothing exactly when the short loses.
+pub fn validate_sell(
+    user: &users::Model,
+    available: Decimal,
+    held: i32,
+    covered: i32,
+    quantity: i32,
+    price: Decimal,
+) -> Result<(), String> {
+    let shorted = shares_shorted(held, quantity);
+    if shorted == 0 {
+        return Ok(());
+    }
+
+    let max_short_position = if user.margin_enabled {
+        config::get_max_short_position()
+    } else {
+        0
+    };
+    if held - quantity < -(covered.max(0) + max_short_position) {
+        if !user.margin_enabled {
+            return Err("Insufficient shares to sell".to_string());
+        }
+        return Err(format!(
+            "Short position would exceed the maximum of {} shares",
+            max_short_position
+        ));
+    }
+
+    if available < collateral(price, shorted) {
+        return Err("Insufficient balance for short collateral".to_string());
+    }
+    Ok(())
+}
+
+/// Move `amount` of a user's available balance in `currency` into their
+/// margin balance as collateral for `shares` sold short, failing with
+/// `INSUFFICIENT_COLLATERAL` when it isn't there
+pub async fn lock<C: ConnectionTrait>(
+    conn: &C,
+    key: PositionKey,
+    currency: &str,
+    shares: i32,
+    amount: Decimal,
+    reference: &str,
+    correlation_id: Option<&str>,
+) -> Result<(), String> {
+    let wallet = locked_wallet(conn, key.0, currency).await?;
+    if balances::available(&wallet) < amount {
+        return Err(INSUFFICIENT_COLLATERAL.to_string());
+    }
+
+    let balance = wallet.balance - amount;
+    move_collateral(
+        conn,
+        wallet,
+        balance,
+        amount,
+        MarginLedgerKind::Lock,
+        key,
+        shares,
+        reference,
+        correlation_id,
+    )
+    .await
+}
+
+/// Return collateral to a user's wallet once `shares` of their short are
+/// bought back or expire worthless. Whatever the margin balance holds beyond
+/// what the remaining short positions require is released, so collateral
+/// already freed by a liquidation isn't paid out twice.
+pub async fn release<C: ConnectionTrait>(
+    conn: &C,
+    key: PositionKey,
+    currency: &str,
+    shares: i32,
+    reference: &str,
+    correlation_id: Option<&str>,
+) -> Result<(), String> {
+    let wallet = locked_wallet(conn, key.0, currency).await?;
+    let amount =
+        (wallet.margin_balance - requirement(conn, key.0, currency).await?).max(Decimal::ZERO);
+    if amount.is_zero() {
+        return Ok(());
+    }
+    let balance = wallet.balance + amount;
+    move_collateral(
+        conn,
+        wallet,
+        balance,
+        -amount,
+        MarginLedgerKind::Release,
+        key,
+        shares,
+        reference,
+        correlation_id,
+    )
+    .await
+}
+
+/// Give up up to `amount` of collateral for `shares` of a short whose option
+/// won, returning how much the margin balance covered
+pub async fn forfeit<C: ConnectionTrait>(
+    conn: &C,
+    key: PositionKey,
+    currency: &str,
+    shares: i32,
+    amount: Decimal,
+    reference: &str,
+) -> Result<Decimal, String> {
+    let wallet = locked_wallet(conn, key.0, currency).await?;
+    let amount = amount.min(wallet.margin_balance);
+    let balance = wallet.balance;
+    move_collateral(
+        conn,
+        wallet,
+        balance,
+        -amount,
+        MarginLedgerKind::Settlement,
+        key,
+        shares,
+        reference,
+        None,
+    )
+    .await?;
+    Ok(amount)
+}
+
+/// Move `amount` of collateral for `shares` to the wallet ahead of a
+/// liquidation, so the reducing order buying them back can be paid for
+pub async fn unlock_for_liquidation<C: ConnectionTrait>(
+    conn: &C,
+    key: PositionKey,
+    currency: &str,
+    shares: i32,
+    amount: Decimal,
+    reference: &str,
+    correlation_id: Option<&str>,
+) -> Result<(), String> {
+    let wallet = locked_wallet(conn, key.0, currency).await?;
+    let amount = amount.min(wallet.margin_balance);
+    let balance = wallet.balance + amount;
+    move_collateral(
+        conn,
+        wallet,
+        balance,
+        -amount,
+        MarginLedgerKind::Liquidation,
+        key,
+        shares,
+        reference,
+        correlation_id,
+    )
+    .await
+}
+
+/// Lock as much of the available balance in `currency` as is needed to bring
+/// the margin balance back up to what the user's short positions in that
+/// currency require, returning the amount locked
+pub async fn top_up<C: ConnectionTrait>(
+    conn: &C,
+    key: PositionKey,
+    currency: &str,
+    reference: &str,
+    correlation_id: Option<&str>,
+) -> Result<Decimal, String> {
+    let wallet = locked_wallet(conn, key.0, currency).await?;
+    let deficit = requirement(conn, key.0, currency).await? - wallet.margin_balance;
+    let amount = deficit.min(balances::available(&wallet));
+    if amount <= Decimal::ZERO {
+        return Ok(Decimal::ZERO);
+    }
+
+    let balance = wallet.balance - amount;
+    move_collateral(
+        conn,
+        wallet,
+        balance,
+        amount,
+        MarginLedgerKind::Lock,
+        key,
+        0,
+        reference,
+        correlation_id,
+    )
+    .await?;
+    Ok(amount)
+}
+
+/// Record that a user's account fell `shortfall` short of the maintenance
+/// requirement, against the short position that would be reduced first
+pub async fn record_margin_call<C: ConnectionTrait>(
+    conn: &C,
+    key: PositionKey,
+    currency: &str,
+    shortfall: Decimal,
+    reference: &str,
+) -> Result<(), String> {
+    let (user_id, event_id, option_id) = key;
+    let wallet = locked_wallet(conn, user_id, currency).await?;
+
+    margin_ledger::ActiveModel {
+        user_id: Set(user_id),
+        event_id: Set(event_id),
+        option_id: Set(option_id),
+        kind: Set(MarginLedgerKind::MarginCall.as_str().to_string()),
+        shares: Set(0),
+        amount: Set(shortfall),
+        margin_before: Set(wallet.margin_balance),
+        margin_after: Set(wallet.margin_balance),
+        reference: Set(reference.to_string()),
+        created_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await
+    .map_err(|e| format!("Failed to record margin call: {}", e))?;
+
+    Ok(())
+}
+
+/// Collateral a user's open short positions in events listed in `currency`
+/// require at their sale prices
+pub async fn requirement<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    currency: &str,
+) -> Result<Decimal, String> {
+    let shorts: Vec<(i32, Decimal)> = user_positions::Entity::find()
+        .select_only()
+        .column(user_positions::Column::Quantity)
+        .column(user_positions::Column::AveragePrice)
+        .filter(user_positions::Column::UserId.eq(user_id))
+        .filter(user_positions::Column::Quantity.lt(0))
+        .filter(
+            user_positions::Column::EventId.in_subquery(
+                Query::select()
+                    .column(events::Column::Id)
+                    .from(events::Entity)
+                    .and_where(events::Column::Currency.eq(currency))
+                    .to_owned(),
+            ),
+        )
+        .into_tuple()
+        .all(conn)
+        .await
+        .map_err(|e| format!("Failed to load short positions: {}", e))?;
+
+    Ok(shorts
+        .into_iter()
+        .map(|(quantity, average_price)| collateral(average_price, -quantity))
+        .sum())
+}
+
+async fn locked_wallet<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    currency: &str,
+) -> Result<wallets::Model, String> {
+    balances::lock(conn, user_id, currency).await.map_err(|e| {
+        format!(
+            "Failed to find {} wallet of user {}: {}",
+            currency, user_id, e
+        )
+    })
+}
+
+/// Apply a change of `margin_change` to the wallet's margin balance, set its
+/// balance, and record the movement in the margin ledger
+#[allow(clippy::too_many_arguments)]
+async fn move_collateral<C: ConnectionTrait>(
+    conn: &C,
+    wallet: wallets::Model,
+    balance: Decimal,
+    margin_change: Decimal,
+    kind: MarginLedgerKind,
+    (user_id, event_id, option_id): PositionKey,
+    shares: i32,
+    reference: &str,
+    correlation_id: Option<&str>,
+) -> Result<(), String> {
+    let margin_before = wallet.margin_balance;
+    let margin_after = margin_before + margin_change;
+
+    let mut active_wallet: wallets::ActiveModel = wallet.into();
+    active_wallet.balance = Set(balance);
+    active_wallet.margin_balance = Set(margin_after);
+    active_wallet.updated_at = Set(chrono::Utc::now().fixed_offset());
+    active_wallet
+        .update(conn)
+        .await
+        .map_err(|e| format!("Failed to update margin balance: {}", e))?;
+
+    margin_ledger::ActiveModel {
+        user_id: Set(user_id),
+        event_id: Set(event_id),
+        option_id: Set(option_id),
+        kind: Set(kind.as_str().to_string()),
+        shares: Set(shares),
+        amount: Set(margin_change.abs()),
+        margin_before: Set(margin_before),
+        margin_after: Set(margin_after),
+        reference: Set(reference.to_string()),
+        correlation_id: Set(correlation_id.map(str::to_string)),
+        created_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await
+    .map_err(|e| format!("Failed to record margin ledger entry: {}", e))?;
+
+    Ok(())
+}
@@ -1,14 +1,35 @@
 use super::{
-    db_persistence::DbPersistence, redis_persistence::RedisOrderBookPersistence, Order, OrderSide,
-    OrderType, TimeInForce,
+    db_persistence::DbPersistence,
+    limits::{align_to_tick, validate_order_limits},
+    matching_service, position_cache,
+    position_tracker::PositionTracker,
+    redis_persistence::RedisOrderBookPersistence,
+    types::OrderStatus,
+    Order, OrderSide, OrderType, TimeInForce,
 };
-use deadpool_redis::Pool;
-use entity::user_positions;
-use sea_orm::{prelude::Decimal, DatabaseConnection};
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use crate::constants::config;
+use crate::settings::registry as settings;
+use crate::types::settings::SettingKey;
+use actix_web::web;
+use deadpool_redis::{redis::AsyncCommands, Pool};
+use entity::{event_options, events, trades, user_positions};
+use sea_orm::{prelude::Decimal, sea_query::Expr, DatabaseConnection};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::error::Error;
+use std::time::Duration;
+
+/// Hash of the markets the market maker re-quotes, `{event}:{option}` to
+/// their `QuotingState`
+const QUOTING_KEY: &str = "market_maker:quoting";
+
+/// Most price levels a market maker quotes on each side
+const MAX_DEPTH_LEVELS: usize = 50;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MarketMakerConfig {
     /// The user ID that will act as the market maker
     pub market_maker_user_id: i32,
@@ -22,6 +43,8 @@ pub struct MarketMakerConfig {
     pub level_quantity: i32,
     /// Price step between levels (e.g., 1.00)
     pub price_step: Decimal,
+    /// Most shares the market maker holds before it stops bidding
+    pub max_inventory: i32,
 }
 
 impl Default for MarketMakerConfig {
@@ -33,8 +56,234 @@ impl Default for MarketMakerConfig {
             depth_levels: 5,
             level_quantity: 100,
             price_step: Decimal::new(100, 2), // 1.00
+            max_inventory: 1000,
+        }
+    }
+}
+
+impl MarketMakerConfig {
+    /// Check the config can quote: a spread below 100%, at most
+    /// `MAX_DEPTH_LEVELS` levels of a positive quantity and price step, and a
+    /// non-negative inventory limit
+    pub fn validate(&self) -> Result<(), String> {
+        if self.spread_percentage < Decimal::ZERO || self.spread_percentage >= Decimal::ONE {
+            return Err("Spread percentage must be at least 0 and below 1".to_string());
+        }
+        if self.depth_levels == 0 || self.depth_levels > MAX_DEPTH_LEVELS {
+            return Err(format!(
+                "Depth levels must be between 1 and {}",
+                MAX_DEPTH_LEVELS
+            ));
+        }
+        if self.level_quantity <= 0 {
+            return Err("Level quantity must be positive".to_string());
+        }
+        if self.price_step <= Decimal::ZERO {
+            return Err("Price step must be positive".to_string());
+        }
+        if self.max_inventory < 0 {
+            return Err("Max inventory can't be negative".to_string());
         }
+        Ok(())
+    }
+
+    /// Shares a seeding with this config lists on the ask side
+    pub fn seeded_shares(&self) -> i32 {
+        self.level_quantity * self.depth_levels as i32
     }
+
+    /// The `mm_*` settings as they apply to a tenant, for `market_maker_user_id`
+    /// seeding at `initial_price`
+    pub fn from_settings(
+        tenant_id: Option<i32>,
+        market_maker_user_id: i32,
+        initial_price: Decimal,
+    ) -> Self {
+        Self {
+            market_maker_user_id,
+            initial_price,
+            spread_percentage: settings::decimal(tenant_id, SettingKey::MmSpreadPercentage),
+            depth_levels: settings::integer(tenant_id, SettingKey::MmDepthLevels)
+                .try_into()
+                .unwrap_or(0),
+            level_quantity: settings::integer(tenant_id, SettingKey::MmLevelQuantity)
+                .try_into()
+                .unwrap_or(0),
+            price_step: settings::decimal(tenant_id, SettingKey::MmPriceStep),
+            max_inventory: settings::integer(tenant_id, SettingKey::MmMaxInventory)
+                .try_into()
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// What the market maker has resting on a market it re-quotes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Quotes {
+    pub order_ids: Vec<String>,
+    /// Price the resting quotes were placed around
+    pub reference_price: Option<Decimal>,
+}
+
+/// A market the market maker re-quotes, as kept in `QUOTING_KEY`. Every
+/// instance reads it before each check, so changes made through the admin API
+/// reach whichever instance runs the market's task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotingState {
+    pub config: MarketMakerConfig,
+    pub quotes: Quotes,
+    /// Whether the market is quoted; a disabled market keeps its config
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Id of the task quoting the market. A task whose id no longer matches
+    /// has been replaced and stops.
+    #[serde(default)]
+    pub task_id: String,
+    /// Shares minted for the market maker by seedings of the market
+    #[serde(default)]
+    pub seeded_shares: i32,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// The market maker's result on one market: what its trades brought in, and
+/// the shares it gained or lost since seeding, valued at the option's
+/// current price
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketMakerPnl {
+    /// Shares held now, its inventory in the option
+    pub position: i32,
+    pub seeded_shares: i32,
+    pub bought: i32,
+    pub sold: i32,
+    /// Sale proceeds less purchase costs and fees
+    pub cash_flow: Decimal,
+    /// Fees the market maker paid on its fills
+    pub fees: Decimal,
+    /// Fees its counterparties paid on those fills
+    pub fees_earned: Decimal,
+    /// Shares bought less shares sold, still open
+    pub open_quantity: i32,
+    /// Average price of the open shares
+    pub average_cost: Decimal,
+    /// Gains on fills that closed earlier ones, against their average cost
+    pub realized_pnl: Decimal,
+    /// Gains on the open shares at `mark_price`
+    pub unrealized_pnl: Decimal,
+    pub mark_price: Decimal,
+    /// `realized_pnl` plus `unrealized_pnl` less `fees`
+    pub pnl: Decimal,
+}
+
+/// Result of one check of a re-quoted market
+enum QuoteOutcome {
+    /// The quotes still rest untouched around the current price
+    Unchanged,
+    /// The quotes were replaced around the current price
+    Requoted,
+    /// Other traders quote tighter than the market maker would, so it has
+    /// stepped out of the book
+    BackedOff,
+    /// The event no longer trades
+    Stopped,
+}
+
+/// The book as the market maker sees it before re-quoting
+struct BookView {
+    last_trade_price: Option<Decimal>,
+    mid_price: Option<Decimal>,
+    /// Best bid and ask of other traders' orders
+    best_bid: Option<Decimal>,
+    best_ask: Option<Decimal>,
+    /// Whether every quote still rests without having traded
+    quotes_untouched: bool,
+}
+
+fn quoting_field(event_id: i32, option_id: i32) -> String {
+    format!("{}:{}", event_id, option_id)
+}
+
+/// The market maker's state on a market, if it has ever quoted it
+pub async fn quoting_state(
+    redis_pool: &Pool,
+    event_id: i32,
+    option_id: i32,
+) -> Result<Option<QuotingState>, String> {
+    let mut conn = redis_pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+    let state: Option<String> = conn
+        .hget(QUOTING_KEY, quoting_field(event_id, option_id))
+        .await
+        .map_err(|e| format!("Failed to load quoting state: {}", e))?;
+    state
+        .map(|state| {
+            serde_json::from_str(&state)
+                .map_err(|e| format!("Failed to parse quoting state: {}", e))
+        })
+        .transpose()
+}
+
+/// Every market the market maker has quoted, enabled or not, with its state
+pub async fn quoted_markets(redis_pool: &Pool) -> Result<Vec<(i32, i32, QuotingState)>, String> {
+    let mut conn = redis_pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+    let markets: Vec<(String, String)> = conn
+        .hgetall(QUOTING_KEY)
+        .await
+        .map_err(|e| format!("Failed to load quoted markets: {}", e))?;
+
+    Ok(markets
+        .into_iter()
+        .filter_map(|(field, state)| {
+            let market = field.split_once(':').and_then(|(event_id, option_id)| {
+                Some((event_id.parse().ok()?, option_id.parse().ok()?))
+            });
+            match (market, serde_json::from_str::<QuotingState>(&state)) {
+                (Some((event_id, option_id)), Ok(state)) => Some((event_id, option_id, state)),
+                _ => {
+                    log::warn!("Skipping unreadable market maker state for {}", field);
+                    None
+                }
+            }
+        })
+        .collect())
+}
+
+async fn store_quoting_state(
+    redis_pool: &Pool,
+    event_id: i32,
+    option_id: i32,
+    state: &QuotingState,
+) -> Result<(), String> {
+    let serialized = serde_json::to_string(state)
+        .map_err(|e| format!("Failed to serialize quoting state: {}", e))?;
+    let mut conn = redis_pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+    conn.hset::<_, _, _, ()>(QUOTING_KEY, quoting_field(event_id, option_id), serialized)
+        .await
+        .map_err(|e| format!("Failed to save quoting state: {}", e))
+}
+
+/// Best price of a side, in priority order, with an order from someone other
+/// than `user_id`
+fn best_other_price<'a>(
+    mut levels: impl Iterator<Item = (&'a Decimal, &'a VecDeque<Order>)>,
+    user_id: i32,
+) -> Option<Decimal> {
+    levels.find_map(|(price, orders)| {
+        orders
+            .iter()
+            .any(|order| order.user_id != user_id)
+            .then_some(*price)
+    })
 }
 
 pub struct MarketMaker {
@@ -52,19 +301,23 @@ impl MarketMaker {
         }
     }
 
-    /// Create initial position for the market maker to enable sell orders
+    /// Create initial position for the market maker to enable sell orders.
+    /// The shares are recorded as issued for the option in the same
+    /// transaction, so the conservation audit can account for them.
     async fn create_market_maker_position(
         &self,
         event_id: i32,
         option_id: i32,
         total_shares_needed: i32,
     ) -> Result<(), Box<dyn Error>> {
+        let txn = self.db.begin().await?;
+
         // Check if position already exists
         let existing = user_positions::Entity::find()
             .filter(user_positions::Column::UserId.eq(self.config.market_maker_user_id))
             .filter(user_positions::Column::EventId.eq(event_id))
             .filter(user_positions::Column::OptionId.eq(option_id))
-            .one(&self.db)
+            .one(&txn)
             .await?;
 
         match existing {
@@ -74,7 +327,7 @@ impl MarketMaker {
                 let mut active_position: user_positions::ActiveModel = position.into();
                 active_position.quantity = Set(new_quantity);
                 active_position.updated_at = Set(chrono::Utc::now().into());
-                active_position.update(&self.db).await?;
+                active_position.update(&txn).await?;
 
                 log::info!(
                     "Updated market maker position for option {}: {} shares (total: {})",
@@ -96,7 +349,7 @@ impl MarketMaker {
                     ..Default::default()
                 };
 
-                new_position.insert(&self.db).await?;
+                new_position.insert(&txn).await?;
 
                 log::info!(
                     "Created market maker position for option {}: {} shares",
@@ -106,17 +359,60 @@ impl MarketMaker {
             }
         }
 
+        event_options::Entity::update_many()
+            .col_expr(
+                event_options::Column::SharesIssued,
+                Expr::col(event_options::Column::SharesIssued).add(total_shares_needed),
+            )
+            .filter(event_options::Column::Id.eq(option_id))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        position_cache::invalidate(self.config.market_maker_user_id, event_id, option_id);
+
         Ok(())
     }
 
+    /// Prices of the levels on one side of `reference_price`, stopping once
+    /// they leave the event's price band
+    fn level_prices(
+        &self,
+        event: &events::Model,
+        reference_price: Decimal,
+        side: OrderSide,
+    ) -> Vec<Decimal> {
+        let half_spread = self.config.spread_percentage / Decimal::from(2);
+        let mut prices = Vec::with_capacity(self.config.depth_levels);
+        for i in 0..self.config.depth_levels {
+            let offset = self.config.price_step * Decimal::from(i as i64);
+            let price = match side {
+                OrderSide::Sell => reference_price * (Decimal::from(1) + half_spread) + offset,
+                OrderSide::Buy => reference_price * (Decimal::from(1) - half_spread) - offset,
+            };
+            let price = align_to_tick(event, price);
+            if validate_order_limits(event, price, self.config.level_quantity, 1).is_err() {
+                break;
+            }
+            prices.push(price);
+        }
+        prices
+    }
+
     /// Seed initial liquidity for an event option
     pub async fn seed_initial_liquidity(
         &self,
         event_id: i32,
         option_id: i32,
     ) -> Result<Vec<String>, Box<dyn Error>> {
+        let event = events::Entity::find_by_id(event_id)
+            .one(&self.db)
+            .await?
+            .ok_or("Event not found")?;
+
         // Calculate total shares needed for sell orders
-        let total_shares_needed = self.config.level_quantity * self.config.depth_levels as i32;
+        let total_shares_needed = self.config.seeded_shares();
 
         // Create market maker position first (before creating sell orders)
         self.create_market_maker_position(event_id, option_id, total_shares_needed)
@@ -125,26 +421,11 @@ impl MarketMaker {
         let redis_persistence = RedisOrderBookPersistence::new(self.redis_pool.clone());
         let db_persistence = DbPersistence::new(self.db.clone());
 
-        // Get or create the order book
-        let mut order_book = redis_persistence
-            .get_or_create_order_book(event_id, option_id)
-            .await?;
-
         let mut order_ids = Vec::new();
-
-        // Calculate spread
-        let half_spread = self.config.spread_percentage / Decimal::from(2);
+        let mut seeded_orders = Vec::new();
 
         // Create sell orders (asks) above the initial price
-        let ask_base_price = self.config.initial_price * (Decimal::from(1) + half_spread);
-        for i in 0..self.config.depth_levels {
-            let price = ask_base_price + (self.config.price_step * Decimal::from(i as i64));
-
-            // Don't exceed 100.00 for prediction markets
-            if price > Decimal::new(10000, 2) {
-                break;
-            }
-
+        for price in self.level_prices(&event, self.config.initial_price, OrderSide::Sell) {
             let order = Order::new(
                 self.config.market_maker_user_id,
                 event_id,
@@ -154,6 +435,7 @@ impl MarketMaker {
                 TimeInForce::GTC,
                 price,
                 self.config.level_quantity,
+                event.currency.clone(),
             );
 
             // Save to database
@@ -162,20 +444,11 @@ impl MarketMaker {
 
             order_ids.push(order.id.clone());
 
-            // Add to order book
-            order_book.add_order_directly(order);
+            seeded_orders.push(order);
         }
 
         // Create buy orders (bids) below the initial price
-        let bid_base_price = self.config.initial_price * (Decimal::from(1) - half_spread);
-        for i in 0..self.config.depth_levels {
-            let price = bid_base_price - (self.config.price_step * Decimal::from(i as i64));
-
-            // Don't go below 0.00
-            if price <= Decimal::new(0, 2) {
-                break;
-            }
-
+        for price in self.level_prices(&event, self.config.initial_price, OrderSide::Buy) {
             let order = Order::new(
                 self.config.market_maker_user_id,
                 event_id,
@@ -185,6 +458,7 @@ impl MarketMaker {
                 TimeInForce::GTC,
                 price,
                 self.config.level_quantity,
+                event.currency.clone(),
             );
 
             // Save to database
@@ -193,14 +467,16 @@ impl MarketMaker {
 
             order_ids.push(order.id.clone());
 
-            // Add to order book
-            order_book.add_order_directly(order);
+            seeded_orders.push(order);
         }
 
-        // Save the updated order book
-        redis_persistence
-            .save_full_order_book(event_id, option_id, &order_book)
-            .await?;
+        // Rest the seeded levels on the order book
+        matching_service::execute(&self.redis_pool, event_id, option_id, move |order_book| {
+            for order in seeded_orders {
+                order_book.add_order_directly(order);
+            }
+        })
+        .await?;
 
         log::info!(
             "Seeded {} orders for event {} option {} with initial price {} (market maker has {} shares)",
@@ -213,4 +489,472 @@ impl MarketMaker {
 
         Ok(order_ids)
     }
+
+    /// Keep re-quoting a market in the background, starting from the orders
+    /// that seeded it, if any. `seeded_shares` is how many shares seedings of
+    /// the market have minted for the market maker in all. The market is
+    /// recorded in Redis so quoting resumes after a restart, and any task
+    /// already quoting it stops at its next check.
+    pub async fn start_quoting(
+        self,
+        event_id: i32,
+        option_id: i32,
+        order_ids: Vec<String>,
+        seeded_shares: i32,
+    ) -> Result<(), String> {
+        let reference_price = (!order_ids.is_empty()).then_some(self.config.initial_price);
+        let task_id = uuid::Uuid::new_v4().to_string();
+        let state = QuotingState {
+            config: self.config.clone(),
+            quotes: Quotes {
+                order_ids,
+                reference_price,
+            },
+            enabled: true,
+            task_id: task_id.clone(),
+            seeded_shares,
+        };
+        store_quoting_state(&self.redis_pool, event_id, option_id, &state).await?;
+        tokio::spawn(self.quote_market(event_id, option_id, task_id));
+        Ok(())
+    }
+
+    /// Stop quoting a market and cancel its resting quotes, keeping its config
+    /// so it can be enabled again
+    pub async fn disable(&self, event_id: i32, option_id: i32) -> Result<(), String> {
+        let Some(mut state) = quoting_state(&self.redis_pool, event_id, option_id).await? else {
+            return Ok(());
+        };
+        state.enabled = false;
+        let order_ids = std::mem::take(&mut state.quotes.order_ids);
+        state.quotes.reference_price = None;
+        store_quoting_state(&self.redis_pool, event_id, option_id, &state).await?;
+        self.cancel_quotes(event_id, option_id, order_ids).await
+    }
+
+    /// Replace the config of a market the market maker has quoted. Its quotes
+    /// are replaced under the new config at the next check.
+    pub async fn reconfigure(&self, event_id: i32, option_id: i32) -> Result<(), String> {
+        let mut state = quoting_state(&self.redis_pool, event_id, option_id)
+            .await?
+            .ok_or("Market maker has not quoted this option")?;
+        state.config = self.config.clone();
+        state.quotes.reference_price = None;
+        store_quoting_state(&self.redis_pool, event_id, option_id, &state).await
+    }
+
+    /// The market maker's result on a market from its trades and position
+    pub async fn pnl(
+        &self,
+        event_id: i32,
+        option_id: i32,
+        seeded_shares: i32,
+    ) -> Result<MarketMakerPnl, String> {
+        let user_id = self.config.market_maker_user_id;
+        let fills = trades::Entity::find()
+            .filter(trades::Column::EventId.eq(event_id))
+            .filter(trades::Column::OptionId.eq(option_id))
+            .filter(
+                trades::Column::BuyerId
+                    .eq(user_id)
+                    .or(trades::Column::SellerId.eq(user_id)),
+            )
+            .order_by_asc(trades::Column::Sequence)
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load market maker trades: {}", e))?;
+
+        let (mut bought, mut sold) = (0, 0);
+        let (mut cash_flow, mut fees, mut fees_earned) =
+            (Decimal::ZERO, Decimal::ZERO, Decimal::ZERO);
+        // Open shares, signed, at their average cost
+        let (mut open, mut average_cost, mut realized_pnl) = (0i32, Decimal::ZERO, Decimal::ZERO);
+        for fill in fills {
+            let mut sides = Vec::with_capacity(2);
+            if fill.buyer_id == user_id {
+                bought += fill.quantity;
+                cash_flow -= fill.total_amount;
+                fees += fill.buyer_fee;
+                fees_earned += fill.seller_fee;
+                sides.push(fill.quantity);
+            }
+            if fill.seller_id == user_id {
+                sold += fill.quantity;
+                cash_flow += fill.total_amount;
+                fees += fill.seller_fee;
+                fees_earned += fill.buyer_fee;
+                sides.push(-fill.quantity);
+            }
+            // Trading with itself earns it nothing
+            if sides.len() == 2 {
+                fees_earned -= fill.buyer_fee + fill.seller_fee;
+            }
+
+            for quantity in sides {
+                if open == 0 || open.signum() == quantity.signum() {
+                    average_cost = (average_cost * Decimal::from(open.abs())
+                        + fill.price * Decimal::from(quantity.abs()))
+                        / Decimal::from(open.abs() + quantity.abs());
+                    open += quantity;
+                    continue;
+                }
+                let closed = quantity.abs().min(open.abs());
+                realized_pnl += (fill.price - average_cost) * Decimal::from(closed * open.signum());
+                open += quantity;
+                if open == 0 {
+                    average_cost = Decimal::ZERO;
+                } else if open.signum() == quantity.signum() {
+                    average_cost = fill.price;
+                }
+            }
+        }
+        cash_flow -= fees;
+
+        let position = PositionTracker::new(self.db.clone())
+            .get_user_position(user_id, event_id, option_id)
+            .await?
+            .quantity;
+        let mark_price = event_options::Entity::find_by_id(option_id)
+            .one(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load option: {}", e))?
+            .map(|option| option.current_price)
+            .unwrap_or(self.config.initial_price);
+        let unrealized_pnl = (mark_price - average_cost) * Decimal::from(open);
+
+        Ok(MarketMakerPnl {
+            position,
+            seeded_shares,
+            bought,
+            sold,
+            cash_flow,
+            fees,
+            fees_earned,
+            open_quantity: open,
+            average_cost: average_cost.round_dp(8),
+            realized_pnl: realized_pnl.round_dp(8),
+            unrealized_pnl: unrealized_pnl.round_dp(8),
+            mark_price,
+            pnl: (realized_pnl + unrealized_pnl - fees).round_dp(8),
+        })
+    }
+
+    /// Check a market every `MARKET_MAKER_REQUOTE_INTERVAL_SECONDS` until its
+    /// event stops trading or it is disabled or handed to another task,
+    /// waiting twice as long after each check that finds the spread too tight,
+    /// up to `MARKET_MAKER_MAX_BACKOFF_SECONDS`
+    async fn quote_market(mut self, event_id: i32, option_id: i32, task_id: String) {
+        let interval = Duration::from_secs(config::get_market_maker_requote_interval_seconds());
+        let max_backoff = Duration::from_secs(config::get_market_maker_max_backoff_seconds());
+        let mut wait = interval;
+
+        loop {
+            tokio::time::sleep(wait).await;
+            let mut quotes = match quoting_state(&self.redis_pool, event_id, option_id).await {
+                Ok(Some(state)) if state.enabled && state.task_id == task_id => {
+                    self.config = state.config;
+                    state.quotes
+                }
+                Ok(_) => {
+                    log::info!(
+                        "Market maker task for event {} option {} was disabled or replaced",
+                        event_id,
+                        option_id
+                    );
+                    return;
+                }
+                Err(e) => {
+                    log::warn!("{}", e);
+                    continue;
+                }
+            };
+            match self
+                .requote(event_id, option_id, &task_id, &mut quotes)
+                .await
+            {
+                Ok(QuoteOutcome::Stopped) => {
+                    log::info!(
+                        "Market maker stopped quoting event {} option {}",
+                        event_id,
+                        option_id
+                    );
+                    return;
+                }
+                Ok(QuoteOutcome::BackedOff) => wait = (wait * 2).min(max_backoff.max(interval)),
+                Ok(QuoteOutcome::Unchanged | QuoteOutcome::Requoted) => wait = interval,
+                Err(e) => log::warn!(
+                    "Market maker failed to re-quote event {} option {}: {}",
+                    event_id,
+                    option_id,
+                    e
+                ),
+            }
+        }
+    }
+
+    /// Replace the quotes on a market around its last trade price, or its mid
+    /// price before it has traded, once they have traded or the price has
+    /// moved. Asks are limited to the shares the market maker holds and bids
+    /// to what it may buy before reaching `max_inventory`.
+    async fn requote(
+        &self,
+        event_id: i32,
+        option_id: i32,
+        task_id: &str,
+        quotes: &mut Quotes,
+    ) -> Result<QuoteOutcome, String> {
+        let event = events::Entity::find_by_id(event_id)
+            .one(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load event: {}", e))?;
+        let Some(event) = event
+            .filter(|event| !["ended", "resolved", "cancelled"].contains(&event.status.as_str()))
+        else {
+            self.cancel_quotes(event_id, option_id, std::mem::take(&mut quotes.order_ids))
+                .await?;
+            self.forget_market(event_id, option_id).await?;
+            return Ok(QuoteOutcome::Stopped);
+        };
+        // Draft events keep their seeded quotes until they open
+        if event.status != "active" {
+            return Ok(QuoteOutcome::Unchanged);
+        }
+
+        let user_id = self.config.market_maker_user_id;
+        let order_ids = quotes.order_ids.clone();
+        let view = matching_service::query(&self.redis_pool, event_id, option_id, move |book| {
+            let (buy_orders, sell_orders, orders, _) = book.get_internal_state();
+            let quote = book.quote();
+            BookView {
+                last_trade_price: quote.last_trade_price,
+                mid_price: quote.mid_price,
+                best_bid: best_other_price(buy_orders.iter().rev(), user_id),
+                best_ask: best_other_price(sell_orders.iter(), user_id),
+                quotes_untouched: order_ids.iter().all(|id| {
+                    orders
+                        .get(id)
+                        .is_some_and(|order| order.filled_quantity == 0)
+                }),
+            }
+        })
+        .await?;
+
+        let reference_price = view
+            .last_trade_price
+            .or(view.mid_price)
+            .unwrap_or(self.config.initial_price);
+
+        // Others already quote within the market maker's spread
+        let target_spread = reference_price * self.config.spread_percentage;
+        if let (Some(best_bid), Some(best_ask)) = (view.best_bid, view.best_ask) {
+            if best_ask - best_bid <= target_spread {
+                if !quotes.order_ids.is_empty() {
+                    self.cancel_quotes(event_id, option_id, std::mem::take(&mut quotes.order_ids))
+                        .await?;
+                    quotes.reference_price = None;
+                    if !self
+                        .save_quotes(event_id, option_id, task_id, quotes)
+                        .await?
+                    {
+                        return Ok(QuoteOutcome::Stopped);
+                    }
+                }
+                return Ok(QuoteOutcome::BackedOff);
+            }
+        }
+
+        if !quotes.order_ids.is_empty()
+            && view.quotes_untouched
+            && quotes.reference_price == Some(reference_price)
+        {
+            return Ok(QuoteOutcome::Unchanged);
+        }
+
+        self.cancel_quotes(event_id, option_id, std::mem::take(&mut quotes.order_ids))
+            .await?;
+
+        let held = PositionTracker::new(self.db.clone())
+            .get_user_position(user_id, event_id, option_id)
+            .await?
+            .quantity
+            .max(0);
+        let mut new_quotes = Vec::new();
+        for (side, mut available) in [
+            (OrderSide::Sell, held),
+            (OrderSide::Buy, self.config.max_inventory - held),
+        ] {
+            for price in self.level_prices(&event, reference_price, side.clone()) {
+                let quantity = self.config.level_quantity.min(available);
+                if quantity <= 0 {
+                    break;
+                }
+                available -= quantity;
+                let mut order = Order::new(
+                    user_id,
+                    event_id,
+                    option_id,
+                    side.clone(),
+                    OrderType::Limit,
+                    TimeInForce::GTC,
+                    price,
+                    quantity,
+                    event.currency.clone(),
+                );
+                // Quotes only add liquidity, so one that would cross is dropped
+                order.post_only = true;
+                new_quotes.push(order);
+            }
+        }
+
+        let db_persistence = DbPersistence::new(self.db.clone());
+        let redis_persistence = RedisOrderBookPersistence::new(self.redis_pool.clone());
+        // Orders are stored before they rest, so a fill always finds its order
+        for order in &new_quotes {
+            db_persistence.save_order(order).await?;
+            redis_persistence.save_order(order).await?;
+        }
+
+        let submitted = new_quotes.clone();
+        let results =
+            matching_service::execute(&self.redis_pool, event_id, option_id, move |book| {
+                submitted
+                    .into_iter()
+                    .map(|order| book.submit_order(order))
+                    .collect::<Vec<_>>()
+            })
+            .await?;
+
+        for (mut order, result) in new_quotes.into_iter().zip(results) {
+            match result {
+                Ok(_) => quotes.order_ids.push(order.id),
+                Err(e) => {
+                    log::debug!("Market maker quote at {} not placed: {}", order.price, e);
+                    order.reject();
+                    if let Err(e) = db_persistence.update_order(&order).await {
+                        log::error!("Failed to update rejected quote in database: {}", e);
+                    }
+                    if let Err(e) = redis_persistence
+                        .update_order_status(&order.id, OrderStatus::Rejected, 0)
+                        .await
+                    {
+                        log::error!("Failed to update rejected quote in Redis: {}", e);
+                    }
+                }
+            }
+        }
+        quotes.reference_price = Some(reference_price);
+        // Disabled or handed over while quoting, so the new quotes aren't ours
+        // to keep
+        if !self
+            .save_quotes(event_id, option_id, task_id, quotes)
+            .await?
+        {
+            self.cancel_quotes(event_id, option_id, std::mem::take(&mut quotes.order_ids))
+                .await?;
+            return Ok(QuoteOutcome::Stopped);
+        }
+
+        log::info!(
+            "Market maker re-quoted event {} option {} around {} with {} orders (holding {} shares)",
+            event_id,
+            option_id,
+            reference_price,
+            quotes.order_ids.len(),
+            held
+        );
+        Ok(QuoteOutcome::Requoted)
+    }
+
+    /// Cancel the quotes still resting on a market
+    async fn cancel_quotes(
+        &self,
+        event_id: i32,
+        option_id: i32,
+        order_ids: Vec<String>,
+    ) -> Result<(), String> {
+        if order_ids.is_empty() {
+            return Ok(());
+        }
+        let cancelled =
+            matching_service::execute(&self.redis_pool, event_id, option_id, move |book| {
+                order_ids
+                    .iter()
+                    .filter_map(|order_id| book.cancel_order(order_id).ok())
+                    .collect::<Vec<_>>()
+            })
+            .await?;
+
+        let db_persistence = DbPersistence::new(self.db.clone());
+        let redis_persistence = RedisOrderBookPersistence::new(self.redis_pool.clone());
+        for order in cancelled {
+            if let Err(e) = db_persistence.update_order(&order).await {
+                log::error!("Failed to update cancelled quote in database: {}", e);
+            }
+            if let Err(e) = redis_persistence
+                .update_order_status(&order.id, OrderStatus::Cancelled, order.filled_quantity)
+                .await
+            {
+                log::error!("Failed to update cancelled quote in Redis: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Record a market's quotes, unless it has been disabled or handed to
+    /// another task since `task_id` took it, returning whether they were saved
+    async fn save_quotes(
+        &self,
+        event_id: i32,
+        option_id: i32,
+        task_id: &str,
+        quotes: &Quotes,
+    ) -> Result<bool, String> {
+        let Some(mut state) = quoting_state(&self.redis_pool, event_id, option_id)
+            .await?
+            .filter(|state| state.enabled && state.task_id == task_id)
+        else {
+            return Ok(false);
+        };
+        state.quotes = quotes.clone();
+        store_quoting_state(&self.redis_pool, event_id, option_id, &state).await?;
+        Ok(true)
+    }
+
+    async fn forget_market(&self, event_id: i32, option_id: i32) -> Result<(), String> {
+        let mut conn = self
+            .redis_pool
+            .get()
+            .await
+            .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+        conn.hdel::<_, _, ()>(QUOTING_KEY, quoting_field(event_id, option_id))
+            .await
+            .map_err(|e| format!("Failed to remove quoting state: {}", e))
+    }
+}
+
+/// Start re-quoting every market the market maker was quoting before the
+/// server last stopped
+pub fn start_market_makers(db: web::Data<DatabaseConnection>, redis_pool: web::Data<Pool>) {
+    tokio::spawn(async move {
+        let markets = match quoted_markets(redis_pool.get_ref()).await {
+            Ok(markets) => markets,
+            Err(e) => {
+                log::error!("{}", e);
+                return;
+            }
+        };
+
+        log::info!("Resuming market maker quoting on {} markets", markets.len());
+        for (event_id, option_id, state) in markets {
+            if !state.enabled {
+                continue;
+            }
+            let market_maker = MarketMaker::new(
+                state.config,
+                db.get_ref().clone(),
+                redis_pool.get_ref().clone(),
+            );
+            tokio::spawn(market_maker.quote_market(event_id, option_id, state.task_id));
+        }
+    });
 }
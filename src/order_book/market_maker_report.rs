@@ -0,0 +1,167 @@
+use super::market_maker::{self, MarketMakerPnl};
+use super::MarketMaker;
+use crate::constants::config;
+use actix_web::web;
+use chrono::{DateTime, Utc};
+use deadpool_redis::{redis::AsyncCommands, Pool};
+use entity::events;
+use sea_orm::{prelude::Decimal, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Latest report, as JSON
+const REPORT_KEY: &str = "market_maker:report";
+
+/// The market maker's result and inventory on one option it quotes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketReport {
+    pub event_id: i32,
+    pub option_id: i32,
+    pub currency: String,
+    pub enabled: bool,
+    pub market_maker_user_id: i32,
+    #[serde(flatten)]
+    pub pnl: MarketMakerPnl,
+}
+
+/// Results of every market in one currency added up
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CurrencyTotals {
+    pub currency: String,
+    pub markets: usize,
+    pub fees: Decimal,
+    pub fees_earned: Decimal,
+    pub realized_pnl: Decimal,
+    pub unrealized_pnl: Decimal,
+    pub pnl: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketMakerReport {
+    pub generated_at: DateTime<Utc>,
+    pub markets: Vec<MarketReport>,
+    pub totals: Vec<CurrencyTotals>,
+}
+
+/// Work out the market maker's result on every market it has quoted
+pub async fn build_report(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+) -> Result<MarketMakerReport, String> {
+    let quoted = market_maker::quoted_markets(redis_pool).await?;
+
+    let event_ids: Vec<i32> = quoted.iter().map(|(event_id, _, _)| *event_id).collect();
+    let currencies: HashMap<i32, String> = events::Entity::find()
+        .filter(events::Column::Id.is_in(event_ids))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load events: {}", e))?
+        .into_iter()
+        .map(|event| (event.id, event.currency))
+        .collect();
+
+    let mut markets = Vec::with_capacity(quoted.len());
+    for (event_id, option_id, state) in quoted {
+        let Some(currency) = currencies.get(&event_id) else {
+            continue;
+        };
+        let market_maker_user_id = state.config.market_maker_user_id;
+        let pnl = MarketMaker::new(state.config, db.clone(), redis_pool.clone())
+            .pnl(event_id, option_id, state.seeded_shares)
+            .await?;
+        markets.push(MarketReport {
+            event_id,
+            option_id,
+            currency: currency.clone(),
+            enabled: state.enabled,
+            market_maker_user_id,
+            pnl,
+        });
+    }
+    markets.sort_by_key(|market| (market.event_id, market.option_id));
+
+    let mut totals: BTreeMap<String, CurrencyTotals> = BTreeMap::new();
+    for market in &markets {
+        let total = totals
+            .entry(market.currency.clone())
+            .or_insert_with(|| CurrencyTotals {
+                currency: market.currency.clone(),
+                ..Default::default()
+            });
+        total.markets += 1;
+        total.fees += market.pnl.fees;
+        total.fees_earned += market.pnl.fees_earned;
+        total.realized_pnl += market.pnl.realized_pnl;
+        total.unrealized_pnl += market.pnl.unrealized_pnl;
+        total.pnl += market.pnl.pnl;
+    }
+
+    Ok(MarketMakerReport {
+        generated_at: Utc::now(),
+        markets,
+        totals: totals.into_values().collect(),
+    })
+}
+
+/// The report the job last stored, if any
+pub async fn load_report(redis_pool: &Pool) -> Result<Option<MarketMakerReport>, String> {
+    let mut conn = redis_pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+    let report: Option<String> = conn
+        .get(REPORT_KEY)
+        .await
+        .map_err(|e| format!("Failed to load market maker report: {}", e))?;
+    report
+        .map(|report| {
+            serde_json::from_str(&report)
+                .map_err(|e| format!("Failed to parse market maker report: {}", e))
+        })
+        .transpose()
+}
+
+/// Build the report and store it for `load_report`
+pub async fn refresh_report(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+) -> Result<MarketMakerReport, String> {
+    let report = build_report(db, redis_pool).await?;
+
+    let serialized = serde_json::to_string(&report)
+        .map_err(|e| format!("Failed to serialize market maker report: {}", e))?;
+    let mut conn = redis_pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+    conn.set::<_, _, ()>(REPORT_KEY, serialized)
+        .await
+        .map_err(|e| format!("Failed to save market maker report: {}", e))?;
+
+    Ok(report)
+}
+
+/// Start a background task that periodically refreshes the market maker report
+pub fn start_market_maker_report_job(
+    db: web::Data<DatabaseConnection>,
+    redis_pool: web::Data<Pool>,
+) {
+    let interval_seconds = config::get_market_maker_report_interval_seconds();
+
+    log::info!(
+        "Starting market maker report job with {}-second interval",
+        interval_seconds
+    );
+
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(interval_seconds));
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = refresh_report(db.get_ref(), redis_pool.get_ref()).await {
+                log::error!("Failed to refresh market maker report: {}", e);
+            }
+        }
+    });
+}
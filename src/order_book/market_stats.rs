@@ -0,0 +1,260 @@
+use super::engine::OrderBookEngine;
+use super::redis_persistence::RedisOrderBookPersistence;
+use crate::constants::config;
+use crate::types::event_option::{HolderBucket, HolderDistributionResponse, OptionStats};
+use crate::utils::cache::CacheService;
+use chrono::{Duration, Utc};
+use deadpool_redis::Pool;
+use entity::{event_options, trades, user_positions};
+use sea_orm::{
+    prelude::{Decimal, Expr},
+    ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect,
+};
+use std::collections::HashMap;
+
+/// Lower bounds of the position size buckets in holder distributions; each
+/// bucket runs up to the next bound and the last is open-ended
+const HOLDER_BUCKET_BOUNDS: [i32; 5] = [1, 10, 100, 1_000, 10_000];
+
+/// Per-option trading statistics for an event. Everything for an event is
+/// computed together with one query per statistic and cached briefly, so event
+/// pages get stats for every option without extra calls.
+pub struct MarketStatsService {
+    db: DatabaseConnection,
+    redis_pool: Pool,
+}
+
+impl MarketStatsService {
+    pub fn new(db: DatabaseConnection, redis_pool: Pool) -> Self {
+        Self { db, redis_pool }
+    }
+
+    /// Stats for every option of an event, keyed by option id
+    pub async fn for_event(&self, event_id: i32) -> Result<HashMap<i32, OptionStats>, String> {
+        let cache_service = CacheService::new(self.redis_pool.clone());
+        let cache_key = format!("market_stats:event:{}", event_id);
+        if let Ok(Some(cached)) = cache_service
+            .get::<HashMap<i32, OptionStats>>(&cache_key)
+            .await
+        {
+            return Ok(cached);
+        }
+
+        let option_ids: Vec<i32> = event_options::Entity::find()
+            .select_only()
+            .column(event_options::Column::Id)
+            .filter(event_options::Column::EventId.eq(event_id))
+            .into_tuple()
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load event options: {}", e))?;
+
+        let volumes = self.volumes_24h(event_id).await?;
+        let holdings = self.holdings(event_id).await?;
+
+        // Read the latest book snapshots rather than loading every option's
+        // book into memory
+        let redis_persistence = RedisOrderBookPersistence::new(self.redis_pool.clone());
+        let mut stats = HashMap::with_capacity(option_ids.len());
+        for option_id in option_ids {
+            let book = redis_persistence
+                .load_full_order_book(event_id, option_id)
+                .await?
+                .unwrap_or_else(|| OrderBookEngine::new(event_id, option_id));
+            let (holders, open_interest) = holdings.get(&option_id).copied().unwrap_or((0, 0));
+
+            stats.insert(
+                option_id,
+                OptionStats {
+                    best_bid: book.get_best_bid_price(),
+                    best_ask: book.get_best_ask_price(),
+                    last_trade_price: book.last_trade_price(),
+                    volume_24h: volumes.get(&option_id).copied().unwrap_or(Decimal::ZERO),
+                    holders,
+                    open_interest,
+                },
+            );
+        }
+
+        if let Err(e) = cache_service
+            .set(&cache_key, &stats, config::get_market_stats_cache_seconds())
+            .await
+        {
+            log::warn!("Failed to cache market stats: {}", e);
+        }
+
+        Ok(stats)
+    }
+
+    /// Stats for one option
+    pub async fn for_option(&self, event_id: i32, option_id: i32) -> Result<OptionStats, String> {
+        Ok(self
+            .for_event(event_id)
+            .await?
+            .remove(&option_id)
+            .unwrap_or_default())
+    }
+
+    /// Holders of an option bucketed by position size. Only counts are
+    /// returned, never who holds what.
+    pub async fn holder_distribution(
+        &self,
+        event_id: i32,
+        option_id: i32,
+    ) -> Result<HolderDistributionResponse, String> {
+        let cache_service = CacheService::new(self.redis_pool.clone());
+        let cache_key = format!("market_stats:holders:{}", option_id);
+        if let Ok(Some(cached)) = cache_service
+            .get::<HolderDistributionResponse>(&cache_key)
+            .await
+        {
+            return Ok(cached);
+        }
+
+        // Index of the bucket each position falls into
+        let bucket_expr = format!(
+            "CASE {} END",
+            HOLDER_BUCKET_BOUNDS
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(index, bound)| format!(
+                    "WHEN user_positions.quantity >= {} THEN {}",
+                    bound, index
+                ))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        let counts: Vec<(i32, i64, i64)> = user_positions::Entity::find()
+            .select_only()
+            .column_as(Expr::cust(bucket_expr.clone()), "bucket")
+            .column_as(Expr::cust("COUNT(*)"), "holders")
+            .column_as(
+                Expr::cust("COALESCE(SUM(user_positions.quantity), 0)::BIGINT"),
+                "shares",
+            )
+            .filter(user_positions::Column::OptionId.eq(option_id))
+            .filter(user_positions::Column::Quantity.gt(0))
+            .group_by(Expr::cust(bucket_expr))
+            .into_tuple()
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to aggregate holders: {}", e))?;
+
+        let buckets: Vec<HolderBucket> = HOLDER_BUCKET_BOUNDS
+            .iter()
+            .enumerate()
+            .map(|(index, bound)| {
+                let (holders, shares) = counts
+                    .iter()
+                    .find(|(bucket, _, _)| *bucket as usize == index)
+                    .map(|(_, holders, shares)| (*holders, *shares))
+                    .unwrap_or((0, 0));
+                HolderBucket {
+                    min_shares: *bound,
+                    max_shares: HOLDER_BUCKET_BOUNDS.get(index + 1).map(|next| next - 1),
+                    holders,
+                    shares,
+                }
+            })
+            .collect();
+
+        let distribution = HolderDistributionResponse {
+            event_id,
+            option_id,
+            holders: buckets.iter().map(|b| b.holders).sum(),
+            open_interest: buckets.iter().map(|b| b.shares).sum(),
+            buckets,
+        };
+
+        if let Err(e) = cache_service
+            .set(
+                &cache_key,
+                &distribution,
+                config::get_market_stats_cache_seconds(),
+            )
+            .await
+        {
+            log::warn!("Failed to cache holder distribution: {}", e);
+        }
+
+        Ok(distribution)
+    }
+
+    /// Drop the cached stats for an event, e.g. after an option is added
+    pub async fn invalidate(&self, event_id: i32) {
+        let cache_service = CacheService::new(self.redis_pool.clone());
+        let cache_key = format!("market_stats:event:{}", event_id);
+        if let Err(e) = cache_service.delete(&cache_key).await {
+            log::warn!("Failed to invalidate market stats cache: {}", e);
+        }
+    }
+
+    /// Traded notional on one option over the last 24 hours
+    pub async fn volume_24h(&self, event_id: i32, option_id: i32) -> Result<Decimal, String> {
+        let since = Utc::now() - Duration::hours(24);
+        let volume: Option<Decimal> = trades::Entity::find()
+            .select_only()
+            .column_as(
+                Expr::cust("COALESCE(SUM(trades.total_amount), 0)"),
+                "volume",
+            )
+            .filter(trades::Column::EventId.eq(event_id))
+            .filter(trades::Column::OptionId.eq(option_id))
+            .filter(trades::Column::Timestamp.gte(since))
+            .into_tuple()
+            .one(&self.db)
+            .await
+            .map_err(|e| format!("Failed to aggregate trading volume: {}", e))?;
+
+        Ok(volume.unwrap_or(Decimal::ZERO))
+    }
+
+    /// Traded notional per option over the last 24 hours
+    async fn volumes_24h(&self, event_id: i32) -> Result<HashMap<i32, Decimal>, String> {
+        let since = Utc::now() - Duration::hours(24);
+        let volumes: Vec<(i32, Decimal)> = trades::Entity::find()
+            .select_only()
+            .column(trades::Column::OptionId)
+            .column_as(
+                Expr::cust("COALESCE(SUM(trades.total_amount), 0)"),
+                "volume",
+            )
+            .filter(trades::Column::EventId.eq(event_id))
+            .filter(trades::Column::Timestamp.gte(since))
+            .group_by(trades::Column::OptionId)
+            .into_tuple()
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to aggregate trading volume: {}", e))?;
+
+        Ok(volumes.into_iter().collect())
+    }
+
+    /// Number of holders and shares held per option
+    async fn holdings(&self, event_id: i32) -> Result<HashMap<i32, (i64, i64)>, String> {
+        let holdings: Vec<(i32, i64, i64)> = user_positions::Entity::find()
+            .select_only()
+            .column(user_positions::Column::OptionId)
+            .column_as(
+                Expr::cust("COUNT(DISTINCT user_positions.user_id)"),
+                "holders",
+            )
+            .column_as(
+                Expr::cust("COALESCE(SUM(user_positions.quantity), 0)::BIGINT"),
+                "open_interest",
+            )
+            .filter(user_positions::Column::EventId.eq(event_id))
+            .filter(user_positions::Column::Quantity.gt(0))
+            .group_by(user_positions::Column::OptionId)
+            .into_tuple()
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to aggregate positions: {}", e))?;
+
+        Ok(holdings
+            .into_iter()
+            .map(|(option_id, holders, open_interest)| (option_id, (holders, open_interest)))
+            .collect())
+    }
+}
@@ -0,0 +1,482 @@
+use super::engine::OrderBookEngine;
+use super::performance;
+use super::redis_persistence::RedisOrderBookPersistence;
+use crate::constants::config;
+use crate::types::order_book::OrderBookResponse;
+use crate::types::websocket::{OpsEventKind, OpsSeverity};
+use crate::websocket::book_depth::{self, SubscriberDepth};
+use crate::websocket::{market_data, ops, ticker};
+use deadpool_redis::Pool;
+use entity::event_options;
+use lazy_static::lazy_static;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+/// Commands that can queue up for a single book before senders wait
+const BOOK_COMMAND_BUFFER: usize = 1024;
+
+/// Runs against the resident book and returns the callback that answers the caller
+type Reply = Box<dyn FnOnce() + Send>;
+
+struct BookCommand {
+    apply: Box<dyn FnOnce(&mut OrderBookEngine) -> Reply + Send>,
+    mutates: bool,
+    submitted_at: Instant,
+}
+
+lazy_static! {
+    static ref BOOKS: Mutex<HashMap<(i32, i32), mpsc::Sender<BookCommand>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Database the options of books are checked against before one is loaded
+static OPTIONS_DB: OnceLock<DatabaseConnection> = OnceLock::new();
+
+/// Let the matching service check options exist before loading their books
+pub fn init(db: DatabaseConnection) {
+    let _ = OPTIONS_DB.set(db);
+}
+
+/// Run `f` against the resident book for an option and journal the change to
+/// Redis before returning.
+///
+/// Each (event, option) book is owned by a single task, so commands for a book
+/// run one at a time in arrival order and never race each other.
+pub async fn execute<R, F>(
+    redis_pool: &Pool,
+    event_id: i32,
+    option_id: i32,
+    f: F,
+) -> Result<R, String>
+where
+    F: FnOnce(&mut OrderBookEngine) -> R + Send + 'static,
+    R: Send + 'static,
+{
+    send(redis_pool, event_id, option_id, f, true).await
+}
+
+/// Run a read-only `f` against the resident book for an option.
+///
+/// Halts and auctions that lapse while reading are picked up by the next
+/// journal entry, so reads never write to Redis themselves.
+pub async fn query<R, F>(
+    redis_pool: &Pool,
+    event_id: i32,
+    option_id: i32,
+    f: F,
+) -> Result<R, String>
+where
+    F: FnOnce(&mut OrderBookEngine) -> R + Send + 'static,
+    R: Send + 'static,
+{
+    send(redis_pool, event_id, option_id, f, false).await
+}
+
+async fn send<R, F>(
+    redis_pool: &Pool,
+    event_id: i32,
+    option_id: i32,
+    f: F,
+    mutates: bool,
+) -> Result<R, String>
+where
+    F: FnOnce(&mut OrderBookEngine) -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    let mut command = BookCommand {
+        apply: Box::new(move |book| {
+            book.begin_command();
+            let result = f(book);
+            Box::new(move || {
+                let _ = tx.send(result);
+            })
+        }),
+        mutates,
+        submitted_at: Instant::now(),
+    };
+
+    // A book evicted between fetching its sender and sending is loaded again
+    let mut attempts = 0;
+    while let Err(mpsc::error::SendError(returned)) = book_sender(redis_pool, event_id, option_id)
+        .await?
+        .send(command)
+        .await
+    {
+        attempts += 1;
+        if attempts > 1 {
+            return Err(format!(
+                "Order book {}:{} is not running",
+                event_id, option_id
+            ));
+        }
+        command = returned;
+    }
+
+    rx.await
+        .map_err(|_| format!("Order book {}:{} dropped the command", event_id, option_id))
+}
+
+/// Books currently resident in memory, as (event_id, option_id)
+pub async fn resident_books() -> Vec<(i32, i32)> {
+    BOOKS
+        .lock()
+        .await
+        .iter()
+        .filter(|(_, sender)| !sender.is_closed())
+        .map(|(key, _)| *key)
+        .collect()
+}
+
+/// Whether `option_id` is an option of `event_id`
+async fn option_exists(event_id: i32, option_id: i32) -> Result<bool, String> {
+    let db = OPTIONS_DB
+        .get()
+        .ok_or_else(|| "Matching service is not initialized".to_string())?;
+    let count = event_options::Entity::find_by_id(option_id)
+        .filter(event_options::Column::EventId.eq(event_id))
+        .count(db)
+        .await
+        .map_err(|e| format!("Failed to look up option {}: {}", option_id, e))?;
+    Ok(count > 0)
+}
+
+/// Get the command channel for a book, loading it from Redis on first use
+async fn book_sender(
+    redis_pool: &Pool,
+    event_id: i32,
+    option_id: i32,
+) -> Result<mpsc::Sender<BookCommand>, String> {
+    let mut books = BOOKS.lock().await;
+    if let Some(sender) = books.get(&(event_id, option_id)) {
+        if !sender.is_closed() {
+            return Ok(sender.clone());
+        }
+    }
+
+    // Books are only created for real options, so lookups of unknown ones
+    // can't fill memory and Redis with empty books
+    if !option_exists(event_id, option_id).await? {
+        return Err(format!(
+            "Option {} of event {} not found",
+            option_id, event_id
+        ));
+    }
+
+    // Hold the registry lock while loading so a book is only ever loaded once
+    let persistence = RedisOrderBookPersistence::new(redis_pool.clone());
+    let (order_book, wal_id) = persistence
+        .get_or_create_order_book(event_id, option_id)
+        .await
+        .inspect_err(|e| {
+            ops::publish(
+                OpsEventKind::RedisDegraded,
+                OpsSeverity::Critical,
+                format!(
+                    "Order book {}:{} could not be loaded from Redis",
+                    event_id, option_id
+                ),
+                json!({ "event_id": event_id, "option_id": option_id, "error": e }),
+            );
+        })?;
+
+    let (sender, receiver) = mpsc::channel(BOOK_COMMAND_BUFFER);
+    tokio::spawn(run_book(
+        event_id,
+        option_id,
+        order_book,
+        wal_id,
+        persistence,
+        receiver,
+    ));
+    books.insert((event_id, option_id), sender.clone());
+
+    log::info!(
+        "Loaded resident order book for event {} option {}",
+        event_id,
+        option_id
+    );
+
+    Ok(sender)
+}
+
+async fn run_book(
+    event_id: i32,
+    option_id: i32,
+    mut order_book: OrderBookEngine,
+    mut wal_id: Option<String>,
+    persistence: RedisOrderBookPersistence,
+    mut receiver: mpsc::Receiver<BookCommand>,
+) {
+    let snapshot_every_entries = config::get_order_book_snapshot_every_entries();
+    let mut snapshot_timer = tokio::time::interval(tokio::time::Duration::from_secs(
+        config::get_order_book_snapshot_interval_seconds(),
+    ));
+    snapshot_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut entries_since_snapshot = 0;
+    let mut published_sequence = order_book.sequence();
+    let mut refresh_timer = tokio::time::interval(tokio::time::Duration::from_millis(
+        config::get_order_book_ws_refresh_ms(),
+    ));
+    refresh_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut refreshed_sequence = order_book.sequence();
+    let mut published_quote = order_book.quote();
+    let mut viewed_sequence = publish_view(event_id, option_id, &order_book, &persistence)
+        .await
+        .then(|| order_book.sequence());
+    let idle_after = Duration::from_secs(config::get_order_book_idle_seconds());
+    let mut last_changed = Instant::now();
+
+    loop {
+        let command = tokio::select! {
+            command = receiver.recv() => match command {
+                Some(command) => command,
+                None => break,
+            },
+            _ = snapshot_timer.tick() => {
+                if entries_since_snapshot > 0
+                    && snapshot(event_id, option_id, &order_book, wal_id.as_deref(), &persistence).await
+                {
+                    entries_since_snapshot = 0;
+                }
+                if last_changed.elapsed() >= idle_after
+                    && evict(event_id, option_id, &receiver, entries_since_snapshot).await
+                {
+                    break;
+                }
+                continue;
+            }
+            // Throttled full refresh for subscribers, at most once per interval
+            _ = refresh_timer.tick() => {
+                // Retry a view that failed to publish, so REST readers don't
+                // keep seeing it until the next change
+                if viewed_sequence != Some(order_book.sequence())
+                    && publish_view(event_id, option_id, &order_book, &persistence).await
+                {
+                    viewed_sequence = Some(order_book.sequence());
+                }
+                if order_book.sequence() != refreshed_sequence {
+                    refreshed_sequence = order_book.sequence();
+                    // Only as deep as the deepest subscriber looks
+                    if let Some(depth) = book_depth::subscriber_depth(event_id, option_id).levels() {
+                        market_data::publish_book_snapshot(OrderBookResponse::from_snapshot(
+                            order_book.get_snapshot_with_depth(depth),
+                            order_book.get_predicted_price(),
+                        ));
+                    }
+                }
+                continue;
+            }
+        };
+
+        let depth = book_depth::subscriber_depth(event_id, option_id);
+        let visible_before = match depth {
+            SubscriberDepth::Levels(levels) => Some(order_book.get_snapshot_with_depth(levels)),
+            _ => None,
+        };
+        let batch_sequence = order_book.sequence();
+        let batch_trades = order_book.trades_executed();
+        let mut dirty = command.mutates;
+        let mut submitted = vec![(command.mutates, command.submitted_at)];
+        let mut replies = vec![(command.apply)(&mut order_book)];
+
+        // Apply everything that queued up behind it and journal the batch once
+        while let Ok(command) = receiver.try_recv() {
+            dirty |= command.mutates;
+            submitted.push((command.mutates, command.submitted_at));
+            replies.push((command.apply)(&mut order_book));
+        }
+
+        let mut changes = Vec::new();
+        if dirty {
+            last_changed = Instant::now();
+            let entry = order_book.take_journal_entry();
+            changes = entry.levels.iter().map(|level| level.delta()).collect();
+            match persistence
+                .append_journal(event_id, option_id, &entry)
+                .await
+            {
+                Ok(id) => {
+                    wal_id = Some(id);
+                    entries_since_snapshot += 1;
+                }
+                Err(e) => {
+                    log::error!(
+                        "Failed to journal order book {}:{} to Redis: {}",
+                        event_id,
+                        option_id,
+                        e
+                    );
+                    ops::publish(
+                        OpsEventKind::RedisDegraded,
+                        OpsSeverity::Critical,
+                        format!(
+                            "Order book {}:{} could not be journaled to Redis",
+                            event_id, option_id
+                        ),
+                        json!({ "event_id": event_id, "option_id": option_id, "error": e }),
+                    );
+                    // Without the entry the book can only be rebuilt from a snapshot
+                    entries_since_snapshot = snapshot_every_entries;
+                }
+            }
+
+            if entries_since_snapshot >= snapshot_every_entries
+                && snapshot(
+                    event_id,
+                    option_id,
+                    &order_book,
+                    wal_id.as_deref(),
+                    &persistence,
+                )
+                .await
+            {
+                entries_since_snapshot = 0;
+            }
+        }
+
+        // Every sequence advance is published, even without level changes (a halt,
+        // say), so subscribers can tell a quiet book from a missed delta
+        if order_book.sequence() != published_sequence {
+            // Subscribers following fewer levels only get the changes within them
+            if let (Some(before), SubscriberDepth::Levels(levels)) = (&visible_before, depth) {
+                let after = order_book.get_snapshot_with_depth(levels);
+                changes = book_depth::changes_within(before, &after, changes);
+            }
+            if depth != SubscriberDepth::Unwatched {
+                market_data::publish_book_delta(
+                    event_id,
+                    option_id,
+                    published_sequence,
+                    order_book.sequence(),
+                    changes,
+                );
+            }
+            published_sequence = order_book.sequence();
+
+            let quote = order_book.quote();
+            if quote != published_quote {
+                ticker::publish_quote(event_id, option_id, quote.clone());
+                published_quote = quote;
+            }
+        }
+
+        // Before replying, so a caller that reads the book back sees its change
+        if viewed_sequence != Some(order_book.sequence())
+            && publish_view(event_id, option_id, &order_book, &persistence).await
+        {
+            viewed_sequence = Some(order_book.sequence());
+        }
+
+        for reply in replies {
+            reply();
+        }
+
+        let latencies: Vec<Duration> = submitted
+            .iter()
+            .filter(|(mutates, _)| *mutates)
+            .map(|(_, submitted_at)| submitted_at.elapsed())
+            .collect();
+        performance::record_batch(
+            event_id,
+            option_id,
+            &latencies,
+            order_book.trades_executed() - batch_trades,
+            order_book.sequence() - batch_sequence,
+        );
+    }
+}
+
+/// Drop a book that hasn't changed for `ORDER_BOOK_IDLE_SECONDS` from
+/// memory, returning whether it was dropped. Only a book whose journal is
+/// folded into its snapshot, with nothing queued and no caller holding its
+/// sender, is dropped; the registry stays locked throughout, so the next
+/// command loads it back from Redis.
+async fn evict(
+    event_id: i32,
+    option_id: i32,
+    receiver: &mpsc::Receiver<BookCommand>,
+    entries_since_snapshot: usize,
+) -> bool {
+    if entries_since_snapshot > 0 {
+        return false;
+    }
+
+    let mut books = BOOKS.lock().await;
+    let Some(sender) = books.get(&(event_id, option_id)) else {
+        return false;
+    };
+    if sender.strong_count() > 1 || !receiver.is_empty() {
+        return false;
+    }
+    books.remove(&(event_id, option_id));
+
+    log::info!(
+        "Evicted idle order book for event {} option {}",
+        event_id,
+        option_id
+    );
+    true
+}
+
+/// Publish the top of the book for `GET /order-book`, returning whether it
+/// was saved
+async fn publish_view(
+    event_id: i32,
+    option_id: i32,
+    order_book: &OrderBookEngine,
+    persistence: &RedisOrderBookPersistence,
+) -> bool {
+    let view = OrderBookResponse::from_snapshot(
+        order_book.get_snapshot(),
+        order_book.get_predicted_price(),
+    );
+    let Err(e) = persistence.save_book_view(&view).await else {
+        return true;
+    };
+
+    log::warn!(
+        "Failed to publish order book {}:{} view to Redis: {}",
+        event_id,
+        option_id,
+        e
+    );
+    false
+}
+
+/// Snapshot the whole book, returning whether it was saved
+async fn snapshot(
+    event_id: i32,
+    option_id: i32,
+    order_book: &OrderBookEngine,
+    wal_id: Option<&str>,
+    persistence: &RedisOrderBookPersistence,
+) -> bool {
+    let Err(e) = persistence
+        .save_full_order_book(event_id, option_id, order_book, wal_id)
+        .await
+    else {
+        return true;
+    };
+
+    log::error!(
+        "Failed to snapshot order book {}:{} to Redis: {}",
+        event_id,
+        option_id,
+        e
+    );
+    ops::publish(
+        OpsEventKind::RedisDegraded,
+        OpsSeverity::Critical,
+        format!(
+            "Order book {}:{} could not be snapshotted to Redis",
+            event_id, option_id
+        ),
+        json!({ "event_id": event_id, "option_id": option_id, "error": e }),
+    );
+    false
+}
@@ -1,9 +1,32 @@
+pub mod amm;
+pub mod auction;
+pub mod book_sampler;
+pub mod clock;
+pub mod complement;
+pub mod conservation;
 pub mod db_persistence;
 pub mod engine;
+pub mod event_lifecycle;
+pub mod external_venue;
+pub mod fees;
+pub mod limits;
+pub mod liquidation;
+pub mod margin;
 pub mod market_maker;
+pub mod market_maker_report;
+pub mod market_stats;
+pub mod matching_service;
+pub mod payouts;
+pub mod performance;
+pub mod position_cache;
 pub mod position_tracker;
 pub mod price_updater;
 pub mod redis_persistence;
+pub mod reservations;
+pub mod risk;
+pub mod router;
+pub mod settlement;
+pub mod settlement_rules;
 pub mod types;
 pub use market_maker::{MarketMaker, MarketMakerConfig};
 pub use types::{Order, OrderSide, OrderType, TimeInForce};
@@ -0,0 +1,125 @@
+use super::margin::PAYOUT_PER_SHARE;
+use super::settlement_rules::SettlementRules;
+use crate::types::event::{OptionValue, SettleEventRequest, SettlementMode};
+use sea_orm::prelude::Decimal;
+use std::collections::{HashMap, HashSet};
+
+/// What each share of every option of an event pays at settlement, by option id
+pub type PayoutSchedule = HashMap<i32, Decimal>;
+
+/// The payout schedule a settlement request describes for an event with
+/// `option_ids` and `rules`, or why the request doesn't describe one
+pub fn schedule(
+    req: &SettleEventRequest,
+    option_ids: &[i32],
+    rules: Option<&str>,
+) -> Result<PayoutSchedule, String> {
+    match req.mode {
+        SettlementMode::WinnerTakeAll => {
+            let winner = req
+                .winning_option_id
+                .ok_or("winning_option_id is required")?;
+            winner_take_all(winner, option_ids)
+        }
+        SettlementMode::MultiWinner => {
+            multi_winner(req.winning_option_ids.as_deref().unwrap_or(&[]), option_ids)
+        }
+        SettlementMode::Scalar => scalar(req.option_values.as_deref().unwrap_or(&[]), option_ids),
+        SettlementMode::Rules => {
+            let rules = SettlementRules::parse(rules.ok_or("Event has no settlement rules")?)?;
+            let inputs = req.inputs.clone().unwrap_or_default();
+            from_rules(&rules, &inputs, option_ids)
+        }
+    }
+}
+
+/// The winner pays `PAYOUT_PER_SHARE` and every other option nothing
+pub fn winner_take_all(winner: i32, option_ids: &[i32]) -> Result<PayoutSchedule, String> {
+    if !option_ids.contains(&winner) {
+        return Err("Invalid winning option ID".to_string());
+    }
+    Ok(option_ids
+        .iter()
+        .map(|&id| {
+            let payout = if id == winner {
+                PAYOUT_PER_SHARE
+            } else {
+                Decimal::ZERO
+            };
+            (id, payout)
+        })
+        .collect())
+}
+
+/// `PAYOUT_PER_SHARE` split evenly across the winners, rounded down to the
+/// cent so a set of winning shares never pays more than a single winner would
+pub fn multi_winner(winners: &[i32], option_ids: &[i32]) -> Result<PayoutSchedule, String> {
+    let winners: HashSet<i32> = winners.iter().copied().collect();
+    if winners.is_empty() {
+        return Err("winning_option_ids must name at least one option".to_string());
+    }
+    if winners.iter().any(|id| !option_ids.contains(id)) {
+        return Err("Invalid winning option ID".to_string());
+    }
+
+    let share = (PAYOUT_PER_SHARE / Decimal::from(winners.len()))
+        .round_dp_with_strategy(2, rust_decimal::RoundingStrategy::ToZero);
+    Ok(option_ids
+        .iter()
+        .map(|id| {
+            let payout = if winners.contains(id) {
+                share
+            } else {
+                Decimal::ZERO
+            };
+            (*id, payout)
+        })
+        .collect())
+}
+
+/// Each option pays the value it resolved to, which must be given for every
+/// option, in whole cents from 0 to `PAYOUT_PER_SHARE`
+pub fn scalar(values: &[OptionValue], option_ids: &[i32]) -> Result<PayoutSchedule, String> {
+    let mut schedule = PayoutSchedule::new();
+    for OptionValue { option_id, value } in values {
+        if !option_ids.contains(option_id) {
+            return Err(format!("Option {} is not part of this event", option_id));
+        }
+        if *value < Decimal::ZERO || *value > PAYOUT_PER_SHARE || value.round_dp(2) != *value {
+            return Err(format!(
+                "The value of option {} must be between 0 and {} in whole cents",
+                option_id, PAYOUT_PER_SHARE
+            ));
+        }
+        if schedule.insert(*option_id, *value).is_some() {
+            return Err(format!("Option {} is valued more than once", option_id));
+        }
+    }
+    if let Some(missing) = option_ids.iter().find(|id| !schedule.contains_key(id)) {
+        return Err(format!("option_values has no value for option {}", missing));
+    }
+    // The complement account mints pairs of shares in a two-option event for
+    // a full payout, so together the two must pay exactly that
+    if option_ids.len() == 2 && schedule.values().sum::<Decimal>() != PAYOUT_PER_SHARE {
+        return Err(format!(
+            "The values of the two options must add up to {}",
+            PAYOUT_PER_SHARE
+        ));
+    }
+    Ok(schedule)
+}
+
+/// What the rules pay given `inputs`, held to the same limits as a `scalar`
+/// settlement
+pub fn from_rules(
+    rules: &SettlementRules,
+    inputs: &HashMap<String, Decimal>,
+    option_ids: &[i32],
+) -> Result<PayoutSchedule, String> {
+    let values: Vec<OptionValue> = rules
+        .evaluate(inputs)?
+        .into_iter()
+        .map(|(option_id, value)| OptionValue { option_id, value })
+        .collect();
+    scalar(&values, option_ids)
+}
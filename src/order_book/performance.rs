@@ -0,0 +1,137 @@
+use crate::constants::config;
+use crate::types::metrics::MarketPerformance;
+use lazy_static::lazy_static;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Latencies kept per market for percentiles, newest last
+const LATENCY_SAMPLES: usize = 1000;
+
+/// Trades and book changes in one second of a market's recent activity
+struct Second {
+    at: Instant,
+    trades: u64,
+    mutations: u64,
+}
+
+#[derive(Default)]
+struct MarketAggregate {
+    commands: u64,
+    latency_micros: u64,
+    max_latency_micros: u64,
+    recent_latencies: VecDeque<u64>,
+    trades: u64,
+    mutations: u64,
+    seconds: VecDeque<Second>,
+}
+
+impl MarketAggregate {
+    /// Drop activity older than `window`
+    fn prune(&mut self, now: Instant, window: Duration) {
+        while self
+            .seconds
+            .front()
+            .is_some_and(|second| now.duration_since(second.at) >= window)
+        {
+            self.seconds.pop_front();
+        }
+    }
+}
+
+lazy_static! {
+    static ref MARKETS: Mutex<HashMap<(i32, i32), MarketAggregate>> = Mutex::new(HashMap::new());
+}
+
+/// Record one batch of commands a book applied together: how long each
+/// change waited from being submitted to its result being returned, and the
+/// trades and sequence advances the batch produced
+pub fn record_batch(
+    event_id: i32,
+    option_id: i32,
+    latencies: &[Duration],
+    trades: u64,
+    mutations: u64,
+) {
+    if latencies.is_empty() && trades == 0 && mutations == 0 {
+        return;
+    }
+    let now = Instant::now();
+
+    let mut markets = MARKETS.lock().unwrap_or_else(|e| e.into_inner());
+    let market = markets.entry((event_id, option_id)).or_default();
+    for latency in latencies {
+        let micros = latency.as_micros() as u64;
+        market.commands += 1;
+        market.latency_micros += micros;
+        market.max_latency_micros = market.max_latency_micros.max(micros);
+        if market.recent_latencies.len() == LATENCY_SAMPLES {
+            market.recent_latencies.pop_front();
+        }
+        market.recent_latencies.push_back(micros);
+    }
+    market.trades += trades;
+    market.mutations += mutations;
+
+    match market.seconds.back_mut() {
+        Some(second) if now.duration_since(second.at) < Duration::from_secs(1) => {
+            second.trades += trades;
+            second.mutations += mutations;
+        }
+        _ => market.seconds.push_back(Second {
+            at: now,
+            trades,
+            mutations,
+        }),
+    }
+    market.prune(
+        now,
+        Duration::from_secs(config::get_market_metrics_window_seconds()),
+    );
+}
+
+/// `percentile` of the ascending microsecond `sorted` samples, in milliseconds
+fn percentile_ms(sorted: &[u64], percentile: usize) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let index = (sorted.len() * percentile).div_ceil(100).saturating_sub(1);
+    sorted[index.min(sorted.len() - 1)] as f64 / 1000.0
+}
+
+/// Every market's matching performance since startup, slowest recent
+/// submissions first
+pub fn market_performance() -> Vec<MarketPerformance> {
+    let window_seconds = config::get_market_metrics_window_seconds();
+    let window = Duration::from_secs(window_seconds);
+    let now = Instant::now();
+
+    let mut markets = MARKETS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut performance: Vec<MarketPerformance> = markets
+        .iter_mut()
+        .map(|((event_id, option_id), market)| {
+            market.prune(now, window);
+            let mut sorted: Vec<u64> = market.recent_latencies.iter().copied().collect();
+            sorted.sort_unstable();
+            let recent_trades: u64 = market.seconds.iter().map(|s| s.trades).sum();
+            let recent_mutations: u64 = market.seconds.iter().map(|s| s.mutations).sum();
+            MarketPerformance {
+                event_id: *event_id,
+                option_id: *option_id,
+                commands: market.commands,
+                avg_latency_ms: market.latency_micros as f64
+                    / 1000.0
+                    / market.commands.max(1) as f64,
+                p50_latency_ms: percentile_ms(&sorted, 50),
+                p99_latency_ms: percentile_ms(&sorted, 99),
+                max_latency_ms: market.max_latency_micros as f64 / 1000.0,
+                trades: market.trades,
+                trades_per_second: recent_trades as f64 / window_seconds.max(1) as f64,
+                mutations: market.mutations,
+                mutations_per_second: recent_mutations as f64 / window_seconds.max(1) as f64,
+            }
+        })
+        .collect();
+    performance.sort_by(|a, b| b.p99_latency_ms.total_cmp(&a.p99_latency_ms));
+    performance
+}
@@ -0,0 +1,73 @@
+use super::types::UserPosition;
+use crate::constants::config;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// (user_id, event_id, option_id)
+pub type PositionKey = (i32, i32, i32);
+
+/// Entries beyond this count trigger a sweep of expired positions on insert
+const SWEEP_THRESHOLD: usize = 10_000;
+
+struct CachedPosition {
+    position: UserPosition,
+    cached_at: Instant,
+}
+
+lazy_static! {
+    static ref POSITION_CACHE: Mutex<HashMap<PositionKey, CachedPosition>> =
+        Mutex::new(HashMap::new());
+}
+
+fn ttl() -> Duration {
+    Duration::from_millis(config::get_position_cache_ttl_ms())
+}
+
+/// Look up a cached position, dropping it if it has outlived the TTL.
+///
+/// The cache only short-circuits reads; position writes still check quantities
+/// against the database, so a stale entry can't let a seller go negative.
+pub fn get(key: PositionKey) -> Option<UserPosition> {
+    let mut cache = POSITION_CACHE.lock().unwrap();
+    match cache.get(&key) {
+        Some(entry) if entry.cached_at.elapsed() < ttl() => Some(entry.position.clone()),
+        Some(_) => {
+            cache.remove(&key);
+            None
+        }
+        None => None,
+    }
+}
+
+pub fn insert(position: &UserPosition) {
+    let mut cache = POSITION_CACHE.lock().unwrap();
+    if cache.len() >= SWEEP_THRESHOLD {
+        let ttl = ttl();
+        cache.retain(|_, entry| entry.cached_at.elapsed() < ttl);
+    }
+    cache.insert(
+        (position.user_id, position.event_id, position.option_id),
+        CachedPosition {
+            position: position.clone(),
+            cached_at: Instant::now(),
+        },
+    );
+}
+
+/// Drop a single position after it was written
+pub fn invalidate(user_id: i32, event_id: i32, option_id: i32) {
+    POSITION_CACHE
+        .lock()
+        .unwrap()
+        .remove(&(user_id, event_id, option_id));
+}
+
+/// Drop every cached position in an event, used when the event is settled
+pub fn invalidate_event(event_id: i32) {
+    POSITION_CACHE
+        .lock()
+        .unwrap()
+        .retain(|(_, cached_event_id, _), _| *cached_event_id != event_id);
+}
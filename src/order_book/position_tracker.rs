@@ -1,11 +1,24 @@
+use super::position_cache::{self, PositionKey};
 use super::types::{Trade, UserPosition};
-use entity::user_positions;
+use crate::settings::registry as settings;
+use entity::{event_options, trades, user_positions, users};
 use sea_orm::{
-    prelude::Decimal, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
-    Set, TransactionTrait,
+    prelude::{Decimal, Expr},
+    sea_query::OnConflict,
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    FromQueryResult, JoinType, QueryFilter, QuerySelect, RelationTrait, Set,
 };
 use std::collections::HashMap;
 
+/// Portfolio totals aggregated in a single query over a user's open positions
+#[derive(Debug, FromQueryResult)]
+pub struct PortfolioTotals {
+    pub total_positions: i64,
+    pub active_events: i64,
+    pub total_invested: Decimal,
+    pub current_value: Decimal,
+}
+
 pub struct PositionTracker {
     db: DatabaseConnection,
 }
@@ -22,6 +35,10 @@ impl PositionTracker {
         event_id: i32,
         option_id: i32,
     ) -> Result<UserPosition, String> {
+        if let Some(position) = position_cache::get((user_id, event_id, option_id)) {
+            return Ok(position);
+        }
+
         let position = user_positions::Entity::find()
             .filter(user_positions::Column::UserId.eq(user_id))
             .filter(user_positions::Column::EventId.eq(event_id))
@@ -30,22 +47,89 @@ impl PositionTracker {
             .await
             .map_err(|e| format!("Failed to get user position: {}", e))?;
 
-        match position {
-            Some(p) => Ok(UserPosition {
+        let position = match position {
+            Some(p) => UserPosition {
                 user_id: p.user_id,
                 event_id: p.event_id,
                 option_id: p.option_id,
                 quantity: p.quantity,
                 average_price: p.average_price,
-            }),
-            None => Ok(UserPosition {
+            },
+            None => UserPosition {
                 user_id,
                 event_id,
                 option_id,
                 quantity: 0,
                 average_price: Decimal::new(0, 2),
-            }),
+            },
+        };
+        position_cache::insert(&position);
+
+        Ok(position)
+    }
+
+    /// Get positions for several (user, event, option) keys, serving what it can from
+    /// the cache and loading the rest in a single query
+    pub async fn get_positions_batch(
+        &self,
+        keys: &[PositionKey],
+    ) -> Result<HashMap<PositionKey, UserPosition>, String> {
+        let mut positions = HashMap::with_capacity(keys.len());
+        let mut missing = Vec::new();
+        for &key in keys {
+            match position_cache::get(key) {
+                Some(position) => {
+                    positions.insert(key, position);
+                }
+                None => missing.push(key),
+            }
+        }
+
+        if missing.is_empty() {
+            return Ok(positions);
         }
+
+        let user_ids: Vec<i32> = missing.iter().map(|(user_id, _, _)| *user_id).collect();
+        let event_ids: Vec<i32> = missing.iter().map(|(_, event_id, _)| *event_id).collect();
+        let option_ids: Vec<i32> = missing.iter().map(|(_, _, option_id)| *option_id).collect();
+
+        let mut loaded: HashMap<PositionKey, UserPosition> = user_positions::Entity::find()
+            .filter(user_positions::Column::UserId.is_in(user_ids))
+            .filter(user_positions::Column::EventId.is_in(event_ids))
+            .filter(user_positions::Column::OptionId.is_in(option_ids))
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to get user positions: {}", e))?
+            .into_iter()
+            .map(|p| {
+                (
+                    (p.user_id, p.event_id, p.option_id),
+                    UserPosition {
+                        user_id: p.user_id,
+                        event_id: p.event_id,
+                        option_id: p.option_id,
+                        quantity: p.quantity,
+                        average_price: p.average_price,
+                    },
+                )
+            })
+            .collect();
+
+        for (user_id, event_id, option_id) in missing {
+            let position = loaded
+                .remove(&(user_id, event_id, option_id))
+                .unwrap_or(UserPosition {
+                    user_id,
+                    event_id,
+                    option_id,
+                    quantity: 0,
+                    average_price: Decimal::new(0, 2),
+                });
+            position_cache::insert(&position);
+            positions.insert((user_id, event_id, option_id), position);
+        }
+
+        Ok(positions)
     }
 
     /// Get all positions for a user
@@ -69,47 +153,69 @@ impl PositionTracker {
             .collect())
     }
 
-    /// Update user positions based on a trade
-    pub async fn update_positions_from_trade(&self, trade: &Trade) -> Result<(), String> {
-        let txn = self
-            .db
-            .begin()
-            .await
-            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    /// Update user positions based on a trade in `txn`, which holds the lock
+    /// on its row `db_trade`. Returns the buyer's and the seller's quantities
+    /// as they were before it.
+    ///
+    /// The quantities are stored on the trade, so a trade whose settlement
+    /// is retried moves positions only once. The caller invalidates the
+    /// cached positions once `txn` commits.
+    pub async fn update_positions_from_trade(
+        &self,
+        txn: &sea_orm::DatabaseTransaction,
+        db_trade: &trades::Model,
+        trade: &Trade,
+    ) -> Result<(i32, i32), String> {
+        if let (Some(buyer_before), Some(seller_before)) = (
+            db_trade.buyer_position_before,
+            db_trade.seller_position_before,
+        ) {
+            return Ok((buyer_before, seller_before));
+        }
 
         // Update buyer's position (increasing shares)
-        self.update_position(
-            &txn,
-            trade.buyer_id,
-            trade.event_id,
-            trade.option_id,
-            trade.quantity,
-            trade.price,
-        )
-        .await?;
+        let buyer_before = self
+            .update_position(
+                txn,
+                trade.buyer_id,
+                trade.event_id,
+                trade.option_id,
+                trade.quantity,
+                trade.price,
+            )
+            .await?;
 
         // Update seller's position (decreasing shares)
-        self.update_position(
-            &txn,
-            trade.seller_id,
-            trade.event_id,
-            trade.option_id,
-            -trade.quantity,
-            trade.price,
-        )
-        .await?;
-
-        // Remove the balance update - this should be handled at the order/trade level
-        // self.update_user_balances(&txn, trade).await?;
-
-        txn.commit()
+        let seller_before = self
+            .update_position(
+                txn,
+                trade.seller_id,
+                trade.event_id,
+                trade.option_id,
+                -trade.quantity,
+                trade.price,
+            )
+            .await?;
+
+        let mut active_trade: trades::ActiveModel = db_trade.clone().into();
+        active_trade.buyer_position_before = Set(Some(buyer_before));
+        active_trade.seller_position_before = Set(Some(seller_before));
+        active_trade
+            .update(txn)
             .await
-            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+            .map_err(|e| format!("Failed to record positions on trade: {}", e))?;
 
-        Ok(())
+        Ok((buyer_before, seller_before))
     }
 
-    /// Update a single user's position
+    /// Update a single user's position, returning its quantity as it was before.
+    ///
+    /// A negative quantity is a short position, held at the average price the
+    /// shares were sold at. Only margin accounts may go short.
+    ///
+    /// The position is upserted in one statement that does the arithmetic in
+    /// SQL, so concurrent trades for the same user and option neither create
+    /// duplicate rows nor overwrite each other's changes.
     async fn update_position(
         &self,
         txn: &sea_orm::DatabaseTransaction,
@@ -118,87 +224,165 @@ impl PositionTracker {
         option_id: i32,
         quantity_change: i32,
         price: Decimal,
-    ) -> Result<(), String> {
-        let existing = user_positions::Entity::find()
-            .filter(user_positions::Column::UserId.eq(user_id))
-            .filter(user_positions::Column::EventId.eq(event_id))
-            .filter(user_positions::Column::OptionId.eq(option_id))
-            .one(txn)
-            .await
-            .map_err(|e| format!("Failed to find position: {}", e))?;
-
-        match existing {
-            Some(position) => {
-                let old_quantity = position.quantity;
-                let old_avg_price = position.average_price;
-                let new_quantity = old_quantity + quantity_change;
+    ) -> Result<i32, String> {
+        let now = chrono::Utc::now();
+        let row = user_positions::ActiveModel {
+            user_id: Set(user_id),
+            event_id: Set(event_id),
+            option_id: Set(option_id),
+            quantity: Set(quantity_change),
+            average_price: Set(price),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        };
 
-                if new_quantity < 0 {
-                    return Err("Insufficient shares to sell".to_string());
-                }
-
-                let mut active_position: user_positions::ActiveModel = position.into();
-
-                if new_quantity == 0 {
-                    // Position closed
-                    active_position.quantity = Set(0);
-                    active_position.average_price = Set(Decimal::new(0, 2));
-                } else if quantity_change > 0 {
-                    // Buying - update average price
-                    let total_cost = old_avg_price * Decimal::from(old_quantity)
-                        + price * Decimal::from(quantity_change);
-                    let new_avg_price = total_cost / Decimal::from(new_quantity);
-
-                    active_position.quantity = Set(new_quantity);
-                    active_position.average_price = Set(new_avg_price);
-                } else {
-                    // Selling - quantity decreases but average price stays the same
-                    active_position.quantity = Set(new_quantity);
-                }
+        let position = user_positions::Entity::insert(row)
+            .on_conflict(
+                OnConflict::columns([
+                    user_positions::Column::UserId,
+                    user_positions::Column::EventId,
+                    user_positions::Column::OptionId,
+                ])
+                .value(
+                    user_positions::Column::Quantity,
+                    Expr::cust("user_positions.quantity + EXCLUDED.quantity"),
+                )
+                // Closed positions have no average price. Opened or flipped
+                // positions are all at the trade price. Growing positions
+                // average in the trade, and reducing ones keep their price.
+                .value(
+                    user_positions::Column::AveragePrice,
+                    Expr::cust(
+                        "CASE \
+                         WHEN user_positions.quantity + EXCLUDED.quantity = 0 THEN 0 \
+                         WHEN user_positions.quantity = 0 \
+                           OR SIGN(user_positions.quantity) \
+                              <> SIGN(user_positions.quantity + EXCLUDED.quantity) \
+                         THEN EXCLUDED.average_price \
+                         WHEN ABS(user_positions.quantity + EXCLUDED.quantity) \
+                              > ABS(user_positions.quantity) \
+                         THEN (user_positions.average_price * ABS(user_positions.quantity) \
+                               + EXCLUDED.average_price * ABS(EXCLUDED.quantity)) \
+                              / ABS(user_positions.quantity + EXCLUDED.quantity) \
+                         ELSE user_positions.average_price END",
+                    ),
+                )
+                .update_column(user_positions::Column::UpdatedAt)
+                .to_owned(),
+            )
+            .exec_with_returning(txn)
+            .await
+            .map_err(|e| format!("Failed to update position: {}", e))?;
 
-                active_position.updated_at = Set(chrono::Utc::now().into());
-                active_position
-                    .update(txn)
-                    .await
-                    .map_err(|e| format!("Failed to update position: {}", e))?;
+        let new_quantity = position.quantity;
+        let old_quantity = new_quantity - quantity_change;
+        if quantity_change < 0 && new_quantity < 0 {
+            // Rejecting here rolls the transaction back, upsert included
+            let short_limit = Self::short_limit(txn, user_id).await?
+                + complementary_holdings_in(txn, user_id, event_id, option_id).await?;
+            if short_limit == 0 {
+                return Err("Insufficient shares to sell".to_string());
             }
-            None => {
-                if quantity_change < 0 {
-                    return Err("Cannot sell shares you don't own".to_string());
-                }
-
-                // Create new position
-                let new_position = user_positions::ActiveModel {
-                    user_id: Set(user_id),
-                    event_id: Set(event_id),
-                    option_id: Set(option_id),
-                    quantity: Set(quantity_change),
-                    average_price: Set(price),
-                    created_at: Set(chrono::Utc::now().into()),
-                    updated_at: Set(chrono::Utc::now().into()),
-                    ..Default::default()
-                };
-
-                new_position
-                    .insert(txn)
-                    .await
-                    .map_err(|e| format!("Failed to create position: {}", e))?;
+            if new_quantity < -short_limit {
+                return Err("Short position limit exceeded".to_string());
             }
         }
 
-        Ok(())
+        Ok(old_quantity)
     }
 
-    /// Validate if user has enough shares to sell
-    pub async fn validate_sell_order(
+    /// Largest short position a user may hold in one option: the
+    /// `max_short_position` setting
+    /// for margin accounts and none otherwise
+    async fn short_limit<C: ConnectionTrait>(conn: &C, user_id: i32) -> Result<i32, String> {
+        let margin_enabled: Option<bool> = users::Entity::find_by_id(user_id)
+            .select_only()
+            .column(users::Column::MarginEnabled)
+            .into_tuple()
+            .one(conn)
+            .await
+            .map_err(|e| format!("Failed to load margin account: {}", e))?;
+
+        Ok(if margin_enabled.unwrap_or(false) {
+            settings::max_short_position()
+        } else {
+            0
+        })
+    }
+
+    /// The other option of a binary event, or `None` when the event has any
+    /// other number of options
+    pub async fn complementary_option(
+        &self,
+        event_id: i32,
+        option_id: i32,
+    ) -> Result<Option<i32>, String> {
+        complementary_option_in(&self.db, event_id, option_id).await
+    }
+
+    /// Shares of the other option of a binary event a user holds, which
+    /// cover sales of this option beyond the shares they hold of it
+    pub async fn complementary_holdings(
         &self,
         user_id: i32,
         event_id: i32,
         option_id: i32,
-        quantity: i32,
-    ) -> Result<bool, String> {
-        let position = self.get_user_position(user_id, event_id, option_id).await?;
-        Ok(position.quantity >= quantity)
+    ) -> Result<i32, String> {
+        complementary_holdings_in(&self.db, user_id, event_id, option_id).await
+    }
+
+    /// Validate that every seller holds enough shares for the combined quantity
+    /// they sell across `trades`, loading all seller positions at once. Shares
+    /// of the other option of a binary event count towards what a seller
+    /// holds, and margin accounts may sell up to `MAX_SHORT_POSITION` shares
+    /// beyond that.
+    ///
+    /// Returns the first trade whose seller falls short.
+    pub async fn validate_trade_sellers<'a>(
+        &self,
+        trades: &'a [Trade],
+    ) -> Result<Option<&'a Trade>, String> {
+        let mut required: HashMap<PositionKey, i32> = HashMap::new();
+        for trade in trades {
+            *required
+                .entry((trade.seller_id, trade.event_id, trade.option_id))
+                .or_default() += trade.quantity;
+        }
+
+        let keys: Vec<PositionKey> = required.keys().copied().collect();
+        let positions = self.get_positions_batch(&keys).await?;
+
+        let seller_ids: Vec<i32> = keys.iter().map(|(user_id, _, _)| *user_id).collect();
+        let margin_sellers: Vec<i32> = users::Entity::find()
+            .select_only()
+            .column(users::Column::Id)
+            .filter(users::Column::Id.is_in(seller_ids))
+            .filter(users::Column::MarginEnabled.eq(true))
+            .into_tuple()
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load margin accounts: {}", e))?;
+        let max_short_position = settings::max_short_position();
+
+        let mut coverage: HashMap<PositionKey, i32> = HashMap::with_capacity(keys.len());
+        for &(user_id, event_id, option_id) in &keys {
+            let covered = self
+                .complementary_holdings(user_id, event_id, option_id)
+                .await?;
+            coverage.insert((user_id, event_id, option_id), covered);
+        }
+
+        Ok(trades.iter().find(|trade| {
+            let key = (trade.seller_id, trade.event_id, trade.option_id);
+            let held = positions.get(&key).map_or(0, |p| p.quantity);
+            let short_limit = if margin_sellers.contains(&trade.seller_id) {
+                max_short_position
+            } else {
+                0
+            };
+            held + coverage[&key] + short_limit < required[&key]
+        }))
     }
 
     /// Get positions grouped by event for portfolio view
@@ -215,4 +399,89 @@ impl PositionTracker {
 
         Ok(grouped)
     }
+
+    /// Sum cost basis and current value of all open positions, joined with option prices
+    pub async fn get_portfolio_totals(&self, user_id: i32) -> Result<PortfolioTotals, String> {
+        let totals = user_positions::Entity::find()
+            .select_only()
+            .column_as(Expr::cust("COUNT(user_positions.id)"), "total_positions")
+            .column_as(
+                Expr::cust("COUNT(DISTINCT user_positions.event_id)"),
+                "active_events",
+            )
+            .column_as(
+                Expr::cust(
+                    "COALESCE(SUM(user_positions.average_price * user_positions.quantity), 0)",
+                ),
+                "total_invested",
+            )
+            .column_as(
+                Expr::cust(
+                    "COALESCE(SUM(event_options.current_price * user_positions.quantity), 0)",
+                ),
+                "current_value",
+            )
+            .join(
+                JoinType::InnerJoin,
+                user_positions::Relation::EventOptions.def(),
+            )
+            .filter(user_positions::Column::UserId.eq(user_id))
+            .filter(user_positions::Column::Quantity.gt(0))
+            .into_model::<PortfolioTotals>()
+            .one(&self.db)
+            .await
+            .map_err(|e| format!("Failed to aggregate portfolio: {}", e))?;
+
+        Ok(totals.unwrap_or(PortfolioTotals {
+            total_positions: 0,
+            active_events: 0,
+            total_invested: Decimal::new(0, 2),
+            current_value: Decimal::new(0, 2),
+        }))
+    }
+}
+
+/// `PositionTracker::complementary_option` on any connection
+async fn complementary_option_in<C: ConnectionTrait>(
+    conn: &C,
+    event_id: i32,
+    option_id: i32,
+) -> Result<Option<i32>, String> {
+    let option_ids: Vec<i32> = event_options::Entity::find()
+        .select_only()
+        .column(event_options::Column::Id)
+        .filter(event_options::Column::EventId.eq(event_id))
+        .into_tuple()
+        .all(conn)
+        .await
+        .map_err(|e| format!("Failed to load event options: {}", e))?;
+
+    Ok(match option_ids.as_slice() {
+        [a, b] if *a == option_id => Some(*b),
+        [a, b] if *b == option_id => Some(*a),
+        _ => None,
+    })
+}
+
+/// `PositionTracker::complementary_holdings` on any connection
+async fn complementary_holdings_in<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    event_id: i32,
+    option_id: i32,
+) -> Result<i32, String> {
+    let Some(other_id) = complementary_option_in(conn, event_id, option_id).await? else {
+        return Ok(0);
+    };
+    let quantity: Option<i32> = user_positions::Entity::find()
+        .select_only()
+        .column(user_positions::Column::Quantity)
+        .filter(user_positions::Column::UserId.eq(user_id))
+        .filter(user_positions::Column::EventId.eq(event_id))
+        .filter(user_positions::Column::OptionId.eq(other_id))
+        .into_tuple()
+        .one(conn)
+        .await
+        .map_err(|e| format!("Failed to load complementary position: {}", e))?;
+    Ok(quantity.unwrap_or(0).max(0))
 }
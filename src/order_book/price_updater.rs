@@ -1,16 +1,291 @@
+use super::engine::OrderBookEngine;
 use super::redis_persistence::RedisOrderBookPersistence;
+use super::router;
 use crate::constants::config;
+use crate::types::websocket::{
+    OpsEventKind, OpsSeverity, OptionPriceUpdate, PriceUpdateSource, SubscriptionChannel,
+    WebSocketMessage,
+};
 use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
-use crate::websocket::server::WebSocketServer;
+use crate::websocket::ops;
+use crate::websocket::server::{Broadcast, WebSocketServer};
 use actix::Addr;
 use actix_web::web;
 use deadpool_redis::Pool;
-use entity::{event_options, events};
+use entity::{event_options, events, price_history, trades};
 use sea_orm::prelude::Decimal;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, JoinType, QueryFilter,
-    QuerySelect, RelationTrait, Set,
+    QueryOrder, QuerySelect, RelationTrait, Set, TransactionTrait,
 };
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Lowest price an option can be set to
+const MIN_OPTION_PRICE: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+
+/// Highest price an option can be set to
+const MAX_OPTION_PRICE: Decimal = Decimal::from_parts(9999, 0, 0, false, 2);
+
+/// Smallest relative change worth writing, 0.5%
+const MIN_PRICE_CHANGE_RATIO: Decimal = Decimal::from_parts(5, 0, 0, false, 3);
+
+/// How an event's option prices are derived
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PricingStrategy {
+    /// Between the best bid and ask, leaning away from the side with more
+    /// volume in the top five levels
+    #[default]
+    BookImbalance,
+    /// Halfway between the best bid and ask
+    MidPrice,
+    /// The price of the last trade
+    LastTrade,
+    /// Volume-weighted average of the last `PRICING_VWAP_TRADES` trades
+    Vwap,
+}
+
+impl PricingStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PricingStrategy::BookImbalance => "book_imbalance",
+            PricingStrategy::MidPrice => "mid_price",
+            PricingStrategy::LastTrade => "last_trade",
+            PricingStrategy::Vwap => "vwap",
+        }
+    }
+
+    /// Parse a stored strategy, falling back to book imbalance
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "mid_price" => PricingStrategy::MidPrice,
+            "last_trade" => PricingStrategy::LastTrade,
+            "vwap" => PricingStrategy::Vwap,
+            _ => PricingStrategy::BookImbalance,
+        }
+    }
+
+    /// The pricer for an option, loaded with the trades it needs
+    pub async fn pricer(
+        self,
+        db: &DatabaseConnection,
+        event_id: i32,
+        option_id: i32,
+    ) -> Result<Box<dyn Pricer>, String> {
+        Ok(match self {
+            PricingStrategy::BookImbalance => Box::new(BookImbalance),
+            PricingStrategy::MidPrice => Box::new(MidPrice),
+            PricingStrategy::LastTrade => Box::new(LastTrade),
+            PricingStrategy::Vwap => Box::new(Vwap {
+                trades: recent_trades(db, event_id, option_id, config::get_pricing_vwap_trades())
+                    .await?,
+            }),
+        })
+    }
+}
+
+/// Works out an option's price from its order book. `None` keeps the current
+/// price.
+pub trait Pricer: Send {
+    fn price(&self, book: &OrderBookEngine) -> Option<Decimal>;
+}
+
+pub struct BookImbalance;
+
+impl Pricer for BookImbalance {
+    fn price(&self, book: &OrderBookEngine) -> Option<Decimal> {
+        book.get_predicted_price()
+    }
+}
+
+/// Falls back to the last trade while either side of the book is empty
+pub struct MidPrice;
+
+impl Pricer for MidPrice {
+    fn price(&self, book: &OrderBookEngine) -> Option<Decimal> {
+        book.calculate_mid_price().or(book.last_trade_price())
+    }
+}
+
+pub struct LastTrade;
+
+impl Pricer for LastTrade {
+    fn price(&self, book: &OrderBookEngine) -> Option<Decimal> {
+        book.last_trade_price()
+    }
+}
+
+/// Averages trades read from the database, so it covers trades from before
+/// the book was last loaded
+pub struct Vwap {
+    /// Price and quantity of each trade
+    trades: Vec<(Decimal, i32)>,
+}
+
+impl Pricer for Vwap {
+    fn price(&self, _book: &OrderBookEngine) -> Option<Decimal> {
+        let quantity: i64 = self
+            .trades
+            .iter()
+            .map(|(_, quantity)| *quantity as i64)
+            .sum();
+        if quantity <= 0 {
+            return None;
+        }
+        let value: Decimal = self
+            .trades
+            .iter()
+            .map(|(price, quantity)| price * Decimal::from(*quantity))
+            .sum();
+        Some((value / Decimal::from(quantity)).round_dp(2))
+    }
+}
+
+/// Price and quantity of an option's last `limit` trades
+async fn recent_trades(
+    db: &DatabaseConnection,
+    event_id: i32,
+    option_id: i32,
+    limit: u64,
+) -> Result<Vec<(Decimal, i32)>, String> {
+    trades::Entity::find()
+        .select_only()
+        .column(trades::Column::Price)
+        .column(trades::Column::Quantity)
+        .filter(trades::Column::EventId.eq(event_id))
+        .filter(trades::Column::OptionId.eq(option_id))
+        .order_by_desc(trades::Column::Sequence)
+        .limit(limit)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load recent trades: {}", e))
+}
+
+/// Pricing strategy configured for an event
+pub async fn strategy_for_event(
+    db: &DatabaseConnection,
+    event_id: i32,
+) -> Result<PricingStrategy, String> {
+    let strategy: Option<String> = events::Entity::find_by_id(event_id)
+        .select_only()
+        .column(events::Column::PricingStrategy)
+        .into_tuple()
+        .one(db)
+        .await
+        .map_err(|e| format!("Failed to load pricing strategy: {}", e))?;
+
+    Ok(strategy
+        .map(|strategy| PricingStrategy::parse(&strategy))
+        .unwrap_or_default())
+}
+
+/// Check a derived price before it replaces an option's current price,
+/// returning the price to store, or `None` to keep the current one. A missing
+/// or non-positive price, as an emptied book can produce, never replaces a
+/// real one; others are clamped to `MIN_OPTION_PRICE`..=`MAX_OPTION_PRICE`.
+/// Jumps beyond `PRICE_JUMP_ALERT_PERCENT` are still applied, but reported.
+fn checked_price(
+    event_id: i32,
+    option_id: i32,
+    current_price: Decimal,
+    derived_price: Decimal,
+) -> Option<Decimal> {
+    if derived_price <= Decimal::ZERO {
+        log::debug!(
+            "Ignoring non-positive price {} for option {}",
+            derived_price,
+            option_id
+        );
+        return None;
+    }
+
+    let new_price = derived_price.clamp(MIN_OPTION_PRICE, MAX_OPTION_PRICE);
+    if new_price != derived_price {
+        log::warn!(
+            "Clamped derived price {} for option {} to {}",
+            derived_price,
+            option_id,
+            new_price
+        );
+    }
+
+    // An option without a price yet takes the first one it gets
+    if current_price <= Decimal::ZERO {
+        return Some(new_price);
+    }
+
+    let change_ratio = ((new_price - current_price) / current_price).abs();
+    if change_ratio <= MIN_PRICE_CHANGE_RATIO {
+        return None;
+    }
+
+    let change_percent = change_ratio * Decimal::ONE_HUNDRED;
+    if change_percent > Decimal::from(config::get_price_jump_alert_percent()) {
+        log::warn!(
+            "Option {} price jumped {:.2}% from {} to {} in one update",
+            option_id,
+            change_percent,
+            current_price,
+            new_price
+        );
+        ops::publish(
+            OpsEventKind::PriceJump,
+            OpsSeverity::Warning,
+            format!(
+                "Option {} price jumped {:.2}% from {} to {}",
+                option_id, change_percent, current_price, new_price
+            ),
+            json!({
+                "event_id": event_id,
+                "option_id": option_id,
+                "old_price": current_price,
+                "new_price": new_price,
+                "change_percent": change_percent.round_dp(2),
+            }),
+        );
+    }
+
+    Some(new_price)
+}
+
+/// Describe an option's move from `old_price` to `new_price`
+fn price_update(
+    event_id: i32,
+    option_id: i32,
+    old_price: Decimal,
+    new_price: Decimal,
+    source: PriceUpdateSource,
+) -> OptionPriceUpdate {
+    let change = new_price - old_price;
+    OptionPriceUpdate {
+        event_id,
+        option_id,
+        price: new_price,
+        previous_price: old_price,
+        change,
+        change_percent: (!old_price.is_zero())
+            .then(|| (change / old_price * Decimal::ONE_HUNDRED).round_dp(2)),
+        source,
+    }
+}
+
+/// Send an event's subscribers the price changes of its options
+fn broadcast_price_updates(
+    ws_server: &Addr<WebSocketServer>,
+    event_id: i32,
+    updates: Vec<OptionPriceUpdate>,
+) {
+    ws_server.do_send(Broadcast {
+        channel: SubscriptionChannel::Event(event_id),
+        message: WebSocketMessage::PriceUpdate {
+            updates,
+            timestamp: chrono::Utc::now(),
+        },
+    });
+}
 
 /// Update event option prices based on order book data for active events only
 pub async fn update_option_prices(
@@ -21,6 +296,24 @@ pub async fn update_option_prices(
     let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
 
     // Get only event options from active events to reduce database load
+    let strategies: HashMap<i32, PricingStrategy> = match events::Entity::find()
+        .select_only()
+        .column(events::Column::Id)
+        .column(events::Column::PricingStrategy)
+        .filter(events::Column::Status.eq("active"))
+        .into_tuple::<(i32, String)>()
+        .all(db.get_ref())
+        .await
+    {
+        Ok(strategies) => strategies
+            .into_iter()
+            .map(|(event_id, strategy)| (event_id, PricingStrategy::parse(&strategy)))
+            .collect(),
+        Err(e) => {
+            log::error!("Failed to get pricing strategies: {}", e);
+            return;
+        }
+    };
     let options = match event_options::Entity::find()
         .join(JoinType::InnerJoin, event_options::Relation::Events.def())
         .filter(events::Column::Status.eq("active"))
@@ -30,6 +323,12 @@ pub async fn update_option_prices(
         Ok(opts) => opts,
         Err(e) => {
             log::error!("Failed to get active event options: {}", e);
+            ops::publish(
+                OpsEventKind::JobFailed,
+                OpsSeverity::Warning,
+                "Price updater could not load active event options",
+                json!({ "job": "price_updater", "error": e.to_string() }),
+            );
             return;
         }
     };
@@ -49,20 +348,35 @@ pub async fn update_option_prices(
             .await
         {
             Ok(Some(order_book)) => {
-                if let Some(predicted_price) = order_book.get_predicted_price() {
-                    // Only update if price has changed significantly (more than 0.5%)
-                    let price_change_ratio =
-                        ((predicted_price - option.current_price) / option.current_price).abs();
-
-                    if price_change_ratio > Decimal::new(5, 3) {
-                        // 0.005 = 0.5%
-                        price_updates.push((
-                            option.event_id,
-                            option.id,
-                            predicted_price,
-                            option.current_price,
-                        ));
+                let strategy = strategies
+                    .get(&option.event_id)
+                    .copied()
+                    .unwrap_or_default();
+                let pricer = match strategy
+                    .pricer(db.get_ref(), option.event_id, option.id)
+                    .await
+                {
+                    Ok(pricer) => pricer,
+                    Err(e) => {
+                        log::error!("Failed to price option {}: {}", option.id, e);
+                        continue;
                     }
+                };
+                let new_price = pricer.price(&order_book).and_then(|predicted_price| {
+                    checked_price(
+                        option.event_id,
+                        option.id,
+                        option.current_price,
+                        predicted_price,
+                    )
+                });
+                if let Some(new_price) = new_price {
+                    price_updates.push((
+                        option.event_id,
+                        option.id,
+                        new_price,
+                        option.current_price,
+                    ));
                 }
             }
             Ok(None) => {
@@ -85,7 +399,8 @@ pub async fn update_option_prices(
 
     // Batch update prices
     let cache_service = CacheService::new(redis_pool.get_ref().clone());
-    let mut events_to_broadcast = std::collections::HashSet::new();
+    let mut events_to_broadcast: std::collections::HashMap<i32, Vec<OptionPriceUpdate>> =
+        std::collections::HashMap::new();
 
     for (event_id, option_id, new_price, old_price) in price_updates {
         if let Err(e) = update_single_option_price(
@@ -95,6 +410,7 @@ pub async fn update_option_prices(
             option_id,
             new_price,
             old_price,
+            PriceUpdateSource::BookImbalance,
         )
         .await
         {
@@ -102,7 +418,17 @@ pub async fn update_option_prices(
             continue;
         }
 
-        events_to_broadcast.insert(event_id);
+        ws_server.do_send(crate::websocket::server::BroadcastOptionRiskUpdate { option_id });
+        events_to_broadcast
+            .entry(event_id)
+            .or_default()
+            .push(price_update(
+                event_id,
+                option_id,
+                old_price,
+                new_price,
+                PriceUpdateSource::BookImbalance,
+            ));
     }
 
     // Broadcast updates for all affected events
@@ -112,7 +438,8 @@ pub async fn update_option_prices(
             ws_server.get_ref().clone(),
         );
 
-        for event_id in events_to_broadcast {
+        for (event_id, updates) in events_to_broadcast {
+            broadcast_price_updates(ws_server.get_ref(), event_id, updates);
             let handlers_clone = crate::websocket::handlers::WebSocketHandlers::new(
                 db.clone(),
                 ws_server.get_ref().clone(),
@@ -127,7 +454,8 @@ pub async fn update_option_prices(
     }
 }
 
-/// Update a single option's price (can be called from order handlers for event-driven updates)
+/// Update a single option's price and record it in its price history (can be
+/// called from order handlers for event-driven updates)
 pub async fn update_single_option_price(
     db: &web::Data<DatabaseConnection>,
     cache_service: &CacheService,
@@ -135,10 +463,15 @@ pub async fn update_single_option_price(
     option_id: i32,
     new_price: Decimal,
     old_price: Decimal,
+    source: PriceUpdateSource,
 ) -> Result<(), String> {
     // Update the database
+    let txn = db
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
     let option = event_options::Entity::find_by_id(option_id)
-        .one(db.get_ref())
+        .one(&txn)
         .await
         .map_err(|e| format!("Failed to find option: {}", e))?
         .ok_or("Option not found")?;
@@ -147,17 +480,38 @@ pub async fn update_single_option_price(
     active_option.current_price = Set(new_price);
 
     active_option
-        .update(db.get_ref())
+        .update(&txn)
         .await
         .map_err(|e| format!("Failed to update option price: {}", e))?;
 
-    log::info!(
-        "Updated option {} price from {} to {} (change: {:.2}%)",
-        option_id,
-        old_price,
-        new_price,
-        ((new_price - old_price) / old_price * Decimal::new(100, 0))
-    );
+    price_history::ActiveModel {
+        event_id: Set(event_id),
+        option_id: Set(option_id),
+        price: Set(new_price),
+        previous_price: Set(old_price),
+        source: Set(source.as_str().to_string()),
+        timestamp: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(|e| format!("Failed to record price history: {}", e))?;
+
+    txn.commit()
+        .await
+        .map_err(|e| format!("Failed to commit option price: {}", e))?;
+
+    if old_price.is_zero() {
+        log::info!("Set option {} price to {}", option_id, new_price);
+    } else {
+        log::info!(
+            "Updated option {} price from {} to {} (change: {:.2}%)",
+            option_id,
+            old_price,
+            new_price,
+            ((new_price - old_price) / old_price * Decimal::new(100, 0))
+        );
+    }
 
     // Invalidate relevant caches
     let event_cache_key = create_cache_key(cache_keys::EVENT_PREFIX, &event_id.to_string());
@@ -173,15 +527,16 @@ pub async fn update_single_option_price(
     Ok(())
 }
 
-/// Update price for a specific option immediately (event-driven)
+/// Update price for a specific option immediately (event-driven), after
+/// `source` changed its order book
 pub async fn update_option_price_immediately(
     db: web::Data<DatabaseConnection>,
     redis_pool: web::Data<Pool>,
     ws_server: web::Data<Addr<WebSocketServer>>,
     event_id: i32,
     option_id: i32,
+    source: PriceUpdateSource,
 ) {
-    let redis_persistence = RedisOrderBookPersistence::new(redis_pool.get_ref().clone());
     let cache_service = CacheService::new(redis_pool.get_ref().clone());
 
     // Get current price from database
@@ -200,38 +555,40 @@ pub async fn update_option_price_immediately(
         }
     };
 
-    // Get predicted price from order book
-    let predicted_price = match redis_persistence
-        .load_full_order_book(event_id, option_id)
-        .await
-    {
-        Ok(Some(order_book)) => {
-            if let Some(price) = order_book.get_predicted_price() {
-                price
-            } else {
-                log::debug!("No predicted price available for option {}", option_id);
-                return;
-            }
-        }
-        Ok(None) => {
-            log::debug!("No order book found for option {}", option_id);
-            return;
-        }
+    // Get predicted price from the event's execution venue, under its
+    // pricing strategy
+    let pricer = match strategy_for_event(db.get_ref(), event_id).await {
+        Ok(strategy) => strategy.pricer(db.get_ref(), event_id, option_id).await,
+        Err(e) => Err(e),
+    };
+    let pricer = match pricer {
+        Ok(pricer) => pricer,
         Err(e) => {
-            log::error!(
-                "Failed to load order book for immediate price update: {}",
-                e
-            );
+            log::error!("Failed to load pricer for immediate price update: {}", e);
             return;
         }
     };
+    let predicted_price =
+        match router::predicted_price(db.get_ref(), &redis_pool, event_id, option_id, pricer).await
+        {
+            Ok(Some(price)) => price,
+            Ok(None) => {
+                log::debug!("No predicted price available for option {}", option_id);
+                return;
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to query order book for immediate price update: {}",
+                    e
+                );
+                return;
+            }
+        };
 
-    // Check if price change is significant
-    let price_change_ratio = ((predicted_price - current_price) / current_price).abs();
-    if price_change_ratio <= Decimal::new(5, 3) {
-        // Less than 0.5% change - not significant enough
+    // Keep the current price unless the new one is valid and significantly different
+    let Some(new_price) = checked_price(event_id, option_id, current_price, predicted_price) else {
         return;
-    }
+    };
 
     // Update the price
     if let Err(e) = update_single_option_price(
@@ -239,8 +596,9 @@ pub async fn update_option_price_immediately(
         &cache_service,
         event_id,
         option_id,
-        predicted_price,
+        new_price,
         current_price,
+        source,
     )
     .await
     {
@@ -249,6 +607,17 @@ pub async fn update_option_price_immediately(
     }
 
     // Broadcast the update
+    broadcast_price_updates(
+        ws_server.get_ref(),
+        event_id,
+        vec![price_update(
+            event_id,
+            option_id,
+            current_price,
+            new_price,
+            source,
+        )],
+    );
     let handlers =
         crate::websocket::handlers::WebSocketHandlers::new(db.clone(), ws_server.get_ref().clone());
 
@@ -257,6 +626,7 @@ pub async fn update_option_price_immediately(
     });
 
     ws_server.do_send(crate::websocket::server::BroadcastEventsUpdate);
+    ws_server.do_send(crate::websocket::server::BroadcastOptionRiskUpdate { option_id });
 }
 
 /// Start a background task to periodically update prices for active events
@@ -1,10 +1,76 @@
 use super::engine::OrderBookEngine;
-use super::types::{Order, OrderStatus, Trade};
-use deadpool_redis::{redis::AsyncCommands, Pool};
+use super::types::{AuctionState, BookJournalEntry, Order, OrderStatus, Trade};
+use crate::types::order_book::OrderBookResponse;
+use deadpool_redis::{
+    redis::{self, AsyncCommands, Script},
+    Pool,
+};
+use lazy_static::lazy_static;
 use sea_orm::prelude::Decimal;
 use serde_json;
 use std::collections::VecDeque;
 
+/// Sorted set of "{event_id}:{option_id}" scored by auction end time in milliseconds
+const SCHEDULED_AUCTIONS_KEY: &str = "auctions:scheduled";
+
+/// Expiry for the price level and metadata keys of a saved order book
+const ORDER_BOOK_TTL_SECONDS: u64 = 86400;
+
+/// Storage layout written by `save_full_order_book`, recorded in the metadata.
+/// Books without it keep each price level under its own key and are migrated
+/// when loaded.
+const ORDER_BOOK_LAYOUT: u64 = 2;
+
+lazy_static! {
+    /// Replaces a saved order book in one step so readers never see a partial book.
+    ///
+    /// KEYS: orders hash, metadata, buy levels hash, sell levels hash, the
+    /// index of per-key price levels left by the previous layout, and the journal.
+    /// ARGV: ttl, metadata, order count, buy level count, sell level count, id of
+    /// the last journal entry in the snapshot (or empty), then (order id, order)
+    /// pairs, (price, orders) buy pairs and (price, orders) sell pairs.
+    static ref SAVE_ORDER_BOOK_SCRIPT: Script = Script::new(
+        r#"
+        local ttl = tonumber(ARGV[1])
+        local counts = { tonumber(ARGV[3]), tonumber(ARGV[4]), tonumber(ARGV[5]) }
+
+        for _, level_key in ipairs(redis.call('SMEMBERS', KEYS[5])) do
+            redis.call('DEL', level_key)
+        end
+        redis.call('DEL', KEYS[1], KEYS[3], KEYS[4], KEYS[5])
+
+        -- Orders, buy levels and sell levels are consecutive runs of field/value pairs
+        local targets = { KEYS[1], KEYS[3], KEYS[4] }
+        local arg = 7
+        for t = 1, 3 do
+            for _ = 1, counts[t] do
+                redis.call('HSET', targets[t], ARGV[arg], ARGV[arg + 1])
+                arg = arg + 2
+            end
+        end
+
+        redis.call('EXPIRE', KEYS[3], ttl)
+        redis.call('EXPIRE', KEYS[4], ttl)
+        redis.call('SET', KEYS[2], ARGV[2], 'EX', ttl)
+
+        -- Entries up to the snapshot are no longer needed to rebuild the book
+        if ARGV[6] ~= '' then
+            redis.call('XTRIM', KEYS[6], 'MINID', ARGV[6])
+        end
+        return counts[1]
+        "#
+    );
+}
+
+/// Saved price levels as (price, serialized orders at that price)
+type PriceLevels = Vec<(String, String)>;
+
+/// Journal stream entries as (entry id, field/value pairs)
+type JournalEntries = Vec<(String, Vec<(String, String)>)>;
+
+/// Field holding the serialized `BookJournalEntry` in each journal stream entry
+const JOURNAL_ENTRY_FIELD: &str = "entry";
+
 pub struct RedisOrderBookPersistence {
     pool: Pool,
 }
@@ -14,12 +80,14 @@ impl RedisOrderBookPersistence {
         Self { pool }
     }
 
-    /// Save the complete order book state to Redis
+    /// Save the complete order book state to Redis, replacing the previous snapshot atomically.
+    /// `wal_id` is the last journal entry the book reflects; older entries are trimmed.
     pub async fn save_full_order_book(
         &self,
         event_id: i32,
         option_id: i32,
         order_book: &OrderBookEngine,
+        wal_id: Option<&str>,
     ) -> Result<(), String> {
         let mut conn = self
             .pool
@@ -32,91 +100,157 @@ impl RedisOrderBookPersistence {
         // Get the internal state of the order book
         let (buy_orders, sell_orders, orders_map, last_trade_price) =
             order_book.get_internal_state();
+        let (reference_price, reference_set_at, halt) = order_book.get_circuit_breaker_state();
 
-        // Start a Redis transaction
-        let _: () = deadpool_redis::redis::cmd("MULTI")
-            .query_async(&mut conn)
-            .await
-            .map_err(|e| format!("Failed to start Redis transaction: {}", e))?;
-
-        // Clear existing data
-        let buy_orders_key = format!("{}:buys", base_key);
-        let sell_orders_key = format!("{}:sells", base_key);
-        let orders_map_key = format!("{}:orders", base_key);
-        let metadata_key = format!("{}:metadata", base_key);
+        let metadata = serde_json::json!({
+            "event_id": event_id,
+            "option_id": option_id,
+            "layout": ORDER_BOOK_LAYOUT,
+            "last_trade_price": last_trade_price,
+            "reference_price": reference_price,
+            "reference_set_at": reference_set_at,
+            "halt": halt,
+            "auction": order_book.auction(),
+            "sequence": order_book.sequence(),
+            "wal_id": wal_id,
+            "last_updated": chrono::Utc::now().to_rfc3339()
+        });
 
-        let _: () = conn
-            .del(&buy_orders_key)
-            .await
-            .map_err(|e| format!("Failed to delete buy orders: {}", e))?;
-        let _: () = conn
-            .del(&sell_orders_key)
-            .await
-            .map_err(|e| format!("Failed to delete sell orders: {}", e))?;
-        let _: () = conn
-            .del(&orders_map_key)
-            .await
-            .map_err(|e| format!("Failed to delete orders map: {}", e))?;
+        let mut invocation = SAVE_ORDER_BOOK_SCRIPT.prepare_invoke();
+        invocation
+            .key(format!("{}:orders", base_key))
+            .key(format!("{}:metadata", base_key))
+            .key(format!("{}:buy_levels", base_key))
+            .key(format!("{}:sell_levels", base_key))
+            .key(format!("{}:levels", base_key))
+            .key(format!("{}:wal", base_key))
+            .arg(ORDER_BOOK_TTL_SECONDS)
+            .arg(metadata.to_string())
+            .arg(orders_map.len())
+            .arg(buy_orders.len())
+            .arg(sell_orders.len())
+            .arg(wal_id.unwrap_or_default());
 
-        // Save buy orders (price -> list of orders)
+        for (order_id, order) in orders_map {
+            let serialized_order = serde_json::to_string(&order)
+                .map_err(|e| format!("Failed to serialize order: {}", e))?;
+            invocation.arg(order_id).arg(serialized_order);
+        }
         for (price, orders) in buy_orders {
-            let price_key = format!("{}:{}", buy_orders_key, price);
             let serialized_orders = serde_json::to_string(&orders)
                 .map_err(|e| format!("Failed to serialize buy orders: {}", e))?;
-            let _: () = conn
-                .set_ex(&price_key, serialized_orders, 86400)
-                .await
-                .map_err(|e| format!("Failed to save buy orders at price {}: {}", price, e))?;
+            invocation.arg(price.to_string()).arg(serialized_orders);
         }
-
-        // Save sell orders (price -> list of orders)
         for (price, orders) in sell_orders {
-            let price_key = format!("{}:{}", sell_orders_key, price);
             let serialized_orders = serde_json::to_string(&orders)
                 .map_err(|e| format!("Failed to serialize sell orders: {}", e))?;
-            let _: () = conn
-                .set_ex(&price_key, serialized_orders, 86400)
-                .await
-                .map_err(|e| format!("Failed to save sell orders at price {}: {}", price, e))?;
+            invocation.arg(price.to_string()).arg(serialized_orders);
         }
 
-        // Save orders map
-        for (order_id, order) in orders_map {
-            let serialized_order = serde_json::to_string(&order)
-                .map_err(|e| format!("Failed to serialize order: {}", e))?;
-            let _: () = conn
-                .hset(&orders_map_key, order_id, serialized_order)
-                .await
-                .map_err(|e| format!("Failed to save order to map: {}", e))?;
-        }
+        let _: i64 = invocation
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to save order book: {}", e))?;
 
-        // Save metadata
-        let metadata = serde_json::json!({
-            "event_id": event_id,
-            "option_id": option_id,
-            "last_trade_price": last_trade_price,
-            "last_updated": chrono::Utc::now().to_rfc3339()
-        });
-        let _: () = conn
-            .set_ex(&metadata_key, metadata.to_string(), 86400)
+        Ok(())
+    }
+
+    /// Append a batch of order book changes to the book's write-ahead log and
+    /// return the id of the new entry
+    pub async fn append_journal(
+        &self,
+        event_id: i32,
+        option_id: i32,
+        entry: &BookJournalEntry,
+    ) -> Result<String, String> {
+        let mut conn = self
+            .pool
+            .get()
             .await
-            .map_err(|e| format!("Failed to save metadata: {}", e))?;
+            .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
 
-        // Execute transaction
-        let _: () = deadpool_redis::redis::cmd("EXEC")
+        let key = format!("orderbook:{}:{}:wal", event_id, option_id);
+        let serialized = serde_json::to_string(entry)
+            .map_err(|e| format!("Failed to serialize journal entry: {}", e))?;
+
+        let (id,): (String,) = redis::pipe()
+            .atomic()
+            .xadd(&key, "*", &[(JOURNAL_ENTRY_FIELD, serialized)])
+            .expire(&key, ORDER_BOOK_TTL_SECONDS as i64)
+            .ignore()
             .query_async(&mut conn)
             .await
-            .map_err(|e| format!("Failed to execute Redis transaction: {}", e))?;
+            .map_err(|e| format!("Failed to append journal entry: {}", e))?;
 
-        Ok(())
+        Ok(id)
+    }
+
+    /// Publish the book as `GET /order-book` serves it, replacing the previous
+    /// view. Only the task that owns the book writes it, after each change.
+    pub async fn save_book_view(&self, view: &OrderBookResponse) -> Result<(), String> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+
+        let serialized = serde_json::to_string(view)
+            .map_err(|e| format!("Failed to serialize order book view: {}", e))?;
+        conn.set_ex::<_, _, ()>(
+            format!("orderbook:{}:{}:view", view.event_id, view.option_id),
+            serialized,
+            ORDER_BOOK_TTL_SECONDS,
+        )
+        .await
+        .map_err(|e| format!("Failed to save order book view: {}", e))
     }
 
-    /// Load the complete order book state from Redis
+    /// The book's latest published view, if it has been loaded since the view
+    /// last expired
+    pub async fn load_book_view(
+        &self,
+        event_id: i32,
+        option_id: i32,
+    ) -> Result<Option<OrderBookResponse>, String> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+
+        let serialized: Option<String> = conn
+            .get(format!("orderbook:{}:{}:view", event_id, option_id))
+            .await
+            .map_err(|e| format!("Failed to load order book view: {}", e))?;
+
+        serialized
+            .map(|s| {
+                serde_json::from_str(&s)
+                    .map_err(|e| format!("Failed to deserialize order book view: {}", e))
+            })
+            .transpose()
+    }
+
+    /// Load the complete order book state from Redis: the last snapshot plus any
+    /// journal entries written after it
     pub async fn load_full_order_book(
         &self,
         event_id: i32,
         option_id: i32,
     ) -> Result<Option<OrderBookEngine>, String> {
+        Ok(self
+            .load_book(event_id, option_id)
+            .await?
+            .map(|(order_book, _, _)| order_book))
+    }
+
+    /// Load a book along with the id of the last journal entry applied to it and
+    /// how many entries were replayed on top of the snapshot
+    async fn load_book(
+        &self,
+        event_id: i32,
+        option_id: i32,
+    ) -> Result<Option<(OrderBookEngine, Option<String>, usize)>, String> {
         let mut conn = self
             .pool
             .get()
@@ -126,21 +260,20 @@ impl RedisOrderBookPersistence {
         let base_key = format!("orderbook:{}:{}", event_id, option_id);
         let metadata_key = format!("{}:metadata", base_key);
 
-        // Check if order book exists
-        let exists: bool = conn
-            .exists(&metadata_key)
-            .await
-            .map_err(|e| format!("Failed to check order book existence: {}", e))?;
+        // Read the metadata and both sides in one round trip so they come from the same save
+        let (metadata_str, buy_levels, sell_levels): (Option<String>, PriceLevels, PriceLevels) =
+            redis::pipe()
+                .atomic()
+                .get(&metadata_key)
+                .hgetall(format!("{}:buy_levels", base_key))
+                .hgetall(format!("{}:sell_levels", base_key))
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| format!("Failed to load order book: {}", e))?;
 
-        if !exists {
+        let Some(metadata_str) = metadata_str else {
             return Ok(None);
-        }
-
-        // Load metadata
-        let metadata_str: String = conn
-            .get(&metadata_key)
-            .await
-            .map_err(|e| format!("Failed to load metadata: {}", e))?;
+        };
         let metadata: serde_json::Value = serde_json::from_str(&metadata_str)
             .map_err(|e| format!("Failed to parse metadata: {}", e))?;
 
@@ -148,92 +281,264 @@ impl RedisOrderBookPersistence {
             .get("last_trade_price")
             .and_then(|v| v.as_str())
             .and_then(|s| s.parse::<Decimal>().ok());
+        let reference_price = metadata
+            .get("reference_price")
+            .and_then(|v| v.as_str())
+            .and_then(|s| s.parse::<Decimal>().ok());
+        let reference_set_at = metadata
+            .get("reference_set_at")
+            .and_then(|v| serde_json::from_value(v.clone()).ok());
+        let halt = metadata
+            .get("halt")
+            .and_then(|v| serde_json::from_value(v.clone()).ok());
+        let auction: Option<AuctionState> = metadata
+            .get("auction")
+            .and_then(|v| serde_json::from_value(v.clone()).ok());
+        let sequence = metadata
+            .get("sequence")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        let snapshot_wal_id = metadata
+            .get("wal_id")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
 
         // Create new order book
         let mut order_book = OrderBookEngine::new(event_id, option_id);
 
-        // Load buy orders
-        let buy_orders_key = format!("{}:buys", base_key);
-        let buy_price_keys: Vec<String> = conn
-            .keys(format!("{}:*", buy_orders_key))
-            .await
-            .map_err(|e| format!("Failed to get buy order keys: {}", e))?;
-
-        for price_key in buy_price_keys {
-            let price_str = price_key
-                .split(':')
-                .next_back()
-                .ok_or("Invalid price key format")?;
-            let price = price_str
-                .parse::<Decimal>()
-                .map_err(|e| format!("Failed to parse price: {}", e))?;
-
-            let orders_str: String = conn
-                .get(&price_key)
-                .await
-                .map_err(|e| format!("Failed to load buy orders at price {}: {}", price, e))?;
+        let is_legacy_layout = metadata.get("layout").is_none();
+        let (levels, legacy_keys) = if is_legacy_layout {
+            self.load_legacy_levels(&mut conn, &base_key).await?
+        } else {
+            (
+                buy_levels.into_iter().chain(sell_levels).collect(),
+                Vec::new(),
+            )
+        };
+
+        for (price, orders_str) in levels {
             let orders: VecDeque<Order> = serde_json::from_str(&orders_str)
-                .map_err(|e| format!("Failed to deserialize buy orders: {}", e))?;
+                .map_err(|e| format!("Failed to deserialize orders at price {}: {}", price, e))?;
 
-            // Reconstruct buy orders in the order book
+            // Each order carries its side, so both sides rebuild the same way
             for order in orders {
                 order_book.add_order_directly(order);
             }
         }
 
-        // Load sell orders
-        let sell_orders_key = format!("{}:sells", base_key);
-        let sell_price_keys: Vec<String> = conn
-            .keys(format!("{}:*", sell_orders_key))
-            .await
-            .map_err(|e| format!("Failed to get sell order keys: {}", e))?;
+        // Set last trade price if available
+        if let Some(price) = last_trade_price {
+            order_book.set_last_trade_price(price);
+        }
+        order_book.restore_circuit_breaker_state(reference_price, reference_set_at, halt);
+        order_book.restore_auction(auction);
+        order_book.restore_sequence(sequence);
 
-        for price_key in sell_price_keys {
-            let price_str = price_key
-                .split(':')
-                .next_back()
-                .ok_or("Invalid price key format")?;
-            let price = price_str
-                .parse::<Decimal>()
-                .map_err(|e| format!("Failed to parse price: {}", e))?;
+        let (wal_id, replayed) = self
+            .replay_journal(&mut conn, &base_key, &mut order_book, snapshot_wal_id)
+            .await?;
 
-            let orders_str: String = conn
-                .get(&price_key)
-                .await
-                .map_err(|e| format!("Failed to load sell orders at price {}: {}", price, e))?;
-            let orders: VecDeque<Order> = serde_json::from_str(&orders_str)
-                .map_err(|e| format!("Failed to deserialize sell orders: {}", e))?;
+        if is_legacy_layout {
+            // Rewrite in the current layout before dropping the old per-level keys
+            self.save_full_order_book(event_id, option_id, &order_book, wal_id.as_deref())
+                .await?;
+            if !legacy_keys.is_empty() {
+                let _: () = conn
+                    .del(&legacy_keys)
+                    .await
+                    .map_err(|e| format!("Failed to remove legacy price levels: {}", e))?;
+            }
+            log::info!(
+                "Migrated order book {}:{} from {} per-key price levels",
+                event_id,
+                option_id,
+                legacy_keys.len()
+            );
+        }
 
-            // Reconstruct sell orders in the order book
-            for order in orders {
-                order_book.add_order_directly(order);
+        Ok(Some((order_book, wal_id, replayed)))
+    }
+
+    /// Apply the journal entries written after `after` (or all of them) in order,
+    /// returning the id of the last entry applied and how many were applied
+    async fn replay_journal(
+        &self,
+        conn: &mut deadpool_redis::Connection,
+        base_key: &str,
+        order_book: &mut OrderBookEngine,
+        after: Option<String>,
+    ) -> Result<(Option<String>, usize), String> {
+        let start = after
+            .as_ref()
+            .map(|id| format!("({}", id))
+            .unwrap_or_else(|| "-".to_string());
+        let entries: JournalEntries = redis::cmd("XRANGE")
+            .arg(format!("{}:wal", base_key))
+            .arg(start)
+            .arg("+")
+            .query_async(conn)
+            .await
+            .map_err(|e| format!("Failed to read order book journal: {}", e))?;
+
+        let mut last_id = after;
+        let replayed = entries.len();
+        for (id, fields) in entries {
+            let serialized = fields
+                .into_iter()
+                .find(|(field, _)| field == JOURNAL_ENTRY_FIELD)
+                .map(|(_, value)| value)
+                .ok_or_else(|| format!("Journal entry {} has no payload", id))?;
+            let entry: BookJournalEntry = serde_json::from_str(&serialized)
+                .map_err(|e| format!("Failed to deserialize journal entry {}: {}", id, e))?;
+            order_book.apply_journal_entry(entry);
+            last_id = Some(id);
+        }
+
+        Ok((last_id, replayed))
+    }
+
+    /// Read price levels saved one key per level by the previous layout. SCAN is
+    /// used because those keys have no index; it only runs once per migrated book.
+    async fn load_legacy_levels(
+        &self,
+        conn: &mut deadpool_redis::Connection,
+        base_key: &str,
+    ) -> Result<(PriceLevels, Vec<String>), String> {
+        let mut level_keys = Vec::new();
+        for side in ["buys", "sells"] {
+            let mut iter = conn
+                .scan_match::<_, String>(format!("{}:{}:*", base_key, side))
+                .await
+                .map_err(|e| format!("Failed to scan legacy price levels: {}", e))?;
+            while let Some(key) = iter.next_item().await {
+                level_keys.push(key);
             }
         }
 
-        // Set last trade price if available
-        if let Some(price) = last_trade_price {
-            order_book.set_last_trade_price(price);
+        let mut levels = Vec::with_capacity(level_keys.len());
+        for key in &level_keys {
+            let price = key
+                .rsplit(':')
+                .next()
+                .ok_or("Invalid price key format")?
+                .to_string();
+            let orders_str: Option<String> = conn
+                .get(key)
+                .await
+                .map_err(|e| format!("Failed to load orders at price {}: {}", price, e))?;
+            if let Some(orders_str) = orders_str {
+                levels.push((price, orders_str));
+            }
         }
 
-        Ok(Some(order_book))
+        Ok((levels, level_keys))
     }
 
-    /// Get or create an order book from Redis
+    /// Get or create an order book from Redis, along with the id of the last
+    /// journal entry it reflects.
+    ///
+    /// Only the book's owner may call this: a replayed book is snapshotted again,
+    /// which trims the journal.
     pub async fn get_or_create_order_book(
         &self,
         event_id: i32,
         option_id: i32,
-    ) -> Result<OrderBookEngine, String> {
+    ) -> Result<(OrderBookEngine, Option<String>), String> {
         // Try to load existing order book
-        if let Some(order_book) = self.load_full_order_book(event_id, option_id).await? {
-            return Ok(order_book);
+        if let Some((mut order_book, wal_id, replayed)) =
+            self.load_book(event_id, option_id).await?
+        {
+            if replayed > 0 {
+                log::info!(
+                    "Replayed {} journal entries for order book {}:{}",
+                    replayed,
+                    event_id,
+                    option_id
+                );
+                self.save_full_order_book(event_id, option_id, &order_book, wal_id.as_deref())
+                    .await?;
+            }
+            order_book.take_journal_entry();
+            return Ok((order_book, wal_id));
         }
 
-        // Create new order book if doesn't exist
+        // Create new order book if doesn't exist, dropping any journal left
+        // behind by an expired snapshot
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+        let _: () = conn
+            .del(format!("orderbook:{}:{}:wal", event_id, option_id))
+            .await
+            .map_err(|e| format!("Failed to clear order book journal: {}", e))?;
+
         let order_book = OrderBookEngine::new(event_id, option_id);
-        self.save_full_order_book(event_id, option_id, &order_book)
+        self.save_full_order_book(event_id, option_id, &order_book, None)
             .await?;
-        Ok(order_book)
+        Ok((order_book, None))
+    }
+
+    /// Register an auction so the scheduler uncrosses it once it ends
+    pub async fn schedule_auction(
+        &self,
+        event_id: i32,
+        option_id: i32,
+        auction: &AuctionState,
+    ) -> Result<(), String> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+
+        let member = format!("{}:{}", event_id, option_id);
+        let score = auction.ends_at.timestamp_millis() as f64;
+        conn.zadd::<_, _, _, ()>(SCHEDULED_AUCTIONS_KEY, member, score)
+            .await
+            .map_err(|e| format!("Failed to schedule auction: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Event options whose auctions have ended and are waiting to be uncrossed
+    pub async fn get_due_auctions(&self) -> Result<Vec<(i32, i32)>, String> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+
+        let now = chrono::Utc::now().timestamp_millis() as f64;
+        let members: Vec<String> = conn
+            .zrangebyscore(SCHEDULED_AUCTIONS_KEY, f64::NEG_INFINITY, now)
+            .await
+            .map_err(|e| format!("Failed to get due auctions: {}", e))?;
+
+        Ok(members
+            .iter()
+            .filter_map(|member| {
+                let (event_id, option_id) = member.split_once(':')?;
+                Some((event_id.parse().ok()?, option_id.parse().ok()?))
+            })
+            .collect())
+    }
+
+    /// Remove an auction from the schedule once it has been uncrossed
+    pub async fn unschedule_auction(&self, event_id: i32, option_id: i32) -> Result<(), String> {
+        let mut conn = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| format!("Failed to get Redis connection: {}", e))?;
+
+        let member = format!("{}:{}", event_id, option_id);
+        conn.zrem::<_, _, ()>(SCHEDULED_AUCTIONS_KEY, member)
+            .await
+            .map_err(|e| format!("Failed to unschedule auction: {}", e))?;
+
+        Ok(())
     }
 
     /// Save an order book to Redis
@@ -399,7 +704,7 @@ impl RedisOrderBookPersistence {
         // Add to event-option trade list
         let event_trades_key =
             format!("event:{}:option:{}:trades", trade.event_id, trade.option_id);
-        let score = trade.timestamp.timestamp_millis() as f64;
+        let score = trade.timestamp.timestamp_micros() as f64;
 
         conn.zadd::<_, _, _, ()>(&event_trades_key, &trade.id, score)
             .await
@@ -510,7 +815,7 @@ impl RedisOrderBookPersistence {
         }
 
         // Sort by created_at descending
-        orders.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        orders.sort_by_key(|o| std::cmp::Reverse(o.created_at));
 
         Ok(orders)
     }
@@ -0,0 +1,177 @@
+use super::fees;
+use super::margin;
+use super::types::{Order, OrderSide, OrderStatus};
+use entity::{orders, wallets};
+use rust_decimal::RoundingStrategy;
+use sea_orm::{
+    prelude::{Decimal, Expr},
+    ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter,
+};
+
+/// Reason `hold` fails when the user's available balance can't cover it
+pub const INSUFFICIENT_BALANCE: &str = "insufficient_balance";
+
+/// Funds an order holds while it is open: for a buy order, the notional of
+/// its unfilled quantity at its hold price plus the worst-case fee on it, and
+/// for a sell order, the collateral of the shares it sells short that haven't
+/// filled yet
+pub fn reservation_for(order: &orders::Model) -> Decimal {
+    let open = order.status == OrderStatus::Pending.to_string()
+        || order.status == OrderStatus::PartiallyFilled.to_string();
+    if !open {
+        return Decimal::ZERO;
+    }
+    let remaining = order.quantity - order.filled_quantity;
+    if order.side == OrderSide::Buy.to_string() {
+        hold_amount(order.hold_price.unwrap_or(order.price), remaining)
+    } else {
+        short_hold_amount(order.price, order.short_quantity, remaining)
+    }
+}
+
+/// `reservation_for` an in-memory order
+pub fn order_reservation(order: &Order) -> Decimal {
+    if !order.is_open() {
+        return Decimal::ZERO;
+    }
+    match order.side {
+        OrderSide::Buy => hold_amount(
+            order.hold_price.unwrap_or(order.price),
+            order.remaining_quantity(),
+        ),
+        OrderSide::Sell => short_hold_amount(
+            order.price,
+            order.short_quantity,
+            order.remaining_quantity(),
+        ),
+    }
+}
+
+/// Funds needed to buy `quantity` at `price`, including the worst-case fee,
+/// rounded up to the cent so holds and releases add up exactly
+pub fn hold_amount(price: Decimal, quantity: i32) -> Decimal {
+    let notional = price * Decimal::from(quantity.max(0));
+    (notional + fees::max_trading_fee(notional))
+        .round_dp_with_strategy(2, RoundingStrategy::AwayFromZero)
+}
+
+/// Collateral held for the `short_quantity` shares of a sell order at `price`
+/// that are still among its `remaining` ones. The order fills the shares the
+/// seller holds first, so the short ones are the last to go.
+fn short_hold_amount(price: Decimal, short_quantity: i32, remaining: i32) -> Decimal {
+    margin::collateral(price, short_quantity.min(remaining))
+}
+
+/// Reserve `amount` of a user's balance in `currency`, failing with
+/// `INSUFFICIENT_BALANCE` unless it fits in what isn't already reserved. The
+/// check and the update are one statement, so concurrent orders can't both
+/// spend the same funds.
+pub async fn hold<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    currency: &str,
+    amount: Decimal,
+) -> Result<(), String> {
+    if amount <= Decimal::ZERO {
+        return Ok(());
+    }
+
+    let result = wallets::Entity::update_many()
+        .col_expr(
+            wallets::Column::ReservedBalance,
+            Expr::col(wallets::Column::ReservedBalance).add(amount),
+        )
+        .filter(wallets::Column::UserId.eq(user_id))
+        .filter(wallets::Column::Currency.eq(currency))
+        .filter(
+            Expr::col(wallets::Column::Balance)
+                .gte(Expr::col(wallets::Column::ReservedBalance).add(amount)),
+        )
+        .exec(conn)
+        .await
+        .map_err(|e| format!("Failed to reserve funds for user {}: {}", user_id, e))?;
+
+    if result.rows_affected == 0 {
+        return Err(INSUFFICIENT_BALANCE.to_string());
+    }
+    Ok(())
+}
+
+/// Move a user's reservation from what an order held before a change to what
+/// it holds after. Increases are not checked against the balance here; callers
+/// that grow an order check availability up front.
+pub async fn rebalance<C: ConnectionTrait>(
+    conn: &C,
+    before: &orders::Model,
+    after: &orders::Model,
+) -> Result<(), String> {
+    let delta = reservation_for(after) - reservation_for(before);
+    if delta.is_zero() {
+        return Ok(());
+    }
+    adjust(conn, after.user_id, &after.currency, delta).await
+}
+
+/// `rebalance` for a change that can grow an order, such as an amendment:
+/// an increase is held like a new order's, failing with `INSUFFICIENT_BALANCE`
+/// unless the balance covers it
+pub async fn rebalance_checked<C: ConnectionTrait>(
+    conn: &C,
+    before: &orders::Model,
+    after: &orders::Model,
+) -> Result<(), String> {
+    let delta = reservation_for(after) - reservation_for(before);
+    if delta > Decimal::ZERO {
+        return hold(conn, after.user_id, &after.currency, delta).await;
+    }
+    rebalance(conn, before, after).await
+}
+
+/// Give back `amount` of a user's reservation in `currency`, such as the hold
+/// of a withdrawal that has been paid out or rejected
+pub async fn release<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    currency: &str,
+    amount: Decimal,
+) -> Result<(), String> {
+    if amount <= Decimal::ZERO {
+        return Ok(());
+    }
+    adjust(conn, user_id, currency, -amount).await
+}
+
+/// Add `delta` to a user's reservation in `currency`. Releasing more than is
+/// held means a hold was never taken or was released twice, so it fails
+/// rather than eat into the holds of other orders.
+async fn adjust<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    currency: &str,
+    delta: Decimal,
+) -> Result<(), String> {
+    let result = wallets::Entity::update_many()
+        .col_expr(
+            wallets::Column::ReservedBalance,
+            Expr::col(wallets::Column::ReservedBalance).add(delta),
+        )
+        .filter(wallets::Column::UserId.eq(user_id))
+        .filter(wallets::Column::Currency.eq(currency))
+        .filter(Expr::col(wallets::Column::ReservedBalance).gte(-delta))
+        .exec(conn)
+        .await
+        .map_err(|e| {
+            format!(
+                "Failed to update reserved funds for user {}: {}",
+                user_id, e
+            )
+        })?;
+
+    if result.rows_affected == 0 {
+        return Err(format!(
+            "Reserved {} funds of user {} can't change by {}: less is held or there is no wallet",
+            currency, user_id, delta
+        ));
+    }
+    Ok(())
+}
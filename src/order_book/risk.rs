@@ -0,0 +1,178 @@
+use super::{margin, types::Trade};
+use crate::constants::config;
+use crate::settings::registry as settings;
+use crate::types::risk::{RiskPosition, RiskSummary};
+use crate::wallets::balances;
+use crate::websocket::server::{BroadcastRiskUpdate, WebSocketServer};
+use actix::Addr;
+use chrono::Utc;
+use entity::{event_options, events, user_positions, users, wallets};
+use sea_orm::{
+    prelude::Decimal, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QuerySelect,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Compute a user's exposure and collateral usage at current option prices,
+/// one summary per currency they hold or have positions in. Positions are
+/// only covered by the wallet in their event's currency.
+pub async fn summary<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+) -> Result<Vec<RiskSummary>, String> {
+    let user = users::Entity::find_by_id(user_id)
+        .one(conn)
+        .await
+        .map_err(|e| format!("Failed to find user {}: {}", user_id, e))?
+        .ok_or_else(|| format!("User {} not found", user_id))?;
+
+    let positions = user_positions::Entity::find()
+        .filter(user_positions::Column::UserId.eq(user_id))
+        .filter(user_positions::Column::Quantity.ne(0))
+        .all(conn)
+        .await
+        .map_err(|e| format!("Failed to load positions: {}", e))?;
+
+    let wallets = balances::for_user(conn, user_id)
+        .await
+        .map_err(|e| format!("Failed to load wallets: {}", e))?;
+
+    let event_ids: Vec<i32> = positions.iter().map(|p| p.event_id).collect();
+    let currencies: HashMap<i32, String> = events::Entity::find()
+        .select_only()
+        .column(events::Column::Id)
+        .column(events::Column::Currency)
+        .filter(events::Column::Id.is_in(event_ids))
+        .into_tuple()
+        .all(conn)
+        .await
+        .map_err(|e| format!("Failed to load event currencies: {}", e))?
+        .into_iter()
+        .collect();
+
+    let option_ids: Vec<i32> = positions.iter().map(|p| p.option_id).collect();
+    let prices: HashMap<i32, Decimal> = event_options::Entity::find()
+        .select_only()
+        .column(event_options::Column::Id)
+        .column(event_options::Column::CurrentPrice)
+        .filter(event_options::Column::Id.is_in(option_ids))
+        .into_tuple()
+        .all(conn)
+        .await
+        .map_err(|e| format!("Failed to load option prices: {}", e))?
+        .into_iter()
+        .collect();
+
+    let mut positions_by_currency: BTreeMap<String, Vec<user_positions::Model>> = wallets
+        .iter()
+        .map(|wallet| (wallet.currency.clone(), Vec::new()))
+        .collect();
+    for position in positions {
+        if let Some(currency) = currencies.get(&position.event_id) {
+            positions_by_currency
+                .entry(currency.clone())
+                .or_default()
+                .push(position);
+        }
+    }
+
+    let mut summaries: Vec<RiskSummary> = positions_by_currency
+        .into_iter()
+        .map(|(currency, positions)| {
+            let wallet = wallets.iter().find(|w| w.currency == currency);
+            summarize(&user, currency, wallet, positions, &prices)
+        })
+        .collect();
+    let base = config::get_base_currency();
+    summaries.sort_by_key(|summary| summary.currency != base);
+    Ok(summaries)
+}
+
+fn summarize(
+    user: &users::Model,
+    currency: String,
+    wallet: Option<&wallets::Model>,
+    positions: Vec<user_positions::Model>,
+    prices: &HashMap<i32, Decimal>,
+) -> RiskSummary {
+    let max_short_position = settings::max_short_position();
+    let short_limit = if user.margin_enabled {
+        max_short_position
+    } else {
+        0
+    };
+
+    let mut long_exposure = Decimal::ZERO;
+    let mut short_exposure = Decimal::ZERO;
+    let mut collateral_requirement = Decimal::ZERO;
+    let mut maintenance_collateral = Decimal::ZERO;
+    let positions: Vec<RiskPosition> = positions
+        .into_iter()
+        .map(|position| {
+            let current_price = prices
+                .get(&position.option_id)
+                .copied()
+                .unwrap_or(position.average_price);
+            let market_value = current_price * Decimal::from(position.quantity);
+            if position.quantity > 0 {
+                long_exposure += market_value;
+            } else {
+                let shares = -position.quantity;
+                short_exposure -= market_value;
+                collateral_requirement += margin::collateral(position.average_price, shares);
+                maintenance_collateral += margin::collateral(current_price, shares);
+            }
+            RiskPosition {
+                event_id: position.event_id,
+                option_id: position.option_id,
+                quantity: position.quantity,
+                average_price: position.average_price,
+                current_price,
+                market_value,
+                shares_to_limit: (position.quantity + short_limit).max(0),
+            }
+        })
+        .collect();
+
+    let available_balance = balances::available_in(wallet);
+    let margin_balance = wallet.map(|w| w.margin_balance).unwrap_or(Decimal::ZERO);
+    let equity = available_balance + margin_balance - short_exposure;
+    let maintenance_requirement = maintenance_collateral * margin::maintenance_rate();
+    let collateral_usage_percent = if maintenance_requirement.is_zero() {
+        Some(Decimal::ZERO)
+    } else if equity > Decimal::ZERO {
+        Some((maintenance_requirement / equity * Decimal::ONE_HUNDRED).round_dp(2))
+    } else {
+        None
+    };
+
+    RiskSummary {
+        user_id: user.id,
+        currency,
+        margin_enabled: user.margin_enabled,
+        wallet_balance: wallet.map(|w| w.balance).unwrap_or(Decimal::ZERO),
+        reserved_balance: wallet.map(|w| w.reserved_balance).unwrap_or(Decimal::ZERO),
+        available_balance,
+        margin_balance,
+        long_exposure,
+        short_exposure,
+        collateral_requirement,
+        maintenance_requirement,
+        equity,
+        collateral_usage_percent,
+        distance_to_maintenance: equity - maintenance_requirement,
+        max_short_position,
+        positions,
+        computed_at: Utc::now(),
+    }
+}
+
+/// Push fresh risk summaries to the `risk` channel of everyone who traded
+pub fn publish_for_trades(ws_server: &Addr<WebSocketServer>, trades: &[Trade]) {
+    let user_ids: BTreeSet<i32> = trades
+        .iter()
+        .flat_map(|t| [t.buyer_id, t.seller_id])
+        .collect();
+    for user_id in user_ids {
+        ws_server.do_send(BroadcastRiskUpdate { user_id });
+    }
+}
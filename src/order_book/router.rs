@@ -0,0 +1,347 @@
+use super::{
+    amm, clock, complement,
+    db_persistence::DbPersistence,
+    external_venue, margin, matching_service,
+    position_tracker::PositionTracker,
+    price_updater::Pricer,
+    reservations,
+    types::{Order, OrderSide, TimeInForce, Trade, TradingHalt},
+    OrderType,
+};
+use crate::wallets::balances;
+use deadpool_redis::Pool;
+use entity::{events, users};
+use sea_orm::{prelude::Decimal, DatabaseConnection, EntityTrait, QuerySelect};
+use serde::{Deserialize, Serialize};
+
+/// Where an event's orders are executed
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionVenue {
+    /// The resident order book in `matching_service`
+    #[default]
+    Internal,
+    /// The automated market maker, which quotes from its own inventory
+    Amm,
+    /// Another exchange, reached through a gateway draining the venue outbox
+    External,
+}
+
+impl ExecutionVenue {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionVenue::Internal => "internal",
+            ExecutionVenue::Amm => "amm",
+            ExecutionVenue::External => "external",
+        }
+    }
+
+    /// Parse a stored venue, falling back to the internal book
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "amm" => ExecutionVenue::Amm,
+            "external" => ExecutionVenue::External,
+            _ => ExecutionVenue::Internal,
+        }
+    }
+}
+
+/// What submitting an order produced, whichever venue executed it
+pub struct Execution {
+    /// Venue the order was sent to
+    pub venue: ExecutionVenue,
+    /// Trades the order took part in, or why the venue rejected it
+    pub result: Result<Vec<Trade>, String>,
+    /// The order as the venue left it: resting or waiting at the venue while
+    /// still open, otherwise filled, cancelled or rejected
+    pub order: Option<Order>,
+    /// Orders cancelled or decremented by self-trade prevention
+    pub self_trade_prevented: Vec<Order>,
+    /// Halt tripped by the circuit breaker while matching
+    pub triggered_halt: Option<TradingHalt>,
+    /// Order book sequence after the order, 0 outside the internal book
+    pub sequence: u64,
+}
+
+impl Execution {
+    fn outside_book(
+        venue: ExecutionVenue,
+        order: Order,
+        result: Result<Vec<Trade>, String>,
+    ) -> Self {
+        Self {
+            venue,
+            result,
+            order: Some(order),
+            self_trade_prevented: Vec::new(),
+            triggered_halt: None,
+            sequence: 0,
+        }
+    }
+}
+
+/// Venue configured for an event
+pub async fn venue_for_event(
+    db: &DatabaseConnection,
+    event_id: i32,
+) -> Result<ExecutionVenue, String> {
+    let venue: Option<String> = events::Entity::find_by_id(event_id)
+        .select_only()
+        .column(events::Column::ExecutionVenue)
+        .into_tuple()
+        .one(db)
+        .await
+        .map_err(|e| format!("Failed to load execution venue: {}", e))?;
+
+    Ok(venue
+        .map(|venue| ExecutionVenue::parse(&venue))
+        .unwrap_or_default())
+}
+
+/// Execute a saved order on its event's venue. Fails only when the venue
+/// can't be reached; a rejection is reported in `Execution::result`.
+pub async fn submit(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    order: Order,
+) -> Result<Execution, String> {
+    match venue_for_event(db, order.event_id).await? {
+        ExecutionVenue::Internal => {
+            if let Some(complement_id) = complement::complement_for(db, &order).await? {
+                return complement::submit(db, redis_pool, order, complement_id).await;
+            }
+            matching_service::execute(redis_pool, order.event_id, order.option_id, move |book| {
+                let result = book.submit_order(order);
+                Execution {
+                    venue: ExecutionVenue::Internal,
+                    result,
+                    order: book.take_submitted(),
+                    self_trade_prevented: book.take_self_trade_prevented(),
+                    triggered_halt: book.take_triggered_halt(),
+                    sequence: book.sequence(),
+                }
+            })
+            .await
+        }
+        ExecutionVenue::Amm => {
+            let result = amm::execute(db, order.clone()).await?;
+            // The AMM fills an order in full or rejects it
+            let mut order = order;
+            match &result {
+                Ok(_) => order.fill(order.remaining_quantity()),
+                Err(_) => order.reject(),
+            }
+            Ok(Execution::outside_book(ExecutionVenue::Amm, order, result))
+        }
+        ExecutionVenue::External => {
+            let result = external_venue::forward(redis_pool, order.clone()).await?;
+            Ok(Execution::outside_book(
+                ExecutionVenue::External,
+                order,
+                result,
+            ))
+        }
+    }
+}
+
+/// What an order would do on its venue, worked out without placing it
+pub struct Preview {
+    pub venue: ExecutionVenue,
+    /// Price and quantity of each fill, or why the order would be rejected
+    pub result: Result<Vec<(Decimal, i32)>, String>,
+    /// Quantity left resting on the book afterwards
+    pub resting_quantity: i32,
+}
+
+/// Preview an order on its event's venue: matched against a copy of the
+/// order book, or priced by the AMM. Complement fills of binary events aren't
+/// included, and orders for the external venue can't be previewed.
+pub async fn preview(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    order: Order,
+) -> Result<Option<Preview>, String> {
+    match venue_for_event(db, order.event_id).await? {
+        ExecutionVenue::Internal => {
+            matching_service::query(redis_pool, order.event_id, order.option_id, move |book| {
+                let mut book = book.clone();
+                let order_id = order.id.clone();
+                let result = book.submit_order(order).map(|trades| {
+                    trades
+                        .iter()
+                        .map(|trade| (trade.price, trade.quantity))
+                        .collect()
+                });
+                let (_, _, orders, _) = book.get_internal_state();
+                let resting_quantity = orders
+                    .get(&order_id)
+                    .map(|order| order.quantity - order.filled_quantity)
+                    .unwrap_or(0);
+                Some(Preview {
+                    venue: ExecutionVenue::Internal,
+                    result,
+                    resting_quantity,
+                })
+            })
+            .await
+        }
+        ExecutionVenue::Amm => Ok(Some(Preview {
+            venue: ExecutionVenue::Amm,
+            result: amm::quote_order(db, &order)
+                .await?
+                .map(|price| vec![(price, order.quantity)]),
+            resting_quantity: 0,
+        })),
+        ExecutionVenue::External => Ok(None),
+    }
+}
+
+/// Cancel an open order on its event's venue, returning it as cancelled
+pub async fn cancel(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    order: Order,
+) -> Result<Result<Order, String>, String> {
+    match venue_for_event(db, order.event_id).await? {
+        ExecutionVenue::Internal => {
+            let order_id = order.id;
+            matching_service::execute(redis_pool, order.event_id, order.option_id, move |book| {
+                book.cancel_order(&order_id)
+            })
+            .await
+        }
+        // AMM orders fill or are rejected immediately, so there is never one open
+        ExecutionVenue::Amm => Ok(Err("Order is not open".to_string())),
+        ExecutionVenue::External => external_venue::cancel(redis_pool, order).await,
+    }
+}
+
+/// Price the venue would trade an option at next, if it has one. The order
+/// book is priced by `pricer`; the AMM always quotes its marginal price.
+pub async fn predicted_price(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    event_id: i32,
+    option_id: i32,
+    pricer: Box<dyn Pricer>,
+) -> Result<Option<Decimal>, String> {
+    match venue_for_event(db, event_id).await? {
+        ExecutionVenue::Internal => {
+            matching_service::query(redis_pool, event_id, option_id, move |book| {
+                pricer.price(book)
+            })
+            .await
+        }
+        ExecutionVenue::Amm => amm::marginal_price(db, event_id, option_id).await,
+        ExecutionVenue::External => Ok(None),
+    }
+}
+
+/// Fill `quantity` of `order` at `price` against a house account standing in
+/// for a venue outside the order book. The house side is saved as an IOC
+/// order, so both sides of the trade have an order to record the fill on.
+/// Fails with `reservations::INSUFFICIENT_BALANCE` when the house account
+/// can't pay for its side or cover the shares it sells.
+pub async fn house_trade(
+    db: &DatabaseConnection,
+    order: &Order,
+    house_user_id: i32,
+    price: Decimal,
+    quantity: i32,
+) -> Result<Trade, String> {
+    let house_side = match order.side {
+        OrderSide::Buy => OrderSide::Sell,
+        OrderSide::Sell => OrderSide::Buy,
+    };
+    if house_side == OrderSide::Sell {
+        let house_user = users::Entity::find_by_id(house_user_id)
+            .one(db)
+            .await
+            .map_err(|e| format!("Failed to load house account: {}", e))?
+            .ok_or_else(|| format!("House account {} not found", house_user_id))?;
+        let held = PositionTracker::new(db.clone())
+            .get_user_position(house_user_id, order.event_id, order.option_id)
+            .await?
+            .quantity;
+        let available = balances::available_in(
+            balances::find(db, house_user_id, &order.currency)
+                .await
+                .map_err(|e| format!("Failed to load house account wallet: {}", e))?
+                .as_ref(),
+        );
+        if margin::validate_sell(&house_user, available, held, 0, quantity, price).is_err() {
+            return Err(reservations::INSUFFICIENT_BALANCE.to_string());
+        }
+    }
+
+    house_fill(db, order, house_user_id, price, quantity).await
+}
+
+/// Fill `quantity` of `order` at `price` against a house account like
+/// `house_trade`, for callers that already checked the house can afford it
+pub async fn house_fill(
+    db: &DatabaseConnection,
+    order: &Order,
+    house_user_id: i32,
+    price: Decimal,
+    quantity: i32,
+) -> Result<Trade, String> {
+    let house_order = house_order(order, house_user_id, price, quantity);
+    DbPersistence::new(db.clone())
+        .save_order(&house_order)
+        .await?;
+    Ok(trade_with_house(order, &house_order, price, quantity))
+}
+
+/// The IOC order a house account places for its side of `quantity` of
+/// `order` at `price`
+pub fn house_order(order: &Order, house_user_id: i32, price: Decimal, quantity: i32) -> Order {
+    let house_side = match order.side {
+        OrderSide::Buy => OrderSide::Sell,
+        OrderSide::Sell => OrderSide::Buy,
+    };
+    let mut house_order = Order::new(
+        house_user_id,
+        order.event_id,
+        order.option_id,
+        house_side,
+        OrderType::Limit,
+        TimeInForce::IOC,
+        price,
+        quantity,
+        order.currency.clone(),
+    );
+    // The house side exists only because of `order`, so it shares its trace
+    house_order.correlation_id = order.correlation_id.clone();
+    house_order
+}
+
+/// Trade of `quantity` of `order` at `price` against `house_order`, already saved
+pub fn trade_with_house(
+    order: &Order,
+    house_order: &Order,
+    price: Decimal,
+    quantity: i32,
+) -> Trade {
+    let (buy_order, sell_order) = match order.side {
+        OrderSide::Buy => (order, house_order),
+        OrderSide::Sell => (house_order, order),
+    };
+    Trade {
+        id: uuid::Uuid::new_v4().to_string(),
+        event_id: order.event_id,
+        option_id: order.option_id,
+        buyer_id: buy_order.user_id,
+        seller_id: sell_order.user_id,
+        buy_order_id: buy_order.id.clone(),
+        sell_order_id: sell_order.id.clone(),
+        price,
+        quantity,
+        total_amount: price * Decimal::from(quantity),
+        currency: order.currency.clone(),
+        timestamp: clock::trade_timestamp(order.event_id, order.option_id),
+        sequence: 0,
+        buy_correlation_id: buy_order.correlation_id.clone(),
+        sell_correlation_id: sell_order.correlation_id.clone(),
+    }
+}
@@ -0,0 +1,267 @@
+use super::db_persistence::{self, trade_from_model};
+use super::fees::TradeFees;
+use super::margin;
+use super::position_cache;
+use super::position_tracker::PositionTracker;
+use super::types::Trade;
+use crate::constants::config;
+use crate::types::order_book::settlement_status;
+use crate::types::websocket::{OpsEventKind, OpsSeverity};
+use crate::wallets::balances;
+use crate::websocket::ops;
+use actix_web::web;
+use chrono::{Duration, Utc};
+use entity::trades;
+use sea_orm::{
+    prelude::{Decimal, Expr},
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use serde_json::json;
+
+/// Most unsettled trades retried in one run of the retry job
+const RETRY_BATCH_SIZE: u64 = 100;
+
+/// Move positions and balances for a trade saved by `DbPersistence::save_trade`
+/// and mark it settled. Everything commits together with the status, so a
+/// failure, such as a short whose collateral can't be locked, leaves no
+/// position behind and the trade may be settled again.
+pub async fn settle_trade(
+    db: &DatabaseConnection,
+    position_tracker: &PositionTracker,
+    trade: &Trade,
+    fees: &TradeFees,
+) -> Result<(), String> {
+    let txn = db
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let db_trade = trades::Entity::find_by_id(trade.id.clone())
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(|e| format!("Failed to load trade: {}", e))?
+        .ok_or_else(|| format!("Trade {} not found", trade.id))?;
+    if db_trade.settlement_status == settlement_status::SETTLED {
+        return Ok(());
+    }
+
+    let (buyer_before, seller_before) = position_tracker
+        .update_positions_from_trade(&txn, &db_trade, trade)
+        .await?;
+
+    // Update wallet balances in the trade's currency
+    let buyer_wallet = balances::lock(&txn, trade.buyer_id, &trade.currency)
+        .await
+        .map_err(|e| format!("Failed to find buyer wallet: {}", e))?;
+    let new_buyer_balance = buyer_wallet.balance - trade.total_amount - fees.buyer_fee;
+    if new_buyer_balance < Decimal::ZERO {
+        return Err("Insufficient buyer balance".to_string());
+    }
+    balances::set_balance(&txn, buyer_wallet, new_buyer_balance)
+        .await
+        .map_err(|e| format!("Failed to update buyer balance: {}", e))?;
+
+    let seller_wallet = balances::lock(&txn, trade.seller_id, &trade.currency)
+        .await
+        .map_err(|e| format!("Failed to find seller wallet: {}", e))?;
+    let new_seller_balance = seller_wallet.balance + trade.total_amount - fees.seller_fee;
+    balances::set_balance(&txn, seller_wallet, new_seller_balance)
+        .await
+        .map_err(|e| format!("Failed to update seller balance: {}", e))?;
+
+    // Record the fill on both orders, releasing what they held for the
+    // filled shares, including the seller's short collateral, so it can be
+    // locked below
+    let mut fills = Vec::with_capacity(2);
+    for order_id in [&trade.buy_order_id, &trade.sell_order_id] {
+        fills.push(db_persistence::apply_fill(&txn, order_id, trade.quantity, &trade.id).await?);
+    }
+
+    // Shares sold beyond the seller's position are backed by collateral
+    // taken from their wallet; shares bought back release theirs
+    let shorted = margin::shares_shorted(seller_before, trade.quantity);
+    if shorted > 0 {
+        let collateral = margin::collateral(trade.price, shorted);
+        let key = (trade.seller_id, trade.event_id, trade.option_id);
+        margin::lock(
+            &txn,
+            key,
+            &trade.currency,
+            shorted,
+            collateral,
+            &trade.id,
+            trade.sell_correlation_id.as_deref(),
+        )
+        .await
+        .map_err(|e| format!("Failed to lock short collateral: {}", e))?;
+    }
+    let covered = margin::shares_covered(buyer_before, trade.quantity);
+    if covered > 0 {
+        let key = (trade.buyer_id, trade.event_id, trade.option_id);
+        margin::release(
+            &txn,
+            key,
+            &trade.currency,
+            covered,
+            &trade.id,
+            trade.buy_correlation_id.as_deref(),
+        )
+        .await
+        .map_err(|e| format!("Failed to release short collateral: {}", e))?;
+    }
+
+    let mut active_trade: trades::ActiveModel = db_trade.into();
+    active_trade.settlement_status = Set(settlement_status::SETTLED.to_string());
+    active_trade.settlement_error = Set(None);
+    active_trade.settled_at = Set(Some(Utc::now().into()));
+    active_trade
+        .update(&txn)
+        .await
+        .map_err(|e| format!("Failed to mark trade settled: {}", e))?;
+
+    let committed = txn
+        .commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e));
+    position_cache::invalidate(trade.buyer_id, trade.event_id, trade.option_id);
+    position_cache::invalidate(trade.seller_id, trade.event_id, trade.option_id);
+    committed?;
+
+    for (order, kind) in fills {
+        db_persistence::publish_fill(order, kind, &trade.id);
+    }
+    Ok(())
+}
+
+/// `settle_trade`, marking the trade failed for the retry job if it can't be
+/// settled
+pub async fn settle_or_record_failure(
+    db: &DatabaseConnection,
+    position_tracker: &PositionTracker,
+    trade: &Trade,
+    fees: &TradeFees,
+) -> Result<(), String> {
+    let result = settle_trade(db, position_tracker, trade, fees).await;
+    if let Err(error) = &result {
+        log::error!(
+            "Failed to settle trade {} (buy correlation {}, sell correlation {}): {}",
+            trade.id,
+            trade.buy_correlation_id.as_deref().unwrap_or("-"),
+            trade.sell_correlation_id.as_deref().unwrap_or("-"),
+            error
+        );
+        if let Err(e) = mark_failed(db, &trade.id, error).await {
+            log::error!("{}", e);
+        }
+    }
+    result
+}
+
+/// Count a failed settlement attempt against a trade
+async fn mark_failed(db: &DatabaseConnection, trade_id: &str, error: &str) -> Result<(), String> {
+    trades::Entity::update_many()
+        .col_expr(
+            trades::Column::SettlementStatus,
+            Expr::value(settlement_status::FAILED),
+        )
+        .col_expr(
+            trades::Column::SettlementAttempts,
+            Expr::col(trades::Column::SettlementAttempts).add(1),
+        )
+        .col_expr(trades::Column::SettlementError, Expr::value(error))
+        .filter(trades::Column::Id.eq(trade_id))
+        .filter(trades::Column::SettlementStatus.ne(settlement_status::SETTLED))
+        .exec(db)
+        .await
+        .map_err(|e| format!("Failed to mark trade {} failed: {}", trade_id, e))?;
+    Ok(())
+}
+
+/// Fees a saved trade was charged
+pub fn recorded_fees(trade: &trades::Model) -> TradeFees {
+    TradeFees {
+        buyer_fee: trade.buyer_fee,
+        seller_fee: trade.seller_fee,
+    }
+}
+
+/// Retry trades whose settlement failed, or that have been pending longer
+/// than the grace period, oldest first
+async fn retry_unsettled_trades(db: &DatabaseConnection) -> Result<(), String> {
+    let max_attempts = config::get_settlement_retry_max_attempts();
+    let stale_before = Utc::now() - Duration::seconds(config::get_settlement_retry_grace_seconds());
+
+    let due = trades::Entity::find()
+        .filter(trades::Column::SettlementAttempts.lt(max_attempts))
+        .filter(
+            Condition::any()
+                .add(trades::Column::SettlementStatus.eq(settlement_status::FAILED))
+                .add(
+                    Condition::all()
+                        .add(trades::Column::SettlementStatus.eq(settlement_status::PENDING))
+                        .add(trades::Column::Timestamp.lt(stale_before)),
+                ),
+        )
+        .order_by_asc(trades::Column::Timestamp)
+        .order_by_asc(trades::Column::Sequence)
+        .limit(RETRY_BATCH_SIZE)
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load unsettled trades: {}", e))?;
+
+    let position_tracker = PositionTracker::new(db.clone());
+    for db_trade in due {
+        let attempts = db_trade.settlement_attempts + 1;
+        let fees = recorded_fees(&db_trade);
+        let trade = trade_from_model(db_trade);
+
+        match settle_or_record_failure(db, &position_tracker, &trade, &fees).await {
+            Ok(()) => log::info!("Settled trade {} on attempt {}", trade.id, attempts),
+            Err(e) if attempts >= max_attempts => {
+                ops::publish(
+                    OpsEventKind::TradeSettlementFailed,
+                    OpsSeverity::Critical,
+                    format!(
+                        "Trade {} is still unsettled after {} attempts",
+                        trade.id, attempts
+                    ),
+                    json!({ "trade_id": trade.id, "attempts": attempts, "error": e }),
+                );
+            }
+            Err(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Start a background task that retries trades left unsettled
+pub fn start_settlement_retry_job(db: web::Data<DatabaseConnection>) {
+    let interval_seconds = config::get_settlement_retry_interval_seconds();
+
+    log::info!(
+        "Starting settlement retry job with {}-second interval",
+        interval_seconds
+    );
+
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(interval_seconds));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = retry_unsettled_trades(&db).await {
+                log::error!("Failed to retry trade settlements: {}", e);
+                ops::publish(
+                    OpsEventKind::JobFailed,
+                    OpsSeverity::Critical,
+                    "Settlement retry job could not load unsettled trades",
+                    json!({ "job": "settlement_retry", "error": e }),
+                );
+            }
+        }
+    });
+}
@@ -0,0 +1,656 @@
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::prelude::Decimal;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+
+/// Longest rules script an event can have
+pub const MAX_SCRIPT_LENGTH: usize = 10_000;
+
+/// Deepest an expression can nest, counting each operator applied to the
+/// result of another, so parsing and evaluation can't exhaust the stack
+const MAX_DEPTH: usize = 64;
+
+/// Longest name of an input or variable
+const MAX_NAME_LENGTH: usize = 64;
+
+const KEYWORDS: [&str; 10] = [
+    "let", "payout", "if", "then", "else", "and", "or", "not", "true", "false",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(Decimal),
+    Name(String),
+    Keyword(&'static str),
+    Symbol(&'static str),
+}
+
+/// Split a script into tokens with the line each starts on
+fn tokenize(script: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = script.chars().peekable();
+    let mut line = 1;
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            '\n' => {
+                line += 1;
+                chars.next();
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '#' => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '0'..='9' | '.' => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                    number.push(c);
+                    chars.next();
+                }
+                let value = Decimal::from_str(&number)
+                    .map_err(|_| format!("line {}: {} is not a number", line, number))?;
+                tokens.push((Token::Number(value), line));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&c) = chars
+                    .peek()
+                    .filter(|c| c.is_ascii_alphanumeric() || **c == '_')
+                {
+                    name.push(c);
+                    chars.next();
+                }
+                if name.len() > MAX_NAME_LENGTH {
+                    return Err(format!(
+                        "line {}: names can be at most {} characters",
+                        line, MAX_NAME_LENGTH
+                    ));
+                }
+                let token = match KEYWORDS.iter().find(|k| **k == name) {
+                    Some(keyword) => Token::Keyword(keyword),
+                    None => Token::Name(name),
+                };
+                tokens.push((token, line));
+            }
+            _ => {
+                chars.next();
+                let next = chars.peek().copied();
+                let symbol = match (c, next) {
+                    ('=', Some('=')) => "==",
+                    ('!', Some('=')) => "!=",
+                    ('<', Some('=')) => "<=",
+                    ('>', Some('=')) => ">=",
+                    _ => "",
+                };
+                if !symbol.is_empty() {
+                    chars.next();
+                    tokens.push((Token::Symbol(symbol), line));
+                    continue;
+                }
+                let symbol = match c {
+                    '=' => "=",
+                    '<' => "<",
+                    '>' => ">",
+                    '+' => "+",
+                    '-' => "-",
+                    '*' => "*",
+                    '/' => "/",
+                    '(' => "(",
+                    ')' => ")",
+                    ',' => ",",
+                    ';' => ";",
+                    _ => return Err(format!("line {}: unexpected character '{}'", line, c)),
+                };
+                tokens.push((Token::Symbol(symbol), line));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Function {
+    Min,
+    Max,
+    Abs,
+    Clamp,
+    Round,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(Decimal),
+    Bool(bool),
+    Name(String),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Debug, Clone)]
+enum Statement {
+    Let(String, Expr),
+    Payout(i32, Expr),
+}
+
+/// Recursive descent over the tokens of a script
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or(self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.line(), message)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|(token, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> Result<(), String> {
+        if self.eat(&expected) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", what)))
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, String> {
+        match self.next() {
+            Some(Token::Keyword("let")) => {
+                let Some(Token::Name(name)) = self.next() else {
+                    self.position -= 1;
+                    return Err(self.error("expected a name after let"));
+                };
+                self.expect(Token::Symbol("="), "=")?;
+                Ok(Statement::Let(name, self.expr()?))
+            }
+            Some(Token::Keyword("payout")) => {
+                let option_id = match self.next() {
+                    Some(Token::Number(number)) if number.fract().is_zero() => number.to_i32(),
+                    _ => None,
+                };
+                let Some(option_id) = option_id else {
+                    self.position -= 1;
+                    return Err(self.error("expected an option id after payout"));
+                };
+                self.expect(Token::Symbol("="), "=")?;
+                Ok(Statement::Payout(option_id, self.expr()?))
+            }
+            _ => {
+                self.position -= 1;
+                Err(self.error("expected let or payout"))
+            }
+        }
+    }
+
+    /// Go one level deeper into an expression
+    fn descend(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("expression is too long or nested too deeply"));
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        self.descend()?;
+        let expr = if self.eat(&Token::Keyword("if")) {
+            let condition = self.expr()?;
+            self.expect(Token::Keyword("then"), "then")?;
+            let then = self.expr()?;
+            self.expect(Token::Keyword("else"), "else")?;
+            let otherwise = self.expr()?;
+            Expr::If(Box::new(condition), Box::new(then), Box::new(otherwise))
+        } else {
+            self.or()?
+        };
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let start = self.depth;
+        let mut left = self.and()?;
+        while self.eat(&Token::Keyword("or")) {
+            self.descend()?;
+            left = Expr::Binary(BinaryOp::Or, Box::new(left), Box::new(self.and()?));
+        }
+        self.depth = start;
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let start = self.depth;
+        let mut left = self.not()?;
+        while self.eat(&Token::Keyword("and")) {
+            self.descend()?;
+            left = Expr::Binary(BinaryOp::And, Box::new(left), Box::new(self.not()?));
+        }
+        self.depth = start;
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat(&Token::Keyword("not")) {
+            return Ok(Expr::Not(Box::new(self.nested(Self::not)?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.sum()?;
+        let op = match self.peek() {
+            Some(Token::Symbol("==")) => BinaryOp::Equal,
+            Some(Token::Symbol("!=")) => BinaryOp::NotEqual,
+            Some(Token::Symbol("<")) => BinaryOp::Less,
+            Some(Token::Symbol("<=")) => BinaryOp::LessOrEqual,
+            Some(Token::Symbol(">")) => BinaryOp::Greater,
+            Some(Token::Symbol(">=")) => BinaryOp::GreaterOrEqual,
+            _ => return Ok(left),
+        };
+        self.position += 1;
+        Ok(Expr::Binary(op, Box::new(left), Box::new(self.sum()?)))
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let start = self.depth;
+        let mut left = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("+")) => BinaryOp::Add,
+                Some(Token::Symbol("-")) => BinaryOp::Subtract,
+                _ => {
+                    self.depth = start;
+                    return Ok(left);
+                }
+            };
+            self.position += 1;
+            self.descend()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let start = self.depth;
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("*")) => BinaryOp::Multiply,
+                Some(Token::Symbol("/")) => BinaryOp::Divide,
+                _ => {
+                    self.depth = start;
+                    return Ok(left);
+                }
+            };
+            self.position += 1;
+            self.descend()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat(&Token::Symbol("-")) {
+            return Ok(Expr::Negate(Box::new(self.nested(Self::unary)?)));
+        }
+        self.atom()
+    }
+
+    /// Parse with `parse` one level deeper
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Expr, String>) -> Result<Expr, String> {
+        self.descend()?;
+        let expr = parse(self)?;
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Keyword("true")) => Ok(Expr::Bool(true)),
+            Some(Token::Keyword("false")) => Ok(Expr::Bool(false)),
+            Some(Token::Symbol("(")) => {
+                let expr = self.expr()?;
+                self.expect(Token::Symbol(")"), ")")?;
+                Ok(expr)
+            }
+            Some(Token::Name(name)) if self.eat(&Token::Symbol("(")) => {
+                let function = match name.as_str() {
+                    "min" => Function::Min,
+                    "max" => Function::Max,
+                    "abs" => Function::Abs,
+                    "clamp" => Function::Clamp,
+                    "round" => Function::Round,
+                    _ => {
+                        self.position -= 2;
+                        return Err(self.error(&format!("unknown function {}", name)));
+                    }
+                };
+                let mut args = Vec::new();
+                if !self.eat(&Token::Symbol(")")) {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(&Token::Symbol(")")) {
+                            break;
+                        }
+                        self.expect(Token::Symbol(","), ", or )")?;
+                    }
+                }
+                let arity_ok = match function {
+                    Function::Min | Function::Max => !args.is_empty(),
+                    Function::Abs => args.len() == 1,
+                    Function::Clamp => args.len() == 3,
+                    Function::Round => args.len() == 1 || args.len() == 2,
+                };
+                if !arity_ok {
+                    return Err(self.error(&format!("wrong number of arguments to {}", name)));
+                }
+                Ok(Expr::Call(function, args))
+            }
+            Some(Token::Name(name)) => Ok(Expr::Name(name)),
+            _ => {
+                self.position -= 1;
+                Err(self.error("expected a value"))
+            }
+        }
+    }
+}
+
+/// Names an expression reads
+fn collect_names<'a>(expr: &'a Expr, names: &mut Vec<&'a str>) {
+    match expr {
+        Expr::Number(_) | Expr::Bool(_) => {}
+        Expr::Name(name) => names.push(name),
+        Expr::Negate(inner) | Expr::Not(inner) => collect_names(inner, names),
+        Expr::Binary(_, left, right) => {
+            collect_names(left, names);
+            collect_names(right, names);
+        }
+        Expr::If(condition, then, otherwise) => {
+            collect_names(condition, names);
+            collect_names(then, names);
+            collect_names(otherwise, names);
+        }
+        Expr::Call(_, args) => args.iter().for_each(|arg| collect_names(arg, names)),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Value {
+    Number(Decimal),
+    Bool(bool),
+}
+
+impl Value {
+    fn number(self) -> Result<Decimal, String> {
+        match self {
+            Value::Number(value) => Ok(value),
+            Value::Bool(_) => Err("expected a number, found true or false".to_string()),
+        }
+    }
+
+    fn bool(self) -> Result<bool, String> {
+        match self {
+            Value::Bool(value) => Ok(value),
+            Value::Number(_) => Err("expected true or false, found a number".to_string()),
+        }
+    }
+}
+
+fn overflow() -> String {
+    "arithmetic overflow".to_string()
+}
+
+fn evaluate(expr: &Expr, names: &HashMap<&str, Value>) -> Result<Value, String> {
+    let number = |expr: &Expr| evaluate(expr, names)?.number();
+    Ok(match expr {
+        Expr::Number(value) => Value::Number(*value),
+        Expr::Bool(value) => Value::Bool(*value),
+        Expr::Name(name) => *names
+            .get(name.as_str())
+            .ok_or_else(|| format!("{} has no value", name))?,
+        Expr::Negate(inner) => Value::Number(-number(inner)?),
+        Expr::Not(inner) => Value::Bool(!evaluate(inner, names)?.bool()?),
+        Expr::Binary(BinaryOp::And, left, right) => {
+            Value::Bool(evaluate(left, names)?.bool()? && evaluate(right, names)?.bool()?)
+        }
+        Expr::Binary(BinaryOp::Or, left, right) => {
+            Value::Bool(evaluate(left, names)?.bool()? || evaluate(right, names)?.bool()?)
+        }
+        Expr::Binary(op, left, right) => {
+            let (left, right) = (number(left)?, number(right)?);
+            match op {
+                BinaryOp::Add => Value::Number(left.checked_add(right).ok_or_else(overflow)?),
+                BinaryOp::Subtract => Value::Number(left.checked_sub(right).ok_or_else(overflow)?),
+                BinaryOp::Multiply => Value::Number(left.checked_mul(right).ok_or_else(overflow)?),
+                BinaryOp::Divide => {
+                    if right.is_zero() {
+                        return Err("division by zero".to_string());
+                    }
+                    Value::Number(left.checked_div(right).ok_or_else(overflow)?)
+                }
+                BinaryOp::Equal => Value::Bool(left == right),
+                BinaryOp::NotEqual => Value::Bool(left != right),
+                BinaryOp::Less => Value::Bool(left < right),
+                BinaryOp::LessOrEqual => Value::Bool(left <= right),
+                BinaryOp::Greater => Value::Bool(left > right),
+                BinaryOp::GreaterOrEqual => Value::Bool(left >= right),
+                BinaryOp::And | BinaryOp::Or => unreachable!(),
+            }
+        }
+        Expr::If(condition, then, otherwise) => {
+            if evaluate(condition, names)?.bool()? {
+                evaluate(then, names)?
+            } else {
+                evaluate(otherwise, names)?
+            }
+        }
+        Expr::Call(function, args) => {
+            let args = args.iter().map(number).collect::<Result<Vec<_>, _>>()?;
+            Value::Number(match function {
+                Function::Min => args.into_iter().min().unwrap_or_default(),
+                Function::Max => args.into_iter().max().unwrap_or_default(),
+                Function::Abs => args[0].abs(),
+                Function::Clamp => {
+                    if args[1] > args[2] {
+                        return Err("clamp needs its lower bound first".to_string());
+                    }
+                    args[0].clamp(args[1], args[2])
+                }
+                Function::Round => {
+                    let places = match args.get(1) {
+                        Some(places) if places.fract().is_zero() => places.to_u32(),
+                        Some(_) => None,
+                        None => Some(2),
+                    };
+                    let Some(places) = places.filter(|places| *places <= 8) else {
+                        return Err("round takes 0 to 8 decimal places".to_string());
+                    };
+                    args[0].round_dp(places)
+                }
+            })
+        }
+    })
+}
+
+/// An event's settlement rules: a script that works out what each option
+/// pays from the numbers its result is resolved to. Scripts have no loops or
+/// access to anything but their inputs, so evaluating one is bounded by its
+/// length.
+#[derive(Debug, Clone)]
+pub struct SettlementRules {
+    statements: Vec<Statement>,
+    inputs: Vec<String>,
+}
+
+impl SettlementRules {
+    /// Parse a script, working out the inputs it reads: every name it uses
+    /// before a `let` gives it a value
+    pub fn parse(script: &str) -> Result<Self, String> {
+        if script.len() > MAX_SCRIPT_LENGTH {
+            return Err(format!(
+                "Rules can be at most {} characters",
+                MAX_SCRIPT_LENGTH
+            ));
+        }
+        let mut parser = Parser {
+            tokens: tokenize(script)?,
+            position: 0,
+            depth: 0,
+        };
+
+        let mut statements = Vec::new();
+        let mut defined: HashSet<String> = HashSet::new();
+        let mut inputs: Vec<String> = Vec::new();
+        let mut paid: HashSet<i32> = HashSet::new();
+        while parser.peek().is_some() {
+            if parser.eat(&Token::Symbol(";")) {
+                continue;
+            }
+            let line = parser.line();
+            let statement = parser.statement()?;
+            let expr = match &statement {
+                Statement::Let(_, expr) | Statement::Payout(_, expr) => expr,
+            };
+            let mut names = Vec::new();
+            collect_names(expr, &mut names);
+            for name in names {
+                if !defined.contains(name) && !inputs.iter().any(|input| input == name) {
+                    inputs.push(name.to_string());
+                }
+            }
+            match &statement {
+                Statement::Let(name, _) => {
+                    if defined.contains(name) || inputs.contains(name) {
+                        return Err(format!("line {}: {} is already defined", line, name));
+                    }
+                    defined.insert(name.clone());
+                }
+                Statement::Payout(option_id, _) => {
+                    if !paid.insert(*option_id) {
+                        return Err(format!(
+                            "line {}: option {} already has a payout",
+                            line, option_id
+                        ));
+                    }
+                }
+            }
+            statements.push(statement);
+        }
+        if paid.is_empty() {
+            return Err("Rules must set at least one payout".to_string());
+        }
+
+        inputs.sort();
+        Ok(Self { statements, inputs })
+    }
+
+    /// Names of the numbers the rules need to settle
+    pub fn inputs(&self) -> &[String] {
+        &self.inputs
+    }
+
+    /// Fails unless the rules set a payout for every one of an event's
+    /// options and no other
+    pub fn check_options(&self, option_ids: &[i32]) -> Result<(), String> {
+        let paid: Vec<i32> = self
+            .statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Payout(option_id, _) => Some(*option_id),
+                Statement::Let(..) => None,
+            })
+            .collect();
+        if let Some(other) = paid.iter().find(|id| !option_ids.contains(id)) {
+            return Err(format!("Option {} is not part of this event", other));
+        }
+        if let Some(missing) = option_ids.iter().find(|id| !paid.contains(id)) {
+            return Err(format!("The rules set no payout for option {}", missing));
+        }
+        Ok(())
+    }
+
+    /// What each option pays given the inputs, which must be exactly the
+    /// ones the rules read
+    pub fn evaluate(
+        &self,
+        inputs: &HashMap<String, Decimal>,
+    ) -> Result<BTreeMap<i32, Decimal>, String> {
+        if let Some(missing) = self.inputs.iter().find(|name| !inputs.contains_key(*name)) {
+            return Err(format!("Input {} is required", missing));
+        }
+        if let Some(unknown) = inputs.keys().find(|name| !self.inputs.contains(name)) {
+            return Err(format!("The rules don't use an input named {}", unknown));
+        }
+
+        let mut names: HashMap<&str, Value> = inputs
+            .iter()
+            .map(|(name, value)| (name.as_str(), Value::Number(*value)))
+            .collect();
+        let mut payouts = BTreeMap::new();
+        for statement in &self.statements {
+            match statement {
+                Statement::Let(name, expr) => {
+                    let value = evaluate(expr, &names).map_err(|e| format!("{}: {}", name, e))?;
+                    names.insert(name, value);
+                }
+                Statement::Payout(option_id, expr) => {
+                    let value = evaluate(expr, &names)
+                        .and_then(Value::number)
+                        .map_err(|e| format!("payout of option {}: {}", option_id, e))?;
+                    payouts.insert(*option_id, value);
+                }
+            }
+        }
+        Ok(payouts)
+    }
+}
@@ -1,6 +1,8 @@
+use crate::constants::config;
 use chrono::{DateTime, Utc};
 use sea_orm::prelude::Decimal;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum OrderSide {
@@ -22,6 +24,16 @@ pub enum TimeInForce {
     FOK, // Fill Or Kill - must fill entire order immediately or cancel
 }
 
+/// What happens when an order would match against another order from the same user
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum SelfTradePrevention {
+    #[default]
+    CancelNewest, // Cancel the incoming order, keep the resting one
+    CancelOldest, // Cancel the resting order, keep matching the incoming one
+    CancelBoth,   // Cancel both orders
+    Decrement,    // Reduce both orders by the overlapping quantity without trading
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum OrderStatus {
     Pending,
@@ -31,6 +43,45 @@ pub enum OrderStatus {
     Rejected,
 }
 
+/// What happened to an order in one entry of its history
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderEventKind {
+    Created,
+    Amended,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected,
+}
+
+impl OrderEventKind {
+    /// The event that moved an order into `status`
+    pub fn for_status(status: &OrderStatus) -> Self {
+        match status {
+            OrderStatus::Pending => OrderEventKind::Amended,
+            OrderStatus::PartiallyFilled => OrderEventKind::PartiallyFilled,
+            OrderStatus::Filled => OrderEventKind::Filled,
+            OrderStatus::Cancelled => OrderEventKind::Cancelled,
+            OrderStatus::Rejected => OrderEventKind::Rejected,
+        }
+    }
+}
+
+/// Immutable record of an order's state right after a transition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderEvent {
+    pub id: i64,
+    pub order_id: String,
+    pub kind: OrderEventKind,
+    pub status: OrderStatus,
+    pub price: Decimal,
+    pub quantity: i32,
+    pub filled_quantity: i32,
+    pub trade_id: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Order {
     pub id: String,
@@ -44,6 +95,29 @@ pub struct Order {
     pub quantity: i32,
     pub filled_quantity: i32,
     pub status: OrderStatus,
+    /// Post-only orders are rejected instead of taking liquidity
+    #[serde(default)]
+    pub post_only: bool,
+    #[serde(default)]
+    pub self_trade_prevention: SelfTradePrevention,
+    /// Identifier the client chose for the order, unique per user
+    #[serde(default)]
+    pub client_order_id: Option<String>,
+    /// Currency of the event, which the order is priced and paid in
+    #[serde(default = "config::get_base_currency")]
+    pub currency: String,
+    /// Id of the request that placed the order, carried onto its trades,
+    /// ledger entries and notifications
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+    /// Price a buy order's funds are held at when it can fill above `price`,
+    /// as market orders can; `None` holds at `price`
+    #[serde(default)]
+    pub hold_price: Option<Decimal>,
+    /// Shares of a sell order beyond the seller's position when it was
+    /// placed, whose collateral it holds until they fill
+    #[serde(default)]
+    pub short_quantity: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -61,6 +135,16 @@ pub struct Trade {
     pub quantity: i32,
     pub total_amount: Decimal,
     pub timestamp: DateTime<Utc>,
+    /// Sequence of the order book change that produced the trade
+    #[serde(default)]
+    pub sequence: u64,
+    #[serde(default = "config::get_base_currency")]
+    pub currency: String,
+    /// Correlation ids of the buy and sell orders
+    #[serde(default)]
+    pub buy_correlation_id: Option<String>,
+    #[serde(default)]
+    pub sell_correlation_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,6 +156,7 @@ pub struct OrderBookSnapshot {
     pub last_trade_price: Option<Decimal>,
     pub mid_price: Option<Decimal>,
     pub spread: Option<Decimal>,
+    pub sequence: u64,
     pub timestamp: DateTime<Utc>,
 }
 
@@ -91,6 +176,36 @@ pub struct MarketDepth {
     pub sell_orders: usize,
 }
 
+/// Call auction on a single option: orders rest without matching until `ends_at`,
+/// then the book uncrosses at a single price
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuctionState {
+    pub started_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+/// Outcome of uncrossing an auction
+#[derive(Debug, Clone)]
+pub struct AuctionResult {
+    // None when the book wasn't crossed and nothing traded
+    pub price: Option<Decimal>,
+    pub trades: Vec<Trade>,
+    // Final state of every order that traded or was cancelled during the uncross
+    pub orders: Vec<Order>,
+    // Order book sequence after the uncross
+    pub sequence: u64,
+}
+
+/// Trading halt on a single option, either tripped by the circuit breaker or set by an admin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradingHalt {
+    pub reason: String,
+    pub manual: bool,
+    pub halted_at: DateTime<Utc>,
+    // None keeps the halt in place until it is resumed manually
+    pub resume_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserPosition {
     pub user_id: i32,
@@ -111,6 +226,7 @@ impl Order {
         time_in_force: TimeInForce,
         price: Decimal,
         quantity: i32,
+        currency: String,
     ) -> Self {
         let now = Utc::now();
         Self {
@@ -125,6 +241,13 @@ impl Order {
             quantity,
             filled_quantity: 0,
             status: OrderStatus::Pending,
+            post_only: false,
+            self_trade_prevention: SelfTradePrevention::default(),
+            client_order_id: None,
+            currency,
+            correlation_id: Some(uuid::Uuid::new_v4().to_string()),
+            hold_price: None,
+            short_quantity: 0,
             created_at: now,
             updated_at: now,
         }
@@ -138,6 +261,26 @@ impl Order {
         self.filled_quantity >= self.quantity
     }
 
+    /// Whether the order can still match
+    pub fn is_open(&self) -> bool {
+        !self.is_filled()
+            && self.status != OrderStatus::Cancelled
+            && self.status != OrderStatus::Rejected
+    }
+
+    /// Reduce the order size without a fill (self-trade prevention decrement)
+    pub fn decrement(&mut self, quantity: i32) {
+        self.quantity -= quantity;
+        if self.remaining_quantity() == 0 {
+            self.status = if self.filled_quantity > 0 {
+                OrderStatus::Filled
+            } else {
+                OrderStatus::Cancelled
+            };
+        }
+        self.updated_at = Utc::now();
+    }
+
     pub fn fill(&mut self, quantity: i32) {
         self.filled_quantity += quantity;
         if self.is_filled() {
@@ -158,3 +301,53 @@ impl Order {
         self.updated_at = Utc::now();
     }
 }
+
+/// Full contents of one price level after a change; empty when the level is gone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelChange {
+    pub side: OrderSide,
+    pub price: Decimal,
+    pub orders: VecDeque<Order>,
+}
+
+impl LevelChange {
+    /// The level's new aggregate resting quantity, as sent to market data clients
+    pub fn delta(&self) -> PriceLevelDelta {
+        PriceLevelDelta {
+            side: self.side.clone(),
+            price: self.price,
+            quantity: self.orders.iter().map(|o| o.remaining_quantity()).sum(),
+        }
+    }
+}
+
+/// New total quantity at a price level; zero when the level is gone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceLevelDelta {
+    pub side: OrderSide,
+    pub price: Decimal,
+    pub quantity: i32,
+}
+
+/// Top of the book and last price, as published on the ticker channel
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Quote {
+    pub best_bid: Option<Decimal>,
+    pub best_ask: Option<Decimal>,
+    pub mid_price: Option<Decimal>,
+    pub last_trade_price: Option<Decimal>,
+}
+
+/// One batch of order book changes in the write-ahead log. Replaying entries in
+/// order on top of the snapshot they follow rebuilds the book exactly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookJournalEntry {
+    pub levels: Vec<LevelChange>,
+    pub last_trade_price: Option<Decimal>,
+    pub reference_price: Option<Decimal>,
+    pub reference_set_at: Option<DateTime<Utc>>,
+    pub halt: Option<TradingHalt>,
+    pub auction: Option<AuctionState>,
+    #[serde(default)]
+    pub sequence: u64,
+}
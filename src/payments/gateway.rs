@@ -0,0 +1,87 @@
+use deadpool_redis::{redis::AsyncCommands, Pool};
+use entity::payment_intents;
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Redis list the payment gateway relay drains, oldest request at the tail.
+/// The relay opens a checkout with the PSP for each intent and reports it
+/// back through the internal payment intents endpoint.
+pub const OUTBOX_KEY: &str = "payments:outbox";
+
+/// Public identifier for a new payment intent
+pub fn generate_intent_id() -> String {
+    format!("pi_{}", uuid::Uuid::new_v4().simple())
+}
+
+/// Queue an intent for the relay to open a checkout for
+pub async fn request_checkout(
+    redis_pool: &Pool,
+    intent: &payment_intents::Model,
+) -> Result<(), String> {
+    let message = json!({
+        "action": "create_intent",
+        "intent": {
+            "id": intent.id,
+            "user_id": intent.user_id,
+            "gateway": intent.gateway,
+            "amount": intent.amount,
+            "currency": intent.currency,
+        },
+        "timestamp": chrono::Utc::now(),
+    });
+    let mut conn = redis_pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to reach payments outbox: {}", e))?;
+    conn.lpush::<_, _, ()>(OUTBOX_KEY, message.to_string())
+        .await
+        .map_err(|e| format!("Failed to queue payment intent: {}", e))
+}
+
+/// Check a webhook's `X-Payment-Signature` header, `t=<unix seconds>,v1=<hex>`,
+/// where `v1` is the HMAC-SHA256 of `<t>.<body>` under the webhook secret.
+/// Timestamps further than `tolerance_seconds` from `now` are refused so a
+/// captured webhook can't be replayed later.
+pub fn verify_webhook(
+    secret: &str,
+    header: &str,
+    body: &[u8],
+    now: i64,
+    tolerance_seconds: i64,
+) -> Result<(), String> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = Some(value),
+            Some(("v1", value)) => signatures.push(value),
+            _ => {}
+        }
+    }
+
+    let timestamp = timestamp.ok_or_else(|| "Signature has no timestamp".to_string())?;
+    let signed_at: i64 = timestamp
+        .parse()
+        .map_err(|_| "Invalid signature timestamp".to_string())?;
+    if (now - signed_at).abs() > tolerance_seconds {
+        return Err("Signature timestamp outside the allowed window".to_string());
+    }
+
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC takes any key length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    // A webhook carries one signature per secret while the gateway rotates
+    let valid = signatures.iter().any(|signature| {
+        hex::decode(signature).is_ok_and(|signature| mac.clone().verify_slice(&signature).is_ok())
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err("Invalid signature".to_string())
+    }
+}
@@ -0,0 +1,3 @@
+pub mod gateway;
+pub mod payouts;
+pub mod service;
@@ -0,0 +1,33 @@
+use deadpool_redis::{redis::AsyncCommands, Pool};
+use entity::transaction;
+use serde_json::json;
+
+/// Redis list the payout relay drains, oldest request at the tail. The relay
+/// pays out each approved withdrawal and reports back through the internal
+/// withdrawals endpoint.
+pub const OUTBOX_KEY: &str = "payouts:outbox";
+
+/// Queue an approved withdrawal for the relay to pay out
+pub async fn request_payout(
+    redis_pool: &Pool,
+    withdrawal: &transaction::Model,
+) -> Result<(), String> {
+    let message = json!({
+        "action": "payout",
+        "withdrawal": {
+            "id": withdrawal.id,
+            "user_id": withdrawal.user_id,
+            "amount": withdrawal.amount,
+            "currency": withdrawal.currency,
+            "reference_id": withdrawal.reference_id,
+        },
+        "timestamp": chrono::Utc::now(),
+    });
+    let mut conn = redis_pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to reach payouts outbox: {}", e))?;
+    conn.lpush::<_, _, ()>(OUTBOX_KEY, message.to_string())
+        .await
+        .map_err(|e| format!("Failed to queue payout: {}", e))
+}
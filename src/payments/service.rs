@@ -0,0 +1,255 @@
+use crate::promotions::service::PromotionService;
+use crate::types::payment::{intent_status, PaymentWebhookData};
+use crate::types::transaction::transaction_status;
+use crate::wallets::{balances, transactions};
+use chrono::Utc;
+use entity::{payment_intents, transaction};
+use sea_orm::{
+    prelude::Decimal, ActiveModelTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QuerySelect, Set, SqlErr, TransactionTrait,
+};
+
+/// What a payment gateway webhook did
+pub enum WebhookOutcome {
+    /// The deposit was credited to the user's wallet
+    Credited {
+        user_id: i32,
+        amount: Decimal,
+        bonus: Option<Decimal>,
+        deposit: transaction::Model,
+    },
+    /// The intent was marked failed; nothing was credited
+    Failed {
+        user_id: i32,
+        deposit: Option<transaction::Model>,
+    },
+    /// An earlier delivery of the same payment already settled the intent
+    AlreadyProcessed,
+    UnknownIntent,
+    /// The payment doesn't match what the intent asked for, so it wasn't
+    /// credited and needs looking into
+    Mismatch(String),
+}
+
+/// Settles payment intents from the gateway's webhooks. Each payment is
+/// credited exactly once: the intent row is locked while it is settled, and
+/// the gateway reference is unique among transactions.
+pub struct PaymentService {
+    db: DatabaseConnection,
+}
+
+impl PaymentService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Credit a paid intent's amount, and any deposit match bonus, to the
+    /// user's wallet
+    pub async fn confirm_deposit(
+        &self,
+        data: &PaymentWebhookData,
+    ) -> Result<WebhookOutcome, String> {
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let Some(intent) = payment_intents::Entity::find_by_id(data.intent_id.clone())
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(|e| format!("Failed to load payment intent: {}", e))?
+        else {
+            return Ok(WebhookOutcome::UnknownIntent);
+        };
+
+        if intent.status == intent_status::SUCCEEDED {
+            return Ok(WebhookOutcome::AlreadyProcessed);
+        }
+        if data.amount != intent.amount || !data.currency.eq_ignore_ascii_case(&intent.currency) {
+            return Ok(WebhookOutcome::Mismatch(format!(
+                "Payment {} of {} {} doesn't match intent {} for {} {}",
+                data.gateway_reference,
+                data.amount,
+                data.currency,
+                intent.id,
+                intent.amount,
+                intent.currency
+            )));
+        }
+
+        let user_id = intent.user_id;
+        let amount = intent.amount;
+        let wallet = balances::lock(&txn, user_id, &intent.currency)
+            .await
+            .map_err(|e| format!("Failed to load wallet: {}", e))?;
+
+        let now = Utc::now();
+        let balance_before = wallet.balance;
+        let balance_after = balance_before + amount;
+
+        let wallet = balances::set_balance(&txn, wallet, balance_after)
+            .await
+            .map_err(|e| format!("Failed to update balance: {}", e))?;
+
+        // Intents opened before deposits were recorded up front have no
+        // transaction yet
+        let pending = match intent.transaction_id {
+            Some(transaction_id) => transaction::Entity::find_by_id(transaction_id)
+                .lock_exclusive()
+                .one(&txn)
+                .await
+                .map_err(|e| format!("Failed to load deposit: {}", e))?,
+            None => None,
+        };
+        let deposit = match pending {
+            Some(pending) => {
+                if pending.status == transaction_status::COMPLETED {
+                    return Ok(WebhookOutcome::AlreadyProcessed);
+                }
+                let mut active = transactions::transition(pending, transaction_status::COMPLETED)?;
+                active.balance_before = Set(balance_before);
+                active.balance_after = Set(balance_after);
+                active.gateway_reference = Set(Some(data.gateway_reference.clone()));
+                active.failure_reason = Set(None);
+                active.update(&txn).await
+            }
+            None => {
+                transaction::ActiveModel {
+                    user_id: Set(user_id),
+                    r#type: Set("deposit".to_string()),
+                    amount: Set(amount),
+                    currency: Set(intent.currency.clone()),
+                    balance_before: Set(balance_before),
+                    balance_after: Set(balance_after),
+                    status: Set(transaction_status::COMPLETED.to_string()),
+                    reference_id: Set(intent.id.clone()),
+                    created_at: Set(now.naive_utc()),
+                    gateway: Set(Some(intent.gateway.clone())),
+                    gateway_reference: Set(Some(data.gateway_reference.clone())),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await
+            }
+        };
+        let deposit = match deposit {
+            Ok(deposit) => deposit,
+            // The payment was already credited through another intent
+            Err(e) if matches!(e.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) => {
+                return Ok(WebhookOutcome::AlreadyProcessed)
+            }
+            Err(e) => return Err(format!("Failed to record deposit: {}", e)),
+        };
+
+        let (_, bonus) = PromotionService::credit_deposit_match(&txn, wallet, amount, now).await?;
+
+        let mut active_intent: payment_intents::ActiveModel = intent.into();
+        active_intent.status = Set(intent_status::SUCCEEDED.to_string());
+        active_intent.gateway_reference = Set(Some(data.gateway_reference.clone()));
+        active_intent.transaction_id = Set(Some(deposit.id));
+        active_intent.failure_reason = Set(None);
+        active_intent.updated_at = Set(now.fixed_offset());
+        active_intent
+            .update(&txn)
+            .await
+            .map_err(|e| format!("Failed to update payment intent: {}", e))?;
+
+        txn.commit()
+            .await
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+        Ok(WebhookOutcome::Credited {
+            user_id,
+            amount,
+            bonus,
+            deposit,
+        })
+    }
+
+    /// Mark an intent failed. A later successful payment for it is still
+    /// credited, since the user may retry at the gateway's checkout.
+    pub async fn fail_deposit(&self, data: &PaymentWebhookData) -> Result<WebhookOutcome, String> {
+        let txn = self
+            .db
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let Some(intent) = payment_intents::Entity::find_by_id(data.intent_id.clone())
+            .lock_exclusive()
+            .one(&txn)
+            .await
+            .map_err(|e| format!("Failed to load payment intent: {}", e))?
+        else {
+            return Ok(WebhookOutcome::UnknownIntent);
+        };
+
+        if intent.status == intent_status::SUCCEEDED || intent.status == intent_status::FAILED {
+            return Ok(WebhookOutcome::AlreadyProcessed);
+        }
+
+        let user_id = intent.user_id;
+        let failure_reason = data
+            .failure_reason
+            .clone()
+            .unwrap_or_else(|| "Payment failed".to_string());
+        let deposit = move_intent_deposit(
+            &txn,
+            &intent,
+            transaction_status::FAILED,
+            Some(failure_reason.clone()),
+        )
+        .await?;
+
+        let mut active_intent: payment_intents::ActiveModel = intent.into();
+        active_intent.status = Set(intent_status::FAILED.to_string());
+        active_intent.gateway_reference = Set(Some(data.gateway_reference.clone()));
+        active_intent.failure_reason = Set(Some(failure_reason));
+        active_intent.updated_at = Set(Utc::now().fixed_offset());
+        active_intent
+            .update(&txn)
+            .await
+            .map_err(|e| format!("Failed to update payment intent: {}", e))?;
+
+        txn.commit()
+            .await
+            .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+        Ok(WebhookOutcome::Failed { user_id, deposit })
+    }
+}
+
+/// Move the deposit transaction recorded for an intent to `to`, returning it
+/// as written. Intents opened before deposits were recorded up front have
+/// none, and a deposit that can't move there is left as it is.
+pub async fn move_intent_deposit<C: ConnectionTrait>(
+    conn: &C,
+    intent: &payment_intents::Model,
+    to: &str,
+    failure_reason: Option<String>,
+) -> Result<Option<transaction::Model>, String> {
+    let Some(transaction_id) = intent.transaction_id else {
+        return Ok(None);
+    };
+    let Some(deposit) = transaction::Entity::find_by_id(transaction_id)
+        .lock_exclusive()
+        .one(conn)
+        .await
+        .map_err(|e| format!("Failed to load deposit: {}", e))?
+    else {
+        return Ok(None);
+    };
+    if !transactions::can_transition(&deposit.r#type, &deposit.status, to) {
+        return Ok(None);
+    }
+
+    let mut active = transactions::transition(deposit, to)?;
+    active.failure_reason = Set(failure_reason);
+    active
+        .update(conn)
+        .await
+        .map(Some)
+        .map_err(|e| format!("Failed to update deposit: {}", e))
+}
@@ -0,0 +1,203 @@
+use crate::constants::config;
+use crate::types::promotion::{PromotionKind, PromotionUserUsage};
+use crate::wallets::balances;
+use chrono::{DateTime, Utc};
+use entity::{promotion_usages, promotions, transaction, wallets};
+use sea_orm::{
+    prelude::{Decimal, Expr},
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set,
+};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Evaluates promotions for the fee engine and the deposit flow, and records
+/// what each user received from them
+pub struct PromotionService {
+    db: DatabaseConnection,
+}
+
+impl PromotionService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Promotions of a kind that are switched on and running at `at`
+    async fn running<C: ConnectionTrait>(
+        conn: &C,
+        kind: PromotionKind,
+        at: DateTime<Utc>,
+    ) -> Result<Vec<promotions::Model>, String> {
+        promotions::Entity::find()
+            .filter(promotions::Column::Kind.eq(kind.as_str()))
+            .filter(promotions::Column::IsActive.eq(true))
+            .filter(promotions::Column::StartsAt.lte(at))
+            .filter(promotions::Column::EndsAt.gt(at))
+            .order_by_asc(promotions::Column::Id)
+            .all(conn)
+            .await
+            .map_err(|e| format!("Failed to load promotions: {}", e))
+    }
+
+    /// The fee-free promotion covering each of the given events, if any. A
+    /// promotion for a specific event wins over one for every event.
+    pub async fn fee_free_promotions(
+        &self,
+        event_ids: &[i32],
+        at: DateTime<Utc>,
+    ) -> Result<HashMap<i32, promotions::Model>, String> {
+        let running = Self::running(&self.db, PromotionKind::FeeFree, at).await?;
+
+        Ok(event_ids
+            .iter()
+            .filter_map(|event_id| {
+                running
+                    .iter()
+                    .find(|p| p.event_id == Some(*event_id))
+                    .or_else(|| running.iter().find(|p| p.event_id.is_none()))
+                    .map(|p| (*event_id, p.clone()))
+            })
+            .collect())
+    }
+
+    /// The bonus a deposit earns from the first running deposit match that the
+    /// user hasn't used up yet
+    pub async fn deposit_match<C: ConnectionTrait>(
+        conn: &C,
+        user_id: i32,
+        amount: Decimal,
+        at: DateTime<Utc>,
+    ) -> Result<Option<(promotions::Model, Decimal)>, String> {
+        for promotion in Self::running(conn, PromotionKind::DepositMatch, at).await? {
+            let (Some(percent), Some(cap)) =
+                (promotion.match_percent, promotion.max_reward_per_user)
+            else {
+                continue;
+            };
+
+            let used = Self::user_total(conn, promotion.id, user_id).await?;
+            let bonus = (amount * percent / Decimal::ONE_HUNDRED)
+                .min(cap - used)
+                .round_dp(2);
+            if bonus > Decimal::ZERO {
+                return Ok(Some((promotion, bonus)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Top a deposit up from a running deposit match: credit the bonus to
+    /// the locked `wallet` it was paid into as its own transaction and record
+    /// the usage. Promotion amounts are in the base currency, so deposits in
+    /// other currencies aren't matched. Returns the wallet as updated and the
+    /// bonus, if one was paid.
+    pub async fn credit_deposit_match<C: ConnectionTrait>(
+        conn: &C,
+        wallet: wallets::Model,
+        amount: Decimal,
+        at: DateTime<Utc>,
+    ) -> Result<(wallets::Model, Option<Decimal>), String> {
+        if wallet.currency != config::get_base_currency() {
+            return Ok((wallet, None));
+        }
+        let user_id = wallet.user_id;
+        let Some((promotion, bonus)) = Self::deposit_match(conn, user_id, amount, at).await? else {
+            return Ok((wallet, None));
+        };
+
+        let bonus_before = wallet.balance;
+        let bonus_after = bonus_before + bonus;
+        let currency = wallet.currency.clone();
+        let wallet = balances::set_balance(conn, wallet, bonus_after)
+            .await
+            .map_err(|e| format!("Failed to credit deposit bonus: {}", e))?;
+
+        let bonus_reference_id = Uuid::new_v4().to_string();
+        transaction::ActiveModel {
+            user_id: Set(user_id),
+            r#type: Set("promotion_bonus".to_string()),
+            amount: Set(bonus),
+            currency: Set(currency),
+            balance_before: Set(bonus_before),
+            balance_after: Set(bonus_after),
+            status: Set("completed".to_string()),
+            reference_id: Set(bonus_reference_id.clone()),
+            created_at: Set(at.naive_utc()),
+            ..Default::default()
+        }
+        .insert(conn)
+        .await
+        .map_err(|e| format!("Failed to create bonus transaction record: {}", e))?;
+
+        Self::record_usage(conn, promotion.id, user_id, bonus, &bonus_reference_id).await?;
+
+        Ok((wallet, Some(bonus)))
+    }
+
+    /// Record a reward a user received from a promotion
+    pub async fn record_usage<C: ConnectionTrait>(
+        conn: &C,
+        promotion_id: i32,
+        user_id: i32,
+        amount: Decimal,
+        reference: &str,
+    ) -> Result<(), String> {
+        promotion_usages::ActiveModel {
+            promotion_id: Set(promotion_id),
+            user_id: Set(user_id),
+            amount: Set(amount),
+            reference: Set(reference.to_string()),
+            created_at: Set(Utc::now().into()),
+            ..Default::default()
+        }
+        .insert(conn)
+        .await
+        .map_err(|e| format!("Failed to record promotion usage: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Usage of a promotion per user, largest rewards first
+    pub async fn usage_by_user(
+        &self,
+        promotion_id: i32,
+    ) -> Result<Vec<PromotionUserUsage>, String> {
+        promotion_usages::Entity::find()
+            .select_only()
+            .column(promotion_usages::Column::UserId)
+            .column_as(Expr::cust("COUNT(*)"), "usage_count")
+            .column_as(
+                Expr::cust("COALESCE(SUM(promotion_usages.amount), 0)"),
+                "total_reward",
+            )
+            .filter(promotion_usages::Column::PromotionId.eq(promotion_id))
+            .group_by(promotion_usages::Column::UserId)
+            .order_by_desc(Expr::cust("total_reward"))
+            .into_model::<PromotionUserUsage>()
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to aggregate promotion usage: {}", e))
+    }
+
+    async fn user_total<C: ConnectionTrait>(
+        conn: &C,
+        promotion_id: i32,
+        user_id: i32,
+    ) -> Result<Decimal, String> {
+        let total: Option<Decimal> = promotion_usages::Entity::find()
+            .select_only()
+            .column_as(
+                Expr::cust("COALESCE(SUM(promotion_usages.amount), 0)"),
+                "total",
+            )
+            .filter(promotion_usages::Column::PromotionId.eq(promotion_id))
+            .filter(promotion_usages::Column::UserId.eq(user_id))
+            .into_tuple()
+            .one(conn)
+            .await
+            .map_err(|e| format!("Failed to load promotion usage: {}", e))?;
+
+        Ok(total.unwrap_or(Decimal::ZERO))
+    }
+}
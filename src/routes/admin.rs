@@ -0,0 +1,427 @@
+use crate::constants::config;
+use crate::handlers::audit_handler::{get_correlation_trace, list_audit_logs};
+use crate::handlers::event_option_handler::get_option_conservation;
+use crate::handlers::event_settlement_handler::void_event;
+use crate::handlers::import_handler::{get_import, import_accounts};
+use crate::handlers::kyc_handler::{
+    approve_kyc, get_kyc_document_content, get_user_kyc, list_kyc_users, override_age, reject_kyc,
+};
+use crate::handlers::maintenance_handler::{clear_maintenance, schedule_maintenance};
+use crate::handlers::market_maker_handler::{
+    disable_market_maker, enable_market_maker, get_market_maker, get_market_maker_report,
+    reseed_market_maker, update_market_maker_config,
+};
+use crate::handlers::metrics_handler::get_market_performance;
+use crate::handlers::moderation_handler::{adjust_balance, ban_user, unban_user};
+use crate::handlers::oracle_handler::{
+    configure_oracle, confirm_proposal, delete_oracle, get_oracle, list_proposals, reject_proposal,
+};
+use crate::handlers::role_handler::{assign_role, list_roles, list_user_roles, remove_role};
+use crate::handlers::settings_handler::{delete_setting, list_settings, update_setting};
+use crate::handlers::settlement_rules_handler::{
+    delete_settlement_rules, get_settlement_rules, preview_settlement_rules, set_settlement_rules,
+};
+use crate::handlers::support_handler::{
+    get_ticket, list_tickets, post_staff_ticket_message, update_ticket,
+};
+use crate::handlers::tenant_handler::{create_tenant, list_tenants, update_tenant};
+use crate::handlers::trade_settlement_handler::{list_unsettled_trades, retry_trade_settlement};
+use crate::handlers::two_factor_handler::reset_two_factor;
+use crate::handlers::withdrawal_handler::{
+    approve_withdrawal, list_withdrawals, reject_withdrawal,
+};
+use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::permission::RequirePermission;
+use crate::middleware::two_factor::TwoFactor;
+use crate::types::rbac::Permission;
+use actix_web::web;
+
+pub fn configure_admin_routes() -> actix_web::Scope {
+    web::scope("/admin")
+        .route(
+            "/audit-logs",
+            web::get()
+                .to(list_audit_logs)
+                .wrap(RequirePermission(Permission::ReadAudits))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/correlations/{correlation_id}",
+            web::get()
+                .to(get_correlation_trace)
+                .wrap(RequirePermission(Permission::ReadAudits))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/void",
+            web::post()
+                .to(void_event)
+                .wrap(TwoFactor)
+                .wrap(RequirePermission(Permission::SettleEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/oracle",
+            web::get()
+                .to(get_oracle)
+                .wrap(RequirePermission(Permission::SettleEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/oracle",
+            web::put()
+                .to(configure_oracle)
+                .wrap(RequirePermission(Permission::SettleEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/oracle",
+            web::delete()
+                .to(delete_oracle)
+                .wrap(RequirePermission(Permission::SettleEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/oracle/confirm",
+            web::post()
+                .to(confirm_proposal)
+                .wrap(TwoFactor)
+                .wrap(RequirePermission(Permission::SettleEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/oracle/reject",
+            web::post()
+                .to(reject_proposal)
+                .wrap(RequirePermission(Permission::SettleEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/settlement-rules",
+            web::get()
+                .to(get_settlement_rules)
+                .wrap(RequirePermission(Permission::SettleEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/settlement-rules",
+            web::put()
+                .to(set_settlement_rules)
+                .wrap(RequirePermission(Permission::SettleEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/settlement-rules",
+            web::delete()
+                .to(delete_settlement_rules)
+                .wrap(RequirePermission(Permission::SettleEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/settlement-rules/preview",
+            web::post()
+                .to(preview_settlement_rules)
+                .wrap(RequirePermission(Permission::SettleEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/oracle/proposals",
+            web::get()
+                .to(list_proposals)
+                .wrap(RequirePermission(Permission::SettleEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/event-options/{option_id}/conservation",
+            web::get()
+                .to(get_option_conservation)
+                .wrap(RequirePermission(Permission::ReadAudits))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/users/{user_id}/2fa",
+            web::delete()
+                .to(reset_two_factor)
+                .wrap(RequirePermission(Permission::SupportUsers))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/users/{user_id}/balance-adjustments",
+            web::post()
+                .to(adjust_balance)
+                .wrap(TwoFactor)
+                .wrap(RequirePermission(Permission::AdjustBalances))
+                .wrap(AuthMiddleware),
+        )
+        .service(
+            web::resource("/imports")
+                .app_data(web::PayloadConfig::new(config::get_import_max_bytes()))
+                .route(
+                    web::post()
+                        .to(import_accounts)
+                        .wrap(TwoFactor)
+                        .wrap(RequirePermission(Permission::AdjustBalances))
+                        .wrap(AuthMiddleware),
+                ),
+        )
+        .route(
+            "/imports/{import_id}",
+            web::get()
+                .to(get_import)
+                .wrap(RequirePermission(Permission::AdjustBalances))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/users/{user_id}/ban",
+            web::post()
+                .to(ban_user)
+                .wrap(RequirePermission(Permission::BanUsers))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/users/{user_id}/ban",
+            web::delete()
+                .to(unban_user)
+                .wrap(RequirePermission(Permission::BanUsers))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/withdrawals",
+            web::get()
+                .to(list_withdrawals)
+                .wrap(RequirePermission(Permission::ApproveWithdrawals))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/withdrawals/{transaction_id}/approve",
+            web::post()
+                .to(approve_withdrawal)
+                .wrap(TwoFactor)
+                .wrap(RequirePermission(Permission::ApproveWithdrawals))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/withdrawals/{transaction_id}/reject",
+            web::post()
+                .to(reject_withdrawal)
+                .wrap(RequirePermission(Permission::ApproveWithdrawals))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/markets/performance",
+            web::get()
+                .to(get_market_performance)
+                .wrap(RequirePermission(Permission::OperateMarkets))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/market-maker/report",
+            web::get()
+                .to(get_market_maker_report)
+                .wrap(RequirePermission(Permission::OperateMarkets))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/options/{option_id}/market-maker",
+            web::get()
+                .to(get_market_maker)
+                .wrap(RequirePermission(Permission::OperateMarkets))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/options/{option_id}/market-maker/enable",
+            web::post()
+                .to(enable_market_maker)
+                .wrap(RequirePermission(Permission::OperateMarkets))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/options/{option_id}/market-maker/disable",
+            web::post()
+                .to(disable_market_maker)
+                .wrap(RequirePermission(Permission::OperateMarkets))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/options/{option_id}/market-maker/config",
+            web::put()
+                .to(update_market_maker_config)
+                .wrap(RequirePermission(Permission::OperateMarkets))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/events/{event_id}/options/{option_id}/market-maker/reseed",
+            web::post()
+                .to(reseed_market_maker)
+                .wrap(RequirePermission(Permission::OperateMarkets))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/trades/unsettled",
+            web::get()
+                .to(list_unsettled_trades)
+                .wrap(RequirePermission(Permission::OperateMarkets))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/trades/{trade_id}/retry-settlement",
+            web::post()
+                .to(retry_trade_settlement)
+                .wrap(RequirePermission(Permission::OperateMarkets))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/support/tickets",
+            web::get()
+                .to(list_tickets)
+                .wrap(RequirePermission(Permission::SupportUsers))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/support/tickets/{ticket_id}",
+            web::get()
+                .to(get_ticket)
+                .wrap(RequirePermission(Permission::SupportUsers))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/support/tickets/{ticket_id}",
+            web::put()
+                .to(update_ticket)
+                .wrap(RequirePermission(Permission::SupportUsers))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/support/tickets/{ticket_id}/messages",
+            web::post()
+                .to(post_staff_ticket_message)
+                .wrap(RequirePermission(Permission::SupportUsers))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/kyc",
+            web::get()
+                .to(list_kyc_users)
+                .wrap(RequirePermission(Permission::ReviewKyc))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/kyc/users/{user_id}",
+            web::get()
+                .to(get_user_kyc)
+                .wrap(RequirePermission(Permission::ReviewKyc))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/kyc/users/{user_id}/approve",
+            web::post()
+                .to(approve_kyc)
+                .wrap(RequirePermission(Permission::ReviewKyc))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/kyc/users/{user_id}/reject",
+            web::post()
+                .to(reject_kyc)
+                .wrap(RequirePermission(Permission::ReviewKyc))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/kyc/users/{user_id}/age",
+            web::put()
+                .to(override_age)
+                .wrap(RequirePermission(Permission::ReviewKyc))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/kyc/documents/{document_id}/content",
+            web::get()
+                .to(get_kyc_document_content)
+                .wrap(RequirePermission(Permission::ReviewKyc))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/roles",
+            web::get()
+                .to(list_roles)
+                .wrap(RequirePermission(Permission::ManageRoles))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/users/{user_id}/roles",
+            web::get()
+                .to(list_user_roles)
+                .wrap(RequirePermission(Permission::ManageRoles))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/users/{user_id}/roles",
+            web::post()
+                .to(assign_role)
+                .wrap(RequirePermission(Permission::ManageRoles))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/users/{user_id}/roles/{role}",
+            web::delete()
+                .to(remove_role)
+                .wrap(RequirePermission(Permission::ManageRoles))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/tenants",
+            web::get()
+                .to(list_tenants)
+                .wrap(RequirePermission(Permission::ManageTenants))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/tenants",
+            web::post()
+                .to(create_tenant)
+                .wrap(RequirePermission(Permission::ManageTenants))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/tenants/{tenant_id}",
+            web::put()
+                .to(update_tenant)
+                .wrap(RequirePermission(Permission::ManageTenants))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/settings",
+            web::get()
+                .to(list_settings)
+                .wrap(RequirePermission(Permission::ManageSettings))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/settings/{key}",
+            web::put()
+                .to(update_setting)
+                .wrap(RequirePermission(Permission::ManageSettings))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/settings/{key}",
+            web::delete()
+                .to(delete_setting)
+                .wrap(RequirePermission(Permission::ManageSettings))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/maintenance",
+            web::put()
+                .to(schedule_maintenance)
+                .wrap(RequirePermission(Permission::ManageSettings))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/maintenance",
+            web::delete()
+                .to(clear_maintenance)
+                .wrap(RequirePermission(Permission::ManageSettings))
+                .wrap(AuthMiddleware),
+        )
+}
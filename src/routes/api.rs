@@ -1,17 +1,35 @@
 use crate::handlers::health::{health_check, index};
+use crate::handlers::maintenance_handler::get_maintenance;
+use crate::handlers::metrics_handler::get_metrics;
+use crate::handlers::schema_handler::get_schema;
+use crate::handlers::tenant_handler::get_current_tenant;
 use actix_web::web;
 
 pub fn configure_routes() -> actix_web::Scope {
     web::scope("")
         .route("/", web::get().to(index))
         .route("/health", web::get().to(health_check))
+        .route("/metrics", web::get().to(get_metrics))
+        .route("/tenant", web::get().to(get_current_tenant))
+        .route("/maintenance", web::get().to(get_maintenance))
+        .route("/schema", web::get().to(get_schema))
         .service(crate::routes::auth::configure_auth_routes())
         .service(crate::routes::user::configure_user_routes())
         .service(crate::routes::transaction::configure_transaction_routes())
+        .service(crate::routes::payment::configure_payment_routes())
         .service(crate::routes::event::configure_event_routes())
         .service(crate::routes::event_option::configure_event_option_routes())
         .service(crate::routes::websocket::configure_websocket_routes())
         .service(crate::routes::order_book::configure_order_book_routes())
         .service(crate::routes::position::configure_position_routes())
         .service(crate::routes::portfolio::configure_portfolio_routes())
+        .service(crate::routes::dashboard::configure_dashboard_routes())
+        .service(crate::routes::export::configure_export_routes())
+        .service(crate::routes::market::configure_market_routes())
+        .service(crate::routes::promotion::configure_promotion_routes())
+        .service(crate::routes::public::configure_public_routes())
+        .service(crate::routes::risk::configure_risk_routes())
+        .service(crate::routes::support::configure_support_routes())
+        .service(crate::routes::admin::configure_admin_routes())
+        .service(crate::routes::internal::configure_internal_routes())
 }
@@ -1,8 +1,29 @@
-use crate::handlers::auth_handler::{login, register};
-use actix_web::web;
+use crate::handlers::auth_handler::{change_password, login, logout, refresh, register};
+use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::rate_limit::{RateLimit, RateLimitBudget};
+use actix_web::body::BoxBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::{web, Error, Scope};
 
-pub fn configure_auth_routes() -> actix_web::Scope {
+/// Login and registration, rate limited per client IP to slow down credential
+/// stuffing
+pub fn configure_auth_routes() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<BoxBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
     web::scope("/auth")
+        .wrap(RateLimit(RateLimitBudget::Auth))
         .route("/register", web::post().to(register))
         .route("/login", web::post().to(login))
+        .route("/refresh", web::post().to(refresh))
+        .route("/logout", web::post().to(logout).wrap(AuthMiddleware))
+        .route(
+            "/password",
+            web::put().to(change_password).wrap(AuthMiddleware),
+        )
 }
@@ -0,0 +1,12 @@
+use crate::handlers::dashboard_handler;
+use crate::middleware::auth::AuthMiddleware;
+use actix_web::web;
+
+pub fn configure_dashboard_routes() -> actix_web::Scope {
+    web::scope("/dashboard").route(
+        "",
+        web::get()
+            .to(dashboard_handler::get_dashboard)
+            .wrap(AuthMiddleware),
+    )
+}
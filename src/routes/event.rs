@@ -1,24 +1,86 @@
-use crate::handlers::event_handler::{create_event, get_event, list_events, update_event};
+use crate::handlers::candle_handler::{get_option_candles, get_option_market_stats};
+use crate::handlers::event_handler::{
+    create_event, feature_event, get_event, get_event_og_card, list_events, unfeature_event,
+    update_event,
+};
 use crate::handlers::event_option_handler::list_event_options;
 use crate::handlers::event_settlement_handler::settle_event;
+use crate::handlers::order_book_handler::get_event_order_books;
 use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::etag::ETag;
+use crate::middleware::permission::RequirePermission;
+use crate::middleware::tenant::TenantEvent;
+use crate::middleware::two_factor::TwoFactor;
+use crate::types::rbac::Permission;
 use actix_web::web;
 
 pub fn configure_event_routes() -> actix_web::Scope {
     web::scope("/events")
-        .route("", web::get().to(list_events))
-        .route("/create", web::post().to(create_event).wrap(AuthMiddleware))
-        .route("/{event_id}", web::get().to(get_event))
+        .route("", web::get().to(list_events).wrap(ETag))
+        .route(
+            "/create",
+            web::post()
+                .to(create_event)
+                .wrap(RequirePermission(Permission::ManageEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/{event_id}",
+            web::get().to(get_event).wrap(ETag).wrap(TenantEvent),
+        )
         .route(
             "/{event_id}",
-            web::put().to(update_event).wrap(AuthMiddleware),
+            web::put()
+                .to(update_event)
+                .wrap(RequirePermission(Permission::ManageEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/{event_id}/og-card",
+            web::get().to(get_event_og_card).wrap(TenantEvent),
+        )
+        .route(
+            "/{event_id}/feature",
+            web::put()
+                .to(feature_event)
+                .wrap(RequirePermission(Permission::ManageEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/{event_id}/feature",
+            web::delete()
+                .to(unfeature_event)
+                .wrap(RequirePermission(Permission::ManageEvents))
+                .wrap(AuthMiddleware),
         )
         .route(
             "/{event_id}/settle",
-            web::post().to(settle_event).wrap(AuthMiddleware),
+            web::post()
+                .to(settle_event)
+                .wrap(TwoFactor)
+                .wrap(RequirePermission(Permission::SettleEvents))
+                .wrap(AuthMiddleware),
         )
         .route(
             "/{event_id}/options",
-            web::get().to(list_event_options).wrap(AuthMiddleware),
+            web::get()
+                .to(list_event_options)
+                .wrap(TenantEvent)
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/{event_id}/options/{option_id}/candles",
+            web::get().to(get_option_candles).wrap(TenantEvent),
+        )
+        .route(
+            "/{event_id}/options/{option_id}/stats",
+            web::get().to(get_option_market_stats).wrap(TenantEvent),
+        )
+        .route(
+            "/{event_id}/orderbooks",
+            web::get()
+                .to(get_event_order_books)
+                .wrap(ETag)
+                .wrap(TenantEvent),
         )
 }
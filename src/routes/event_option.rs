@@ -1,16 +1,36 @@
 use crate::handlers::event_option_handler::*;
 use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::permission::RequirePermission;
+use crate::middleware::tenant::TenantEvent;
+use crate::types::rbac::Permission;
 use actix_web::web;
 
 pub fn configure_event_option_routes() -> actix_web::Scope {
     web::scope("/event-options")
         .route(
             "/create",
-            web::post().to(create_event_option).wrap(AuthMiddleware),
+            web::post()
+                .to(create_event_option)
+                .wrap(RequirePermission(Permission::ManageEvents))
+                .wrap(AuthMiddleware),
         )
         .route(
             "/{option_id}",
-            web::put().to(update_event_option).wrap(AuthMiddleware),
+            web::put()
+                .to(update_event_option)
+                .wrap(RequirePermission(Permission::ManageEvents))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/{option_id}",
+            web::get().to(get_event_option).wrap(TenantEvent),
+        )
+        .route(
+            "/{option_id}/holders",
+            web::get().to(get_option_holders).wrap(TenantEvent),
+        )
+        .route(
+            "/{option_id}/price-history",
+            web::get().to(get_option_price_history).wrap(TenantEvent),
         )
-        .route("/{option_id}", web::get().to(get_event_option))
 }
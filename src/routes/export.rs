@@ -0,0 +1,47 @@
+use crate::handlers::export_handler;
+use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::permission::RequirePermission;
+use crate::types::rbac::Permission;
+use actix_web::web;
+
+pub fn configure_export_routes() -> actix_web::Scope {
+    web::scope("/exports")
+        // Stream the current user's trades
+        .route(
+            "/trades",
+            web::get()
+                .to(export_handler::export_my_trades)
+                .wrap(AuthMiddleware),
+        )
+        // Stream the current user's orders
+        .route(
+            "/orders",
+            web::get()
+                .to(export_handler::export_my_orders)
+                .wrap(AuthMiddleware),
+        )
+        // Stream all trades (staff)
+        .route(
+            "/admin/trades",
+            web::get()
+                .to(export_handler::export_all_trades)
+                .wrap(RequirePermission(Permission::ReadExports))
+                .wrap(AuthMiddleware),
+        )
+        // Stream all orders (staff)
+        .route(
+            "/admin/orders",
+            web::get()
+                .to(export_handler::export_all_orders)
+                .wrap(RequirePermission(Permission::ReadExports))
+                .wrap(AuthMiddleware),
+        )
+        // Stream the order event log and settlement runs as an archive (staff)
+        .route(
+            "/admin/archive",
+            web::get()
+                .to(export_handler::export_archive)
+                .wrap(RequirePermission(Permission::ReadExports))
+                .wrap(AuthMiddleware),
+        )
+}
@@ -0,0 +1,49 @@
+use crate::handlers::{internal_handler, oracle_handler, payment_handler, withdrawal_handler};
+use crate::middleware::internal_auth::InternalAuth;
+use actix_web::body::BoxBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::{web, Error, Scope};
+
+/// Endpoints for other services of the platform, never for users; see
+/// `InternalAuth`
+pub fn configure_internal_routes() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<BoxBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    web::scope("/internal")
+        .wrap(InternalAuth)
+        // Refresh events subscribers
+        .route(
+            "/broadcast/events",
+            web::post().to(internal_handler::broadcast_events),
+        )
+        // Refresh a user's portfolio, transactions and risk subscribers
+        .route(
+            "/broadcast/users/{user_id}",
+            web::post().to(internal_handler::broadcast_user),
+        )
+        // Run the tier recalculation job now
+        .route(
+            "/jobs/tier-recalculation",
+            web::post().to(internal_handler::run_tier_recalculation),
+        )
+        // Report the checkout the payment gateway relay opened for an intent
+        .route(
+            "/payments/intents/{intent_id}",
+            web::post().to(payment_handler::report_intent),
+        )
+        // Report whether the payout relay paid out an approved withdrawal
+        .route(
+            "/withdrawals/{transaction_id}",
+            web::post().to(withdrawal_handler::report_payout),
+        ) // Report the feed the oracle relay fetched for an event
+        .route(
+            "/oracle/events/{event_id}/feed",
+            web::post().to(oracle_handler::report_feed),
+        )
+}
@@ -0,0 +1,16 @@
+use crate::handlers::market_handler;
+use crate::middleware::rate_limit::{RateLimit, RateLimitBudget};
+use crate::middleware::tenant::TenantEvent;
+use actix_web::web;
+
+pub fn configure_market_routes() -> actix_web::Scope {
+    web::scope("/markets")
+        // Time-bucketed book depth for heatmap rendering (public)
+        .route(
+            "/{event_id}/{option_id}/heatmap",
+            web::get()
+                .to(market_handler::get_depth_heatmap)
+                .wrap(TenantEvent)
+                .wrap(RateLimit(RateLimitBudget::Reads)),
+        )
+}
@@ -1,10 +1,20 @@
+pub mod admin;
 pub mod api;
 pub mod auth;
+pub mod dashboard;
 pub mod event;
 pub mod event_option;
+pub mod export;
+pub mod internal;
+pub mod market;
 pub mod order_book;
+pub mod payment;
 pub mod portfolio;
 pub mod position;
+pub mod promotion;
+pub mod public;
+pub mod risk;
+pub mod support;
 pub mod transaction;
 pub mod user;
 pub mod websocket;
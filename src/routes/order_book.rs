@@ -1,4 +1,15 @@
-use crate::{handlers::order_book_handler, middleware::auth::AuthMiddleware};
+use crate::handlers::{order_book_handler, venue_handler};
+use crate::middleware::{
+    age::RequireMinimumAge,
+    auth::AuthMiddleware,
+    consent::RequireConsent,
+    etag::ETag,
+    idempotency::Idempotency,
+    permission::RequirePermission,
+    rate_limit::{RateLimit, RateLimitBudget},
+    tenant::TenantEvent,
+};
+use crate::types::rbac::Permission;
 use actix_web::{web, Scope};
 
 pub fn configure_order_book_routes() -> Scope {
@@ -8,6 +19,38 @@ pub fn configure_order_book_routes() -> Scope {
             "/orders",
             web::post()
                 .to(order_book_handler::place_order)
+                .wrap(Idempotency)
+                .wrap(RequireConsent)
+                .wrap(RequireMinimumAge)
+                .wrap(RateLimit(RateLimitBudget::Orders))
+                .wrap(AuthMiddleware),
+        )
+        // Place several orders in one request
+        .route(
+            "/orders/batch",
+            web::post()
+                .to(order_book_handler::place_order_batch)
+                .wrap(Idempotency)
+                .wrap(RequireConsent)
+                .wrap(RequireMinimumAge)
+                .wrap(RateLimit(RateLimitBudget::Orders))
+                .wrap(AuthMiddleware),
+        )
+        // Check an order and preview its fills without placing it
+        .route(
+            "/orders/validate",
+            web::post()
+                .to(order_book_handler::validate_order)
+                .wrap(RequireConsent)
+                .wrap(RequireMinimumAge)
+                .wrap(RateLimit(RateLimitBudget::Reads))
+                .wrap(AuthMiddleware),
+        )
+        // Cancel all of the user's open orders, optionally in one event or option
+        .route(
+            "/orders",
+            web::delete()
+                .to(order_book_handler::cancel_all_orders)
                 .wrap(AuthMiddleware),
         )
         // Cancel an order
@@ -17,6 +60,20 @@ pub fn configure_order_book_routes() -> Scope {
                 .to(order_book_handler::cancel_order)
                 .wrap(AuthMiddleware),
         )
+        // Look up one of the user's orders by client order id
+        .route(
+            "/orders/client/{client_order_id}",
+            web::get()
+                .to(order_book_handler::get_order_by_client_id)
+                .wrap(AuthMiddleware),
+        )
+        // Cancel an order by client order id
+        .route(
+            "/orders/client/{client_order_id}/cancel",
+            web::post()
+                .to(order_book_handler::cancel_order_by_client_id)
+                .wrap(AuthMiddleware),
+        )
         // Get user's orders
         .route(
             "/orders/my",
@@ -24,19 +81,96 @@ pub fn configure_order_book_routes() -> Scope {
                 .to(order_book_handler::get_user_orders)
                 .wrap(AuthMiddleware),
         )
+        // Status history of an order (owner or admin)
+        .route(
+            "/orders/{order_id}/history",
+            web::get()
+                .to(order_book_handler::get_order_history)
+                .wrap(AuthMiddleware),
+        )
+        // Amend price or quantity of a resting order
+        .route(
+            "/orders/{order_id}",
+            web::put()
+                .to(order_book_handler::amend_order)
+                .wrap(RequireConsent)
+                .wrap(RequireMinimumAge)
+                .wrap(RateLimit(RateLimitBudget::Orders))
+                .wrap(AuthMiddleware),
+        )
         // Get order book for an event option
         .route(
             "/events/{event_id}/options/{option_id}",
-            web::get().to(order_book_handler::get_order_book),
+            web::get()
+                .to(order_book_handler::get_order_book)
+                .wrap(ETag)
+                .wrap(TenantEvent)
+                .wrap(RateLimit(RateLimitBudget::Reads)),
         )
         // Get market depth for an event option
         .route(
             "/events/{event_id}/options/{option_id}/depth",
-            web::get().to(order_book_handler::get_market_depth),
+            web::get()
+                .to(order_book_handler::get_market_depth)
+                .wrap(ETag)
+                .wrap(TenantEvent)
+                .wrap(RateLimit(RateLimitBudget::Reads)),
+        )
+        // Liquidity an event option gets from its complement under complement pricing
+        .route(
+            "/events/{event_id}/options/{option_id}/implied",
+            web::get()
+                .to(order_book_handler::get_implied_order_book)
+                .wrap(ETag)
+                .wrap(TenantEvent)
+                .wrap(RateLimit(RateLimitBudget::Reads)),
+        )
+        // Current trading halt for an event option, if any
+        .route(
+            "/events/{event_id}/options/{option_id}/halt",
+            web::get()
+                .to(order_book_handler::get_trading_status)
+                .wrap(TenantEvent)
+                .wrap(RateLimit(RateLimitBudget::Reads)),
+        )
+        // Halt trading in an event option (staff)
+        .route(
+            "/events/{event_id}/options/{option_id}/halt",
+            web::post()
+                .to(order_book_handler::halt_trading)
+                .wrap(RequirePermission(Permission::OperateMarkets))
+                .wrap(AuthMiddleware),
+        )
+        // Resume trading in an event option (staff)
+        .route(
+            "/events/{event_id}/options/{option_id}/resume",
+            web::post()
+                .to(order_book_handler::resume_trading)
+                .wrap(RequirePermission(Permission::OperateMarkets))
+                .wrap(AuthMiddleware),
+        )
+        // Start a call auction in an event option (staff)
+        .route(
+            "/events/{event_id}/options/{option_id}/auction",
+            web::post()
+                .to(order_book_handler::start_auction)
+                .wrap(RequirePermission(Permission::OperateMarkets))
+                .wrap(AuthMiddleware),
+        )
+        // Report a fill from the external venue gateway (staff)
+        .route(
+            "/venues/external/fills",
+            web::post()
+                .to(venue_handler::report_external_fill)
+                .wrap(RequirePermission(Permission::OperateMarkets))
+                .wrap(AuthMiddleware),
         )
         // Get trade history for an event option
         .route(
             "/events/{event_id}/options/{option_id}/trades",
-            web::get().to(order_book_handler::get_trade_history),
+            web::get()
+                .to(order_book_handler::get_trade_history)
+                .wrap(TenantEvent)
+                .wrap(RateLimit(RateLimitBudget::Reads)),
         )
 }
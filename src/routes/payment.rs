@@ -0,0 +1,27 @@
+use crate::handlers::payment_handler::{
+    create_deposit_intent, get_deposit_intent, list_deposit_intents, payment_webhook,
+};
+use crate::middleware::{age::RequireMinimumAge, auth::AuthMiddleware, idempotency::Idempotency};
+use actix_web::web;
+
+pub fn configure_payment_routes() -> actix_web::Scope {
+    web::scope("/payments")
+        .route(
+            "/deposits",
+            web::post()
+                .to(create_deposit_intent)
+                .wrap(Idempotency)
+                .wrap(RequireMinimumAge)
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/deposits",
+            web::get().to(list_deposit_intents).wrap(AuthMiddleware),
+        )
+        .route(
+            "/deposits/{intent_id}",
+            web::get().to(get_deposit_intent).wrap(AuthMiddleware),
+        )
+        // Called by the payment gateway, authenticated by its signature
+        .route("/webhook", web::post().to(payment_webhook))
+}
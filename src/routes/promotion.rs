@@ -0,0 +1,41 @@
+use crate::handlers::promotion_handler::{
+    create_promotion, get_promotion_report, list_active_promotions, list_promotions,
+    update_promotion,
+};
+use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::permission::RequirePermission;
+use crate::types::rbac::Permission;
+use actix_web::web;
+
+pub fn configure_promotion_routes() -> actix_web::Scope {
+    web::scope("/promotions")
+        .route("", web::get().to(list_active_promotions))
+        .route(
+            "",
+            web::post()
+                .to(create_promotion)
+                .wrap(RequirePermission(Permission::ManagePromotions))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/all",
+            web::get()
+                .to(list_promotions)
+                .wrap(RequirePermission(Permission::ManagePromotions))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/{promotion_id}",
+            web::put()
+                .to(update_promotion)
+                .wrap(RequirePermission(Permission::ManagePromotions))
+                .wrap(AuthMiddleware),
+        )
+        .route(
+            "/{promotion_id}/report",
+            web::get()
+                .to(get_promotion_report)
+                .wrap(RequirePermission(Permission::ManagePromotions))
+                .wrap(AuthMiddleware),
+        )
+}
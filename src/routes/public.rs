@@ -0,0 +1,76 @@
+use crate::handlers::{
+    candle_handler, event_handler, event_option_handler, market_handler, order_book_handler,
+};
+use crate::middleware::public_api::PublicApi;
+use crate::middleware::tenant::TenantEvent;
+use actix_web::body::BoxBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::{web, Error, Scope};
+
+/// Read-only market data for third parties. Needs no auth, and is rate limited
+/// per client IP and cacheable by CDNs; see `PublicApi`.
+pub fn configure_public_routes() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<BoxBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    web::scope("/public")
+        .wrap(PublicApi)
+        .route("/events", web::get().to(event_handler::list_events))
+        .route(
+            "/events/{event_id}",
+            web::get().to(event_handler::get_event).wrap(TenantEvent),
+        )
+        .route(
+            "/events/{event_id}/options",
+            web::get()
+                .to(event_option_handler::list_event_options)
+                .wrap(TenantEvent),
+        )
+        .route(
+            "/events/{event_id}/orderbooks",
+            web::get()
+                .to(order_book_handler::get_event_order_books)
+                .wrap(TenantEvent),
+        )
+        .route(
+            "/events/{event_id}/options/{option_id}/book",
+            web::get()
+                .to(order_book_handler::get_order_book)
+                .wrap(TenantEvent),
+        )
+        .route(
+            "/events/{event_id}/options/{option_id}/depth",
+            web::get()
+                .to(order_book_handler::get_market_depth)
+                .wrap(TenantEvent),
+        )
+        .route(
+            "/events/{event_id}/options/{option_id}/trades",
+            web::get()
+                .to(order_book_handler::get_trade_history)
+                .wrap(TenantEvent),
+        )
+        .route(
+            "/events/{event_id}/options/{option_id}/candles",
+            web::get()
+                .to(candle_handler::get_option_candles)
+                .wrap(TenantEvent),
+        )
+        .route(
+            "/events/{event_id}/options/{option_id}/stats",
+            web::get()
+                .to(candle_handler::get_option_market_stats)
+                .wrap(TenantEvent),
+        )
+        .route(
+            "/events/{event_id}/options/{option_id}/heatmap",
+            web::get()
+                .to(market_handler::get_depth_heatmap)
+                .wrap(TenantEvent),
+        )
+}
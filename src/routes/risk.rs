@@ -0,0 +1,12 @@
+use crate::handlers::risk_handler;
+use crate::middleware::auth::AuthMiddleware;
+use actix_web::web;
+
+pub fn configure_risk_routes() -> actix_web::Scope {
+    web::scope("/risk").route(
+        "/summary",
+        web::get()
+            .to(risk_handler::get_risk_summary)
+            .wrap(AuthMiddleware),
+    )
+}
@@ -0,0 +1,25 @@
+use crate::handlers::support_handler::{
+    create_ticket, get_my_ticket, list_my_tickets, post_my_ticket_message,
+};
+use crate::middleware::auth::AuthMiddleware;
+use actix_web::web;
+
+pub fn configure_support_routes() -> actix_web::Scope {
+    web::scope("/support")
+        .route(
+            "/tickets",
+            web::post().to(create_ticket).wrap(AuthMiddleware),
+        )
+        .route(
+            "/tickets",
+            web::get().to(list_my_tickets).wrap(AuthMiddleware),
+        )
+        .route(
+            "/tickets/{ticket_id}",
+            web::get().to(get_my_ticket).wrap(AuthMiddleware),
+        )
+        .route(
+            "/tickets/{ticket_id}/messages",
+            web::post().to(post_my_ticket_message).wrap(AuthMiddleware),
+        )
+}
@@ -1,7 +1,9 @@
 use crate::handlers::transaction_handler::{
     deposit_money, get_transaction_history, withdraw_money,
 };
-use crate::middleware::auth::AuthMiddleware;
+use crate::middleware::{
+    age::RequireMinimumAge, auth::AuthMiddleware, idempotency::Idempotency, two_factor::TwoFactor,
+};
 use actix_web::web;
 
 pub fn configure_transaction_routes() -> actix_web::Scope {
@@ -9,11 +11,19 @@ pub fn configure_transaction_routes() -> actix_web::Scope {
         web::scope("")
             .route(
                 "/deposit",
-                web::post().to(deposit_money).wrap(AuthMiddleware),
+                web::post()
+                    .to(deposit_money)
+                    .wrap(Idempotency)
+                    .wrap(RequireMinimumAge)
+                    .wrap(AuthMiddleware),
             )
             .route(
                 "/withdraw",
-                web::post().to(withdraw_money).wrap(AuthMiddleware),
+                web::post()
+                    .to(withdraw_money)
+                    .wrap(Idempotency)
+                    .wrap(TwoFactor)
+                    .wrap(AuthMiddleware),
             )
             .route(
                 "/transactions",
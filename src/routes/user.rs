@@ -1,4 +1,18 @@
+use crate::constants::config;
+use crate::handlers::api_key_handler::{create_api_key, list_api_keys, revoke_api_key};
+use crate::handlers::consent_handler::{accept_policy, get_my_consents};
+use crate::handlers::kyc_handler::{
+    get_my_kyc, set_date_of_birth, submit_kyc, upload_kyc_document,
+};
+use crate::handlers::margin_handler::{get_margin_account, set_margin_account};
+use crate::handlers::preferences_handler::{get_preferences, update_preferences};
+use crate::handlers::tier_handler::get_my_tier;
+use crate::handlers::two_factor_handler::{
+    confirm_two_factor, disable_two_factor, enroll_two_factor, get_two_factor_status,
+    regenerate_backup_codes,
+};
 use crate::handlers::user_handler::{get_current_user_details, get_user_details, list_users};
+use crate::handlers::wallet_handler::get_my_balance;
 use crate::middleware::auth::AuthMiddleware;
 use actix_web::web;
 
@@ -10,6 +24,81 @@ pub fn configure_user_routes() -> actix_web::Scope {
                 "/me",
                 web::get().to(get_current_user_details).wrap(AuthMiddleware),
             )
+            .route(
+                "/me/preferences",
+                web::get().to(get_preferences).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/preferences",
+                web::put().to(update_preferences).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/balance",
+                web::get().to(get_my_balance).wrap(AuthMiddleware),
+            )
+            .route("/me/tier", web::get().to(get_my_tier).wrap(AuthMiddleware))
+            .route(
+                "/me/margin",
+                web::get().to(get_margin_account).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/margin",
+                web::put().to(set_margin_account).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/api-keys",
+                web::get().to(list_api_keys).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/api-keys",
+                web::post().to(create_api_key).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/api-keys/{key_id}",
+                web::delete().to(revoke_api_key).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/consents",
+                web::get().to(get_my_consents).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/consents",
+                web::post().to(accept_policy).wrap(AuthMiddleware),
+            )
+            .route("/me/kyc", web::get().to(get_my_kyc).wrap(AuthMiddleware))
+            .service(
+                web::resource("/me/kyc/documents")
+                    .app_data(web::PayloadConfig::new(config::get_kyc_max_document_bytes()))
+                    .route(web::post().to(upload_kyc_document).wrap(AuthMiddleware)),
+            )
+            .route(
+                "/me/kyc/date-of-birth",
+                web::put().to(set_date_of_birth).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/kyc/submit",
+                web::post().to(submit_kyc).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/2fa",
+                web::get().to(get_two_factor_status).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/2fa/enroll",
+                web::post().to(enroll_two_factor).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/2fa/confirm",
+                web::post().to(confirm_two_factor).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/2fa/backup-codes",
+                web::post().to(regenerate_backup_codes).wrap(AuthMiddleware),
+            )
+            .route(
+                "/me/2fa/disable",
+                web::post().to(disable_two_factor).wrap(AuthMiddleware),
+            )
             .route("/{user_id}", web::get().to(get_user_details)),
     )
 }
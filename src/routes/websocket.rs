@@ -1,27 +1,131 @@
-use crate::utils::user::extract_user_id_from_headers;
+use crate::utils::{auth_session, user::extract_user_id_from_headers};
+use crate::websocket::firehose::{ConnectionSlot, FirehoseSession};
 use crate::websocket::{server::WebSocketServer, session::WebSocketSession};
 use actix::Addr;
-use actix_web::{web, HttpRequest, HttpResponse, Result};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Result};
 use actix_web_actors::ws;
+use deadpool_redis::Pool;
+use entity::{tenants, users};
 use log::info;
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde_json::json;
 
 pub async fn websocket_route(
     req: HttpRequest,
     stream: web::Payload,
     ws_server: web::Data<Addr<WebSocketServer>>,
+    redis_pool: web::Data<Pool>,
+    db: web::Data<DatabaseConnection>,
 ) -> Result<HttpResponse, actix_web::Error> {
     info!("WebSocket connection attempt");
 
-    // Try to extract user ID from token (optional for WebSocket)
-    let user_id = extract_user_id_from_headers(&req);
+    // Connections are served as the tenant whose site they were opened on
+    let tenant_id = req
+        .extensions()
+        .get::<tenants::Model>()
+        .map(|t| t.id)
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown tenant"))?;
 
-    let session = WebSocketSession::new(ws_server.get_ref().clone(), user_id);
+    // Try to extract user ID from token (optional for WebSocket). A token
+    // whose session was revoked connects unauthenticated.
+    let (user_id, auth_session) = match extract_user_id_from_headers(&req) {
+        Some((user_id, session_id)) => {
+            match auth_session::is_active(&redis_pool, &session_id).await {
+                Ok(true) => (Some(user_id), Some(session_id)),
+                Ok(false) => {
+                    info!("WebSocket token for user {} has a revoked session", user_id);
+                    (None, None)
+                }
+                Err(e) => {
+                    log::error!("Failed to check WebSocket session: {}", e);
+                    (None, None)
+                }
+            }
+        }
+        None => (None, None),
+    };
+
+    let user = match user_id {
+        Some(user_id) => users::Entity::find_by_id(user_id)
+            .one(db.get_ref())
+            .await
+            .map_err(|e| {
+                log::error!("Database error: {}", e);
+                actix_web::error::ErrorInternalServerError("Database error occurred")
+            })?,
+        None => None,
+    };
+
+    // A user of another tenant's site connects unauthenticated; as over
+    // HTTP, admins may connect on any
+    let (user_id, auth_session) = match &user {
+        Some(user) if user.tenant_id != tenant_id && user.role != "admin" => {
+            info!(
+                "WebSocket token for user {} is for another tenant's site",
+                user.id
+            );
+            (None, None)
+        }
+        _ => (user_id, auth_session),
+    };
+
+    // Admin channels are only offered to active admins
+    let is_admin = user_id.is_some()
+        && user
+            .as_ref()
+            .is_some_and(|user| user.is_active && user.role == "admin");
+
+    let session = WebSocketSession::new(
+        ws_server.get_ref().clone(),
+        redis_pool,
+        user_id,
+        auth_session,
+        tenant_id,
+        is_admin,
+    );
     let resp = ws::start(session, &req, stream)?;
 
     info!("WebSocket connection established for user: {:?}", user_id);
     Ok(resp)
 }
 
+/// Unauthenticated stream of every public trade and ticker update of the
+/// site's tenant, capped at `FIREHOSE_MAX_CONNECTIONS_PER_IP` connections
+/// per client IP
+pub async fn firehose_route(
+    req: HttpRequest,
+    stream: web::Payload,
+) -> Result<HttpResponse, actix_web::Error> {
+    let tenant_id = req
+        .extensions()
+        .get::<tenants::Model>()
+        .map(|t| t.id)
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown tenant"))?;
+
+    let client = req
+        .connection_info()
+        .realip_remote_addr()
+        .unwrap_or("unknown")
+        .to_string();
+
+    let slot = match ConnectionSlot::acquire(&client) {
+        Some(slot) => slot,
+        None => {
+            log::warn!("Firehose connection limit reached by {}", client);
+            return Ok(HttpResponse::TooManyRequests().json(json!({
+                "success": false,
+                "message": "Too many firehose connections from this address"
+            })));
+        }
+    };
+
+    let resp = ws::start(FirehoseSession::new(slot, tenant_id), &req, stream)?;
+    info!("Firehose connection established for {}", client);
+    Ok(resp)
+}
+
 pub fn configure_websocket_routes() -> actix_web::Scope {
-    web::scope("/ws").route("/connect", web::get().to(websocket_route))
+    web::scope("/ws")
+        .route("/connect", web::get().to(websocket_route))
+        .route("/firehose", web::get().to(firehose_route))
 }
@@ -0,0 +1,108 @@
+use crate::constants::config;
+use crate::types::settings::SettingKey;
+use actix_web::web;
+use entity::settings;
+use lazy_static::lazy_static;
+use sea_orm::{prelude::Decimal, DatabaseConnection, EntityTrait};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+lazy_static! {
+    /// Every stored setting, by tenant (`None` for deployment-wide) and key
+    static ref SETTINGS: RwLock<HashMap<(Option<i32>, SettingKey), settings::Model>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Load every setting, replacing what was held. Rows with keys this build
+/// doesn't know are skipped.
+pub async fn reload(db: &DatabaseConnection) -> Result<(), String> {
+    let rows = settings::Entity::find()
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load settings: {}", e))?;
+
+    *SETTINGS.write().unwrap() = rows
+        .into_iter()
+        .filter_map(|row| SettingKey::parse(&row.key).map(|key| ((row.tenant_id, key), row)))
+        .collect();
+    Ok(())
+}
+
+/// Start a background task that reloads the settings periodically
+pub fn start_settings_refresh(db: web::Data<DatabaseConnection>) {
+    log::info!("Starting settings refresh");
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            config::get_settings_refresh_interval_seconds().max(1),
+        ));
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = reload(db.get_ref()).await {
+                log::error!("Failed to refresh settings: {}", e);
+            }
+        }
+    });
+}
+
+/// Record a setting changed on this instance
+pub fn upsert(key: SettingKey, row: settings::Model) {
+    SETTINGS.write().unwrap().insert((row.tenant_id, key), row);
+}
+
+/// Forget a setting removed on this instance
+pub fn remove(tenant_id: Option<i32>, key: SettingKey) {
+    SETTINGS.write().unwrap().remove(&(tenant_id, key));
+}
+
+/// The stored setting for exactly this tenant, or deployment-wide for `None`
+pub fn stored(tenant_id: Option<i32>, key: SettingKey) -> Option<settings::Model> {
+    SETTINGS.read().unwrap().get(&(tenant_id, key)).cloned()
+}
+
+/// The stored setting that applies to a tenant: its own if it may override
+/// the key and does, else the deployment-wide one
+pub fn effective(tenant_id: Option<i32>, key: SettingKey) -> Option<settings::Model> {
+    let settings = SETTINGS.read().unwrap();
+    tenant_id
+        .filter(|_| key.per_tenant())
+        .and_then(|tenant_id| settings.get(&(Some(tenant_id), key)))
+        .or_else(|| settings.get(&(None, key)))
+        .cloned()
+}
+
+fn value(tenant_id: Option<i32>, key: SettingKey) -> String {
+    effective(tenant_id, key)
+        .map(|row| row.value)
+        .unwrap_or_else(|| key.default_value())
+}
+
+/// An integer setting as it applies to a tenant
+pub fn integer(tenant_id: Option<i32>, key: SettingKey) -> i64 {
+    value(tenant_id, key)
+        .parse()
+        .or_else(|_| key.default_value().parse())
+        .unwrap_or(0)
+}
+
+/// A decimal setting as it applies to a tenant
+pub fn decimal(tenant_id: Option<i32>, key: SettingKey) -> Decimal {
+    Decimal::from_str(&value(tenant_id, key))
+        .or_else(|_| Decimal::from_str(&key.default_value()))
+        .unwrap_or(Decimal::ZERO)
+}
+
+/// Trading fee of a tenant's trades, in basis points
+pub fn trading_fee_bps(tenant_id: Option<i32>) -> u32 {
+    let bps = integer(tenant_id, SettingKey::TradingFeeBps).max(0);
+    u32::try_from(bps)
+        .unwrap_or(u32::MAX)
+        .min(config::get_trading_fee_bps())
+}
+
+/// Largest short position a margin account may hold in one option
+pub fn max_short_position() -> i32 {
+    i32::try_from(integer(None, SettingKey::MaxShortPosition)).unwrap_or(i32::MAX)
+}
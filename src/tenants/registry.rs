@@ -0,0 +1,156 @@
+use crate::constants::config;
+use actix_web::web;
+use entity::{event_options, events, tenants};
+use lazy_static::lazy_static;
+use sea_orm::{DatabaseConnection, EntityTrait, QuerySelect};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+lazy_static! {
+    /// Every tenant, active or not, by id
+    static ref TENANTS: RwLock<HashMap<i32, tenants::Model>> = RwLock::new(HashMap::new());
+    /// Tenant each event belongs to. Events never change tenant, so entries
+    /// only go stale by missing events created through another instance.
+    static ref EVENT_TENANTS: RwLock<HashMap<i32, i32>> = RwLock::new(HashMap::new());
+}
+
+/// Load every tenant and the tenant of every event, replacing what was held
+pub async fn reload(db: &DatabaseConnection) -> Result<(), String> {
+    let tenants = tenants::Entity::find()
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load tenants: {}", e))?;
+    let event_tenants: Vec<(i32, i32)> = events::Entity::find()
+        .select_only()
+        .column(events::Column::Id)
+        .column(events::Column::TenantId)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to load event tenants: {}", e))?;
+
+    *TENANTS.write().unwrap() = tenants.into_iter().map(|t| (t.id, t)).collect();
+    *EVENT_TENANTS.write().unwrap() = event_tenants.into_iter().collect();
+    Ok(())
+}
+
+/// Start a background task that reloads the registry periodically
+pub fn start_tenant_refresh(db: web::Data<DatabaseConnection>) {
+    log::info!("Starting tenant registry refresh");
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            config::get_tenant_refresh_interval_seconds().max(1),
+        ));
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = reload(db.get_ref()).await {
+                log::error!("Failed to refresh tenant registry: {}", e);
+            }
+        }
+    });
+}
+
+/// Record a tenant created or changed on this instance
+pub fn upsert(tenant: tenants::Model) {
+    TENANTS.write().unwrap().insert(tenant.id, tenant);
+}
+
+/// Record the tenant of an event created on this instance
+pub fn record_event(event_id: i32, tenant_id: i32) {
+    EVENT_TENANTS.write().unwrap().insert(event_id, tenant_id);
+}
+
+/// The tenant requests are served as when they name no other
+pub fn default_tenant() -> Option<tenants::Model> {
+    let slug = config::get_default_tenant_slug();
+    by_slug(&slug)
+}
+
+/// An active tenant by slug
+pub fn by_slug(slug: &str) -> Option<tenants::Model> {
+    TENANTS
+        .read()
+        .unwrap()
+        .values()
+        .find(|t| t.is_active && t.slug.eq_ignore_ascii_case(slug))
+        .cloned()
+}
+
+/// An active tenant by the domain its site is served on
+pub fn by_domain(host: &str) -> Option<tenants::Model> {
+    TENANTS
+        .read()
+        .unwrap()
+        .values()
+        .find(|t| {
+            t.is_active
+                && t.domain
+                    .as_deref()
+                    .is_some_and(|domain| domain.eq_ignore_ascii_case(host))
+        })
+        .cloned()
+}
+
+/// Whether any active tenant lists `origin` among its CORS origins
+pub fn allows_origin(origin: &str) -> bool {
+    TENANTS
+        .read()
+        .unwrap()
+        .values()
+        .filter(|t| t.is_active)
+        .any(|t| split_origins(&t.cors_origins).any(|o| o == origin))
+}
+
+/// Origins as stored in `tenants.cors_origins`
+pub fn split_origins(origins: &str) -> impl Iterator<Item = &str> {
+    origins.split(',').map(str::trim).filter(|o| !o.is_empty())
+}
+
+/// The tenant of an event, if the registry knows the event
+pub fn event_tenant(event_id: i32) -> Option<i32> {
+    EVENT_TENANTS.read().unwrap().get(&event_id).copied()
+}
+
+/// The tenant of an event, looking it up if the registry doesn't know it yet.
+/// Returns `Ok(None)` if the event doesn't exist.
+pub async fn resolve_event_tenant(
+    db: &DatabaseConnection,
+    event_id: i32,
+) -> Result<Option<i32>, String> {
+    if let Some(tenant_id) = event_tenant(event_id) {
+        return Ok(Some(tenant_id));
+    }
+
+    let tenant_id: Option<i32> = events::Entity::find_by_id(event_id)
+        .select_only()
+        .column(events::Column::TenantId)
+        .into_tuple()
+        .one(db)
+        .await
+        .map_err(|e| format!("Failed to look up event tenant: {}", e))?;
+    if let Some(tenant_id) = tenant_id {
+        record_event(event_id, tenant_id);
+    }
+    Ok(tenant_id)
+}
+
+/// The tenant of the event an option belongs to. Returns `Ok(None)` if the
+/// option doesn't exist.
+pub async fn resolve_option_tenant(
+    db: &DatabaseConnection,
+    option_id: i32,
+) -> Result<Option<i32>, String> {
+    let event_id: Option<i32> = event_options::Entity::find_by_id(option_id)
+        .select_only()
+        .column(event_options::Column::EventId)
+        .into_tuple()
+        .one(db)
+        .await
+        .map_err(|e| format!("Failed to look up option: {}", e))?;
+    match event_id {
+        Some(event_id) => resolve_event_tenant(db, event_id).await,
+        None => Ok(None),
+    }
+}
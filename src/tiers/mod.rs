@@ -0,0 +1,2 @@
+pub mod recalculation;
+pub mod service;
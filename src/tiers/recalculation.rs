@@ -0,0 +1,57 @@
+use super::service::TierService;
+use crate::constants::config;
+use crate::types::websocket::{OpsEventKind, OpsSeverity};
+use crate::websocket::ops;
+use actix_web::web;
+use chrono::{NaiveDate, Timelike, Utc};
+use sea_orm::DatabaseConnection;
+use serde_json::json;
+
+/// How often the job checks whether the nightly run is due
+const TIER_CHECK_INTERVAL_SECONDS: u64 = 300;
+
+/// Start a background task that recalculates every user's tier once a day
+pub fn start_tier_recalculation_job(db: web::Data<DatabaseConnection>) {
+    log::info!(
+        "Starting tier recalculation job, running after {:02}:00 UTC",
+        config::get_tier_recalculation_hour_utc()
+    );
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            TIER_CHECK_INTERVAL_SECONDS,
+        ));
+        let mut last_run: Option<NaiveDate> = None;
+
+        loop {
+            interval.tick().await;
+
+            let now = Utc::now();
+            if now.hour() < config::get_tier_recalculation_hour_utc()
+                || last_run == Some(now.date_naive())
+            {
+                continue;
+            }
+
+            let tier_service = TierService::new(db.get_ref().clone());
+            match tier_service.recalculate_all(now).await {
+                Ok(changed) => {
+                    log::info!(
+                        "Tier recalculation finished, {} users changed tier",
+                        changed
+                    );
+                    last_run = Some(now.date_naive());
+                }
+                Err(e) => {
+                    log::error!("Tier recalculation failed: {}", e);
+                    ops::publish(
+                        OpsEventKind::JobFailed,
+                        OpsSeverity::Warning,
+                        "Nightly tier recalculation failed",
+                        json!({ "job": "tier_recalculation", "error": e }),
+                    );
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,199 @@
+use crate::constants::config;
+use crate::types::tier::{NextTierResponse, Tier, TierBenefits, TierResponse};
+use chrono::{DateTime, Duration, Utc};
+use entity::{trades, user_tiers, users, wallets};
+use sea_orm::{
+    prelude::{Decimal, Expr},
+    sea_query::OnConflict,
+    ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect, Set,
+};
+use std::collections::HashMap;
+
+/// Resolves a user's tier and the benefits it grants. The fee engine, order size
+/// limits and withdrawals all go through here so they agree on a user's tier.
+pub struct TierService {
+    db: DatabaseConnection,
+}
+
+impl TierService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// The user's tier as of the last recalculation; Standard until the first run
+    pub async fn resolve(&self, user_id: i32) -> Result<Tier, String> {
+        let tier = user_tiers::Entity::find_by_id(user_id)
+            .one(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load user tier: {}", e))?;
+
+        Ok(tier.map(|t| Tier::parse(&t.tier)).unwrap_or(Tier::Standard))
+    }
+
+    pub async fn benefits(&self, user_id: i32) -> Result<TierBenefits, String> {
+        Ok(self.resolve(user_id).await?.benefits())
+    }
+
+    /// Benefits for several users at once, e.g. both sides of a batch of trades
+    pub async fn benefits_for_users(
+        &self,
+        user_ids: &[i32],
+    ) -> Result<HashMap<i32, TierBenefits>, String> {
+        let stored: HashMap<i32, Tier> = user_tiers::Entity::find()
+            .filter(user_tiers::Column::UserId.is_in(user_ids.to_vec()))
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load user tiers: {}", e))?
+            .into_iter()
+            .map(|t| (t.user_id, Tier::parse(&t.tier)))
+            .collect();
+
+        Ok(user_ids
+            .iter()
+            .map(|id| {
+                let tier = stored.get(id).copied().unwrap_or(Tier::Standard);
+                (*id, tier.benefits())
+            })
+            .collect())
+    }
+
+    pub async fn get_tier(&self, user_id: i32) -> Result<TierResponse, String> {
+        let stored = user_tiers::Entity::find_by_id(user_id)
+            .one(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load user tier: {}", e))?;
+
+        let response = match stored {
+            Some(stored) => {
+                let tier = Tier::parse(&stored.tier);
+                TierResponse {
+                    tier,
+                    benefits: tier.benefits(),
+                    trading_volume: stored.trading_volume,
+                    wallet_balance: stored.wallet_balance,
+                    calculated_at: Some(stored.calculated_at.into()),
+                    next_tier: NextTierResponse::after(tier),
+                }
+            }
+            None => TierResponse {
+                tier: Tier::Standard,
+                benefits: Tier::Standard.benefits(),
+                trading_volume: Decimal::ZERO,
+                wallet_balance: Decimal::ZERO,
+                calculated_at: None,
+                next_tier: NextTierResponse::after(Tier::Standard),
+            },
+        };
+
+        Ok(response)
+    }
+
+    /// Recompute every active user's tier from their trailing trading volume and
+    /// current wallet balance, both in the base currency that tier thresholds
+    /// are set in. Returns how many users changed tier.
+    pub async fn recalculate_all(&self, now: DateTime<Utc>) -> Result<usize, String> {
+        let since = now - Duration::days(config::get_tier_volume_window_days());
+        let volumes = self.trading_volumes(since, now).await?;
+
+        let previous: HashMap<i32, String> = user_tiers::Entity::find()
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load user tiers: {}", e))?
+            .into_iter()
+            .map(|t| (t.user_id, t.tier))
+            .collect();
+
+        let balances: HashMap<i32, Decimal> = wallets::Entity::find()
+            .filter(wallets::Column::Currency.eq(config::get_base_currency()))
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load wallets: {}", e))?
+            .into_iter()
+            .map(|w| (w.user_id, w.balance))
+            .collect();
+
+        let active_users = users::Entity::find()
+            .filter(users::Column::IsActive.eq(true))
+            .all(&self.db)
+            .await
+            .map_err(|e| format!("Failed to load users: {}", e))?;
+
+        let mut changed = 0;
+        for user in active_users {
+            let volume = volumes.get(&user.id).copied().unwrap_or(Decimal::ZERO);
+            let balance = balances.get(&user.id).copied().unwrap_or(Decimal::ZERO);
+            let tier = Tier::for_metrics(volume, balance);
+
+            let was = previous
+                .get(&user.id)
+                .map(|t| Tier::parse(t))
+                .unwrap_or(Tier::Standard);
+            if was != tier {
+                log::info!(
+                    "User {} moved from {} to {} tier",
+                    user.id,
+                    was.as_str(),
+                    tier.as_str()
+                );
+                changed += 1;
+            }
+
+            let row = user_tiers::ActiveModel {
+                user_id: Set(user.id),
+                tier: Set(tier.as_str().to_string()),
+                trading_volume: Set(volume),
+                wallet_balance: Set(balance),
+                calculated_at: Set(now.into()),
+            };
+            user_tiers::Entity::insert(row)
+                .on_conflict(
+                    OnConflict::column(user_tiers::Column::UserId)
+                        .update_columns([
+                            user_tiers::Column::Tier,
+                            user_tiers::Column::TradingVolume,
+                            user_tiers::Column::WalletBalance,
+                            user_tiers::Column::CalculatedAt,
+                        ])
+                        .to_owned(),
+                )
+                .exec(&self.db)
+                .await
+                .map_err(|e| format!("Failed to save tier for user {}: {}", user.id, e))?;
+        }
+
+        Ok(changed)
+    }
+
+    /// Traded notional per user over the window, counting both buys and sells
+    async fn trading_volumes(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<HashMap<i32, Decimal>, String> {
+        let mut volumes: HashMap<i32, Decimal> = HashMap::new();
+
+        for side in [trades::Column::BuyerId, trades::Column::SellerId] {
+            let totals: Vec<(i32, Decimal)> = trades::Entity::find()
+                .select_only()
+                .column(side)
+                .column_as(
+                    Expr::cust("COALESCE(SUM(trades.total_amount), 0)"),
+                    "volume",
+                )
+                .filter(trades::Column::Currency.eq(config::get_base_currency()))
+                .filter(trades::Column::Timestamp.gte(since))
+                .filter(trades::Column::Timestamp.lt(until))
+                .group_by(side)
+                .into_tuple()
+                .all(&self.db)
+                .await
+                .map_err(|e| format!("Failed to aggregate trading volume: {}", e))?;
+
+            for (user_id, volume) in totals {
+                *volumes.entry(user_id).or_insert(Decimal::ZERO) += volume;
+            }
+        }
+
+        Ok(volumes)
+    }
+}
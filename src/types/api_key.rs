@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use entity::api_keys;
+use serde::{Deserialize, Serialize};
+
+/// What a request signed with an API key may do
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyScope {
+    /// `GET` requests
+    Read,
+    /// Placing, amending and cancelling orders
+    Trade,
+    /// Withdrawing from the wallet
+    Withdraw,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::Read => "read",
+            ApiKeyScope::Trade => "trade",
+            ApiKeyScope::Withdraw => "withdraw",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "read" => Some(ApiKeyScope::Read),
+            "trade" => Some(ApiKeyScope::Trade),
+            "withdraw" => Some(ApiKeyScope::Withdraw),
+            _ => None,
+        }
+    }
+
+    /// Scopes as stored in `api_keys.scopes`
+    pub fn join(scopes: &[ApiKeyScope]) -> String {
+        scopes
+            .iter()
+            .map(|scope| scope.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Scopes stored in `api_keys.scopes`, skipping any that are unknown
+    pub fn split(scopes: &str) -> Vec<ApiKeyScope> {
+        scopes.split(',').filter_map(ApiKeyScope::parse).collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub label: String,
+    pub scopes: Vec<ApiKeyScope>,
+    /// Signed requests the key may make per minute; defaults to
+    /// `API_KEY_DEFAULT_RATE_LIMIT_PER_MINUTE`
+    pub rate_limit_per_minute: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiKeyResponse {
+    pub key_id: String,
+    pub label: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub rate_limit_per_minute: i32,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<api_keys::Model> for ApiKeyResponse {
+    fn from(key: api_keys::Model) -> Self {
+        Self {
+            scopes: ApiKeyScope::split(&key.scopes),
+            key_id: key.key_id,
+            label: key.label,
+            rate_limit_per_minute: key.rate_limit_per_minute,
+            last_used_at: key.last_used_at.map(|t| t.with_timezone(&Utc)),
+            revoked_at: key.revoked_at.map(|t| t.with_timezone(&Utc)),
+            created_at: key.created_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// A newly created key. The secret is only ever shown here.
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKeyResponse {
+    #[serde(flatten)]
+    pub key: ApiKeyResponse,
+    pub secret: String,
+}
@@ -0,0 +1,160 @@
+use crate::utils::pagination::PaginationQuery;
+use chrono::{DateTime, Utc};
+use entity::audit_logs;
+use serde::{Deserialize, Serialize};
+
+/// A privileged change recorded in the audit log
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AuditAction {
+    #[serde(rename = "event.update")]
+    EventUpdate,
+    #[serde(rename = "event.settle")]
+    EventSettle,
+    #[serde(rename = "event.void")]
+    EventVoid,
+    #[serde(rename = "oracle.update")]
+    OracleUpdate,
+    #[serde(rename = "settlement_rules.update")]
+    SettlementRulesUpdate,
+    #[serde(rename = "balance.adjust")]
+    BalanceAdjust,
+    #[serde(rename = "user.ban")]
+    UserBan,
+    #[serde(rename = "user.unban")]
+    UserUnban,
+    #[serde(rename = "user.import")]
+    UserImport,
+    #[serde(rename = "setting.update")]
+    SettingUpdate,
+    #[serde(rename = "maintenance.update")]
+    MaintenanceUpdate,
+    #[serde(rename = "kyc.approve")]
+    KycApprove,
+    #[serde(rename = "kyc.reject")]
+    KycReject,
+    #[serde(rename = "age.override")]
+    AgeOverride,
+    #[serde(rename = "withdrawal.approve")]
+    WithdrawalApprove,
+    #[serde(rename = "withdrawal.reject")]
+    WithdrawalReject,
+    #[serde(rename = "ticket.update")]
+    TicketUpdate,
+    #[serde(rename = "market_maker.update")]
+    MarketMakerUpdate,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::EventUpdate => "event.update",
+            AuditAction::EventSettle => "event.settle",
+            AuditAction::EventVoid => "event.void",
+            AuditAction::OracleUpdate => "oracle.update",
+            AuditAction::SettlementRulesUpdate => "settlement_rules.update",
+            AuditAction::BalanceAdjust => "balance.adjust",
+            AuditAction::UserBan => "user.ban",
+            AuditAction::UserUnban => "user.unban",
+            AuditAction::UserImport => "user.import",
+            AuditAction::SettingUpdate => "setting.update",
+            AuditAction::MaintenanceUpdate => "maintenance.update",
+            AuditAction::KycApprove => "kyc.approve",
+            AuditAction::KycReject => "kyc.reject",
+            AuditAction::AgeOverride => "age.override",
+            AuditAction::WithdrawalApprove => "withdrawal.approve",
+            AuditAction::WithdrawalReject => "withdrawal.reject",
+            AuditAction::TicketUpdate => "ticket.update",
+            AuditAction::MarketMakerUpdate => "market_maker.update",
+        }
+    }
+
+    /// What the action changes
+    pub fn target_type(&self) -> AuditTarget {
+        match self {
+            AuditAction::EventUpdate
+            | AuditAction::EventSettle
+            | AuditAction::EventVoid
+            | AuditAction::OracleUpdate
+            | AuditAction::SettlementRulesUpdate
+            | AuditAction::MarketMakerUpdate => AuditTarget::Event,
+            AuditAction::BalanceAdjust
+            | AuditAction::UserBan
+            | AuditAction::UserUnban
+            | AuditAction::UserImport
+            | AuditAction::KycApprove
+            | AuditAction::KycReject
+            | AuditAction::AgeOverride => AuditTarget::User,
+            AuditAction::SettingUpdate | AuditAction::MaintenanceUpdate => AuditTarget::Setting,
+            AuditAction::WithdrawalApprove | AuditAction::WithdrawalReject => {
+                AuditTarget::Transaction
+            }
+            AuditAction::TicketUpdate => AuditTarget::Ticket,
+        }
+    }
+}
+
+/// The kind of record an audited action changes
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditTarget {
+    Event,
+    User,
+    Setting,
+    Transaction,
+    Ticket,
+}
+
+impl AuditTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditTarget::Event => "event",
+            AuditTarget::User => "user",
+            AuditTarget::Setting => "setting",
+            AuditTarget::Transaction => "transaction",
+            AuditTarget::Ticket => "ticket",
+        }
+    }
+}
+
+/// Audit log filters. `from` and `to` bound when the action was taken.
+#[derive(Debug, Deserialize)]
+pub struct ListAuditLogsQuery {
+    pub actor_id: Option<i32>,
+    pub action: Option<AuditAction>,
+    pub target_type: Option<AuditTarget>,
+    pub target_id: Option<i32>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub pagination: PaginationQuery,
+}
+
+/// Who changed what, and how
+#[derive(Debug, Serialize)]
+pub struct AuditLogResponse {
+    pub id: i64,
+    pub actor_id: i32,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: i32,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<audit_logs::Model> for AuditLogResponse {
+    fn from(entry: audit_logs::Model) -> Self {
+        Self {
+            id: entry.id,
+            actor_id: entry.actor_id,
+            action: entry.action,
+            target_type: entry.target_type,
+            target_id: entry.target_id,
+            before: entry.before,
+            after: entry.after,
+            ip: entry.ip,
+            created_at: entry.created_at.with_timezone(&Utc),
+        }
+    }
+}
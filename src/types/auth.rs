@@ -7,6 +7,8 @@ pub struct RegisterRequest {
     pub phone: Option<String>,
     pub password: String,
     pub full_name: Option<String>,
+    /// Checked against the tenant's minimum age before deposits and trading
+    pub date_of_birth: Option<chrono::NaiveDate>,
     #[serde(default = "default_role")]
     pub role: String,
 }
@@ -20,3 +22,14 @@ pub struct LoginRequest {
     pub email: String,
     pub password: String,
 }
+
+#[derive(Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
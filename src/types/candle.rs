@@ -0,0 +1,137 @@
+use chrono::{DateTime, TimeZone, Utc};
+use entity::candles;
+use sea_orm::prelude::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Width of an OHLCV bar. Bars are aligned to the Unix epoch in UTC.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum CandleInterval {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+impl CandleInterval {
+    pub const ALL: [CandleInterval; 4] = [
+        CandleInterval::OneMinute,
+        CandleInterval::FiveMinutes,
+        CandleInterval::OneHour,
+        CandleInterval::OneDay,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CandleInterval::OneMinute => "1m",
+            CandleInterval::FiveMinutes => "5m",
+            CandleInterval::OneHour => "1h",
+            CandleInterval::OneDay => "1d",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "1m" => Some(CandleInterval::OneMinute),
+            "5m" => Some(CandleInterval::FiveMinutes),
+            "1h" => Some(CandleInterval::OneHour),
+            "1d" => Some(CandleInterval::OneDay),
+            _ => None,
+        }
+    }
+
+    pub fn seconds(&self) -> i64 {
+        match self {
+            CandleInterval::OneMinute => 60,
+            CandleInterval::FiveMinutes => 5 * 60,
+            CandleInterval::OneHour => 60 * 60,
+            CandleInterval::OneDay => 24 * 60 * 60,
+        }
+    }
+
+    /// Start of the bar that `at` falls in
+    pub fn bucket_start(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let seconds = at.timestamp();
+        let start = seconds - seconds.rem_euclid(self.seconds());
+        Utc.timestamp_opt(start, 0).single().unwrap_or(at)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CandlesQuery {
+    /// One of 1m, 5m, 1h or 1d; defaults to 1m
+    pub interval: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CandleResponse {
+    pub event_id: i32,
+    pub option_id: i32,
+    pub interval: String,
+    pub open_time: DateTime<Utc>,
+    pub close_time: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    /// Shares traded
+    pub volume: i64,
+    /// Traded notional
+    pub notional: Decimal,
+    pub trade_count: i32,
+}
+
+impl From<candles::Model> for CandleResponse {
+    fn from(candle: candles::Model) -> Self {
+        let open_time: DateTime<Utc> = candle.open_time.into();
+        let width = CandleInterval::parse(&candle.interval)
+            .map(|interval| interval.seconds())
+            .unwrap_or_default();
+
+        Self {
+            event_id: candle.event_id,
+            option_id: candle.option_id,
+            interval: candle.interval,
+            open_time,
+            close_time: open_time + chrono::Duration::seconds(width),
+            open: candle.open,
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+            volume: candle.volume,
+            notional: candle.notional,
+            trade_count: candle.trade_count,
+        }
+    }
+}
+
+/// Rolling 24h trading summary for one option, built from its 5m bars
+#[derive(Debug, Clone, Serialize)]
+pub struct OptionMarketStats {
+    pub event_id: i32,
+    pub option_id: i32,
+    /// First traded price in the window; `None` if nothing traded
+    pub open: Option<Decimal>,
+    pub high: Option<Decimal>,
+    pub low: Option<Decimal>,
+    /// Most recent traded price, even if it is older than the window
+    pub last: Option<Decimal>,
+    /// `last` minus `open`
+    pub change: Option<Decimal>,
+    pub change_percent: Option<Decimal>,
+    /// Shares traded in the window
+    pub volume: i64,
+    /// Traded notional in the window
+    pub notional: Decimal,
+    pub trade_count: i64,
+    pub open_buy_orders: usize,
+    pub open_sell_orders: usize,
+    pub window_start: DateTime<Utc>,
+    pub timestamp: DateTime<Utc>,
+}
@@ -0,0 +1,49 @@
+use crate::types::settings::SettingKey;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A policy users must accept, in its current version, before they can trade
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyDocument {
+    Terms,
+    PrivacyPolicy,
+}
+
+impl PolicyDocument {
+    pub const ALL: [PolicyDocument; 2] = [PolicyDocument::Terms, PolicyDocument::PrivacyPolicy];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyDocument::Terms => "terms",
+            PolicyDocument::PrivacyPolicy => "privacy_policy",
+        }
+    }
+
+    /// The setting holding the document's current version
+    pub fn version_setting(&self) -> SettingKey {
+        match self {
+            PolicyDocument::Terms => SettingKey::TermsVersion,
+            PolicyDocument::PrivacyPolicy => SettingKey::PrivacyPolicyVersion,
+        }
+    }
+}
+
+/// Accept a policy. `version` must be its current version, so a user can't
+/// accept a text they weren't shown.
+#[derive(Debug, Deserialize)]
+pub struct AcceptPolicyRequest {
+    pub document: PolicyDocument,
+    pub version: i32,
+}
+
+/// Where a user stands with one policy
+#[derive(Debug, Serialize)]
+pub struct ConsentStatusResponse {
+    pub document: PolicyDocument,
+    pub current_version: i32,
+    pub accepted_version: Option<i32>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    /// Whether the user has accepted the current version
+    pub up_to_date: bool,
+}
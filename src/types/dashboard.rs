@@ -0,0 +1,45 @@
+use crate::types::transaction::TransactionResponse;
+use sea_orm::prelude::Decimal;
+use serde::Serialize;
+
+/// Balance and position value in one currency
+#[derive(Serialize)]
+pub struct WalletSummary {
+    pub currency: String,
+    pub wallet_balance: Decimal,
+    pub positions_value: Decimal,
+    pub total_equity: Decimal,
+}
+
+#[derive(Serialize)]
+pub struct DashboardPosition {
+    pub event_id: i32,
+    pub option_id: i32,
+    pub option_text: String,
+    pub currency: String,
+    pub quantity: i32,
+    pub avg_price: Decimal,
+    pub current_price: Decimal,
+    pub position_value: Decimal,
+    pub pnl: Decimal,
+}
+
+/// Event the user is engaged with through a position or an open order
+#[derive(Serialize)]
+pub struct WatchedEvent {
+    pub id: i32,
+    pub title: String,
+    pub status: String,
+    pub end_time: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct DashboardResponse {
+    /// One entry per currency the user holds or has positions in, base
+    /// currency first
+    pub wallets: Vec<WalletSummary>,
+    pub top_positions: Vec<DashboardPosition>,
+    pub open_orders_count: u64,
+    pub recent_transactions: Vec<TransactionResponse>,
+    pub watched_events: Vec<WatchedEvent>,
+}
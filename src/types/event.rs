@@ -1,8 +1,11 @@
+use crate::order_book::price_updater::PricingStrategy;
+use crate::order_book::router::ExecutionVenue;
 use crate::utils::pagination::PaginationQuery;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use entity::{event_options, events};
-use sea_orm::prelude::Decimal;
+use sea_orm::{prelude::Decimal, ColumnTrait, Condition};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Deserialize, Debug)]
 pub struct CreateEventRequest {
@@ -13,6 +16,20 @@ pub struct CreateEventRequest {
     pub min_bet_amount: Option<Decimal>,
     pub max_bet_amount: Option<Decimal>,
     pub image_url: Option<String>,
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+    pub tick_size: Option<Decimal>,
+    pub max_order_quantity: Option<i32>,
+    /// Where the event's orders execute, the order book by default
+    pub execution_venue: Option<ExecutionVenue>,
+    /// How option prices are derived, from book imbalance by default
+    pub pricing_strategy: Option<PricingStrategy>,
+    /// Match orders on a binary event's options against each other's book
+    /// at the complementary price, off by default
+    pub complement_pricing: Option<bool>,
+    /// Currency the event is priced and settled in, the base currency by
+    /// default. It can't be changed once the event exists.
+    pub currency: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -25,16 +42,79 @@ pub struct UpdateEventRequest {
     pub min_bet_amount: Option<Decimal>,
     pub max_bet_amount: Option<Decimal>,
     pub image_url: Option<String>,
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+    pub tick_size: Option<Decimal>,
+    pub max_order_quantity: Option<i32>,
+    /// Where the event's orders execute, the order book by default
+    pub execution_venue: Option<ExecutionVenue>,
+    /// How option prices are derived, from book imbalance by default
+    pub pricing_strategy: Option<PricingStrategy>,
+    /// Match orders on a binary event's options against each other's book
+    /// at the complementary price, off by default
+    pub complement_pricing: Option<bool>,
 }
 
 #[derive(Default, Deserialize)]
 pub struct ListEventsQuery {
     pub status: Option<String>,
     pub category: Option<String>,
+    /// Only events featured right now, or only those that aren't
+    pub featured: Option<bool>,
+    /// Comma-separated subset of `EventResponse::FIELDS` to return
+    pub fields: Option<String>,
     #[serde(flatten)]
     pub pagination: PaginationQuery,
 }
 
+/// Pin an event to the homepage, optionally only within a window
+#[derive(Deserialize)]
+pub struct FeatureEventRequest {
+    /// Position on the homepage, lowest first
+    pub rank: i32,
+    pub featured_from: Option<DateTime<Utc>>,
+    pub featured_until: Option<DateTime<Utc>>,
+}
+
+impl FeatureEventRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rank < 1 {
+            return Err("rank must be at least 1".to_string());
+        }
+        if let Some(until) = self.featured_until {
+            if until <= Utc::now() {
+                return Err("featured_until must be in the future".to_string());
+            }
+            if self.featured_from.is_some_and(|from| until <= from) {
+                return Err("featured_until must be after featured_from".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Events that are featured at `now`: ranked, and inside their window if they have one
+pub fn featured_condition(now: NaiveDateTime) -> Condition {
+    Condition::all()
+        .add(events::Column::FeaturedRank.is_not_null())
+        .add(
+            Condition::any()
+                .add(events::Column::FeaturedFrom.is_null())
+                .add(events::Column::FeaturedFrom.lte(now)),
+        )
+        .add(
+            Condition::any()
+                .add(events::Column::FeaturedUntil.is_null())
+                .add(events::Column::FeaturedUntil.gt(now)),
+        )
+}
+
+fn is_featured_at(event: &events::Model, now: NaiveDateTime) -> bool {
+    event.featured_rank.is_some()
+        && event.featured_from.is_none_or(|from| from <= now)
+        && event.featured_until.is_none_or(|until| until > now)
+}
+
 #[derive(Serialize)]
 pub struct OptionResponse {
     pub id: i32,
@@ -42,6 +122,8 @@ pub struct OptionResponse {
     pub current_price: Decimal,
     pub total_backing: Decimal,
     pub is_winning_option: Option<bool>,
+    /// What each share paid, once the event has settled
+    pub payout_per_share: Option<Decimal>,
 }
 
 impl From<event_options::Model> for OptionResponse {
@@ -52,6 +134,7 @@ impl From<event_options::Model> for OptionResponse {
             current_price: option.current_price,
             total_backing: option.total_backing,
             is_winning_option: option.is_winning_option,
+            payout_per_share: option.payout_per_share,
         }
     }
 }
@@ -73,13 +156,63 @@ pub struct EventResponse {
     pub winning_option_id: Option<i32>,
     pub resolution_note: String,
     pub resolved_at: Option<chrono::NaiveDateTime>,
+    pub min_price: Decimal,
+    pub max_price: Decimal,
+    pub tick_size: Decimal,
+    pub max_order_quantity: i32,
+    pub execution_venue: ExecutionVenue,
+    pub pricing_strategy: PricingStrategy,
+    pub complement_pricing: bool,
+    pub currency: String,
+    /// Whether the event is on the homepage right now
+    pub featured: bool,
+    pub featured_rank: Option<i32>,
+    pub featured_from: Option<chrono::NaiveDateTime>,
+    pub featured_until: Option<chrono::NaiveDateTime>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
     pub options: Vec<OptionResponse>,
 }
 
+impl EventResponse {
+    /// Names accepted by `fields=` on event listings
+    pub const FIELDS: &'static [&'static str] = &[
+        "id",
+        "title",
+        "description",
+        "category",
+        "status",
+        "end_time",
+        "min_bet_amount",
+        "max_bet_amount",
+        "total_volume",
+        "image_url",
+        "created_by",
+        "resolved_by",
+        "winning_option_id",
+        "resolution_note",
+        "resolved_at",
+        "min_price",
+        "max_price",
+        "tick_size",
+        "max_order_quantity",
+        "execution_venue",
+        "pricing_strategy",
+        "complement_pricing",
+        "currency",
+        "featured",
+        "featured_rank",
+        "featured_from",
+        "featured_until",
+        "created_at",
+        "updated_at",
+        "options",
+    ];
+}
+
 impl From<(events::Model, Vec<event_options::Model>)> for EventResponse {
     fn from((event, options): (events::Model, Vec<event_options::Model>)) -> Self {
+        let featured = is_featured_at(&event, Utc::now().naive_utc());
         Self {
             id: event.id,
             title: event.title,
@@ -108,6 +241,18 @@ impl From<(events::Model, Vec<event_options::Model>)> for EventResponse {
             } else {
                 Some(event.resolved_at)
             },
+            min_price: event.min_price,
+            max_price: event.max_price,
+            tick_size: event.tick_size,
+            max_order_quantity: event.max_order_quantity,
+            execution_venue: ExecutionVenue::parse(&event.execution_venue),
+            pricing_strategy: PricingStrategy::parse(&event.pricing_strategy),
+            complement_pricing: event.complement_pricing,
+            currency: event.currency,
+            featured,
+            featured_rank: event.featured_rank,
+            featured_from: event.featured_from,
+            featured_until: event.featured_until,
             created_at: event.created_at,
             updated_at: event.updated_at,
             options: options.into_iter().map(OptionResponse::from).collect(),
@@ -115,12 +260,66 @@ impl From<(events::Model, Vec<event_options::Model>)> for EventResponse {
     }
 }
 
+/// How an event's options pay out at settlement
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementMode {
+    /// One option pays 100.00 per share and the rest nothing
+    #[default]
+    WinnerTakeAll,
+    /// 100.00 per share is split evenly across several winning options
+    MultiWinner,
+    /// Each option pays the value it resolved to, from 0 to 100.00 per share
+    Scalar,
+    /// The event's settlement rules work out what each option pays from
+    /// the numbers it resolved to
+    Rules,
+}
+
+/// The value an option resolved to in a scalar settlement
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct OptionValue {
+    pub option_id: i32,
+    pub value: Decimal,
+}
+
+/// `winning_option_id` settles a `winner_take_all` event, `winning_option_ids`
+/// a `multi_winner` one, `option_values`, one for every option, a `scalar`
+/// one, and `inputs`, one for every input its rules read, a `rules` one
 #[derive(Deserialize)]
 pub struct SettleEventRequest {
-    pub winning_option_id: i32,
+    #[serde(default)]
+    pub mode: SettlementMode,
+    pub winning_option_id: Option<i32>,
+    pub winning_option_ids: Option<Vec<i32>>,
+    pub option_values: Option<Vec<OptionValue>>,
+    pub inputs: Option<HashMap<String, Decimal>>,
     pub resolution_note: Option<String>,
 }
 
+/// Attach a settlement rules script to an event
+#[derive(Deserialize)]
+pub struct SettlementRulesRequest {
+    pub script: String,
+}
+
+/// Work out what an event's rules, or `script` in their place, would pay
+/// given `inputs`, without settling it
+#[derive(Deserialize)]
+pub struct PreviewSettlementRulesRequest {
+    pub script: Option<String>,
+    #[serde(default)]
+    pub inputs: HashMap<String, Decimal>,
+}
+
+/// What each share of an option paid
+#[derive(Serialize)]
+pub struct OptionPayout {
+    pub option_id: i32,
+    pub option_text: String,
+    pub payout_per_share: Decimal,
+}
+
 #[derive(Serialize)]
 pub struct SettlementPayout {
     pub user_id: i32,
@@ -137,10 +336,87 @@ pub struct SettlementPayout {
 pub struct SettlementResponse {
     pub event_id: i32,
     pub event_title: String,
-    pub winning_option_id: i32,
-    pub winning_option_text: String,
+    pub mode: SettlementMode,
+    /// The option paying the most per share, if any pays
+    pub winning_option_id: Option<i32>,
+    pub winning_option_text: Option<String>,
+    pub option_payouts: Vec<OptionPayout>,
+    /// Currency the payouts were made in
+    pub currency: String,
     pub total_payouts: Decimal,
     pub total_positions_settled: usize,
     pub payouts: Vec<SettlementPayout>,
     pub settlement_timestamp: chrono::NaiveDateTime,
 }
+
+#[derive(Deserialize)]
+pub struct VoidEventRequest {
+    /// Why the event can't be resolved, kept as its resolution note
+    pub reason: String,
+}
+
+/// A position closed by voiding its event, and what its holder got back.
+/// Shorts have a negative `shares_held` and `refund`, as their sale proceeds
+/// are taken back.
+#[derive(Serialize)]
+pub struct VoidRefund {
+    pub user_id: i32,
+    pub username: String,
+    pub option_id: i32,
+    pub option_text: String,
+    pub shares_held: i32,
+    pub average_price: Decimal,
+    pub refund: Decimal,
+}
+
+#[derive(Serialize)]
+pub struct VoidEventResponse {
+    pub event_id: i32,
+    pub event_title: String,
+    pub reason: String,
+    /// Currency the refunds were made in
+    pub currency: String,
+    pub total_refunds: Decimal,
+    pub total_positions_refunded: usize,
+    pub refunds: Vec<VoidRefund>,
+    pub void_timestamp: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OgCardFormat {
+    #[default]
+    Json,
+    Svg,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OgCardQuery {
+    pub format: Option<OgCardFormat>,
+}
+
+/// The option a share card leads with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OgCardOption {
+    pub id: i32,
+    pub option_text: String,
+    pub price: Decimal,
+    /// Price as a fraction of the 100.00 maximum, e.g. 0.62 for 62.00
+    pub implied_probability: Decimal,
+}
+
+/// Summary of an event for link previews and social shares
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OgCardResponse {
+    pub event_id: i32,
+    pub title: String,
+    pub category: String,
+    pub status: String,
+    pub image_url: String,
+    pub end_time: NaiveDateTime,
+    /// The winning option once resolved, otherwise the highest priced one
+    pub leading_option: Option<OgCardOption>,
+    pub total_volume: Decimal,
+    pub volume_24h: Decimal,
+    pub generated_at: DateTime<Utc>,
+}
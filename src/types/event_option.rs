@@ -1,4 +1,6 @@
-use entity::event_options;
+use crate::order_book::MarketMakerConfig;
+use chrono::{DateTime, Utc};
+use entity::{event_options, price_history};
 use sea_orm::prelude::Decimal;
 use serde::{Deserialize, Serialize};
 
@@ -18,6 +20,28 @@ pub struct LiquidityConfig {
     pub depth_levels: Option<usize>,
     pub level_quantity: Option<i32>,
     pub price_step: Option<Decimal>,
+    pub max_inventory: Option<i32>,
+}
+
+impl LiquidityConfig {
+    /// Override the parts of `config` this sets
+    pub fn apply_to(&self, config: &mut MarketMakerConfig) {
+        if let Some(spread) = self.spread_percentage {
+            config.spread_percentage = spread;
+        }
+        if let Some(levels) = self.depth_levels {
+            config.depth_levels = levels;
+        }
+        if let Some(quantity) = self.level_quantity {
+            config.level_quantity = quantity;
+        }
+        if let Some(step) = self.price_step {
+            config.price_step = step;
+        }
+        if let Some(max_inventory) = self.max_inventory {
+            config.max_inventory = max_inventory;
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -27,6 +51,20 @@ pub struct UpdateEventOptionRequest {
     pub is_winning_option: Option<bool>,
 }
 
+/// Trading statistics for one option, computed by the market stats service
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OptionStats {
+    pub best_bid: Option<Decimal>,
+    pub best_ask: Option<Decimal>,
+    pub last_trade_price: Option<Decimal>,
+    /// Traded notional over the last 24 hours
+    pub volume_24h: Decimal,
+    /// Users holding at least one share
+    pub holders: i64,
+    /// Shares held across all users
+    pub open_interest: i64,
+}
+
 #[derive(Serialize)]
 pub struct EventOptionResponse {
     pub id: i32,
@@ -35,10 +73,14 @@ pub struct EventOptionResponse {
     pub current_price: Decimal,
     pub total_backing: Decimal,
     pub is_winning_option: Option<bool>,
+    /// What each share paid, once the event has settled
+    pub payout_per_share: Option<Decimal>,
+    #[serde(flatten)]
+    pub stats: OptionStats,
 }
 
-impl From<event_options::Model> for EventOptionResponse {
-    fn from(option: event_options::Model) -> Self {
+impl From<(event_options::Model, OptionStats)> for EventOptionResponse {
+    fn from((option, stats): (event_options::Model, OptionStats)) -> Self {
         Self {
             id: option.id,
             event_id: option.event_id,
@@ -46,6 +88,93 @@ impl From<event_options::Model> for EventOptionResponse {
             current_price: option.current_price,
             total_backing: option.total_backing,
             is_winning_option: option.is_winning_option,
+            payout_per_share: option.payout_per_share,
+            stats,
+        }
+    }
+}
+
+/// Number of holders whose position falls within a range of share counts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HolderBucket {
+    pub min_shares: i32,
+    /// Inclusive upper bound; None for the open-ended top bucket
+    pub max_shares: Option<i32>,
+    pub holders: i64,
+    pub shares: i64,
+}
+
+/// How an option's shares are spread across holders, without identifying any
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HolderDistributionResponse {
+    pub event_id: i32,
+    pub option_id: i32,
+    pub holders: i64,
+    pub open_interest: i64,
+    pub buckets: Vec<HolderBucket>,
+}
+
+/// An account whose position differs from what it bought less what it sold.
+/// The market maker that seeded the option holds the issued shares this way;
+/// any other account is a sign that shares were created or destroyed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UntradedHolding {
+    pub user_id: i32,
+    pub position: i32,
+    pub bought: i64,
+    pub sold: i64,
+    /// Position less net traded shares
+    pub untraded: i64,
+}
+
+/// Whether every share of an option held or owed is accounted for by issuance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareConservationReport {
+    pub event_id: i32,
+    pub option_id: i32,
+    /// Shares given to the market maker when liquidity was seeded
+    pub shares_issued: i64,
+    /// Shares held by accounts with a long position
+    pub total_long: i64,
+    /// Shares owed by accounts with a short position
+    pub total_short: i64,
+    /// Long less short, which should equal `shares_issued`
+    pub net_position: i64,
+    /// `net_position` less `shares_issued`; zero when balanced
+    pub discrepancy: i64,
+    pub balanced: bool,
+    pub trades: i64,
+    pub traded_shares: i64,
+    pub untraded_holdings: Vec<UntradedHolding>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PriceHistoryQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<u64>,
+}
+
+/// A price the price updater gave an option
+#[derive(Debug, Clone, Serialize)]
+pub struct PriceHistoryPoint {
+    pub price: Decimal,
+    pub previous_price: Decimal,
+    /// `trade` or `book_imbalance`, as in price update messages
+    pub source: String,
+    /// `price` divided by 100.00
+    pub implied_probability: Decimal,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl From<price_history::Model> for PriceHistoryPoint {
+    fn from(point: price_history::Model) -> Self {
+        Self {
+            implied_probability: (point.price / Decimal::ONE_HUNDRED).round_dp(4),
+            price: point.price,
+            previous_price: point.previous_price,
+            source: point.source,
+            timestamp: point.timestamp.with_timezone(&Utc),
         }
     }
 }
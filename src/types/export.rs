@@ -0,0 +1,208 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use sea_orm::prelude::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Export range and format. `after_id` resumes an interrupted export after the
+/// last row received; `user_id` is only honored on admin exports.
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub format: Option<ExportFormat>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub after_id: Option<String>,
+    pub event_id: Option<i32>,
+    pub user_id: Option<i32>,
+}
+
+/// Position of the last exported row in (timestamp, id) order
+pub type ExportCursor = (DateTime<FixedOffset>, String);
+
+/// A row that can be written to a CSV or ndjson export
+pub trait ExportRecord: Serialize {
+    const CSV_HEADER: &'static str;
+
+    fn csv_record(&self) -> String;
+
+    fn cursor(&self) -> ExportCursor;
+}
+
+#[derive(Debug, Serialize)]
+pub struct TradeExportRow {
+    pub id: String,
+    pub event_id: i32,
+    pub option_id: i32,
+    pub buyer_id: i32,
+    pub seller_id: i32,
+    pub buy_order_id: String,
+    pub sell_order_id: String,
+    pub price: Decimal,
+    pub quantity: i32,
+    pub total_amount: Decimal,
+    pub timestamp: DateTime<FixedOffset>,
+}
+
+impl ExportRecord for TradeExportRow {
+    const CSV_HEADER: &'static str = "id,event_id,option_id,buyer_id,seller_id,buy_order_id,sell_order_id,price,quantity,total_amount,timestamp";
+
+    fn csv_record(&self) -> String {
+        [
+            csv_field(&self.id),
+            self.event_id.to_string(),
+            self.option_id.to_string(),
+            self.buyer_id.to_string(),
+            self.seller_id.to_string(),
+            csv_field(&self.buy_order_id),
+            csv_field(&self.sell_order_id),
+            self.price.to_string(),
+            self.quantity.to_string(),
+            self.total_amount.to_string(),
+            self.timestamp.to_rfc3339(),
+        ]
+        .join(",")
+    }
+
+    fn cursor(&self) -> ExportCursor {
+        (self.timestamp, self.id.clone())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderExportRow {
+    pub id: String,
+    pub user_id: i32,
+    pub event_id: i32,
+    pub option_id: i32,
+    pub side: String,
+    pub order_type: String,
+    pub time_in_force: String,
+    pub price: Decimal,
+    pub quantity: i32,
+    pub filled_quantity: i32,
+    pub status: String,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+}
+
+impl ExportRecord for OrderExportRow {
+    const CSV_HEADER: &'static str = "id,user_id,event_id,option_id,side,order_type,time_in_force,price,quantity,filled_quantity,status,created_at,updated_at";
+
+    fn csv_record(&self) -> String {
+        [
+            csv_field(&self.id),
+            self.user_id.to_string(),
+            self.event_id.to_string(),
+            self.option_id.to_string(),
+            csv_field(&self.side),
+            csv_field(&self.order_type),
+            csv_field(&self.time_in_force),
+            self.price.to_string(),
+            self.quantity.to_string(),
+            self.filled_quantity.to_string(),
+            csv_field(&self.status),
+            self.created_at.to_rfc3339(),
+            self.updated_at.to_rfc3339(),
+        ]
+        .join(",")
+    }
+
+    fn cursor(&self) -> ExportCursor {
+        (self.created_at, self.id.clone())
+    }
+}
+
+/// Name of the archive format, written in every archive's header
+pub const ARCHIVE_SCHEMA: &str = "exchange.archive";
+
+/// Version of the archive format. Bump it whenever a record gains, loses or
+/// changes a field, so offline tools can tell which layout they are reading.
+pub const ARCHIVE_SCHEMA_VERSION: u32 = 1;
+
+/// Range of an archive, both ends required
+#[derive(Debug, Deserialize)]
+pub struct ArchiveQuery {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+/// One line of an archive: a header, then every order event, then every
+/// settlement run, then a trailer with the counts
+#[derive(Debug, Serialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum ArchiveRecord {
+    Header {
+        schema: &'static str,
+        schema_version: u32,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        generated_at: DateTime<Utc>,
+    },
+    OrderEvent(OrderEventArchiveRow),
+    Settlement(SettlementArchiveRow),
+    Trailer {
+        order_events: u64,
+        settlements: u64,
+    },
+}
+
+/// An entry of the order event log, with the order it belongs to
+#[derive(Debug, Serialize)]
+pub struct OrderEventArchiveRow {
+    pub id: i64,
+    pub order_id: String,
+    pub user_id: Option<i32>,
+    pub event_id: Option<i32>,
+    pub option_id: Option<i32>,
+    pub side: Option<String>,
+    pub kind: String,
+    pub status: String,
+    pub price: Decimal,
+    pub quantity: i32,
+    pub filled_quantity: i32,
+    pub trade_id: Option<String>,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+/// A settlement or void of an event, as the audit log recorded it
+#[derive(Debug, Serialize)]
+pub struct SettlementArchiveRow {
+    pub id: i64,
+    pub action: String,
+    pub event_id: i32,
+    pub actor_id: i32,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,61 @@
+use sea_orm::prelude::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Import id and whether to only validate. The id is chosen by the caller and
+/// can only ever be applied once.
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    pub import_id: String,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// A JSON import body
+#[derive(Debug, Deserialize)]
+pub struct ImportRequest {
+    pub records: Vec<ImportRecord>,
+}
+
+/// One row of an import: the user it belongs to, matched by email and created
+/// when missing, with an optional balance to credit and position to open.
+/// A user with several balances or positions takes one row for each.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ImportRecord {
+    pub email: String,
+    pub username: Option<String>,
+    pub full_name: Option<String>,
+    /// bcrypt hash carried over from the old system. Created users without
+    /// one can't log in until they reset their password.
+    pub password_hash: Option<String>,
+    pub currency: Option<String>,
+    pub balance: Option<Decimal>,
+    pub event_id: Option<i32>,
+    pub option_id: Option<i32>,
+    pub quantity: Option<i32>,
+    pub average_price: Option<Decimal>,
+}
+
+/// Why a row can't be imported. Rows count from 1, not counting a CSV header.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRowError {
+    pub row: usize,
+    pub message: String,
+}
+
+/// What an import did, or on a dry run would do. Nothing is applied when
+/// there are errors.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub import_id: String,
+    pub dry_run: bool,
+    pub applied: bool,
+    pub rows: usize,
+    pub users_created: usize,
+    pub users_matched: usize,
+    /// Total credited in each currency
+    pub balances_credited: BTreeMap<String, Decimal>,
+    pub positions_created: usize,
+    pub shares_issued: i64,
+    pub errors: Vec<ImportRowError>,
+}
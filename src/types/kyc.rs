@@ -0,0 +1,210 @@
+use crate::utils::pagination::PaginationQuery;
+use chrono::{DateTime, NaiveDate, Utc};
+use entity::{kyc_documents, users};
+use serde::{Deserialize, Serialize};
+
+/// Where a user is in identity verification
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KycStatus {
+    /// Nothing submitted for review yet
+    Unverified,
+    /// Documents submitted and awaiting review
+    Pending,
+    Verified,
+    /// Documents turned down; the user may upload new ones and resubmit
+    Rejected,
+}
+
+impl KycStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KycStatus::Unverified => "unverified",
+            KycStatus::Pending => "pending",
+            KycStatus::Verified => "verified",
+            KycStatus::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "unverified" => Some(KycStatus::Unverified),
+            "pending" => Some(KycStatus::Pending),
+            "verified" => Some(KycStatus::Verified),
+            "rejected" => Some(KycStatus::Rejected),
+            _ => None,
+        }
+    }
+
+    /// Whether the user may upload documents and submit them for review
+    pub fn can_submit(&self) -> bool {
+        matches!(self, KycStatus::Unverified | KycStatus::Rejected)
+    }
+}
+
+/// Whether a user is known to be old enough to deposit and trade
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AgeStatus {
+    /// No date of birth given yet
+    Unverified,
+    /// Date of birth given by the user, not yet checked against documents
+    Declared,
+    /// Date of birth confirmed by identity verification or by staff
+    Verified,
+    /// Under the minimum age
+    Rejected,
+}
+
+impl AgeStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AgeStatus::Unverified => "unverified",
+            AgeStatus::Declared => "declared",
+            AgeStatus::Verified => "verified",
+            AgeStatus::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "unverified" => Some(AgeStatus::Unverified),
+            "declared" => Some(AgeStatus::Declared),
+            "verified" => Some(AgeStatus::Verified),
+            "rejected" => Some(AgeStatus::Rejected),
+            _ => None,
+        }
+    }
+
+    /// Whether the user may still give or correct their date of birth
+    pub fn can_declare(&self) -> bool {
+        matches!(self, AgeStatus::Unverified | AgeStatus::Declared)
+    }
+}
+
+/// The kind of identity document uploaded
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KycDocumentType {
+    Passport,
+    NationalId,
+    DriversLicense,
+    ProofOfAddress,
+    Selfie,
+}
+
+impl KycDocumentType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KycDocumentType::Passport => "passport",
+            KycDocumentType::NationalId => "national_id",
+            KycDocumentType::DriversLicense => "drivers_license",
+            KycDocumentType::ProofOfAddress => "proof_of_address",
+            KycDocumentType::Selfie => "selfie",
+        }
+    }
+}
+
+/// Review state of a single document: `uploaded` until the user submits,
+/// then `pending` until an admin approves or rejects the submission
+pub mod document_status {
+    pub const UPLOADED: &str = "uploaded";
+    pub const PENDING: &str = "pending";
+    pub const APPROVED: &str = "approved";
+    pub const REJECTED: &str = "rejected";
+}
+
+/// Content types accepted for documents
+pub const ALLOWED_CONTENT_TYPES: [&str; 3] = ["image/jpeg", "image/png", "application/pdf"];
+
+#[derive(Debug, Deserialize)]
+pub struct UploadKycDocumentQuery {
+    pub document_type: KycDocumentType,
+}
+
+/// An uploaded document, without its content
+#[derive(Debug, Serialize)]
+pub struct KycDocumentResponse {
+    pub id: i32,
+    pub document_type: String,
+    pub content_type: String,
+    pub size_bytes: i32,
+    pub sha256: String,
+    pub status: String,
+    pub rejection_reason: Option<String>,
+    pub reviewed_by: Option<i32>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<kyc_documents::Model> for KycDocumentResponse {
+    fn from(document: kyc_documents::Model) -> Self {
+        Self {
+            id: document.id,
+            document_type: document.document_type,
+            content_type: document.content_type,
+            size_bytes: document.size_bytes,
+            sha256: document.sha256,
+            status: document.status,
+            rejection_reason: document.rejection_reason,
+            reviewed_by: document.reviewed_by,
+            reviewed_at: document.reviewed_at.map(|at| at.with_timezone(&Utc)),
+            created_at: document.created_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// A user awaiting or past review, for the admin queue
+#[derive(Debug, Serialize)]
+pub struct KycUserResponse {
+    pub user_id: i32,
+    pub username: String,
+    pub email: String,
+    pub full_name: Option<String>,
+    pub kyc_status: String,
+    pub date_of_birth: Option<NaiveDate>,
+    pub age_status: String,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl From<users::Model> for KycUserResponse {
+    fn from(user: users::Model) -> Self {
+        Self {
+            user_id: user.id,
+            username: user.username,
+            email: user.email,
+            full_name: user.full_name,
+            kyc_status: user.kyc_status,
+            date_of_birth: user.date_of_birth,
+            age_status: user.age_status,
+            updated_at: user.updated_at,
+        }
+    }
+}
+
+/// `status` defaults to `pending`, the users awaiting review
+#[derive(Debug, Deserialize)]
+pub struct ListKycQuery {
+    pub status: Option<KycStatus>,
+    #[serde(flatten)]
+    pub pagination: PaginationQuery,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RejectKycRequest {
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetDateOfBirthRequest {
+    pub date_of_birth: NaiveDate,
+}
+
+/// Staff decision on a user's age, replacing whatever was declared or
+/// verified. `date_of_birth` corrects the recorded date when given.
+#[derive(Debug, Deserialize)]
+pub struct OverrideAgeRequest {
+    pub age_status: AgeStatus,
+    pub date_of_birth: Option<NaiveDate>,
+    pub reason: String,
+}
@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Message shown when a window is scheduled without one
+pub const DEFAULT_MESSAGE: &str = "The exchange is undergoing scheduled maintenance";
+
+/// A period during which the API is read-only
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub message: String,
+    pub starts_at: DateTime<Utc>,
+    /// When the maintenance is expected to be over; `None` until it's known
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+impl MaintenanceWindow {
+    /// Whether the API is read-only at `now`. A window stays in force past
+    /// its expected end until it is cleared.
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at <= now
+    }
+
+    /// Seconds clients should wait before retrying, when the end is known
+    pub fn retry_after(&self, now: DateTime<Utc>) -> Option<i64> {
+        self.ends_at
+            .map(|ends_at| (ends_at - now).num_seconds().max(1))
+    }
+}
+
+/// Starts now when `starts_at` is left out
+#[derive(Debug, Deserialize)]
+pub struct ScheduleMaintenanceRequest {
+    pub message: Option<String>,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MaintenanceStatusResponse {
+    pub active: bool,
+    pub window: Option<MaintenanceWindow>,
+}
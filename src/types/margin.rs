@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use entity::margin_ledger;
+use sea_orm::prelude::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Why collateral moved in or out of a margin account
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MarginLedgerKind {
+    /// Collateral taken from the wallet when a short position grew
+    Lock,
+    /// Collateral returned to the wallet when shorts were bought back or expired worthless
+    Release,
+    /// Collateral forfeited because a shorted option won
+    Settlement,
+    /// The account fell below the maintenance requirement; `amount` is the shortfall
+    MarginCall,
+    /// Collateral freed to pay for a reducing order after the grace period ran out
+    Liquidation,
+}
+
+impl MarginLedgerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MarginLedgerKind::Lock => "lock",
+            MarginLedgerKind::Release => "release",
+            MarginLedgerKind::Settlement => "settlement",
+            MarginLedgerKind::MarginCall => "margin_call",
+            MarginLedgerKind::Liquidation => "liquidation",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetMarginRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShortPositionResponse {
+    pub event_id: i32,
+    pub option_id: i32,
+    /// Currency of the event, which the collateral is held in
+    pub currency: String,
+    /// Shares owed, as a positive number
+    pub shares: i32,
+    /// Average price the shares were sold at
+    pub average_price: Decimal,
+    /// Collateral backing the position
+    pub collateral: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarginLedgerEntry {
+    pub event_id: i32,
+    pub option_id: i32,
+    pub kind: String,
+    pub shares: i32,
+    pub amount: Decimal,
+    pub margin_before: Decimal,
+    pub margin_after: Decimal,
+    pub reference: String,
+    /// Id of the request whose order moved the collateral
+    pub correlation_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<margin_ledger::Model> for MarginLedgerEntry {
+    fn from(entry: margin_ledger::Model) -> Self {
+        Self {
+            event_id: entry.event_id,
+            option_id: entry.option_id,
+            kind: entry.kind,
+            shares: entry.shares,
+            amount: entry.amount,
+            margin_before: entry.margin_before,
+            margin_after: entry.margin_after,
+            reference: entry.reference,
+            correlation_id: entry.correlation_id,
+            created_at: entry.created_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// Margin held in one currency
+#[derive(Debug, Serialize)]
+pub struct MarginBalanceResponse {
+    pub currency: String,
+    /// Collateral currently held
+    pub margin_balance: Decimal,
+    /// Collateral the open short positions in the currency require
+    pub requirement: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarginAccountResponse {
+    pub enabled: bool,
+    /// Collateral held and required in each currency with short positions
+    /// or collateral
+    pub balances: Vec<MarginBalanceResponse>,
+    pub max_short_position: i32,
+    pub short_positions: Vec<ShortPositionResponse>,
+    /// Most recent ledger entries, newest first
+    pub ledger: Vec<MarginLedgerEntry>,
+}
@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use sea_orm::prelude::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// `window` is a duration such as `15m`, `1h` or `24h`
+#[derive(Debug, Deserialize)]
+pub struct HeatmapQuery {
+    pub window: Option<String>,
+}
+
+/// Depth heatmap as a time x price grid. `bid_depth[t][p]` is the average resting
+/// bid quantity at `prices[p]` during the bucket starting at `timestamps[t]`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DepthHeatmapResponse {
+    pub event_id: i32,
+    pub option_id: i32,
+    pub window_seconds: i64,
+    pub bucket_seconds: i64,
+    pub prices: Vec<Decimal>,
+    pub timestamps: Vec<DateTime<Utc>>,
+    pub bid_depth: Vec<Vec<i32>>,
+    pub ask_depth: Vec<Vec<i32>>,
+}
@@ -0,0 +1,17 @@
+use sea_orm::prelude::Decimal;
+use serde::Deserialize;
+
+/// Re-seed an option's liquidity, around `initial_price` or else the option's
+/// current price
+#[derive(Debug, Deserialize)]
+pub struct ReseedMarketMakerRequest {
+    pub initial_price: Option<Decimal>,
+}
+
+/// `refresh` builds the report now instead of returning the one the
+/// scheduled job last built
+#[derive(Debug, Deserialize)]
+pub struct MarketMakerReportQuery {
+    #[serde(default)]
+    pub refresh: bool,
+}
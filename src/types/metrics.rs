@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize)]
+pub struct RouteQueryMetrics {
+    pub route: String,
+    pub requests: u64,
+    pub total_queries: u64,
+    pub avg_queries: f64,
+    pub max_queries: u64,
+    pub total_db_time_ms: f64,
+    pub avg_db_time_ms: f64,
+    pub max_db_time_ms: f64,
+    /// Requests that issued more queries than the per-request budget
+    pub over_budget_requests: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoutePayloadMetrics {
+    pub route: String,
+    pub responses: u64,
+    pub avg_bytes: f64,
+    pub max_bytes: u64,
+    pub budget_bytes: u64,
+    /// Responses larger than the route's budget
+    pub over_budget_responses: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PoolMetrics {
+    pub workload: &'static str,
+    pub max_connections: u32,
+    pub open_connections: u32,
+    /// Connections checked out by a query or transaction right now
+    pub in_use_connections: u32,
+}
+
+/// Matching performance of one resident order book since startup
+#[derive(Debug, Serialize)]
+pub struct MarketPerformance {
+    pub event_id: i32,
+    pub option_id: i32,
+    /// Changes submitted to the book, such as orders, cancels and amendments
+    pub commands: u64,
+    /// Time from a change being submitted to its result returning, including
+    /// queueing behind other changes and journaling
+    pub avg_latency_ms: f64,
+    /// Percentiles over the most recent 1000 changes
+    pub p50_latency_ms: f64,
+    pub p99_latency_ms: f64,
+    pub max_latency_ms: f64,
+    pub trades: u64,
+    /// Averaged over the last `MARKET_METRICS_WINDOW_SECONDS`
+    pub trades_per_second: f64,
+    /// Sequence advances, one per change that altered the book
+    pub mutations: u64,
+    pub mutations_per_second: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryMetricsResponse {
+    pub query_budget_per_request: u64,
+    pub slow_request_db_time_ms: u64,
+    pub routes: Vec<RouteQueryMetrics>,
+    /// Uncompressed response sizes, largest average first
+    pub payloads: Vec<RoutePayloadMetrics>,
+    pub pools: Vec<PoolMetrics>,
+    /// Order books, slowest recent changes first
+    pub markets: Vec<MarketPerformance>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MarketPerformanceQuery {
+    /// Only the books of this event
+    pub event_id: Option<i32>,
+}
@@ -1,8 +1,34 @@
+pub mod api_key;
+pub mod audit;
 pub mod auth;
+pub mod candle;
+pub mod consent;
+pub mod dashboard;
 pub mod event;
 pub mod event_option;
+pub mod export;
+pub mod import;
+pub mod kyc;
+pub mod maintenance;
+pub mod margin;
+pub mod market;
+pub mod market_maker;
+pub mod metrics;
+pub mod oracle;
 pub mod order_book;
+pub mod payment;
+pub mod preferences;
+pub mod promotion;
+pub mod rbac;
 pub mod response;
+pub mod risk;
+pub mod schema;
+pub mod settings;
+pub mod support;
+pub mod tenant;
+pub mod tier;
 pub mod transaction;
+pub mod two_factor;
 pub mod user;
+pub mod wallet;
 pub mod websocket;
@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use entity::event_oracles;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Longest challenge window an oracle may have, one week
+pub const MAX_CHALLENGE_WINDOW_MINUTES: i32 = 7 * 24 * 60;
+
+/// Longest feed value that is recorded on a proposal, matching the column width
+pub const MAX_VALUE_LENGTH: usize = 200;
+
+/// Where an oracle is in resolving its event
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OracleStatus {
+    /// Polling its feed for a result
+    Waiting,
+    /// The feed gave a result; waiting for the challenge window to pass or
+    /// for an admin to confirm it
+    Proposed,
+    /// An admin rejected the proposed result; the event is settled by hand
+    Rejected,
+    /// The event was settled with the proposed result
+    Settled,
+}
+
+impl OracleStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OracleStatus::Waiting => "waiting",
+            OracleStatus::Proposed => "proposed",
+            OracleStatus::Rejected => "rejected",
+            OracleStatus::Settled => "settled",
+        }
+    }
+}
+
+/// `value_path` is a JSON pointer into the feed, such as `/result/winner`.
+/// `option_mappings` maps each value the feed may give there to the option
+/// it means.
+#[derive(Debug, Deserialize)]
+pub struct ConfigureOracleRequest {
+    pub source_url: String,
+    pub value_path: String,
+    pub option_mappings: HashMap<String, i32>,
+    pub auto_settle: Option<bool>,
+    pub challenge_window_minutes: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RejectProposalRequest {
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OracleResponse {
+    pub id: i32,
+    pub event_id: i32,
+    pub source_url: String,
+    pub value_path: String,
+    pub option_mappings: serde_json::Value,
+    pub auto_settle: bool,
+    pub challenge_window_minutes: i32,
+    pub status: String,
+    pub proposed_option_id: Option<i32>,
+    pub proposed_value: Option<String>,
+    pub proposed_at: Option<DateTime<Utc>>,
+    pub settle_after: Option<DateTime<Utc>>,
+    pub last_checked_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_by: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<event_oracles::Model> for OracleResponse {
+    fn from(oracle: event_oracles::Model) -> Self {
+        Self {
+            id: oracle.id,
+            event_id: oracle.event_id,
+            source_url: oracle.source_url,
+            value_path: oracle.value_path,
+            option_mappings: oracle.option_mappings,
+            auto_settle: oracle.auto_settle,
+            challenge_window_minutes: oracle.challenge_window_minutes,
+            status: oracle.status,
+            proposed_option_id: oracle.proposed_option_id,
+            proposed_value: oracle.proposed_value,
+            proposed_at: oracle.proposed_at.map(|at| at.with_timezone(&Utc)),
+            settle_after: oracle.settle_after.map(|at| at.with_timezone(&Utc)),
+            last_checked_at: oracle.last_checked_at.map(|at| at.with_timezone(&Utc)),
+            last_error: oracle.last_error,
+            created_by: oracle.created_by,
+            created_at: oracle.created_at.with_timezone(&Utc),
+            updated_at: oracle.updated_at.with_timezone(&Utc),
+        }
+    }
+}
@@ -1,11 +1,23 @@
+use crate::order_book::router::ExecutionVenue;
 use crate::order_book::types::{
-    MarketDepth, OrderSide, OrderStatus, OrderType, PriceLevel, TimeInForce,
+    MarketDepth, Order, OrderBookSnapshot, OrderEventKind, OrderSide, OrderStatus, OrderType,
+    PriceLevel, SelfTradePrevention, TimeInForce,
 };
 use crate::utils::pagination::PaginationQuery;
 use chrono::{DateTime, Utc};
+use entity::trades;
 use sea_orm::prelude::Decimal;
 use serde::{Deserialize, Serialize};
 
+/// Settlement statuses of a trade
+pub mod settlement_status {
+    /// Recorded, with positions and balances not yet fully moved
+    pub const PENDING: &str = "pending";
+    pub const SETTLED: &str = "settled";
+    /// The last attempt failed; the retry job picks it up again
+    pub const FAILED: &str = "failed";
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PlaceOrderRequest {
     pub event_id: i32,
@@ -15,14 +27,58 @@ pub struct PlaceOrderRequest {
     pub time_in_force: Option<TimeInForce>,
     pub price: Decimal,
     pub quantity: i32,
+    /// Reject the order instead of matching if it would cross the spread
+    pub post_only: Option<bool>,
+    /// Policy applied when the order would match the user's own resting order
+    pub self_trade_prevention: Option<SelfTradePrevention>,
+    /// The client's own identifier for the order, unique per user
+    pub client_order_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PlaceOrderResponse {
     pub success: bool,
     pub order_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_order_id: Option<String>,
     pub trades: Vec<TradeResponse>,
+    /// Currency of the order's event, which `wallet_balance` is in
+    pub currency: String,
     pub wallet_balance: Decimal,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejection_reason: Option<String>,
+}
+
+/// Orders placed one after another in a single request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlaceOrderBatchRequest {
+    pub orders: Vec<PlaceOrderRequest>,
+}
+
+/// Outcome of one order in a batch: the status and body `POST /orders`
+/// would have returned for it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchOrderResult {
+    pub index: usize,
+    pub status: u16,
+    pub response: serde_json::Value,
+}
+
+/// What an order would do if it were placed now
+#[derive(Debug, Serialize)]
+pub struct OrderPreview {
+    pub venue: ExecutionVenue,
+    pub filled_quantity: i32,
+    /// Average price of the fills, or `None` when nothing would fill
+    pub average_price: Option<Decimal>,
+    pub notional: Decimal,
+    /// Trading fee on the fills after the user's tier discount
+    pub estimated_fee: Decimal,
+    /// Quantity that would rest on the book
+    pub resting_quantity: i32,
+    /// Quantity that would neither fill nor rest, as IOC, FOK and market
+    /// orders don't rest
+    pub unfilled_quantity: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,6 +86,40 @@ pub struct CancelOrderRequest {
     pub order_id: String,
 }
 
+/// Filters for cancelling all of a user's open orders at once
+#[derive(Debug, Deserialize)]
+pub struct CancelAllOrdersQuery {
+    pub event_id: Option<i32>,
+    pub option_id: Option<i32>,
+}
+
+/// A fill the external venue gateway reports for an order it was sent
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExternalFillRequest {
+    pub order_id: String,
+    pub price: Decimal,
+    pub quantity: i32,
+}
+
+/// Manual trading halt; without a duration the halt lasts until resumed
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HaltTradingRequest {
+    pub reason: Option<String>,
+    pub duration_seconds: Option<i64>,
+}
+
+/// Start a call auction; the duration defaults to AUCTION_DEFAULT_DURATION_SECONDS
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StartAuctionRequest {
+    pub duration_seconds: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AmendOrderRequest {
+    pub price: Option<Decimal>,
+    pub quantity: Option<i32>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OrderResponse {
     pub id: String,
@@ -43,10 +133,72 @@ pub struct OrderResponse {
     pub quantity: i32,
     pub filled_quantity: i32,
     pub status: OrderStatus,
+    pub client_order_id: Option<String>,
+    pub correlation_id: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl OrderResponse {
+    /// Names accepted by `fields=` on order listings
+    pub const FIELDS: &'static [&'static str] = &[
+        "id",
+        "user_id",
+        "event_id",
+        "option_id",
+        "side",
+        "order_type",
+        "time_in_force",
+        "price",
+        "quantity",
+        "filled_quantity",
+        "status",
+        "client_order_id",
+        "correlation_id",
+        "created_at",
+        "updated_at",
+    ];
+}
+
+impl From<Order> for OrderResponse {
+    fn from(order: Order) -> Self {
+        Self {
+            id: order.id,
+            user_id: order.user_id,
+            event_id: order.event_id,
+            option_id: order.option_id,
+            side: order.side,
+            order_type: order.order_type,
+            time_in_force: order.time_in_force,
+            price: order.price,
+            quantity: order.quantity,
+            filled_quantity: order.filled_quantity,
+            status: order.status,
+            client_order_id: order.client_order_id,
+            correlation_id: order.correlation_id,
+            created_at: order.created_at,
+            updated_at: order.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderEventResponse {
+    pub kind: OrderEventKind,
+    pub status: OrderStatus,
+    pub price: Decimal,
+    pub quantity: i32,
+    pub filled_quantity: i32,
+    pub trade_id: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderHistoryResponse {
+    pub order: OrderResponse,
+    pub events: Vec<OrderEventResponse>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TradeResponse {
     pub id: String,
@@ -58,9 +210,85 @@ pub struct TradeResponse {
     pub quantity: i32,
     pub total_amount: Decimal,
     pub timestamp: DateTime<Utc>,
+    pub sequence: u64,
 }
 
+impl TradeResponse {
+    /// Names accepted by `fields=` on trade listings
+    pub const FIELDS: &'static [&'static str] = &[
+        "id",
+        "event_id",
+        "option_id",
+        "buyer_id",
+        "seller_id",
+        "price",
+        "quantity",
+        "total_amount",
+        "timestamp",
+        "sequence",
+    ];
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListUnsettledTradesQuery {
+    pub status: Option<String>,
+    #[serde(flatten)]
+    pub pagination: PaginationQuery,
+}
+
+/// A trade and how far moving its positions and balances has got
 #[derive(Debug, Serialize, Deserialize)]
+pub struct UnsettledTradeResponse {
+    pub id: String,
+    pub event_id: i32,
+    pub option_id: i32,
+    pub buyer_id: i32,
+    pub seller_id: i32,
+    pub price: Decimal,
+    pub quantity: i32,
+    pub total_amount: Decimal,
+    pub currency: String,
+    pub timestamp: DateTime<Utc>,
+    pub settlement_status: String,
+    pub settlement_attempts: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settlement_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buy_correlation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sell_correlation_id: Option<String>,
+}
+
+impl From<trades::Model> for UnsettledTradeResponse {
+    fn from(trade: trades::Model) -> Self {
+        Self {
+            id: trade.id,
+            event_id: trade.event_id,
+            option_id: trade.option_id,
+            buyer_id: trade.buyer_id,
+            seller_id: trade.seller_id,
+            price: trade.price,
+            quantity: trade.quantity,
+            total_amount: trade.total_amount,
+            currency: trade.currency,
+            timestamp: trade.timestamp.into(),
+            settlement_status: trade.settlement_status,
+            settlement_attempts: trade.settlement_attempts,
+            settlement_error: trade.settlement_error,
+            buy_correlation_id: trade.buy_correlation_id,
+            sell_correlation_id: trade.sell_correlation_id,
+        }
+    }
+}
+
+/// Optional projection for order and trade listings
+#[derive(Debug, Deserialize)]
+pub struct FieldsQuery {
+    /// Comma-separated field names; `id` is always included
+    pub fields: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderBookResponse {
     pub event_id: i32,
     pub option_id: i32,
@@ -70,9 +298,42 @@ pub struct OrderBookResponse {
     pub mid_price: Option<Decimal>,
     pub spread: Option<Decimal>,
     pub predicted_price: Option<Decimal>,
+    pub sequence: u64,
 }
 
+/// Liquidity one option of a binary event gets from the other option's book
+/// under complement pricing
 #[derive(Debug, Serialize, Deserialize)]
+pub struct ImpliedOrderBookResponse {
+    pub event_id: i32,
+    pub option_id: i32,
+    /// Option whose book the levels are mirrored from
+    pub complement_option_id: i32,
+    pub bids: Vec<PriceLevelResponse>,
+    pub asks: Vec<PriceLevelResponse>,
+    /// Sequence of the complementary book when it was read
+    pub sequence: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompactOrderBookResponse {
+    pub option_id: i32,
+    pub option_text: String,
+    pub bids: Vec<PriceLevelResponse>,
+    pub asks: Vec<PriceLevelResponse>,
+    pub last_trade_price: Option<Decimal>,
+    pub mid_price: Option<Decimal>,
+    pub spread: Option<Decimal>,
+    pub sequence: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventOrderBooksResponse {
+    pub event_id: i32,
+    pub order_books: Vec<CompactOrderBookResponse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PriceLevelResponse {
     pub price: Decimal,
     pub quantity: i32,
@@ -86,6 +347,7 @@ pub struct MarketDepthResponse {
     pub depth: Vec<MarketDepth>,
     pub total_bid_volume: i32,
     pub total_ask_volume: i32,
+    pub sequence: u64,
 }
 
 #[allow(dead_code)]
@@ -105,6 +367,22 @@ pub struct OrderHistoryQuery {
     pub pagination: PaginationQuery,
 }
 
+impl OrderBookResponse {
+    pub fn from_snapshot(snapshot: OrderBookSnapshot, predicted_price: Option<Decimal>) -> Self {
+        Self {
+            event_id: snapshot.event_id,
+            option_id: snapshot.option_id,
+            bids: snapshot.bids.into_iter().map(|l| l.into()).collect(),
+            asks: snapshot.asks.into_iter().map(|l| l.into()).collect(),
+            last_trade_price: snapshot.last_trade_price,
+            mid_price: snapshot.mid_price,
+            spread: snapshot.spread,
+            predicted_price,
+            sequence: snapshot.sequence,
+        }
+    }
+}
+
 impl From<PriceLevel> for PriceLevelResponse {
     fn from(level: PriceLevel) -> Self {
         Self {
@@ -0,0 +1,95 @@
+use crate::utils::pagination::PaginationQuery;
+use chrono::{DateTime, Utc};
+use entity::payment_intents;
+use sea_orm::prelude::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Statuses of a payment intent
+pub mod intent_status {
+    /// Queued for the payment gateway relay, no checkout yet
+    pub const CREATED: &str = "created";
+    /// Checkout opened at the gateway, awaiting the user's payment
+    pub const PENDING: &str = "pending";
+    /// Paid and credited to the wallet
+    pub const SUCCEEDED: &str = "succeeded";
+    pub const FAILED: &str = "failed";
+}
+
+/// Webhook events the payment gateway sends
+pub mod webhook_event {
+    pub const PAYMENT_SUCCEEDED: &str = "payment.succeeded";
+    pub const PAYMENT_FAILED: &str = "payment.failed";
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateDepositIntentRequest {
+    pub amount: Decimal,
+    /// Wallet the deposit is paid into; PAYMENT_CURRENCY if omitted
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaymentIntentResponse {
+    pub id: String,
+    pub gateway: String,
+    pub amount: Decimal,
+    pub currency: String,
+    pub status: String,
+    pub gateway_reference: Option<String>,
+    /// Where the user completes the payment, once the gateway has opened it
+    pub checkout_url: Option<String>,
+    /// The deposit transaction, once credited
+    pub transaction_id: Option<i32>,
+    pub failure_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<payment_intents::Model> for PaymentIntentResponse {
+    fn from(intent: payment_intents::Model) -> Self {
+        Self {
+            id: intent.id,
+            gateway: intent.gateway,
+            amount: intent.amount,
+            currency: intent.currency,
+            status: intent.status,
+            gateway_reference: intent.gateway_reference,
+            checkout_url: intent.checkout_url,
+            transaction_id: intent.transaction_id,
+            failure_reason: intent.failure_reason,
+            created_at: intent.created_at.with_timezone(&Utc),
+            updated_at: intent.updated_at.with_timezone(&Utc),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListPaymentIntentsQuery {
+    pub status: Option<String>,
+    #[serde(flatten)]
+    pub pagination: PaginationQuery,
+}
+
+/// The checkout the gateway relay opened for an intent, or why it couldn't
+#[derive(Debug, Deserialize)]
+pub struct ReportIntentRequest {
+    pub gateway_reference: Option<String>,
+    pub checkout_url: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Body of a payment gateway webhook
+#[derive(Debug, Deserialize)]
+pub struct PaymentWebhook {
+    pub event: String,
+    pub data: PaymentWebhookData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PaymentWebhookData {
+    pub intent_id: String,
+    pub gateway_reference: String,
+    pub amount: Decimal,
+    pub currency: String,
+    pub failure_reason: Option<String>,
+}
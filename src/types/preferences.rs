@@ -0,0 +1,200 @@
+use chrono::{DateTime, Utc};
+use entity::user_preferences;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestFrequency {
+    Daily,
+    Weekly,
+}
+
+impl DigestFrequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestFrequency::Daily => "daily",
+            DigestFrequency::Weekly => "weekly",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "weekly" => DigestFrequency::Weekly,
+            _ => DigestFrequency::Daily,
+        }
+    }
+
+    /// Days covered by one digest
+    pub fn period_days(&self) -> i64 {
+        match self {
+            DigestFrequency::Daily => 1,
+            DigestFrequency::Weekly => 7,
+        }
+    }
+}
+
+/// Kinds of notification a user can opt in or out of
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationCategory {
+    Fills,
+    Settlements,
+    Marketing,
+    /// Margin calls and liquidations, which can't be opted out of
+    Margin,
+    /// Deposit and withdrawal status changes, which can't be opted out of
+    Payments,
+    /// Replies and status changes on the user's support tickets, which can't
+    /// be opted out of
+    Support,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationChannels {
+    pub email: bool,
+    pub push: bool,
+    pub ws: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationCategories {
+    pub fills: bool,
+    pub settlements: bool,
+    pub marketing: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailDigestPreferences {
+    pub enabled: bool,
+    pub frequency: DigestFrequency,
+    pub last_sent_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreferencesResponse {
+    pub channels: NotificationChannels,
+    pub categories: NotificationCategories,
+    pub email_digest: EmailDigestPreferences,
+    pub locale: String,
+    pub display_currency: String,
+}
+
+impl PreferencesResponse {
+    /// Preferences of a user who never changed them: fills and settlements over
+    /// email and WebSocket, no marketing, digests off
+    pub fn defaults() -> Self {
+        Self {
+            channels: NotificationChannels {
+                email: true,
+                push: false,
+                ws: true,
+            },
+            categories: NotificationCategories {
+                fills: true,
+                settlements: true,
+                marketing: false,
+            },
+            email_digest: EmailDigestPreferences {
+                enabled: false,
+                frequency: DigestFrequency::Daily,
+                last_sent_at: None,
+            },
+            locale: "en-US".to_string(),
+            display_currency: "USD".to_string(),
+        }
+    }
+
+    pub fn category_enabled(&self, category: NotificationCategory) -> bool {
+        match category {
+            NotificationCategory::Fills => self.categories.fills,
+            NotificationCategory::Settlements => self.categories.settlements,
+            NotificationCategory::Marketing => self.categories.marketing,
+            NotificationCategory::Margin
+            | NotificationCategory::Payments
+            | NotificationCategory::Support => true,
+        }
+    }
+}
+
+impl From<user_preferences::Model> for PreferencesResponse {
+    fn from(preferences: user_preferences::Model) -> Self {
+        Self {
+            channels: NotificationChannels {
+                email: preferences.email_enabled,
+                push: preferences.push_enabled,
+                ws: preferences.ws_enabled,
+            },
+            categories: NotificationCategories {
+                fills: preferences.fills_enabled,
+                settlements: preferences.settlements_enabled,
+                marketing: preferences.marketing_enabled,
+            },
+            email_digest: EmailDigestPreferences {
+                enabled: preferences.email_digest_enabled,
+                frequency: DigestFrequency::parse(&preferences.digest_frequency),
+                last_sent_at: preferences.last_digest_sent_at.map(Into::into),
+            },
+            locale: preferences.locale,
+            display_currency: preferences.display_currency,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateChannelsRequest {
+    pub email: Option<bool>,
+    pub push: Option<bool>,
+    pub ws: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCategoriesRequest {
+    pub fills: Option<bool>,
+    pub settlements: Option<bool>,
+    pub marketing: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateEmailDigestRequest {
+    pub enabled: Option<bool>,
+    pub frequency: Option<DigestFrequency>,
+}
+
+/// Partial update; omitted fields keep their current value
+#[derive(Debug, Deserialize)]
+pub struct UpdatePreferencesRequest {
+    pub channels: Option<UpdateChannelsRequest>,
+    pub categories: Option<UpdateCategoriesRequest>,
+    pub email_digest: Option<UpdateEmailDigestRequest>,
+    pub locale: Option<String>,
+    pub display_currency: Option<String>,
+}
+
+impl UpdatePreferencesRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(locale) = &self.locale {
+            if !is_valid_locale(locale) {
+                return Err("Locale must look like \"en\" or \"en-US\"".to_string());
+            }
+        }
+        if let Some(currency) = &self.display_currency {
+            if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+                return Err("Display currency must be a 3-letter ISO 4217 code".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A BCP 47 language tag limited to language and optional region
+fn is_valid_locale(locale: &str) -> bool {
+    let mut parts = locale.split('-');
+    let language_ok = parts
+        .next()
+        .is_some_and(|l| (2..=3).contains(&l.len()) && l.chars().all(|c| c.is_ascii_lowercase()));
+    let region_ok = match parts.next() {
+        Some(region) => region.len() == 2 && region.chars().all(|c| c.is_ascii_uppercase()),
+        None => true,
+    };
+    language_ok && region_ok && parts.next().is_none()
+}
@@ -0,0 +1,144 @@
+use chrono::{DateTime, Utc};
+use entity::promotions;
+use sea_orm::prelude::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PromotionKind {
+    /// No trading fees on the promotion's event, or on every event when it has none
+    FeeFree,
+    /// Deposits are topped up by a percentage, up to a cap per user
+    DepositMatch,
+}
+
+impl PromotionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PromotionKind::FeeFree => "fee_free",
+            PromotionKind::DepositMatch => "deposit_match",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "fee_free" => Some(PromotionKind::FeeFree),
+            "deposit_match" => Some(PromotionKind::DepositMatch),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePromotionRequest {
+    pub name: String,
+    pub kind: PromotionKind,
+    pub event_id: Option<i32>,
+    pub match_percent: Option<Decimal>,
+    pub max_reward_per_user: Option<Decimal>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+impl CreatePromotionRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Name is required".to_string());
+        }
+        validate_window(self.starts_at, self.ends_at)?;
+        if let Some(cap) = self.max_reward_per_user {
+            if cap <= Decimal::ZERO {
+                return Err("max_reward_per_user must be positive".to_string());
+            }
+        }
+        match self.kind {
+            PromotionKind::FeeFree => {
+                if self.match_percent.is_some() {
+                    return Err("Fee-free promotions don't take a match_percent".to_string());
+                }
+            }
+            PromotionKind::DepositMatch => {
+                validate_match_percent(self.match_percent)?;
+                if self.max_reward_per_user.is_none() {
+                    return Err("Deposit matches need a max_reward_per_user".to_string());
+                }
+                if self.event_id.is_some() {
+                    return Err("Deposit matches can't be limited to an event".to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Partial update; omitted fields keep their current value
+#[derive(Debug, Deserialize)]
+pub struct UpdatePromotionRequest {
+    pub name: Option<String>,
+    pub match_percent: Option<Decimal>,
+    pub max_reward_per_user: Option<Decimal>,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub is_active: Option<bool>,
+}
+
+pub fn validate_window(starts_at: DateTime<Utc>, ends_at: DateTime<Utc>) -> Result<(), String> {
+    if ends_at <= starts_at {
+        return Err("ends_at must be after starts_at".to_string());
+    }
+    Ok(())
+}
+
+pub fn validate_match_percent(match_percent: Option<Decimal>) -> Result<(), String> {
+    match match_percent {
+        Some(percent) if percent > Decimal::ZERO && percent <= Decimal::ONE_HUNDRED => Ok(()),
+        _ => Err("match_percent must be above 0 and at most 100".to_string()),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PromotionResponse {
+    pub id: i32,
+    pub name: String,
+    pub kind: String,
+    pub event_id: Option<i32>,
+    pub match_percent: Option<Decimal>,
+    pub max_reward_per_user: Option<Decimal>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub is_active: bool,
+}
+
+impl From<promotions::Model> for PromotionResponse {
+    fn from(promotion: promotions::Model) -> Self {
+        Self {
+            id: promotion.id,
+            name: promotion.name,
+            kind: promotion.kind,
+            event_id: promotion.event_id,
+            match_percent: promotion.match_percent,
+            max_reward_per_user: promotion.max_reward_per_user,
+            starts_at: promotion.starts_at.into(),
+            ends_at: promotion.ends_at.into(),
+            is_active: promotion.is_active,
+        }
+    }
+}
+
+/// What one user got out of a promotion
+#[derive(Debug, Serialize, sea_orm::FromQueryResult)]
+pub struct PromotionUserUsage {
+    pub user_id: i32,
+    pub usage_count: i64,
+    pub total_reward: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PromotionReportResponse {
+    pub promotion: PromotionResponse,
+    pub unique_users: usize,
+    pub usage_count: i64,
+    /// Fees waived or bonuses paid out in total
+    pub total_reward: Decimal,
+    pub users: Vec<PromotionUserUsage>,
+}
@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Something only admins and staff with a role granting it may do. Admins
+/// hold every permission.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Permission {
+    /// Creating and editing events and their options, and featuring events
+    #[serde(rename = "events:manage")]
+    ManageEvents,
+    /// Settling events
+    #[serde(rename = "events:settle")]
+    SettleEvents,
+    /// Halting and resuming trading, call auctions, external venue fills and
+    /// retrying trade settlements
+    #[serde(rename = "markets:operate")]
+    OperateMarkets,
+    /// Creating and editing promotions
+    #[serde(rename = "promotions:manage")]
+    ManagePromotions,
+    /// Exporting every user's trades and orders
+    #[serde(rename = "exports:read")]
+    ReadExports,
+    /// Audits such as share conservation, and the audit log
+    #[serde(rename = "audit:read")]
+    ReadAudits,
+    /// Looking up users' orders, answering their support tickets and resetting
+    /// their two-factor authentication
+    #[serde(rename = "users:support")]
+    SupportUsers,
+    /// Banning and unbanning users
+    #[serde(rename = "users:ban")]
+    BanUsers,
+    /// Crediting and debiting users' wallets by hand
+    #[serde(rename = "balances:adjust")]
+    AdjustBalances,
+    /// Paying out or rejecting withdrawal requests
+    #[serde(rename = "withdrawals:approve")]
+    ApproveWithdrawals,
+    /// Reviewing users' identity documents
+    #[serde(rename = "kyc:review")]
+    ReviewKyc,
+    /// Assigning roles to users
+    #[serde(rename = "roles:manage")]
+    ManageRoles,
+    /// Creating and configuring tenants' sites
+    #[serde(rename = "tenants:manage")]
+    ManageTenants,
+    /// Changing fees, limits and market maker defaults
+    #[serde(rename = "settings:manage")]
+    ManageSettings,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ManageEvents => "events:manage",
+            Permission::SettleEvents => "events:settle",
+            Permission::OperateMarkets => "markets:operate",
+            Permission::ManagePromotions => "promotions:manage",
+            Permission::ReadExports => "exports:read",
+            Permission::ReadAudits => "audit:read",
+            Permission::SupportUsers => "users:support",
+            Permission::BanUsers => "users:ban",
+            Permission::AdjustBalances => "balances:adjust",
+            Permission::ApproveWithdrawals => "withdrawals:approve",
+            Permission::ReviewKyc => "kyc:review",
+            Permission::ManageRoles => "roles:manage",
+            Permission::ManageTenants => "tenants:manage",
+            Permission::ManageSettings => "settings:manage",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "events:manage" => Some(Permission::ManageEvents),
+            "events:settle" => Some(Permission::SettleEvents),
+            "markets:operate" => Some(Permission::OperateMarkets),
+            "promotions:manage" => Some(Permission::ManagePromotions),
+            "exports:read" => Some(Permission::ReadExports),
+            "audit:read" => Some(Permission::ReadAudits),
+            "users:support" => Some(Permission::SupportUsers),
+            "users:ban" => Some(Permission::BanUsers),
+            "balances:adjust" => Some(Permission::AdjustBalances),
+            "withdrawals:approve" => Some(Permission::ApproveWithdrawals),
+            "kyc:review" => Some(Permission::ReviewKyc),
+            "roles:manage" => Some(Permission::ManageRoles),
+            "tenants:manage" => Some(Permission::ManageTenants),
+            "settings:manage" => Some(Permission::ManageSettings),
+            _ => None,
+        }
+    }
+}
+
+/// A staff role and what it grants
+#[derive(Debug, Serialize)]
+pub struct RoleResponse {
+    pub name: String,
+    pub description: String,
+    pub permissions: Vec<Permission>,
+}
+
+/// A role held by a user
+#[derive(Debug, Serialize)]
+pub struct UserRoleResponse {
+    pub role: String,
+    pub granted_by: Option<i32>,
+    pub granted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignRoleRequest {
+    pub role: String,
+}
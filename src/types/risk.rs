@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use sea_orm::prelude::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// An open position valued at its option's current price
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskPosition {
+    pub event_id: i32,
+    pub option_id: i32,
+    /// Negative for a short position
+    pub quantity: i32,
+    pub average_price: Decimal,
+    pub current_price: Decimal,
+    /// Current price times quantity, negative for a short position
+    pub market_value: Decimal,
+    /// Shares that can still be sold before reaching the short limit, or the
+    /// shares held when the user has no margin account
+    pub shares_to_limit: i32,
+}
+
+/// A user's exposure and collateral in one currency at current prices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskSummary {
+    pub user_id: i32,
+    /// Currency of the wallet and of the events the positions are in
+    pub currency: String,
+    pub margin_enabled: bool,
+    pub wallet_balance: Decimal,
+    pub reserved_balance: Decimal,
+    pub available_balance: Decimal,
+    pub margin_balance: Decimal,
+    /// What the long positions are worth at current prices
+    pub long_exposure: Decimal,
+    /// What buying back every short position would cost at current prices
+    pub short_exposure: Decimal,
+    /// Collateral the short positions require at their sale prices
+    pub collateral_requirement: Decimal,
+    /// Equity the account has to keep before it gets a margin call
+    pub maintenance_requirement: Decimal,
+    /// Available balance and collateral left after buying back every short
+    pub equity: Decimal,
+    /// Maintenance requirement as a percentage of equity; 100 or more means
+    /// the account is below maintenance. `None` when equity isn't positive.
+    pub collateral_usage_percent: Option<Decimal>,
+    /// How far equity is above the maintenance requirement, negative when below it
+    pub distance_to_maintenance: Decimal,
+    pub max_short_position: i32,
+    pub positions: Vec<RiskPosition>,
+    pub computed_at: DateTime<Utc>,
+}
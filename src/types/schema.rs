@@ -0,0 +1,1151 @@
+use crate::types::candle::CandleResponse;
+use crate::types::event::{EventResponse, OptionResponse};
+use crate::types::event_option::{EventOptionResponse, PriceHistoryPoint};
+use crate::types::order_book::{OrderBookResponse, OrderResponse, TradeResponse};
+use crate::types::transaction::TransactionResponse;
+use crate::types::user::UserResponse;
+use serde::Serialize;
+
+/// Version of the WebSocket protocol this server speaks. Raised whenever a
+/// message type ships that older clients wouldn't understand.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Oldest protocol version still served. Clients that never say which
+/// version they speak are taken to speak this one.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// A type sent over the wire and the version of its fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SchemaEntry {
+    /// `type` tag of a WebSocket message, or the name of a REST type
+    pub name: &'static str,
+    /// Raised when a field is renamed, removed or changes meaning. Adding an
+    /// optional field doesn't raise it.
+    pub version: u32,
+    /// Protocol version the type first shipped in. WebSocket sessions that
+    /// negotiated an older version aren't sent it.
+    pub since: u32,
+}
+
+const fn entry(name: &'static str, version: u32, since: u32) -> SchemaEntry {
+    SchemaEntry {
+        name,
+        version,
+        since,
+    }
+}
+
+/// REST response types with a versioned shape
+pub trait Versioned {
+    const SCHEMA: SchemaEntry;
+}
+
+impl Versioned for EventResponse {
+    const SCHEMA: SchemaEntry = entry("event", 1, 1);
+}
+
+impl Versioned for OptionResponse {
+    const SCHEMA: SchemaEntry = entry("event_option_summary", 1, 1);
+}
+
+impl Versioned for EventOptionResponse {
+    const SCHEMA: SchemaEntry = entry("event_option", 1, 1);
+}
+
+impl Versioned for PriceHistoryPoint {
+    const SCHEMA: SchemaEntry = entry("price_history_point", 1, 1);
+}
+
+impl Versioned for OrderResponse {
+    const SCHEMA: SchemaEntry = entry("order", 1, 1);
+}
+
+impl Versioned for TradeResponse {
+    const SCHEMA: SchemaEntry = entry("trade", 1, 1);
+}
+
+impl Versioned for OrderBookResponse {
+    const SCHEMA: SchemaEntry = entry("order_book", 1, 1);
+}
+
+impl Versioned for CandleResponse {
+    const SCHEMA: SchemaEntry = entry("candle", 1, 1);
+}
+
+impl Versioned for TransactionResponse {
+    const SCHEMA: SchemaEntry = entry("transaction", 1, 1);
+}
+
+impl Versioned for UserResponse {
+    const SCHEMA: SchemaEntry = entry("user", 1, 1);
+}
+
+/// Every versioned REST type
+pub const REST_TYPES: &[SchemaEntry] = &[
+    EventResponse::SCHEMA,
+    OptionResponse::SCHEMA,
+    EventOptionResponse::SCHEMA,
+    PriceHistoryPoint::SCHEMA,
+    OrderResponse::SCHEMA,
+    TradeResponse::SCHEMA,
+    OrderBookResponse::SCHEMA,
+    CandleResponse::SCHEMA,
+    TransactionResponse::SCHEMA,
+    UserResponse::SCHEMA,
+];
+
+/// WebSocket message types. `WebSocketMessage::schema` maps each variant to
+/// one of these, so a new variant can't ship without an entry.
+pub mod ws {
+    use super::{entry, SchemaEntry};
+
+    pub const EVENTS_DATA: SchemaEntry = entry("events_data", 1, 1);
+    pub const EVENT_DATA: SchemaEntry = entry("event_data", 1, 1);
+    pub const TRANSACTIONS_DATA: SchemaEntry = entry("transactions_data", 1, 1);
+    pub const PORTFOLIO_DATA: SchemaEntry = entry("portfolio_data", 1, 1);
+    pub const TRADING_STATUS: SchemaEntry = entry("trading_status", 1, 1);
+    pub const ORDERBOOK_SNAPSHOT: SchemaEntry = entry("orderbook_snapshot", 1, 1);
+    pub const ORDERBOOK_DELTA: SchemaEntry = entry("orderbook_delta", 1, 1);
+    pub const PRICE_UPDATE: SchemaEntry = entry("price_update", 1, 1);
+    pub const ORDERS_DATA: SchemaEntry = entry("orders_data", 1, 1);
+    pub const ORDER_UPDATE: SchemaEntry = entry("order_update", 1, 1);
+    pub const RISK_UPDATE: SchemaEntry = entry("risk_update", 1, 1);
+    pub const TRADE: SchemaEntry = entry("trade", 1, 1);
+    pub const TICKER: SchemaEntry = entry("ticker", 1, 1);
+    pub const CANDLE: SchemaEntry = entry("candle", 1, 1);
+    pub const OPS_EVENT: SchemaEntry = entry("ops_event", 1, 1);
+    pub const NOTIFICATION: SchemaEntry = entry("notification", 1, 1);
+    pub const MAINTENANCE: SchemaEntry = entry("maintenance", 1, 1);
+    pub const RESUMED: SchemaEntry = entry("resumed", 1, 1);
+    pub const CANCEL_ON_DISCONNECT: SchemaEntry = entry("cancel_on_disconnect", 1, 1);
+    pub const WELCOME: SchemaEntry = entry("welcome", 1, 1);
+    pub const SUBSCRIBE: SchemaEntry = entry("subscribe", 1, 1);
+    pub const UNSUBSCRIBE: SchemaEntry = entry("unsubscribe", 1, 1);
+    pub const PING: SchemaEntry = entry("ping", 1, 1);
+    pub const PONG: SchemaEntry = entry("pong", 1, 1);
+    pub const ERROR: SchemaEntry = entry("error", 1, 1);
+
+    pub const ALL: &[SchemaEntry] = &[
+        EVENTS_DATA,
+        EVENT_DATA,
+        TRANSACTIONS_DATA,
+        PORTFOLIO_DATA,
+        TRADING_STATUS,
+        ORDERBOOK_SNAPSHOT,
+        ORDERBOOK_DELTA,
+        PRICE_UPDATE,
+        ORDERS_DATA,
+        ORDER_UPDATE,
+        RISK_UPDATE,
+        TRADE,
+        TICKER,
+        CANDLE,
+        OPS_EVENT,
+        NOTIFICATION,
+        MAINTENANCE,
+        RESUMED,
+        CANCEL_ON_DISCONNECT,
+        WELCOME,
+        SUBSCRIBE,
+        UNSUBSCRIBE,
+        PING,
+        PONG,
+        ERROR,
+    ];
+}
+
+/// WebSocket message types a session speaking `protocol_version` is sent
+pub fn ws_messages_for(protocol_version: u32) -> Vec<SchemaEntry> {
+    ws::ALL
+        .iter()
+        .filter(|message| message.since <= protocol_version)
+        .copied()
+        .collect()
+}
+
+/// The protocol version to speak with a client asking for `requested`: the
+/// newest both sides know, or an error if the client is too old to serve
+pub fn negotiate(requested: u32) -> Result<u32, String> {
+    if requested < MIN_PROTOCOL_VERSION {
+        return Err(format!(
+            "Protocol version {} is no longer supported; the oldest is {}",
+            requested, MIN_PROTOCOL_VERSION
+        ));
+    }
+    Ok(requested.min(PROTOCOL_VERSION))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order_book::price_updater::PricingStrategy;
+    use crate::order_book::router::ExecutionVenue;
+    use crate::order_book::types::{
+        AuctionState, OrderEventKind, OrderSide, OrderStatus, OrderType, PriceLevelDelta, Quote,
+        TimeInForce, TradingHalt,
+    };
+    use crate::types::event_option::OptionStats;
+    use crate::types::maintenance::MaintenanceWindow;
+    use crate::types::order_book::PriceLevelResponse;
+    use crate::types::preferences::NotificationCategory;
+    use crate::types::risk::{RiskPosition, RiskSummary};
+    use crate::types::wallet::WalletResponse;
+    use crate::types::websocket::{
+        OpsEventKind, OpsSeverity, OptionPriceUpdate, PriceUpdateSource, WebSocketMessage,
+        WebSocketRequest, WebSocketResponse,
+    };
+    use crate::utils::pagination::{PaginatedResponse, PaginationInfo};
+    use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+    use sea_orm::prelude::Decimal;
+    use serde::de::DeserializeOwned;
+    use serde_json::{json, Value};
+    use std::collections::BTreeSet;
+
+    /// Check that `value` is still sent as `golden`, the wire shape of
+    /// `version` of `entry`. A shape change fails here until the golden JSON
+    /// is updated, and raising the version fails until it is too.
+    fn assert_wire<T: Serialize>(entry: SchemaEntry, version: u32, value: &T, golden: Value) {
+        assert_eq!(
+            entry.version, version,
+            "{} is registered at version {} but its golden JSON is version {}",
+            entry.name, entry.version, version
+        );
+        assert_eq!(
+            serde_json::to_value(value).unwrap(),
+            golden,
+            "{} version {} no longer matches its golden JSON",
+            entry.name,
+            version
+        );
+    }
+
+    /// `assert_wire` for a type clients also send or that is read back:
+    /// `golden` must parse and serialize back unchanged
+    fn assert_round_trip<T: Serialize + DeserializeOwned>(
+        entry: SchemaEntry,
+        version: u32,
+        golden: Value,
+    ) {
+        let value: T = serde_json::from_value(golden.clone())
+            .unwrap_or_else(|e| panic!("{} golden JSON doesn't parse: {}", entry.name, e));
+        assert_wire(entry, version, &value, golden);
+    }
+
+    fn at() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 8, 1, 12, 0, 0).unwrap()
+    }
+
+    fn naive_at() -> NaiveDateTime {
+        at().naive_utc()
+    }
+
+    fn price(cents: i64) -> Decimal {
+        Decimal::new(cents, 2)
+    }
+
+    fn option() -> OptionResponse {
+        OptionResponse {
+            id: 7,
+            option_text: "Yes".to_string(),
+            current_price: price(6000),
+            total_backing: price(120000),
+            is_winning_option: None,
+            payout_per_share: None,
+        }
+    }
+
+    fn option_json() -> Value {
+        json!({
+            "id": 7,
+            "option_text": "Yes",
+            "current_price": "60.00",
+            "total_backing": "1200.00",
+            "is_winning_option": null,
+            "payout_per_share": null
+        })
+    }
+
+    fn event() -> EventResponse {
+        EventResponse {
+            id: 3,
+            title: "Will it rain?".to_string(),
+            description: "Rain in the city on Friday".to_string(),
+            category: "weather".to_string(),
+            status: "active".to_string(),
+            end_time: naive_at(),
+            min_bet_amount: price(100),
+            max_bet_amount: price(100000),
+            total_volume: price(250000),
+            image_url: String::new(),
+            created_by: 1,
+            resolved_by: None,
+            winning_option_id: None,
+            resolution_note: String::new(),
+            resolved_at: None,
+            min_price: price(1),
+            max_price: price(9999),
+            tick_size: price(1),
+            max_order_quantity: 10000,
+            execution_venue: ExecutionVenue::Internal,
+            pricing_strategy: PricingStrategy::BookImbalance,
+            complement_pricing: true,
+            currency: "USD".to_string(),
+            featured: false,
+            featured_rank: None,
+            featured_from: None,
+            featured_until: None,
+            created_at: naive_at(),
+            updated_at: naive_at(),
+            options: vec![option()],
+        }
+    }
+
+    fn event_json() -> Value {
+        json!({
+            "id": 3,
+            "title": "Will it rain?",
+            "description": "Rain in the city on Friday",
+            "category": "weather",
+            "status": "active",
+            "end_time": "2025-08-01T12:00:00",
+            "min_bet_amount": "1.00",
+            "max_bet_amount": "1000.00",
+            "total_volume": "2500.00",
+            "image_url": "",
+            "created_by": 1,
+            "resolved_by": null,
+            "winning_option_id": null,
+            "resolution_note": "",
+            "resolved_at": null,
+            "min_price": "0.01",
+            "max_price": "99.99",
+            "tick_size": "0.01",
+            "max_order_quantity": 10000,
+            "execution_venue": "internal",
+            "pricing_strategy": "book_imbalance",
+            "complement_pricing": true,
+            "currency": "USD",
+            "featured": false,
+            "featured_rank": null,
+            "featured_from": null,
+            "featured_until": null,
+            "created_at": "2025-08-01T12:00:00",
+            "updated_at": "2025-08-01T12:00:00",
+            "options": [option_json()]
+        })
+    }
+
+    fn order_json() -> Value {
+        json!({
+            "id": "9f2c",
+            "user_id": 12,
+            "event_id": 3,
+            "option_id": 7,
+            "side": "Buy",
+            "order_type": "Limit",
+            "time_in_force": "GTC",
+            "price": "60.00",
+            "quantity": 10,
+            "filled_quantity": 4,
+            "status": "PartiallyFilled",
+            "client_order_id": "client-1",
+            "correlation_id": "req-1",
+            "created_at": "2025-08-01T12:00:00Z",
+            "updated_at": "2025-08-01T12:00:00Z"
+        })
+    }
+
+    fn order() -> OrderResponse {
+        OrderResponse {
+            id: "9f2c".to_string(),
+            user_id: 12,
+            event_id: 3,
+            option_id: 7,
+            side: OrderSide::Buy,
+            order_type: OrderType::Limit,
+            time_in_force: TimeInForce::GTC,
+            price: price(6000),
+            quantity: 10,
+            filled_quantity: 4,
+            status: OrderStatus::PartiallyFilled,
+            client_order_id: Some("client-1".to_string()),
+            correlation_id: Some("req-1".to_string()),
+            created_at: at(),
+            updated_at: at(),
+        }
+    }
+
+    fn order_book_json() -> Value {
+        json!({
+            "event_id": 3,
+            "option_id": 7,
+            "bids": [{"price": "59.00", "quantity": 20, "order_count": 2}],
+            "asks": [{"price": "61.00", "quantity": 5, "order_count": 1}],
+            "last_trade_price": "60.00",
+            "mid_price": "60.00",
+            "spread": "2.00",
+            "predicted_price": null,
+            "sequence": 42
+        })
+    }
+
+    fn order_book() -> OrderBookResponse {
+        OrderBookResponse {
+            event_id: 3,
+            option_id: 7,
+            bids: vec![PriceLevelResponse {
+                price: price(5900),
+                quantity: 20,
+                order_count: 2,
+            }],
+            asks: vec![PriceLevelResponse {
+                price: price(6100),
+                quantity: 5,
+                order_count: 1,
+            }],
+            last_trade_price: Some(price(6000)),
+            mid_price: Some(price(6000)),
+            spread: Some(price(200)),
+            predicted_price: None,
+            sequence: 42,
+        }
+    }
+
+    fn candle() -> CandleResponse {
+        CandleResponse {
+            event_id: 3,
+            option_id: 7,
+            interval: "1m".to_string(),
+            open_time: at(),
+            close_time: at() + chrono::Duration::minutes(1),
+            open: price(5900),
+            high: price(6100),
+            low: price(5800),
+            close: price(6000),
+            volume: 30,
+            notional: price(180000),
+            trade_count: 4,
+        }
+    }
+
+    fn candle_json() -> Value {
+        json!({
+            "event_id": 3,
+            "option_id": 7,
+            "interval": "1m",
+            "open_time": "2025-08-01T12:00:00Z",
+            "close_time": "2025-08-01T12:01:00Z",
+            "open": "59.00",
+            "high": "61.00",
+            "low": "58.00",
+            "close": "60.00",
+            "volume": 30,
+            "notional": "1800.00",
+            "trade_count": 4
+        })
+    }
+
+    fn transaction() -> TransactionResponse {
+        TransactionResponse {
+            id: 5,
+            user_id: 12,
+            r#type: "deposit".to_string(),
+            currency: "USD".to_string(),
+            amount: 100.0,
+            balance_before: 50.0,
+            balance_after: 150.0,
+            status: "completed".to_string(),
+            reference_id: "ref-5".to_string(),
+            created_at: at(),
+            rejection_reason: None,
+            failure_reason: None,
+            gateway_reference: None,
+            correlation_id: Some("req-5".to_string()),
+        }
+    }
+
+    fn transaction_json() -> Value {
+        json!({
+            "id": 5,
+            "user_id": 12,
+            "type": "deposit",
+            "currency": "USD",
+            "amount": 100.0,
+            "balance_before": 50.0,
+            "balance_after": 150.0,
+            "status": "completed",
+            "reference_id": "ref-5",
+            "created_at": "2025-08-01T12:00:00Z",
+            "rejection_reason": null,
+            "failure_reason": null,
+            "gateway_reference": null,
+            "correlation_id": "req-5"
+        })
+    }
+
+    fn pagination() -> PaginationInfo {
+        PaginationInfo {
+            current_page: 1,
+            total_pages: 1,
+            total_items: 1,
+            items_per_page: 20,
+        }
+    }
+
+    fn pagination_json() -> Value {
+        json!({
+            "current_page": 1,
+            "total_pages": 1,
+            "total_items": 1,
+            "items_per_page": 20
+        })
+    }
+
+    #[test]
+    fn rest_types_match_their_golden_json() {
+        let mut covered = BTreeSet::new();
+
+        assert_wire(EventResponse::SCHEMA, 1, &event(), event_json());
+        covered.insert(EventResponse::SCHEMA.name);
+
+        assert_wire(OptionResponse::SCHEMA, 1, &option(), option_json());
+        covered.insert(OptionResponse::SCHEMA.name);
+
+        let event_option = EventOptionResponse {
+            id: 7,
+            event_id: 3,
+            option_text: "Yes".to_string(),
+            current_price: price(6000),
+            total_backing: price(120000),
+            is_winning_option: Some(true),
+            payout_per_share: Some(price(10000)),
+            stats: OptionStats {
+                best_bid: Some(price(5900)),
+                best_ask: None,
+                last_trade_price: Some(price(6000)),
+                volume_24h: price(30000),
+                holders: 8,
+                open_interest: 120,
+            },
+        };
+        assert_wire(
+            EventOptionResponse::SCHEMA,
+            1,
+            &event_option,
+            json!({
+                "id": 7,
+                "event_id": 3,
+                "option_text": "Yes",
+                "current_price": "60.00",
+                "total_backing": "1200.00",
+                "is_winning_option": true,
+                "payout_per_share": "100.00",
+                "best_bid": "59.00",
+                "best_ask": null,
+                "last_trade_price": "60.00",
+                "volume_24h": "300.00",
+                "holders": 8,
+                "open_interest": 120
+            }),
+        );
+        covered.insert(EventOptionResponse::SCHEMA.name);
+
+        let point = PriceHistoryPoint {
+            price: price(6000),
+            previous_price: price(5800),
+            source: "trade".to_string(),
+            implied_probability: Decimal::new(6, 1),
+            timestamp: at(),
+        };
+        assert_wire(
+            PriceHistoryPoint::SCHEMA,
+            1,
+            &point,
+            json!({
+                "price": "60.00",
+                "previous_price": "58.00",
+                "source": "trade",
+                "implied_probability": "0.6",
+                "timestamp": "2025-08-01T12:00:00Z"
+            }),
+        );
+        covered.insert(PriceHistoryPoint::SCHEMA.name);
+
+        assert_round_trip::<OrderResponse>(OrderResponse::SCHEMA, 1, order_json());
+        covered.insert(OrderResponse::SCHEMA.name);
+
+        assert_round_trip::<TradeResponse>(
+            TradeResponse::SCHEMA,
+            1,
+            json!({
+                "id": "t-1",
+                "event_id": 3,
+                "option_id": 7,
+                "buyer_id": 12,
+                "seller_id": 13,
+                "price": "60.00",
+                "quantity": 4,
+                "total_amount": "240.00",
+                "timestamp": "2025-08-01T12:00:00Z",
+                "sequence": 41
+            }),
+        );
+        covered.insert(TradeResponse::SCHEMA.name);
+
+        assert_round_trip::<OrderBookResponse>(OrderBookResponse::SCHEMA, 1, order_book_json());
+        covered.insert(OrderBookResponse::SCHEMA.name);
+
+        assert_wire(CandleResponse::SCHEMA, 1, &candle(), candle_json());
+        covered.insert(CandleResponse::SCHEMA.name);
+
+        assert_wire(
+            TransactionResponse::SCHEMA,
+            1,
+            &transaction(),
+            transaction_json(),
+        );
+        covered.insert(TransactionResponse::SCHEMA.name);
+
+        let user = UserResponse {
+            id: 12,
+            username: "trader".to_string(),
+            email: "trader@example.com".to_string(),
+            phone: None,
+            full_name: Some("A Trader".to_string()),
+            wallets: vec![WalletResponse {
+                currency: "USD".to_string(),
+                balance: price(15000),
+                reserved_balance: price(2400),
+                available_balance: price(12600),
+                margin_balance: price(0),
+                updated_at: at().fixed_offset(),
+            }],
+            margin_enabled: false,
+            is_active: true,
+            role: "user".to_string(),
+            kyc_status: "verified".to_string(),
+            age_status: "verified".to_string(),
+            created_at: naive_at(),
+            updated_at: naive_at(),
+        };
+        assert_wire(
+            UserResponse::SCHEMA,
+            1,
+            &user,
+            json!({
+                "id": 12,
+                "username": "trader",
+                "email": "trader@example.com",
+                "phone": null,
+                "full_name": "A Trader",
+                "wallets": [{
+                    "currency": "USD",
+                    "balance": "150.00",
+                    "reserved_balance": "24.00",
+                    "available_balance": "126.00",
+                    "margin_balance": "0.00",
+                    "updated_at": "2025-08-01T12:00:00Z"
+                }],
+                "margin_enabled": false,
+                "is_active": true,
+                "role": "user",
+                "kyc_status": "verified",
+                "age_status": "verified",
+                "created_at": "2025-08-01T12:00:00",
+                "updated_at": "2025-08-01T12:00:00"
+            }),
+        );
+        covered.insert(UserResponse::SCHEMA.name);
+
+        let registered: BTreeSet<_> = REST_TYPES.iter().map(|entry| entry.name).collect();
+        assert_eq!(covered, registered, "every REST type needs golden JSON");
+    }
+
+    /// Check that `message` is sent as `golden` inside a success response
+    /// carrying `version`, and that its `type` tag is its registry name
+    fn assert_message(message: WebSocketMessage, version: u32, golden: Value) -> &'static str {
+        let entry = message.schema();
+        assert_eq!(golden["type"], entry.name, "type tag of {}", entry.name);
+        assert_wire(
+            entry,
+            version,
+            &WebSocketResponse::success(message),
+            json!({
+                "success": true,
+                "message": null,
+                "data": golden,
+                "version": version
+            }),
+        );
+        entry.name
+    }
+
+    #[test]
+    fn websocket_messages_match_their_golden_json() {
+        let stamp = "2025-08-01T12:00:00Z";
+        let messages = vec![
+            (
+                WebSocketMessage::EventsData {
+                    data: PaginatedResponse::new(vec![event()], pagination()),
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "events_data",
+                    "data": {"data": [event_json()], "pagination": pagination_json()},
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::EventData {
+                    event: event(),
+                    timestamp: at(),
+                },
+                json!({"type": "event_data", "event": event_json(), "timestamp": stamp}),
+            ),
+            (
+                WebSocketMessage::TransactionsData {
+                    data: PaginatedResponse::new(vec![transaction()], pagination()),
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "transactions_data",
+                    "data": {"data": [transaction_json()], "pagination": pagination_json()},
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::PortfolioData {
+                    data: json!({"positions": []}),
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "portfolio_data",
+                    "data": {"positions": []},
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::TradingStatus {
+                    event_id: 3,
+                    option_id: 7,
+                    sequence: 42,
+                    halted: true,
+                    halt: Some(TradingHalt {
+                        reason: "Price moved 20%".to_string(),
+                        manual: false,
+                        halted_at: at(),
+                        resume_at: None,
+                    }),
+                    auction: Some(AuctionState {
+                        started_at: at(),
+                        ends_at: at() + chrono::Duration::minutes(5),
+                    }),
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "trading_status",
+                    "event_id": 3,
+                    "option_id": 7,
+                    "sequence": 42,
+                    "halted": true,
+                    "halt": {
+                        "reason": "Price moved 20%",
+                        "manual": false,
+                        "halted_at": stamp,
+                        "resume_at": null
+                    },
+                    "auction": {"started_at": stamp, "ends_at": "2025-08-01T12:05:00Z"},
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::OrderBookSnapshot {
+                    order_book: order_book(),
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "orderbook_snapshot",
+                    "order_book": order_book_json(),
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::OrderBookDelta {
+                    event_id: 3,
+                    option_id: 7,
+                    prev_sequence: 41,
+                    sequence: 42,
+                    changes: vec![PriceLevelDelta {
+                        side: OrderSide::Sell,
+                        price: price(6100),
+                        quantity: 0,
+                    }],
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "orderbook_delta",
+                    "event_id": 3,
+                    "option_id": 7,
+                    "prev_sequence": 41,
+                    "sequence": 42,
+                    "changes": [{"side": "Sell", "price": "61.00", "quantity": 0}],
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::PriceUpdate {
+                    updates: vec![OptionPriceUpdate {
+                        event_id: 3,
+                        option_id: 7,
+                        price: price(6000),
+                        previous_price: price(5800),
+                        change: price(200),
+                        change_percent: None,
+                        source: PriceUpdateSource::Trade,
+                    }],
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "price_update",
+                    "updates": [{
+                        "event_id": 3,
+                        "option_id": 7,
+                        "price": "60.00",
+                        "previous_price": "58.00",
+                        "change": "2.00",
+                        "change_percent": null,
+                        "source": "trade"
+                    }],
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::OrdersData {
+                    orders: vec![order()],
+                    timestamp: at(),
+                },
+                json!({"type": "orders_data", "orders": [order_json()], "timestamp": stamp}),
+            ),
+            (
+                WebSocketMessage::OrderUpdate {
+                    kind: OrderEventKind::PartiallyFilled,
+                    order: order(),
+                    trade_id: Some("t-1".to_string()),
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "order_update",
+                    "kind": "partially_filled",
+                    "order": order_json(),
+                    "trade_id": "t-1",
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::RiskUpdate {
+                    summaries: vec![RiskSummary {
+                        user_id: 12,
+                        currency: "USD".to_string(),
+                        margin_enabled: true,
+                        wallet_balance: price(15000),
+                        reserved_balance: price(0),
+                        available_balance: price(15000),
+                        margin_balance: price(4000),
+                        long_exposure: price(0),
+                        short_exposure: price(6000),
+                        collateral_requirement: price(4000),
+                        maintenance_requirement: price(1000),
+                        equity: price(19000),
+                        collateral_usage_percent: None,
+                        distance_to_maintenance: price(3000),
+                        max_short_position: 100,
+                        positions: vec![RiskPosition {
+                            event_id: 3,
+                            option_id: 7,
+                            quantity: -1,
+                            average_price: price(6000),
+                            current_price: price(6000),
+                            market_value: price(-6000),
+                            shares_to_limit: 99,
+                        }],
+                        computed_at: at(),
+                    }],
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "risk_update",
+                    "summaries": [{
+                        "user_id": 12,
+                        "currency": "USD",
+                        "margin_enabled": true,
+                        "wallet_balance": "150.00",
+                        "reserved_balance": "0.00",
+                        "available_balance": "150.00",
+                        "margin_balance": "40.00",
+                        "long_exposure": "0.00",
+                        "short_exposure": "60.00",
+                        "collateral_requirement": "40.00",
+                        "maintenance_requirement": "10.00",
+                        "equity": "190.00",
+                        "collateral_usage_percent": null,
+                        "distance_to_maintenance": "30.00",
+                        "max_short_position": 100,
+                        "positions": [{
+                            "event_id": 3,
+                            "option_id": 7,
+                            "quantity": -1,
+                            "average_price": "60.00",
+                            "current_price": "60.00",
+                            "market_value": "-60.00",
+                            "shares_to_limit": 99
+                        }],
+                        "computed_at": stamp
+                    }],
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::Trade {
+                    id: "t-1".to_string(),
+                    event_id: 3,
+                    option_id: 7,
+                    price: price(6000),
+                    quantity: 4,
+                    aggressor_side: Some(OrderSide::Buy),
+                    sequence: 41,
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "trade",
+                    "id": "t-1",
+                    "event_id": 3,
+                    "option_id": 7,
+                    "price": "60.00",
+                    "quantity": 4,
+                    "aggressor_side": "Buy",
+                    "sequence": 41,
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::Ticker {
+                    event_id: 3,
+                    option_id: 7,
+                    quote: Quote {
+                        best_bid: Some(price(5900)),
+                        best_ask: Some(price(6100)),
+                        mid_price: Some(price(6000)),
+                        last_trade_price: None,
+                    },
+                    volume_24h: price(30000),
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "ticker",
+                    "event_id": 3,
+                    "option_id": 7,
+                    "best_bid": "59.00",
+                    "best_ask": "61.00",
+                    "mid_price": "60.00",
+                    "last_trade_price": null,
+                    "volume_24h": "300.00",
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::Candle {
+                    candle: candle(),
+                    timestamp: at(),
+                },
+                json!({"type": "candle", "candle": candle_json(), "timestamp": stamp}),
+            ),
+            (
+                WebSocketMessage::OpsEvent {
+                    kind: OpsEventKind::CircuitBreakerTripped,
+                    severity: OpsSeverity::Warning,
+                    message: "Option 7 halted".to_string(),
+                    details: json!({"option_id": 7}),
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "ops_event",
+                    "kind": "circuit_breaker_tripped",
+                    "severity": "warning",
+                    "message": "Option 7 halted",
+                    "details": {"option_id": 7},
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::Notification {
+                    category: NotificationCategory::Fills,
+                    title: "Order filled".to_string(),
+                    body: "4 shares at 60.00".to_string(),
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "notification",
+                    "category": "fills",
+                    "title": "Order filled",
+                    "body": "4 shares at 60.00",
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::Maintenance {
+                    active: true,
+                    window: Some(MaintenanceWindow {
+                        message: "Upgrading".to_string(),
+                        starts_at: at(),
+                        ends_at: None,
+                    }),
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "maintenance",
+                    "active": true,
+                    "window": {"message": "Upgrading", "starts_at": stamp, "ends_at": null},
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::Resumed {
+                    token: "resume-1".to_string(),
+                    channels: vec!["orders".to_string()],
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "resumed",
+                    "token": "resume-1",
+                    "channels": ["orders"],
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::CancelOnDisconnect {
+                    enabled: true,
+                    timestamp: at(),
+                },
+                json!({"type": "cancel_on_disconnect", "enabled": true, "timestamp": stamp}),
+            ),
+            (
+                WebSocketMessage::Welcome {
+                    protocol_version: 1,
+                    server_protocol_version: 1,
+                    min_protocol_version: 1,
+                    message_types: vec![ws::WELCOME],
+                    timestamp: at(),
+                },
+                json!({
+                    "type": "welcome",
+                    "protocol_version": 1,
+                    "server_protocol_version": 1,
+                    "min_protocol_version": 1,
+                    "message_types": [{"name": "welcome", "version": 1, "since": 1}],
+                    "timestamp": stamp
+                }),
+            ),
+            (
+                WebSocketMessage::Subscribe {
+                    channel: "orderbook".to_string(),
+                    params: Some(json!({"event_id": 3, "option_id": 7})),
+                },
+                json!({
+                    "type": "subscribe",
+                    "channel": "orderbook",
+                    "params": {"event_id": 3, "option_id": 7}
+                }),
+            ),
+            (
+                WebSocketMessage::Unsubscribe {
+                    channel: "orderbook".to_string(),
+                },
+                json!({"type": "unsubscribe", "channel": "orderbook"}),
+            ),
+            (
+                WebSocketMessage::Ping { timestamp: at() },
+                json!({"type": "ping", "timestamp": stamp}),
+            ),
+            (
+                WebSocketMessage::Pong { timestamp: at() },
+                json!({"type": "pong", "timestamp": stamp}),
+            ),
+            (
+                WebSocketMessage::Error {
+                    message: "Unknown channel".to_string(),
+                    timestamp: at(),
+                },
+                json!({"type": "error", "message": "Unknown channel", "timestamp": stamp}),
+            ),
+        ];
+
+        let covered: BTreeSet<_> = messages
+            .into_iter()
+            .map(|(message, golden)| assert_message(message, 1, golden))
+            .collect();
+        let registered: BTreeSet<_> = ws::ALL.iter().map(|entry| entry.name).collect();
+        assert_eq!(
+            covered, registered,
+            "every WebSocket message needs golden JSON"
+        );
+    }
+
+    #[test]
+    fn hello_is_answered_with_welcome() {
+        let hello = json!({
+            "type": "hello",
+            "channel": null,
+            "params": {"protocol_version": 1}
+        });
+        let request: WebSocketRequest = serde_json::from_value(hello.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&request).unwrap(), hello);
+        assert_eq!(request.r#type, "hello");
+
+        let requested = request.params.as_ref().unwrap()["protocol_version"]
+            .as_u64()
+            .unwrap() as u32;
+        let protocol_version = negotiate(requested).unwrap();
+        let message_types = ws_messages_for(protocol_version);
+        let welcome = WebSocketResponse::success(WebSocketMessage::Welcome {
+            protocol_version,
+            server_protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            message_types: message_types.clone(),
+            timestamp: at(),
+        });
+
+        let listed: Vec<Value> = message_types
+            .iter()
+            .map(
+                |entry| json!({"name": entry.name, "version": entry.version, "since": entry.since}),
+            )
+            .collect();
+        assert_eq!(listed.len(), ws::ALL.len());
+        assert_eq!(
+            serde_json::to_value(&welcome).unwrap(),
+            json!({
+                "success": true,
+                "message": null,
+                "data": {
+                    "type": "welcome",
+                    "protocol_version": 1,
+                    "server_protocol_version": 1,
+                    "min_protocol_version": 1,
+                    "message_types": listed,
+                    "timestamp": "2025-08-01T12:00:00Z"
+                },
+                "version": 1
+            })
+        );
+    }
+
+    #[test]
+    fn negotiation_settles_on_a_version_both_sides_speak() {
+        assert_eq!(negotiate(MIN_PROTOCOL_VERSION), Ok(MIN_PROTOCOL_VERSION));
+        assert_eq!(negotiate(PROTOCOL_VERSION + 1), Ok(PROTOCOL_VERSION));
+        assert!(negotiate(MIN_PROTOCOL_VERSION - 1).is_err());
+        assert!(ws_messages_for(MIN_PROTOCOL_VERSION)
+            .iter()
+            .all(|message| message.since <= MIN_PROTOCOL_VERSION));
+    }
+}
@@ -0,0 +1,213 @@
+use crate::constants::config;
+use chrono::{DateTime, Utc};
+use sea_orm::prelude::Decimal;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// An operator-editable setting
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingKey {
+    /// Fee charged to each side of a trade, in basis points of its notional
+    TradingFeeBps,
+    /// Minimum bet of events created without one
+    DefaultMinBetAmount,
+    /// Maximum bet of events created without one
+    DefaultMaxBetAmount,
+    /// Spread between the market maker's best bid and ask, as a fraction
+    MmSpreadPercentage,
+    /// Price levels the market maker seeds on each side
+    MmDepthLevels,
+    /// Shares the market maker seeds at each level
+    MmLevelQuantity,
+    /// Price step between the market maker's levels
+    MmPriceStep,
+    /// Most shares of an option the market maker holds before it stops bidding
+    MmMaxInventory,
+    /// Minutes before an event ends during which no new orders are accepted
+    TradingFreezeMinutes,
+    /// Largest short position a margin account may hold in one option
+    MaxShortPosition,
+    /// Version of the terms of service users must have accepted to trade
+    TermsVersion,
+    /// Version of the privacy policy users must have accepted to trade
+    PrivacyPolicyVersion,
+    /// Age in years users must have reached to deposit or trade
+    MinimumAge,
+}
+
+/// The type of value a setting holds
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingKind {
+    Integer,
+    Decimal,
+}
+
+impl SettingKey {
+    pub const ALL: [SettingKey; 13] = [
+        SettingKey::TradingFeeBps,
+        SettingKey::DefaultMinBetAmount,
+        SettingKey::DefaultMaxBetAmount,
+        SettingKey::MmSpreadPercentage,
+        SettingKey::MmDepthLevels,
+        SettingKey::MmLevelQuantity,
+        SettingKey::MmPriceStep,
+        SettingKey::MmMaxInventory,
+        SettingKey::TradingFreezeMinutes,
+        SettingKey::MaxShortPosition,
+        SettingKey::TermsVersion,
+        SettingKey::PrivacyPolicyVersion,
+        SettingKey::MinimumAge,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SettingKey::TradingFeeBps => "trading_fee_bps",
+            SettingKey::DefaultMinBetAmount => "default_min_bet_amount",
+            SettingKey::DefaultMaxBetAmount => "default_max_bet_amount",
+            SettingKey::MmSpreadPercentage => "mm_spread_percentage",
+            SettingKey::MmDepthLevels => "mm_depth_levels",
+            SettingKey::MmLevelQuantity => "mm_level_quantity",
+            SettingKey::MmPriceStep => "mm_price_step",
+            SettingKey::MmMaxInventory => "mm_max_inventory",
+            SettingKey::TradingFreezeMinutes => "trading_freeze_minutes",
+            SettingKey::MaxShortPosition => "max_short_position",
+            SettingKey::TermsVersion => "terms_version",
+            SettingKey::PrivacyPolicyVersion => "privacy_policy_version",
+            SettingKey::MinimumAge => "minimum_age",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|key| key.as_str() == s)
+    }
+
+    pub fn kind(&self) -> SettingKind {
+        match self {
+            SettingKey::DefaultMinBetAmount
+            | SettingKey::DefaultMaxBetAmount
+            | SettingKey::MmSpreadPercentage
+            | SettingKey::MmPriceStep => SettingKind::Decimal,
+            SettingKey::TradingFeeBps
+            | SettingKey::MmDepthLevels
+            | SettingKey::MmLevelQuantity
+            | SettingKey::MmMaxInventory
+            | SettingKey::TradingFreezeMinutes
+            | SettingKey::MaxShortPosition
+            | SettingKey::TermsVersion
+            | SettingKey::PrivacyPolicyVersion
+            | SettingKey::MinimumAge => SettingKind::Integer,
+        }
+    }
+
+    /// Whether tenants may override the deployment-wide value. Margin runs
+    /// across every tenant, so its limits can't differ between them.
+    pub fn per_tenant(&self) -> bool {
+        !matches!(self, SettingKey::MaxShortPosition)
+    }
+
+    /// The value used when no setting has been stored
+    pub fn default_value(&self) -> String {
+        match self {
+            SettingKey::TradingFeeBps => config::get_trading_fee_bps().to_string(),
+            SettingKey::DefaultMinBetAmount => "10.00".to_string(),
+            SettingKey::DefaultMaxBetAmount => "1000.00".to_string(),
+            SettingKey::MmSpreadPercentage => "0.02".to_string(),
+            SettingKey::MmDepthLevels => "5".to_string(),
+            SettingKey::MmLevelQuantity => "100".to_string(),
+            SettingKey::MmPriceStep => "1.00".to_string(),
+            SettingKey::MmMaxInventory => "1000".to_string(),
+            SettingKey::TradingFreezeMinutes => "0".to_string(),
+            SettingKey::MaxShortPosition => config::get_max_short_position().to_string(),
+            SettingKey::TermsVersion | SettingKey::PrivacyPolicyVersion => "1".to_string(),
+            SettingKey::MinimumAge => "18".to_string(),
+        }
+    }
+
+    /// Check a value for the setting, returning it as stored
+    pub fn validate(&self, value: &serde_json::Value) -> Result<String, String> {
+        let raw = match value {
+            serde_json::Value::String(s) => s.trim().to_string(),
+            serde_json::Value::Number(n) => n.to_string(),
+            _ => return Err(format!("{} must be a number", self.as_str())),
+        };
+
+        match self.kind() {
+            SettingKind::Integer => {
+                let value: i64 = raw
+                    .parse()
+                    .map_err(|_| format!("{} must be a whole number", self.as_str()))?;
+                let (min, max) = match self {
+                    // Open orders hold funds for the fee at TRADING_FEE_BPS
+                    SettingKey::TradingFeeBps => (0, i64::from(config::get_trading_fee_bps())),
+                    SettingKey::MmDepthLevels => (1, 50),
+                    SettingKey::MmLevelQuantity => (1, 1_000_000),
+                    SettingKey::TradingFreezeMinutes => (0, 10_080),
+                    SettingKey::TermsVersion | SettingKey::PrivacyPolicyVersion => {
+                        (1, i64::from(i32::MAX))
+                    }
+                    SettingKey::MinimumAge => (13, 100),
+                    _ => (0, i64::from(i32::MAX)),
+                };
+                if !(min..=max).contains(&value) {
+                    return Err(format!(
+                        "{} must be between {} and {}",
+                        self.as_str(),
+                        min,
+                        max
+                    ));
+                }
+                Ok(value.to_string())
+            }
+            SettingKind::Decimal => {
+                let value = Decimal::from_str(&raw)
+                    .map_err(|_| format!("{} must be a decimal number", self.as_str()))?;
+                let max = match self {
+                    SettingKey::MmSpreadPercentage => Some(Decimal::ONE),
+                    _ => None,
+                };
+                if value < Decimal::ZERO || max.is_some_and(|max| value > max) {
+                    return Err(match max {
+                        Some(max) => format!("{} must be between 0 and {}", self.as_str(), max),
+                        None => format!("{} can't be negative", self.as_str()),
+                    });
+                }
+                Ok(value.normalize().to_string())
+            }
+        }
+    }
+}
+
+/// Where a setting's effective value comes from
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingSource {
+    Tenant,
+    System,
+    Default,
+}
+
+/// A setting's value as it applies to the deployment or to one tenant
+#[derive(Debug, Serialize)]
+pub struct SettingResponse {
+    pub key: SettingKey,
+    pub kind: SettingKind,
+    pub per_tenant: bool,
+    pub value: String,
+    pub source: SettingSource,
+    pub updated_by: Option<i32>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// `tenant_id` picks the tenant whose settings are read or changed; without
+/// it the deployment-wide settings are
+#[derive(Debug, Deserialize)]
+pub struct SettingsScopeQuery {
+    pub tenant_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateSettingRequest {
+    pub value: serde_json::Value,
+}
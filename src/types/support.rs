@@ -0,0 +1,179 @@
+use crate::types::audit::AuditLogResponse;
+use crate::utils::pagination::PaginationQuery;
+use chrono::{DateTime, Utc};
+use entity::{support_ticket_messages, support_tickets};
+use serde::{Deserialize, Serialize};
+
+/// Longest subject a ticket may have, matching the column width
+pub const MAX_SUBJECT_LENGTH: usize = 200;
+
+/// Longest message that may be posted on a ticket
+pub const MAX_MESSAGE_LENGTH: usize = 5000;
+
+/// Where a ticket is in triage
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TicketStatus {
+    /// Waiting for support staff to pick it up
+    Open,
+    /// Being looked into by support staff
+    InProgress,
+    /// Answered by support staff; the user may reply to reopen it
+    Resolved,
+    /// Done with; nothing more can be posted
+    Closed,
+}
+
+impl TicketStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TicketStatus::Open => "open",
+            TicketStatus::InProgress => "in_progress",
+            TicketStatus::Resolved => "resolved",
+            TicketStatus::Closed => "closed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "open" => Some(TicketStatus::Open),
+            "in_progress" => Some(TicketStatus::InProgress),
+            "resolved" => Some(TicketStatus::Resolved),
+            "closed" => Some(TicketStatus::Closed),
+            _ => None,
+        }
+    }
+
+    /// Whether staff may move a ticket from this status to `to`. Closed
+    /// tickets stay closed.
+    pub fn can_become(&self, to: TicketStatus) -> bool {
+        *self != to && *self != TicketStatus::Closed
+    }
+}
+
+/// What a ticket disputes
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TicketReferenceType {
+    /// A trade, by its id
+    Trade,
+    /// The settlement of an event, by the event's id
+    Settlement,
+    /// A wallet transaction, by its id
+    Transaction,
+}
+
+impl TicketReferenceType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TicketReferenceType::Trade => "trade",
+            TicketReferenceType::Settlement => "settlement",
+            TicketReferenceType::Transaction => "transaction",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTicketRequest {
+    pub reference_type: TicketReferenceType,
+    pub reference_id: String,
+    pub subject: String,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PostTicketMessageRequest {
+    pub body: String,
+}
+
+/// Staff changes to a ticket. Fields left out are unchanged.
+#[derive(Debug, Deserialize)]
+pub struct UpdateTicketRequest {
+    pub status: Option<TicketStatus>,
+    pub assigned_to: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListMyTicketsQuery {
+    pub status: Option<TicketStatus>,
+    #[serde(flatten)]
+    pub pagination: PaginationQuery,
+}
+
+/// Without a status the queue holds the open and in-progress tickets
+#[derive(Debug, Deserialize)]
+pub struct ListTicketsQuery {
+    pub status: Option<TicketStatus>,
+    pub reference_type: Option<TicketReferenceType>,
+    #[serde(flatten)]
+    pub pagination: PaginationQuery,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TicketResponse {
+    pub id: i32,
+    pub user_id: i32,
+    pub reference_type: String,
+    pub reference_id: String,
+    pub subject: String,
+    pub status: String,
+    pub assigned_to: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+impl From<support_tickets::Model> for TicketResponse {
+    fn from(ticket: support_tickets::Model) -> Self {
+        Self {
+            id: ticket.id,
+            user_id: ticket.user_id,
+            reference_type: ticket.reference_type,
+            reference_id: ticket.reference_id,
+            subject: ticket.subject,
+            status: ticket.status,
+            assigned_to: ticket.assigned_to,
+            created_at: ticket.created_at.with_timezone(&Utc),
+            updated_at: ticket.updated_at.with_timezone(&Utc),
+            resolved_at: ticket.resolved_at.map(|at| at.with_timezone(&Utc)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TicketMessageResponse {
+    pub id: i32,
+    pub author_id: i32,
+    pub from_staff: bool,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<support_ticket_messages::Model> for TicketMessageResponse {
+    fn from(message: support_ticket_messages::Model) -> Self {
+        Self {
+            id: message.id,
+            author_id: message.author_id,
+            from_staff: message.from_staff,
+            body: message.body,
+            created_at: message.created_at.with_timezone(&Utc),
+        }
+    }
+}
+
+/// A ticket and its conversation, oldest message first
+#[derive(Debug, Serialize)]
+pub struct TicketDetailResponse {
+    #[serde(flatten)]
+    pub ticket: TicketResponse,
+    pub messages: Vec<TicketMessageResponse>,
+}
+
+/// A ticket as staff see it, with the audit log entries for what it disputes
+/// and for the ticket itself, newest first
+#[derive(Debug, Serialize)]
+pub struct AdminTicketDetailResponse {
+    #[serde(flatten)]
+    pub ticket: TicketDetailResponse,
+    pub audit_logs: Vec<AuditLogResponse>,
+}
@@ -0,0 +1,110 @@
+use crate::tenants::registry;
+use chrono::{DateTime, Utc};
+use entity::tenants;
+use serde::{Deserialize, Serialize};
+
+/// How a tenant's site looks
+#[derive(Debug, Serialize)]
+pub struct TenantBranding {
+    pub logo_url: String,
+    pub primary_color: String,
+    pub support_email: String,
+}
+
+/// What a tenant's site allows
+#[derive(Debug, Serialize)]
+pub struct TenantSettings {
+    pub allow_registration: bool,
+}
+
+/// The tenant a request is served as, for its site's frontend
+#[derive(Debug, Serialize)]
+pub struct TenantResponse {
+    pub slug: String,
+    pub name: String,
+    pub domain: Option<String>,
+    pub branding: TenantBranding,
+    pub settings: TenantSettings,
+}
+
+impl From<tenants::Model> for TenantResponse {
+    fn from(tenant: tenants::Model) -> Self {
+        Self {
+            slug: tenant.slug,
+            name: tenant.name,
+            domain: tenant.domain,
+            branding: TenantBranding {
+                logo_url: tenant.logo_url,
+                primary_color: tenant.primary_color,
+                support_email: tenant.support_email,
+            },
+            settings: TenantSettings {
+                allow_registration: tenant.allow_registration,
+            },
+        }
+    }
+}
+
+/// A tenant with its full configuration, for admins
+#[derive(Debug, Serialize)]
+pub struct TenantDetailResponse {
+    pub id: i32,
+    pub slug: String,
+    pub name: String,
+    pub domain: Option<String>,
+    pub cors_origins: Vec<String>,
+    pub logo_url: String,
+    pub primary_color: String,
+    pub support_email: String,
+    pub allow_registration: bool,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<tenants::Model> for TenantDetailResponse {
+    fn from(tenant: tenants::Model) -> Self {
+        Self {
+            cors_origins: registry::split_origins(&tenant.cors_origins)
+                .map(str::to_string)
+                .collect(),
+            id: tenant.id,
+            slug: tenant.slug,
+            name: tenant.name,
+            domain: tenant.domain,
+            logo_url: tenant.logo_url,
+            primary_color: tenant.primary_color,
+            support_email: tenant.support_email,
+            allow_registration: tenant.allow_registration,
+            is_active: tenant.is_active,
+            created_at: tenant.created_at.with_timezone(&Utc),
+            updated_at: tenant.updated_at.with_timezone(&Utc),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTenantRequest {
+    pub slug: String,
+    pub name: String,
+    pub domain: Option<String>,
+    pub cors_origins: Option<Vec<String>>,
+    pub logo_url: Option<String>,
+    pub primary_color: Option<String>,
+    pub support_email: Option<String>,
+    pub allow_registration: Option<bool>,
+}
+
+/// Fields left out are unchanged. An empty `domain` removes the tenant's
+/// domain.
+#[derive(Debug, Deserialize)]
+pub struct UpdateTenantRequest {
+    pub name: Option<String>,
+    pub domain: Option<String>,
+    pub cors_origins: Option<Vec<String>>,
+    pub logo_url: Option<String>,
+    pub primary_color: Option<String>,
+    pub support_email: Option<String>,
+    pub allow_registration: Option<bool>,
+    pub is_active: Option<bool>,
+}
@@ -0,0 +1,128 @@
+use chrono::{DateTime, Utc};
+use sea_orm::prelude::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Account tier, from lowest to highest
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Tier {
+    Standard,
+    Silver,
+    Gold,
+    Platinum,
+}
+
+/// What a tier is entitled to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TierBenefits {
+    /// Percentage taken off the trading fee
+    pub fee_discount_percent: Decimal,
+    /// Multiplier applied to each event's maximum order quantity
+    pub order_size_multiplier: i32,
+    /// Most that can be withdrawn per UTC day; unlimited when absent
+    pub daily_withdrawal_limit: Option<Decimal>,
+    /// Withdrawals are processed ahead of other users'
+    pub priority_withdrawals: bool,
+}
+
+/// Minimum trailing trading volume or wallet balance for each tier above Standard.
+/// Meeting either threshold qualifies.
+const TIER_THRESHOLDS: [(Tier, i64, i64); 3] = [
+    (Tier::Platinum, 1_000_000, 250_000),
+    (Tier::Gold, 100_000, 25_000),
+    (Tier::Silver, 10_000, 2_500),
+];
+
+impl Tier {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Tier::Standard => "standard",
+            Tier::Silver => "silver",
+            Tier::Gold => "gold",
+            Tier::Platinum => "platinum",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "silver" => Tier::Silver,
+            "gold" => Tier::Gold,
+            "platinum" => Tier::Platinum,
+            _ => Tier::Standard,
+        }
+    }
+
+    /// The highest tier whose volume or balance threshold is met
+    pub fn for_metrics(trading_volume: Decimal, wallet_balance: Decimal) -> Self {
+        TIER_THRESHOLDS
+            .iter()
+            .find(|(_, min_volume, min_balance)| {
+                trading_volume >= Decimal::from(*min_volume)
+                    || wallet_balance >= Decimal::from(*min_balance)
+            })
+            .map(|(tier, _, _)| *tier)
+            .unwrap_or(Tier::Standard)
+    }
+
+    pub fn benefits(&self) -> TierBenefits {
+        match self {
+            Tier::Standard => TierBenefits {
+                fee_discount_percent: Decimal::ZERO,
+                order_size_multiplier: 1,
+                daily_withdrawal_limit: Some(Decimal::from(10000)),
+                priority_withdrawals: false,
+            },
+            Tier::Silver => TierBenefits {
+                fee_discount_percent: Decimal::from(10),
+                order_size_multiplier: 2,
+                daily_withdrawal_limit: Some(Decimal::from(50000)),
+                priority_withdrawals: false,
+            },
+            Tier::Gold => TierBenefits {
+                fee_discount_percent: Decimal::from(25),
+                order_size_multiplier: 5,
+                daily_withdrawal_limit: Some(Decimal::from(250000)),
+                priority_withdrawals: true,
+            },
+            Tier::Platinum => TierBenefits {
+                fee_discount_percent: Decimal::from(50),
+                order_size_multiplier: 10,
+                daily_withdrawal_limit: None,
+                priority_withdrawals: true,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TierResponse {
+    pub tier: Tier,
+    pub benefits: TierBenefits,
+    pub trading_volume: Decimal,
+    pub wallet_balance: Decimal,
+    /// When the tier was last recalculated; absent until the first nightly run
+    pub calculated_at: Option<DateTime<Utc>>,
+    pub next_tier: Option<NextTierResponse>,
+}
+
+/// What it takes to reach the next tier
+#[derive(Debug, Serialize)]
+pub struct NextTierResponse {
+    pub tier: Tier,
+    pub min_trading_volume: Decimal,
+    pub min_wallet_balance: Decimal,
+}
+
+impl NextTierResponse {
+    pub fn after(tier: Tier) -> Option<Self> {
+        TIER_THRESHOLDS
+            .iter()
+            .rev()
+            .find(|(next, _, _)| *next > tier)
+            .map(|(next, min_volume, min_balance)| Self {
+                tier: *next,
+                min_trading_volume: Decimal::from(*min_volume),
+                min_wallet_balance: Decimal::from(*min_balance),
+            })
+    }
+}
@@ -1,13 +1,31 @@
 use serde::{Deserialize, Serialize};
 
+/// Statuses of a wallet transaction. Most are `completed` as soon as they are
+/// written; withdrawals and card deposits move through the others.
+pub mod transaction_status {
+    /// Awaiting admin review (withdrawals) or the gateway's checkout (card deposits)
+    pub const PENDING: &str = "pending";
+    /// Handed to the payout relay, or awaiting the user's payment at checkout
+    pub const PROCESSING: &str = "processing";
+    pub const COMPLETED: &str = "completed";
+    /// The payout or payment failed; `failure_reason` says why
+    pub const FAILED: &str = "failed";
+    /// Refused by an admin
+    pub const REJECTED: &str = "rejected";
+}
+
 #[derive(Deserialize)]
 pub struct DepositRequest {
     pub amount: f64,
+    /// Wallet the deposit is paid into; the base currency if omitted
+    pub currency: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct WithdrawRequest {
     pub amount: f64,
+    /// Wallet the withdrawal is paid from; the base currency if omitted
+    pub currency: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -15,10 +33,73 @@ pub struct TransactionResponse {
     pub id: i32,
     pub user_id: i32,
     pub r#type: String,
+    pub currency: String,
     pub amount: f64,
     pub balance_before: f64,
     pub balance_after: f64,
     pub status: String,
     pub reference_id: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Why an admin rejected a withdrawal
+    pub rejection_reason: Option<String>,
+    /// Why a payout or card payment failed
+    pub failure_reason: Option<String>,
+    /// The payment gateway's id for a deposit paid through it
+    pub gateway_reference: Option<String>,
+    /// Id of the request whose order led to this transaction
+    pub correlation_id: Option<String>,
+}
+
+/// A withdrawal awaiting or past admin review and payout
+#[derive(Serialize)]
+pub struct WithdrawalResponse {
+    pub id: i32,
+    pub user_id: i32,
+    pub currency: String,
+    pub amount: sea_orm::prelude::Decimal,
+    pub status: String,
+    pub reference_id: String,
+    pub reviewed_by: Option<i32>,
+    pub reviewed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub rejection_reason: Option<String>,
+    pub failure_reason: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<entity::transaction::Model> for WithdrawalResponse {
+    fn from(t: entity::transaction::Model) -> Self {
+        Self {
+            id: t.id,
+            user_id: t.user_id,
+            currency: t.currency,
+            amount: t.amount,
+            status: t.status,
+            reference_id: t.reference_id,
+            reviewed_by: t.reviewed_by,
+            reviewed_at: t.reviewed_at.map(|at| at.and_utc()),
+            rejection_reason: t.rejection_reason,
+            failure_reason: t.failure_reason,
+            created_at: t.created_at.and_utc(),
+        }
+    }
+}
+
+/// `status` defaults to `pending`, the withdrawals awaiting review
+#[derive(Deserialize)]
+pub struct ListWithdrawalsQuery {
+    pub status: Option<String>,
+    #[serde(flatten)]
+    pub pagination: crate::utils::pagination::PaginationQuery,
+}
+
+#[derive(Deserialize)]
+pub struct RejectWithdrawalRequest {
+    pub reason: String,
+}
+
+/// The payout relay's result for an approved withdrawal; `error` says why
+/// it couldn't be paid out
+#[derive(Deserialize)]
+pub struct ReportPayoutRequest {
+    pub error: Option<String>,
 }
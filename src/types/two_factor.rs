@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A code from the user's authenticator app or, where accepted, a backup code
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorStatusResponse {
+    pub enabled: bool,
+    pub enabled_at: Option<DateTime<Utc>>,
+    /// Unused backup codes, zero when two-factor authentication is off
+    pub backup_codes_remaining: usize,
+}
+
+/// A new authenticator secret, to be confirmed with a code before it applies
+#[derive(Debug, Serialize)]
+pub struct TwoFactorEnrollmentResponse {
+    /// Base32 secret, for entering by hand
+    pub secret: String,
+    /// `otpauth://` URI to render as a QR code for authenticator apps
+    pub otpauth_url: String,
+}
+
+/// Backup codes, each usable once in place of an authenticator code. They
+/// are only ever shown here.
+#[derive(Debug, Serialize)]
+pub struct BackupCodesResponse {
+    pub backup_codes: Vec<String>,
+}
@@ -1,5 +1,6 @@
+use crate::types::wallet::WalletResponse;
 use crate::utils::pagination::PaginationQuery;
-use entity::users;
+use entity::{users, wallets};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize)]
@@ -16,26 +17,47 @@ pub struct UserResponse {
     pub email: String,
     pub phone: Option<String>,
     pub full_name: Option<String>,
-    pub wallet_balance: sea_orm::prelude::Decimal,
+    /// Balances in each currency the user has held, base currency first
+    pub wallets: Vec<WalletResponse>,
+    pub margin_enabled: bool,
     pub is_active: bool,
     pub role: String,
+    pub kyc_status: String,
+    pub age_status: String,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
 }
 
-impl From<users::Model> for UserResponse {
-    fn from(user: users::Model) -> Self {
+impl UserResponse {
+    pub fn new(user: users::Model, wallets: Vec<wallets::Model>) -> Self {
         Self {
             id: user.id,
             username: user.username,
             email: user.email,
             phone: user.phone,
             full_name: user.full_name,
-            wallet_balance: user.wallet_balance,
+            wallets: wallets.into_iter().map(WalletResponse::from).collect(),
+            margin_enabled: user.margin_enabled,
             is_active: user.is_active,
             role: user.role,
+            kyc_status: user.kyc_status,
+            age_status: user.age_status,
             created_at: user.created_at,
             updated_at: user.updated_at,
         }
     }
 }
+
+/// Credit (positive `amount`) or debit (negative) a user's wallet in
+/// `currency`, the base currency if omitted, by hand
+#[derive(Deserialize)]
+pub struct AdjustBalanceRequest {
+    pub amount: sea_orm::prelude::Decimal,
+    pub currency: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Deserialize)]
+pub struct BanUserRequest {
+    pub reason: String,
+}
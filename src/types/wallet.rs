@@ -0,0 +1,106 @@
+use crate::order_book::reservations;
+use crate::wallets::balances;
+use chrono::{DateTime, FixedOffset, Utc};
+use entity::{orders, transaction, wallets};
+use sea_orm::prelude::Decimal;
+use serde::Serialize;
+
+/// A user's balances in one currency
+#[derive(Debug, Clone, Serialize)]
+pub struct WalletResponse {
+    pub currency: String,
+    pub balance: Decimal,
+    /// Part of the balance held for open buy orders and unfinished withdrawals
+    pub reserved_balance: Decimal,
+    pub available_balance: Decimal,
+    /// Collateral held for short positions, outside the balance
+    pub margin_balance: Decimal,
+    pub updated_at: DateTime<FixedOffset>,
+}
+
+impl From<wallets::Model> for WalletResponse {
+    fn from(wallet: wallets::Model) -> Self {
+        Self {
+            available_balance: balances::available(&wallet),
+            currency: wallet.currency,
+            balance: wallet.balance,
+            reserved_balance: wallet.reserved_balance,
+            margin_balance: wallet.margin_balance,
+            updated_at: wallet.updated_at,
+        }
+    }
+}
+
+/// Funds an open order holds
+#[derive(Debug, Serialize)]
+pub struct OrderHoldResponse {
+    pub order_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_order_id: Option<String>,
+    pub event_id: i32,
+    pub option_id: i32,
+    pub side: String,
+    pub price: Decimal,
+    /// Shares still to be bought or sold
+    pub remaining_quantity: i32,
+    /// Notional of the remaining shares plus the worst-case fee on them for
+    /// a buy order, or the collateral of the remaining shares sold short for
+    /// a sell order
+    pub amount: Decimal,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+impl From<&orders::Model> for OrderHoldResponse {
+    fn from(order: &orders::Model) -> Self {
+        Self {
+            order_id: order.id.clone(),
+            client_order_id: order.client_order_id.clone(),
+            event_id: order.event_id,
+            option_id: order.option_id,
+            side: order.side.clone(),
+            price: order.price,
+            remaining_quantity: order.quantity - order.filled_quantity,
+            amount: reservations::reservation_for(order),
+            created_at: order.created_at,
+        }
+    }
+}
+
+/// Funds a withdrawal awaiting review or payout holds
+#[derive(Debug, Serialize)]
+pub struct WithdrawalHoldResponse {
+    pub transaction_id: i32,
+    pub reference_id: String,
+    /// `pending` review or `processing` by the payout relay
+    pub status: String,
+    pub amount: Decimal,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<&transaction::Model> for WithdrawalHoldResponse {
+    fn from(withdrawal: &transaction::Model) -> Self {
+        Self {
+            transaction_id: withdrawal.id,
+            reference_id: withdrawal.reference_id.clone(),
+            status: withdrawal.status.clone(),
+            amount: withdrawal.amount,
+            created_at: DateTime::from_naive_utc_and_offset(withdrawal.created_at, Utc),
+        }
+    }
+}
+
+/// A user's balance in one currency, split into what is held and what can
+/// still be spent or withdrawn
+#[derive(Debug, Serialize)]
+pub struct BalanceResponse {
+    pub currency: String,
+    pub total: Decimal,
+    pub reserved: Decimal,
+    /// Held by the open buy orders in `orders`
+    pub reserved_by_orders: Decimal,
+    /// Held by the unfinished withdrawals in `withdrawals`
+    pub reserved_by_withdrawals: Decimal,
+    pub available: Decimal,
+    pub orders: Vec<OrderHoldResponse>,
+    pub withdrawals: Vec<WithdrawalHoldResponse>,
+}
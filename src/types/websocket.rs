@@ -1,11 +1,37 @@
+use crate::order_book::types::{
+    AuctionState, OrderEventKind, OrderSide, PriceLevelDelta, Quote, TradingHalt,
+};
+use crate::types::candle::{CandleInterval, CandleResponse};
+use crate::types::maintenance::MaintenanceWindow;
+use crate::types::order_book::{OrderBookResponse, OrderResponse};
+use crate::types::preferences::NotificationCategory;
+use crate::types::risk::RiskSummary;
+use crate::types::schema::{ws, SchemaEntry};
 use crate::types::{event::EventResponse, transaction::TransactionResponse};
 use crate::utils::pagination::PaginatedResponse;
 use actix::Message;
 use chrono::{DateTime, Utc};
+use sea_orm::prelude::Decimal;
 use serde::{Deserialize, Serialize};
 
 /// Pre-serialized message for efficient broadcasting
-pub struct PreSerializedMessage(pub String);
+#[derive(Clone)]
+pub struct PreSerializedMessage {
+    pub json: String,
+    /// Protocol version the message type first shipped in
+    pub since: u32,
+}
+
+impl PreSerializedMessage {
+    /// Serialize a message once to send it to many sessions
+    pub fn new(message: WebSocketMessage) -> serde_json::Result<Self> {
+        let since = message.schema().since;
+        Ok(Self {
+            json: serde_json::to_string(&WebSocketResponse::success(message))?,
+            since,
+        })
+    }
+}
 
 impl Message for PreSerializedMessage {
     type Result = ();
@@ -35,6 +61,121 @@ pub enum WebSocketMessage {
         data: serde_json::Value, // Will be replaced with position-based portfolio
         timestamp: DateTime<Utc>,
     },
+    #[serde(rename = "trading_status")]
+    TradingStatus {
+        event_id: i32,
+        option_id: i32,
+        sequence: u64,
+        halted: bool,
+        halt: Option<TradingHalt>,
+        auction: Option<AuctionState>,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "orderbook_snapshot")]
+    OrderBookSnapshot {
+        order_book: OrderBookResponse,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "orderbook_delta")]
+    OrderBookDelta {
+        event_id: i32,
+        option_id: i32,
+        prev_sequence: u64,
+        sequence: u64,
+        changes: Vec<PriceLevelDelta>,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "price_update")]
+    PriceUpdate {
+        updates: Vec<OptionPriceUpdate>,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "orders_data")]
+    OrdersData {
+        orders: Vec<OrderResponse>,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "order_update")]
+    OrderUpdate {
+        kind: OrderEventKind,
+        order: OrderResponse,
+        trade_id: Option<String>,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "risk_update")]
+    RiskUpdate {
+        /// One summary per currency, base currency first
+        summaries: Vec<RiskSummary>,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "trade")]
+    Trade {
+        id: String,
+        event_id: i32,
+        option_id: i32,
+        price: Decimal,
+        quantity: i32,
+        /// Side of the order that took liquidity; None for auction uncrossing
+        aggressor_side: Option<OrderSide>,
+        sequence: u64,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "ticker")]
+    Ticker {
+        event_id: i32,
+        option_id: i32,
+        #[serde(flatten)]
+        quote: Quote,
+        volume_24h: Decimal,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "candle")]
+    Candle {
+        candle: CandleResponse,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "ops_event")]
+    OpsEvent {
+        kind: OpsEventKind,
+        severity: OpsSeverity,
+        message: String,
+        details: serde_json::Value,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "notification")]
+    Notification {
+        category: NotificationCategory,
+        title: String,
+        body: String,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "maintenance")]
+    Maintenance {
+        active: bool,
+        window: Option<MaintenanceWindow>,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "resumed")]
+    Resumed {
+        token: String,
+        channels: Vec<String>,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "cancel_on_disconnect")]
+    CancelOnDisconnect {
+        enabled: bool,
+        timestamp: DateTime<Utc>,
+    },
+    #[serde(rename = "welcome")]
+    Welcome {
+        /// Version negotiated for this connection
+        protocol_version: u32,
+        server_protocol_version: u32,
+        min_protocol_version: u32,
+        /// Message types the connection will be sent
+        message_types: Vec<SchemaEntry>,
+        timestamp: DateTime<Utc>,
+    },
     #[allow(dead_code)]
     #[serde(rename = "subscribe")]
     Subscribe {
@@ -56,6 +197,74 @@ pub enum WebSocketMessage {
     },
 }
 
+impl WebSocketMessage {
+    /// The registry entry of this message's type
+    pub fn schema(&self) -> SchemaEntry {
+        match self {
+            WebSocketMessage::EventsData { .. } => ws::EVENTS_DATA,
+            WebSocketMessage::EventData { .. } => ws::EVENT_DATA,
+            WebSocketMessage::TransactionsData { .. } => ws::TRANSACTIONS_DATA,
+            WebSocketMessage::PortfolioData { .. } => ws::PORTFOLIO_DATA,
+            WebSocketMessage::TradingStatus { .. } => ws::TRADING_STATUS,
+            WebSocketMessage::OrderBookSnapshot { .. } => ws::ORDERBOOK_SNAPSHOT,
+            WebSocketMessage::OrderBookDelta { .. } => ws::ORDERBOOK_DELTA,
+            WebSocketMessage::PriceUpdate { .. } => ws::PRICE_UPDATE,
+            WebSocketMessage::OrdersData { .. } => ws::ORDERS_DATA,
+            WebSocketMessage::OrderUpdate { .. } => ws::ORDER_UPDATE,
+            WebSocketMessage::RiskUpdate { .. } => ws::RISK_UPDATE,
+            WebSocketMessage::Trade { .. } => ws::TRADE,
+            WebSocketMessage::Ticker { .. } => ws::TICKER,
+            WebSocketMessage::Candle { .. } => ws::CANDLE,
+            WebSocketMessage::OpsEvent { .. } => ws::OPS_EVENT,
+            WebSocketMessage::Notification { .. } => ws::NOTIFICATION,
+            WebSocketMessage::Maintenance { .. } => ws::MAINTENANCE,
+            WebSocketMessage::Resumed { .. } => ws::RESUMED,
+            WebSocketMessage::CancelOnDisconnect { .. } => ws::CANCEL_ON_DISCONNECT,
+            WebSocketMessage::Welcome { .. } => ws::WELCOME,
+            WebSocketMessage::Subscribe { .. } => ws::SUBSCRIBE,
+            WebSocketMessage::Unsubscribe { .. } => ws::UNSUBSCRIBE,
+            WebSocketMessage::Ping { .. } => ws::PING,
+            WebSocketMessage::Pong { .. } => ws::PONG,
+            WebSocketMessage::Error { .. } => ws::ERROR,
+        }
+    }
+}
+
+/// What caused an option's price to be recalculated
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceUpdateSource {
+    /// The option traded
+    Trade,
+    /// Orders were placed, amended or cancelled without trading, or the
+    /// periodic price updater found the option's price had moved
+    BookImbalance,
+}
+
+impl PriceUpdateSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PriceUpdateSource::Trade => "trade",
+            PriceUpdateSource::BookImbalance => "book_imbalance",
+        }
+    }
+}
+
+/// An option's new price, with enough of its old one to show which way it moved
+#[derive(Debug, Clone, Serialize)]
+pub struct OptionPriceUpdate {
+    pub event_id: i32,
+    pub option_id: i32,
+    pub price: Decimal,
+    pub previous_price: Decimal,
+    /// `price - previous_price`
+    pub change: Decimal,
+    /// Change as a percentage of the previous price, `None` when the option
+    /// had no price before
+    pub change_percent: Option<Decimal>,
+    pub source: PriceUpdateSource,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebSocketRequest {
     pub r#type: String,
@@ -63,11 +272,56 @@ pub struct WebSocketRequest {
     pub params: Option<serde_json::Value>,
 }
 
+/// Operational event streamed to admins on the `ops` channel
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpsEventKind {
+    CircuitBreakerTripped,
+    SettlementCompleted,
+    SettlementFailed,
+    EventVoided,
+    RedisDegraded,
+    JobFailed,
+    PayloadOverBudget,
+    MarginLiquidation,
+    PriceJump,
+    WithdrawalRequested,
+    PaymentMismatch,
+    TradeSettlementFailed,
+    OracleProposed,
+    ShareConservationDiscrepancy,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpsSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// A channel a session asked for, kept so it can be re-established on resume
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionIntent {
+    pub channel: String,
+    pub params: Option<serde_json::Value>,
+}
+
+/// Subscriptions stored in Redis under a client's resume token
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubscriptionIntents {
+    pub user_id: Option<i32>,
+    pub subscriptions: Vec<SubscriptionIntent>,
+}
+
 #[derive(Serialize)]
 pub struct WebSocketResponse {
     pub success: bool,
     pub message: Option<String>,
     pub data: Option<WebSocketMessage>,
+    /// Schema version of `data`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
 }
 
 impl WebSocketResponse {
@@ -75,6 +329,7 @@ impl WebSocketResponse {
         Self {
             success: true,
             message: None,
+            version: Some(data.schema().version),
             data: Some(data),
         }
     }
@@ -84,6 +339,7 @@ impl WebSocketResponse {
             success: false,
             message: Some(message),
             data: None,
+            version: None,
         }
     }
 }
@@ -94,6 +350,13 @@ pub enum SubscriptionChannel {
     Event(i32),
     Transactions,
     Portfolio,
+    Orders,
+    Risk,
+    Ops,
+    OrderBook(i32, i32),
+    Trades(i32, i32),
+    Ticker(i32, i32),
+    Candles(i32, i32, CandleInterval),
 }
 
 impl std::fmt::Display for SubscriptionChannel {
@@ -103,19 +366,80 @@ impl std::fmt::Display for SubscriptionChannel {
             SubscriptionChannel::Event(id) => write!(f, "event:{}", id),
             SubscriptionChannel::Transactions => write!(f, "transactions"),
             SubscriptionChannel::Portfolio => write!(f, "portfolio"),
+            SubscriptionChannel::Orders => write!(f, "orders"),
+            SubscriptionChannel::Risk => write!(f, "risk"),
+            SubscriptionChannel::Ops => write!(f, "ops"),
+            SubscriptionChannel::OrderBook(event_id, option_id) => {
+                write!(f, "orderbook:{}:{}", event_id, option_id)
+            }
+            SubscriptionChannel::Trades(event_id, option_id) => {
+                write!(f, "trades:{}:{}", event_id, option_id)
+            }
+            SubscriptionChannel::Ticker(event_id, option_id) => {
+                write!(f, "ticker:{}:{}", event_id, option_id)
+            }
+            SubscriptionChannel::Candles(event_id, option_id, interval) => {
+                write!(
+                    f,
+                    "candles:{}:{}:{}",
+                    event_id,
+                    option_id,
+                    interval.as_str()
+                )
+            }
         }
     }
 }
 
 impl SubscriptionChannel {
+    /// The event a channel carries data for, if it is about a single event
+    pub fn event_id(&self) -> Option<i32> {
+        match self {
+            SubscriptionChannel::Event(event_id)
+            | SubscriptionChannel::OrderBook(event_id, _)
+            | SubscriptionChannel::Trades(event_id, _)
+            | SubscriptionChannel::Ticker(event_id, _)
+            | SubscriptionChannel::Candles(event_id, _, _) => Some(*event_id),
+            _ => None,
+        }
+    }
+
     pub fn from_string(s: &str) -> Option<Self> {
         match s {
             "events" => Some(SubscriptionChannel::Events),
             "transactions" => Some(SubscriptionChannel::Transactions),
             "portfolio" => Some(SubscriptionChannel::Portfolio),
+            "orders" => Some(SubscriptionChannel::Orders),
+            "risk" => Some(SubscriptionChannel::Risk),
+            "ops" => Some(SubscriptionChannel::Ops),
             _ => {
                 if let Some(id_str) = s.strip_prefix("event:") {
                     id_str.parse::<i32>().ok().map(SubscriptionChannel::Event)
+                } else if let Some(ids) = s.strip_prefix("orderbook:") {
+                    let (event_id, option_id) = ids.split_once(':')?;
+                    Some(SubscriptionChannel::OrderBook(
+                        event_id.parse().ok()?,
+                        option_id.parse().ok()?,
+                    ))
+                } else if let Some(ids) = s.strip_prefix("trades:") {
+                    let (event_id, option_id) = ids.split_once(':')?;
+                    Some(SubscriptionChannel::Trades(
+                        event_id.parse().ok()?,
+                        option_id.parse().ok()?,
+                    ))
+                } else if let Some(ids) = s.strip_prefix("ticker:") {
+                    let (event_id, option_id) = ids.split_once(':')?;
+                    Some(SubscriptionChannel::Ticker(
+                        event_id.parse().ok()?,
+                        option_id.parse().ok()?,
+                    ))
+                } else if let Some(ids) = s.strip_prefix("candles:") {
+                    let mut parts = ids.splitn(3, ':');
+                    Some(SubscriptionChannel::Candles(
+                        parts.next()?.parse().ok()?,
+                        parts.next()?.parse().ok()?,
+                        CandleInterval::parse(parts.next()?)?,
+                    ))
                 } else {
                     None
                 }
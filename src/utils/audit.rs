@@ -0,0 +1,78 @@
+use crate::types::audit::AuditAction;
+use actix_web::HttpRequest;
+use chrono::Utc;
+use entity::{audit_logs, events, support_tickets};
+use sea_orm::{ActiveModelTrait, ConnectionTrait, Set};
+use serde_json::{json, Value};
+
+/// A privileged change, with the target's state before and after it
+pub struct AuditEntry {
+    pub actor_id: i32,
+    pub action: AuditAction,
+    pub target_id: i32,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub ip: Option<String>,
+}
+
+/// Record a privileged change. Pass the transaction making the change so the
+/// change and its record are committed together.
+pub async fn record<C: ConnectionTrait>(db: &C, entry: AuditEntry) -> Result<(), String> {
+    audit_logs::ActiveModel {
+        actor_id: Set(entry.actor_id),
+        action: Set(entry.action.as_str().to_string()),
+        target_type: Set(entry.action.target_type().as_str().to_string()),
+        target_id: Set(entry.target_id),
+        before: Set(entry.before),
+        after: Set(entry.after),
+        ip: Set(entry.ip),
+        created_at: Set(Utc::now().fixed_offset()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(|e| format!("Failed to record audit log: {}", e))?;
+    Ok(())
+}
+
+/// The address a request came from, as the audit log records it
+pub fn client_ip(req: &HttpRequest) -> Option<String> {
+    req.connection_info()
+        .realip_remote_addr()
+        .map(str::to_string)
+}
+
+/// The fields of an event an admin can change, for before and after payloads
+pub fn event_snapshot(event: &events::Model) -> Value {
+    json!({
+        "title": event.title,
+        "description": event.description,
+        "category": event.category,
+        "status": event.status,
+        "end_time": event.end_time,
+        "min_bet_amount": event.min_bet_amount,
+        "max_bet_amount": event.max_bet_amount,
+        "image_url": event.image_url,
+        "min_price": event.min_price,
+        "max_price": event.max_price,
+        "tick_size": event.tick_size,
+        "max_order_quantity": event.max_order_quantity,
+        "execution_venue": event.execution_venue,
+        "pricing_strategy": event.pricing_strategy,
+        "complement_pricing": event.complement_pricing,
+        "winning_option_id": event.winning_option_id,
+        "resolved_by": event.resolved_by,
+        "resolution_note": event.resolution_note,
+        "resolved_at": event.resolved_at,
+    })
+}
+
+/// The fields of a support ticket staff can change, for before and after
+/// payloads
+pub fn ticket_snapshot(ticket: &support_tickets::Model) -> Value {
+    json!({
+        "status": ticket.status,
+        "assigned_to": ticket.assigned_to,
+        "resolved_at": ticket.resolved_at,
+    })
+}
@@ -0,0 +1,215 @@
+use deadpool_redis::{
+    redis::{AsyncCommands, Script},
+    Connection, Pool,
+};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::constants::config;
+use crate::utils::jwt::create_jwt_token;
+
+/// A login session. Its access tokens are only accepted while it is stored;
+/// revoking it deletes it.
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredSession {
+    user_id: i32,
+    /// SHA-256 of the secret half of the session's current refresh token
+    refresh_hash: String,
+}
+
+/// Tokens issued for a session at login or refresh
+#[derive(Debug, Serialize)]
+pub struct SessionTokens {
+    pub token: String,
+    pub refresh_token: String,
+    /// Seconds until `token` expires
+    pub expires_in: i64,
+}
+
+fn session_key(session_id: &str) -> String {
+    format!("auth_session:{}", session_id)
+}
+
+fn user_sessions_key(user_id: i32) -> String {
+    format!("auth_user_sessions:{}", user_id)
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+fn refresh_ttl_seconds() -> u64 {
+    (config::get_refresh_token_ttl_days().max(1) * 86_400) as u64
+}
+
+lazy_static! {
+    /// Replaces the session in KEYS[1] with ARGV[2], expiring in ARGV[3]
+    /// seconds, if it still holds ARGV[1]. Returns 1 if it was replaced, so
+    /// of two refreshes presenting the same token only one rotates it.
+    static ref ROTATE_SESSION_SCRIPT: Script = Script::new(
+        r#"
+        if redis.call('GET', KEYS[1]) == ARGV[1] then
+            redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[3])
+            return 1
+        end
+        return 0
+        "#
+    );
+}
+
+/// A new refresh token secret for a session of `user_id`, with the session
+/// to store for it
+fn new_session(user_id: i32) -> Result<(String, String), String> {
+    let secret = uuid::Uuid::new_v4().simple().to_string();
+    let session = StoredSession {
+        user_id,
+        refresh_hash: hash_secret(&secret),
+    };
+    let serialized = serde_json::to_string(&session)
+        .map_err(|e| format!("Failed to serialize session: {}", e))?;
+    Ok((secret, serialized))
+}
+
+/// Store a session under `session_id` with a new refresh token, and sign an
+/// access token for it
+async fn issue(pool: &Pool, session_id: &str, user_id: i32) -> Result<SessionTokens, String> {
+    let (secret, serialized) = new_session(user_id)?;
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to reach session store: {}", e))?;
+    conn.set_ex::<_, _, ()>(session_key(session_id), serialized, refresh_ttl_seconds())
+        .await
+        .map_err(|e| format!("Failed to store session: {}", e))?;
+    tokens(&mut conn, session_id, user_id, &secret).await
+}
+
+/// Index a stored session under its user and sign an access token for it
+async fn tokens(
+    conn: &mut Connection,
+    session_id: &str,
+    user_id: i32,
+    secret: &str,
+) -> Result<SessionTokens, String> {
+    conn.sadd::<_, _, ()>(user_sessions_key(user_id), session_id)
+        .await
+        .map_err(|e| format!("Failed to index session: {}", e))?;
+    conn.expire::<_, ()>(user_sessions_key(user_id), refresh_ttl_seconds() as i64)
+        .await
+        .map_err(|e| format!("Failed to index session: {}", e))?;
+
+    let token = create_jwt_token(&user_id.to_string(), session_id)
+        .map_err(|e| format!("Failed to sign access token: {}", e))?;
+    Ok(SessionTokens {
+        token,
+        refresh_token: format!("{}.{}", session_id, secret),
+        expires_in: config::get_access_token_ttl_minutes() * 60,
+    })
+}
+
+/// Start a session for a user who just logged in or registered
+pub async fn start(pool: &Pool, user_id: i32) -> Result<SessionTokens, String> {
+    let session_id = uuid::Uuid::new_v4().simple().to_string();
+    issue(pool, &session_id, user_id).await
+}
+
+/// Exchange a refresh token for a new access token and refresh token. Each
+/// refresh token works once; presenting one that was already exchanged
+/// revokes the session, since it may have been stolen. The new token replaces
+/// the old one only if the session still holds it, so concurrent refreshes
+/// with one token can't both succeed. Returns `Ok(None)` if the token is
+/// invalid or its session was revoked.
+pub async fn refresh(pool: &Pool, refresh_token: &str) -> Result<Option<SessionTokens>, String> {
+    let Some((session_id, secret)) = refresh_token.split_once('.') else {
+        return Ok(None);
+    };
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to reach session store: {}", e))?;
+    let stored: Option<String> = conn
+        .get(session_key(session_id))
+        .await
+        .map_err(|e| format!("Failed to load session: {}", e))?;
+    let Some(stored) = stored else {
+        return Ok(None);
+    };
+    let session: StoredSession =
+        serde_json::from_str(&stored).map_err(|e| format!("Failed to parse session: {}", e))?;
+
+    let rotated = if session.refresh_hash == hash_secret(secret) {
+        let (new_secret, serialized) = new_session(session.user_id)?;
+        let replaced: i32 = ROTATE_SESSION_SCRIPT
+            .key(session_key(session_id))
+            .arg(&stored)
+            .arg(serialized)
+            .arg(refresh_ttl_seconds())
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to rotate session: {}", e))?;
+        (replaced == 1).then_some(new_secret)
+    } else {
+        None
+    };
+
+    let Some(new_secret) = rotated else {
+        log::warn!(
+            "Reused refresh token for session {} of user {}, revoking it",
+            session_id,
+            session.user_id
+        );
+        revoke(pool, session.user_id, session_id).await?;
+        return Ok(None);
+    };
+
+    tokens(&mut conn, session_id, session.user_id, &new_secret)
+        .await
+        .map(Some)
+}
+
+/// Whether a session hasn't expired or been revoked
+pub async fn is_active(pool: &Pool, session_id: &str) -> Result<bool, String> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to reach session store: {}", e))?;
+    conn.exists(session_key(session_id))
+        .await
+        .map_err(|e| format!("Failed to check session: {}", e))
+}
+
+/// Revoke one session, as on logout
+pub async fn revoke(pool: &Pool, user_id: i32, session_id: &str) -> Result<(), String> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to reach session store: {}", e))?;
+    conn.del::<_, ()>(session_key(session_id))
+        .await
+        .map_err(|e| format!("Failed to revoke session: {}", e))?;
+    conn.srem::<_, _, ()>(user_sessions_key(user_id), session_id)
+        .await
+        .map_err(|e| format!("Failed to revoke session: {}", e))
+}
+
+/// Revoke every session of a user, as on a password change
+pub async fn revoke_all(pool: &Pool, user_id: i32) -> Result<(), String> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to reach session store: {}", e))?;
+    let session_ids: Vec<String> = conn
+        .smembers(user_sessions_key(user_id))
+        .await
+        .map_err(|e| format!("Failed to list sessions: {}", e))?;
+    for session_id in &session_ids {
+        conn.del::<_, ()>(session_key(session_id))
+            .await
+            .map_err(|e| format!("Failed to revoke session: {}", e))?;
+    }
+    conn.del::<_, ()>(user_sessions_key(user_id))
+        .await
+        .map_err(|e| format!("Failed to revoke sessions: {}", e))
+}
@@ -47,7 +47,6 @@ impl CacheService {
     }
 
     /// Set a value in cache without expiration
-    #[allow(dead_code)]
     pub async fn set_persistent<T>(
         &self,
         key: &str,
@@ -81,7 +80,6 @@ impl CacheService {
     }
 
     /// Set expiration for an existing key
-    #[allow(dead_code)]
     pub async fn expire(
         &self,
         key: &str,
@@ -101,7 +99,6 @@ impl CacheService {
     }
 
     /// Increment a numeric value
-    #[allow(dead_code)]
     pub async fn increment(
         &self,
         key: &str,
@@ -130,12 +127,18 @@ pub fn create_cache_key(prefix: &str, identifier: &str) -> String {
     format!("{}:{}", prefix, identifier)
 }
 
+/// Cache key for data that differs per tenant, such as event listings
+pub fn tenant_cache_key(tenant_id: i32, key: &str) -> String {
+    format!("tenant:{}:{}", tenant_id, key)
+}
+
 /// Common cache key prefixes
 pub mod cache_keys {
     pub const USER_PREFIX: &str = "user";
     #[allow(dead_code)]
     pub const SESSION_PREFIX: &str = "session";
     pub const EVENT_PREFIX: &str = "event";
+    pub const OG_CARD_PREFIX: &str = "og_card";
     #[allow(dead_code)]
     pub const TRANSACTION_PREFIX: &str = "transaction";
 }
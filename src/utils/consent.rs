@@ -0,0 +1,55 @@
+use crate::settings::registry as settings;
+use crate::types::consent::{ConsentStatusResponse, PolicyDocument};
+use chrono::Utc;
+use entity::user_consents;
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder};
+
+/// The version of a policy users of a tenant must have accepted
+pub fn current_version(tenant_id: Option<i32>, document: PolicyDocument) -> i32 {
+    i32::try_from(settings::integer(tenant_id, document.version_setting())).unwrap_or(i32::MAX)
+}
+
+/// Where a user stands with each policy: the latest version they accepted
+/// against the version now in force
+pub async fn statuses<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    tenant_id: Option<i32>,
+) -> Result<Vec<ConsentStatusResponse>, DbErr> {
+    let consents = user_consents::Entity::find()
+        .filter(user_consents::Column::UserId.eq(user_id))
+        .order_by_desc(user_consents::Column::Version)
+        .all(conn)
+        .await?;
+
+    Ok(PolicyDocument::ALL
+        .into_iter()
+        .map(|document| {
+            let current_version = current_version(tenant_id, document);
+            let latest = consents
+                .iter()
+                .find(|consent| consent.document == document.as_str());
+            ConsentStatusResponse {
+                document,
+                current_version,
+                accepted_version: latest.map(|consent| consent.version),
+                accepted_at: latest.map(|consent| consent.accepted_at.with_timezone(&Utc)),
+                up_to_date: latest.is_some_and(|consent| consent.version >= current_version),
+            }
+        })
+        .collect())
+}
+
+/// Policies whose current version the user hasn't accepted
+pub async fn outstanding<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    tenant_id: Option<i32>,
+) -> Result<Vec<PolicyDocument>, DbErr> {
+    Ok(statuses(conn, user_id, tenant_id)
+        .await?
+        .into_iter()
+        .filter(|status| !status.up_to_date)
+        .map(|status| status.document)
+        .collect())
+}
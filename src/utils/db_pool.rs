@@ -0,0 +1,121 @@
+use crate::constants::config;
+use crate::types::metrics::PoolMetrics;
+use sea_orm::{ConnectOptions, Database, DatabaseConnection, DbErr};
+use std::time::Duration;
+
+/// The kind of work a connection pool serves. Each workload gets its own
+/// pool, so a slow export can't hold the connections order placement needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workload {
+    /// Order placement, settlement and everything else users wait on
+    Trading,
+    /// Exports, reports and audits. Only reads, so it may point at a replica
+    Reporting,
+}
+
+impl Workload {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Workload::Trading => "trading",
+            Workload::Reporting => "reporting",
+        }
+    }
+
+    fn max_connections(&self) -> u32 {
+        match self {
+            Workload::Trading => config::get_db_max_connections(),
+            Workload::Reporting => config::get_reporting_db_max_connections(),
+        }
+    }
+
+    fn min_connections(&self) -> u32 {
+        match self {
+            Workload::Trading => config::get_db_min_connections(),
+            Workload::Reporting => config::get_reporting_db_min_connections(),
+        }
+    }
+
+    fn acquire_timeout(&self) -> Duration {
+        Duration::from_secs(match self {
+            Workload::Trading => config::get_db_acquire_timeout_seconds(),
+            Workload::Reporting => config::get_reporting_db_acquire_timeout_seconds(),
+        })
+    }
+
+    fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(match self {
+            Workload::Trading => config::get_db_idle_timeout_seconds(),
+            Workload::Reporting => config::get_reporting_db_idle_timeout_seconds(),
+        })
+    }
+
+    fn statement_timeout_ms(&self) -> u64 {
+        match self {
+            Workload::Trading => config::get_db_statement_timeout_ms(),
+            Workload::Reporting => config::get_reporting_db_statement_timeout_ms(),
+        }
+    }
+}
+
+/// The reporting pool, registered as app data next to the trading pool's
+/// `DatabaseConnection`
+#[derive(Clone)]
+pub struct ReportingDb(DatabaseConnection);
+
+impl ReportingDb {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self(db)
+    }
+
+    pub fn connection(&self) -> &DatabaseConnection {
+        &self.0
+    }
+}
+
+/// `url` with a server-side `statement_timeout`, unless it is 0 or the URL
+/// already sets one
+fn with_statement_timeout(url: &str, timeout_ms: u64) -> String {
+    if timeout_ms == 0 || url.contains("statement_timeout") {
+        return url.to_string();
+    }
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!(
+        "{}{}options[statement_timeout]={}",
+        url, separator, timeout_ms
+    )
+}
+
+/// Open the connection pool for a workload, sized and timed out as configured
+pub async fn connect(url: &str, workload: Workload) -> Result<DatabaseConnection, DbErr> {
+    let mut options =
+        ConnectOptions::new(with_statement_timeout(url, workload.statement_timeout_ms()));
+    options
+        .max_connections(workload.max_connections())
+        .min_connections(workload.min_connections())
+        .connect_timeout(Duration::from_secs(config::get_db_connect_timeout_seconds()))
+        .acquire_timeout(workload.acquire_timeout())
+        .idle_timeout(workload.idle_timeout())
+        .max_lifetime(Duration::from_secs(config::get_db_max_lifetime_seconds()));
+
+    let db = Database::connect(options).await?;
+    log::info!(
+        "Connected {} database pool ({}-{} connections)",
+        workload.as_str(),
+        workload.min_connections(),
+        workload.max_connections()
+    );
+    Ok(db)
+}
+
+/// How many of a pool's connections are open and in use, for `/metrics`
+pub fn pool_metrics(db: &DatabaseConnection, workload: Workload) -> PoolMetrics {
+    let pool = db.get_postgres_connection_pool();
+    let open = pool.size();
+    let idle = pool.num_idle() as u32;
+    PoolMetrics {
+        workload: workload.as_str(),
+        max_connections: workload.max_connections(),
+        open_connections: open,
+        in_use_connections: open.saturating_sub(idle),
+    }
+}
@@ -0,0 +1,42 @@
+use serde_json::Value;
+
+/// Parse a `fields=` list against the fields a listing can return.
+/// No list means the full objects; `id` is always kept so clients can
+/// still key what they get back
+pub fn parse(raw: Option<&str>, allowed: &[&str]) -> Result<Option<Vec<String>>, String> {
+    let raw = match raw.map(str::trim) {
+        Some(raw) if !raw.is_empty() => raw,
+        _ => return Ok(None),
+    };
+
+    let mut fields = vec!["id".to_string()];
+    for field in raw.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        if !allowed.contains(&field) {
+            return Err(format!("Unknown field '{}'", field));
+        }
+        if !fields.iter().any(|f| f == field) {
+            fields.push(field.to_string());
+        }
+    }
+    Ok(Some(fields))
+}
+
+/// Keep only the selected keys on each object of a serialized listing
+pub fn project(value: Value, fields: Option<&[String]>) -> Value {
+    let Some(fields) = fields else {
+        return value;
+    };
+    match value {
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| project(item, Some(fields)))
+                .collect(),
+        ),
+        Value::Object(mut object) => {
+            object.retain(|key, _| fields.iter().any(|f| f == key));
+            Value::Object(object)
+        }
+        other => other,
+    }
+}
@@ -8,18 +8,22 @@ use crate::constants;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // Subject (user ID)
+    pub sid: String, // Login session, see utils::auth_session
     pub exp: usize,  // Expiration time
     pub iat: usize,  // Issued at
 }
 
-pub fn create_jwt_token(user_id: &str) -> Result<String, JwtError> {
+/// Sign a short-lived access token for one of a user's login sessions
+pub fn create_jwt_token(user_id: &str, session_id: &str) -> Result<String, JwtError> {
     let secret = constants::config::get_jwt_secret();
 
     let now = chrono::Utc::now();
-    let exp = (now + chrono::Duration::hours(24)).timestamp() as usize; // 24 hours
+    let exp = (now + chrono::Duration::minutes(constants::config::get_access_token_ttl_minutes()))
+        .timestamp() as usize;
 
     let claims = Claims {
         sub: user_id.to_string(),
+        sid: session_id.to_string(),
         exp,
         iat: now.timestamp() as usize,
     };
@@ -42,8 +46,3 @@ pub fn validate_jwt_token(token: &str) -> Result<Claims, JwtError> {
 
     Ok(token_data.claims)
 }
-
-pub fn verify_jwt_token(token: &str) -> Result<String, JwtError> {
-    let claims = validate_jwt_token(token)?;
-    Ok(claims.sub)
-}
@@ -0,0 +1,92 @@
+use crate::constants::config;
+use crate::settings::registry as settings;
+use crate::types::kyc::{AgeStatus, KycStatus};
+use crate::types::settings::SettingKey;
+use chrono::{NaiveDate, Utc};
+use entity::{transaction, users};
+use rust_decimal::Decimal;
+use sea_orm::{
+    prelude::Expr, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QuerySelect,
+};
+
+/// Why `user` may not deposit `amount` in `currency`, if it would take their
+/// lifetime deposits in that currency past the threshold without a verified
+/// identity
+pub async fn deposit_refusal<C: ConnectionTrait>(
+    conn: &C,
+    user: &users::Model,
+    currency: &str,
+    amount: Decimal,
+) -> Result<Option<String>, DbErr> {
+    if user.kyc_status == KycStatus::Verified.as_str() {
+        return Ok(None);
+    }
+
+    let deposited: Option<Decimal> = transaction::Entity::find()
+        .select_only()
+        .column_as(
+            Expr::cust("COALESCE(SUM(transaction.amount), 0)"),
+            "deposited",
+        )
+        .filter(transaction::Column::UserId.eq(user.id))
+        .filter(transaction::Column::Type.eq("deposit"))
+        .filter(transaction::Column::Currency.eq(currency))
+        .filter(transaction::Column::Status.eq("completed"))
+        .into_tuple()
+        .one(conn)
+        .await?;
+
+    let threshold = Decimal::from(config::get_kyc_deposit_threshold());
+    if deposited.unwrap_or_default() + amount > threshold {
+        return Ok(Some(format!(
+            "Identity verification is required to deposit more than {} {} in total",
+            threshold, currency
+        )));
+    }
+    Ok(None)
+}
+
+/// The age users of a tenant must have reached to deposit or trade
+pub fn minimum_age(tenant_id: Option<i32>) -> u32 {
+    u32::try_from(settings::integer(tenant_id, SettingKey::MinimumAge)).unwrap_or(u32::MAX)
+}
+
+/// Whether a date of birth could be real: not in the future, nor before 1900
+pub fn is_plausible_date_of_birth(date_of_birth: NaiveDate) -> bool {
+    NaiveDate::from_ymd_opt(1900, 1, 1).is_some_and(|earliest| date_of_birth >= earliest)
+        && date_of_birth <= Utc::now().date_naive()
+}
+
+/// Whole years from `date_of_birth` to today
+pub fn age(date_of_birth: NaiveDate) -> u32 {
+    Utc::now()
+        .date_naive()
+        .years_since(date_of_birth)
+        .unwrap_or(0)
+}
+
+/// The status a date of birth given by the user earns them
+pub fn declared_age_status(date_of_birth: NaiveDate, tenant_id: Option<i32>) -> AgeStatus {
+    if age(date_of_birth) >= minimum_age(tenant_id) {
+        AgeStatus::Declared
+    } else {
+        AgeStatus::Rejected
+    }
+}
+
+/// Why `user` may not deposit or trade on account of their age, if anything.
+/// The date of birth is checked against the tenant's current minimum, so
+/// raising it applies to users already declared or verified.
+pub fn age_refusal(user: &users::Model) -> Option<String> {
+    let minimum = minimum_age(Some(user.tenant_id));
+    let too_young = || format!("You must be at least {} to deposit or trade", minimum);
+
+    match AgeStatus::parse(&user.age_status).unwrap_or(AgeStatus::Unverified) {
+        AgeStatus::Unverified => Some("Add your date of birth to deposit or trade".to_string()),
+        AgeStatus::Rejected => Some(too_young()),
+        AgeStatus::Declared | AgeStatus::Verified => user
+            .date_of_birth
+            .filter(|date_of_birth| age(*date_of_birth) < minimum)
+            .map(|_| too_young()),
+    }
+}
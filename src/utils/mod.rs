@@ -1,6 +1,20 @@
+pub mod api_key;
+pub mod audit;
 pub mod auth;
+pub mod auth_session;
 pub mod cache;
+pub mod consent;
+pub mod db_pool;
+pub mod fields;
 pub mod helpers;
+pub mod internal_token;
 pub mod jwt;
+pub mod kyc;
+pub mod og_card;
 pub mod pagination;
+pub mod payload_metrics;
+pub mod permissions;
+pub mod query_metrics;
+pub mod totp;
+pub mod two_factor;
 pub mod user;
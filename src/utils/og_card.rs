@@ -0,0 +1,56 @@
+use crate::types::event::OgCardResponse;
+use sea_orm::prelude::Decimal;
+
+const WIDTH: u32 = 1200;
+const HEIGHT: u32 = 630;
+/// Longest title drawn before it is cut short with an ellipsis
+const MAX_TITLE_CHARS: usize = 70;
+
+/// Render a share card as a 1200x630 SVG, the size link previews expect
+pub fn render_svg(card: &OgCardResponse) -> String {
+    let title = truncate(&card.title, MAX_TITLE_CHARS);
+    let (leader, probability) = match &card.leading_option {
+        Some(option) => (
+            truncate(&option.option_text, 40),
+            format!(
+                "{}%",
+                (option.implied_probability * Decimal::ONE_HUNDRED).round_dp(0)
+            ),
+        ),
+        None => ("No options yet".to_string(), String::new()),
+    };
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">
+<rect width="{w}" height="{h}" fill="#0f172a"/>
+<text x="60" y="90" font-family="sans-serif" font-size="28" fill="#94a3b8">{category}</text>
+<text x="60" y="170" font-family="sans-serif" font-size="52" font-weight="bold" fill="#f8fafc">{title}</text>
+<text x="60" y="360" font-family="sans-serif" font-size="40" fill="#e2e8f0">{leader}</text>
+<text x="60" y="470" font-family="sans-serif" font-size="96" font-weight="bold" fill="#22c55e">{probability}</text>
+<text x="60" y="570" font-family="sans-serif" font-size="28" fill="#94a3b8">Volume {volume}</text>
+</svg>"##,
+        w = WIDTH,
+        h = HEIGHT,
+        category = escape(&card.category),
+        title = escape(&title),
+        leader = escape(&leader),
+        probability = probability,
+        volume = card.total_volume.round_dp(2),
+    )
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,84 @@
+use crate::constants::config;
+use crate::types::metrics::RoutePayloadMetrics;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Routes allowed a different budget than `RESPONSE_SIZE_BUDGET_BYTES`, keyed
+/// by method and route pattern
+const ROUTE_BUDGETS: &[(&str, u64)] = &[
+    ("GET /events", 200 * 1024),
+    ("GET /public/events", 200 * 1024),
+];
+
+/// Minimum time between alerts for the same route, so a hot endpoint that is
+/// over budget doesn't flood the ops channel
+const ALERT_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Default)]
+struct PayloadAggregate {
+    responses: u64,
+    bytes: u64,
+    max_bytes: u64,
+    over_budget: u64,
+    last_alert: Option<Instant>,
+}
+
+lazy_static! {
+    static ref PAYLOAD_AGGREGATES: Mutex<HashMap<String, PayloadAggregate>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Budget for a route, in uncompressed bytes
+pub fn budget_for(route: &str) -> u64 {
+    ROUTE_BUDGETS
+        .iter()
+        .find(|(r, _)| *r == route)
+        .map(|(_, budget)| *budget)
+        .unwrap_or_else(config::get_response_size_budget_bytes)
+}
+
+/// Fold a response size into the per-route aggregates. Returns the budget when
+/// the response exceeded it and the route is due an alert.
+pub fn record_response(route: &str, bytes: u64) -> Option<u64> {
+    let budget = budget_for(route);
+
+    let mut aggregates = PAYLOAD_AGGREGATES.lock().unwrap_or_else(|e| e.into_inner());
+    let aggregate = aggregates.entry(route.to_string()).or_default();
+    aggregate.responses += 1;
+    aggregate.bytes += bytes;
+    aggregate.max_bytes = aggregate.max_bytes.max(bytes);
+    if bytes <= budget {
+        return None;
+    }
+
+    aggregate.over_budget += 1;
+    let due = aggregate
+        .last_alert
+        .is_none_or(|at| at.elapsed() >= ALERT_INTERVAL);
+    if due {
+        aggregate.last_alert = Some(Instant::now());
+        Some(budget)
+    } else {
+        None
+    }
+}
+
+/// Per-route response sizes ordered by average size, largest first
+pub fn route_payload_metrics() -> Vec<RoutePayloadMetrics> {
+    let aggregates = PAYLOAD_AGGREGATES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut metrics: Vec<RoutePayloadMetrics> = aggregates
+        .iter()
+        .map(|(route, a)| RoutePayloadMetrics {
+            route: route.clone(),
+            responses: a.responses,
+            avg_bytes: a.bytes as f64 / a.responses.max(1) as f64,
+            max_bytes: a.max_bytes,
+            budget_bytes: budget_for(route),
+            over_budget_responses: a.over_budget,
+        })
+        .collect();
+    metrics.sort_by(|a, b| b.avg_bytes.total_cmp(&a.avg_bytes));
+    metrics
+}
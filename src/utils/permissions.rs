@@ -0,0 +1,54 @@
+use crate::middleware::auth::AuthenticatedUser;
+use crate::types::rbac::Permission;
+use entity::{role_permissions, user_roles};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, JoinType, QueryFilter, QuerySelect,
+    RelationTrait,
+};
+use std::collections::HashSet;
+
+/// Permissions granted to a user by their roles
+pub async fn granted_by_roles(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<HashSet<Permission>, DbErr> {
+    let permissions: Vec<String> = role_permissions::Entity::find()
+        .select_only()
+        .column(role_permissions::Column::Permission)
+        .join(JoinType::InnerJoin, role_permissions::Relation::Roles.def())
+        .join(
+            JoinType::InnerJoin,
+            entity::roles::Relation::UserRoles.def(),
+        )
+        .filter(user_roles::Column::UserId.eq(user_id))
+        .distinct()
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    Ok(permissions
+        .iter()
+        .filter_map(|permission| Permission::parse(permission))
+        .collect())
+}
+
+/// Whether an authenticated user holds a permission. Admins hold them all.
+/// Requests signed with an API key carry the `user` role, and roles don't
+/// apply to them, so keys never act with staff permissions.
+pub async fn has_permission(
+    db: &DatabaseConnection,
+    auth_user: &AuthenticatedUser,
+    via_session: bool,
+    permission: Permission,
+) -> Result<bool, DbErr> {
+    if auth_user.role == "admin" {
+        return Ok(true);
+    }
+    if !via_session {
+        return Ok(false);
+    }
+    let Ok(user_id) = auth_user.id.parse::<i32>() else {
+        return Ok(false);
+    };
+    Ok(granted_by_roles(db, user_id).await?.contains(&permission))
+}
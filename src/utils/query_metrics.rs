@@ -0,0 +1,108 @@
+use crate::constants::config;
+use crate::types::metrics::RouteQueryMetrics;
+use lazy_static::lazy_static;
+use sea_orm::metric::Info;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Query count and database time accumulated while serving a single request
+#[derive(Default)]
+pub struct RequestQueryStats {
+    queries: AtomicU64,
+    db_time_micros: AtomicU64,
+}
+
+impl RequestQueryStats {
+    pub fn queries(&self) -> u64 {
+        self.queries.load(Ordering::Relaxed)
+    }
+
+    pub fn db_time(&self) -> Duration {
+        Duration::from_micros(self.db_time_micros.load(Ordering::Relaxed))
+    }
+}
+
+tokio::task_local! {
+    pub static REQUEST_QUERY_STATS: Arc<RequestQueryStats>;
+}
+
+#[derive(Default)]
+struct RouteAggregate {
+    requests: u64,
+    queries: u64,
+    max_queries: u64,
+    db_time_micros: u64,
+    max_db_time_micros: u64,
+    over_budget: u64,
+}
+
+lazy_static! {
+    static ref ROUTE_AGGREGATES: Mutex<HashMap<String, RouteAggregate>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Metric callback registered on the database connection.
+///
+/// Attributes each query to the request being served on the current task and
+/// logs individual statements slower than the configured threshold.
+pub fn record_query(info: &Info<'_>) {
+    let _ = REQUEST_QUERY_STATS.try_with(|stats| {
+        stats.queries.fetch_add(1, Ordering::Relaxed);
+        stats
+            .db_time_micros
+            .fetch_add(info.elapsed.as_micros() as u64, Ordering::Relaxed);
+    });
+
+    if info.elapsed.as_millis() as u64 >= config::get_slow_query_threshold_ms() {
+        log::warn!(
+            "Slow query ({} ms{}): {}",
+            info.elapsed.as_millis(),
+            if info.failed { ", failed" } else { "" },
+            info.statement.sql
+        );
+    }
+}
+
+/// Fold a finished request into the per-route aggregates, returning whether it
+/// exceeded the query budget
+pub fn record_request(route: &str, stats: &RequestQueryStats) -> bool {
+    let queries = stats.queries();
+    let db_time_micros = stats.db_time().as_micros() as u64;
+    let over_budget = queries > config::get_query_budget_per_request();
+
+    let mut aggregates = ROUTE_AGGREGATES.lock().unwrap_or_else(|e| e.into_inner());
+    let aggregate = aggregates.entry(route.to_string()).or_default();
+    aggregate.requests += 1;
+    aggregate.queries += queries;
+    aggregate.max_queries = aggregate.max_queries.max(queries);
+    aggregate.db_time_micros += db_time_micros;
+    aggregate.max_db_time_micros = aggregate.max_db_time_micros.max(db_time_micros);
+    if over_budget {
+        aggregate.over_budget += 1;
+    }
+
+    over_budget
+}
+
+/// Per-route aggregates ordered by total query count, heaviest first
+pub fn route_metrics() -> Vec<RouteQueryMetrics> {
+    let aggregates = ROUTE_AGGREGATES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut metrics: Vec<RouteQueryMetrics> = aggregates
+        .iter()
+        .map(|(route, a)| RouteQueryMetrics {
+            route: route.clone(),
+            requests: a.requests,
+            total_queries: a.queries,
+            avg_queries: a.queries as f64 / a.requests.max(1) as f64,
+            max_queries: a.max_queries,
+            total_db_time_ms: a.db_time_micros as f64 / 1000.0,
+            avg_db_time_ms: a.db_time_micros as f64 / 1000.0 / a.requests.max(1) as f64,
+            max_db_time_ms: a.max_db_time_micros as f64 / 1000.0,
+            over_budget_requests: a.over_budget,
+        })
+        .collect();
+    metrics.sort_by_key(|m| std::cmp::Reverse(m.total_queries));
+    metrics
+}
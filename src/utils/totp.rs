@@ -0,0 +1,168 @@
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::constants::config;
+
+type HmacSha1 = Hmac<Sha1>;
+
+/// Seconds each code is valid for, as authenticator apps assume
+const STEP_SECONDS: i64 = 30;
+/// Digits in a code
+const DIGITS: u32 = 6;
+/// Steps either side of the current one that are still accepted, to allow
+/// for clock drift
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+/// Backup codes issued at once
+pub const BACKUP_CODE_COUNT: usize = 10;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Unpadded RFC 4648 base32, the encoding authenticator apps take secrets in
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    encoded
+}
+
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in encoded.trim_end_matches('=').bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// A new random 160-bit secret, base32-encoded
+pub fn generate_secret() -> String {
+    let mut bytes = Vec::with_capacity(32);
+    bytes.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    bytes.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    base32_encode(&bytes[..20])
+}
+
+/// `otpauth://` URI an authenticator app enrolls from, usually shown as a QR
+/// code
+pub fn provisioning_uri(account: &str, secret: &str) -> String {
+    let issuer = urlencode(&config::get_totp_issuer());
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer,
+        urlencode(account),
+        secret,
+        issuer,
+        DIGITS,
+        STEP_SECONDS
+    )
+}
+
+fn urlencode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// RFC 6238 code for a time step
+fn code_at(key: &[u8], step: i64) -> String {
+    let mut mac = HmacSha1::new_from_slice(key).expect("HMAC takes any key length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    )
+}
+
+/// The time step `code` is valid for, if it is valid for the current time or
+/// a step either side of it. Callers record the step so a code can't be used
+/// twice.
+pub fn verify(secret: &str, code: &str) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let key = base32_decode(secret)?;
+    let current = chrono::Utc::now().timestamp() / STEP_SECONDS;
+
+    (current - ALLOWED_DRIFT_STEPS..=current + ALLOWED_DRIFT_STEPS).find(|step| {
+        let expected = code_at(&key, *step);
+        // Compare every byte so timing doesn't reveal how much matched
+        expected
+            .bytes()
+            .zip(code.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+    })
+}
+
+/// New single-use backup codes, formatted `xxxxx-xxxxx`
+pub fn generate_backup_codes() -> Vec<String> {
+    (0..BACKUP_CODE_COUNT)
+        .map(|_| {
+            let random = uuid::Uuid::new_v4().simple().to_string();
+            format!("{}-{}", &random[..5], &random[5..10])
+        })
+        .collect()
+}
+
+/// Hash of a backup code as kept in `user_security.backup_codes`. Dashes and
+/// case are ignored, so codes can be typed either way.
+pub fn hash_backup_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+/// Backup code hashes as stored, comma-separated
+pub fn join_backup_hashes(hashes: &[String]) -> String {
+    hashes.join(",")
+}
+
+/// Backup code hashes stored in `user_security.backup_codes`
+pub fn split_backup_hashes(stored: &str) -> Vec<String> {
+    stored
+        .split(',')
+        .filter(|hash| !hash.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,190 @@
+use chrono::Utc;
+use deadpool_redis::{redis::AsyncCommands, Pool};
+use entity::user_security;
+use sea_orm::{
+    sea_query::{Condition, Expr},
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+};
+
+use crate::utils::totp;
+
+/// Wrong codes a user may enter before two-factor checks are locked
+const MAX_FAILED_ATTEMPTS: i64 = 5;
+/// How long failed attempts are remembered, and so how long a lock lasts
+const FAILED_ATTEMPTS_WINDOW_SECONDS: i64 = 900;
+
+/// Outcome of checking a two-factor code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeCheck {
+    Valid,
+    Invalid,
+    /// Too many wrong codes were entered recently; the code wasn't checked
+    Locked,
+}
+
+fn failures_key(user_id: i32) -> String {
+    format!("two_factor:failures:{}", user_id)
+}
+
+/// The user's two-factor settings, if they ever enrolled
+pub async fn find(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Option<user_security::Model>, DbErr> {
+    user_security::Entity::find()
+        .filter(user_security::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+}
+
+/// Check a code from the user's authenticator app or one of their backup
+/// codes, using it up so it can't be presented again. With
+/// `allow_backup_code` false only an authenticator code is accepted.
+pub async fn check_code(
+    db: &DatabaseConnection,
+    redis_pool: Option<&Pool>,
+    security: &user_security::Model,
+    code: &str,
+    allow_backup_code: bool,
+) -> Result<CodeCheck, String> {
+    if let Some(pool) = redis_pool {
+        if failed_attempts(pool, security.user_id).await >= MAX_FAILED_ATTEMPTS {
+            return Ok(CodeCheck::Locked);
+        }
+    }
+
+    let valid = use_totp_code(db, security, code).await?
+        || (allow_backup_code && use_backup_code(db, security, code).await?);
+
+    if valid {
+        return Ok(CodeCheck::Valid);
+    }
+    if let Some(pool) = redis_pool {
+        record_failure(pool, security.user_id).await;
+    }
+    Ok(CodeCheck::Invalid)
+}
+
+/// Accept an authenticator code for a later time step than any used before,
+/// so each code works once
+async fn use_totp_code(
+    db: &DatabaseConnection,
+    security: &user_security::Model,
+    code: &str,
+) -> Result<bool, String> {
+    let Some(secret) = &security.totp_secret else {
+        return Ok(false);
+    };
+    let Some(step) = totp::verify(secret, code) else {
+        return Ok(false);
+    };
+
+    let result = user_security::Entity::update_many()
+        .col_expr(user_security::Column::LastTotpStep, Expr::value(step))
+        .col_expr(
+            user_security::Column::UpdatedAt,
+            Expr::value(Utc::now().fixed_offset()),
+        )
+        .filter(user_security::Column::Id.eq(security.id))
+        .filter(
+            Condition::any()
+                .add(user_security::Column::LastTotpStep.is_null())
+                .add(user_security::Column::LastTotpStep.lt(step)),
+        )
+        .exec(db)
+        .await
+        .map_err(|e| format!("Failed to record two-factor code: {}", e))?;
+    Ok(result.rows_affected == 1)
+}
+
+/// Accept a backup code and strike it from the list. The update only
+/// applies if the list is unchanged, so two requests can't share a code.
+async fn use_backup_code(
+    db: &DatabaseConnection,
+    security: &user_security::Model,
+    code: &str,
+) -> Result<bool, String> {
+    let hash = totp::hash_backup_code(code);
+    let mut hashes = totp::split_backup_hashes(&security.backup_codes);
+    let Some(index) = hashes.iter().position(|h| *h == hash) else {
+        return Ok(false);
+    };
+    hashes.remove(index);
+
+    let result = user_security::Entity::update_many()
+        .col_expr(
+            user_security::Column::BackupCodes,
+            Expr::value(totp::join_backup_hashes(&hashes)),
+        )
+        .col_expr(
+            user_security::Column::UpdatedAt,
+            Expr::value(Utc::now().fixed_offset()),
+        )
+        .filter(user_security::Column::Id.eq(security.id))
+        .filter(user_security::Column::BackupCodes.eq(security.backup_codes.clone()))
+        .exec(db)
+        .await
+        .map_err(|e| format!("Failed to use backup code: {}", e))?;
+
+    if result.rows_affected == 1 {
+        log::info!(
+            "User {} used a backup code, {} left",
+            security.user_id,
+            hashes.len()
+        );
+    }
+    Ok(result.rows_affected == 1)
+}
+
+async fn failed_attempts(pool: &Pool, user_id: i32) -> i64 {
+    let mut conn = match pool.get().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Failed to check two-factor attempts: {}", e);
+            return 0;
+        }
+    };
+    conn.get::<_, Option<i64>>(failures_key(user_id))
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Failed to check two-factor attempts: {}", e);
+            None
+        })
+        .unwrap_or(0)
+}
+
+async fn record_failure(pool: &Pool, user_id: i32) {
+    let mut conn = match pool.get().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Failed to record two-factor attempt: {}", e);
+            return;
+        }
+    };
+    let key = failures_key(user_id);
+    let recorded = async {
+        let attempts: i64 = conn.incr(&key, 1).await?;
+        if attempts == 1 {
+            conn.expire::<_, ()>(&key, FAILED_ATTEMPTS_WINDOW_SECONDS)
+                .await?;
+        }
+        Ok::<_, deadpool_redis::redis::RedisError>(attempts)
+    }
+    .await;
+    match recorded {
+        Ok(attempts) if attempts >= MAX_FAILED_ATTEMPTS => {
+            log::warn!("Two-factor checks locked for user {}", user_id)
+        }
+        Ok(_) => {}
+        Err(e) => log::error!("Failed to record two-factor attempt: {}", e),
+    }
+}
+
+/// Forget failed attempts once the user has recovered access
+pub async fn clear_failures(pool: &Pool, user_id: i32) {
+    if let Ok(mut conn) = pool.get().await {
+        if let Err(e) = conn.del::<_, ()>(failures_key(user_id)).await {
+            log::error!("Failed to clear two-factor attempts: {}", e);
+        }
+    }
+}
@@ -1,8 +1,11 @@
-use crate::utils::jwt::verify_jwt_token;
+use crate::utils::jwt::{validate_jwt_token, Claims};
 use actix_web::HttpRequest;
 use log::{error, info};
 
-pub fn extract_user_id_from_headers(req: &HttpRequest) -> Option<i32> {
+/// User id and login session of the access token in the `token` query
+/// parameter or the `Authorization` header. The caller still has to check the
+/// session hasn't been revoked.
+pub fn extract_user_id_from_headers(req: &HttpRequest) -> Option<(i32, String)> {
     // First try to get token from query parameter (for Postman and other tools)
     if let Some(query_string) = req.uri().query() {
         let params: Vec<(&str, &str)> = query_string
@@ -19,14 +22,14 @@ pub fn extract_user_id_from_headers(req: &HttpRequest) -> Option<i32> {
         for (key, value) in params {
             if key == "token" {
                 // Found token in query params
-                match verify_jwt_token(value) {
-                    Ok(user_id_str) => match user_id_str.parse::<i32>() {
+                match validate_jwt_token(value) {
+                    Ok(Claims { sub, sid, .. }) => match sub.parse::<i32>() {
                         Ok(user_id) => {
                             info!("Authenticated WebSocket user {} via query param", user_id);
-                            return Some(user_id);
+                            return Some((user_id, sid));
                         }
                         Err(_) => {
-                            error!("Failed to parse user ID from token: {}", user_id_str);
+                            error!("Failed to parse user ID from token: {}", sub);
                             return None;
                         }
                     },
@@ -50,17 +53,17 @@ pub fn extract_user_id_from_headers(req: &HttpRequest) -> Option<i32> {
     let token = &auth_str[7..];
 
     // Extract user ID from token (same logic as auth middleware)
-    match verify_jwt_token(token) {
-        Ok(user_id_str) => match user_id_str.parse::<i32>() {
+    match validate_jwt_token(token) {
+        Ok(Claims { sub, sid, .. }) => match sub.parse::<i32>() {
             Ok(user_id) => {
                 info!(
                     "Authenticated WebSocket user {} via Authorization header",
                     user_id
                 );
-                Some(user_id)
+                Some((user_id, sid))
             }
             Err(_) => {
-                error!("Failed to parse user ID from token: {}", user_id_str);
+                error!("Failed to parse user ID from token: {}", sub);
                 None
             }
         },
@@ -0,0 +1,136 @@
+use crate::constants::config;
+use chrono::Utc;
+use entity::wallets;
+use sea_orm::{
+    prelude::Decimal, sea_query::OnConflict, ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr,
+    EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use std::collections::HashMap;
+
+/// The currency a request names, upper-cased, or the base currency when it
+/// names none. Fails unless the currency is one of `SUPPORTED_CURRENCIES`.
+pub fn resolve_currency(requested: Option<&str>) -> Result<String, String> {
+    let currency = match requested.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(currency) => currency.to_ascii_uppercase(),
+        None => return Ok(config::get_base_currency()),
+    };
+    if !config::get_supported_currencies().contains(&currency) {
+        return Err(format!("Unsupported currency: {}", currency));
+    }
+    Ok(currency)
+}
+
+/// Balance a wallet can spend on new orders or withdraw
+pub fn available(wallet: &wallets::Model) -> Decimal {
+    wallet.balance - wallet.reserved_balance
+}
+
+/// `available` for a wallet that may not exist yet
+pub fn available_in(wallet: Option<&wallets::Model>) -> Decimal {
+    wallet.map(available).unwrap_or(Decimal::ZERO)
+}
+
+/// A user's wallet in `currency`, if they have ever held it
+pub async fn find<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    currency: &str,
+) -> Result<Option<wallets::Model>, DbErr> {
+    wallets::Entity::find()
+        .filter(wallets::Column::UserId.eq(user_id))
+        .filter(wallets::Column::Currency.eq(currency))
+        .one(conn)
+        .await
+}
+
+/// Every wallet a user holds, base currency first
+pub async fn for_user<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+) -> Result<Vec<wallets::Model>, DbErr> {
+    let base = config::get_base_currency();
+    let mut wallets = wallets::Entity::find()
+        .filter(wallets::Column::UserId.eq(user_id))
+        .order_by_asc(wallets::Column::Currency)
+        .all(conn)
+        .await?;
+    wallets.sort_by_key(|wallet| wallet.currency != base);
+    Ok(wallets)
+}
+
+/// Wallets of several users at once, keyed by user, base currency first
+pub async fn for_users<C: ConnectionTrait>(
+    conn: &C,
+    user_ids: &[i32],
+) -> Result<HashMap<i32, Vec<wallets::Model>>, DbErr> {
+    let base = config::get_base_currency();
+    let mut by_user: HashMap<i32, Vec<wallets::Model>> = HashMap::new();
+    for wallet in wallets::Entity::find()
+        .filter(wallets::Column::UserId.is_in(user_ids.to_vec()))
+        .order_by_asc(wallets::Column::Currency)
+        .all(conn)
+        .await?
+    {
+        by_user.entry(wallet.user_id).or_default().push(wallet);
+    }
+    for wallets in by_user.values_mut() {
+        wallets.sort_by_key(|wallet| wallet.currency != base);
+    }
+    Ok(by_user)
+}
+
+/// Balance in `currency` among a user's wallets, zero if they hold none
+pub fn balance_in(wallets: &[wallets::Model], currency: &str) -> Decimal {
+    wallets
+        .iter()
+        .find(|wallet| wallet.currency == currency)
+        .map(|wallet| wallet.balance)
+        .unwrap_or(Decimal::ZERO)
+}
+
+/// A user's wallet in `currency`, locked for update. One is opened with
+/// nothing in it the first time a user holds the currency.
+pub async fn lock<C: ConnectionTrait>(
+    conn: &C,
+    user_id: i32,
+    currency: &str,
+) -> Result<wallets::Model, DbErr> {
+    let now = Utc::now().fixed_offset();
+    wallets::Entity::insert(wallets::ActiveModel {
+        user_id: Set(user_id),
+        currency: Set(currency.to_string()),
+        balance: Set(Decimal::ZERO),
+        reserved_balance: Set(Decimal::ZERO),
+        margin_balance: Set(Decimal::ZERO),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([wallets::Column::UserId, wallets::Column::Currency])
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(conn)
+    .await?;
+
+    wallets::Entity::find()
+        .filter(wallets::Column::UserId.eq(user_id))
+        .filter(wallets::Column::Currency.eq(currency))
+        .lock_exclusive()
+        .one(conn)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("{} wallet of user {}", currency, user_id)))
+}
+
+/// Set a locked wallet's balance
+pub async fn set_balance<C: ConnectionTrait>(
+    conn: &C,
+    wallet: wallets::Model,
+    balance: Decimal,
+) -> Result<wallets::Model, DbErr> {
+    let mut active: wallets::ActiveModel = wallet.into();
+    active.balance = Set(balance);
+    active.updated_at = Set(Utc::now().fixed_offset());
+    active.update(conn).await
+}
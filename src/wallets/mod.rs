@@ -0,0 +1,2 @@
+pub mod balances;
+pub mod transactions;
@@ -0,0 +1,142 @@
+use crate::notifications::dispatcher::{self, Notification};
+use crate::types::preferences::NotificationCategory;
+use crate::types::transaction::transaction_status;
+use crate::utils::cache::{cache_keys, create_cache_key, CacheService};
+use crate::websocket::handlers::WebSocketHandlers;
+use crate::websocket::server::{BroadcastTransactionsUpdate, WebSocketServer};
+use actix::Addr;
+use actix_web::web;
+use deadpool_redis::Pool;
+use entity::transaction;
+use sea_orm::{DatabaseConnection, Set};
+
+/// Whether a transaction of type `kind` may move from status `from` to `to`.
+/// Completed, failed and rejected transactions are final, except that a card
+/// deposit that failed may still be paid, since the user can retry at the
+/// gateway's checkout.
+pub fn can_transition(kind: &str, from: &str, to: &str) -> bool {
+    use transaction_status::{COMPLETED, FAILED, PENDING, PROCESSING, REJECTED};
+
+    matches!(
+        (from, to),
+        (PENDING, PROCESSING | COMPLETED | FAILED | REJECTED) | (PROCESSING, COMPLETED | FAILED)
+    ) || (kind == "deposit" && from == FAILED && to == COMPLETED)
+}
+
+/// Move a transaction to status `to`, for the caller to fill in the rest of
+/// the change and save. Fails if the transaction can't move there from its
+/// current status.
+pub fn transition(
+    transaction: transaction::Model,
+    to: &str,
+) -> Result<transaction::ActiveModel, String> {
+    if !can_transition(&transaction.r#type, &transaction.status, to) {
+        return Err(format!(
+            "Transaction {} is {} and can't become {}",
+            transaction.id, transaction.status, to
+        ));
+    }
+    let mut active: transaction::ActiveModel = transaction.into();
+    active.status = Set(to.to_string());
+    Ok(active)
+}
+
+/// What to tell a user about their deposit or withdrawal reaching its status
+fn status_notification(transaction: &transaction::Model) -> Option<Notification> {
+    let amount = format!("{} {}", transaction.amount, transaction.currency);
+    let reason = transaction
+        .failure_reason
+        .as_deref()
+        .or(transaction.rejection_reason.as_deref())
+        .unwrap_or("no reason given");
+
+    let (title, body) = match (transaction.r#type.as_str(), transaction.status.as_str()) {
+        ("withdraw", transaction_status::PENDING) => (
+            "Withdrawal requested",
+            format!("Your withdrawal of {} is awaiting approval", amount),
+        ),
+        ("withdraw", transaction_status::PROCESSING) => (
+            "Withdrawal approved",
+            format!("Your withdrawal of {} is being paid out", amount),
+        ),
+        ("withdraw", transaction_status::COMPLETED) => (
+            "Withdrawal paid",
+            format!("Your withdrawal of {} has been paid out", amount),
+        ),
+        ("withdraw", transaction_status::FAILED) => (
+            "Withdrawal failed",
+            format!(
+                "Your withdrawal of {} couldn't be paid out ({}); the funds are back in your wallet",
+                amount, reason
+            ),
+        ),
+        ("withdraw", transaction_status::REJECTED) => (
+            "Withdrawal rejected",
+            format!("Your withdrawal of {} was rejected: {}", amount, reason),
+        ),
+        ("deposit", transaction_status::PROCESSING) => (
+            "Deposit awaiting payment",
+            format!("Complete the payment of {} at the checkout", amount),
+        ),
+        ("deposit", transaction_status::COMPLETED) => (
+            "Deposit received",
+            format!("{} has been added to your wallet", amount),
+        ),
+        ("deposit", transaction_status::FAILED) => (
+            "Deposit failed",
+            format!("Your deposit of {} failed: {}", amount, reason),
+        ),
+        _ => return None,
+    };
+
+    Some(Notification {
+        category: NotificationCategory::Payments,
+        title: title.to_string(),
+        body,
+    })
+}
+
+/// Announce a deposit or withdrawal's new status in the background: the
+/// user's cached profile and portfolio are dropped, their `transactions` and
+/// `portfolio` subscribers refreshed, and they are sent a `payments`
+/// notification
+pub fn publish_status_change(
+    db: &DatabaseConnection,
+    redis_pool: &Pool,
+    ws_server: &Addr<WebSocketServer>,
+    transaction: &transaction::Model,
+) {
+    let user_id = transaction.user_id;
+    let notification = status_notification(transaction);
+    let db = db.clone();
+    let redis_pool = redis_pool.clone();
+    let ws_server = ws_server.clone();
+
+    ws_server.do_send(BroadcastTransactionsUpdate { user_id });
+
+    tokio::spawn(async move {
+        let cache_service = CacheService::new(redis_pool.clone());
+        let user_cache_key = create_cache_key(cache_keys::USER_PREFIX, &user_id.to_string());
+        if let Err(e) = cache_service.delete(&user_cache_key).await {
+            log::warn!("Failed to invalidate user cache: {}", e);
+        }
+        if let Err(e) = cache_service
+            .delete(&format!("portfolio:{}", user_id))
+            .await
+        {
+            log::warn!("Failed to invalidate portfolio cache: {}", e);
+        }
+
+        WebSocketHandlers::new(web::Data::new(db.clone()), ws_server.clone())
+            .fetch_and_broadcast_portfolio(user_id)
+            .await;
+
+        if let Some(notification) = notification {
+            if let Err(e) =
+                dispatcher::dispatch(&db, &redis_pool, &ws_server, user_id, notification).await
+            {
+                log::warn!("Failed to notify user {} of transaction: {}", user_id, e);
+            }
+        }
+    });
+}
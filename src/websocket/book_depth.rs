@@ -0,0 +1,131 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::order_book::types::{OrderBookSnapshot, OrderSide, PriceLevel, PriceLevelDelta};
+
+/// How much of a book its WebSocket subscribers follow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriberDepth {
+    /// Nobody is subscribed, so nothing needs to be built
+    Unwatched,
+    /// The most levels per side any subscriber asked for
+    Levels(usize),
+    /// At least one subscriber follows every level
+    Full,
+}
+
+impl SubscriberDepth {
+    /// Levels per side to build for subscribers, or `None` when there are none
+    pub fn levels(&self) -> Option<usize> {
+        match self {
+            SubscriberDepth::Unwatched => None,
+            SubscriberDepth::Levels(depth) => Some(*depth),
+            SubscriberDepth::Full => Some(usize::MAX),
+        }
+    }
+}
+
+/// Depth each subscribed session asked for, `None` for every level
+type SessionDepths = HashMap<usize, Option<usize>>;
+
+lazy_static! {
+    static ref DEPTHS: RwLock<HashMap<(i32, i32), SessionDepths>> = RwLock::new(HashMap::new());
+}
+
+/// The `depth` subscription parameter: a positive number of levels per side,
+/// or `None` for every level
+pub fn requested_depth(params: Option<&serde_json::Value>) -> Option<usize> {
+    params
+        .and_then(|params| params.get("depth"))
+        .and_then(|depth| depth.as_u64())
+        .filter(|depth| *depth > 0)
+        .map(|depth| depth as usize)
+}
+
+/// Record that a session follows the top `depth` levels of a book
+pub fn subscribe(event_id: i32, option_id: i32, session_id: usize, depth: Option<usize>) {
+    let mut depths = DEPTHS.write().unwrap_or_else(|e| e.into_inner());
+    depths
+        .entry((event_id, option_id))
+        .or_default()
+        .insert(session_id, depth);
+}
+
+/// Forget a session's subscription to a book
+pub fn unsubscribe(event_id: i32, option_id: i32, session_id: usize) {
+    let mut depths = DEPTHS.write().unwrap_or_else(|e| e.into_inner());
+    if let Some(sessions) = depths.get_mut(&(event_id, option_id)) {
+        sessions.remove(&session_id);
+        if sessions.is_empty() {
+            depths.remove(&(event_id, option_id));
+        }
+    }
+}
+
+/// Forget every book subscription of a disconnected session
+pub fn disconnect(session_id: usize) {
+    let mut depths = DEPTHS.write().unwrap_or_else(|e| e.into_inner());
+    depths.retain(|_, sessions| {
+        sessions.remove(&session_id);
+        !sessions.is_empty()
+    });
+}
+
+/// How much of a book its current subscribers follow
+pub fn subscriber_depth(event_id: i32, option_id: i32) -> SubscriberDepth {
+    let depths = DEPTHS.read().unwrap_or_else(|e| e.into_inner());
+    let Some(sessions) = depths.get(&(event_id, option_id)) else {
+        return SubscriberDepth::Unwatched;
+    };
+    sessions
+        .values()
+        .try_fold(0, |deepest, depth| depth.map(|depth| deepest.max(depth)))
+        .map_or(SubscriberDepth::Full, SubscriberDepth::Levels)
+}
+
+fn side_levels<'a>(snapshot: &'a OrderBookSnapshot, side: &OrderSide) -> &'a [PriceLevel] {
+    match side {
+        OrderSide::Buy => &snapshot.bids,
+        OrderSide::Sell => &snapshot.asks,
+    }
+}
+
+/// Level changes a subscriber following the top levels of `before` needs to
+/// hold the top levels of `after`: changes at prices in either, and the
+/// current size of levels that came into view without changing
+pub fn changes_within(
+    before: &OrderBookSnapshot,
+    after: &OrderBookSnapshot,
+    changes: Vec<PriceLevelDelta>,
+) -> Vec<PriceLevelDelta> {
+    let mut visible: Vec<PriceLevelDelta> = changes
+        .into_iter()
+        .filter(|change| {
+            side_levels(before, &change.side)
+                .iter()
+                .chain(side_levels(after, &change.side))
+                .any(|level| level.price == change.price)
+        })
+        .collect();
+
+    for side in [OrderSide::Buy, OrderSide::Sell] {
+        for level in side_levels(after, &side) {
+            let known = side_levels(before, &side)
+                .iter()
+                .any(|seen| seen.price == level.price)
+                || visible
+                    .iter()
+                    .any(|change| change.side == side && change.price == level.price);
+            if !known {
+                visible.push(PriceLevelDelta {
+                    side: side.clone(),
+                    price: level.price,
+                    quantity: level.quantity,
+                });
+            }
+        }
+    }
+
+    visible
+}
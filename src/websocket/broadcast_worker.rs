@@ -0,0 +1,103 @@
+use actix::prelude::{Actor, Context, Handler, Message, Recipient};
+use std::collections::HashMap;
+
+use crate::types::websocket::{PreSerializedMessage, SubscriptionChannel, WebSocketMessage};
+
+/// Fans channel broadcasts out to subscribers for the shard of channels routed to it.
+///
+/// Each worker runs on its own arbiter and keeps its own copy of the subscribers
+/// for its channels, so serializing and delivering a busy event's updates doesn't
+/// hold up the routing actor or channels on other workers.
+#[derive(Default)]
+pub struct BroadcastWorker {
+    /// Map of channel to subscribed session addresses
+    subscribers: HashMap<SubscriptionChannel, HashMap<usize, Recipient<PreSerializedMessage>>>,
+}
+
+impl Actor for BroadcastWorker {
+    type Context = Context<Self>;
+}
+
+/// Add a session to a channel on this worker
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct WorkerSubscribe {
+    pub session_id: usize,
+    pub channel: SubscriptionChannel,
+    pub addr: Recipient<PreSerializedMessage>,
+}
+
+/// Remove a session from a channel on this worker
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct WorkerUnsubscribe {
+    pub session_id: usize,
+    pub channel: SubscriptionChannel,
+}
+
+/// Remove a disconnected session from every channel on this worker
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct WorkerDisconnect {
+    pub session_id: usize,
+}
+
+/// Deliver a message to every subscriber of a channel on this worker
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct WorkerBroadcast {
+    pub channel: SubscriptionChannel,
+    pub message: WebSocketMessage,
+}
+
+impl Handler<WorkerSubscribe> for BroadcastWorker {
+    type Result = ();
+
+    fn handle(&mut self, msg: WorkerSubscribe, _: &mut Context<Self>) -> Self::Result {
+        self.subscribers
+            .entry(msg.channel)
+            .or_default()
+            .insert(msg.session_id, msg.addr);
+    }
+}
+
+impl Handler<WorkerUnsubscribe> for BroadcastWorker {
+    type Result = ();
+
+    fn handle(&mut self, msg: WorkerUnsubscribe, _: &mut Context<Self>) -> Self::Result {
+        if let Some(sessions) = self.subscribers.get_mut(&msg.channel) {
+            sessions.remove(&msg.session_id);
+            if sessions.is_empty() {
+                self.subscribers.remove(&msg.channel);
+            }
+        }
+    }
+}
+
+impl Handler<WorkerDisconnect> for BroadcastWorker {
+    type Result = ();
+
+    fn handle(&mut self, msg: WorkerDisconnect, _: &mut Context<Self>) -> Self::Result {
+        self.subscribers.retain(|_, sessions| {
+            sessions.remove(&msg.session_id);
+            !sessions.is_empty()
+        });
+    }
+}
+
+impl Handler<WorkerBroadcast> for BroadcastWorker {
+    type Result = ();
+
+    fn handle(&mut self, msg: WorkerBroadcast, _: &mut Context<Self>) -> Self::Result {
+        let Some(sessions) = self.subscribers.get(&msg.channel) else {
+            return;
+        };
+
+        // Serialize message once
+        if let Ok(json_msg) = PreSerializedMessage::new(msg.message) {
+            for addr in sessions.values() {
+                addr.do_send(json_msg.clone());
+            }
+        }
+    }
+}
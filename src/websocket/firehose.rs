@@ -0,0 +1,254 @@
+use actix::prelude::{Actor, ActorContext, AsyncContext, Handler, StreamHandler};
+use actix_web_actors::ws;
+use deadpool_redis::{
+    redis::{self, AsyncCommands},
+    Pool,
+};
+use futures_util::StreamExt;
+use lazy_static::lazy_static;
+use log::warn;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::constants::config;
+use crate::tenants::registry;
+use crate::types::schema::MIN_PROTOCOL_VERSION;
+use crate::types::websocket::{PreSerializedMessage, WebSocketMessage, WebSocketResponse};
+
+/// Redis pub/sub channel every instance publishes market data to and relays
+/// to its own firehose connections
+const FIREHOSE_CHANNEL: &str = "market_data:firehose";
+
+/// Messages a connection can fall behind by before it misses some
+const FIREHOSE_BUFFER: usize = 4096;
+
+/// How long to wait before resubscribing after the Redis connection drops
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// How often heartbeat pings are sent
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long before lack of client response causes a timeout
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(60);
+
+static OUTBOUND: OnceLock<mpsc::UnboundedSender<String>> = OnceLock::new();
+/// Messages relayed from Redis, with the tenant whose event they are about
+static INBOUND: OnceLock<broadcast::Sender<(i32, String)>> = OnceLock::new();
+
+lazy_static! {
+    static ref CONNECTIONS: Mutex<HashMap<String, usize>> = Mutex::new(HashMap::new());
+}
+
+/// Start publishing market data to Redis and relaying what every instance
+/// publishes to this instance's firehose connections. Messages published
+/// before this is called are dropped.
+pub fn init(redis_pool: Pool, redis_url: String) {
+    let (outbound, mut pending) = mpsc::unbounded_channel::<String>();
+    if OUTBOUND.set(outbound).is_err() {
+        return;
+    }
+    let (inbound, _) = broadcast::channel(FIREHOSE_BUFFER);
+    let _ = INBOUND.set(inbound.clone());
+
+    tokio::spawn(async move {
+        while let Some(message) = pending.recv().await {
+            let result = match redis_pool.get().await {
+                Ok(mut conn) => conn
+                    .publish::<_, _, ()>(FIREHOSE_CHANNEL, message)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            if let Err(e) = result {
+                log::error!("Failed to publish to the firehose: {}", e);
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = relay(&redis_url, &inbound).await {
+                log::error!("Firehose subscription failed: {}", e);
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+/// Forward messages from the Redis channel to local connections until the
+/// subscription drops
+async fn relay(
+    redis_url: &str,
+    inbound: &broadcast::Sender<(i32, String)>,
+) -> redis::RedisResult<()> {
+    let client = redis::Client::open(redis_url)?;
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe(FIREHOSE_CHANNEL).await?;
+
+    let mut messages = pubsub.into_on_message();
+    while let Some(message) = messages.next().await {
+        let payload: String = message.get_payload()?;
+        let Some((tenant_id, json)) = payload
+            .split_once(':')
+            .and_then(|(tenant_id, json)| Some((tenant_id.parse().ok()?, json.to_string())))
+        else {
+            warn!("Dropping malformed firehose message");
+            continue;
+        };
+        // Sending only fails when nobody is connected
+        let _ = inbound.send((tenant_id, json));
+    }
+    Ok(())
+}
+
+/// Send a public market data message about an event to the firehose
+/// connections of its tenant's site on every instance. Messages about events
+/// the registry doesn't know are dropped.
+pub fn publish(event_id: i32, message: WebSocketMessage) {
+    let Some(outbound) = OUTBOUND.get() else {
+        return;
+    };
+    let Some(tenant_id) = registry::event_tenant(event_id) else {
+        warn!(
+            "Not publishing firehose message for unknown event {}",
+            event_id
+        );
+        return;
+    };
+    // Firehose clients can't negotiate a protocol version
+    if message.schema().since > MIN_PROTOCOL_VERSION {
+        return;
+    }
+    match serde_json::to_string(&WebSocketResponse::success(message)) {
+        Ok(json) => {
+            let _ = outbound.send(format!("{}:{}", tenant_id, json));
+        }
+        Err(e) => log::error!("Failed to serialize firehose message: {}", e),
+    }
+}
+
+/// One of a client IP's firehose connections, given back when dropped
+pub struct ConnectionSlot {
+    client: String,
+}
+
+impl ConnectionSlot {
+    /// Take a connection slot for `client`, or `None` if it already holds
+    /// `FIREHOSE_MAX_CONNECTIONS_PER_IP`
+    pub fn acquire(client: &str) -> Option<Self> {
+        let mut connections = CONNECTIONS.lock().unwrap();
+        let count = connections.entry(client.to_string()).or_insert(0);
+        if *count >= config::get_firehose_max_connections_per_ip() {
+            return None;
+        }
+        *count += 1;
+        Some(Self {
+            client: client.to_string(),
+        })
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        let mut connections = CONNECTIONS.lock().unwrap();
+        if let Some(count) = connections.get_mut(&self.client) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&self.client);
+            }
+        }
+    }
+}
+
+/// A read-only connection streaming every public trade and ticker update of
+/// one tenant's events
+pub struct FirehoseSession {
+    hb: Instant,
+    tenant_id: i32,
+    _slot: ConnectionSlot,
+}
+
+impl FirehoseSession {
+    pub fn new(slot: ConnectionSlot, tenant_id: i32) -> Self {
+        Self {
+            hb: Instant::now(),
+            tenant_id,
+            _slot: slot,
+        }
+    }
+}
+
+impl Actor for FirehoseSession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if Instant::now().duration_since(act.hb) > CLIENT_TIMEOUT {
+                warn!("Firehose client heartbeat failed, disconnecting!");
+                ctx.stop();
+                return;
+            }
+            ctx.ping(b"");
+        });
+
+        let Some(inbound) = INBOUND.get() else {
+            ctx.stop();
+            return;
+        };
+        let mut receiver = inbound.subscribe();
+        let addr = ctx.address();
+        let tenant_id = self.tenant_id;
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok((message_tenant_id, message)) => {
+                        if !addr.connected() {
+                            break;
+                        }
+                        if message_tenant_id == tenant_id {
+                            addr.do_send(PreSerializedMessage {
+                                json: message,
+                                since: MIN_PROTOCOL_VERSION,
+                            });
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Firehose connection fell behind by {} messages", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+}
+
+impl Handler<PreSerializedMessage> for FirehoseSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: PreSerializedMessage, ctx: &mut Self::Context) {
+        ctx.text(msg.json);
+    }
+}
+
+/// Clients only keep the connection alive; anything they send is ignored
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for FirehoseSession {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(ws::Message::Ping(msg)) => {
+                self.hb = Instant::now();
+                ctx.pong(&msg);
+            }
+            Ok(ws::Message::Pong(_)) | Ok(ws::Message::Text(_)) => {
+                self.hb = Instant::now();
+            }
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Ok(ws::Message::Binary(_)) | Ok(ws::Message::Nop) => (),
+            Ok(ws::Message::Continuation(_)) | Err(_) => ctx.stop(),
+        }
+    }
+}
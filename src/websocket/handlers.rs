@@ -1,6 +1,7 @@
 use actix::Addr;
 use actix_web::web;
 use chrono::Utc;
+use deadpool_redis::Pool;
 use log::{error, info, warn};
 use sea_orm::{
     ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
@@ -8,14 +9,24 @@ use sea_orm::{
 };
 use serde_json;
 
+use crate::candles::service::CandleService;
+use crate::constants::config;
+use crate::order_book::db_persistence::DbPersistence;
+use crate::order_book::market_stats::MarketStatsService;
+use crate::order_book::matching_service;
+use crate::order_book::risk;
+use crate::types::candle::{CandleInterval, CandleResponse};
+use crate::types::order_book::{OrderBookResponse, OrderResponse};
+use crate::types::wallet::WalletResponse;
 use crate::types::websocket::{SubscriptionChannel, WebSocketMessage};
 use crate::types::{
-    event::{EventResponse, ListEventsQuery},
+    event::{featured_condition, EventResponse, ListEventsQuery},
     transaction::TransactionResponse,
 };
 use crate::utils::pagination::{PaginatedResponse, PaginationInfo, PaginationQuery};
-use crate::websocket::server::{Broadcast, SendToUser, WebSocketServer};
-use entity::{event_options, events, transaction, users};
+use crate::wallets::balances;
+use crate::websocket::server::{Broadcast, SendToUser, SendToUserChannel, WebSocketServer};
+use entity::{event_options, events, transaction, user_positions, users};
 use sea_orm::prelude::Decimal;
 
 pub struct WebSocketHandlers {
@@ -70,10 +81,11 @@ impl WebSocketHandlers {
         });
     }
 
-    /// Fetch and send initial events data to a specific session
+    /// Fetch and send a tenant's events to a specific session
     pub async fn fetch_and_send_initial_events(
         &self,
         session_id: usize,
+        tenant_id: i32,
         query_params: Option<serde_json::Value>,
     ) {
         // Debug log the raw params
@@ -152,7 +164,8 @@ impl WebSocketHandlers {
             query.pagination.get_limit()
         );
 
-        let mut events_query = events::Entity::find();
+        let mut events_query =
+            events::Entity::find().filter(events::Column::TenantId.eq(tenant_id));
 
         // Apply filters
         if let Some(status) = &query.status {
@@ -161,6 +174,16 @@ impl WebSocketHandlers {
         if let Some(category) = &query.category {
             events_query = events_query.filter(events::Column::Category.eq(category));
         }
+        let featured = featured_condition(Utc::now().naive_utc());
+        match query.featured {
+            Some(true) => {
+                events_query = events_query
+                    .filter(featured)
+                    .order_by_asc(events::Column::FeaturedRank)
+            }
+            Some(false) => events_query = events_query.filter(featured.not()),
+            None => {}
+        }
 
         // Apply pagination
         let page = query.pagination.get_page();
@@ -267,6 +290,155 @@ impl WebSocketHandlers {
             });
     }
 
+    /// Send the current ticker to a session that just subscribed to `ticker:{e}:{o}`
+    pub async fn fetch_and_send_initial_ticker(
+        &self,
+        session_id: usize,
+        redis_pool: &Pool,
+        event_id: i32,
+        option_id: i32,
+    ) {
+        // Only existing options get a resident book
+        match event_options::Entity::find_by_id(option_id)
+            .one(self.db.get_ref())
+            .await
+        {
+            Ok(Some(option)) if option.event_id == event_id => {}
+            Ok(_) => {
+                warn!("Option {} not found in event {}", option_id, event_id);
+                return;
+            }
+            Err(e) => {
+                error!("Failed to fetch event option: {}", e);
+                return;
+            }
+        }
+
+        let quote =
+            match matching_service::query(redis_pool, event_id, option_id, |book| book.quote())
+                .await
+            {
+                Ok(quote) => quote,
+                Err(e) => {
+                    error!("Failed to reach order book: {}", e);
+                    return;
+                }
+            };
+
+        let volume_24h =
+            match MarketStatsService::new(self.db.get_ref().clone(), redis_pool.clone())
+                .volume_24h(event_id, option_id)
+                .await
+            {
+                Ok(volume) => volume,
+                Err(e) => {
+                    error!("Failed to read ticker volume: {}", e);
+                    return;
+                }
+            };
+
+        let message = WebSocketMessage::Ticker {
+            event_id,
+            option_id,
+            quote,
+            volume_24h,
+            timestamp: Utc::now(),
+        };
+
+        self.ws_server
+            .do_send(crate::websocket::server::SendToSession {
+                session_id,
+                message,
+            });
+    }
+
+    /// Send the latest bar of an interval to a session that subscribed to its
+    /// candle channel; later trades update it through the channel
+    pub async fn fetch_and_send_initial_candle(
+        &self,
+        session_id: usize,
+        event_id: i32,
+        option_id: i32,
+        interval: CandleInterval,
+    ) {
+        let candle = match CandleService::new(self.db.get_ref().clone())
+            .latest(option_id, interval)
+            .await
+        {
+            Ok(Some(candle)) if candle.event_id == event_id => candle,
+            // Nothing has traded yet
+            Ok(_) => return,
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        };
+
+        self.ws_server
+            .do_send(crate::websocket::server::SendToSession {
+                session_id,
+                message: WebSocketMessage::Candle {
+                    candle: CandleResponse::from(candle),
+                    timestamp: Utc::now(),
+                },
+            });
+    }
+
+    /// Send the order book to a session that subscribed to its channel, to the
+    /// depth it asked for or in full
+    pub async fn fetch_and_send_initial_order_book(
+        &self,
+        session_id: usize,
+        redis_pool: &Pool,
+        event_id: i32,
+        option_id: i32,
+        depth: Option<usize>,
+    ) {
+        // Only existing options get a resident book
+        match event_options::Entity::find_by_id(option_id)
+            .one(self.db.get_ref())
+            .await
+        {
+            Ok(Some(option)) if option.event_id == event_id => {}
+            Ok(_) => {
+                warn!("Option {} not found in event {}", option_id, event_id);
+                return;
+            }
+            Err(e) => {
+                error!("Failed to fetch event option: {}", e);
+                return;
+            }
+        }
+
+        let order_book =
+            match matching_service::query(redis_pool, event_id, option_id, move |book| {
+                let snapshot = match depth {
+                    Some(depth) => book.get_snapshot_with_depth(depth),
+                    None => book.get_full_snapshot(),
+                };
+                OrderBookResponse::from_snapshot(snapshot, book.get_predicted_price())
+            })
+            .await
+            {
+                Ok(order_book) => order_book,
+                Err(e) => {
+                    error!("Failed to reach order book: {}", e);
+                    return;
+                }
+            };
+
+        let message = WebSocketMessage::OrderBookSnapshot {
+            order_book,
+            timestamp: Utc::now(),
+        };
+
+        self.ws_server
+            .do_send(crate::websocket::server::SendToSession {
+                session_id,
+                message,
+            });
+    }
+
     /// Fetch and send initial transactions data to a specific session
     pub async fn fetch_and_send_initial_transactions(
         &self,
@@ -366,12 +538,17 @@ impl WebSocketHandlers {
                 id: t.id,
                 user_id: t.user_id,
                 r#type: t.r#type,
+                currency: t.currency,
                 amount: t.amount.to_string().parse::<f64>().unwrap_or(0.0),
                 balance_before: t.balance_before.to_string().parse::<f64>().unwrap_or(0.0),
                 balance_after: t.balance_after.to_string().parse::<f64>().unwrap_or(0.0),
                 status: t.status,
                 reference_id: t.reference_id,
                 created_at: t.created_at.and_utc(),
+                rejection_reason: t.rejection_reason,
+                failure_reason: t.failure_reason,
+                gateway_reference: t.gateway_reference,
+                correlation_id: t.correlation_id,
             })
             .collect();
 
@@ -490,6 +667,7 @@ impl WebSocketHandlers {
                 "event_id": event.id,
                 "event_title": event.title,
                 "event_status": event.status,
+                "currency": event.currency,
                 "invested": event_invested,
                 "current_value": event_current_value,
                 "pnl": event_pnl,
@@ -499,11 +677,23 @@ impl WebSocketHandlers {
 
         let total_pnl = current_value - total_invested;
 
+        let wallets = match balances::for_user(self.db.get_ref(), user.id).await {
+            Ok(wallets) => wallets,
+            Err(e) => {
+                error!("Failed to fetch wallets: {}", e);
+                return;
+            }
+        };
+
         let portfolio_data = serde_json::json!({
             "total_invested": total_invested,
             "current_value": current_value,
             "total_pnl": total_pnl,
-            "wallet_balance": user.wallet_balance,
+            "wallet_balance": balances::balance_in(&wallets, &config::get_base_currency()),
+            "wallets": wallets
+                .into_iter()
+                .map(WalletResponse::from)
+                .collect::<Vec<_>>(),
             "active_positions": active_positions,
         });
 
@@ -515,6 +705,93 @@ impl WebSocketHandlers {
         self.ws_server.do_send(SendToUser { user_id, message });
     }
 
+    /// Send a user's open orders to a session that just subscribed to `orders`
+    pub async fn fetch_and_send_initial_orders(&self, session_id: usize, user_id: i32) {
+        let db_persistence = DbPersistence::new(self.db.get_ref().clone());
+        let orders = match db_persistence.get_open_orders(user_id).await {
+            Ok(orders) => orders,
+            Err(e) => {
+                error!("Failed to fetch open orders: {}", e);
+                return;
+            }
+        };
+
+        let message = WebSocketMessage::OrdersData {
+            orders: orders.into_iter().map(OrderResponse::from).collect(),
+            timestamp: Utc::now(),
+        };
+
+        self.ws_server
+            .do_send(crate::websocket::server::SendToSession {
+                session_id,
+                message,
+            });
+    }
+
+    /// Send a user's current risk summary to a session that just subscribed
+    pub async fn fetch_and_send_initial_risk(&self, session_id: usize, user_id: i32) {
+        let summaries = match risk::summary(self.db.get_ref(), user_id).await {
+            Ok(summaries) => summaries,
+            Err(e) => {
+                error!("Failed to compute risk summary: {}", e);
+                return;
+            }
+        };
+
+        self.ws_server
+            .do_send(crate::websocket::server::SendToSession {
+                session_id,
+                message: WebSocketMessage::RiskUpdate {
+                    summaries,
+                    timestamp: Utc::now(),
+                },
+            });
+    }
+
+    /// Recompute a user's risk summary and push it to their `risk` channel
+    pub async fn fetch_and_broadcast_risk(&self, user_id: i32) {
+        let summaries = match risk::summary(self.db.get_ref(), user_id).await {
+            Ok(summaries) => summaries,
+            Err(e) => {
+                error!("Failed to compute risk summary: {}", e);
+                return;
+            }
+        };
+
+        self.ws_server.do_send(SendToUserChannel {
+            user_id,
+            channel: SubscriptionChannel::Risk,
+            message: WebSocketMessage::RiskUpdate {
+                summaries,
+                timestamp: Utc::now(),
+            },
+        });
+    }
+
+    /// Push fresh risk summaries to those of `user_ids` holding `option_id`
+    pub async fn fetch_and_broadcast_option_risk(&self, option_id: i32, user_ids: Vec<i32>) {
+        let holders: Vec<i32> = match user_positions::Entity::find()
+            .select_only()
+            .column(user_positions::Column::UserId)
+            .filter(user_positions::Column::OptionId.eq(option_id))
+            .filter(user_positions::Column::Quantity.ne(0))
+            .filter(user_positions::Column::UserId.is_in(user_ids))
+            .into_tuple()
+            .all(self.db.get_ref())
+            .await
+        {
+            Ok(holders) => holders,
+            Err(e) => {
+                error!("Failed to fetch option holders: {}", e);
+                return;
+            }
+        };
+
+        for user_id in holders {
+            self.fetch_and_broadcast_risk(user_id).await;
+        }
+    }
+
     /// Fetch and send initial portfolio data to a specific session
     pub async fn fetch_and_send_initial_portfolio(&self, session_id: usize, user_id: i32) {
         // Import position tracker
@@ -602,6 +879,7 @@ impl WebSocketHandlers {
                 "event_id": event.id,
                 "event_title": event.title,
                 "event_status": event.status,
+                "currency": event.currency,
                 "invested": event_invested,
                 "current_value": event_current_value,
                 "pnl": event_pnl,
@@ -611,11 +889,23 @@ impl WebSocketHandlers {
 
         let total_pnl = current_value - total_invested;
 
+        let wallets = match balances::for_user(self.db.get_ref(), user.id).await {
+            Ok(wallets) => wallets,
+            Err(e) => {
+                error!("Failed to fetch wallets: {}", e);
+                return;
+            }
+        };
+
         let portfolio_data = serde_json::json!({
             "total_invested": total_invested,
             "current_value": current_value,
             "total_pnl": total_pnl,
-            "wallet_balance": user.wallet_balance,
+            "wallet_balance": balances::balance_in(&wallets, &config::get_base_currency()),
+            "wallets": wallets
+                .into_iter()
+                .map(WalletResponse::from)
+                .collect::<Vec<_>>(),
             "active_positions": active_positions,
         });
 
@@ -0,0 +1,103 @@
+use actix::Addr;
+use chrono::Utc;
+use entity::candles;
+use std::sync::OnceLock;
+
+use crate::order_book::types::{OrderSide, PriceLevelDelta, Trade};
+use crate::types::candle::{CandleInterval, CandleResponse};
+use crate::types::order_book::OrderBookResponse;
+use crate::types::websocket::{SubscriptionChannel, WebSocketMessage};
+use crate::websocket::server::{Broadcast, WebSocketServer};
+use crate::websocket::{firehose, ticker};
+
+static MARKET_DATA_SERVER: OnceLock<Addr<WebSocketServer>> = OnceLock::new();
+
+/// Route order book market data to the WebSocket server. Updates published
+/// before this is called are dropped.
+pub fn init(ws_server: Addr<WebSocketServer>) {
+    let _ = MARKET_DATA_SERVER.set(ws_server);
+}
+
+/// Push the price levels changed between two sequence numbers to subscribers of
+/// the book's `orderbook:{event}:{option}` channel
+pub fn publish_book_delta(
+    event_id: i32,
+    option_id: i32,
+    prev_sequence: u64,
+    sequence: u64,
+    changes: Vec<PriceLevelDelta>,
+) {
+    if let Some(ws_server) = MARKET_DATA_SERVER.get() {
+        ws_server.do_send(Broadcast {
+            channel: SubscriptionChannel::OrderBook(event_id, option_id),
+            message: WebSocketMessage::OrderBookDelta {
+                event_id,
+                option_id,
+                prev_sequence,
+                sequence,
+                changes,
+                timestamp: Utc::now(),
+            },
+        });
+    }
+}
+
+/// Push a full refresh of a book to subscribers of its channel
+pub fn publish_book_snapshot(order_book: OrderBookResponse) {
+    if let Some(ws_server) = MARKET_DATA_SERVER.get() {
+        ws_server.do_send(Broadcast {
+            channel: SubscriptionChannel::OrderBook(order_book.event_id, order_book.option_id),
+            message: WebSocketMessage::OrderBookSnapshot {
+                order_book,
+                timestamp: Utc::now(),
+            },
+        });
+    }
+}
+
+/// Push settled trades to subscribers of their `trades:{event}:{option}` tape
+/// and to the firehose.
+/// `aggressor_side` is the side of the incoming order that matched, if any.
+pub fn publish_trades(trades: &[Trade], aggressor_side: Option<OrderSide>) {
+    ticker::record_trades(trades);
+
+    let message = |trade: &Trade| WebSocketMessage::Trade {
+        id: trade.id.clone(),
+        event_id: trade.event_id,
+        option_id: trade.option_id,
+        price: trade.price,
+        quantity: trade.quantity,
+        aggressor_side: aggressor_side.clone(),
+        sequence: trade.sequence,
+        timestamp: trade.timestamp,
+    };
+
+    for trade in trades {
+        firehose::publish(trade.event_id, message(trade));
+        if let Some(ws_server) = MARKET_DATA_SERVER.get() {
+            ws_server.do_send(Broadcast {
+                channel: SubscriptionChannel::Trades(trade.event_id, trade.option_id),
+                message: message(trade),
+            });
+        }
+    }
+}
+
+/// Push bars changed by a settlement to subscribers of their
+/// `candles:{event}:{option}:{interval}` channel
+pub fn publish_candles(candles: Vec<candles::Model>) {
+    if let Some(ws_server) = MARKET_DATA_SERVER.get() {
+        for candle in candles {
+            let Some(interval) = CandleInterval::parse(&candle.interval) else {
+                continue;
+            };
+            ws_server.do_send(Broadcast {
+                channel: SubscriptionChannel::Candles(candle.event_id, candle.option_id, interval),
+                message: WebSocketMessage::Candle {
+                    candle: CandleResponse::from(candle),
+                    timestamp: Utc::now(),
+                },
+            });
+        }
+    }
+}
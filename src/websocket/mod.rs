@@ -1,3 +1,10 @@
+pub mod book_depth;
+pub mod broadcast_worker;
+pub mod firehose;
 pub mod handlers;
+pub mod market_data;
+pub mod ops;
+pub mod order_updates;
 pub mod server;
 pub mod session;
+pub mod ticker;
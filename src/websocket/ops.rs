@@ -0,0 +1,38 @@
+use actix::Addr;
+use chrono::Utc;
+use std::sync::OnceLock;
+
+use crate::types::websocket::{OpsEventKind, OpsSeverity, SubscriptionChannel, WebSocketMessage};
+use crate::websocket::server::{Broadcast, WebSocketServer};
+
+static OPS_SERVER: OnceLock<Addr<WebSocketServer>> = OnceLock::new();
+
+/// Route ops events to the WebSocket server. Events published before this is
+/// called are only logged.
+pub fn init(ws_server: Addr<WebSocketServer>) {
+    let _ = OPS_SERVER.set(ws_server);
+}
+
+/// Stream an operational event to admins subscribed to the `ops` channel
+pub fn publish(
+    kind: OpsEventKind,
+    severity: OpsSeverity,
+    message: impl Into<String>,
+    details: serde_json::Value,
+) {
+    let message = message.into();
+    log::debug!("Ops event {:?} ({:?}): {}", kind, severity, message);
+
+    if let Some(ws_server) = OPS_SERVER.get() {
+        ws_server.do_send(Broadcast {
+            channel: SubscriptionChannel::Ops,
+            message: WebSocketMessage::OpsEvent {
+                kind,
+                severity,
+                message,
+                details,
+                timestamp: Utc::now(),
+            },
+        });
+    }
+}
@@ -0,0 +1,32 @@
+use actix::Addr;
+use chrono::Utc;
+use std::sync::OnceLock;
+
+use crate::order_book::types::{Order, OrderEventKind};
+use crate::types::order_book::OrderResponse;
+use crate::types::websocket::{SubscriptionChannel, WebSocketMessage};
+use crate::websocket::server::{SendToUserChannel, WebSocketServer};
+
+static ORDER_UPDATES_SERVER: OnceLock<Addr<WebSocketServer>> = OnceLock::new();
+
+/// Route order updates to the WebSocket server. Updates published before this
+/// is called are dropped.
+pub fn init(ws_server: Addr<WebSocketServer>) {
+    let _ = ORDER_UPDATES_SERVER.set(ws_server);
+}
+
+/// Push an order's new state to its owner's `orders` channel
+pub fn publish(order: Order, kind: OrderEventKind, trade_id: Option<String>) {
+    if let Some(ws_server) = ORDER_UPDATES_SERVER.get() {
+        ws_server.do_send(SendToUserChannel {
+            user_id: order.user_id,
+            channel: SubscriptionChannel::Orders,
+            message: WebSocketMessage::OrderUpdate {
+                kind,
+                order: OrderResponse::from(order),
+                trade_id,
+                timestamp: Utc::now(),
+            },
+        });
+    }
+}
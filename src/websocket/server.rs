@@ -1,12 +1,19 @@
-use actix::prelude::{Actor, AsyncContext, Context, Handler, Message, Recipient};
+use actix::prelude::{Actor, Addr, Arbiter, AsyncContext, Context, Handler, Message, Recipient};
 use actix_web::web;
 use deadpool_redis::Pool;
 use log::info;
 use sea_orm::DatabaseConnection;
 use serde_json;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 
+use crate::constants::config;
 use crate::types::websocket::{PreSerializedMessage, SubscriptionChannel, WebSocketMessage};
+use crate::websocket::book_depth;
+use crate::websocket::broadcast_worker::{
+    BroadcastWorker, WorkerBroadcast, WorkerDisconnect, WorkerSubscribe, WorkerUnsubscribe,
+};
 
 /// WebSocket server manages all connections and subscriptions
 #[derive(Default)]
@@ -15,6 +22,8 @@ pub struct WebSocketServer {
     sessions: HashMap<usize, Recipient<PreSerializedMessage>>,
     /// Map of user id to session ids
     user_sessions: HashMap<i32, HashSet<usize>>,
+    /// Map of session id to the tenant whose site it was opened on
+    session_tenants: HashMap<usize, i32>,
     /// Map of channels to subscribed session ids
     subscriptions: HashMap<SubscriptionChannel, HashSet<usize>>,
     /// Map of (session_id, channel) to subscription parameters
@@ -25,6 +34,10 @@ pub struct WebSocketServer {
     db: Option<web::Data<DatabaseConnection>>,
     /// Redis pool for caching
     redis_pool: Option<web::Data<Pool>>,
+    /// Workers that deliver channel broadcasts, each owning a shard of the channels
+    broadcast_workers: Vec<Addr<BroadcastWorker>>,
+    /// Sessions whose user's open orders are cancelled when they disconnect
+    cancel_on_disconnect: HashSet<usize>,
 }
 
 impl WebSocketServer {
@@ -32,27 +45,43 @@ impl WebSocketServer {
         Self {
             sessions: HashMap::new(),
             user_sessions: HashMap::new(),
+            session_tenants: HashMap::new(),
             subscriptions: HashMap::new(),
             subscription_params: HashMap::new(),
             session_counter: 0,
             db: Some(db),
             redis_pool: Some(redis_pool),
+            broadcast_workers: (0..config::get_ws_broadcast_workers().max(1))
+                .map(|_| {
+                    BroadcastWorker::start_in_arbiter(&Arbiter::new().handle(), |_| {
+                        BroadcastWorker::default()
+                    })
+                })
+                .collect(),
+            cancel_on_disconnect: HashSet::new(),
         }
     }
 
+    /// Worker responsible for a channel, chosen by hashing the channel
+    fn broadcast_worker(&self, channel: &SubscriptionChannel) -> Option<&Addr<BroadcastWorker>> {
+        if self.broadcast_workers.is_empty() {
+            return None;
+        }
+        let mut hasher = DefaultHasher::new();
+        channel.hash(&mut hasher);
+        let index = (hasher.finish() % self.broadcast_workers.len() as u64) as usize;
+        self.broadcast_workers.get(index)
+    }
+
     /// Send message to all subscribed sessions for a channel
     pub fn send_to_channel(&self, channel: &SubscriptionChannel, message: WebSocketMessage) {
         if let Some(session_ids) = self.subscriptions.get(channel) {
             // Serialize message once
-            if let Ok(json_msg) = serde_json::to_string(
-                &crate::types::websocket::WebSocketResponse::success(message),
-            ) {
+            if let Ok(json_msg) = PreSerializedMessage::new(message) {
                 for &session_id in session_ids {
                     if let Some(addr) = self.sessions.get(&session_id) {
                         // Send pre-serialized message
-                        addr.do_send(crate::types::websocket::PreSerializedMessage(
-                            json_msg.clone(),
-                        ));
+                        addr.do_send(json_msg.clone());
                     }
                 }
             }
@@ -63,42 +92,107 @@ impl WebSocketServer {
     pub fn send_to_user(&self, user_id: i32, message: WebSocketMessage) {
         if let Some(session_ids) = self.user_sessions.get(&user_id) {
             // Serialize message once
-            if let Ok(json_msg) = serde_json::to_string(
-                &crate::types::websocket::WebSocketResponse::success(message),
-            ) {
+            if let Ok(json_msg) = PreSerializedMessage::new(message) {
                 for &session_id in session_ids {
                     if let Some(addr) = self.sessions.get(&session_id) {
                         // Send pre-serialized message
-                        addr.do_send(crate::types::websocket::PreSerializedMessage(
-                            json_msg.clone(),
-                        ));
+                        addr.do_send(json_msg.clone());
                     }
                 }
             }
         }
     }
 
+    /// Send message to the user's sessions subscribed to `channel`
+    pub fn send_to_user_channel(
+        &self,
+        user_id: i32,
+        channel: &SubscriptionChannel,
+        message: WebSocketMessage,
+    ) {
+        let (Some(user_sessions), Some(subscribed)) = (
+            self.user_sessions.get(&user_id),
+            self.subscriptions.get(channel),
+        ) else {
+            return;
+        };
+
+        if let Ok(json_msg) = PreSerializedMessage::new(message) {
+            for session_id in user_sessions.intersection(subscribed) {
+                if let Some(addr) = self.sessions.get(session_id) {
+                    addr.do_send(json_msg.clone());
+                }
+            }
+        }
+    }
+
+    /// Users with at least one session subscribed to `channel`
+    fn subscribed_users(&self, channel: &SubscriptionChannel) -> Vec<i32> {
+        let Some(subscribed) = self.subscriptions.get(channel) else {
+            return Vec::new();
+        };
+        self.user_sessions
+            .iter()
+            .filter(|(_, sessions)| !sessions.is_disjoint(subscribed))
+            .map(|(user_id, _)| *user_id)
+            .collect()
+    }
+
+    /// Cancel the open orders of the user a disconnecting session belonged to
+    fn cancel_user_orders(&self, session_id: usize, ctx: &mut Context<Self>) {
+        let (Some(db), Some(redis_pool)) = (&self.db, &self.redis_pool) else {
+            return;
+        };
+        let Some(user_id) = self
+            .user_sessions
+            .iter()
+            .find(|(_, sessions)| sessions.contains(&session_id))
+            .map(|(user_id, _)| *user_id)
+        else {
+            return;
+        };
+
+        let (db, redis_pool) = (db.clone(), redis_pool.clone());
+        let ws_server = web::Data::new(ctx.address());
+        tokio::spawn(async move {
+            match crate::handlers::order_book_handler::cancel_open_orders(
+                &db,
+                &redis_pool,
+                &ws_server,
+                user_id,
+                None,
+                None,
+            )
+            .await
+            {
+                Ok(cancelled) => info!(
+                    "Cancelled {} orders of user {} on disconnect",
+                    cancelled.len(),
+                    user_id
+                ),
+                Err(e) => log::error!(
+                    "Failed to cancel orders of user {} on disconnect: {}",
+                    user_id,
+                    e
+                ),
+            }
+        });
+    }
+
     /// Send message to specific session
     pub fn send_to_session(&self, session_id: usize, message: WebSocketMessage) {
         if let Some(addr) = self.sessions.get(&session_id) {
-            if let Ok(json_msg) = serde_json::to_string(
-                &crate::types::websocket::WebSocketResponse::success(message),
-            ) {
-                addr.do_send(PreSerializedMessage(json_msg));
+            if let Ok(json_msg) = PreSerializedMessage::new(message) {
+                addr.do_send(json_msg);
             }
         }
     }
 
     /// Send message to all sessions
-    #[allow(dead_code)]
     pub fn send_to_all(&self, message: WebSocketMessage) {
-        if let Ok(json_msg) = serde_json::to_string(
-            &crate::types::websocket::WebSocketResponse::success(message),
-        ) {
+        if let Ok(json_msg) = PreSerializedMessage::new(message) {
             for addr in self.sessions.values() {
-                addr.do_send(crate::types::websocket::PreSerializedMessage(
-                    json_msg.clone(),
-                ));
+                addr.do_send(json_msg.clone());
             }
         }
     }
@@ -115,6 +209,7 @@ pub struct Connect {
     pub id: usize,
     pub addr: Recipient<PreSerializedMessage>,
     pub user_id: Option<i32>,
+    pub tenant_id: i32,
 }
 
 /// Session is disconnected
@@ -124,6 +219,14 @@ pub struct Disconnect {
     pub id: usize,
 }
 
+/// Turn cancel-on-disconnect on or off for an authenticated session
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SetCancelOnDisconnect {
+    pub id: usize,
+    pub enabled: bool,
+}
+
 /// Subscribe to a channel
 #[derive(Message)]
 #[rtype(result = "()")]
@@ -158,6 +261,23 @@ pub struct SendToUser {
     pub message: WebSocketMessage,
 }
 
+/// Send message to a user's sessions that are subscribed to one of their
+/// private channels
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SendToUserChannel {
+    pub user_id: i32,
+    pub channel: SubscriptionChannel,
+    pub message: WebSocketMessage,
+}
+
+/// Send message to every session
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct BroadcastToAll {
+    pub message: WebSocketMessage,
+}
+
 /// Send message to specific session
 #[derive(Message)]
 #[rtype(result = "()")]
@@ -178,6 +298,21 @@ pub struct BroadcastTransactionsUpdate {
     pub user_id: i32,
 }
 
+/// Recompute a user's risk summary for their `risk` channel, after a fill
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct BroadcastRiskUpdate {
+    pub user_id: i32,
+}
+
+/// Recompute risk summaries for `risk` subscribers holding an option whose
+/// price moved
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct BroadcastOptionRiskUpdate {
+    pub option_id: i32,
+}
+
 /// Broadcast portfolio update for a specific user
 #[derive(Message)]
 #[rtype(result = "()")]
@@ -198,6 +333,7 @@ impl Handler<Connect> for WebSocketServer {
 
         // Store session
         self.sessions.insert(session_id, msg.addr);
+        self.session_tenants.insert(session_id, msg.tenant_id);
 
         // If user is authenticated, store user session mapping
         if let Some(user_id) = msg.user_id {
@@ -207,6 +343,14 @@ impl Handler<Connect> for WebSocketServer {
                 .insert(session_id);
         }
 
+        // Let clients connecting during or ahead of maintenance know of it
+        if let Some(window) = crate::maintenance::registry::current() {
+            self.send_to_session(
+                session_id,
+                crate::maintenance::registry::notice(Some(window)),
+            );
+        }
+
         session_id
     }
 }
@@ -215,11 +359,16 @@ impl Handler<Connect> for WebSocketServer {
 impl Handler<Disconnect> for WebSocketServer {
     type Result = ();
 
-    fn handle(&mut self, msg: Disconnect, _: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, msg: Disconnect, ctx: &mut Context<Self>) -> Self::Result {
         info!("WebSocket disconnected: {}", msg.id);
 
+        if self.cancel_on_disconnect.remove(&msg.id) {
+            self.cancel_user_orders(msg.id, ctx);
+        }
+
         // Remove session
         self.sessions.remove(&msg.id);
+        self.session_tenants.remove(&msg.id);
 
         // Remove from user sessions
         self.user_sessions.retain(|_, sessions| {
@@ -239,6 +388,23 @@ impl Handler<Disconnect> for WebSocketServer {
         // Remove all subscription parameters for this session
         self.subscription_params
             .retain(|(session_id, _), _| *session_id != msg.id);
+        book_depth::disconnect(msg.id);
+
+        for worker in &self.broadcast_workers {
+            worker.do_send(WorkerDisconnect { session_id: msg.id });
+        }
+    }
+}
+
+impl Handler<SetCancelOnDisconnect> for WebSocketServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetCancelOnDisconnect, _: &mut Context<Self>) -> Self::Result {
+        if msg.enabled {
+            self.cancel_on_disconnect.insert(msg.id);
+        } else {
+            self.cancel_on_disconnect.remove(&msg.id);
+        }
     }
 }
 
@@ -255,6 +421,28 @@ impl Handler<Subscribe> for WebSocketServer {
             .or_default()
             .insert(msg.id);
 
+        // Register the session with the worker that delivers this channel's broadcasts
+        if let (Some(worker), Some(addr)) = (
+            self.broadcast_worker(&msg.channel),
+            self.sessions.get(&msg.id),
+        ) {
+            worker.do_send(WorkerSubscribe {
+                session_id: msg.id,
+                channel: msg.channel.clone(),
+                addr: addr.clone(),
+            });
+        }
+
+        // Books only build as many levels as their deepest subscriber follows
+        if let SubscriptionChannel::OrderBook(event_id, option_id) = msg.channel {
+            book_depth::subscribe(
+                event_id,
+                option_id,
+                msg.id,
+                book_depth::requested_depth(msg.params.as_ref()),
+            );
+        }
+
         // Store subscription parameters if provided
         if let Some(ref params) = msg.params {
             self.subscription_params
@@ -264,10 +452,12 @@ impl Handler<Subscribe> for WebSocketServer {
         // Fetch and send initial data if we have database access
         if let Some(db) = &self.db {
             let db_clone = db.clone();
+            let redis_pool = self.redis_pool.clone();
             let ws_server_addr = ctx.address();
             let channel = msg.channel.clone();
             let session_id = msg.id;
             let user_id = msg.user_id;
+            let tenant_id = self.session_tenants.get(&msg.id).copied();
             let params = msg.params.clone();
 
             // Spawn async task to fetch initial data
@@ -277,10 +467,12 @@ impl Handler<Subscribe> for WebSocketServer {
 
                 match &channel {
                     SubscriptionChannel::Events => {
-                        info!("Fetching initial events data for session {}", session_id);
-                        handlers
-                            .fetch_and_send_initial_events(session_id, params)
-                            .await;
+                        if let Some(tenant_id) = tenant_id {
+                            info!("Fetching initial events data for session {}", session_id);
+                            handlers
+                                .fetch_and_send_initial_events(session_id, tenant_id, params)
+                                .await;
+                        }
                     }
                     SubscriptionChannel::Event(event_id) => {
                         info!(
@@ -313,6 +505,55 @@ impl Handler<Subscribe> for WebSocketServer {
                                 .await;
                         }
                     }
+                    SubscriptionChannel::Orders => {
+                        if let Some(user_id) = user_id {
+                            handlers
+                                .fetch_and_send_initial_orders(session_id, user_id)
+                                .await;
+                        }
+                    }
+                    SubscriptionChannel::Risk => {
+                        if let Some(user_id) = user_id {
+                            handlers
+                                .fetch_and_send_initial_risk(session_id, user_id)
+                                .await;
+                        }
+                    }
+                    // Ops events and the trade tape are live only, there is no
+                    // initial state to send
+                    SubscriptionChannel::Ops | SubscriptionChannel::Trades(..) => {}
+                    SubscriptionChannel::Candles(event_id, option_id, interval) => {
+                        handlers
+                            .fetch_and_send_initial_candle(
+                                session_id, *event_id, *option_id, *interval,
+                            )
+                            .await;
+                    }
+                    SubscriptionChannel::Ticker(event_id, option_id) => {
+                        if let Some(redis_pool) = redis_pool {
+                            handlers
+                                .fetch_and_send_initial_ticker(
+                                    session_id,
+                                    redis_pool.get_ref(),
+                                    *event_id,
+                                    *option_id,
+                                )
+                                .await;
+                        }
+                    }
+                    SubscriptionChannel::OrderBook(event_id, option_id) => {
+                        if let Some(redis_pool) = redis_pool {
+                            handlers
+                                .fetch_and_send_initial_order_book(
+                                    session_id,
+                                    redis_pool.get_ref(),
+                                    *event_id,
+                                    *option_id,
+                                    book_depth::requested_depth(params.as_ref()),
+                                )
+                                .await;
+                        }
+                    }
                 }
             });
         }
@@ -342,6 +583,16 @@ impl Handler<Unsubscribe> for WebSocketServer {
         // Remove subscription parameters
         self.subscription_params
             .remove(&(msg.id, msg.channel.clone()));
+        if let SubscriptionChannel::OrderBook(event_id, option_id) = msg.channel {
+            book_depth::unsubscribe(event_id, option_id, msg.id);
+        }
+
+        if let Some(worker) = self.broadcast_worker(&msg.channel) {
+            worker.do_send(WorkerUnsubscribe {
+                session_id: msg.id,
+                channel: msg.channel,
+            });
+        }
     }
 }
 
@@ -350,7 +601,13 @@ impl Handler<Broadcast> for WebSocketServer {
     type Result = ();
 
     fn handle(&mut self, msg: Broadcast, _: &mut Context<Self>) -> Self::Result {
-        self.send_to_channel(&msg.channel, msg.message);
+        match self.broadcast_worker(&msg.channel) {
+            Some(worker) => worker.do_send(WorkerBroadcast {
+                channel: msg.channel,
+                message: msg.message,
+            }),
+            None => self.send_to_channel(&msg.channel, msg.message),
+        }
     }
 }
 
@@ -363,6 +620,24 @@ impl Handler<SendToUser> for WebSocketServer {
     }
 }
 
+/// Send to user channel handler
+impl Handler<SendToUserChannel> for WebSocketServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SendToUserChannel, _: &mut Context<Self>) -> Self::Result {
+        self.send_to_user_channel(msg.user_id, &msg.channel, msg.message);
+    }
+}
+
+/// Broadcast to all handler
+impl Handler<BroadcastToAll> for WebSocketServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: BroadcastToAll, _: &mut Context<Self>) -> Self::Result {
+        self.send_to_all(msg.message);
+    }
+}
+
 /// Send to session handler
 impl Handler<SendToSession> for WebSocketServer {
     type Result = ();
@@ -381,6 +656,10 @@ impl Handler<BroadcastEventsUpdate> for WebSocketServer {
         if let Some(session_ids) = self.subscriptions.get(&SubscriptionChannel::Events) {
             if let (Some(db), Some(_redis_pool)) = (&self.db, &self.redis_pool) {
                 for &session_id in session_ids {
+                    let Some(&tenant_id) = self.session_tenants.get(&session_id) else {
+                        continue;
+                    };
+
                     // Get stored parameters for this session
                     let params = self
                         .subscription_params
@@ -397,9 +676,10 @@ impl Handler<BroadcastEventsUpdate> for WebSocketServer {
                             ws_server_addr,
                         );
 
-                        // Fetch and send events with the session's specific parameters
+                        // Fetch and send the tenant's events with the session's
+                        // specific parameters
                         handlers
-                            .fetch_and_send_initial_events(session_id, params)
+                            .fetch_and_send_initial_events(session_id, tenant_id, params)
                             .await;
                     });
                 }
@@ -455,6 +735,50 @@ impl Handler<BroadcastTransactionsUpdate> for WebSocketServer {
 }
 
 /// Broadcast portfolio update handler - sends personalized portfolio data to each subscriber
+impl Handler<BroadcastRiskUpdate> for WebSocketServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: BroadcastRiskUpdate, ctx: &mut Context<Self>) -> Self::Result {
+        let Some(db) = &self.db else {
+            return;
+        };
+        if !self
+            .subscribed_users(&SubscriptionChannel::Risk)
+            .contains(&msg.user_id)
+        {
+            return;
+        }
+
+        let handlers =
+            crate::websocket::handlers::WebSocketHandlers::new(db.clone(), ctx.address());
+        tokio::spawn(async move {
+            handlers.fetch_and_broadcast_risk(msg.user_id).await;
+        });
+    }
+}
+
+impl Handler<BroadcastOptionRiskUpdate> for WebSocketServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: BroadcastOptionRiskUpdate, ctx: &mut Context<Self>) -> Self::Result {
+        let Some(db) = &self.db else {
+            return;
+        };
+        let user_ids = self.subscribed_users(&SubscriptionChannel::Risk);
+        if user_ids.is_empty() {
+            return;
+        }
+
+        let handlers =
+            crate::websocket::handlers::WebSocketHandlers::new(db.clone(), ctx.address());
+        tokio::spawn(async move {
+            handlers
+                .fetch_and_broadcast_option_risk(msg.option_id, user_ids)
+                .await;
+        });
+    }
+}
+
 impl Handler<BroadcastPortfolioUpdate> for WebSocketServer {
     type Result = ();
 
@@ -3,18 +3,26 @@ use actix::prelude::{
     StreamHandler, WrapFuture,
 };
 use actix::Addr;
+use actix_web::web;
 use actix_web_actors::ws;
 use chrono::Utc;
+use deadpool_redis::Pool;
 use log::{error, warn};
 use serde_json;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
+use crate::constants::config;
+use crate::tenants::registry;
+use crate::types::schema::{self, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
 use crate::types::websocket::{
-    PreSerializedMessage, SubscriptionChannel, WebSocketMessage, WebSocketRequest,
-    WebSocketResponse,
+    PreSerializedMessage, SubscriptionChannel, SubscriptionIntent, SubscriptionIntents,
+    WebSocketMessage, WebSocketRequest, WebSocketResponse,
+};
+use crate::utils::{auth_session, cache::CacheService};
+use crate::websocket::server::{
+    Connect, Disconnect, SetCancelOnDisconnect, Subscribe, Unsubscribe, WebSocketServer,
 };
-use crate::websocket::server::{Connect, Disconnect, Subscribe, Unsubscribe, WebSocketServer};
 
 /// How often heartbeat pings are sent
 const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
@@ -22,6 +30,18 @@ const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
 /// How long before lack of client response causes a timeout
 const CLIENT_TIMEOUT: Duration = Duration::from_secs(60);
 
+fn resume_key(token: &str) -> String {
+    format!("ws_resume:{}", token)
+}
+
+/// Tokens are client-chosen, so they must be long enough not to be guessed
+fn is_valid_resume_token(token: &str) -> bool {
+    (16..=128).contains(&token.len())
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
 pub struct WebSocketSession {
     /// unique session id
     pub id: usize,
@@ -32,18 +52,47 @@ pub struct WebSocketSession {
     pub server: Addr<WebSocketServer>,
     /// User ID if authenticated
     pub user_id: Option<i32>,
-    /// Subscribed channels
-    pub subscriptions: HashSet<SubscriptionChannel>,
+    /// Login session of the token the user authenticated with; the connection
+    /// is closed once it is revoked
+    pub auth_session: Option<String>,
+    /// Tenant whose site the connection was opened on; only its events can
+    /// be subscribed to
+    pub tenant_id: i32,
+    /// Whether the user may subscribe to admin channels
+    pub is_admin: bool,
+    /// Subscribed channels and the parameters they were subscribed with
+    pub subscriptions: HashMap<SubscriptionChannel, Option<serde_json::Value>>,
+    /// Redis pool for persisting subscriptions under the resume token
+    pub redis_pool: web::Data<Pool>,
+    /// Client-provided token that subscriptions are persisted under, once resumed
+    pub resume_token: Option<String>,
+    /// Protocol version agreed in the `hello` handshake. Message types newer
+    /// than it aren't sent, so clients that never say hello keep getting only
+    /// what they understood when they were written.
+    pub protocol_version: u32,
 }
 
 impl WebSocketSession {
-    pub fn new(server: Addr<WebSocketServer>, user_id: Option<i32>) -> Self {
+    pub fn new(
+        server: Addr<WebSocketServer>,
+        redis_pool: web::Data<Pool>,
+        user_id: Option<i32>,
+        auth_session: Option<String>,
+        tenant_id: i32,
+        is_admin: bool,
+    ) -> Self {
         Self {
             id: 0,
             hb: Instant::now(),
             server,
             user_id,
-            subscriptions: HashSet::new(),
+            auth_session,
+            tenant_id,
+            is_admin,
+            subscriptions: HashMap::new(),
+            redis_pool,
+            resume_token: None,
+            protocol_version: MIN_PROTOCOL_VERSION,
         }
     }
 
@@ -69,9 +118,47 @@ impl WebSocketSession {
 
             // Send WebSocket protocol ping (not application-level message)
             ctx.ping(b"");
+
+            act.check_session(ctx);
         });
     }
 
+    /// Close the connection if the login session it authenticated with has
+    /// been revoked by logout or a password change
+    fn check_session(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        let Some(session_id) = self.auth_session.clone() else {
+            return;
+        };
+        let redis_pool = self.redis_pool.clone();
+
+        async move { auth_session::is_active(&redis_pool, &session_id).await }
+            .into_actor(self)
+            .then(|active, act, ctx| {
+                match active {
+                    Ok(false) => {
+                        warn!(
+                            "Closing WebSocket session {}: login session revoked",
+                            act.id
+                        );
+                        if let Ok(json) = serde_json::to_string(&WebSocketResponse::error(
+                            "Session has been revoked".to_string(),
+                        )) {
+                            ctx.text(json);
+                        }
+                        ctx.close(Some(ws::CloseReason {
+                            code: ws::CloseCode::Policy,
+                            description: Some("Session revoked".to_string()),
+                        }));
+                        ctx.stop();
+                    }
+                    Ok(true) => {}
+                    Err(e) => error!("Failed to check WebSocket session {}: {}", act.id, e),
+                }
+                fut::ready(())
+            })
+            .spawn(ctx);
+    }
+
     /// Handle subscribe request
     fn handle_subscribe(
         &mut self,
@@ -81,17 +168,35 @@ impl WebSocketSession {
         if let Some(channel_enum) = SubscriptionChannel::from_string(&channel) {
             // Check if user has permission to subscribe to this channel
             match &channel_enum {
-                SubscriptionChannel::Transactions | SubscriptionChannel::Portfolio => {
-                    if self.user_id.is_none() {
-                        return Some(WebSocketResponse::error(
-                            "Authentication required for this channel".to_string(),
-                        ));
-                    }
+                SubscriptionChannel::Transactions
+                | SubscriptionChannel::Portfolio
+                | SubscriptionChannel::Orders
+                | SubscriptionChannel::Risk
+                    if self.user_id.is_none() =>
+                {
+                    return Some(WebSocketResponse::error(
+                        "Authentication required for this channel".to_string(),
+                    ));
+                }
+                SubscriptionChannel::Ops if !self.is_admin => {
+                    return Some(WebSocketResponse::error(
+                        "Admin role required for this channel".to_string(),
+                    ));
                 }
                 _ => {}
             }
 
-            self.subscriptions.insert(channel_enum.clone());
+            if let Some(event_id) = channel_enum.event_id() {
+                if registry::event_tenant(event_id) != Some(self.tenant_id) {
+                    return Some(WebSocketResponse::error(format!(
+                        "Event not found: {}",
+                        event_id
+                    )));
+                }
+            }
+
+            self.subscriptions
+                .insert(channel_enum.clone(), params.clone());
 
             // Notify server about subscription
             self.server.do_send(Subscribe {
@@ -133,6 +238,181 @@ impl WebSocketSession {
             )))
         }
     }
+
+    /// Handle resume request: re-establish the subscriptions stored under the
+    /// token, then keep persisting this session's subscriptions under it
+    fn handle_resume(
+        &mut self,
+        params: Option<serde_json::Value>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) -> Option<WebSocketResponse> {
+        let token = match params
+            .as_ref()
+            .and_then(|p| p.get("token"))
+            .and_then(|t| t.as_str())
+        {
+            Some(token) if is_valid_resume_token(token) => token.to_string(),
+            _ => {
+                return Some(WebSocketResponse::error(
+                    "Resume token must be 16-128 letters, digits, '-' or '_'".to_string(),
+                ))
+            }
+        };
+
+        let cache_service = CacheService::new(self.redis_pool.get_ref().clone());
+        let key = resume_key(&token);
+
+        // Hold other messages until the stored subscriptions are re-established
+        async move { cache_service.get::<SubscriptionIntents>(&key).await }
+            .into_actor(self)
+            .map(move |result, act, ctx| {
+                let intents = match result {
+                    Ok(intents) => intents.unwrap_or_default(),
+                    Err(e) => {
+                        error!("Failed to load WebSocket subscriptions: {}", e);
+                        act.send_response(
+                            ctx,
+                            WebSocketResponse::error("Failed to resume subscriptions".to_string()),
+                        );
+                        return;
+                    }
+                };
+
+                if intents.user_id.is_some() && intents.user_id != act.user_id {
+                    act.send_response(
+                        ctx,
+                        WebSocketResponse::error(
+                            "Resume token belongs to another user".to_string(),
+                        ),
+                    );
+                    return;
+                }
+
+                for intent in intents.subscriptions {
+                    if let Some(response) = act.handle_subscribe(intent.channel, intent.params) {
+                        act.send_response(ctx, response);
+                    }
+                }
+
+                act.resume_token = Some(token.clone());
+                act.persist_subscriptions(ctx);
+
+                let channels = act.subscriptions.keys().map(|c| c.to_string()).collect();
+                act.send_response(
+                    ctx,
+                    WebSocketResponse::success(WebSocketMessage::Resumed {
+                        token,
+                        channels,
+                        timestamp: Utc::now(),
+                    }),
+                );
+            })
+            .wait(ctx);
+
+        None
+    }
+
+    /// Turn cancel-on-disconnect on or off. While it is on, the user's open
+    /// orders are cancelled when this connection drops or times out.
+    fn handle_cancel_on_disconnect(
+        &mut self,
+        params: Option<serde_json::Value>,
+    ) -> Option<WebSocketResponse> {
+        if self.user_id.is_none() {
+            return Some(WebSocketResponse::error(
+                "Authentication required for cancel_on_disconnect".to_string(),
+            ));
+        }
+        let Some(enabled) = params
+            .as_ref()
+            .and_then(|p| p.get("enabled"))
+            .and_then(|e| e.as_bool())
+        else {
+            return Some(WebSocketResponse::error(
+                "cancel_on_disconnect requires a boolean 'enabled'".to_string(),
+            ));
+        };
+
+        self.server.do_send(SetCancelOnDisconnect {
+            id: self.id,
+            enabled,
+        });
+        Some(WebSocketResponse::success(
+            WebSocketMessage::CancelOnDisconnect {
+                enabled,
+                timestamp: Utc::now(),
+            },
+        ))
+    }
+
+    /// Handle hello request: agree on the protocol version the client asks
+    /// for, or the newest the server speaks if that is older, and list the
+    /// message types the connection will be sent
+    fn handle_hello(&mut self, params: Option<serde_json::Value>) -> Option<WebSocketResponse> {
+        let Some(requested) = params
+            .as_ref()
+            .and_then(|p| p.get("protocol_version"))
+            .and_then(|v| v.as_u64())
+        else {
+            return Some(WebSocketResponse::error(
+                "hello requires a numeric 'protocol_version'".to_string(),
+            ));
+        };
+        let requested = u32::try_from(requested).unwrap_or(u32::MAX);
+
+        match schema::negotiate(requested) {
+            Ok(protocol_version) => {
+                self.protocol_version = protocol_version;
+                Some(WebSocketResponse::success(WebSocketMessage::Welcome {
+                    protocol_version,
+                    server_protocol_version: PROTOCOL_VERSION,
+                    min_protocol_version: MIN_PROTOCOL_VERSION,
+                    message_types: schema::ws_messages_for(protocol_version),
+                    timestamp: Utc::now(),
+                }))
+            }
+            Err(message) => Some(WebSocketResponse::error(message)),
+        }
+    }
+
+    /// Store the current subscriptions under the resume token, if there is one
+    fn persist_subscriptions(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        let Some(token) = &self.resume_token else {
+            return;
+        };
+
+        let intents = SubscriptionIntents {
+            user_id: self.user_id,
+            subscriptions: self
+                .subscriptions
+                .iter()
+                .map(|(channel, params)| SubscriptionIntent {
+                    channel: channel.to_string(),
+                    params: params.clone(),
+                })
+                .collect(),
+        };
+        let cache_service = CacheService::new(self.redis_pool.get_ref().clone());
+        let key = resume_key(token);
+
+        // Waited on so writes land in the order the subscriptions changed
+        async move {
+            if let Err(e) = cache_service
+                .set(&key, &intents, config::get_ws_resume_ttl_seconds())
+                .await
+            {
+                warn!("Failed to persist WebSocket subscriptions: {}", e);
+            }
+        }
+        .into_actor(self)
+        .wait(ctx);
+    }
+
+    fn send_response(&self, ctx: &mut ws::WebsocketContext<Self>, response: WebSocketResponse) {
+        if let Ok(json) = serde_json::to_string(&response) {
+            ctx.text(json);
+        }
+    }
 }
 
 impl Actor for WebSocketSession {
@@ -152,6 +432,7 @@ impl Actor for WebSocketSession {
                 id: self.id,
                 addr: addr.clone().recipient::<PreSerializedMessage>(),
                 user_id: self.user_id,
+                tenant_id: self.tenant_id,
             })
             .into_actor(self)
             .then(|res, act, ctx| {
@@ -176,6 +457,9 @@ impl Handler<WebSocketMessage> for WebSocketSession {
     type Result = ();
 
     fn handle(&mut self, msg: WebSocketMessage, ctx: &mut Self::Context) {
+        if msg.schema().since > self.protocol_version {
+            return;
+        }
         let response = WebSocketResponse::success(msg);
         if let Ok(json) = serde_json::to_string(&response) {
             ctx.text(json);
@@ -222,7 +506,9 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebSocketSession
                 let response = match request.r#type.as_str() {
                     "subscribe" => {
                         if let Some(channel) = request.channel {
-                            self.handle_subscribe(channel, request.params)
+                            let response = self.handle_subscribe(channel, request.params);
+                            self.persist_subscriptions(ctx);
+                            response
                         } else {
                             Some(WebSocketResponse::error(
                                 "Channel required for subscribe".to_string(),
@@ -231,13 +517,18 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebSocketSession
                     }
                     "unsubscribe" => {
                         if let Some(channel) = request.channel {
-                            self.handle_unsubscribe(channel)
+                            let response = self.handle_unsubscribe(channel);
+                            self.persist_subscriptions(ctx);
+                            response
                         } else {
                             Some(WebSocketResponse::error(
                                 "Channel required for unsubscribe".to_string(),
                             ))
                         }
                     }
+                    "hello" => self.handle_hello(request.params),
+                    "resume" => self.handle_resume(request.params, ctx),
+                    "cancel_on_disconnect" => self.handle_cancel_on_disconnect(request.params),
                     "ping" => Some(WebSocketResponse::success(WebSocketMessage::Pong {
                         timestamp: Utc::now(),
                     })),
@@ -274,6 +565,10 @@ impl Handler<PreSerializedMessage> for WebSocketSession {
     type Result = ();
 
     fn handle(&mut self, msg: PreSerializedMessage, ctx: &mut Self::Context) {
-        ctx.text(msg.0);
+        // Message types newer than the client's protocol are dropped
+        if msg.since > self.protocol_version {
+            return;
+        }
+        ctx.text(msg.json);
     }
 }
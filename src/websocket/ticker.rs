@@ -0,0 +1,136 @@
+use actix::Addr;
+use chrono::Utc;
+use deadpool_redis::Pool;
+use sea_orm::{prelude::Decimal, DatabaseConnection};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+
+use crate::constants::config;
+use crate::order_book::market_stats::MarketStatsService;
+use crate::order_book::types::{Quote, Trade};
+use crate::types::websocket::{SubscriptionChannel, WebSocketMessage};
+use crate::websocket::firehose;
+use crate::websocket::server::{Broadcast, WebSocketServer};
+
+/// How long a book's 24h volume is trusted before it is re-read from the database
+const VOLUME_REFRESH_SECONDS: u64 = 60;
+
+enum TickerUpdate {
+    Quote {
+        event_id: i32,
+        option_id: i32,
+        quote: Quote,
+    },
+    Traded {
+        event_id: i32,
+        option_id: i32,
+        notional: Decimal,
+    },
+}
+
+/// Latest state of one book's ticker and whether it still needs sending
+#[derive(Default)]
+struct BookTicker {
+    quote: Quote,
+    volume_24h: Option<(Decimal, Instant)>,
+    pending: bool,
+}
+
+static TICKER_UPDATES: OnceLock<mpsc::UnboundedSender<TickerUpdate>> = OnceLock::new();
+
+/// Start the task that conflates ticker changes and sends each book's latest
+/// ticker, to its channel and the firehose, at most `TICKER_MAX_MESSAGES_PER_SECOND` times a second. Updates
+/// published before this is called are dropped.
+pub fn init(ws_server: Addr<WebSocketServer>, db: DatabaseConnection, redis_pool: Pool) {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    if TICKER_UPDATES.set(sender).is_err() {
+        return;
+    }
+    let stats = MarketStatsService::new(db, redis_pool);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(
+            1000 / config::get_ticker_max_messages_per_second().max(1),
+        ));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut books: HashMap<(i32, i32), BookTicker> = HashMap::new();
+
+        loop {
+            tokio::select! {
+                update = receiver.recv() => match update {
+                    Some(TickerUpdate::Quote { event_id, option_id, quote }) => {
+                        let book = books.entry((event_id, option_id)).or_default();
+                        book.quote = quote;
+                        book.pending = true;
+                    }
+                    Some(TickerUpdate::Traded { event_id, option_id, notional }) => {
+                        let book = books.entry((event_id, option_id)).or_default();
+                        if let Some((volume, _)) = book.volume_24h.as_mut() {
+                            *volume += notional;
+                        }
+                        book.pending = true;
+                    }
+                    None => break,
+                },
+                _ = interval.tick() => {
+                    for (&(event_id, option_id), book) in books.iter_mut().filter(|(_, b)| b.pending) {
+                        book.pending = false;
+
+                        let stale = book.volume_24h.is_none_or(|(_, read_at)| {
+                            read_at.elapsed() >= Duration::from_secs(VOLUME_REFRESH_SECONDS)
+                        });
+                        if stale {
+                            match stats.volume_24h(event_id, option_id).await {
+                                Ok(volume) => book.volume_24h = Some((volume, Instant::now())),
+                                Err(e) => log::error!("Failed to read ticker volume: {}", e),
+                            }
+                        }
+
+                        let volume_24h = book
+                            .volume_24h
+                            .map(|(volume, _)| volume)
+                            .unwrap_or(Decimal::ZERO);
+                        let message = || WebSocketMessage::Ticker {
+                            event_id,
+                            option_id,
+                            quote: book.quote.clone(),
+                            volume_24h,
+                            timestamp: Utc::now(),
+                        };
+                        firehose::publish(event_id, message());
+                        ws_server.do_send(Broadcast {
+                            channel: SubscriptionChannel::Ticker(event_id, option_id),
+                            message: message(),
+                        });
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Record a book's new quote; subscribers get it on the next conflated send
+pub fn publish_quote(event_id: i32, option_id: i32, quote: Quote) {
+    if let Some(updates) = TICKER_UPDATES.get() {
+        let _ = updates.send(TickerUpdate::Quote {
+            event_id,
+            option_id,
+            quote,
+        });
+    }
+}
+
+/// Add settled trades to their books' 24h volume
+pub fn record_trades(trades: &[Trade]) {
+    if let Some(updates) = TICKER_UPDATES.get() {
+        for trade in trades {
+            let _ = updates.send(TickerUpdate::Traded {
+                event_id: trade.event_id,
+                option_id: trade.option_id,
+                notional: trade.total_amount,
+            });
+        }
+    }
+}